@@ -0,0 +1,86 @@
+#!/usr/bin/env bash
+# Builds src/nodes/<node>/dsp into src/nodes/<node>/<node>.wasm; each node's build-wasm.sh
+# calls this with its own folder name.
+#
+#   build-node-wasm.sh <node> [--simd] [--features <extra,...>]
+#
+# `--simd` also builds <node>.simd.wasm with simd128 on (the crate's `simd` feature), which the
+# loader picks where the browser validates it; `--features` adds crate features to both builds.
+#
+# The builds are no_std, with dsp-core's small allocator in place of std's (`dsp_core::bump`).
+# They ask for the cdylib alone: the crates list an rlib too, for the rack, and building both
+# keeps the panic messages' formatting through LTO, over 10 KB of it.
+set -euo pipefail
+
+if [[ $# -lt 1 ]]; then
+  echo "usage: $0 <node> [--simd] [--features <extra,...>]" >&2
+  exit 1
+fi
+
+NODE="$1"
+shift
+SIMD=0
+FEATURES="bump-alloc"
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --simd) SIMD=1 ;;
+    --features)
+      FEATURES="$FEATURES,$2"
+      shift
+      ;;
+    *)
+      echo "unknown option: $1" >&2
+      exit 1
+      ;;
+  esac
+  shift
+done
+
+REPO_DIR="$(cd -- "$(dirname -- "${BASH_SOURCE[0]}")/.." && pwd)"
+ROOT_DIR="$REPO_DIR/src/nodes/$NODE"
+DSP_DIR="$ROOT_DIR/dsp"
+CRATE="$(sed -n 's/^name = "\(.*\)"$/\1/p' "$DSP_DIR/Cargo.toml" | head -n 1)"
+
+TARGET="wasm32-unknown-unknown"
+
+if ! command -v cargo >/dev/null 2>&1; then
+  echo "cargo not found" >&2
+  exit 1
+fi
+
+echo "Building $NODE DSP (Rust -> WASM)..."
+
+# Prefer rustup-managed toolchains so `rustup target add wasm32-unknown-unknown` works as expected
+# even if Homebrew's `cargo/rustc` appear earlier in PATH.
+if command -v rustup >/dev/null 2>&1; then
+  TOOLCHAIN="${RUSTUP_TOOLCHAIN:-stable}"
+  CARGO_BIN="$(rustup which cargo --toolchain "$TOOLCHAIN")"
+  RUSTC_BIN="$(rustup which rustc --toolchain "$TOOLCHAIN")"
+  TOOLCHAIN_BIN_DIR="$(dirname -- "$RUSTC_BIN")"
+  PATH="$TOOLCHAIN_BIN_DIR:$PATH"
+  export RUSTC="$RUSTC_BIN"
+else
+  CARGO_BIN=cargo
+fi
+
+# build <target dir> <out wasm> [cargo args...]: the scalar and simd128 builds keep separate
+# target dirs so switching RUSTFLAGS doesn't rebuild the other from scratch.
+build() {
+  local target_dir="$1"
+  local out_wasm="$2"
+  shift 2
+  (cd "$DSP_DIR" && "$CARGO_BIN" rustc --release --target "$TARGET" --target-dir "$target_dir" --crate-type cdylib "$@")
+  local wasm_path="$target_dir/$TARGET/release/$CRATE.wasm"
+  if [[ ! -f "$wasm_path" ]]; then
+    echo "WASM output not found at: $wasm_path" >&2
+    exit 1
+  fi
+  cp -f "$wasm_path" "$out_wasm"
+  echo "Wrote $out_wasm"
+}
+
+build "$DSP_DIR/target" "$ROOT_DIR/$NODE.wasm" --no-default-features --features "$FEATURES"
+
+if [[ "$SIMD" == 1 ]]; then
+  RUSTFLAGS="${RUSTFLAGS:-} -C target-feature=+simd128" build "$DSP_DIR/target/simd" "$ROOT_DIR/$NODE.simd.wasm" --no-default-features --features "$FEATURES,simd"
+fi
@@ -8,6 +8,7 @@ if (skipWasm) process.exit(0);
 
 const repoRoot = process.cwd();
 const nodesRoot = path.join(repoRoot, "src", "nodes");
+const rackScript = path.join(repoRoot, "src", "rack", "build-wasm.sh");
 
 function listNodeBuildSteps() {
   const entries = readdirSync(nodesRoot, { withFileTypes: true })
@@ -23,6 +24,8 @@ function listNodeBuildSteps() {
     if (existsSync(mjs)) steps.push({ node: dir, kind: "node", file: mjs });
     else if (existsSync(sh)) steps.push({ node: dir, kind: "bash", file: sh });
   }
+  // The rack: every node crate linked into one module.
+  if (existsSync(rackScript)) steps.push({ node: "rack", kind: "bash", file: rackScript });
   return steps;
 }
 
@@ -0,0 +1,316 @@
+//! Block-size independence. A node has to give the same output however its input is cut
+//! into process calls: the render quantum is 128 frames today, but browsers may vary it, and
+//! offline renders and the chain engine call with whatever they have. Anything a node works
+//! out once per call (a smoother stepped per block, a meter or clock counted in calls, a
+//! scratch buffer sized for 128 frames) breaks that. `test_block_sizes!` generates a node
+//! crate's test: it renders `FRAMES` frames of `test_signal` through a fresh instance in one
+//! call, then again split every way in `PATTERNS`, and asserts the outputs are bit-identical.
+//! `render_quanta` and `amplitude` are for the measurements node crates' own tests make.
+
+use alloc::{vec, vec::Vec};
+use core::ops::Range;
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+/// Sample rate the harness creates nodes at.
+pub const SAMPLE_RATE: f32 = 48_000.0;
+
+/// Channels of the main input and output.
+pub const CHANNELS: usize = 2;
+
+/// Channels of the side-chain, carrier or clock input.
+pub const AUX_CHANNELS: usize = 2;
+
+/// Frames per render: half a second, past the longest meter window.
+pub const FRAMES: usize = 24_000;
+
+/// Block lengths the split renders cycle through: the render quantum, growing blocks from a
+/// single frame, and an irregular mix either side of 128 and 512.
+pub const PATTERNS: &[&[usize]] = &[
+    &[128],
+    &[1, 2, 3, 5, 8, 13, 21, 34, 55, 89, 144, 233],
+    &[7, 250, 1, 64, 513, 3, 129, 1000],
+];
+
+/// `0..total_frames` in consecutive ranges, their lengths cycling through `pattern`.
+pub fn splits(total_frames: usize, pattern: &[usize]) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    for &len in pattern.iter().cycle() {
+        if start >= total_frames {
+            break;
+        }
+        let end = (start + len.max(1)).min(total_frames);
+        spans.push(start..end);
+        start = end;
+    }
+    spans
+}
+
+/// Interleaved test input, different on every channel and for every `seed`: a rising sine
+/// sweep, noise, a burst switching on and off every 100 ms (so gates, envelopes and
+/// dynamics move) and a full-scale click every 250 ms.
+pub fn test_signal(frames: usize, channels: usize, seed: u32) -> Vec<f32> {
+    let mut state = 0x9e37_79b9_u32 ^ seed.wrapping_mul(0x85eb_ca6b);
+    let mut out = Vec::with_capacity(frames * channels);
+    for i in 0..frames {
+        let t = i as f32 / SAMPLE_RATE;
+        let burst = if (i / 4_800) % 2 == 0 { 0.5 } else { 0.02 };
+        for c in 0..channels {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let noise = state as f32 / u32::MAX as f32 * 2.0 - 1.0;
+            let f = 60.0 * (1.0 + c as f32 + seed as f32) + 4_000.0 * t;
+            let sweep = (core::f32::consts::TAU * f * t).sin();
+            let click = if i % 12_000 == 100 + c { 1.0 } else { 0.0 };
+            out.push(burst * (0.6 * sweep + 0.3 * noise) + click);
+        }
+    }
+    out
+}
+
+/// `frames` frames of a mono node's output, rendered in 128-frame quanta: `process` gets each
+/// quantum's span and fills its output.
+#[cfg(feature = "std")]
+pub fn render_quanta(frames: usize, mut process: impl FnMut(Range<usize>, &mut [f32])) -> Vec<f32> {
+    let mut output = vec![0.0; frames];
+    for span in splits(frames, &[128]) {
+        process(span.clone(), &mut output[span]);
+    }
+    output
+}
+
+/// Amplitude of the `hz` component over the last second of `signal` (all of it when it's
+/// shorter), past whatever attack or settling comes first.
+#[cfg(feature = "std")]
+pub fn amplitude(signal: &[f32], hz: f32) -> f32 {
+    let tail = &signal[signal.len().saturating_sub(SAMPLE_RATE as usize)..];
+    let w = core::f32::consts::TAU * hz / SAMPLE_RATE;
+    let (re, im) = tail
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(re, im), (i, x)| {
+            let (sin, cos) = (w * i as f32).sin_cos();
+            (re + x * cos, im + x * sin)
+        });
+    2.0 * (re * re + im * im).sqrt() / tail.len() as f32
+}
+
+/// The buffers a render walks: input, aux and output, interleaved, `FRAMES` long.
+pub struct Buffers {
+    pub input: Vec<f32>,
+    pub aux: Vec<f32>,
+    pub output: Vec<f32>,
+}
+
+impl Buffers {
+    pub fn new() -> Self {
+        Self {
+            input: test_signal(FRAMES, CHANNELS, 0),
+            aux: test_signal(FRAMES, AUX_CHANNELS, 1),
+            output: vec![0.0; FRAMES * CHANNELS],
+        }
+    }
+
+    /// Input from `frame` on.
+    pub fn input_at(&self, frame: usize) -> *const f32 {
+        self.input.as_ptr().wrapping_add(frame * CHANNELS)
+    }
+
+    /// Aux input from `frame` on.
+    pub fn aux_at(&self, frame: usize) -> *const f32 {
+        self.aux.as_ptr().wrapping_add(frame * AUX_CHANNELS)
+    }
+
+    /// Output from `frame` on.
+    pub fn output_at(&mut self, frame: usize) -> *mut f32 {
+        self.output.as_mut_ptr().wrapping_add(frame * CHANNELS)
+    }
+}
+
+impl Default for Buffers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Calls `render` with the whole of `0..FRAMES` as one span, then with it split by each of
+/// `PATTERNS`, and panics naming the first value a split render gets different from the
+/// single call (compared bit for bit, so NaNs and signed zeros count). `render` should make a
+/// fresh node, process the spans in order and return what it produced.
+pub fn assert_independent(mut render: impl FnMut(&[Range<usize>]) -> Vec<f32>) {
+    let reference = render(&splits(FRAMES, &[FRAMES]));
+    for pattern in PATTERNS {
+        let split = render(&splits(FRAMES, pattern));
+        assert_eq!(split.len(), reference.len(), "split by {pattern:?}");
+        if let Some(i) = (0..split.len()).find(|&i| split[i].to_bits() != reference[i].to_bits()) {
+            panic!(
+                "split by {pattern:?}, value {i} (frame {}) is {} instead of {}",
+                i / CHANNELS,
+                split[i],
+                reference[i]
+            );
+        }
+    }
+}
+
+/// Generates `output_is_independent_of_block_size`, a test of the node behind `$new`,
+/// `$process` and `$free`. The form names the buffers `$process` takes after its instance: a
+/// node-abi process export, or its aux version with the side-chain, carrier or clock
+/// alongside. `setup` runs on the fresh instance before the render (to set parameters or
+/// queue notes a generator would otherwise sit silent without), and `read` returns readings
+/// appended to the output after it (for meters, which have none):
+///
+/// ```text
+/// dsp_core::test_block_sizes!(eq_new, eq_process(input, output), eq_free);
+/// dsp_core::test_block_sizes!(
+///     tone_new, tone_process(input, output), tone_free,
+///     setup: |p| tone_set_params(p, 1, 220.0, -6.0),
+/// );
+/// dsp_core::test_block_sizes!(
+///     tuner_new, tuner_process(input, output), tuner_free,
+///     read: |p| vec![tuner_get_hz(p)],
+/// );
+/// dsp_core::test_block_sizes!(
+///     ducker_new, ducker_process_sidechained(input, aux, output), ducker_free
+/// );
+/// ```
+#[macro_export]
+macro_rules! test_block_sizes {
+    ($new:ident, $process:ident(input, output), $free:ident $(, $key:ident: $value:expr)* $(,)?) => {
+        $crate::test_block_sizes!(@test $new, $free, |p, span: core::ops::Range<usize>, io: &mut $crate::blocksize::Buffers| {
+            let (input, output) = (io.input_at(span.start), io.output_at(span.start));
+            $process(p, input, output, span.len(), $crate::blocksize::CHANNELS)
+        } $(, $key: $value)*);
+    };
+    ($new:ident, $process:ident(input, aux, output), $free:ident $(, $key:ident: $value:expr)* $(,)?) => {
+        $crate::test_block_sizes!(@test $new, $free, |p, span: core::ops::Range<usize>, io: &mut $crate::blocksize::Buffers| {
+            let (input, aux) = (io.input_at(span.start), io.aux_at(span.start));
+            let output = io.output_at(span.start);
+            $process(
+                p,
+                input,
+                aux,
+                output,
+                span.len(),
+                $crate::blocksize::CHANNELS,
+                $crate::blocksize::AUX_CHANNELS,
+            )
+        } $(, $key: $value)*);
+    };
+    (@test $new:ident, $free:ident, $process:expr $(, setup: $setup:expr)? $(, read: $read:expr)?) => {
+        #[cfg(test)]
+        #[test]
+        fn output_is_independent_of_block_size() {
+            $crate::blocksize::assert_independent(|spans| {
+                let p = $new($crate::blocksize::SAMPLE_RATE);
+                $(($setup)(p);)?
+                let mut io = $crate::blocksize::Buffers::new();
+                let process = $process;
+                for span in spans {
+                    process(p, span.clone(), &mut io);
+                }
+                #[allow(unused_mut)]
+                let mut out = core::mem::take(&mut io.output);
+                $(out.extend(($read)(p));)?
+                $free(p);
+                out
+            });
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A one-pole lowpass; `per_block` steps its coefficient once per call, the bug this
+    /// catches.
+    pub struct Smoother {
+        y: f32,
+        coeff: f32,
+        per_block: bool,
+    }
+
+    fn smoother_new(_sample_rate_hz: f32) -> *mut Smoother {
+        Box::into_raw(Box::new(Smoother {
+            y: 0.0,
+            coeff: 0.5,
+            per_block: false,
+        }))
+    }
+
+    fn per_block_new(_sample_rate_hz: f32) -> *mut Smoother {
+        let p = smoother_new(SAMPLE_RATE);
+        unsafe { (*p).per_block = true };
+        p
+    }
+
+    fn smoother_free(ptr: *mut Smoother) {
+        drop(unsafe { Box::from_raw(ptr) });
+    }
+
+    fn smoother_process(
+        ptr: *mut Smoother,
+        in_ptr: *const f32,
+        out_ptr: *mut f32,
+        frames: usize,
+        channels: usize,
+    ) {
+        let s = unsafe { &mut *ptr };
+        let input = unsafe { core::slice::from_raw_parts(in_ptr, frames * channels) };
+        let output = unsafe { core::slice::from_raw_parts_mut(out_ptr, frames * channels) };
+        for (y, x) in output.iter_mut().zip(input) {
+            s.y += s.coeff * (x - s.y);
+            *y = s.y;
+        }
+        if s.per_block {
+            s.coeff *= 0.99;
+        }
+    }
+
+    test_block_sizes!(smoother_new, smoother_process(input, output), smoother_free);
+
+    #[test]
+    fn splits_cover_the_render() {
+        assert_eq!(splits(10, &[3]), [0..3, 3..6, 6..9, 9..10]);
+        assert_eq!(splits(6, &[1, 4]), [0..1, 1..5, 5..6]);
+        assert_eq!(splits(4, &[0]), [0..1, 1..2, 2..3, 3..4]);
+        assert!(splits(0, &[128]).is_empty());
+        for pattern in PATTERNS {
+            let spans = splits(FRAMES, pattern);
+            assert_eq!(spans.last().map(|s| s.end), Some(FRAMES));
+        }
+    }
+
+    #[test]
+    fn amplitude_reads_the_last_second() {
+        // A burst, then a second of a 0.5 sine: the burst doesn't count.
+        let y = render_quanta(72_000, |span, y| {
+            for (i, y) in span.zip(y) {
+                let sine = 0.5 * (core::f32::consts::TAU * 1000.0 * i as f32 / SAMPLE_RATE).sin();
+                *y = if i < 24_000 { 1.0 } else { sine };
+            }
+        });
+        assert!((amplitude(&y, 1000.0) - 0.5).abs() < 1e-3);
+        assert!(amplitude(&y, 3000.0) < 1e-3);
+        assert!((amplitude(&y[24_000..48_000], 1000.0) - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    #[should_panic(expected = "split by [128]")]
+    fn per_block_state_is_caught() {
+        assert_independent(|spans| {
+            let p = per_block_new(SAMPLE_RATE);
+            let mut io = Buffers::new();
+            for span in spans {
+                let (input, output) = (io.input_at(span.start), io.output_at(span.start));
+                smoother_process(p, input, output, span.len(), CHANNELS);
+            }
+            smoother_free(p);
+            io.output
+        });
+    }
+}
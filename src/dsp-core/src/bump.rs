@@ -0,0 +1,261 @@
+//! The small allocator a `no_std` node build links in place of std's dlmalloc: the `bump-alloc`
+//! feature makes `BumpAlloc` the global allocator on wasm32. Nodes allocate when they're
+//! created and when a parameter resizes a buffer, and free the same sizes again, so it doesn't
+//! need to be clever: every block is rounded up to a power of two, freed blocks go on a list
+//! per size, and anything the lists can't serve is bumped off the end of the heap, growing the
+//! wasm memory as it goes. Memory is never returned to the host, and a block can take up to
+//! twice what was asked for; neither matters next to the few kilobytes of code it saves.
+
+use core::alloc::Layout;
+use core::ops::Range;
+use core::ptr::null_mut;
+
+/// log2 of the smallest block: room for the free-list link, at `memory::MIN_ALIGN`.
+const MIN_SIZE_LOG2: u32 = 4;
+
+const CLASSES: usize = (usize::BITS - MIN_SIZE_LOG2) as usize;
+
+/// Free lists over a bump region. Addresses are plain `usize`s so the logic runs (and is
+/// tested) on the host; `BumpAlloc` puts it on the wasm memory.
+pub struct Heap {
+    next: usize,
+    end: usize,
+    /// First free block of each size class, linked through their first word; 0 ends a list.
+    free: [usize; CLASSES],
+}
+
+/// Size class and block size of `layout`, or `None` when it can't be rounded up.
+fn class(layout: Layout) -> Option<(usize, usize)> {
+    let size = layout
+        .size()
+        .max(layout.align())
+        .max(1 << MIN_SIZE_LOG2)
+        .checked_next_power_of_two()?;
+    Some(((size.trailing_zeros() - MIN_SIZE_LOG2) as usize, size))
+}
+
+impl Heap {
+    pub const fn new() -> Self {
+        Self {
+            next: 0,
+            end: 0,
+            free: [0; CLASSES],
+        }
+    }
+
+    /// Bumps from `region` until it runs out.
+    pub fn add_region(&mut self, region: Range<usize>) {
+        self.next = region.start;
+        self.end = region.end;
+    }
+
+    /// A block for `layout`, null when there's none to be had. `grow(bytes)` is asked for a
+    /// region of at least `bytes` when the bump region runs out; one that starts where the old
+    /// one ended extends it.
+    ///
+    /// # Safety
+    /// The regions must be memory the heap owns, readable and writable.
+    pub unsafe fn alloc(
+        &mut self,
+        layout: Layout,
+        grow: impl FnOnce(usize) -> Option<Range<usize>>,
+    ) -> *mut u8 {
+        let Some((class, size)) = class(layout) else {
+            return null_mut();
+        };
+        let head = self.free[class];
+        if head != 0 && head.is_multiple_of(layout.align()) {
+            self.free[class] = *(head as *const usize);
+            return head as *mut u8;
+        }
+        let align = layout.align().max(1 << MIN_SIZE_LOG2);
+        let mut start = self.next.next_multiple_of(align);
+        if start.checked_add(size).is_none_or(|end| end > self.end) {
+            let Some(region) = size.checked_add(align).and_then(grow) else {
+                return null_mut();
+            };
+            if region.start != self.end {
+                self.next = region.start;
+            }
+            self.end = region.end;
+            start = self.next.next_multiple_of(align);
+            if start.checked_add(size).is_none_or(|end| end > self.end) {
+                return null_mut();
+            }
+        }
+        self.next = start + size;
+        start as *mut u8
+    }
+
+    /// Puts a block from `alloc` back on its free list.
+    ///
+    /// # Safety
+    /// `ptr` must come from `alloc` on this heap with the same `layout`, and not be freed yet.
+    pub unsafe fn dealloc(&mut self, ptr: *mut u8, layout: Layout) {
+        if let Some((class, _)) = class(layout) {
+            *(ptr as *mut usize) = self.free[class];
+            self.free[class] = ptr as usize;
+        }
+    }
+
+    /// Whether a block of `old` can be kept as it is for `new_size` (same size class).
+    pub fn fits(old: Layout, new_size: usize) -> bool {
+        let new = Layout::from_size_align(new_size, old.align()).ok();
+        new.and_then(class).map(|(c, _)| c) == class(old).map(|(c, _)| c)
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `Heap` as the global allocator of a wasm32 build, starting at `__heap_base` and growing
+/// memory 0 by whole pages. Single-threaded (no `atomics`), like the worklet it runs in.
+#[cfg(all(target_arch = "wasm32", not(target_feature = "atomics")))]
+pub struct BumpAlloc(core::cell::UnsafeCell<Heap>);
+
+#[cfg(all(
+    feature = "bump-alloc",
+    target_arch = "wasm32",
+    not(target_feature = "atomics")
+))]
+#[global_allocator]
+static ALLOCATOR: BumpAlloc = BumpAlloc::new();
+
+#[cfg(all(target_arch = "wasm32", not(target_feature = "atomics")))]
+mod wasm {
+    use super::*;
+    use core::alloc::GlobalAlloc;
+    use core::arch::wasm32::{memory_grow, memory_size};
+
+    const PAGE: usize = 65_536;
+
+    extern "C" {
+        static __heap_base: u8;
+    }
+
+    // The wasm32 target without atomics has one thread.
+    unsafe impl Sync for BumpAlloc {}
+
+    impl BumpAlloc {
+        pub const fn new() -> Self {
+            Self(core::cell::UnsafeCell::new(Heap::new()))
+        }
+    }
+
+    impl Default for BumpAlloc {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    unsafe impl GlobalAlloc for BumpAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let heap = &mut *self.0.get();
+            if heap.end == 0 {
+                heap.add_region(&raw const __heap_base as usize..memory_size(0) * PAGE);
+            }
+            heap.alloc(layout, |bytes| {
+                let pages = bytes.div_ceil(PAGE);
+                match memory_grow(0, pages) {
+                    usize::MAX => None,
+                    old => Some(old * PAGE..(old + pages) * PAGE),
+                }
+            })
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            (*self.0.get()).dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            if Heap::fits(layout, new_size) {
+                return ptr;
+            }
+            let new = Layout::from_size_align_unchecked(new_size, layout.align());
+            let block = self.alloc(new);
+            if !block.is_null() {
+                core::ptr::copy_nonoverlapping(ptr, block, layout.size().min(new_size));
+                self.dealloc(ptr, layout);
+            }
+            block
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 64 bytes at 64-byte alignment; only its room is used.
+    #[repr(align(64))]
+    #[derive(Clone, Copy)]
+    #[allow(dead_code)]
+    struct Line([u8; 64]);
+
+    /// A heap over the first 256 bytes of a 4 KB arena.
+    fn arena() -> (Vec<Line>, Heap) {
+        let mem = vec![Line([0; 64]); 64];
+        let mut heap = Heap::new();
+        let start = mem.as_ptr() as usize;
+        heap.add_region(start..start + 256);
+        (mem, heap)
+    }
+
+    fn layout(size: usize, align: usize) -> Layout {
+        Layout::from_size_align(size, align).unwrap()
+    }
+
+    #[test]
+    fn reuses_freed_blocks_by_size() {
+        let (mem, mut heap) = arena();
+        let base = mem.as_ptr() as usize;
+        let at = |p: *mut u8| p as usize - base;
+        unsafe {
+            let a = heap.alloc(layout(24, 4), |_| None);
+            let b = heap.alloc(layout(32, 4), |_| None);
+            assert_eq!((at(a), at(b)), (0, 32));
+            heap.dealloc(a, layout(24, 4));
+            // Same class (32 bytes): the freed block; a smaller class bumps.
+            let c = heap.alloc(layout(20, 8), |_| None);
+            let d = heap.alloc(layout(8, 8), |_| None);
+            assert_eq!((c, at(d)), (a, 64));
+            // A freed block that isn't aligned widely enough is passed over.
+            let e = heap.alloc(layout(64, 16), |_| None);
+            assert_eq!(at(e), 80);
+            heap.dealloc(e, layout(64, 16));
+            let f = heap.alloc(layout(64, 64), |_| None);
+            assert_eq!(at(f), 192);
+            assert_eq!(heap.alloc(layout(50, 16), |_| None), e);
+        }
+    }
+
+    #[test]
+    fn grows_when_the_region_runs_out() {
+        let (mem, mut heap) = arena();
+        let start = mem.as_ptr() as usize;
+        heap.add_region(start..start + 200);
+        let block = layout(128, 16);
+        unsafe {
+            assert_eq!(heap.alloc(block, |_| None) as usize, start);
+            assert!(heap.alloc(block, |_| None).is_null());
+            // A region where the old one ended extends it; anywhere else starts over.
+            let b = heap.alloc(block, |bytes| Some(start + 200..start + 200 + bytes));
+            assert_eq!(b as usize, start + 128);
+            let c = heap.alloc(block, |_| Some(start + 2048..start + 4096));
+            assert_eq!(c as usize, start + 2048);
+            assert!(heap.alloc(layout(usize::MAX / 2, 1), |_| None).is_null());
+        }
+    }
+
+    #[test]
+    fn resizes_in_place_within_a_class() {
+        let block = layout(100, 16);
+        assert!(Heap::fits(block, 128));
+        assert!(Heap::fits(block, 65));
+        assert!(!Heap::fits(block, 129));
+        assert!(!Heap::fits(block, 64));
+    }
+}
@@ -0,0 +1,305 @@
+use alloc::{vec, vec::Vec};
+
+use crate::fft::{Complex, Fft};
+
+/// Uniformly partitioned overlap-save convolution (one channel).
+///
+/// Input is gathered into blocks of `block` samples, so the output lags the direct-form
+/// convolution by exactly `block` samples (`latency()`); the impulse response can be swapped
+/// without reallocating as long as it fits the length given to `new`. One made with
+/// `crossfading` fades from the old response's output to the new one's over the next block
+/// instead of switching outright, for kernels that change while audio plays.
+///
+/// Only the first partition needs the block being gathered; the others multiply spectra that
+/// are already in the delay line, so they are accumulated a few per sample while it fills and
+/// a block boundary costs two FFTs and one partition rather than the whole response.
+pub struct PartitionedConvolver {
+    block: usize,
+    fft: Fft,
+    kernel: Kernel,
+    /// The response `set_ir` replaced, until the next block has faded it out; `None` unless
+    /// `crossfading`.
+    previous: Option<Kernel>,
+    fading: bool,
+    /// Frequency-domain delay line of past input spectra (ring, newest at `fdl_pos`).
+    fdl: Vec<Vec<Complex>>,
+    fdl_pos: usize,
+    input: Vec<f32>,
+    output: Vec<f32>,
+    fill: usize,
+    scratch: Vec<Complex>,
+}
+
+struct Kernel {
+    /// Spectrum of each `block`-long IR segment, zero-padded to `2 * block`.
+    partitions: Vec<Vec<Complex>>,
+    active: usize,
+    /// Sum over partitions `1..=done` of their products with the past input, for the block
+    /// being gathered.
+    tail: Vec<Complex>,
+    done: usize,
+}
+
+impl Kernel {
+    fn new(count: usize, len: usize) -> Self {
+        Self {
+            partitions: (0..count).map(|_| vec![Complex::default(); len]).collect(),
+            active: 0,
+            tail: vec![Complex::default(); len],
+            done: 0,
+        }
+    }
+
+    fn load(&mut self, ir: &[f32], block: usize, fft: &Fft) {
+        self.active = ir.len().div_ceil(block).min(self.partitions.len());
+        for (p, part) in self.partitions.iter_mut().enumerate().take(self.active) {
+            part.fill(Complex::default());
+            for (dst, &v) in part.iter_mut().zip(ir.iter().skip(p * block).take(block)) {
+                dst.re = v;
+            }
+            fft.forward(part);
+        }
+        self.restart();
+    }
+
+    fn restart(&mut self) {
+        self.tail.fill(Complex::default());
+        self.done = 0;
+    }
+
+    /// Brings `done` up to `fill / block` of the partitions after the first. While a block is
+    /// gathered, the input `p` blocks before it sits at `fdl[fdl_pos + p - 1]`.
+    fn accumulate(&mut self, fdl: &[Vec<Complex>], fdl_pos: usize, fill: usize, block: usize) {
+        let rest = self.active.saturating_sub(1);
+        let target = rest * fill / block;
+        let count = fdl.len();
+        for p in self.done + 1..=target {
+            let x = &fdl[(fdl_pos + p - 1) % count];
+            for ((acc, &xv), &hv) in self.tail.iter_mut().zip(x).zip(&self.partitions[p]) {
+                *acc = *acc + xv * hv;
+            }
+        }
+        self.done = self.done.max(target);
+    }
+
+    /// The block's output (the tail plus the first partition times `newest`), in the second
+    /// half of `scratch`.
+    fn convolve(&mut self, newest: &[Complex], scratch: &mut [Complex], fft: &Fft) {
+        scratch.copy_from_slice(&self.tail);
+        if self.active > 0 {
+            for ((acc, &xv), &hv) in scratch.iter_mut().zip(newest).zip(&self.partitions[0]) {
+                *acc = *acc + xv * hv;
+            }
+        }
+        fft.inverse(scratch);
+        self.restart();
+    }
+}
+
+impl PartitionedConvolver {
+    /// `block` is rounded up to a power of two; room is reserved for IRs up to `max_ir_len`.
+    pub fn new(block: usize, max_ir_len: usize) -> Self {
+        let block = block.max(1).next_power_of_two();
+        let count = max_ir_len.div_ceil(block).max(1);
+        Self {
+            block,
+            fft: Fft::new(2 * block),
+            kernel: Kernel::new(count, 2 * block),
+            previous: None,
+            fading: false,
+            fdl: (0..count)
+                .map(|_| vec![Complex::default(); 2 * block])
+                .collect(),
+            fdl_pos: 0,
+            input: vec![0.0; 2 * block],
+            output: vec![0.0; block],
+            fill: 0,
+            scratch: vec![Complex::default(); 2 * block],
+        }
+    }
+
+    /// Like `new`, with room for the outgoing response so `set_ir` crossfades.
+    pub fn crossfading(block: usize, max_ir_len: usize) -> Self {
+        let mut conv = Self::new(block, max_ir_len);
+        conv.previous = Some(Kernel::new(conv.fdl.len(), 2 * conv.block));
+        conv
+    }
+
+    pub fn latency(&self) -> usize {
+        self.block
+    }
+
+    /// Loads a new impulse response (truncated to the capacity); the input history is kept.
+    /// Several calls before the next block boundary fade from the response that was playing to
+    /// the last one.
+    pub fn set_ir(&mut self, ir: &[f32]) {
+        if let Some(previous) = &mut self.previous {
+            if !self.fading {
+                core::mem::swap(&mut self.kernel, previous);
+                self.fading = true;
+            }
+        }
+        self.kernel.load(ir, self.block, &self.fft);
+        self.kernel
+            .accumulate(&self.fdl, self.fdl_pos, self.fill, self.block);
+    }
+
+    pub fn clear(&mut self) {
+        for s in &mut self.fdl {
+            s.fill(Complex::default());
+        }
+        self.input.fill(0.0);
+        self.output.fill(0.0);
+        self.fill = 0;
+        self.fading = false;
+        self.kernel.restart();
+        if let Some(previous) = &mut self.previous {
+            previous.restart();
+        }
+    }
+
+    #[inline]
+    pub fn process(&mut self, x: f32) -> f32 {
+        let b = self.block;
+        self.input[b + self.fill] = x;
+        let y = self.output[self.fill];
+        self.fill += 1;
+        self.kernel
+            .accumulate(&self.fdl, self.fdl_pos, self.fill, b);
+        if self.fading {
+            if let Some(previous) = &mut self.previous {
+                previous.accumulate(&self.fdl, self.fdl_pos, self.fill, b);
+            }
+        }
+        if self.fill == b {
+            self.fill = 0;
+            self.run_block();
+        }
+        y
+    }
+
+    fn run_block(&mut self) {
+        let b = self.block;
+        let count = self.fdl.len();
+        self.fdl_pos = (self.fdl_pos + count - 1) % count;
+        let newest = &mut self.fdl[self.fdl_pos];
+        for (dst, &v) in newest.iter_mut().zip(&self.input) {
+            *dst = Complex::new(v, 0.0);
+        }
+        self.fft.forward(newest);
+        let newest = &self.fdl[self.fdl_pos];
+
+        self.kernel.convolve(newest, &mut self.scratch, &self.fft);
+        for (dst, c) in self.output.iter_mut().zip(&self.scratch[b..]) {
+            *dst = c.re;
+        }
+        if self.fading {
+            self.fading = false;
+            if let Some(previous) = &mut self.previous {
+                previous.convolve(newest, &mut self.scratch, &self.fft);
+                let step = 1.0 / b as f32;
+                for (i, (dst, c)) in self.output.iter_mut().zip(&self.scratch[b..]).enumerate() {
+                    let t = (i + 1) as f32 * step;
+                    *dst = c.re + t * (*dst - c.re);
+                }
+            }
+        }
+        self.input.copy_within(b.., 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_direct_convolution() {
+        let ir: Vec<f32> = (0..100)
+            .map(|i| ((i * 7 % 13) as f32 - 6.0) / 10.0)
+            .collect();
+        let x: Vec<f32> = (0..500)
+            .map(|i| ((i * 5 % 11) as f32 - 5.0) / 5.0)
+            .collect();
+        let mut conv = PartitionedConvolver::new(16, 128);
+        conv.set_ir(&ir);
+        let lat = conv.latency();
+        let y: Vec<f32> = x.iter().map(|&v| conv.process(v)).collect();
+        for n in lat..x.len() {
+            let direct: f32 = (0..ir.len())
+                .filter(|&k| k <= n - lat)
+                .map(|k| ir[k] * x[n - lat - k])
+                .sum();
+            assert!((y[n] - direct).abs() < 1e-3, "{n}: {} vs {direct}", y[n]);
+        }
+    }
+
+    /// A long response's partitions are spread evenly over the block's samples rather than
+    /// all computed at its boundary.
+    #[test]
+    fn partition_work_is_spread_across_the_block() {
+        let mut conv = PartitionedConvolver::new(16, 16 * 33);
+        conv.set_ir(&[0.5; 16 * 33]);
+        let mut last = 0;
+        for i in 1..16 {
+            conv.process(0.0);
+            let done = conv.kernel.done;
+            assert!(done - last <= 2, "{i}: {last} -> {done}");
+            assert_eq!(done, 32 * i / 16);
+            last = done;
+        }
+    }
+
+    /// The partitions accumulated before a mid-block `set_ir` are redone with the new response,
+    /// so the output from the next block on is the new response's.
+    #[test]
+    fn mid_block_set_ir_takes_effect_at_the_next_block() {
+        let ir = |scale: f32| -> Vec<f32> {
+            (0..60)
+                .map(|i| scale * ((i % 7) as f32 - 3.0) / 8.0)
+                .collect()
+        };
+        let x: Vec<f32> = (0..200)
+            .map(|i| ((i * 3 % 17) as f32 - 8.0) / 8.0)
+            .collect();
+        let mut conv = PartitionedConvolver::new(16, 64);
+        conv.set_ir(&ir(1.0));
+        let mut y: Vec<f32> = x[..100].iter().map(|&v| conv.process(v)).collect();
+        conv.set_ir(&ir(-0.5));
+        y.extend(x[100..].iter().map(|&v| conv.process(v)));
+        let h = ir(-0.5);
+        for n in 112..x.len() {
+            let direct: f32 = (0..h.len())
+                .filter(|&k| k <= n - 16)
+                .map(|k| h[k] * x[n - 16 - k])
+                .sum();
+            assert!((y[n] - direct).abs() < 1e-3, "{n}: {} vs {direct}", y[n]);
+        }
+    }
+
+    /// A gain change through the kernel ramps across one block when crossfading, and steps
+    /// otherwise.
+    #[test]
+    fn crossfading_ramps_to_the_new_response_over_a_block() {
+        for crossfade in [false, true] {
+            let mut conv = if crossfade {
+                PartitionedConvolver::crossfading(16, 32)
+            } else {
+                PartitionedConvolver::new(16, 32)
+            };
+            conv.set_ir(&[1.0]);
+            let mut y: Vec<f32> = (0..40).map(|_| conv.process(1.0)).collect();
+            conv.set_ir(&[0.25]);
+            conv.set_ir(&[0.5]);
+            y.extend((0..40).map(|_| conv.process(1.0)));
+            let steepest = y[32..]
+                .windows(2)
+                .fold(0.0_f32, |m, w| m.max((w[1] - w[0]).abs()));
+            if crossfade {
+                assert!(steepest <= 0.5 / 16.0 + 1e-5, "{steepest}");
+            } else {
+                assert!((steepest - 0.5).abs() < 1e-5, "{steepest}");
+            }
+            assert!((y[79] - 0.5).abs() < 1e-5 && (y[36] - 1.0).abs() < 1e-5);
+        }
+    }
+}
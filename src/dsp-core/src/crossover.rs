@@ -0,0 +1,128 @@
+use core::f32::consts::FRAC_1_SQRT_2;
+
+use crate::biquad::{Biquad, Response};
+
+pub const MAX_BANDS: usize = 4;
+
+/// Fourth-order Linkwitz-Riley section: two cascaded Butterworth biquads.
+#[derive(Clone, Copy, Default)]
+pub struct Lr4 {
+    stages: [Biquad; 2],
+}
+
+impl Lr4 {
+    pub fn set(&mut self, response: Response, freq_hz: f32, sample_rate_hz: f32) {
+        for s in &mut self.stages {
+            s.set(response, freq_hz, FRAC_1_SQRT_2, sample_rate_hz);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for s in &mut self.stages {
+            s.clear();
+        }
+    }
+
+    #[inline]
+    pub fn process(&mut self, x: f32) -> f32 {
+        let y = self.stages[0].process(x);
+        self.stages[1].process(y)
+    }
+}
+
+/// Splits one channel into up to `MAX_BANDS` LR4 bands that sum back to an allpassed copy of the
+/// input.
+///
+/// The splits are cascaded low to high; each lower band is run through the allpass of every
+/// crossover above it so all bands share the same phase response.
+#[derive(Clone, Copy, Default)]
+pub struct Crossover {
+    bands: usize,
+    lp: [Lr4; MAX_BANDS - 1],
+    hp: [Lr4; MAX_BANDS - 1],
+    /// `ap[band][split]`, used for `split > band`.
+    ap: [[Biquad; MAX_BANDS - 1]; MAX_BANDS - 1],
+}
+
+impl Crossover {
+    pub fn new() -> Self {
+        Self {
+            bands: 1,
+            ..Default::default()
+        }
+    }
+
+    /// `freqs_hz` must be ascending and hold `bands - 1` split points; `bands` is clamped to
+    /// 1..=`MAX_BANDS`. Filter state is kept so the split points can move while running.
+    pub fn set(&mut self, bands: usize, freqs_hz: &[f32], sample_rate_hz: f32) {
+        self.bands = bands.clamp(1, MAX_BANDS).min(freqs_hz.len() + 1);
+        for (i, &f) in freqs_hz.iter().take(self.bands - 1).enumerate() {
+            self.lp[i].set(Response::Lowpass, f, sample_rate_hz);
+            self.hp[i].set(Response::Highpass, f, sample_rate_hz);
+            for band in self.ap.iter_mut().take(i) {
+                band[i].set(Response::Allpass, f, FRAC_1_SQRT_2, sample_rate_hz);
+            }
+        }
+    }
+
+    pub fn bands(&self) -> usize {
+        self.bands
+    }
+
+    pub fn clear(&mut self) {
+        for f in self.lp.iter_mut().chain(self.hp.iter_mut()) {
+            f.clear();
+        }
+        for band in &mut self.ap {
+            for f in band {
+                f.clear();
+            }
+        }
+    }
+
+    /// Writes the first `bands()` entries of `out`, lowest band first.
+    #[inline]
+    pub fn split(&mut self, x: f32, out: &mut [f32; MAX_BANDS]) {
+        let last = self.bands - 1;
+        let mut rest = x;
+        for (i, band) in out.iter_mut().enumerate().take(last) {
+            let mut low = self.lp[i].process(rest);
+            for ap in &mut self.ap[i][i + 1..last] {
+                low = ap.process(low);
+            }
+            *band = low;
+            rest = self.hp[i].process(rest);
+        }
+        out[last] = rest;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+
+    /// The bands of a 4-way split must sum to a flat magnitude response.
+    #[test]
+    fn bands_sum_flat() {
+        let sr = 48000.0;
+        let mut xo = Crossover::new();
+        xo.set(4, &[200.0, 1500.0, 6000.0], sr);
+        for &freq in &[50.0, 200.0, 700.0, 1500.0, 3000.0, 6000.0, 12000.0] {
+            xo.clear();
+            let mut bands = [0.0; MAX_BANDS];
+            let (mut in_power, mut out_power) = (0.0_f64, 0.0_f64);
+            for n in 0..48000 {
+                let x = (2.0 * PI * freq * n as f32 / sr).sin();
+                xo.split(x, &mut bands);
+                if n >= 24000 {
+                    let y: f32 = bands.iter().sum();
+                    in_power += (x * x) as f64;
+                    out_power += (y * y) as f64;
+                }
+            }
+            let gain = (out_power / in_power).sqrt();
+            assert!((gain - 1.0).abs() < 0.01, "{freq} Hz: {gain}");
+        }
+    }
+}
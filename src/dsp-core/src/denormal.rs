@@ -0,0 +1,46 @@
+//! Keeping recursive state out of the subnormal range. WASM has no flush-to-zero mode, and on
+//! some engines every operation on a subnormal costs a hundred times a normal one, so a
+//! decaying reverb or filter tail can take more CPU than the signal did. Feedback paths and
+//! filter states snap to zero once they fall below `FLUSH_THRESHOLD`, long before they reach
+//! the subnormal range (below about 1.2e-38).
+
+/// About -300 dBFS: far below anything audible, far above the subnormals.
+pub const FLUSH_THRESHOLD: f32 = 1e-15;
+
+/// `x`, or 0 once it is smaller than `FLUSH_THRESHOLD`. NaN passes through.
+#[inline]
+pub fn flush(x: f32) -> f32 {
+    if x.abs() < FLUSH_THRESHOLD {
+        0.0
+    } else {
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::biquad::{Biquad, Response};
+
+    #[test]
+    fn snaps_only_below_the_threshold() {
+        assert_eq!(flush(1e-16), 0.0);
+        assert_eq!(flush(-1e-16), 0.0);
+        assert_eq!(flush(f32::MIN_POSITIVE / 4.0), 0.0);
+        assert_eq!(flush(1e-14), 1e-14);
+        assert_eq!(flush(-0.5), -0.5);
+        assert!(flush(f32::NAN).is_nan());
+    }
+
+    #[test]
+    fn decaying_filter_never_goes_subnormal() {
+        let mut f = Biquad::default();
+        f.set(Response::Lowpass, 1_000.0, 8.0, 48_000.0);
+        let mut y = f.process(1.0);
+        for _ in 0..500_000 {
+            y = f.process(0.0);
+            assert!(!y.is_subnormal());
+        }
+        assert_eq!(y, 0.0);
+    }
+}
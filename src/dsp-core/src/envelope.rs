@@ -0,0 +1,193 @@
+use crate::denormal::flush;
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+/// One-pole coefficient reaching ~63% of a step in `ms`; anything shorter than one sample is
+/// instant.
+pub fn one_pole_coeff(ms: f32, sample_rate_hz: f32) -> f32 {
+    let n = ms / 1000.0 * sample_rate_hz;
+    if n > 1.0 {
+        (-1.0 / n).exp()
+    } else {
+        0.0
+    }
+}
+
+/// Level below which a releasing ADSR counts as finished (-80 dB).
+const SILENT: f32 = 1e-4;
+/// ln(1000): ADSR decay and release times are to -60 dB, which takes this many time constants.
+const T60_TAUS: f32 = 6.908;
+
+/// Attack/release envelope follower: rises with the attack coefficient, falls with the release
+/// one. Feed it a rectified level (or a level in dB; it is linear in its input).
+#[derive(Clone, Copy)]
+pub struct EnvelopeFollower {
+    attack: f32,
+    release: f32,
+    env: f32,
+}
+
+impl EnvelopeFollower {
+    pub fn new(attack_ms: f32, release_ms: f32, sample_rate_hz: f32) -> Self {
+        let mut e = Self {
+            attack: 0.0,
+            release: 0.0,
+            env: 0.0,
+        };
+        e.set_times(attack_ms, release_ms, sample_rate_hz);
+        e
+    }
+
+    pub fn set_times(&mut self, attack_ms: f32, release_ms: f32, sample_rate_hz: f32) {
+        self.attack = one_pole_coeff(attack_ms, sample_rate_hz);
+        self.release = one_pole_coeff(release_ms, sample_rate_hz);
+    }
+
+    pub fn value(&self) -> f32 {
+        self.env
+    }
+
+    pub fn reset(&mut self, value: f32) {
+        self.env = value;
+    }
+
+    #[inline]
+    pub fn process(&mut self, x: f32) -> f32 {
+        let c = if x > self.env {
+            self.attack
+        } else {
+            self.release
+        };
+        self.env = flush(self.env * c + (1.0 - c) * x);
+        self.env
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Stage {
+    Idle,
+    Attack,
+    Decay,
+    Release,
+}
+
+/// Per-sample ADSR rates, shared by every voice.
+#[derive(Clone, Copy)]
+pub struct AdsrParams {
+    attack_step: f32,
+    decay: f32,
+    sustain: f32,
+    release: f32,
+}
+
+impl AdsrParams {
+    /// Linear attack over `attack_ms`; exponential decay and release reaching -60 dB in
+    /// `decay_ms` and `release_ms`.
+    pub fn new(
+        attack_ms: f32,
+        decay_ms: f32,
+        sustain: f32,
+        release_ms: f32,
+        sample_rate_hz: f32,
+    ) -> Self {
+        let attack_samples = attack_ms / 1000.0 * sample_rate_hz;
+        Self {
+            attack_step: if attack_samples > 1.0 {
+                1.0 / attack_samples
+            } else {
+                1.0
+            },
+            decay: one_pole_coeff(decay_ms / T60_TAUS, sample_rate_hz),
+            sustain,
+            release: one_pole_coeff(release_ms / T60_TAUS, sample_rate_hz),
+        }
+    }
+}
+
+/// ADSR envelope. Retriggering attacks from the current level, so a stolen or repeated voice
+/// doesn't click back to zero.
+#[derive(Clone, Copy)]
+pub struct Adsr {
+    stage: Stage,
+    level: f32,
+}
+
+impl Default for Adsr {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Adsr {
+    pub const fn new() -> Self {
+        Self {
+            stage: Stage::Idle,
+            level: 0.0,
+        }
+    }
+
+    pub fn gate_on(&mut self) {
+        self.stage = Stage::Attack;
+    }
+
+    pub fn gate_off(&mut self) {
+        if self.stage != Stage::Idle {
+            self.stage = Stage::Release;
+        }
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Finished releasing (or never started).
+    pub fn is_idle(&self) -> bool {
+        self.stage == Stage::Idle
+    }
+
+    #[inline]
+    pub fn process(&mut self, p: &AdsrParams) -> f32 {
+        match self.stage {
+            Stage::Idle => {}
+            Stage::Attack => {
+                self.level += p.attack_step;
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = Stage::Decay;
+                }
+            }
+            // Sustain is the end of the decay: it just keeps tracking the (settable) level.
+            Stage::Decay => self.level = p.sustain + (self.level - p.sustain) * p.decay,
+            Stage::Release => {
+                self.level *= p.release;
+                if self.level < SILENT {
+                    self.level = 0.0;
+                    self.stage = Stage::Idle;
+                }
+            }
+        }
+        self.level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follower_attacks_and_releases_at_its_rates() {
+        let sr = 48_000.0;
+        let mut e = EnvelopeFollower::new(1.0, 100.0, sr);
+        for _ in 0..48 {
+            e.process(1.0);
+        }
+        assert!((e.value() - 0.632).abs() < 0.01, "{}", e.value());
+        e.reset(1.0);
+        for _ in 0..4800 {
+            e.process(0.0);
+        }
+        assert!((e.value() - 0.368).abs() < 0.01, "{}", e.value());
+        assert_eq!(one_pole_coeff(0.01, sr), 0.0);
+    }
+}
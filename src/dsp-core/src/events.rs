@@ -0,0 +1,138 @@
+//! Event rings the worklet writes straight into wasm memory, read by a node as it works through
+//! each block: notes for the generators, parameter changes for everything. Layout (`repr(C)`,
+//! little-endian, all 4-byte fields): the write count at byte 0, the read count at byte 4,
+//! then `EVENT_CAPACITY` events, of 16 bytes for notes (`kind`, `note`, `value` as f32,
+//! `frame`) and 12 for parameters (`index`, `value` as f32, `frame`). Counts run freely and
+//! wrap; an event lives at `count % EVENT_CAPACITY`. The writer fills the slot and then bumps
+//! the write count (with `Atomics.store` if the memory is shared); the ring is full when the
+//! counts are `EVENT_CAPACITY` apart. Events go in in frame order.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+pub const EVENT_CAPACITY: usize = 256;
+
+/// `note` starts at `value` velocity (0–1).
+pub const NOTE_ON: u32 = 1;
+/// `note` is released (`value` ignored).
+pub const NOTE_OFF: u32 = 0;
+/// Every sounding note is released.
+pub const ALL_NOTES_OFF: u32 = 2;
+/// Bends every voice by `value` semitones (absolute, not cumulative).
+pub const PITCH_BEND: u32 = 3;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NoteEvent {
+    pub kind: u32,
+    pub note: u32,
+    pub value: f32,
+    /// Offset into the block the event lands in; later than the block means its last frame.
+    pub frame: u32,
+}
+
+/// Sets parameter `index` (as in `node_abi`'s `set_param`) to `value` at `frame`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ParamEvent {
+    pub index: u32,
+    pub value: f32,
+    /// Offset into the block the event lands in; later than the block means its last frame.
+    pub frame: u32,
+}
+
+#[repr(C)]
+pub struct Ring<T> {
+    write: AtomicU32,
+    read: AtomicU32,
+    events: [T; EVENT_CAPACITY],
+}
+
+pub type EventRing = Ring<NoteEvent>;
+pub type ParamRing = Ring<ParamEvent>;
+
+impl<T: Copy + Default> Default for Ring<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Copy + Default> Ring<T> {
+    pub fn new() -> Self {
+        Self {
+            write: AtomicU32::new(0),
+            read: AtomicU32::new(0),
+            events: [T::default(); EVENT_CAPACITY],
+        }
+    }
+
+    /// Queues an event from Rust (the worklet writes the memory directly). False when full.
+    pub fn push(&mut self, event: T) -> bool {
+        let write = self.write.load(Ordering::Relaxed);
+        if write.wrapping_sub(self.read.load(Ordering::Acquire)) as usize >= EVENT_CAPACITY {
+            return false;
+        }
+        self.events[write as usize % EVENT_CAPACITY] = event;
+        self.write.store(write.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    /// The oldest queued event, without consuming it.
+    pub fn peek(&self) -> Option<T> {
+        let read = self.read.load(Ordering::Relaxed);
+        let write = self.write.load(Ordering::Acquire);
+        let queued = write.wrapping_sub(read) as usize;
+        // A writer that overran the ring (or scribbled on the counts) loses everything queued.
+        if queued > EVENT_CAPACITY {
+            self.read.store(write, Ordering::Release);
+            return None;
+        }
+        (queued > 0).then(|| self.events[read as usize % EVENT_CAPACITY])
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let event = self.peek()?;
+        let read = self.read.load(Ordering::Relaxed);
+        self.read.store(read.wrapping_add(1), Ordering::Release);
+        Some(event)
+    }
+
+    /// Drops everything queued.
+    pub fn clear(&mut self) {
+        let write = self.write.load(Ordering::Acquire);
+        self.read.store(write, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_and_reports_full() {
+        let mut ring = EventRing::new();
+        let ev = |note| NoteEvent {
+            kind: NOTE_ON,
+            note,
+            value: 1.0,
+            frame: 0,
+        };
+        for round in 0..3 {
+            for i in 0..EVENT_CAPACITY as u32 {
+                assert!(ring.push(ev(i + round)));
+            }
+            assert!(!ring.push(ev(0)));
+            for i in 0..EVENT_CAPACITY as u32 {
+                assert_eq!(ring.pop(), Some(ev(i + round)));
+            }
+            assert_eq!(ring.pop(), None);
+        }
+    }
+
+    #[test]
+    fn layout_matches_the_worklet_writer() {
+        assert_eq!(core::mem::size_of::<NoteEvent>(), 16);
+        assert_eq!(core::mem::size_of::<EventRing>(), 8 + 16 * EVENT_CAPACITY);
+        assert_eq!(core::mem::size_of::<ParamEvent>(), 12);
+        assert_eq!(core::mem::size_of::<ParamRing>(), 8 + 12 * EVENT_CAPACITY);
+    }
+}
@@ -0,0 +1,152 @@
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use core::ops::{Add, Mul, Sub};
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex {
+    pub const fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    #[inline]
+    pub fn norm(self) -> f32 {
+        (self.re * self.re + self.im * self.im).sqrt()
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    #[inline]
+    fn add(self, o: Self) -> Self {
+        Self::new(self.re + o.re, self.im + o.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    #[inline]
+    fn sub(self, o: Self) -> Self {
+        Self::new(self.re - o.re, self.im - o.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    #[inline]
+    fn mul(self, o: Self) -> Self {
+        Self::new(
+            self.re * o.re - self.im * o.im,
+            self.re * o.im + self.im * o.re,
+        )
+    }
+}
+
+/// In-place iterative radix-2 FFT with precomputed twiddles; sizes are powers of two.
+pub struct Fft {
+    n: usize,
+    twiddles: Vec<Complex>,
+    bitrev: Vec<usize>,
+}
+
+impl Fft {
+    /// `n` is rounded up to a power of two (at least 2).
+    pub fn new(n: usize) -> Self {
+        let n = n.max(2).next_power_of_two();
+        let bits = n.trailing_zeros();
+        let twiddles = (0..n / 2)
+            .map(|k| {
+                let w = -2.0 * PI * k as f32 / n as f32;
+                Complex::new(w.cos(), w.sin())
+            })
+            .collect();
+        let bitrev = (0..n)
+            .map(|i| i.reverse_bits() >> (usize::BITS - bits))
+            .collect();
+        Self {
+            n,
+            twiddles,
+            bitrev,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn forward(&self, buf: &mut [Complex]) {
+        self.transform(buf, false);
+    }
+
+    /// Inverse transform, scaled by `1/n` so `inverse(forward(x)) == x`.
+    pub fn inverse(&self, buf: &mut [Complex]) {
+        self.transform(buf, true);
+        let scale = 1.0 / self.n as f32;
+        for c in buf.iter_mut().take(self.n) {
+            c.re *= scale;
+            c.im *= scale;
+        }
+    }
+
+    fn transform(&self, buf: &mut [Complex], inverse: bool) {
+        let n = self.n;
+        let buf = &mut buf[..n];
+        for i in 0..n {
+            let j = self.bitrev[i];
+            if j > i {
+                buf.swap(i, j);
+            }
+        }
+        let mut size = 2;
+        while size <= n {
+            let half = size / 2;
+            let step = n / size;
+            for start in (0..n).step_by(size) {
+                for k in 0..half {
+                    let mut w = self.twiddles[k * step];
+                    if inverse {
+                        w.im = -w.im;
+                    }
+                    let a = buf[start + k];
+                    let b = buf[start + k + half] * w;
+                    buf[start + k] = a + b;
+                    buf[start + k + half] = a - b;
+                }
+            }
+            size *= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_and_single_bin() {
+        let fft = Fft::new(64);
+        let x: Vec<Complex> = (0..64)
+            .map(|i| Complex::new((2.0 * PI * 5.0 * i as f32 / 64.0).cos(), 0.0))
+            .collect();
+        let mut buf = x.clone();
+        fft.forward(&mut buf);
+        assert!((buf[5].re - 32.0).abs() < 1e-3);
+        assert!((buf[59].re - 32.0).abs() < 1e-3);
+        assert!(buf[6].norm() < 1e-3);
+        fft.inverse(&mut buf);
+        for (a, b) in buf.iter().zip(&x) {
+            assert!((a.re - b.re).abs() < 1e-5 && a.im.abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Decibel/linear conversions.
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+#[inline]
+pub fn db_to_lin(db: f32) -> f32 {
+    (10.0_f32).powf(db / 20.0)
+}
+
+/// -Infinity for 0 (and anything negative), for readouts that show silence as such.
+#[inline]
+pub fn lin_to_db(lin: f32) -> f32 {
+    if lin > 0.0 {
+        20.0 * lin.log10()
+    } else {
+        f32::NEG_INFINITY
+    }
+}
+
+/// Never below -180 dB (1e-9), for gain computers that do arithmetic on the level: silence
+/// stays finite instead of turning the slope maths into NaN.
+#[inline]
+pub fn lin_to_db_floor(lin: f32) -> f32 {
+    20.0 * lin.max(1e-9).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        assert!((db_to_lin(-6.0206) - 0.5).abs() < 1e-5);
+        assert_eq!(db_to_lin(0.0), 1.0);
+        for db in [-120.0, -40.0, -3.0, 0.0, 12.0] {
+            assert!((lin_to_db(db_to_lin(db)) - db).abs() < 1e-3, "{db}");
+        }
+        assert_eq!(lin_to_db(0.0), f32::NEG_INFINITY);
+        assert_eq!(lin_to_db_floor(0.0), -180.0);
+        assert_eq!(lin_to_db_floor(0.5), lin_to_db(0.5));
+    }
+}
@@ -0,0 +1,167 @@
+use alloc::{vec, vec::Vec};
+use core::f32::consts::PI;
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+/// Taps of the 2x stage (4k + 3, so the end taps are live); the 4x stage runs at twice the rate
+/// and gets away with fewer (4k + 1, keeping its delay even).
+const FIRST_TAPS: usize = 35;
+const SECOND_TAPS: usize = 17;
+
+/// Blackman-windowed half-band low-pass (cutoff at a quarter of its rate). Every other
+/// coefficient apart from the centre is zero, so only the live ones are kept.
+struct HalfbandFilter {
+    taps: Vec<(usize, f32)>,
+    hist: Vec<f32>,
+    pos: usize,
+}
+
+impl HalfbandFilter {
+    fn new(len: usize) -> Self {
+        let center = (len / 2) as isize;
+        let taps = (0..len)
+            .filter_map(|k| {
+                let t = k as isize - center;
+                if t != 0 && t % 2 == 0 {
+                    return None;
+                }
+                let sinc = if t == 0 {
+                    0.5
+                } else {
+                    (PI * t as f32 / 2.0).sin() / (PI * t as f32)
+                };
+                let w = 2.0 * PI * k as f32 / (len - 1) as f32;
+                let blackman = 0.42 - 0.5 * w.cos() + 0.08 * (2.0 * w).cos();
+                Some((k, sinc * blackman))
+            })
+            .collect::<Vec<_>>();
+        let sum: f32 = taps.iter().map(|&(_, c)| c).sum();
+        Self {
+            taps: taps.into_iter().map(|(k, c)| (k, c / sum)).collect(),
+            hist: vec![0.0; len],
+            pos: 0,
+        }
+    }
+
+    /// Group delay at this filter's rate.
+    fn delay(&self) -> usize {
+        self.hist.len() / 2
+    }
+
+    fn clear(&mut self) {
+        self.hist.fill(0.0);
+    }
+
+    #[inline]
+    fn push(&mut self, x: f32) {
+        let len = self.hist.len();
+        self.pos = (self.pos + len - 1) % len;
+        self.hist[self.pos] = x;
+    }
+
+    #[inline]
+    fn output(&self) -> f32 {
+        let len = self.hist.len();
+        self.taps
+            .iter()
+            .map(|&(k, c)| c * self.hist[(self.pos + k) % len])
+            .sum()
+    }
+
+    /// Zero-stuffs one sample into two at twice the rate.
+    #[inline]
+    fn upsample(&mut self, x: f32) -> [f32; 2] {
+        self.push(2.0 * x);
+        let a = self.output();
+        self.push(0.0);
+        [a, self.output()]
+    }
+
+    /// Filters two samples down to one, taken at the first so delays stay whole.
+    #[inline]
+    fn downsample(&mut self, pair: [f32; 2]) -> f32 {
+        self.push(pair[0]);
+        let y = self.output();
+        self.push(pair[1]);
+        y
+    }
+}
+
+/// One channel of 1x/2x/4x oversampling through cascaded half-band stages; `process` runs a
+/// closure (a waveshaper, usually) at the raised rate.
+pub struct HalfbandOversampler {
+    factor: usize,
+    up: [HalfbandFilter; 2],
+    down: [HalfbandFilter; 2],
+}
+
+impl HalfbandOversampler {
+    /// `factor` is 1, 2 or 4 (anything else rounds down to one of those).
+    pub fn new(factor: usize) -> Self {
+        let mut s = Self {
+            factor: 1,
+            up: [
+                HalfbandFilter::new(FIRST_TAPS),
+                HalfbandFilter::new(SECOND_TAPS),
+            ],
+            down: [
+                HalfbandFilter::new(FIRST_TAPS),
+                HalfbandFilter::new(SECOND_TAPS),
+            ],
+        };
+        s.set_factor(factor);
+        s
+    }
+
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    /// Changes the factor, clearing the filters if it actually changed.
+    pub fn set_factor(&mut self, factor: usize) {
+        let factor = match factor {
+            0 | 1 => 1,
+            2 | 3 => 2,
+            _ => 4,
+        };
+        if factor != self.factor {
+            self.factor = factor;
+            self.clear();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for f in self.up.iter_mut().chain(&mut self.down) {
+            f.clear();
+        }
+    }
+
+    /// Round-trip delay in base-rate samples.
+    pub fn latency(&self) -> usize {
+        match self.factor {
+            1 => 0,
+            2 => self.up[0].delay(),
+            _ => self.up[0].delay() + self.up[1].delay() / 2,
+        }
+    }
+
+    #[inline]
+    pub fn process(&mut self, x: f32, mut f: impl FnMut(f32) -> f32) -> f32 {
+        match self.factor {
+            1 => f(x),
+            2 => {
+                let [a, b] = self.up[0].upsample(x);
+                self.down[0].downsample([f(a), f(b)])
+            }
+            _ => {
+                let [a, b] = self.up[0].upsample(x);
+                let [a0, a1] = self.up[1].upsample(a);
+                let [b0, b1] = self.up[1].upsample(b);
+                let a = self.down[1].downsample([f(a0), f(a1)]);
+                let b = self.down[1].downsample([f(b0), f(b1)]);
+                self.down[0].downsample([a, b])
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Delay compensation. Every node reports how far its output lags its input, in samples
+//! (`*_latency_samples`, 0 for most); these add the reports up along a serial chain, and work
+//! out how much to delay each of several parallel paths so they line up again where they mix.
+
+/// Total latency of nodes in series.
+pub fn chain_latency(latencies: &[u32]) -> u32 {
+    latencies
+        .iter()
+        .fold(0, |total, &l| total.saturating_add(l))
+}
+
+/// Latency from the chain's input to each node's output: `out[i]` is the sum of
+/// `latencies[..=i]`, as far as `out` reaches. Returns the whole chain's total.
+pub fn cumulative_latency(latencies: &[u32], out: &mut [u32]) -> u32 {
+    let mut total = 0_u32;
+    for (i, &l) in latencies.iter().enumerate() {
+        total = total.saturating_add(l);
+        if let Some(o) = out.get_mut(i) {
+            *o = total;
+        }
+    }
+    total
+}
+
+/// Delay each of several parallel paths needs so they arrive together: `out[i]` is how far
+/// `paths[i]` falls short of the slowest path, as far as `out` reaches. Returns the slowest
+/// path's latency, which is the latency of the aligned mix.
+pub fn compensation(paths: &[u32], out: &mut [u32]) -> u32 {
+    let slowest = paths.iter().copied().max().unwrap_or(0);
+    for (o, &p) in out.iter_mut().zip(paths) {
+        *o = slowest - p;
+    }
+    slowest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sums_chains_and_aligns_parallel_paths() {
+        // Look-ahead limiter, an EQ, a 4x oversampled clipper.
+        let chain = [240, 0, 16];
+        assert_eq!(chain_latency(&chain), 256);
+        assert_eq!(chain_latency(&[]), 0);
+        assert_eq!(chain_latency(&[u32::MAX, 1]), u32::MAX);
+
+        let mut at = [0; 3];
+        assert_eq!(cumulative_latency(&chain, &mut at), 256);
+        assert_eq!(at, [240, 240, 256]);
+        let mut short = [0; 1];
+        assert_eq!(cumulative_latency(&chain, &mut short), 256);
+        assert_eq!(short, [240]);
+
+        // A dry path, the chain above, and an STFT path.
+        let mut delays = [0; 3];
+        assert_eq!(compensation(&[0, 256, 1024], &mut delays), 1024);
+        assert_eq!(delays, [1024, 768, 0]);
+        assert_eq!(compensation(&[], &mut []), 0);
+    }
+}
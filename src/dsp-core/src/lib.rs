@@ -0,0 +1,39 @@
+//! DSP building blocks shared by the node crates under `src/nodes/*/dsp`. `no_std` (with
+//! `alloc`) unless the default `std` feature is on; see `math` and `bump`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod biquad;
+pub mod blocksize;
+pub mod bump;
+pub mod convolver;
+pub mod crossover;
+pub mod delay;
+pub mod denormal;
+pub mod envelope;
+pub mod events;
+pub mod fft;
+pub mod gain;
+pub mod halfband;
+pub mod latency;
+pub mod loudness;
+pub mod math;
+pub mod memory;
+pub mod meter;
+pub mod offline;
+pub mod oscillator;
+pub mod oversample;
+pub mod param;
+pub mod precision;
+pub mod preset;
+pub mod saturate;
+pub mod simd;
+pub mod smooth;
+pub mod state;
+pub mod stft;
+pub mod svf;
+pub mod true_peak;
+pub mod window;
+pub mod yin;
@@ -0,0 +1,332 @@
+//! ITU-R BS.1770 / EBU R128 loudness: K-weighting, momentary (400 ms) and short-term (3 s)
+//! loudness, gated integrated loudness and loudness range (EBU Tech 3342).
+
+use alloc::{vec, vec::Vec};
+
+use crate::biquad::Biquad;
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+/// Loudness is measured on 100 ms sub-blocks; the momentary window is 4 of them and the
+/// short-term window 30.
+const MOMENTARY_BLOCKS: usize = 4;
+const SHORT_TERM_BLOCKS: usize = 30;
+
+/// Absolute gate, and the gates relative to the ungated mean for the integrated loudness and
+/// the loudness range.
+const ABSOLUTE_GATE: f32 = -70.0;
+const INTEGRATED_GATE: f32 = -10.0;
+const RANGE_GATE: f32 = -20.0;
+
+/// Gated histograms span -70 to +5 LUFS in 0.1 LU bins.
+const HIST_MAX: f32 = 5.0;
+const HIST_STEP: f32 = 0.1;
+const HIST_BINS: usize = ((HIST_MAX - ABSOLUTE_GATE) / HIST_STEP) as usize;
+
+fn loudness(energy: f64) -> f32 {
+    if energy > 0.0 {
+        (-0.691 + 10.0 * energy.log10()) as f32
+    } else {
+        f32::NEG_INFINITY
+    }
+}
+
+/// BS.1770 channel weight: in a 5.1 layout (L R C LFE Ls Rs) the LFE is left out and the
+/// surrounds count +1.5 dB; anything else weighs every channel the same.
+fn channel_weight(ch: usize, channels: usize) -> f64 {
+    match (channels, ch) {
+        (6, 3) => 0.0,
+        (6, 4 | 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+/// The K-weighting pre-filter: a +4 dB high shelf modelling the head, then the RLB high-pass.
+/// Coefficients are derived for any rate the way libebur128 does, matching the 48 kHz ones in
+/// BS.1770.
+#[derive(Clone, Copy)]
+pub struct KWeighting {
+    shelf: Biquad,
+    highpass: Biquad,
+}
+
+impl KWeighting {
+    pub fn new(sample_rate_hz: f32) -> Self {
+        let sr = sample_rate_hz as f64;
+        let (f0, gain_db, q) = (
+            1_681.974_450_955_533,
+            3.999_843_853_973_347,
+            0.707_175_236_955_419_6,
+        );
+        let k = (core::f64::consts::PI * f0 / sr).tan();
+        let vh = 10.0_f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.499_666_774_154_541_6);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::from_coeffs(
+            ((vh + vb * k / q + k * k) / a0) as f32,
+            (2.0 * (k * k - vh) / a0) as f32,
+            ((vh - vb * k / q + k * k) / a0) as f32,
+            (2.0 * (k * k - 1.0) / a0) as f32,
+            ((1.0 - k / q + k * k) / a0) as f32,
+        );
+        let (f0, q) = (38.135_470_876_024_44, 0.500_327_037_323_877_3);
+        let k = (core::f64::consts::PI * f0 / sr).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let highpass = Biquad::from_coeffs(
+            1.0,
+            -2.0,
+            1.0,
+            (2.0 * (k * k - 1.0) / a0) as f32,
+            ((1.0 - k / q + k * k) / a0) as f32,
+        );
+        Self { shelf, highpass }
+    }
+
+    pub fn clear(&mut self) {
+        self.shelf.clear();
+        self.highpass.clear();
+    }
+
+    #[inline]
+    pub fn process(&mut self, x: f32) -> f32 {
+        self.highpass.process(self.shelf.process(x))
+    }
+}
+
+/// Loudness values binned at 0.1 LU, with the exact energy sum per bin, so gating over hours
+/// of programme takes fixed memory.
+struct Histogram {
+    counts: Vec<u32>,
+    energies: Vec<f64>,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            counts: vec![0; HIST_BINS],
+            energies: vec![0.0; HIST_BINS],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.counts.fill(0);
+        self.energies.fill(0.0);
+    }
+
+    fn bin_loudness(bin: usize) -> f32 {
+        ABSOLUTE_GATE + (bin as f32 + 0.5) * HIST_STEP
+    }
+
+    /// Adds a block; those under the absolute gate are dropped here.
+    fn add(&mut self, energy: f64) {
+        let lufs = loudness(energy);
+        if lufs.is_nan() || lufs <= ABSOLUTE_GATE {
+            return;
+        }
+        let bin = (((lufs - ABSOLUTE_GATE) / HIST_STEP) as usize).min(HIST_BINS - 1);
+        self.counts[bin] += 1;
+        self.energies[bin] += energy;
+    }
+
+    /// First bin at or above the gate `relative` LU under the mean of everything held, or
+    /// `None` while empty.
+    fn gate(&self, relative: f32) -> Option<usize> {
+        let count: u64 = self.counts.iter().map(|&c| c as u64).sum();
+        if count == 0 {
+            return None;
+        }
+        let mean = self.energies.iter().sum::<f64>() / count as f64;
+        let gate = loudness(mean) + relative;
+        Some(
+            (0..HIST_BINS)
+                .find(|&b| Self::bin_loudness(b) >= gate)
+                .unwrap_or(HIST_BINS),
+        )
+    }
+}
+
+/// EBU R128 meter for up to eight channels. Feed interleaved frames; readings are in LUFS
+/// (LU for the range), negative infinity until there is something above the gates.
+pub struct Loudness {
+    filters: [KWeighting; MAX_CHANNELS],
+    block_len: usize,
+    block_pos: usize,
+    block_energy: f64,
+    /// Mean-square energy of the last 30 sub-blocks, newest at `blocks_pos - 1`.
+    blocks: [f64; SHORT_TERM_BLOCKS],
+    blocks_pos: usize,
+    blocks_done: u64,
+    momentary: f32,
+    short_term: f32,
+    integrated_hist: Histogram,
+    range_hist: Histogram,
+}
+
+impl Loudness {
+    pub fn new(sample_rate_hz: f32) -> Self {
+        Self {
+            filters: [KWeighting::new(sample_rate_hz); MAX_CHANNELS],
+            block_len: ((sample_rate_hz * 0.1).round() as usize).max(1),
+            block_pos: 0,
+            block_energy: 0.0,
+            blocks: [0.0; SHORT_TERM_BLOCKS],
+            blocks_pos: 0,
+            blocks_done: 0,
+            momentary: f32::NEG_INFINITY,
+            short_term: f32::NEG_INFINITY,
+            integrated_hist: Histogram::new(),
+            range_hist: Histogram::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for f in &mut self.filters {
+            f.clear();
+        }
+        self.block_pos = 0;
+        self.block_energy = 0.0;
+        self.blocks = [0.0; SHORT_TERM_BLOCKS];
+        self.blocks_pos = 0;
+        self.blocks_done = 0;
+        self.momentary = f32::NEG_INFINITY;
+        self.short_term = f32::NEG_INFINITY;
+        self.integrated_hist.clear();
+        self.range_hist.clear();
+    }
+
+    /// Mean of the newest `n` sub-blocks.
+    fn window(&self, n: usize) -> f64 {
+        (1..=n)
+            .map(|k| self.blocks[(self.blocks_pos + SHORT_TERM_BLOCKS - k) % SHORT_TERM_BLOCKS])
+            .sum::<f64>()
+            / n as f64
+    }
+
+    fn end_block(&mut self) {
+        self.blocks[self.blocks_pos] = self.block_energy / self.block_len as f64;
+        self.blocks_pos = (self.blocks_pos + 1) % SHORT_TERM_BLOCKS;
+        self.blocks_done += 1;
+        self.block_pos = 0;
+        self.block_energy = 0.0;
+        if self.blocks_done >= MOMENTARY_BLOCKS as u64 {
+            let e = self.window(MOMENTARY_BLOCKS);
+            self.momentary = loudness(e);
+            self.integrated_hist.add(e);
+        }
+        if self.blocks_done >= SHORT_TERM_BLOCKS as u64 {
+            let e = self.window(SHORT_TERM_BLOCKS);
+            self.short_term = loudness(e);
+            self.range_hist.add(e);
+        }
+    }
+
+    /// Measures `input`, `channels` interleaved (up to eight; more are ignored).
+    pub fn process(&mut self, input: &[f32], channels: usize) {
+        let used = channels.min(MAX_CHANNELS);
+        for frame in input.chunks_exact(channels) {
+            let mut sum = 0.0;
+            for (ch, &x) in frame[..used].iter().enumerate() {
+                let y = self.filters[ch].process(x) as f64;
+                sum += channel_weight(ch, channels) * y * y;
+            }
+            self.block_energy += sum;
+            self.block_pos += 1;
+            if self.block_pos == self.block_len {
+                self.end_block();
+            }
+        }
+    }
+
+    pub fn momentary(&self) -> f32 {
+        self.momentary
+    }
+
+    pub fn short_term(&self) -> f32 {
+        self.short_term
+    }
+
+    /// Gated over everything since creation or the last reset.
+    pub fn integrated(&self) -> f32 {
+        let h = &self.integrated_hist;
+        let Some(gate) = h.gate(INTEGRATED_GATE) else {
+            return f32::NEG_INFINITY;
+        };
+        let count: u64 = h.counts[gate..].iter().map(|&c| c as u64).sum();
+        if count == 0 {
+            return f32::NEG_INFINITY;
+        }
+        loudness(h.energies[gate..].iter().sum::<f64>() / count as f64)
+    }
+
+    /// Spread between the 10th and 95th percentiles of the gated short-term loudness; 0 until
+    /// there is any.
+    pub fn range(&self) -> f32 {
+        let h = &self.range_hist;
+        let Some(gate) = h.gate(RANGE_GATE) else {
+            return 0.0;
+        };
+        let counts = &h.counts[gate..];
+        let count: u64 = counts.iter().map(|&c| c as u64).sum();
+        if count == 0 {
+            return 0.0;
+        }
+        let percentile = |p: f64| {
+            let target = ((count - 1) as f64 * p).round() as u64;
+            let mut seen = 0;
+            for (b, &c) in counts.iter().enumerate() {
+                seen += c as u64;
+                if seen > target {
+                    return Histogram::bin_loudness(gate + b);
+                }
+            }
+            Histogram::bin_loudness(HIST_BINS - 1)
+        };
+        percentile(0.95) - percentile(0.10)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+
+    fn stereo_tone(amplitude_db: f32, seconds: f32, sr: f32) -> Vec<f32> {
+        let a = 10.0_f32.powf(amplitude_db / 20.0);
+        (0..(seconds * sr) as usize)
+            .flat_map(|i| {
+                let x = a * (2.0 * PI * 1000.0 * i as f32 / sr).sin();
+                [x, x]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tech_3341_tone_reads_minus_23() {
+        for sr in [44100.0, 48000.0, 96000.0] {
+            let mut m = Loudness::new(sr);
+            m.process(&stereo_tone(-23.0, 20.0, sr), 2);
+            for lufs in [m.momentary(), m.short_term(), m.integrated()] {
+                assert!((lufs + 23.0).abs() < 0.1, "{sr} Hz: {lufs}");
+            }
+            // Silence falls under the absolute gate and leaves the integrated reading alone.
+            m.process(&vec![0.0; 2 * sr as usize * 10], 2);
+            assert!((m.integrated() + 23.0).abs() < 0.1);
+            assert!(m.momentary() < ABSOLUTE_GATE);
+        }
+    }
+
+    #[test]
+    fn tech_3342_steps_give_ten_lu_range() {
+        let sr = 48000.0;
+        let mut m = Loudness::new(sr);
+        m.process(&stereo_tone(-20.0, 20.0, sr), 2);
+        m.process(&stereo_tone(-30.0, 20.0, sr), 2);
+        assert!((m.range() - 10.0).abs() < 1.0, "{}", m.range());
+        m.reset();
+        assert_eq!(m.integrated(), f32::NEG_INFINITY);
+        assert_eq!(m.range(), 0.0);
+    }
+}
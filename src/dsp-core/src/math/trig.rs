@@ -0,0 +1,301 @@
+//! sin, cos and tan for `Float`, after musl's (the ones compiler-builtins ships) minus the
+//! Payne-Hanek reduction musl keeps for huge arguments: that routine and its tables are most of
+//! the 6.5 KB musl's versions add to every module that takes a sine, for arguments no node
+//! produces. Past the range the split pi/2 constants cover (2^28 pi/2 for f32, 2^20 pi/2 for
+//! f64) the argument is first wrapped to one turn in f64 (`wrap`), which keeps the result a
+//! sine but drifts from the true one as the argument grows.
+//!
+//! f32 works in f64 throughout, so the reduction is exact enough to leave only the kernels'
+//! error (under an ulp); f64 tan is sin / cos of the reduced argument, within two.
+
+use core::f64::consts::{FRAC_2_PI, FRAC_PI_2, TAU};
+
+/// Adding and subtracting it rounds an f64 below 2^51 to the nearest integer.
+const TO_INT: f64 = 1.5 / f64::EPSILON;
+
+/// pi/2 as 33 + 53 bits, enough for an f32 argument times up to 2^28.
+const PIO2_1F: f64 = 1.570_796_310_901_641_8;
+const PIO2_1TF: f64 = 1.589_325_477_352_819_6e-8;
+const MEDIUM_F32: f64 = 268_435_456.0 * FRAC_PI_2;
+
+/// pi/2 in three 33-bit pieces and their tails, for up to 2^20 times it.
+const PIO2_1: f64 = 1.570_796_326_734_125_6;
+const PIO2_1T: f64 = 6.077_100_506_506_192e-11;
+const PIO2_2: f64 = 6.077_100_506_303_966e-11;
+const PIO2_2T: f64 = 2.022_266_248_795_950_6e-21;
+const PIO2_3: f64 = 2.022_266_248_711_166_5e-21;
+const PIO2_3T: f64 = 8.478_427_660_368_9e-32;
+const MEDIUM_F64: f64 = 1_048_576.0 * FRAC_PI_2;
+
+/// `x` less a whole number of turns (NaN for infinities). Not `%`, which would link fmod and a
+/// u128 division.
+fn wrap(x: f64) -> f64 {
+    let turns = x / TAU;
+    let fract = if turns.abs() < 4_503_599_627_370_496.0 {
+        turns - turns as i64 as f64
+    } else {
+        0.0 * turns
+    };
+    fract * TAU
+}
+
+/// `x` as `n * pi/2 + y`, |y| <= pi/4; `n` mod 4 picks the quadrant.
+fn rem_pio2f(x: f64) -> (i32, f64) {
+    let x = if x.abs() < MEDIUM_F32 { x } else { wrap(x) };
+    let f = x * FRAC_2_PI + TO_INT - TO_INT;
+    (f as i32, x - f * PIO2_1F - f * PIO2_1TF)
+}
+
+/// `rem_pio2f` for f64, with `y` as a head and tail: one round of subtraction, then a second
+/// and third while cancellation has eaten into the head's precision.
+fn rem_pio2(x: f64) -> (i32, f64, f64) {
+    let x = if x.abs() < MEDIUM_F64 { x } else { wrap(x) };
+    let exponent = |v: f64| ((v.to_bits() >> 52) & 0x7ff) as i32;
+    let f = x * FRAC_2_PI + TO_INT - TO_INT;
+    let mut r = x - f * PIO2_1;
+    let mut w = f * PIO2_1T;
+    let mut y0 = r - w;
+    let ex = exponent(x);
+    if ex - exponent(y0) > 16 {
+        let t = r;
+        w = f * PIO2_2;
+        r = t - w;
+        w = f * PIO2_2T - ((t - r) - w);
+        y0 = r - w;
+        if ex - exponent(y0) > 49 {
+            let t = r;
+            w = f * PIO2_3;
+            r = t - w;
+            w = f * PIO2_3T - ((t - r) - w);
+            y0 = r - w;
+        }
+    }
+    (f as i32, y0, (r - y0) - w)
+}
+
+/// sin on [-pi/4, pi/4], rounded to f32.
+fn k_sinf(x: f64) -> f32 {
+    const S1: f64 = -0.166_666_666_416_265_24;
+    const S2: f64 = 0.008_333_329_385_889_463;
+    const S3: f64 = -0.000_198_393_348_360_966_32;
+    const S4: f64 = 0.000_002_718_311_493_989_822;
+    let z = x * x;
+    let w = z * z;
+    let r = S3 + z * S4;
+    let s = z * x;
+    ((x + s * (S1 + z * S2)) + s * w * r) as f32
+}
+
+/// cos on [-pi/4, pi/4], rounded to f32.
+fn k_cosf(x: f64) -> f32 {
+    const C0: f64 = -0.499_999_997_251_031;
+    const C1: f64 = 0.041_666_623_323_739_06;
+    const C2: f64 = -0.001_388_676_377_460_993;
+    const C3: f64 = 0.000_024_390_448_796_277_41;
+    let z = x * x;
+    let w = z * z;
+    let r = C2 + z * C3;
+    (((1.0 + z * C0) + w * C1) + (w * z) * r) as f32
+}
+
+/// tan on [-pi/4, pi/4], or -1/tan when `odd`, rounded to f32.
+fn k_tanf(x: f64, odd: bool) -> f32 {
+    const T: [f64; 6] = [
+        0.333_331_395_030_791_4,
+        0.133_392_002_712_976_74,
+        0.053_381_237_844_567_04,
+        0.024_528_318_116_654_728,
+        0.002_974_357_433_599_673,
+        0.009_465_647_849_436_732,
+    ];
+    let z = x * x;
+    let r = T[4] + z * T[5];
+    let t = T[2] + z * T[3];
+    let w = z * z;
+    let s = z * x;
+    let u = T[0] + z * T[1];
+    let r = (x + s * u) + (s * w) * (t + w * r);
+    (if odd { -1.0 / r } else { r }) as f32
+}
+
+/// sin of `x + y` on [-pi/4, pi/4], `y` the tail of the reduced argument.
+fn k_sin(x: f64, y: f64) -> f64 {
+    const S1: f64 = -1.666_666_666_666_663_2e-1;
+    const S2: f64 = 8.333_333_333_322_49e-3;
+    const S3: f64 = -1.984_126_982_985_795e-4;
+    const S4: f64 = 2.755_731_370_707_006_8e-6;
+    const S5: f64 = -2.505_076_025_340_686_3e-8;
+    const S6: f64 = 1.589_690_995_211_55e-10;
+    let z = x * x;
+    let w = z * z;
+    let r = S2 + z * (S3 + z * S4) + z * w * (S5 + z * S6);
+    let v = z * x;
+    x - ((z * (0.5 * y - v * r) - y) - v * S1)
+}
+
+/// cos of `x + y` on [-pi/4, pi/4].
+fn k_cos(x: f64, y: f64) -> f64 {
+    const C1: f64 = 4.166_666_666_666_66e-2;
+    const C2: f64 = -1.388_888_888_887_411e-3;
+    const C3: f64 = 2.480_158_728_947_673e-5;
+    const C4: f64 = -2.755_731_435_139_066_3e-7;
+    const C5: f64 = 2.087_572_321_298_175e-9;
+    const C6: f64 = -1.135_964_755_778_819_5e-11;
+    let z = x * x;
+    let w = z * z;
+    let r = z * (C1 + z * (C2 + z * C3)) + w * w * (C4 + z * (C5 + z * C6));
+    let hz = 0.5 * z;
+    let w = 1.0 - hz;
+    w + (((1.0 - w) - hz) + (z * r - x * y))
+}
+
+pub fn sinf(x: f32) -> f32 {
+    if x.abs() < 2.441_406_3e-4 {
+        return x;
+    }
+    let (n, y) = rem_pio2f(x as f64);
+    match n & 3 {
+        0 => k_sinf(y),
+        1 => k_cosf(y),
+        2 => k_sinf(-y),
+        _ => -k_cosf(y),
+    }
+}
+
+pub fn cosf(x: f32) -> f32 {
+    let (n, y) = rem_pio2f(x as f64);
+    match n & 3 {
+        0 => k_cosf(y),
+        1 => k_sinf(-y),
+        2 => -k_cosf(y),
+        _ => k_sinf(y),
+    }
+}
+
+pub fn sincosf(x: f32) -> (f32, f32) {
+    if x.abs() < 2.441_406_3e-4 {
+        return (x, 1.0);
+    }
+    let (n, y) = rem_pio2f(x as f64);
+    let (s, c) = (k_sinf(y), k_cosf(y));
+    match n & 3 {
+        0 => (s, c),
+        1 => (c, -s),
+        2 => (-s, -c),
+        _ => (-c, s),
+    }
+}
+
+pub fn tanf(x: f32) -> f32 {
+    if x.abs() < 2.441_406_3e-4 {
+        return x;
+    }
+    let (n, y) = rem_pio2f(x as f64);
+    k_tanf(y, n & 1 == 1)
+}
+
+pub fn sincos(x: f64) -> (f64, f64) {
+    if x.abs() < 1.490_116_119_384_765_6e-8 {
+        return (x, 1.0);
+    }
+    let (n, y0, y1) = rem_pio2(x);
+    let (s, c) = (k_sin(y0, y1), k_cos(y0, y1));
+    match n & 3 {
+        0 => (s, c),
+        1 => (c, -s),
+        2 => (-s, -c),
+        _ => (-c, s),
+    }
+}
+
+pub fn sin(x: f64) -> f64 {
+    if x.abs() < 1.490_116_119_384_765_6e-8 {
+        return x;
+    }
+    let (n, y0, y1) = rem_pio2(x);
+    match n & 3 {
+        0 => k_sin(y0, y1),
+        1 => k_cos(y0, y1),
+        2 => -k_sin(y0, y1),
+        _ => -k_cos(y0, y1),
+    }
+}
+
+pub fn cos(x: f64) -> f64 {
+    let (n, y0, y1) = rem_pio2(x);
+    match n & 3 {
+        0 => k_cos(y0, y1),
+        1 => -k_sin(y0, y1),
+        2 => -k_cos(y0, y1),
+        _ => k_sin(y0, y1),
+    }
+}
+
+pub fn tan(x: f64) -> f64 {
+    let (s, c) = sincos(x);
+    s / c
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ulps32(a: f32, b: f32) -> u32 {
+        (a.to_bits() as i32)
+            .wrapping_sub(b.to_bits() as i32)
+            .unsigned_abs()
+    }
+
+    fn ulps64(a: f64, b: f64) -> u64 {
+        (a.to_bits() as i64)
+            .wrapping_sub(b.to_bits() as i64)
+            .unsigned_abs()
+    }
+
+    /// Arguments across the range the nodes use, close to every multiple of pi/4 up to 1000
+    /// (where the reduction cancels the most) and in between.
+    fn arguments() -> impl Iterator<Item = f64> {
+        let steps = (0..40_000).map(|i| i as f64 * 0.025_132_7);
+        let near = (1..1300).flat_map(|k| {
+            let m = k as f64 * core::f64::consts::FRAC_PI_4;
+            [m, m - 1e-7, m + 3e-6]
+        });
+        let small = [1e-9, 1e-5, 2.4e-4, 2.5e-4, 0.1];
+        steps.chain(near).chain(small).flat_map(|x| [x, -x])
+    }
+
+    #[test]
+    fn f32_is_within_an_ulp_of_std() {
+        for x in arguments().map(|x| x as f32) {
+            let (s, c) = sincosf(x);
+            assert!(ulps32(sinf(x), x.sin()) <= 1, "sin({x})");
+            assert!(ulps32(cosf(x), x.cos()) <= 1, "cos({x})");
+            assert!(ulps32(tanf(x), x.tan()) <= 1, "tan({x})");
+            assert_eq!((s, c), (sinf(x), cosf(x)), "sincos({x})");
+        }
+    }
+
+    #[test]
+    fn f64_is_within_two_ulps_of_std() {
+        for x in arguments() {
+            let (s, c) = sincos(x);
+            assert!(ulps64(sin(x), x.sin()) <= 1, "sin({x})");
+            assert!(ulps64(cos(x), x.cos()) <= 1, "cos({x})");
+            assert!(ulps64(tan(x), x.tan()) <= 2, "tan({x})");
+            assert_eq!((s, c), (sin(x), cos(x)), "sincos({x})");
+        }
+    }
+
+    #[test]
+    fn huge_arguments_stay_a_sine() {
+        for x in [3e8_f32, -1e12, 1e30] {
+            let (s, c) = sincosf(x);
+            assert!((s * s + c * c - 1.0).abs() < 1e-6, "{x}");
+        }
+        for x in [2e6_f64, -1e15, 1e300] {
+            let (s, c) = sincos(x);
+            assert!((s * s + c * c - 1.0).abs() < 1e-12, "{x}");
+        }
+        assert!(sinf(f32::INFINITY).is_nan() && cos(f64::NAN).is_nan());
+    }
+}
@@ -0,0 +1,131 @@
+//! The allocation exports the worklet glue uses to get buffers inside a node's WASM memory.
+//! Every block is aligned to at least `MIN_ALIGN` (one v128), so simd128 kernels can load
+//! from any of them; `alloc_zeroed` hands out silence instead of whatever the allocator last
+//! left there. Node crates export these through `export_memory!`.
+
+use alloc::alloc::Layout;
+
+/// Alignment of every block (and the rounded-up alignment of smaller requests).
+pub const MIN_ALIGN: usize = 16;
+
+fn layout(bytes: usize, align: usize) -> Option<Layout> {
+    if !align.is_power_of_two() {
+        return None;
+    }
+    Layout::from_size_align(bytes, align.max(MIN_ALIGN)).ok()
+}
+
+fn allocate(bytes: usize, align: usize, zeroed: bool) -> *mut u8 {
+    let Some(layout) = layout(bytes, align) else {
+        return core::ptr::null_mut();
+    };
+    if bytes == 0 {
+        // Non-null (loaders treat 0 as failure) and never dereferenced.
+        return layout.align() as *mut u8;
+    }
+    unsafe {
+        if zeroed {
+            alloc::alloc::alloc_zeroed(layout)
+        } else {
+            alloc::alloc::alloc(layout)
+        }
+    }
+}
+
+/// `bytes` uninitialised bytes aligned to `align` (a power of two); null when `align` isn't
+/// one or the allocation fails.
+pub fn alloc(bytes: usize, align: usize) -> *mut u8 {
+    allocate(bytes, align, false)
+}
+
+/// `alloc`, filled with zeros.
+pub fn alloc_zeroed(bytes: usize, align: usize) -> *mut u8 {
+    allocate(bytes, align, true)
+}
+
+/// Frees a block from `alloc` or `alloc_zeroed`; `bytes` and `align` must be what it was
+/// asked for with. Null and empty blocks are ignored.
+///
+/// # Safety
+/// `ptr` must come from `alloc`/`alloc_zeroed` with the same `bytes` and `align`, and not
+/// have been freed already.
+pub unsafe fn free(ptr: *mut u8, bytes: usize, align: usize) {
+    if ptr.is_null() || bytes == 0 {
+        return;
+    }
+    if let Some(layout) = layout(bytes, align) {
+        alloc::alloc::dealloc(ptr, layout);
+    }
+}
+
+/// A `no_std` wasm build's panic handler: trap, which the worklet sees as the call throwing.
+/// Here rather than in each node crate so several can link into one module (the rack).
+#[cfg(all(not(feature = "std"), target_arch = "wasm32"))]
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    core::arch::wasm32::unreachable()
+}
+
+/// Generates a node crate's allocation exports:
+///
+/// - `wasm_alloc(bytes) -> ptr` and `wasm_alloc_zeroed(bytes) -> ptr`, `MIN_ALIGN`-aligned,
+///   freed with `wasm_free(ptr, bytes)`
+/// - `wasm_alloc_aligned(bytes, align) -> ptr` for wider alignment (a power of two; null
+///   otherwise), freed with `wasm_free_aligned(ptr, bytes, align)`
+///
+/// Only one crate in a link may expand it: a node crate built into the rack skips it (its
+/// `rack` feature) and the rack exports them for every node.
+#[macro_export]
+macro_rules! export_memory {
+    () => {
+        #[no_mangle]
+        pub extern "C" fn wasm_alloc(bytes: usize) -> *mut u8 {
+            $crate::memory::alloc(bytes, $crate::memory::MIN_ALIGN)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn wasm_alloc_zeroed(bytes: usize) -> *mut u8 {
+            $crate::memory::alloc_zeroed(bytes, $crate::memory::MIN_ALIGN)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn wasm_alloc_aligned(bytes: usize, align: usize) -> *mut u8 {
+            $crate::memory::alloc(bytes, align)
+        }
+
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn wasm_free(ptr: *mut u8, bytes: usize) {
+            unsafe { $crate::memory::free(ptr, bytes, $crate::memory::MIN_ALIGN) }
+        }
+
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn wasm_free_aligned(ptr: *mut u8, bytes: usize, align: usize) {
+            unsafe { $crate::memory::free(ptr, bytes, align) }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_are_aligned_and_zeroed() {
+        for bytes in [1, 12, 4096] {
+            let p = alloc_zeroed(bytes, MIN_ALIGN);
+            assert_eq!(p as usize % MIN_ALIGN, 0);
+            let block = unsafe { core::slice::from_raw_parts(p, bytes) };
+            assert!(block.iter().all(|&b| b == 0));
+            unsafe { free(p, bytes, MIN_ALIGN) };
+        }
+        let p = alloc(100, 256);
+        assert_eq!(p as usize % 256, 0);
+        unsafe { free(p, 100, 256) };
+
+        assert!(alloc(8, 3).is_null());
+        assert!(!alloc(0, 64).is_null());
+        unsafe { free(alloc(0, 64), 0, 64) };
+    }
+}
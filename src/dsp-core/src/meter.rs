@@ -0,0 +1,94 @@
+//! Meter readings taken over a fixed number of frames rather than over whatever the last
+//! process call happened to cover, so a gain-reduction meter reads the same whether the host
+//! calls with 128 frames, 1 or 4096.
+
+use crate::offline::CHUNK_FRAMES;
+
+/// The lowest value pushed over each window of `len` frames (`CHUNK_FRAMES` by default, one
+/// render quantum at the standard size). The reading is the last complete window's; it
+/// starts, and goes back to on `reset`, at `idle`.
+#[derive(Clone, Copy, Debug)]
+pub struct WindowMin {
+    idle: f32,
+    len: usize,
+    pos: usize,
+    current: f32,
+    reading: f32,
+}
+
+impl WindowMin {
+    pub const fn new(idle: f32) -> Self {
+        Self::with_len(idle, CHUNK_FRAMES)
+    }
+
+    pub const fn with_len(idle: f32, len: usize) -> Self {
+        Self {
+            idle,
+            len: if len == 0 { 1 } else { len },
+            pos: 0,
+            current: idle,
+            reading: idle,
+        }
+    }
+
+    /// Changes the window (for a rate change such as oversampling); the window in progress
+    /// starts over.
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len.max(1);
+        self.pos = 0;
+        self.current = self.idle;
+    }
+
+    /// Folds in one frame's value, publishing the window's lowest when it completes.
+    #[inline]
+    pub fn push(&mut self, v: f32) {
+        self.current = self.current.min(v);
+        self.pos += 1;
+        if self.pos == self.len {
+            self.reading = self.current;
+            self.current = self.idle;
+            self.pos = 0;
+        }
+    }
+
+    /// Lowest value of the last complete window.
+    pub fn reading(&self) -> f32 {
+        self.reading
+    }
+
+    /// Restores a saved reading, with a fresh window in progress.
+    pub fn set_reading(&mut self, reading: f32) {
+        self.reading = reading;
+        self.pos = 0;
+        self.current = self.idle;
+    }
+
+    pub fn reset(&mut self) {
+        self.set_reading(self.idle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_last_complete_window() {
+        let mut m = WindowMin::with_len(0.0, 4);
+        for v in [-1.0, -3.0, -2.0] {
+            m.push(v);
+        }
+        assert_eq!(m.reading(), 0.0);
+        m.push(0.0);
+        assert_eq!(m.reading(), -3.0);
+        for _ in 0..4 {
+            m.push(-0.5);
+        }
+        assert_eq!(m.reading(), -0.5);
+        m.push(-9.0);
+        assert_eq!(m.reading(), -0.5);
+        m.reset();
+        assert_eq!(m.reading(), 0.0);
+        assert_eq!(WindowMin::new(1.0).len, CHUNK_FRAMES);
+    }
+}
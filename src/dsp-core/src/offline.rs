@@ -0,0 +1,28 @@
+//! Rendering buffers of any length outside the worklet: rendering a graph to a file, or
+//! golden-output tests. A node's `*_process_offline` export (node-abi's `split_offline`) walks
+//! the whole buffer in `CHUNK_FRAMES` pieces, so the node sees the same call pattern (and its
+//! meters and smoothers run the same) as under the worklet.
+
+use core::ops::Range;
+
+/// Frames per call, the Web Audio render quantum.
+pub const CHUNK_FRAMES: usize = 128;
+
+/// `0..total_frames` in consecutive ranges of at most `CHUNK_FRAMES` frames.
+pub fn chunks(total_frames: usize) -> impl Iterator<Item = Range<usize>> {
+    (0..total_frames)
+        .step_by(CHUNK_FRAMES)
+        .map(move |start| start..(start + CHUNK_FRAMES).min(total_frames))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_cover_the_buffer() {
+        let spans: Vec<_> = chunks(300).collect();
+        assert_eq!(spans, [0..128, 128..256, 256..300]);
+        assert_eq!(chunks(0).count(), 0);
+    }
+}
@@ -0,0 +1,124 @@
+use alloc::{vec, vec::Vec};
+use core::f32::consts::PI;
+
+use crate::state::{State, StateReader, StateWriter};
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+const HALF_TAPS_PER_PHASE: usize = 8;
+
+/// Combined delay of one up/down round trip, in base-rate samples.
+pub const RESAMPLER_LATENCY: usize = 2 * HALF_TAPS_PER_PHASE;
+
+/// Linear-phase windowed-sinc lowpass with `16 * factor + 1` taps (group delay of 8 base-rate
+/// samples), used for both interpolation and decimation.
+fn design_filter(factor: usize) -> Vec<f32> {
+    let taps = 2 * HALF_TAPS_PER_PHASE * factor + 1;
+    let center = (taps / 2) as f32;
+    let fc = 0.45 / factor as f32;
+    let mut h: Vec<f32> = (0..taps)
+        .map(|k| {
+            let t = k as f32 - center;
+            let sinc = if t == 0.0 {
+                1.0
+            } else {
+                (2.0 * PI * fc * t).sin() / (2.0 * PI * fc * t)
+            };
+            let w = 2.0 * PI * k as f32 / (taps - 1) as f32;
+            let blackman = 0.42 - 0.5 * w.cos() + 0.08 * (2.0 * w).cos();
+            sinc * blackman
+        })
+        .collect();
+    let sum: f32 = h.iter().sum();
+    for c in &mut h {
+        *c /= sum;
+    }
+    h
+}
+
+/// One channel of polyphase up/down sampling by a fixed integer factor.
+pub struct Resampler {
+    factor: usize,
+    h: Vec<f32>,
+    up: Vec<f32>,
+    up_pos: usize,
+    down: Vec<f32>,
+    down_pos: usize,
+}
+
+impl Resampler {
+    pub fn new(factor: usize) -> Self {
+        let factor = factor.max(1);
+        let h = design_filter(factor);
+        Self {
+            factor,
+            up: vec![0.0; 2 * HALF_TAPS_PER_PHASE + 1],
+            up_pos: 0,
+            down: vec![0.0; h.len()],
+            down_pos: 0,
+            h,
+        }
+    }
+
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    pub fn clear(&mut self) {
+        self.up.fill(0.0);
+        self.up_pos = 0;
+        self.down.fill(0.0);
+        self.down_pos = 0;
+    }
+
+    /// Writes `factor` interpolated samples for one input sample into `out`.
+    #[inline]
+    pub fn upsample(&mut self, x: f32, out: &mut [f32]) {
+        let factor = self.factor;
+        let len = self.up.len();
+        self.up_pos = (self.up_pos + len - 1) % len;
+        self.up[self.up_pos] = x;
+        for (p, y) in out.iter_mut().take(factor).enumerate() {
+            let mut acc = 0.0;
+            for (j, c) in self.h[p..].iter().step_by(factor).enumerate() {
+                acc += c * self.up[(self.up_pos + j) % len];
+            }
+            *y = acc * factor as f32;
+        }
+    }
+
+    /// Consumes `factor` samples and returns one decimated sample, filtered at the first of
+    /// them so the round trip delay stays a whole number of base-rate samples.
+    #[inline]
+    pub fn downsample(&mut self, input: &[f32]) -> f32 {
+        let len = self.down.len();
+        let mut y = 0.0;
+        for (p, &x) in input.iter().take(self.factor).enumerate() {
+            self.down_pos = (self.down_pos + len - 1) % len;
+            self.down[self.down_pos] = x;
+            if p == 0 {
+                for (k, c) in self.h.iter().enumerate() {
+                    y += c * self.down[(self.down_pos + k) % len];
+                }
+            }
+        }
+        y
+    }
+}
+
+impl State for Resampler {
+    fn save(&self, w: &mut StateWriter) {
+        w.f32s(&self.up);
+        w.index(self.up_pos);
+        w.f32s(&self.down);
+        w.index(self.down_pos);
+    }
+
+    fn load(&mut self, r: &mut StateReader) {
+        r.f32s(&mut self.up);
+        self.up_pos = r.index(self.up.len());
+        r.f32s(&mut self.down);
+        self.down_pos = r.index(self.down.len());
+    }
+}
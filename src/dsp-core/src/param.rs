@@ -0,0 +1,32 @@
+//! Sanitising parameter values from the host.
+
+/// `v` limited to `min..=max`; NaN and the infinities give `min`, so a bad value from the
+/// worklet can't reach a coefficient.
+#[inline]
+pub fn clamp(v: f32, min: f32, max: f32) -> f32 {
+    if !v.is_finite() {
+        return min;
+    }
+    if v < min {
+        min
+    } else if v > max {
+        max
+    } else {
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_and_rejects_non_finite() {
+        assert_eq!(clamp(0.5, 0.0, 1.0), 0.5);
+        assert_eq!(clamp(-2.0, 0.0, 1.0), 0.0);
+        assert_eq!(clamp(7.0, 0.0, 1.0), 1.0);
+        for v in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert_eq!(clamp(v, -1.0, 1.0), -1.0);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Precision of the recursive filters' coefficients and state. f32 is plenty almost
+//! everywhere, but a low, high-Q band (a 30 Hz bell at 44.1 kHz) puts its poles so close to the
+//! unit circle that f32 rounding in the coefficients and the feedback shows up as a shifted
+//! response, drift and low-level limit cycles. The `f64` feature runs `Biquad`, `BiquadBank`
+//! and the SVF integrators in f64; samples in and out stay f32, and the simd128 biquad bank
+//! (f32 lanes) gives way to the scalar one.
+
+#[cfg(feature = "f64")]
+pub type Real = f64;
+#[cfg(not(feature = "f64"))]
+pub type Real = f32;
+
+pub const PI: Real = core::f64::consts::PI as Real;
+
+/// A `Real` result as a sample again; a no-op unless `f64` is on.
+#[inline]
+#[allow(clippy::unnecessary_cast)]
+pub fn to_f32(x: Real) -> f32 {
+    x as f32
+}
+
+/// `denormal::flush` at `Real` precision.
+#[inline]
+pub fn flush(x: Real) -> Real {
+    if x.abs() < crate::denormal::FLUSH_THRESHOLD as Real {
+        0.0
+    } else {
+        x
+    }
+}
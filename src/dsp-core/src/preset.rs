@@ -0,0 +1,182 @@
+//! Presets: a node's parameter values as a compact binary blob that keeps loading after the
+//! node changes. Values are keyed by parameter name rather than index, so reordering or
+//! adding parameters doesn't shift them; and the blob records the node's preset version, so
+//! the node's migrations can bring an old preset up to date (a renamed parameter, a changed
+//! range) before it is applied.
+//!
+//! Layout, little-endian: `PRESET_MAGIC` and `PRESET_FORMAT` (u32 each), the node's preset
+//! version (u32), the entry count (u32), then per entry the name's length (one byte), its
+//! UTF-8 bytes and the value (f32).
+
+use alloc::{string::String, vec, vec::Vec};
+
+use crate::state::{StateReader, StateWriter};
+
+/// A preset's first four bytes, "PRST".
+pub const PRESET_MAGIC: u32 = u32::from_le_bytes(*b"PRST");
+/// Version of the layout above, bumped if it changes; independent of any node's version.
+pub const PRESET_FORMAT: u32 = 1;
+
+/// Longest parameter name a preset stores.
+pub const MAX_NAME_BYTES: usize = u8::MAX as usize;
+
+/// Upgrades a preset by one version. A node's migrations are listed oldest first:
+/// `migrations[v]` takes a version `v` preset to `v + 1`, and the node's current preset
+/// version is their count.
+pub type Migration = fn(&mut Preset);
+
+/// Parameter values by name, in the order they were set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Preset {
+    /// The node's preset version these values follow.
+    pub version: u32,
+    values: Vec<(String, f32)>,
+}
+
+impl Preset {
+    pub fn new(version: u32) -> Self {
+        Self {
+            version,
+            values: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<f32> {
+        self.values.iter().find(|(n, _)| n == name).map(|&(_, v)| v)
+    }
+
+    /// Sets `name`, replacing its value if present. Names longer than `MAX_NAME_BYTES` are
+    /// ignored.
+    pub fn set(&mut self, name: &str, value: f32) {
+        if name.len() > MAX_NAME_BYTES {
+            return;
+        }
+        match self.values.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value,
+            None => self.values.push((name.into(), value)),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<f32> {
+        let i = self.values.iter().position(|(n, _)| n == name)?;
+        Some(self.values.remove(i).1)
+    }
+
+    /// Moves `from`'s value to `to`, for a renamed parameter; nothing if `from` isn't set.
+    pub fn rename(&mut self, from: &str, to: &str) {
+        if let Some(value) = self.remove(from) {
+            self.set(to, value);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f32)> {
+        self.values.iter().map(|(n, v)| (n.as_str(), *v))
+    }
+
+    /// Runs the migrations from the preset's version up to the node's current one. False,
+    /// leaving it alone, for a preset from a newer node than `migrations` knows about.
+    pub fn migrate(&mut self, migrations: &[Migration]) -> bool {
+        let Some(pending) = migrations.get(self.version as usize..) else {
+            return false;
+        };
+        for migration in pending {
+            migration(self);
+            self.version += 1;
+        }
+        true
+    }
+
+    pub fn write(&self, w: &mut StateWriter) {
+        w.u32(PRESET_MAGIC);
+        w.u32(PRESET_FORMAT);
+        w.u32(self.version);
+        w.index(self.values.len());
+        for (name, value) in &self.values {
+            w.bytes(&[name.len() as u8]);
+            w.bytes(name.as_bytes());
+            w.f32(*value);
+        }
+    }
+
+    /// Bytes `encode` produces.
+    pub fn encoded_len(&self) -> usize {
+        let mut w = StateWriter::sizing();
+        self.write(&mut w);
+        w.len()
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![0; self.encoded_len()];
+        self.write(&mut StateWriter::new(&mut out));
+        out
+    }
+
+    /// The preset in `bytes`; `None` if they aren't one (wrong magic or format, cut short,
+    /// a name that isn't UTF-8).
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut r = StateReader::new(bytes);
+        if r.u32() != PRESET_MAGIC || r.u32() != PRESET_FORMAT {
+            return None;
+        }
+        let mut preset = Self::new(r.u32());
+        let count = r.u32() as usize;
+        // Each entry takes at least 5 bytes; don't trust the count further than that.
+        if r.failed() || count > r.remaining() / 5 {
+            return None;
+        }
+        for _ in 0..count {
+            let [len] = r.bytes::<1>();
+            let name = core::str::from_utf8(r.take(len as usize)?).ok()?;
+            let value = r.f32();
+            if r.failed() {
+                return None;
+            }
+            preset.set(name, value);
+        }
+        Some(preset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_migrates() {
+        let mut preset = Preset::new(0);
+        preset.set("Cutoff", 800.0);
+        preset.set("Q", 0.7);
+        preset.set("Cutoff", 1200.0);
+        let bytes = preset.encode();
+        assert_eq!(bytes.len(), preset.encoded_len());
+        assert_eq!(Preset::decode(&bytes), Some(preset.clone()));
+        assert_eq!(Preset::decode(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(Preset::decode(&bytes[4..]), None);
+
+        let migrations: &[Migration] = &[
+            |p| p.rename("Q", "Resonance"),
+            |p| {
+                let hz = p.get("Cutoff").unwrap_or(1000.0);
+                p.set("Cutoff", hz / 1000.0);
+            },
+        ];
+        assert!(preset.migrate(migrations));
+        assert_eq!(preset.version, 2);
+        assert_eq!(
+            preset.iter().collect::<Vec<_>>(),
+            [("Cutoff", 1.2), ("Resonance", 0.7)]
+        );
+        // Already current; and a preset from a newer node is left alone.
+        assert!(preset.migrate(migrations));
+        assert!(!preset.migrate(&migrations[..1]));
+        assert_eq!(preset.version, 2);
+    }
+}
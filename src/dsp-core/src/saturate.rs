@@ -0,0 +1,71 @@
+//! Memoryless soft clippers, all odd, with unity slope at 0 and holding at ±1. Run them
+//! oversampled (`crate::oversample`) where the harmonics they add would alias.
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+#[inline]
+pub fn hard_clip(x: f32) -> f32 {
+    x.clamp(-1.0, 1.0)
+}
+
+/// Cubic `1.5x - 0.5x^3` on `x / 1.5`: reaches full scale with zero slope at |x| = 1.5.
+#[inline]
+pub fn cubic_clip(x: f32) -> f32 {
+    let x = (x * (2.0 / 3.0)).clamp(-1.0, 1.0);
+    1.5 * x - 0.5 * x * x * x
+}
+
+#[inline]
+pub fn tanh_clip(x: f32) -> f32 {
+    x.tanh()
+}
+
+/// `x / (1 + |x|)`: the gentlest knee, approaching full scale slowly; cheap enough for
+/// per-sample feedback paths.
+#[inline]
+pub fn rational_clip(x: f32) -> f32 {
+    x / (1.0 + x.abs())
+}
+
+/// Linear up to `knee` (0–1), then bends into full scale along a tanh curve, so quiet material
+/// passes untouched.
+#[inline]
+pub fn knee_clip(x: f32, knee: f32) -> f32 {
+    let a = x.abs();
+    if a <= knee {
+        return x;
+    }
+    let room = 1.0 - knee;
+    let y = if room > 0.0 {
+        knee + room * ((a - knee) / room).tanh()
+    } else {
+        1.0
+    };
+    y.copysign(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unity_slope_and_bounded() {
+        let curves: [fn(f32) -> f32; 5] = [hard_clip, cubic_clip, tanh_clip, rational_clip, |x| {
+            knee_clip(x, 0.5)
+        }];
+        for (i, f) in curves.iter().enumerate() {
+            assert!((f(1e-3) / 1e-3 - 1.0).abs() < 1e-3, "curve {i}");
+            let mut prev = f(-20.0);
+            for k in -199..=200 {
+                let x = k as f32 / 10.0;
+                let y = f(x);
+                assert!(y.abs() <= 1.0 && y >= prev, "curve {i} at {x}");
+                assert_eq!(f(-x), -y, "curve {i} at {x}");
+                prev = y;
+            }
+        }
+        assert_eq!(cubic_clip(1.5), 1.0);
+        assert_eq!(knee_clip(0.4, 0.5), 0.4);
+    }
+}
@@ -0,0 +1,276 @@
+//! Hot inner loops with wasm simd128 implementations: gain, mixing and a four-lane biquad
+//! bank. The simd128 paths are compiled in with the `simd` feature on a build targeting
+//! `+simd128`; anywhere else (the worklet's fallback build, native tests) the same functions
+//! run the scalar loops, so callers never branch. Browsers without simd128 can't even
+//! validate such a module, so the choice between the two builds is made by the loader.
+
+use crate::biquad::Biquad;
+use crate::precision::Real;
+
+/// Channels a `BiquadBank` runs side by side (one v128 of f32).
+pub const LANES: usize = 4;
+
+/// Whether this build runs the simd128 paths (all but the biquad bank in an `f64` build; see
+/// `precision`).
+pub const ENABLED: bool = cfg!(all(
+    feature = "simd",
+    target_arch = "wasm32",
+    target_feature = "simd128"
+));
+
+/// `buf *= gain`.
+pub fn scale(buf: &mut [f32], gain: f32) {
+    imp::scale(buf, gain);
+}
+
+/// `buf *= gains`, element-wise over the shorter of the two.
+pub fn multiply(buf: &mut [f32], gains: &[f32]) {
+    imp::multiply(buf, gains);
+}
+
+/// `dst += src * gain`, over the shorter of the two.
+pub fn mix_into(dst: &mut [f32], src: &[f32], gain: f32) {
+    imp::mix_into(dst, src, gain);
+}
+
+/// `LANES` independent transposed direct form II biquads stepped together, one channel (or
+/// band) per lane. Lanes take their coefficients from a `Biquad`; an unset lane passes
+/// silence.
+#[derive(Clone, Copy, Default)]
+#[repr(C, align(16))]
+pub struct BiquadBank {
+    b0: [Real; LANES],
+    b1: [Real; LANES],
+    b2: [Real; LANES],
+    a1: [Real; LANES],
+    a2: [Real; LANES],
+    z1: [Real; LANES],
+    z2: [Real; LANES],
+}
+
+impl BiquadBank {
+    /// Copies `filter`'s coefficients into `lane`; the lane's state is kept.
+    pub fn set_lane(&mut self, lane: usize, filter: &Biquad) {
+        let [b0, b1, b2, a1, a2] = filter.coeffs();
+        self.b0[lane] = b0;
+        self.b1[lane] = b1;
+        self.b2[lane] = b2;
+        self.a1[lane] = a1;
+        self.a2[lane] = a2;
+    }
+
+    /// `set_lane` for every lane.
+    pub fn set_all(&mut self, filter: &Biquad) {
+        for lane in 0..LANES {
+            self.set_lane(lane, filter);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.z1 = [0.0; LANES];
+        self.z2 = [0.0; LANES];
+    }
+
+    #[inline]
+    pub fn process(&mut self, x: [f32; LANES]) -> [f32; LANES] {
+        bank::biquad_bank(self, x)
+    }
+}
+
+// simd128 lanes are f32, so an `f64` build runs the scalar bank even with the feature on.
+#[cfg(not(all(
+    feature = "simd",
+    target_arch = "wasm32",
+    target_feature = "simd128",
+    not(feature = "f64")
+)))]
+mod bank {
+    use super::{BiquadBank, LANES};
+    use crate::precision::{flush, to_f32, Real};
+
+    #[inline]
+    pub fn biquad_bank(f: &mut BiquadBank, x: [f32; LANES]) -> [f32; LANES] {
+        let mut y = [0.0; LANES];
+        for i in 0..LANES {
+            let x = x[i] as Real;
+            let yi = f.b0[i] * x + f.z1[i];
+            f.z1[i] = flush(f.b1[i] * x - f.a1[i] * yi + f.z2[i]);
+            f.z2[i] = flush(f.b2[i] * x - f.a2[i] * yi);
+            y[i] = to_f32(yi);
+        }
+        y
+    }
+}
+
+#[cfg(all(
+    feature = "simd",
+    target_arch = "wasm32",
+    target_feature = "simd128",
+    not(feature = "f64")
+))]
+use imp as bank;
+
+#[cfg(not(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128")))]
+mod imp {
+    pub fn scale(buf: &mut [f32], gain: f32) {
+        for v in buf {
+            *v *= gain;
+        }
+    }
+
+    pub fn multiply(buf: &mut [f32], gains: &[f32]) {
+        for (v, g) in buf.iter_mut().zip(gains) {
+            *v *= g;
+        }
+    }
+
+    pub fn mix_into(dst: &mut [f32], src: &[f32], gain: f32) {
+        for (d, s) in dst.iter_mut().zip(src) {
+            *d += s * gain;
+        }
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+mod imp {
+    #[cfg(not(feature = "f64"))]
+    use super::BiquadBank;
+    use super::LANES;
+    #[cfg(not(feature = "f64"))]
+    use crate::denormal::FLUSH_THRESHOLD;
+    use core::arch::wasm32::*;
+
+    // wasm loads and stores don't need alignment, so slices go through v128 in place and the
+    // tail (fewer than `LANES` samples) falls back to scalar.
+
+    pub fn scale(buf: &mut [f32], gain: f32) {
+        let g = f32x4_splat(gain);
+        let mut chunks = buf.chunks_exact_mut(LANES);
+        for c in &mut chunks {
+            let p = c.as_mut_ptr() as *mut v128;
+            unsafe { v128_store(p, f32x4_mul(v128_load(p), g)) };
+        }
+        for v in chunks.into_remainder() {
+            *v *= gain;
+        }
+    }
+
+    pub fn multiply(buf: &mut [f32], gains: &[f32]) {
+        let n = buf.len().min(gains.len());
+        let (buf, gains) = (&mut buf[..n], &gains[..n]);
+        let mut chunks = buf.chunks_exact_mut(LANES);
+        let mut gain_chunks = gains.chunks_exact(LANES);
+        for (c, g) in (&mut chunks).zip(&mut gain_chunks) {
+            let p = c.as_mut_ptr() as *mut v128;
+            unsafe {
+                let g = v128_load(g.as_ptr() as *const v128);
+                v128_store(p, f32x4_mul(v128_load(p), g));
+            }
+        }
+        for (v, g) in chunks
+            .into_remainder()
+            .iter_mut()
+            .zip(gain_chunks.remainder())
+        {
+            *v *= g;
+        }
+    }
+
+    pub fn mix_into(dst: &mut [f32], src: &[f32], gain: f32) {
+        let n = dst.len().min(src.len());
+        let (dst, src) = (&mut dst[..n], &src[..n]);
+        let g = f32x4_splat(gain);
+        let mut chunks = dst.chunks_exact_mut(LANES);
+        let mut src_chunks = src.chunks_exact(LANES);
+        for (d, s) in (&mut chunks).zip(&mut src_chunks) {
+            let p = d.as_mut_ptr() as *mut v128;
+            unsafe {
+                let s = v128_load(s.as_ptr() as *const v128);
+                v128_store(p, f32x4_add(v128_load(p), f32x4_mul(s, g)));
+            }
+        }
+        for (d, s) in chunks
+            .into_remainder()
+            .iter_mut()
+            .zip(src_chunks.remainder())
+        {
+            *d += s * gain;
+        }
+    }
+
+    /// `denormal::flush` on each lane.
+    #[cfg(not(feature = "f64"))]
+    #[inline]
+    fn flush(v: v128) -> v128 {
+        v128_andnot(v, f32x4_lt(f32x4_abs(v), f32x4_splat(FLUSH_THRESHOLD)))
+    }
+
+    #[cfg(not(feature = "f64"))]
+    #[inline]
+    pub fn biquad_bank(f: &mut BiquadBank, x: [f32; LANES]) -> [f32; LANES] {
+        let load = |a: &[f32; LANES]| unsafe { v128_load(a.as_ptr() as *const v128) };
+        let x = load(&x);
+        let y = f32x4_add(f32x4_mul(load(&f.b0), x), load(&f.z1));
+        let z1 = f32x4_add(
+            f32x4_sub(f32x4_mul(load(&f.b1), x), f32x4_mul(load(&f.a1), y)),
+            load(&f.z2),
+        );
+        let z2 = f32x4_sub(f32x4_mul(load(&f.b2), x), f32x4_mul(load(&f.a2), y));
+        let (z1, z2) = (flush(z1), flush(z2));
+        let mut out = [0.0; LANES];
+        unsafe {
+            v128_store(f.z1.as_mut_ptr() as *mut v128, z1);
+            v128_store(f.z2.as_mut_ptr() as *mut v128, z2);
+            v128_store(out.as_mut_ptr() as *mut v128, y);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::biquad::Response;
+
+    #[test]
+    fn slice_kernels_cover_the_tail() {
+        let mut buf: Vec<f32> = (0..7).map(|i| i as f32).collect();
+        scale(&mut buf, 0.5);
+        assert_eq!(buf, [0.0, 0.5, 1.0, 1.5, 2.0, 2.5, 3.0]);
+        multiply(&mut buf, &[2.0; 6]);
+        assert_eq!(buf, [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 3.0]);
+        mix_into(&mut buf, &[1.0; 9], -1.0);
+        assert_eq!(buf, [-1.0, 0.0, 1.0, 2.0, 3.0, 4.0, 2.0]);
+    }
+
+    #[test]
+    fn bank_lanes_match_single_biquads() {
+        let mut filters = [Biquad::default(); LANES];
+        let responses = [
+            Response::Lowpass,
+            Response::Highpass,
+            Response::Peak,
+            Response::HighShelf,
+        ];
+        let mut bank = BiquadBank::default();
+        for (lane, (f, r)) in filters.iter_mut().zip(responses).enumerate() {
+            f.set_with_gain(r, 300.0 * (lane + 1) as f32, 0.9, 6.0, 48_000.0);
+            bank.set_lane(lane, f);
+        }
+        for n in 0..256 {
+            let x = [
+                (n as f32 * 0.3).sin(),
+                if n == 0 { 1.0 } else { 0.0 },
+                (n % 7) as f32 - 3.0,
+                -0.25,
+            ];
+            let y = bank.process(x);
+            for lane in 0..LANES {
+                let expected = filters[lane].process(x[lane]);
+                assert!((y[lane] - expected).abs() < 1e-6, "lane {lane} at {n}");
+            }
+        }
+        bank.clear();
+        assert_eq!(bank.process([0.0; LANES]), [0.0; LANES]);
+    }
+}
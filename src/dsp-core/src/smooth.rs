@@ -0,0 +1,200 @@
+//! Per-sample smoothing of control values, so parameter changes from the UI don't click.
+
+use crate::envelope::one_pole_coeff;
+use crate::state::{State, StateReader, StateWriter};
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+pub const PARAM_SMOOTHING_MS: f32 = 10.0;
+
+/// One-pole parameter smoother; snaps to the target once within `1e-6`, or once rounding
+/// stops it getting any closer (near 1.0 a 10 ms step stalls around 1e-5 short).
+#[derive(Clone, Copy)]
+pub struct Smoothed {
+    current: f32,
+    target: f32,
+    coeff: f32,
+}
+
+impl Smoothed {
+    /// Smooths over `PARAM_SMOOTHING_MS`.
+    pub fn new(value: f32, sample_rate_hz: f32) -> Self {
+        Self::with_time(value, PARAM_SMOOTHING_MS, sample_rate_hz)
+    }
+
+    pub fn with_time(value: f32, ms: f32, sample_rate_hz: f32) -> Self {
+        let mut s = Self {
+            current: value,
+            target: value,
+            coeff: 0.0,
+        };
+        s.set_time(ms, sample_rate_hz);
+        s
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate_hz: f32) {
+        self.set_time(PARAM_SMOOTHING_MS, sample_rate_hz);
+    }
+
+    pub fn set_time(&mut self, ms: f32, sample_rate_hz: f32) {
+        self.coeff = one_pole_coeff(ms, sample_rate_hz);
+    }
+
+    pub fn set_target(&mut self, value: f32) {
+        self.target = value;
+    }
+
+    /// Jumps straight to `value`, for resets.
+    pub fn set_immediate(&mut self, value: f32) {
+        self.current = value;
+        self.target = value;
+    }
+
+    #[inline]
+    pub fn value(&self) -> f32 {
+        self.current
+    }
+
+    #[inline]
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    #[inline]
+    pub fn is_settled(&self) -> bool {
+        self.current == self.target
+    }
+
+    #[inline]
+    pub fn next_value(&mut self) -> f32 {
+        if self.current != self.target {
+            let next = self.target + (self.current - self.target) * self.coeff;
+            let stalled = next == self.current;
+            self.current = next;
+            if stalled || (self.current - self.target).abs() < 1e-6 {
+                self.current = self.target;
+            }
+        }
+        self.current
+    }
+}
+
+pub const CROSSFADE_MS: f32 = 20.0;
+
+/// Linear ramp between two paths, read out as equal-power (cos/sin) gains.
+#[derive(Clone, Copy)]
+pub struct Crossfade {
+    pos: f32,
+    target: f32,
+    step: f32,
+}
+
+impl Crossfade {
+    pub fn new(sample_rate_hz: f32) -> Self {
+        let mut c = Self {
+            pos: 0.0,
+            target: 0.0,
+            step: 1.0,
+        };
+        c.set_sample_rate(sample_rate_hz);
+        c
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate_hz: f32) {
+        self.step = 1.0 / (CROSSFADE_MS / 1000.0 * sample_rate_hz).max(1.0);
+    }
+
+    /// `true` fades towards the second path.
+    pub fn set_target(&mut self, second: bool) {
+        self.target = if second { 1.0 } else { 0.0 };
+    }
+
+    /// Jumps straight to the target, for resets.
+    pub fn settle(&mut self) {
+        self.pos = self.target;
+    }
+
+    #[inline]
+    pub fn is_settled(&self) -> bool {
+        self.pos == self.target
+    }
+
+    /// Fully on the second path and not moving.
+    #[inline]
+    pub fn is_second(&self) -> bool {
+        self.pos == 1.0 && self.is_settled()
+    }
+
+    /// Advances one sample and returns `(first, second)` gains.
+    #[inline]
+    pub fn next_gains(&mut self) -> (f32, f32) {
+        if self.pos < self.target {
+            self.pos = (self.pos + self.step).min(self.target);
+        } else if self.pos > self.target {
+            self.pos = (self.pos - self.step).max(self.target);
+        }
+        if self.pos == 0.0 {
+            return (1.0, 0.0);
+        }
+        if self.pos == 1.0 {
+            return (0.0, 1.0);
+        }
+        let x = self.pos * core::f32::consts::FRAC_PI_2;
+        (x.cos(), x.sin())
+    }
+}
+
+impl State for Smoothed {
+    fn save(&self, w: &mut StateWriter) {
+        w.f32(self.current);
+        w.f32(self.target);
+    }
+
+    fn load(&mut self, r: &mut StateReader) {
+        self.current = r.f32();
+        self.target = r.f32();
+    }
+}
+
+impl State for Crossfade {
+    fn save(&self, w: &mut StateWriter) {
+        w.f32(self.pos);
+        w.f32(self.target);
+    }
+
+    fn load(&mut self, r: &mut StateReader) {
+        self.pos = r.f32();
+        self.target = r.f32();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smoother_reaches_63_percent_in_its_time_then_settles() {
+        let mut s = Smoothed::new(0.0, 48_000.0);
+        s.set_target(1.0);
+        for _ in 0..480 {
+            s.next_value();
+        }
+        assert!((s.value() - 0.632).abs() < 0.01, "{}", s.value());
+        for _ in 0..48_000 {
+            s.next_value();
+        }
+        assert!(s.is_settled() && s.value() == 1.0);
+    }
+
+    #[test]
+    fn crossfade_keeps_power_and_lands() {
+        let mut c = Crossfade::new(48_000.0);
+        c.set_target(true);
+        for _ in 0..1000 {
+            let (a, b) = c.next_gains();
+            assert!((a * a + b * b - 1.0).abs() < 1e-5);
+        }
+        assert!(c.is_second());
+    }
+}
@@ -0,0 +1,288 @@
+//! Snapshotting the running state of DSP blocks (filter memories, delay-line contents,
+//! envelopes, smoother positions) so a node can be restored mid-stream. Only what evolves
+//! while processing is written: coefficients, windows and delays follow from the parameters,
+//! which the node restores first. Values are little-endian; slices carry their length, and a
+//! reader refuses one whose length doesn't match the block it is loading into.
+
+use crate::precision::Real;
+
+/// Appends state to a byte buffer, or only measures it (`sizing`).
+pub struct StateWriter<'a> {
+    buf: Option<&'a mut [u8]>,
+    len: usize,
+    overflowed: bool,
+}
+
+impl<'a> StateWriter<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf: Some(buf),
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Counts bytes without storing them.
+    pub fn sizing() -> Self {
+        Self {
+            buf: None,
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Bytes written (or that would have been).
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the buffer was too small for what was written.
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        if let Some(buf) = self.buf.as_deref_mut() {
+            match buf.get_mut(self.len..self.len + bytes.len()) {
+                Some(dst) => dst.copy_from_slice(bytes),
+                None => self.overflowed = true,
+            }
+        }
+        self.len += bytes.len();
+    }
+
+    pub fn u32(&mut self, v: u32) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, v: u64) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    pub fn f32(&mut self, v: f32) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    pub fn f64(&mut self, v: f64) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    /// Filter state at `Real` precision (so an `f64` build's snapshots only load into
+    /// another `f64` build).
+    pub fn real(&mut self, v: Real) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    /// A position or count; stored as u32.
+    pub fn index(&mut self, v: usize) {
+        self.u32(v as u32);
+    }
+
+    pub fn f32s(&mut self, vs: &[f32]) {
+        self.index(vs.len());
+        for &v in vs {
+            self.f32(v);
+        }
+    }
+
+    pub fn u64s(&mut self, vs: &[u64]) {
+        self.index(vs.len());
+        for &v in vs {
+            self.u64(v);
+        }
+    }
+}
+
+/// Reads back what a `StateWriter` wrote. Past the end, or after a mismatched slice, it has
+/// failed: every read from then on returns 0 and leaves its target alone.
+pub struct StateReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    failed: bool,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf,
+            pos: 0,
+            failed: false,
+        }
+    }
+
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Bytes not read yet.
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    pub fn bytes<const N: usize>(&mut self) -> [u8; N] {
+        let mut out = [0; N];
+        match self.buf.get(self.pos..self.pos + N) {
+            Some(src) if !self.failed => {
+                out.copy_from_slice(src);
+                self.pos += N;
+            }
+            _ => self.failed = true,
+        }
+        out
+    }
+
+    /// The next `len` bytes, or `None` (and failed) if there aren't that many.
+    pub fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let src = self
+            .buf
+            .get(self.pos..self.pos + len)
+            .filter(|_| !self.failed);
+        match src {
+            Some(_) => self.pos += len,
+            None => self.failed = true,
+        }
+        src
+    }
+
+    pub fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.bytes())
+    }
+
+    pub fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.bytes())
+    }
+
+    pub fn f32(&mut self) -> f32 {
+        f32::from_le_bytes(self.bytes())
+    }
+
+    pub fn f64(&mut self) -> f64 {
+        f64::from_le_bytes(self.bytes())
+    }
+
+    pub fn real(&mut self) -> Real {
+        Real::from_le_bytes(self.bytes())
+    }
+
+    /// A position or count below `limit`; failed (and 0) otherwise.
+    pub fn index(&mut self, limit: usize) -> usize {
+        let v = self.u32() as usize;
+        if v < limit {
+            v
+        } else {
+            self.failed = true;
+            0
+        }
+    }
+
+    fn len(&mut self, expected: usize) -> bool {
+        let len = self.u32() as usize;
+        if len != expected || self.remaining() < len {
+            self.failed = true;
+        }
+        !self.failed
+    }
+
+    /// Fills `out`, if the stored slice is exactly as long.
+    pub fn f32s(&mut self, out: &mut [f32]) {
+        if self.len(out.len()) {
+            for v in out {
+                *v = self.f32();
+            }
+        }
+    }
+
+    pub fn u64s(&mut self, out: &mut [u64]) {
+        if self.len(out.len()) {
+            for v in out {
+                *v = self.u64();
+            }
+        }
+    }
+}
+
+/// A block whose running state can be saved and restored. `load` expects the block to have
+/// the same parameters and capacity as the one that was saved; loading anything else leaves
+/// the reader failed.
+pub trait State {
+    fn save(&self, w: &mut StateWriter);
+    fn load(&mut self, r: &mut StateReader);
+}
+
+impl<T: State> State for [T] {
+    fn save(&self, w: &mut StateWriter) {
+        w.index(self.len());
+        for t in self {
+            t.save(w);
+        }
+    }
+
+    fn load(&mut self, r: &mut StateReader) {
+        if r.len(self.len()) {
+            for t in self {
+                t.load(r);
+            }
+        }
+    }
+}
+
+impl<T: State, const N: usize> State for [T; N] {
+    fn save(&self, w: &mut StateWriter) {
+        self.as_slice().save(w);
+    }
+
+    fn load(&mut self, r: &mut StateReader) {
+        self.as_mut_slice().load(r);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::biquad::{Biquad, Response};
+
+    #[test]
+    fn round_trips_and_refuses_mismatches() {
+        let mut a = [Biquad::default(); 2];
+        for f in &mut a {
+            f.set(Response::Lowpass, 500.0, 0.7, 48_000.0);
+        }
+        for n in 0..50 {
+            a[0].process(n as f32);
+            a[1].process(-(n as f32));
+        }
+        let mut w = StateWriter::sizing();
+        a.save(&mut w);
+        let mut buf = vec![0; w.len()];
+        let mut w = StateWriter::new(&mut buf);
+        a.save(&mut w);
+        assert!(!w.overflowed());
+
+        let mut b = a;
+        for f in &mut b {
+            f.clear();
+        }
+        let mut r = StateReader::new(&buf);
+        b.load(&mut r);
+        assert!(!r.failed() && r.remaining() == 0);
+        assert_eq!(a[0].process(1.0), b[0].process(1.0));
+        assert_eq!(a[1].process(1.0), b[1].process(1.0));
+
+        let mut three = [Biquad::default(); 3];
+        let mut r = StateReader::new(&buf);
+        three.load(&mut r);
+        assert!(r.failed());
+        let mut r = StateReader::new(&buf[..buf.len() - 1]);
+        b.load(&mut r);
+        assert!(r.failed());
+
+        let mut small = [0; 4];
+        let mut w = StateWriter::new(&mut small);
+        a.save(&mut w);
+        assert!(w.overflowed());
+    }
+}
@@ -0,0 +1,133 @@
+use alloc::{vec, vec::Vec};
+
+use crate::fft::{Complex, Fft};
+use crate::window::Window;
+
+/// Streaming short-time Fourier transform with overlap-add resynthesis (one channel). Every
+/// `hop` samples the last `len()` inputs are Hann-windowed and transformed, the caller edits
+/// the non-negative-frequency bins, and the frame is windowed again and overlap-added. An
+/// untouched spectrum passes the input through delayed by exactly `len()` samples.
+pub struct Stft {
+    fft: Fft,
+    window: Vec<f32>,
+    hop: usize,
+    scale: f32,
+    input: Vec<f32>,
+    ola: Vec<f32>,
+    spectrum: Vec<Complex>,
+    fill: usize,
+}
+
+impl Stft {
+    /// `n` is rounded up to a power of two; `overlap` (at least 2, 4 is typical) frames cover
+    /// every sample.
+    pub fn new(n: usize, overlap: usize) -> Self {
+        let fft = Fft::new(n);
+        let n = fft.len();
+        let hop = n / overlap.clamp(2, n);
+        let mut window = vec![0.0; n];
+        Window::Hann.fill(&mut window);
+        let scale = hop as f32 / window.iter().map(|w| w * w).sum::<f32>();
+        Self {
+            fft,
+            window,
+            hop,
+            scale,
+            input: vec![0.0; n],
+            ola: vec![0.0; n],
+            spectrum: vec![Complex::default(); n],
+            fill: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.fft.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    /// Bins handed to the callback: DC up to and including Nyquist.
+    pub fn bins(&self) -> usize {
+        self.len() / 2 + 1
+    }
+
+    pub fn latency(&self) -> usize {
+        self.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.input.fill(0.0);
+        self.ola.fill(0.0);
+        self.fill = 0;
+    }
+
+    /// Takes one input sample and returns one output sample. `edit` runs whenever a frame
+    /// completes, with bins `0..bins()` of its spectrum; the negative frequencies are rebuilt
+    /// from them.
+    #[inline]
+    pub fn process(&mut self, x: f32, edit: impl FnOnce(&mut [Complex])) -> f32 {
+        let n = self.len();
+        let hop = self.hop;
+        self.input[n - hop + self.fill] = x;
+        let y = self.ola[self.fill];
+        self.fill += 1;
+        if self.fill == hop {
+            self.fill = 0;
+            self.frame(edit);
+        }
+        y
+    }
+
+    fn frame(&mut self, edit: impl FnOnce(&mut [Complex])) {
+        let n = self.len();
+        let hop = self.hop;
+        for ((c, x), w) in self.spectrum.iter_mut().zip(&self.input).zip(&self.window) {
+            *c = Complex::new(x * w, 0.0);
+        }
+        self.input.copy_within(hop.., 0);
+        self.fft.forward(&mut self.spectrum);
+        edit(&mut self.spectrum[..n / 2 + 1]);
+        for k in n / 2 + 1..n {
+            let c = self.spectrum[n - k];
+            self.spectrum[k] = Complex::new(c.re, -c.im);
+        }
+        self.spectrum[0].im = 0.0;
+        self.spectrum[n / 2].im = 0.0;
+        self.fft.inverse(&mut self.spectrum);
+
+        self.ola.copy_within(hop.., 0);
+        self.ola[n - hop..].fill(0.0);
+        for ((o, c), w) in self.ola.iter_mut().zip(&self.spectrum).zip(&self.window) {
+            *o += c.re * w * self.scale;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_is_a_pure_delay() {
+        let mut stft = Stft::new(256, 4);
+        let x: Vec<f32> = (0..4096)
+            .map(|i| (i as f32 * 0.037).sin() + 0.3 * (i as f32 * 0.31).cos())
+            .collect();
+        let y: Vec<f32> = x.iter().map(|&v| stft.process(v, |_| {})).collect();
+        let d = stft.latency();
+        for i in 2 * d..x.len() {
+            assert!(
+                (y[i] - x[i - d]).abs() < 1e-4,
+                "{i}: {} vs {}",
+                y[i],
+                x[i - d]
+            );
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::precision::{flush, to_f32, Real, PI};
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+/// Integrator states are clamped here so a self-oscillating filter hit hard can't run away.
+const STATE_LIMIT: Real = 8.0;
+
+/// Zero-delay-feedback (topology-preserving transform) state-variable filter, after Simper.
+/// The integrators run at `Real` precision.
+#[derive(Clone, Copy, Default)]
+pub struct SvfState {
+    ic1: Real,
+    ic2: Real,
+}
+
+#[derive(Clone, Copy)]
+pub struct Coeffs {
+    k: Real,
+    a1: Real,
+    a2: Real,
+    a3: Real,
+}
+
+impl Coeffs {
+    /// `damping` is `1/Q`: 2 has no peak, values near 0 ring.
+    pub fn new(cutoff_hz: f32, damping: f32, sample_rate_hz: f32) -> Self {
+        let g = (PI * (cutoff_hz as Real / sample_rate_hz as Real).clamp(1e-5, 0.49)).tan();
+        let a1 = 1.0 / (1.0 + g * (g + damping as Real));
+        let a2 = g * a1;
+        Self {
+            k: damping as Real,
+            a1,
+            a2,
+            a3: g * a2,
+        }
+    }
+}
+
+impl SvfState {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns `(low, band, high)`.
+    #[inline]
+    pub fn process(&mut self, v0: f32, c: &Coeffs) -> (f32, f32, f32) {
+        let v0 = v0 as Real;
+        let v3 = v0 - self.ic2;
+        let v1 = c.a1 * self.ic1 + c.a2 * v3;
+        let v2 = self.ic2 + c.a2 * self.ic1 + c.a3 * v3;
+        self.ic1 = flush((2.0 * v1 - self.ic1).clamp(-STATE_LIMIT, STATE_LIMIT));
+        self.ic2 = flush((2.0 * v2 - self.ic2).clamp(-STATE_LIMIT, STATE_LIMIT));
+        (to_f32(v2), to_f32(v1), to_f32(v0 - c.k * v1 - v2))
+    }
+}
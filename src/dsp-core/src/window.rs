@@ -0,0 +1,89 @@
+//! Analysis windows, in the periodic form (the one that overlap-adds and reads spectra
+//! cleanly).
+
+use core::f32::consts::PI;
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Window {
+    Rectangular,
+    Hann,
+    Hamming,
+    Blackman,
+    /// Four-term Blackman-Harris: sidelobes under -92 dB.
+    BlackmanHarris,
+    /// Wide main lobe but under 0.01 dB scalloping, for reading tone levels.
+    FlatTop,
+}
+
+impl Window {
+    /// Index as the worklets pass it, in declaration order; out of range is Hann.
+    pub fn from_index(index: u32) -> Self {
+        match index {
+            0 => Self::Rectangular,
+            2 => Self::Hamming,
+            3 => Self::Blackman,
+            4 => Self::BlackmanHarris,
+            5 => Self::FlatTop,
+            _ => Self::Hann,
+        }
+    }
+
+    /// Cosine-sum coefficients a0, a1, ... with w(i) = a0 - a1 cos(x) + a2 cos(2x) - ...
+    fn coeffs(self) -> &'static [f32] {
+        match self {
+            Self::Rectangular => &[1.0],
+            Self::Hann => &[0.5, 0.5],
+            Self::Hamming => &[0.54, 0.46],
+            Self::Blackman => &[0.42, 0.5, 0.08],
+            Self::BlackmanHarris => &[0.358_75, 0.488_29, 0.141_28, 0.011_68],
+            Self::FlatTop => &[
+                0.215_578_95,
+                0.416_631_58,
+                0.277_263_16,
+                0.083_578_95,
+                0.006_947_368,
+            ],
+        }
+    }
+
+    /// Mean of the window, which is what a bin-centred sine's magnitude is scaled by.
+    pub fn coherent_gain(self) -> f32 {
+        self.coeffs()[0]
+    }
+
+    /// Writes the window over all of `out`.
+    pub fn fill(self, out: &mut [f32]) {
+        let n = out.len() as f32;
+        let coeffs = self.coeffs();
+        for (i, w) in out.iter_mut().enumerate() {
+            let x = 2.0 * PI * i as f32 / n;
+            let mut sign = 1.0;
+            *w = 0.0;
+            for (k, a) in coeffs.iter().enumerate() {
+                *w += sign * a * (k as f32 * x).cos();
+                sign = -sign;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hann_shape_and_coherent_gain() {
+        let mut w = vec![0.0; 64];
+        Window::Hann.fill(&mut w);
+        assert!(w[0].abs() < 1e-6 && (w[32] - 1.0).abs() < 1e-6);
+        for index in 0..6 {
+            let window = Window::from_index(index);
+            window.fill(&mut w);
+            let mean = w.iter().sum::<f32>() / w.len() as f32;
+            assert!((mean - window.coherent_gain()).abs() < 1e-5, "{window:?}");
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use alloc::{vec, vec::Vec};
+
+use crate::fft::{Complex, Fft};
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+/// YIN fundamental-frequency estimator (de Cheveigné & Kawahara). The difference function is
+/// built from an FFT cross-correlation plus running energies, so a frame costs two forward
+/// transforms and one inverse instead of `window * max_lag` multiplies.
+pub struct Yin {
+    window: usize,
+    min_lag: usize,
+    max_lag: usize,
+    threshold: f32,
+    sample_rate_hz: f32,
+    fft: Fft,
+    a: Vec<Complex>,
+    b: Vec<Complex>,
+    diff: Vec<f32>,
+}
+
+/// A detected pitch and how periodic the frame was (1 - the normalised difference at the
+/// chosen lag; around 0.9 and up for clean voiced material).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pitch {
+    pub hz: f32,
+    pub confidence: f32,
+}
+
+impl Yin {
+    /// Covers `min_hz`..`max_hz`; the integration window is one period of `min_hz`.
+    pub fn new(min_hz: f32, max_hz: f32, sample_rate_hz: f32) -> Self {
+        let max_lag = (sample_rate_hz / min_hz).ceil() as usize + 1;
+        let min_lag = ((sample_rate_hz / max_hz).floor() as usize).max(2);
+        let window = max_lag;
+        let fft = Fft::new(window + max_lag + 1);
+        let n = fft.len();
+        Self {
+            window,
+            min_lag,
+            max_lag,
+            threshold: 0.15,
+            sample_rate_hz,
+            fft,
+            a: vec![Complex::default(); n],
+            b: vec![Complex::default(); n],
+            diff: vec![0.0; max_lag + 1],
+        }
+    }
+
+    /// Samples `detect` looks at.
+    pub fn frame_len(&self) -> usize {
+        self.window + self.max_lag + 1
+    }
+
+    /// Cumulative-mean-normalised difference below which the first dip is taken as the period.
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    /// Estimates the fundamental of the last `frame_len()` samples of `frame` (oldest first).
+    /// `None` for silence or when no lag in range looks periodic at all.
+    pub fn detect(&mut self, frame: &[f32]) -> Option<Pitch> {
+        let len = self.frame_len();
+        let x = &frame[frame.len().saturating_sub(len)..];
+        if x.len() < len {
+            return None;
+        }
+        let w = self.window;
+
+        for (i, (a, b)) in self.a.iter_mut().zip(self.b.iter_mut()).enumerate() {
+            let v = x.get(i).copied().unwrap_or(0.0);
+            *a = Complex::new(if i < w { v } else { 0.0 }, 0.0);
+            *b = Complex::new(v, 0.0);
+        }
+        self.fft.forward(&mut self.a);
+        self.fft.forward(&mut self.b);
+        for (a, b) in self.a.iter_mut().zip(&self.b) {
+            *a = Complex::new(a.re, -a.im) * *b;
+        }
+        self.fft.inverse(&mut self.a);
+
+        let e0: f32 = x[..w].iter().map(|v| v * v).sum();
+        if e0 < 1e-8 * w as f32 {
+            return None;
+        }
+        let mut et = e0;
+        self.diff[0] = 0.0;
+        let mut running = 0.0;
+        for tau in 1..=self.max_lag {
+            et += x[tau + w - 1] * x[tau + w - 1] - x[tau - 1] * x[tau - 1];
+            let d = (e0 + et - 2.0 * self.a[tau].re).max(0.0);
+            running += d;
+            self.diff[tau] = if running > 0.0 {
+                d * tau as f32 / running
+            } else {
+                1.0
+            };
+        }
+
+        let mut best =
+            (self.min_lag..self.max_lag).min_by(|&p, &q| self.diff[p].total_cmp(&self.diff[q]))?;
+        if let Some(mut tau) = (self.min_lag..self.max_lag).find(|&t| self.diff[t] < self.threshold)
+        {
+            while tau + 1 < self.max_lag && self.diff[tau + 1] < self.diff[tau] {
+                tau += 1;
+            }
+            best = tau;
+        }
+
+        let (l, c, r) = (self.diff[best - 1], self.diff[best], self.diff[best + 1]);
+        let den = l - 2.0 * c + r;
+        let shift = if den > 1e-9 {
+            (0.5 * (l - r) / den).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+        Some(Pitch {
+            hz: self.sample_rate_hz / (best as f32 + shift),
+            confidence: (1.0 - c).clamp(0.0, 1.0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+
+    #[test]
+    fn finds_harmonic_tone_fundamental() {
+        let sr = 48000.0;
+        let mut yin = Yin::new(60.0, 1000.0, sr);
+        for &f in &[82.4, 220.0, 440.0, 903.0] {
+            let frame: Vec<f32> = (0..yin.frame_len())
+                .map(|i| {
+                    let t = 2.0 * PI * f * i as f32 / sr;
+                    t.sin() + 0.6 * (2.0 * t).sin() + 0.3 * (3.0 * t + 1.0).sin()
+                })
+                .collect();
+            let p = yin.detect(&frame).unwrap();
+            assert!((p.hz / f - 1.0).abs() < 0.005, "{f} Hz: {}", p.hz);
+            assert!(p.confidence > 0.9);
+        }
+        assert!(yin.detect(&vec![0.0; yin.frame_len()]).is_none());
+    }
+}
@@ -0,0 +1,210 @@
+//! wasm-bindgen wrappers over a `Node`, for callers on the main thread (offline rendering,
+//! tests, UI previews) that would rather hold a typed object than walk raw pointers. The
+//! wrapper drives the same `Node` impl as the extern-C exports, so a crate built with the
+//! `bindgen` feature serves both the AudioWorklet loader and wasm-bindgen's generated JS.
+
+use crate::ParamDescriptor;
+use wasm_bindgen::prelude::*;
+
+/// A `ParamDescriptor` as a JS object.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ParamInfo {
+    descriptor: ParamDescriptor,
+}
+
+#[wasm_bindgen]
+impl ParamInfo {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.descriptor.name().into()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn unit(&self) -> String {
+        self.descriptor.unit().into()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn min(&self) -> f32 {
+        self.descriptor.min
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn max(&self) -> f32 {
+        self.descriptor.max
+    }
+
+    #[wasm_bindgen(getter, js_name = defaultValue)]
+    pub fn default_value(&self) -> f32 {
+        self.descriptor.default
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn scale(&self) -> u32 {
+        self.descriptor.scale
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn flags(&self) -> u32 {
+        self.descriptor.flags
+    }
+}
+
+impl From<&ParamDescriptor> for ParamInfo {
+    fn from(descriptor: &ParamDescriptor) -> Self {
+        Self {
+            descriptor: *descriptor,
+        }
+    }
+}
+
+/// A parameter table as `ParamInfo`s, in index order.
+pub fn param_infos(table: &[ParamDescriptor]) -> Vec<ParamInfo> {
+    table.iter().map(ParamInfo::from).collect()
+}
+
+/// Generates a wasm-bindgen class wrapping a `Node` type; JS sees `new Name(sampleRate)`,
+/// `Name.params()`, `setParam`, `getParam`, `queueParam`, `queueNote`, `latencySamples`,
+/// `meters`, `reset`, `savePreset`, `loadPreset`, and `process` (or `processAux`, with the
+/// auxiliary bus) taking `Float32Array`s (interleaved, written back in place) or `render`
+/// returning a new one. The invoking crate needs `wasm-bindgen` as a direct (optional)
+/// dependency, as the attribute's expansion names it, and invokes this behind its own
+/// `bindgen` feature:
+///
+/// ```text
+/// [features]
+/// bindgen = ["dep:wasm-bindgen", "node-abi/bindgen"]
+///
+/// #[cfg(feature = "bindgen")]
+/// node_abi::bindgen_node!(Limiter as LimiterNode);
+/// ```
+#[macro_export]
+macro_rules! bindgen_node {
+    ($node:ty as $name:ident) => {
+        #[::wasm_bindgen::prelude::wasm_bindgen]
+        pub struct $name {
+            node: Box<$node>,
+        }
+
+        #[::wasm_bindgen::prelude::wasm_bindgen]
+        impl $name {
+            #[wasm_bindgen(constructor)]
+            pub fn new(sample_rate_hz: f32) -> $name {
+                $name {
+                    node: Box::new(<$node as $crate::Node>::new(sample_rate_hz)),
+                }
+            }
+
+            #[wasm_bindgen(js_name = abiVersion)]
+            pub fn abi_version() -> u32 {
+                $crate::ABI_VERSION
+            }
+
+            /// The parameter table, in index order.
+            pub fn params() -> Vec<$crate::bindgen::ParamInfo> {
+                $crate::bindgen::param_infos(<$node as $crate::Node>::PARAMS)
+            }
+
+            #[wasm_bindgen(js_name = setParam)]
+            pub fn set_param(&mut self, index: u32, value: f32) {
+                $crate::Node::set_param(&mut *self.node, index, value);
+            }
+
+            #[wasm_bindgen(js_name = getParam)]
+            pub fn get_param(&self, index: u32) -> f32 {
+                $crate::Node::get_param(&*self.node, index)
+            }
+
+            /// Queues a change for `frame` of the next `process` call; false when the ring
+            /// is full.
+            #[wasm_bindgen(js_name = queueParam)]
+            pub fn queue_param(&mut self, index: u32, value: f32, frame: u32) -> bool {
+                $crate::Node::param_events(&mut *self.node).push($crate::ParamEvent {
+                    index,
+                    value,
+                    frame,
+                })
+            }
+
+            #[wasm_bindgen(getter, js_name = latencySamples)]
+            pub fn latency_samples(&self) -> u32 {
+                $crate::Node::latency_samples(&*self.node)
+            }
+
+            /// Clears the running state; parameters and queued changes are kept.
+            pub fn reset(&mut self) {
+                $crate::Node::reset(&mut *self.node);
+            }
+
+            /// The parameter values as a preset blob (`dsp_core::preset`).
+            #[wasm_bindgen(js_name = savePreset)]
+            pub fn save_preset(&self) -> Vec<u8> {
+                $crate::save_preset(&*self.node).encode()
+            }
+
+            /// Applies a preset blob, migrating it first; false if it isn't one or comes
+            /// from a newer version of the node.
+            #[wasm_bindgen(js_name = loadPreset)]
+            pub fn load_preset(&mut self, bytes: &[u8]) -> bool {
+                $crate::Preset::decode(bytes)
+                    .is_some_and(|preset| $crate::load_preset(&mut *self.node, preset))
+            }
+
+            /// Processes as many whole frames as both arrays hold.
+            pub fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+                let channels = channels.clamp(1, <$node as $crate::Node>::MAX_CHANNELS);
+                $crate::process_with_events(&mut *self.node, input, output, channels);
+            }
+
+            /// `process` into a freshly allocated array the length of `input`.
+            pub fn render(&mut self, input: &[f32], channels: usize) -> Vec<f32> {
+                let mut output = vec![0.0; input.len()];
+                self.process(input, &mut output, channels);
+                output
+            }
+
+            /// `process` with the auxiliary bus (a side-chain key, a carrier) alongside,
+            /// `aux_channels` interleaved; a node without one ignores `aux`.
+            #[wasm_bindgen(js_name = processAux)]
+            pub fn process_aux(
+                &mut self,
+                input: &[f32],
+                aux: &[f32],
+                aux_channels: usize,
+                output: &mut [f32],
+                channels: usize,
+            ) {
+                let block = $crate::IoFrames {
+                    input,
+                    aux,
+                    output,
+                    channels: channels.clamp(1, <$node as $crate::Node>::MAX_CHANNELS),
+                    aux_channels: aux_channels.min(<$node as $crate::Node>::AUX_CHANNELS),
+                };
+                $crate::process_block(&mut *self.node, block, false);
+            }
+
+            /// Queues a note event (`dsp_core::events`) for `frame` of the next `process`
+            /// call; false when the node plays no notes or the ring is full.
+            #[wasm_bindgen(js_name = queueNote)]
+            pub fn queue_note(&mut self, kind: u32, note: u32, value: f32, frame: u32) -> bool {
+                $crate::Node::note_events(&mut *self.node).is_some_and(|ring| {
+                    ring.push($crate::NoteEvent {
+                        kind,
+                        note,
+                        value,
+                        frame,
+                    })
+                })
+            }
+
+            /// The current meter readings, as the I/O block's meters region holds them.
+            pub fn meters(&self) -> Vec<f32> {
+                let mut out = vec![0.0; <$node as $crate::Node>::METER_COUNT];
+                $crate::Node::meters(&*self.node, &mut out);
+                out
+            }
+        }
+    };
+}
@@ -0,0 +1,249 @@
+//! Keeping a panic inside a node from taking the worklet down with it. Every export runs the
+//! node through `guard`: a panic is caught, recorded as the node's `LastError`, and the export
+//! falls back (silence, 0, null); the node stays failed from then on, since a panic may have
+//! left it half-updated.
+//!
+//! wasm32-unknown-unknown can't unwind yet, so there a panic still traps. The panic hook
+//! records it first, though, so once the loader has caught the trap it can read the error
+//! through the same exports and mute the node. A `no_std` build has no hook (the panic
+//! handler is `dsp_core`'s bare trap): `guard` leaves the node's address behind while it
+//! runs, and a guard that never returned is taken as a panic, recorded without a location.
+//! Either record is keyed by the node's address, so freeing a node `forget`s it: the allocator
+//! may hand the address to the next node.
+
+use core::cell::Cell;
+#[cfg(feature = "std")]
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "std")]
+use std::sync::Once;
+
+use crate::registry::{lookup, Handle, Instance, Registered};
+use crate::Node;
+
+pub const ERROR_NONE: u32 = 0;
+/// The node panicked; the message says where.
+pub const ERROR_PANIC: u32 = 1;
+
+const MESSAGE_BYTES: usize = 128;
+
+/// A node's last failure: a code and a NUL-terminated UTF-8 message, in the node's memory so
+/// the loader can read it in place.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct LastError {
+    code: u32,
+    message: [u8; MESSAGE_BYTES],
+}
+
+impl Default for LastError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LastError {
+    pub const fn new() -> Self {
+        Self {
+            code: ERROR_NONE,
+            message: [0; MESSAGE_BYTES],
+        }
+    }
+
+    pub fn code(&self) -> u32 {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        crate::text(&self.message)
+    }
+
+    pub fn message_ptr(&self) -> *const u8 {
+        self.message.as_ptr()
+    }
+
+    /// Records `code` with `message`, cut at a character boundary to fit.
+    pub fn set(&mut self, code: u32, message: &str) {
+        let mut len = message.len().min(MESSAGE_BYTES - 1);
+        while !message.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.code = code;
+        self.message = [0; MESSAGE_BYTES];
+        self.message[..len].copy_from_slice(&message.as_bytes()[..len]);
+    }
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    /// Address of the node inside `guard`, so the hook knows whose panic it is.
+    static ACTIVE: Cell<usize> = const { Cell::new(0) };
+    /// The hook's record of the last panic and the node it happened in; outlives a trap.
+    static PANICKED: Cell<(usize, LastError)> = const { Cell::new((0, LastError::new())) };
+}
+
+#[cfg(not(feature = "std"))]
+struct Active(Cell<usize>);
+
+// wasm32 without threads, as for `registry::SingleThread`.
+#[cfg(not(feature = "std"))]
+unsafe impl Sync for Active {}
+
+/// Address of the node inside `guard`; still set after a trap out of it.
+#[cfg(not(feature = "std"))]
+static ACTIVE: Active = Active(Cell::new(0));
+
+/// `f`, or `None` if it panicked. Without `std` a panic traps instead of returning here.
+pub fn catch<R>(f: impl FnOnce() -> R) -> Option<R> {
+    #[cfg(feature = "std")]
+    return panic::catch_unwind(AssertUnwindSafe(f)).ok();
+    #[cfg(not(feature = "std"))]
+    return Some(f());
+}
+
+#[cfg(feature = "std")]
+fn install_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let owner = ACTIVE.with(Cell::get);
+            if owner != 0 {
+                let payload = info.payload();
+                let what = payload
+                    .downcast_ref::<&str>()
+                    .copied()
+                    .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                    .unwrap_or("panic");
+                let message = match info.location() {
+                    Some(at) => format!("{what} at {}:{}", at.file(), at.line()),
+                    None => what.to_owned(),
+                };
+                let mut error = LastError::new();
+                error.set(ERROR_PANIC, &message);
+                PANICKED.with(|p| p.set((owner, error)));
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// The node's `LastError`, after taking over a panic the hook recorded for it (one `guard`
+/// never got to see, because it trapped).
+pub fn recorded_error<N: Node>(node: &mut N) -> &LastError {
+    let addr = node as *mut N as usize;
+    #[cfg(feature = "std")]
+    {
+        let (owner, error) = PANICKED.with(Cell::get);
+        if owner == addr {
+            PANICKED.with(|p| p.set((0, LastError::new())));
+            *node.last_error() = error;
+        }
+    }
+    #[cfg(not(feature = "std"))]
+    if ACTIVE.0.get() == addr {
+        ACTIVE.0.set(0);
+        node.last_error().set(ERROR_PANIC, "panic");
+    }
+    node.last_error()
+}
+
+/// Drops a panic recorded against `node` that no export has taken over yet; the free export
+/// calls it before dropping the node.
+pub fn forget<N: Node>(node: &N) {
+    let addr = node as *const N as usize;
+    #[cfg(feature = "std")]
+    PANICKED.with(|p| {
+        if p.get().0 == addr {
+            p.set((0, LastError::new()));
+        }
+    });
+    #[cfg(not(feature = "std"))]
+    if ACTIVE.0.get() == addr {
+        ACTIVE.0.set(0);
+    }
+}
+
+/// `recorded_error` for the node behind `handle`; `None` for a handle that names no node.
+pub fn handle_error<N: Registered, R>(
+    handle: Handle,
+    f: impl FnOnce(&LastError) -> R,
+) -> Option<R> {
+    let instance = lookup::<N>(handle)?;
+    Some(f(recorded_error(unsafe { &mut (*instance).node })))
+}
+
+/// Runs `f` on the node behind `handle`. `None` when the handle names no live node (null,
+/// freed, or garbage), the node has already failed, or `f` panics (which is recorded); the
+/// caller then falls back to its neutral result.
+pub fn guard<N: Registered, R>(handle: Handle, f: impl FnOnce(&mut N) -> R) -> Option<R> {
+    guard_instance(handle, |instance: &mut Instance<N>| f(&mut instance.node))
+}
+
+/// `guard` handing `f` the whole `Instance`, for exports that work through its I/O block.
+pub fn guard_instance<N: Registered, R>(
+    handle: Handle,
+    f: impl FnOnce(&mut Instance<N>) -> R,
+) -> Option<R> {
+    let instance = unsafe { &mut *lookup::<N>(handle)? };
+    if recorded_error(&mut instance.node).code() != ERROR_NONE {
+        return None;
+    }
+    let owner = &mut instance.node as *mut N as usize;
+    #[cfg(feature = "std")]
+    let result = {
+        install_hook();
+        let outer = ACTIVE.with(|a| a.replace(owner));
+        let result = catch(|| f(&mut *instance));
+        ACTIVE.with(|a| a.set(outer));
+        result
+    };
+    #[cfg(not(feature = "std"))]
+    let result = {
+        let outer = ACTIVE.0.replace(owner);
+        let result = f(&mut *instance);
+        ACTIVE.0.set(outer);
+        Some(result)
+    };
+    match result {
+        Some(r) => Some(r),
+        None => {
+            let node = &mut instance.node;
+            if recorded_error(node).code() == ERROR_NONE {
+                node.last_error().set(ERROR_PANIC, "panic");
+            }
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::lookup;
+    use crate::tests::{gain_free, gain_new, Gain};
+
+    /// What a trap out of `guard` leaves behind for the node behind `handle`.
+    fn trap(handle: Handle) -> usize {
+        let addr = unsafe { &(*lookup::<Gain>(handle).unwrap()).node } as *const Gain as usize;
+        let mut error = LastError::new();
+        error.set(ERROR_PANIC, "trapped");
+        PANICKED.with(|p| p.set((addr, error)));
+        addr
+    }
+
+    #[test]
+    fn freeing_a_trapped_node_forgets_its_panic() {
+        let (trapped, other) = (gain_new(48_000.0), gain_new(48_000.0));
+        trap(trapped);
+        gain_free(other);
+        assert_ne!(PANICKED.with(Cell::get).0, 0);
+        gain_free(trapped);
+        assert_eq!(PANICKED.with(Cell::get).0, 0);
+
+        // A node made where the trapped one was starts out working.
+        let fresh = gain_new(48_000.0);
+        let instance = unsafe { &mut *lookup::<Gain>(fresh).unwrap() };
+        assert_eq!(recorded_error(&mut instance.node).code(), ERROR_NONE);
+        gain_free(fresh);
+    }
+}
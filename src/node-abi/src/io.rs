@@ -0,0 +1,301 @@
+//! A persistent block of WASM memory per instance for everything the worklet exchanges with
+//! the node each quantum: input and output planes, an auxiliary input (side-chain) bus, the
+//! parameter values and the node's meters. `p_get_io_layout` sizes it once and returns its
+//! address; the block starts with an `IoLayout` saying where each region is, so the loader
+//! works out its views once and `p_process_io(handle, frames)` passes no pointers at all.
+//!
+//! A generator's block has no input planes and a meter's no output planes: the region is
+//! there, 0 bytes long, and its channel count says so.
+//!
+//! Planes are `channel_stride` floats apart, frame `i` of channel `c` of a region at
+//! `region_offset + 4 * (c * channel_stride + i)` bytes from the block's start. A parameter
+//! value the loader writes into the params region is applied at the start of the next
+//! `p_process_io` (before the block's queued events); the meters region is rewritten at the
+//! end of each.
+
+use alloc::{vec, vec::Vec};
+
+/// `IoLayout`'s size in floats; the regions follow it.
+const HEADER_WORDS: usize = 16;
+
+/// The header of an instance's I/O block, as the loader reads it: 16 u32s, offsets in bytes
+/// from the header itself.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct IoLayout {
+    /// The whole block, header included.
+    pub bytes: u32,
+    pub max_frames: u32,
+    /// Width of the frames the node processes.
+    pub channels: u32,
+    /// Floats from one channel's plane to the next.
+    pub channel_stride: u32,
+    pub input_offset: u32,
+    pub output_offset: u32,
+    pub aux_offset: u32,
+    pub aux_channels: u32,
+    pub params_offset: u32,
+    pub param_count: u32,
+    pub meters_offset: u32,
+    pub meter_count: u32,
+    /// Planes in the input region: `channels`, or 0 for a generator.
+    pub input_channels: u32,
+    /// Planes in the output region: `channels`, or 0 for a meter.
+    pub output_channels: u32,
+    pub reserved: [u32; 2],
+}
+
+const _: () = assert!(core::mem::size_of::<IoLayout>() == HEADER_WORDS * 4);
+
+/// One block of interleaved frames gathered from the planes, for `Node::process` and friends;
+/// `input` or `output` is empty when the block has no such planes.
+pub struct IoFrames<'a> {
+    pub input: &'a [f32],
+    pub aux: &'a [f32],
+    pub output: &'a mut [f32],
+    pub channels: usize,
+    pub aux_channels: usize,
+}
+
+/// An instance's I/O block plus the interleaved scratch `p_process_io` runs through, all
+/// sized by `negotiate` so processing never allocates.
+#[derive(Default)]
+pub struct IoBuffers {
+    mem: Vec<f32>,
+    layout: IoLayout,
+    /// The params region as last applied, to spot what the loader changed.
+    applied: Vec<f32>,
+    input: Vec<f32>,
+    aux: Vec<f32>,
+    output: Vec<f32>,
+}
+
+impl IoBuffers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// (Re)builds the block for up to `max_frames` frames and returns its address; the params
+    /// region starts out holding `params`, everything else silent. The node processes
+    /// whichever of `input_channels` and `output_channels` is wider (the other is that or 0).
+    /// Asking for the current shape again keeps the block (and the loader's views of it).
+    pub fn negotiate(
+        &mut self,
+        max_frames: usize,
+        input_channels: usize,
+        output_channels: usize,
+        aux_channels: usize,
+        params: &[f32],
+        meter_count: usize,
+    ) -> *const IoLayout {
+        let same = self.layout.max_frames as usize == max_frames
+            && self.layout.input_channels as usize == input_channels
+            && self.layout.output_channels as usize == output_channels
+            && self.layout.aux_channels as usize == aux_channels
+            && self.layout.param_count as usize == params.len()
+            && self.layout.meter_count as usize == meter_count;
+        if !(same && self.is_ready()) {
+            let plane = max_frames;
+            let input = HEADER_WORDS;
+            let output = input + input_channels * plane;
+            let aux = output + output_channels * plane;
+            let param = aux + aux_channels * plane;
+            let meters = param + params.len();
+            let words = meters + meter_count;
+            self.layout = IoLayout {
+                bytes: (words * 4) as u32,
+                max_frames: max_frames as u32,
+                channels: input_channels.max(output_channels) as u32,
+                channel_stride: plane as u32,
+                input_offset: (input * 4) as u32,
+                output_offset: (output * 4) as u32,
+                aux_offset: (aux * 4) as u32,
+                aux_channels: aux_channels as u32,
+                params_offset: (param * 4) as u32,
+                param_count: params.len() as u32,
+                meters_offset: (meters * 4) as u32,
+                meter_count: meter_count as u32,
+                input_channels: input_channels as u32,
+                output_channels: output_channels as u32,
+                reserved: [0; 2],
+            };
+            self.mem = vec![0.0; words];
+            let header = self.mem.as_mut_ptr() as *mut IoLayout;
+            unsafe { header.write(self.layout) };
+            self.input = vec![0.0; input_channels * plane];
+            self.aux = vec![0.0; aux_channels * plane];
+            self.output = vec![0.0; output_channels * plane];
+        }
+        let range = self.words(self.layout.params_offset, params.len());
+        self.mem[range].copy_from_slice(params);
+        self.applied = params.to_vec();
+        self.mem.as_ptr() as *const IoLayout
+    }
+
+    /// Whether `negotiate` has run.
+    pub fn is_ready(&self) -> bool {
+        !self.mem.is_empty()
+    }
+
+    pub fn layout(&self) -> &IoLayout {
+        &self.layout
+    }
+
+    fn words(&self, offset_bytes: u32, len: usize) -> core::ops::Range<usize> {
+        let start = offset_bytes as usize / 4;
+        start..start + len
+    }
+
+    /// Hands `set` each parameter whose value in the params region changed since last time.
+    pub fn apply_params(&mut self, mut set: impl FnMut(u32, f32)) {
+        let range = self.words(self.layout.params_offset, self.applied.len());
+        for (i, (&value, applied)) in self.mem[range].iter().zip(&mut self.applied).enumerate() {
+            if value.to_bits() != applied.to_bits() {
+                *applied = value;
+                set(i as u32, value);
+            }
+        }
+    }
+
+    /// Interleaves the first `frames` frames (at most `max_frames`) of the input and aux
+    /// planes, the first `aux_channels` of the aux bus.
+    pub fn gather(&mut self, frames: usize, aux_channels: usize) -> IoFrames<'_> {
+        let frames = frames.min(self.layout.max_frames as usize);
+        let inputs = self.layout.input_channels as usize;
+        let outputs = self.layout.output_channels as usize;
+        let aux_channels = aux_channels.min(self.layout.aux_channels as usize);
+        let layout = self.layout;
+        interleave(
+            &self.mem,
+            |c| plane(&layout, layout.input_offset, c),
+            &mut self.input[..frames * inputs],
+            inputs,
+        );
+        interleave(
+            &self.mem,
+            |c| plane(&layout, layout.aux_offset, c),
+            &mut self.aux[..frames * aux_channels],
+            aux_channels,
+        );
+        IoFrames {
+            input: &self.input[..frames * inputs],
+            aux: &self.aux[..frames * aux_channels],
+            output: &mut self.output[..frames * outputs],
+            channels: layout.channels as usize,
+            aux_channels,
+        }
+    }
+
+    /// Copies what the last `gather`'s output holds back out to the output planes.
+    pub fn scatter(&mut self, frames: usize) {
+        let frames = frames.min(self.layout.max_frames as usize);
+        let channels = self.layout.output_channels as usize;
+        for c in 0..channels {
+            let range = plane(&self.layout, self.layout.output_offset, c);
+            let out = &mut self.mem[range][..frames];
+            for (i, y) in out.iter_mut().enumerate() {
+                *y = self.output[i * channels + c];
+            }
+        }
+    }
+
+    /// Silences the output planes, as a failed node's output.
+    pub fn silence(&mut self) {
+        let range = self.words(
+            self.layout.output_offset,
+            self.layout.output_channels as usize * self.layout.channel_stride as usize,
+        );
+        self.mem[range].fill(0.0);
+    }
+
+    pub fn meters_mut(&mut self) -> &mut [f32] {
+        let range = self.words(self.layout.meters_offset, self.layout.meter_count as usize);
+        &mut self.mem[range]
+    }
+}
+
+/// Channel `channel`'s plane in the region at `offset_bytes`, in floats.
+fn plane(layout: &IoLayout, offset_bytes: u32, channel: usize) -> core::ops::Range<usize> {
+    let stride = layout.channel_stride as usize;
+    let start = offset_bytes as usize / 4 + channel * stride;
+    start..start + stride
+}
+
+fn interleave(
+    mem: &[f32],
+    plane: impl Fn(usize) -> core::ops::Range<usize>,
+    out: &mut [f32],
+    channels: usize,
+) {
+    for c in 0..channels {
+        let src = &mem[plane(c)];
+        for (y, x) in out.iter_mut().skip(c).step_by(channels).zip(src) {
+            *y = *x;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_describes_the_block() {
+        let mut io = IoBuffers::new();
+        let ptr = io.negotiate(4, 2, 2, 1, &[0.5, 3.0], 1);
+        let layout = unsafe { *ptr };
+        assert_eq!(layout, *io.layout());
+        assert_eq!(layout.channel_stride, 4);
+        assert_eq!(
+            (layout.input_offset, layout.output_offset, layout.aux_offset),
+            (64, 96, 128)
+        );
+        assert_eq!((layout.params_offset, layout.meters_offset), (144, 152));
+        assert_eq!(layout.bytes, 156);
+        // Same shape, same block.
+        assert_eq!(io.negotiate(4, 2, 2, 1, &[0.5, 3.0], 1), ptr);
+
+        let base = ptr as *mut f32;
+        let at = |offset: u32, i: usize| unsafe { base.add(offset as usize / 4 + i) };
+        for i in 0..8 {
+            unsafe { *at(layout.input_offset, i) = i as f32 };
+        }
+        unsafe { *at(layout.aux_offset, 1) = -1.0 };
+        let frames = io.gather(3, 1);
+        assert_eq!(frames.input, [0.0, 4.0, 1.0, 5.0, 2.0, 6.0]);
+        assert_eq!(frames.aux, [0.0, -1.0, 0.0]);
+        frames
+            .output
+            .copy_from_slice(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        io.scatter(3);
+        let out: Vec<f32> = (0..8)
+            .map(|i| unsafe { *at(layout.output_offset, i) })
+            .collect();
+        assert_eq!(out, [1.0, 3.0, 5.0, 0.0, 2.0, 4.0, 6.0, 0.0]);
+
+        unsafe { *at(layout.params_offset, 1) = 7.0 };
+        let mut changed = Vec::new();
+        io.apply_params(|i, v| changed.push((i, v)));
+        io.apply_params(|i, v| changed.push((i, v)));
+        assert_eq!(changed, [(1, 7.0)]);
+
+        io.silence();
+        assert!((0..8).all(|i| unsafe { *at(layout.output_offset, i) } == 0.0));
+    }
+
+    #[test]
+    fn missing_buses_take_no_room() {
+        let mut io = IoBuffers::new();
+        let layout = unsafe { *io.negotiate(4, 0, 2, 0, &[], 0) };
+        assert_eq!(layout.channels, 2);
+        assert_eq!((layout.input_offset, layout.output_offset), (64, 64));
+        assert_eq!(layout.bytes, 96);
+        let frames = io.gather(4, 0);
+        assert_eq!((frames.input.len(), frames.output.len()), (0, 8));
+
+        let layout = unsafe { *io.negotiate(4, 2, 0, 0, &[], 0) };
+        assert_eq!((layout.output_channels, layout.aux_offset), (0, 96));
+        let frames = io.gather(4, 0);
+        assert_eq!((frames.input.len(), frames.output.len()), (8, 0));
+    }
+}
@@ -0,0 +1,1766 @@
+//! The extern-C surface every node exports, so one loader can drive any node's WASM module.
+//! A node with prefix `p` exports:
+//!
+//! - `p_abi_version() -> u32`: `ABI_VERSION` as built; a loader refuses a module that
+//!   disagrees instead of calling into a layout it doesn't know
+//! - `p_new(sample_rate_hz) -> handle` and `p_free(handle)`: a `Handle` (u32) naming the
+//!   instance, 0 if it couldn't be made; see `registry`
+//! - `p_param_count() -> u32` and `p_param_descriptors() -> ptr`: the node's parameter table,
+//!   `ParamDescriptor`s in index order, in static memory the loader reads in place
+//! - `p_set_param(handle, index, value)`: one parameter by index, values in the parameter's own
+//!   units (booleans and choices as 0, 1, ...); an unknown index is ignored
+//! - `p_get_param(handle, index) -> f32`: the value last set (or the default), clamped as the
+//!   node applied it; 0 for an unknown index
+//! - `p_param_events(handle) -> ptr`: the node's `ParamRing` (layout in `dsp_core::events`), for
+//!   changes that have to land on a given frame; `set_param` applies straight away
+//! - `p_process(handle, in_ptr, out_ptr, frames, channels)`: `frames` frames of `channels`
+//!   interleaved samples (clamped to 1..=`Node::MAX_CHANNELS`); the buffers must not overlap.
+//!   Queued parameter events are applied at their frames, the block processed in pieces
+//!   between them. A generator (`Node::INPUT` false) ignores `in_ptr`, which may be null, and a
+//!   meter (`Node::OUTPUT` false) `out_ptr`
+//! - `p_process_offline(handle, in_ptr, out_ptr, total_frames, channels)`: `p_process` over a
+//!   buffer of any length, in render-quantum pieces (`dsp_core::offline`); queued event
+//!   frames count from the start of the buffer
+//! - `p_latency_samples(handle) -> u32`: delay the node adds to its output, in samples (0 for
+//!   most); the hand-written node crates export the same name, and
+//!   `dsp_core::latency` adds the reports up for delay compensation
+//! - `p_get_io_layout(handle, max_frames, channels) -> ptr`: the instance's persistent I/O
+//!   block, sized for up to `max_frames` frames of `channels` channels (clamped as for
+//!   `p_process`), starting with an `IoLayout`; null for 0 frames. Asking again with another
+//!   shape moves the block
+//! - `p_process_io(handle, frames)`: `p_process` from the block's input planes to its output
+//!   planes, first applying parameter values written into its params region, then updating
+//!   its meters; see `io`
+//! - for a node with an auxiliary input (`Node::AUX_CHANNELS` above 0: a side-chain key, a
+//!   vocoder's carrier), optionally `p_process_aux(handle, in_ptr, aux_ptr, out_ptr, frames,
+//!   channels, aux_channels)`, `p_process_aux_offline(...)` with the same arguments and
+//!   `p_process_io_aux(handle, frames, aux_channels)`: the above with `aux_channels`
+//!   (clamped to 1..=`AUX_CHANNELS`) interleaved alongside, or the first `aux_channels` planes
+//!   of the block's aux bus. A null `aux_ptr` or 0 `aux_channels` is the plain export. The
+//!   node crate names them after what the bus carries (`limiter_process_sidechained`)
+//! - `p_reset(handle)`: clears the running state (`Node::reset`) for a transport stop or a
+//!   seek, so old tails don't leak into what plays next; parameters are kept
+//! - `p_state_size(handle) -> u32`, `p_save_state(handle, dest, bytes) -> u32` and
+//!   `p_load_state(handle, src, bytes) -> u32`: a snapshot of the instance (parameters and
+//!   running state) for session save/load or a worklet restart; saving returns the bytes
+//!   written (0 if `bytes` is too few), loading how much it restored. See `state`
+//! - `p_preset_size(handle) -> u32`, `p_save_preset(handle, dest, bytes) -> u32` and
+//!   `p_load_preset(handle, src, bytes) -> u32`: the parameter values as a preset
+//!   (`dsp_core::preset`), keyed by name and versioned so it survives node updates; saving
+//!   returns the bytes written (0 if `bytes` is too few), loading 1 if the preset applied
+//! - `p_last_error_code(handle) -> u32` and `p_last_error_message_ptr(handle) -> ptr`: the
+//!   node's last failure (`ERROR_*`) and a NUL-terminated message; see `error`
+//!
+//! Every export takes a handle that names no live instance (0, freed, or garbage) or a null
+//! buffer as a no-op, `p_process` writing silence.
+//! A node that panics is muted: the export that panicked and every later one fall back to
+//! silence (or 0, or null). Nodes implement `Node` and generate the exports with
+//! `export_node!`; anything node-specific (meters, side-chains, bulk setters) is exported by
+//! hand next to them, through `guard`; so is a generator's note ring (`Node::note_events`),
+//! whose events `p_process` plays at their frames like parameter events.
+//!
+//! With the `bindgen` feature, `bindgen_node!` also wraps the node in a wasm-bindgen class
+//! for main-thread callers; see `bindgen`. Without the default `std` feature the crate is
+//! `no_std` (with `alloc`), like `dsp_core`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+
+pub use dsp_core::events::{EventRing, NoteEvent, ParamEvent, ParamRing};
+pub use dsp_core::preset::{Migration, Preset};
+pub use dsp_core::state::{State, StateReader, StateWriter};
+pub use error::{
+    guard, guard_instance, handle_error, recorded_error, LastError, ERROR_NONE, ERROR_PANIC,
+};
+pub use io::{IoBuffers, IoFrames, IoLayout};
+pub use registry::{Handle, Instance, Registered, Registry, NULL_HANDLE};
+pub use state::{STATE_FULL, STATE_PARAMS, STATE_REJECTED};
+
+pub mod error;
+pub mod io;
+pub mod registry;
+pub mod state;
+
+#[cfg(feature = "bindgen")]
+pub mod bindgen;
+
+/// Bumped whenever an export's signature or meaning changes.
+pub const ABI_VERSION: u32 = 11;
+
+/// The control moves evenly between `min` and `max`.
+pub const SCALE_LINEAR: u32 = 0;
+/// The control moves evenly in ratio (`min` must be above 0): times, frequencies.
+pub const SCALE_LOG: u32 = 1;
+
+/// On/off: 0 or 1, drawn as a switch.
+pub const FLAG_TOGGLE: u32 = 1 << 0;
+/// Whole numbers only (counts, factors, choices).
+pub const FLAG_STEPPED: u32 = 1 << 1;
+/// Changing it clears or rebuilds internal state, so it shouldn't be automated.
+pub const FLAG_NO_AUTOMATION: u32 = 1 << 2;
+
+const NAME_BYTES: usize = 32;
+const UNIT_BYTES: usize = 12;
+
+/// One parameter as the loader reads it from WASM memory: 64 bytes, the name and unit as
+/// NUL-padded UTF-8 followed by `min`, `max`, `default` (f32), `scale` and `flags` (u32).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParamDescriptor {
+    pub name: [u8; NAME_BYTES],
+    pub unit: [u8; UNIT_BYTES],
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+    pub scale: u32,
+    pub flags: u32,
+}
+
+/// `s` NUL-padded to `N` bytes, leaving at least one NUL; too long fails to compile.
+const fn padded<const N: usize>(s: &str) -> [u8; N] {
+    let bytes = s.as_bytes();
+    assert!(bytes.len() < N, "parameter name or unit too long");
+    let mut out = [0; N];
+    let mut i = 0;
+    while i < bytes.len() {
+        out[i] = bytes[i];
+        i += 1;
+    }
+    out
+}
+
+impl ParamDescriptor {
+    pub const fn linear(name: &str, unit: &str, min: f32, max: f32, default: f32) -> Self {
+        Self {
+            name: padded(name),
+            unit: padded(unit),
+            min,
+            max,
+            default,
+            scale: SCALE_LINEAR,
+            flags: 0,
+        }
+    }
+
+    pub const fn log(name: &str, unit: &str, min: f32, max: f32, default: f32) -> Self {
+        Self {
+            scale: SCALE_LOG,
+            ..Self::linear(name, unit, min, max, default)
+        }
+    }
+
+    pub const fn toggle(name: &str, default: bool) -> Self {
+        Self {
+            flags: FLAG_TOGGLE | FLAG_STEPPED,
+            ..Self::linear(name, "", 0.0, 1.0, if default { 1.0 } else { 0.0 })
+        }
+    }
+
+    pub const fn stepped(name: &str, unit: &str, min: f32, max: f32, default: f32) -> Self {
+        Self {
+            flags: FLAG_STEPPED,
+            ..Self::linear(name, unit, min, max, default)
+        }
+    }
+
+    /// Adds `flags` to the ones the constructor set.
+    pub const fn with_flags(self, flags: u32) -> Self {
+        Self {
+            flags: self.flags | flags,
+            ..self
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        text(&self.name)
+    }
+
+    pub fn unit(&self) -> &str {
+        text(&self.unit)
+    }
+}
+
+fn text(bytes: &[u8]) -> &str {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    core::str::from_utf8(&bytes[..len]).unwrap_or("")
+}
+
+pub trait Node: Sized {
+    /// Widest interleaved frame `process` is handed.
+    const MAX_CHANNELS: usize;
+
+    /// Parameter table; a parameter's index is its position.
+    const PARAMS: &'static [ParamDescriptor];
+
+    /// False for a generator: `process` is handed an empty `input`, and the I/O block has no
+    /// input planes.
+    const INPUT: bool = true;
+
+    /// False for a meter or analyzer: `process` is handed an empty `output`, and the I/O block
+    /// has no output planes. The loader passes the input on downstream itself.
+    const OUTPUT: bool = true;
+
+    /// Channels of the I/O block's auxiliary input bus (a side-chain key, say).
+    const AUX_CHANNELS: usize = 0;
+
+    /// Values `meters` writes into the I/O block after each `p_process_io`.
+    const METER_COUNT: usize = 0;
+
+    /// Upgrades for presets saved by earlier versions of the node, oldest first (see
+    /// `dsp_core::preset::Migration`); append one whenever a parameter is renamed or its
+    /// range or units change.
+    const PRESET_MIGRATIONS: &'static [Migration] = &[];
+
+    fn new(sample_rate_hz: f32) -> Self;
+
+    /// Out-of-range values are clamped; unknown indices ignored.
+    fn set_param(&mut self, index: u32, value: f32);
+
+    fn get_param(&self, index: u32) -> f32;
+
+    /// The ring the worklet queues frame-stamped parameter changes on.
+    fn param_events(&mut self) -> &mut ParamRing;
+
+    /// Where `guard` records the node's failure.
+    fn last_error(&mut self) -> &mut LastError;
+
+    /// The ring the worklet queues notes on, for a node that plays them; they are handed to
+    /// `note` at their frames as parameter events are applied.
+    fn note_events(&mut self) -> Option<&mut EventRing> {
+        None
+    }
+
+    /// Plays a note event from `note_events`; the next piece `process` is handed starts at its
+    /// frame.
+    fn note(&mut self, _event: NoteEvent) {}
+
+    /// Called once per processing call before its first piece, for anything kept per call
+    /// rather than per `process` (meters, mostly).
+    fn start_block(&mut self) {}
+
+    /// `input` and `output` hold the same number of frames of `channels` interleaved samples
+    /// (but see `INPUT` and `OUTPUT`).
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize);
+
+    /// `process` with the auxiliary bus alongside: `aux` holds the same frames, `aux_channels`
+    /// (1..=`AUX_CHANNELS`) interleaved, or is empty when the caller passed none, which the
+    /// default hands straight to `process`. Nodes with an auxiliary bus override it.
+    fn process_aux(
+        &mut self,
+        input: &[f32],
+        aux: &[f32],
+        aux_channels: usize,
+        output: &mut [f32],
+        channels: usize,
+    ) {
+        let _ = (aux, aux_channels);
+        self.process(input, output, channels);
+    }
+
+    fn latency_samples(&self) -> u32 {
+        0
+    }
+
+    /// Fills `out` (`METER_COUNT` long) with the node's current meter readings.
+    fn meters(&self, _out: &mut [f32]) {}
+
+    /// Writes what processing has built up that the parameters don't determine (delay lines,
+    /// envelopes, smoother positions), for `p_save_state`.
+    fn save_state(&self, _w: &mut StateWriter) {}
+
+    /// Reads back what `save_state` wrote, into a node whose parameters are already restored.
+    fn load_state(&mut self, _r: &mut StateReader) {}
+
+    /// Drops what processing has built up (envelopes, delay lines, filter memories) without
+    /// reallocating, so the next block starts as a new node's would; parameters and queued
+    /// events are kept.
+    fn reset(&mut self) {}
+}
+
+/// Works through a block of `frames` frames in pieces split at the queued parameter and note
+/// events: after `Node::start_block`, applies the events due at a piece's first frame
+/// (parameters before notes on the same frame), then hands `f` the piece's frame range.
+/// Events stamped past the block land on its last frame, so the rings are empty afterwards (an
+/// empty block leaves them queued). For exports with their own buffer layouts.
+pub fn split_at_events<N: Node>(
+    node: &mut N,
+    frames: usize,
+    mut f: impl FnMut(&mut N, core::ops::Range<usize>),
+) {
+    node.start_block();
+    let mut start = 0;
+    while start < frames {
+        let end = loop {
+            let param = node.param_events().peek().map(|e| e.frame);
+            let note = node.note_events().and_then(|r| r.peek()).map(|e| e.frame);
+            let Some(next) = param.into_iter().chain(note).min() else {
+                break frames;
+            };
+            let frame = (next as usize).min(frames - 1);
+            if frame > start {
+                break frame;
+            }
+            if param == Some(next) {
+                if let Some(e) = node.param_events().pop() {
+                    node.set_param(e.index, e.value);
+                }
+            } else if let Some(e) = node.note_events().and_then(|r| r.pop()) {
+                node.note(e);
+            }
+        };
+        f(node, start..end);
+        start = end;
+    }
+}
+
+/// `split_at_events` for a whole offline render: event frames count from its start, and no
+/// piece is longer than `dsp_core::offline::CHUNK_FRAMES`.
+pub fn split_offline<N: Node>(
+    node: &mut N,
+    frames: usize,
+    mut f: impl FnMut(&mut N, core::ops::Range<usize>),
+) {
+    split_at_events(node, frames, |node, span| {
+        for chunk in dsp_core::offline::chunks(span.len()) {
+            f(node, span.start + chunk.start..span.start + chunk.end);
+        }
+    });
+}
+
+/// Runs a block through `process_aux` in pieces split at the queued events, as
+/// `split_offline` splits it when `offline`. It runs as many frames as every bus the node has
+/// holds; a bus it doesn't have is dropped, and an empty `aux` (or 0 `aux_channels`) is none.
+pub fn process_block<N: Node>(node: &mut N, block: IoFrames<'_>, offline: bool) {
+    let IoFrames {
+        input,
+        aux,
+        output,
+        channels,
+        aux_channels,
+    } = block;
+    let input = if N::INPUT { input } else { &[] };
+    let output = if N::OUTPUT { output } else { &mut [] };
+    let (aux, aux_channels) = if aux.is_empty() || aux_channels == 0 {
+        (&[][..], 0)
+    } else {
+        (aux, aux_channels)
+    };
+    let mut frames = if N::INPUT { input.len() } else { output.len() } / channels;
+    if N::INPUT && N::OUTPUT {
+        frames = frames.min(output.len() / channels);
+    }
+    if let Some(aux_frames) = aux.len().checked_div(aux_channels) {
+        frames = frames.min(aux_frames);
+    }
+    let run = |node: &mut N, span: core::ops::Range<usize>| {
+        let at = |len: usize, width: usize| {
+            if len == 0 {
+                0..0
+            } else {
+                span.start * width..span.end * width
+            }
+        };
+        let (i, a, o) = (
+            at(input.len(), channels),
+            at(aux.len(), aux_channels),
+            at(output.len(), channels),
+        );
+        node.process_aux(&input[i], &aux[a], aux_channels, &mut output[o], channels);
+    };
+    if offline {
+        split_offline(node, frames, run);
+    } else {
+        split_at_events(node, frames, run);
+    }
+}
+
+/// `process_block` for interleaved buffers without an auxiliary bus, as the standard
+/// `process` export runs it.
+pub fn process_with_events<N: Node>(
+    node: &mut N,
+    input: &[f32],
+    output: &mut [f32],
+    channels: usize,
+) {
+    let block = IoFrames {
+        input,
+        aux: &[],
+        output,
+        channels,
+        aux_channels: 0,
+    };
+    process_block(node, block, false);
+}
+
+/// `process_with_events` over a whole offline render, as the `process_offline` export runs it.
+pub fn process_offline<N: Node>(node: &mut N, input: &[f32], output: &mut [f32], channels: usize) {
+    let block = IoFrames {
+        input,
+        aux: &[],
+        output,
+        channels,
+        aux_channels: 0,
+    };
+    process_block(node, block, true);
+}
+
+/// (Re)negotiates the instance's I/O block, as `p_get_io_layout` does; the params region
+/// starts out holding the current values.
+pub fn negotiate_io<N: Node>(
+    instance: &mut Instance<N>,
+    max_frames: usize,
+    channels: usize,
+) -> *const IoLayout {
+    let Instance { node, io } = instance;
+    let params: Vec<f32> = (0..N::PARAMS.len() as u32)
+        .map(|index| node.get_param(index))
+        .collect();
+    let channels = channels.clamp(1, N::MAX_CHANNELS);
+    io.negotiate(
+        max_frames,
+        if N::INPUT { channels } else { 0 },
+        if N::OUTPUT { channels } else { 0 },
+        N::AUX_CHANNELS,
+        &params,
+        N::METER_COUNT,
+    )
+}
+
+/// `process_block` through the instance's I/O block, with the first `aux_channels` planes of
+/// its aux bus, as `p_process_io` (0) and `p_process_io_aux` run it; nothing happens before
+/// the block is negotiated.
+pub fn process_io<N: Node>(instance: &mut Instance<N>, frames: usize, aux_channels: usize) {
+    let Instance { node, io } = instance;
+    if !io.is_ready() {
+        return;
+    }
+    io.apply_params(|index, value| node.set_param(index, value));
+    process_block(node, io.gather(frames, aux_channels), false);
+    io.scatter(frames);
+    node.meters(io.meters_mut());
+}
+
+/// A process export's arguments, for `process_raw`.
+#[doc(hidden)]
+pub struct RawBlock {
+    pub input: *const f32,
+    pub aux: *const f32,
+    pub output: *mut f32,
+    pub frames: usize,
+    pub channels: usize,
+    pub aux_channels: usize,
+}
+
+/// The body of `export_node!`'s process exports: checks and clamps the caller's block, runs it
+/// through `process_block` and writes silence if the node fails.
+///
+/// # Safety
+///
+/// Every pointer the node reads (see `Node::INPUT`, `Node::OUTPUT`) must be null or valid for
+/// `frames` frames of its bus, after clamping.
+#[doc(hidden)]
+pub unsafe fn process_raw<N: Registered>(handle: Handle, raw: RawBlock, offline: bool) {
+    if (N::INPUT && raw.input.is_null()) || (N::OUTPUT && raw.output.is_null()) {
+        return;
+    }
+    let channels = raw.channels.clamp(1, N::MAX_CHANNELS);
+    let aux_channels = if raw.aux.is_null() || N::AUX_CHANNELS == 0 {
+        0
+    } else {
+        raw.aux_channels.clamp(1, N::AUX_CHANNELS)
+    };
+    let n = raw.frames.saturating_mul(channels);
+    let input = if N::INPUT {
+        core::slice::from_raw_parts(raw.input, n)
+    } else {
+        &[]
+    };
+    let output = if N::OUTPUT {
+        core::slice::from_raw_parts_mut(raw.output, n)
+    } else {
+        &mut []
+    };
+    let aux = if aux_channels > 0 {
+        core::slice::from_raw_parts(raw.aux, raw.frames.saturating_mul(aux_channels))
+    } else {
+        &[]
+    };
+    let done = guard(handle, |node: &mut N| {
+        let block = IoFrames {
+            input,
+            aux,
+            output: &mut *output,
+            channels,
+            aux_channels,
+        };
+        process_block(node, block, offline)
+    });
+    if done.is_none() {
+        output.fill(0.0);
+    }
+}
+
+/// The node's parameter values as a preset at its current preset version.
+pub fn save_preset<N: Node>(node: &N) -> Preset {
+    let mut preset = Preset::new(N::PRESET_MIGRATIONS.len() as u32);
+    for (index, param) in N::PARAMS.iter().enumerate() {
+        preset.set(param.name(), node.get_param(index as u32));
+    }
+    preset
+}
+
+/// Migrates `preset` and applies it through `set_param`: parameters it doesn't name go to
+/// their defaults, names the node doesn't have are ignored. False, leaving the node alone,
+/// for a preset from a newer version of the node.
+pub fn load_preset<N: Node>(node: &mut N, mut preset: Preset) -> bool {
+    if !preset.migrate(N::PRESET_MIGRATIONS) {
+        return false;
+    }
+    for (index, param) in N::PARAMS.iter().enumerate() {
+        let value = preset.get(param.name()).unwrap_or(param.default);
+        node.set_param(index as u32, value);
+    }
+    true
+}
+
+/// Silences the output planes of the instance behind `handle`, if it has an I/O block; what
+/// an I/O export falls back to when `guard_instance` refuses.
+pub fn silence_io<N: Registered>(handle: Handle) {
+    if let Some(instance) = registry::lookup::<N>(handle) {
+        let io = unsafe { &mut (*instance).io };
+        if io.is_ready() {
+            io.silence();
+        }
+    }
+}
+
+/// Generates the standard exports for a `Node` type under the given names, which by
+/// convention are the node's prefix followed by the key:
+///
+/// ```text
+/// node_abi::export_node!(Limiter {
+///     abi_version: limiter_abi_version,
+///     new: limiter_new,
+///     free: limiter_free,
+///     param_count: limiter_param_count,
+///     param_descriptors: limiter_param_descriptors,
+///     set_param: limiter_set_param,
+///     get_param: limiter_get_param,
+///     param_events: limiter_param_events,
+///     process: limiter_process,
+///     process_offline: limiter_process_offline,
+///     latency_samples: limiter_latency_samples,
+///     io_layout: limiter_get_io_layout,
+///     process_io: limiter_process_io,
+///     reset: limiter_reset,
+///     state_size: limiter_state_size,
+///     save_state: limiter_save_state,
+///     load_state: limiter_load_state,
+///     preset_size: limiter_preset_size,
+///     save_preset: limiter_save_preset,
+///     load_preset: limiter_load_preset,
+///     last_error_code: limiter_last_error_code,
+///     last_error_message: limiter_last_error_message_ptr,
+///     process_aux: limiter_process_sidechained,
+///     process_aux_offline: limiter_process_offline_sidechained,
+///     process_io_aux: limiter_process_io_sidechained,
+/// });
+/// ```
+///
+/// The last three, the auxiliary-bus exports, are optional.
+#[macro_export]
+macro_rules! export_node {
+    ($node:ty {
+        abi_version: $abi_version:ident,
+        new: $new:ident,
+        free: $free:ident,
+        param_count: $param_count:ident,
+        param_descriptors: $param_descriptors:ident,
+        set_param: $set_param:ident,
+        get_param: $get_param:ident,
+        param_events: $param_events:ident,
+        process: $process:ident,
+        process_offline: $process_offline:ident,
+        latency_samples: $latency_samples:ident,
+        io_layout: $io_layout:ident,
+        process_io: $process_io:ident,
+        reset: $reset:ident,
+        state_size: $state_size:ident,
+        save_state: $save_state:ident,
+        load_state: $load_state:ident,
+        preset_size: $preset_size:ident,
+        save_preset: $save_preset:ident,
+        load_preset: $load_preset:ident,
+        last_error_code: $last_error_code:ident,
+        last_error_message: $last_error_message:ident
+        $(,
+            process_aux: $process_aux:ident,
+            process_aux_offline: $process_aux_offline:ident,
+            process_io_aux: $process_io_aux:ident
+        )? $(,)?
+    }) => {
+        impl $crate::Registered for $node {
+            fn with_registry<R>(f: impl FnOnce(&mut $crate::Registry<Self>) -> R) -> R {
+                $crate::registry_static!(REGISTRY: $crate::Registry<$node>);
+                REGISTRY.with(|r| f(&mut r.borrow_mut()))
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $abi_version() -> u32 {
+            $crate::ABI_VERSION
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $new(sample_rate_hz: f32) -> $crate::Handle {
+            $crate::error::catch(|| {
+                $crate::registry::register(<$node as $crate::Node>::new(sample_rate_hz))
+            })
+            .unwrap_or($crate::NULL_HANDLE)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $free(handle: $crate::Handle) {
+            if let Some(instance) = $crate::registry::unregister::<$node>(handle) {
+                $crate::error::forget(&instance.node);
+                $crate::error::catch(|| drop(instance));
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $param_count() -> u32 {
+            <$node as $crate::Node>::PARAMS.len() as u32
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $param_descriptors() -> *const $crate::ParamDescriptor {
+            <$node as $crate::Node>::PARAMS.as_ptr()
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $get_param(handle: $crate::Handle, index: u32) -> f32 {
+            $crate::guard(handle, |node: &mut $node| {
+                $crate::Node::get_param(node, index)
+            })
+            .unwrap_or(0.0)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $set_param(handle: $crate::Handle, index: u32, value: f32) {
+            $crate::guard(handle, |node: &mut $node| {
+                $crate::Node::set_param(node, index, value)
+            });
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $param_events(handle: $crate::Handle) -> *mut $crate::ParamRing {
+            $crate::guard(handle, |node: &mut $node| {
+                $crate::Node::param_events(node) as *mut _
+            })
+            .unwrap_or(core::ptr::null_mut())
+        }
+
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn $process(
+            handle: $crate::Handle,
+            in_ptr: *const f32,
+            out_ptr: *mut f32,
+            frames: usize,
+            channels: usize,
+        ) {
+            let raw = $crate::RawBlock {
+                input: in_ptr,
+                aux: core::ptr::null(),
+                output: out_ptr,
+                frames,
+                channels,
+                aux_channels: 0,
+            };
+            unsafe { $crate::process_raw::<$node>(handle, raw, false) }
+        }
+
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn $process_offline(
+            handle: $crate::Handle,
+            in_ptr: *const f32,
+            out_ptr: *mut f32,
+            total_frames: usize,
+            channels: usize,
+        ) {
+            let raw = $crate::RawBlock {
+                input: in_ptr,
+                aux: core::ptr::null(),
+                output: out_ptr,
+                frames: total_frames,
+                channels,
+                aux_channels: 0,
+            };
+            unsafe { $crate::process_raw::<$node>(handle, raw, true) }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $latency_samples(handle: $crate::Handle) -> u32 {
+            $crate::guard(handle, |node: &mut $node| {
+                $crate::Node::latency_samples(node)
+            })
+            .unwrap_or(0)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $io_layout(
+            handle: $crate::Handle,
+            max_frames: u32,
+            channels: u32,
+        ) -> *const $crate::IoLayout {
+            if max_frames == 0 {
+                return core::ptr::null();
+            }
+            $crate::guard_instance(handle, |instance| {
+                $crate::negotiate_io::<$node>(instance, max_frames as usize, channels as usize)
+            })
+            .unwrap_or(core::ptr::null())
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $process_io(handle: $crate::Handle, frames: u32) {
+            let done = $crate::guard_instance(handle, |instance| {
+                $crate::process_io::<$node>(instance, frames as usize, 0)
+            });
+            if done.is_none() {
+                $crate::silence_io::<$node>(handle);
+            }
+        }
+
+        $(
+            #[no_mangle]
+            #[allow(clippy::not_unsafe_ptr_arg_deref)]
+            pub extern "C" fn $process_aux(
+                handle: $crate::Handle,
+                in_ptr: *const f32,
+                aux_ptr: *const f32,
+                out_ptr: *mut f32,
+                frames: usize,
+                channels: usize,
+                aux_channels: usize,
+            ) {
+                let raw = $crate::RawBlock {
+                    input: in_ptr,
+                    aux: aux_ptr,
+                    output: out_ptr,
+                    frames,
+                    channels,
+                    aux_channels,
+                };
+                unsafe { $crate::process_raw::<$node>(handle, raw, false) }
+            }
+
+            #[no_mangle]
+            #[allow(clippy::not_unsafe_ptr_arg_deref)]
+            pub extern "C" fn $process_aux_offline(
+                handle: $crate::Handle,
+                in_ptr: *const f32,
+                aux_ptr: *const f32,
+                out_ptr: *mut f32,
+                total_frames: usize,
+                channels: usize,
+                aux_channels: usize,
+            ) {
+                let raw = $crate::RawBlock {
+                    input: in_ptr,
+                    aux: aux_ptr,
+                    output: out_ptr,
+                    frames: total_frames,
+                    channels,
+                    aux_channels,
+                };
+                unsafe { $crate::process_raw::<$node>(handle, raw, true) }
+            }
+
+            #[no_mangle]
+            pub extern "C" fn $process_io_aux(
+                handle: $crate::Handle,
+                frames: u32,
+                aux_channels: u32,
+            ) {
+                let done = $crate::guard_instance(handle, |instance| {
+                    $crate::process_io::<$node>(instance, frames as usize, aux_channels as usize)
+                });
+                if done.is_none() {
+                    $crate::silence_io::<$node>(handle);
+                }
+            }
+        )?
+
+        #[no_mangle]
+        pub extern "C" fn $reset(handle: $crate::Handle) {
+            $crate::guard(handle, |node: &mut $node| $crate::Node::reset(node));
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $state_size(handle: $crate::Handle) -> u32 {
+            $crate::guard(handle, |node: &mut $node| {
+                $crate::state::state_size(node) as u32
+            })
+            .unwrap_or(0)
+        }
+
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn $save_state(handle: $crate::Handle, dest: *mut u8, bytes: u32) -> u32 {
+            if dest.is_null() {
+                return 0;
+            }
+            let dest = unsafe { core::slice::from_raw_parts_mut(dest, bytes as usize) };
+            $crate::guard(handle, |node: &mut $node| {
+                $crate::state::save_state(node, dest) as u32
+            })
+            .unwrap_or(0)
+        }
+
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn $load_state(handle: $crate::Handle, src: *const u8, bytes: u32) -> u32 {
+            if src.is_null() {
+                return $crate::STATE_REJECTED;
+            }
+            let src = unsafe { core::slice::from_raw_parts(src, bytes as usize) };
+            $crate::guard(handle, |node: &mut $node| {
+                $crate::state::load_state(node, src)
+            })
+            .unwrap_or($crate::STATE_REJECTED)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $preset_size(handle: $crate::Handle) -> u32 {
+            $crate::guard(handle, |node: &mut $node| {
+                $crate::save_preset(node).encoded_len() as u32
+            })
+            .unwrap_or(0)
+        }
+
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn $save_preset(handle: $crate::Handle, dest: *mut u8, bytes: u32) -> u32 {
+            if dest.is_null() {
+                return 0;
+            }
+            let dest = unsafe { core::slice::from_raw_parts_mut(dest, bytes as usize) };
+            $crate::guard(handle, |node: &mut $node| {
+                let mut w = $crate::StateWriter::new(dest);
+                $crate::save_preset(node).write(&mut w);
+                if w.overflowed() {
+                    0
+                } else {
+                    w.len() as u32
+                }
+            })
+            .unwrap_or(0)
+        }
+
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn $load_preset(handle: $crate::Handle, src: *const u8, bytes: u32) -> u32 {
+            if src.is_null() {
+                return 0;
+            }
+            let src = unsafe { core::slice::from_raw_parts(src, bytes as usize) };
+            let Some(preset) = $crate::Preset::decode(src) else {
+                return 0;
+            };
+            $crate::guard(handle, |node: &mut $node| {
+                $crate::load_preset(node, preset) as u32
+            })
+            .unwrap_or(0)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $last_error_code(handle: $crate::Handle) -> u32 {
+            $crate::handle_error::<$node, _>(handle, $crate::LastError::code)
+                .unwrap_or($crate::ERROR_NONE)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $last_error_message(handle: $crate::Handle) -> *const u8 {
+            $crate::handle_error::<$node, _>(handle, $crate::LastError::message_ptr)
+                .unwrap_or(core::ptr::null())
+        }
+    };
+}
+
+/// Generates `io_block_matches_process`, a test of the node `$node` through its I/O block:
+/// the layout has the buses the node declares (no input planes for a generator, an aux bus
+/// as wide as `AUX_CHANNELS`), and rendering `dsp_core::blocksize::test_signal` through it a
+/// render quantum at a time, with a second test signal on the aux bus, gives the output and
+/// meters the interleaved `process_aux` does. `setup` runs on both fresh instances (to load
+/// data or queue notes):
+///
+/// ```text
+/// node_abi::test_io_block!(Vocoder);
+/// node_abi::test_io_block!(Synth, setup: |s: &mut Synth| s.notes.push(note_on));
+/// ```
+#[macro_export]
+macro_rules! test_io_block {
+    ($node:ty $(, setup: $setup:expr)? $(,)?) => {
+        #[cfg(test)]
+        #[test]
+        fn io_block_matches_process() {
+            #[allow(unused_variables)]
+            let setup = |node: &mut $node| {};
+            $(let setup = $setup;)?
+            $crate::assert_io_matches_process::<$node>(setup);
+        }
+    };
+}
+
+/// The body of `test_io_block!`.
+#[doc(hidden)]
+pub fn assert_io_matches_process<N: Node>(setup: impl Fn(&mut N)) {
+    use dsp_core::blocksize::{test_signal, AUX_CHANNELS, CHANNELS, FRAMES, SAMPLE_RATE};
+    use dsp_core::offline::{chunks, CHUNK_FRAMES};
+
+    let channels = CHANNELS.min(N::MAX_CHANNELS);
+    let aux_channels = AUX_CHANNELS.min(N::AUX_CHANNELS);
+    let input = test_signal(FRAMES, channels, 0);
+    let aux = test_signal(FRAMES, aux_channels, 1);
+
+    let mut instance = Instance {
+        node: N::new(SAMPLE_RATE),
+        io: IoBuffers::new(),
+    };
+    setup(&mut instance.node);
+    let base = negotiate_io(&mut instance, CHUNK_FRAMES, channels) as *mut f32;
+    let layout = unsafe { *(base as *const IoLayout) };
+    let inputs = if N::INPUT { channels } else { 0 };
+    let outputs = if N::OUTPUT { channels } else { 0 };
+    assert_eq!(
+        (
+            layout.input_channels,
+            layout.output_channels,
+            layout.aux_channels
+        ),
+        (inputs as u32, outputs as u32, N::AUX_CHANNELS as u32)
+    );
+    assert_eq!(
+        (layout.param_count, layout.meter_count),
+        (N::PARAMS.len() as u32, N::METER_COUNT as u32)
+    );
+    let plane = |offset: u32, c: usize| unsafe {
+        let at = offset as usize / 4 + c * layout.channel_stride as usize;
+        core::slice::from_raw_parts_mut(base.add(at), CHUNK_FRAMES)
+    };
+
+    let mut direct = N::new(SAMPLE_RATE);
+    setup(&mut direct);
+    let mut expected = vec![0.0; FRAMES * channels];
+    let mut output = vec![0.0; FRAMES * channels];
+    for span in chunks(FRAMES) {
+        let frames = span.len();
+        for c in 0..inputs {
+            for (i, x) in plane(layout.input_offset, c)[..frames]
+                .iter_mut()
+                .enumerate()
+            {
+                *x = input[(span.start + i) * channels + c];
+            }
+        }
+        for c in 0..aux_channels {
+            for (i, x) in plane(layout.aux_offset, c)[..frames].iter_mut().enumerate() {
+                *x = aux[(span.start + i) * aux_channels + c];
+            }
+        }
+        process_io(&mut instance, frames, aux_channels);
+        for c in 0..outputs {
+            for (i, y) in plane(layout.output_offset, c)[..frames].iter().enumerate() {
+                output[(span.start + i) * channels + c] = *y;
+            }
+        }
+
+        let block = IoFrames {
+            input: &input[span.start * channels..span.end * channels],
+            aux: &aux[span.start * aux_channels..span.end * aux_channels],
+            output: &mut expected[span.start * channels..span.end * channels],
+            channels,
+            aux_channels,
+        };
+        process_block(&mut direct, block, false);
+    }
+    assert!(output == expected, "the I/O block renders differently");
+    let mut meters = vec![0.0; N::METER_COUNT];
+    direct.meters(&mut meters);
+    let block_meters = unsafe {
+        core::slice::from_raw_parts(base.add(layout.meters_offset as usize / 4), N::METER_COUNT)
+    };
+    assert_eq!(block_meters, &meters[..]);
+}
+
+/// Generates `params_match_table`, a test that the node `$node` keeps to its `PARAMS`: each
+/// descriptor is well formed (`min < max`, the default between them, whole numbers when
+/// stepped, `min > 0` on a log scale), a new node starts at the defaults, and `set_param`
+/// clamps to the range and reads back through `get_param`; unknown indices are ignored.
+///
+/// ```text
+/// node_abi::test_params!(Compressor);
+/// ```
+#[macro_export]
+macro_rules! test_params {
+    ($node:ty $(,)?) => {
+        #[cfg(test)]
+        #[test]
+        fn params_match_table() {
+            $crate::assert_params_match_table::<$node>();
+        }
+    };
+}
+
+/// The body of `test_params!`.
+#[doc(hidden)]
+pub fn assert_params_match_table<N: Node>() {
+    let close = |a: f32, b: f32| (a - b).abs() <= 1e-4 * b.abs().max(1.0);
+    let mut node = N::new(dsp_core::blocksize::SAMPLE_RATE);
+    for (index, d) in N::PARAMS.iter().enumerate() {
+        let name = d.name();
+        let i = index as u32;
+        assert!(!name.is_empty(), "parameter {index} has no name");
+        assert!(d.min < d.max, "{name}: empty range");
+        assert!(
+            (d.min..=d.max).contains(&d.default),
+            "{name}: default out of range"
+        );
+        if d.scale == SCALE_LOG {
+            assert!(d.min > 0.0, "{name}: log scale from {}", d.min);
+        }
+        if d.flags & FLAG_STEPPED != 0 {
+            for v in [d.min, d.max, d.default] {
+                assert_eq!(v, v as i64 as f32, "{name}: stepped but not whole");
+            }
+        }
+        let current = node.get_param(i);
+        assert!(close(current, d.default), "{name}: starts at {current}");
+        for (set, expect) in [
+            (d.min, d.min),
+            (d.max, d.max),
+            (d.min - (d.max - d.min) - 1.0, d.min),
+            (d.max * 2.0 + 1.0, d.max),
+        ] {
+            node.set_param(i, set);
+            let got = node.get_param(i);
+            assert!(
+                close(got, expect),
+                "{name}: set {set}, reads {got}, not {expect}"
+            );
+        }
+        node.set_param(i, d.default);
+    }
+    let values: Vec<f32> = (0..N::PARAMS.len() as u32)
+        .map(|i| node.get_param(i))
+        .collect();
+    let unknown = N::PARAMS.len() as u32;
+    node.set_param(unknown, 1.0);
+    assert_eq!(node.get_param(unknown), 0.0);
+    let after: Vec<f32> = (0..unknown).map(|i| node.get_param(i)).collect();
+    assert_eq!(values, after, "an unknown index changed a parameter");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub struct Gain {
+        gain: f32,
+        delay: u32,
+        events: ParamRing,
+        error: LastError,
+        /// Length of every `process` call, to check the splitting.
+        calls: Vec<usize>,
+        /// Frames processed; the running state a snapshot carries.
+        frames: u64,
+    }
+
+    impl Node for Gain {
+        const MAX_CHANNELS: usize = 2;
+        const PARAMS: &'static [ParamDescriptor] = &[
+            ParamDescriptor::linear("Gain", "", 0.0, 2.0, 1.0),
+            ParamDescriptor::stepped("Delay", "samples", 0.0, 64.0, 0.0)
+                .with_flags(FLAG_NO_AUTOMATION),
+        ];
+        const METER_COUNT: usize = 1;
+        const PRESET_MIGRATIONS: &'static [Migration] = &[|p| p.rename("Level", "Gain")];
+
+        fn new(_sample_rate_hz: f32) -> Self {
+            Self {
+                gain: 1.0,
+                delay: 0,
+                events: ParamRing::new(),
+                error: LastError::new(),
+                calls: Vec::new(),
+                frames: 0,
+            }
+        }
+
+        fn set_param(&mut self, index: u32, value: f32) {
+            match index {
+                0 => self.gain = value,
+                1 => self.delay = value as u32,
+                // Stands in for a bug.
+                2 => panic!("gain exploded"),
+                _ => {}
+            }
+        }
+
+        fn get_param(&self, index: u32) -> f32 {
+            match index {
+                0 => self.gain,
+                1 => self.delay as f32,
+                _ => 0.0,
+            }
+        }
+
+        fn param_events(&mut self) -> &mut ParamRing {
+            &mut self.events
+        }
+
+        fn last_error(&mut self) -> &mut LastError {
+            &mut self.error
+        }
+
+        fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+            self.calls.push(input.len() / channels);
+            self.frames += (input.len() / channels) as u64;
+            for (y, x) in output.iter_mut().zip(input) {
+                *y = x * self.gain;
+            }
+        }
+
+        fn latency_samples(&self) -> u32 {
+            self.delay
+        }
+
+        fn meters(&self, out: &mut [f32]) {
+            out[0] = self.calls.len() as f32;
+        }
+
+        fn save_state(&self, w: &mut StateWriter) {
+            w.u64(self.frames);
+        }
+
+        fn load_state(&mut self, r: &mut StateReader) {
+            self.frames = r.u64();
+        }
+
+        fn reset(&mut self) {
+            self.frames = 0;
+        }
+    }
+
+    export_node!(Gain {
+        abi_version: gain_abi_version,
+        new: gain_new,
+        free: gain_free,
+        param_count: gain_param_count,
+        param_descriptors: gain_param_descriptors,
+        set_param: gain_set_param,
+        get_param: gain_get_param,
+        param_events: gain_param_events,
+        process: gain_process,
+        process_offline: gain_process_offline,
+        latency_samples: gain_latency_samples,
+        io_layout: gain_get_io_layout,
+        process_io: gain_process_io,
+        reset: gain_reset,
+        state_size: gain_state_size,
+        save_state: gain_save_state,
+        load_state: gain_load_state,
+        preset_size: gain_preset_size,
+        save_preset: gain_save_preset,
+        load_preset: gain_load_preset,
+        last_error_code: gain_last_error_code,
+        last_error_message: gain_last_error_message_ptr,
+    });
+
+    /// A generator: counts frames; a note restarts the count from its number.
+    pub struct Ramp {
+        frames: u32,
+        events: ParamRing,
+        notes: EventRing,
+        error: LastError,
+    }
+
+    impl Node for Ramp {
+        const MAX_CHANNELS: usize = 2;
+        const PARAMS: &'static [ParamDescriptor] = &[];
+        const INPUT: bool = false;
+
+        fn new(_sample_rate_hz: f32) -> Self {
+            Self {
+                frames: 0,
+                events: ParamRing::new(),
+                notes: EventRing::new(),
+                error: LastError::new(),
+            }
+        }
+
+        fn set_param(&mut self, _index: u32, _value: f32) {}
+
+        fn get_param(&self, _index: u32) -> f32 {
+            0.0
+        }
+
+        fn param_events(&mut self) -> &mut ParamRing {
+            &mut self.events
+        }
+
+        fn last_error(&mut self) -> &mut LastError {
+            &mut self.error
+        }
+
+        fn note_events(&mut self) -> Option<&mut EventRing> {
+            Some(&mut self.notes)
+        }
+
+        fn note(&mut self, event: NoteEvent) {
+            self.frames = event.note;
+        }
+
+        fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+            assert!(input.is_empty());
+            for frame in output.chunks_exact_mut(channels) {
+                frame.fill(self.frames as f32);
+                self.frames += 1;
+            }
+        }
+    }
+
+    export_node!(Ramp {
+        abi_version: ramp_abi_version,
+        new: ramp_new,
+        free: ramp_free,
+        param_count: ramp_param_count,
+        param_descriptors: ramp_param_descriptors,
+        set_param: ramp_set_param,
+        get_param: ramp_get_param,
+        param_events: ramp_param_events,
+        process: ramp_process,
+        process_offline: ramp_process_offline,
+        latency_samples: ramp_latency_samples,
+        io_layout: ramp_get_io_layout,
+        process_io: ramp_process_io,
+        reset: ramp_reset,
+        state_size: ramp_state_size,
+        save_state: ramp_save_state,
+        load_state: ramp_load_state,
+        preset_size: ramp_preset_size,
+        save_preset: ramp_save_preset,
+        load_preset: ramp_load_preset,
+        last_error_code: ramp_last_error_code,
+        last_error_message: ramp_last_error_message_ptr,
+    });
+
+    /// A meter with a ring modulator's key input: reads the peak of the input times the aux
+    /// bus (the input alone without one), writes nothing.
+    pub struct Peak {
+        peak: f32,
+        events: ParamRing,
+        error: LastError,
+    }
+
+    impl Node for Peak {
+        const MAX_CHANNELS: usize = 2;
+        const PARAMS: &'static [ParamDescriptor] = &[];
+        const OUTPUT: bool = false;
+        const AUX_CHANNELS: usize = 2;
+        const METER_COUNT: usize = 1;
+
+        fn new(_sample_rate_hz: f32) -> Self {
+            Self {
+                peak: 0.0,
+                events: ParamRing::new(),
+                error: LastError::new(),
+            }
+        }
+
+        fn set_param(&mut self, _index: u32, _value: f32) {}
+
+        fn get_param(&self, _index: u32) -> f32 {
+            0.0
+        }
+
+        fn param_events(&mut self) -> &mut ParamRing {
+            &mut self.events
+        }
+
+        fn last_error(&mut self) -> &mut LastError {
+            &mut self.error
+        }
+
+        fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+            self.process_aux(input, &[], 0, output, channels);
+        }
+
+        fn process_aux(
+            &mut self,
+            input: &[f32],
+            aux: &[f32],
+            aux_channels: usize,
+            output: &mut [f32],
+            channels: usize,
+        ) {
+            assert!(output.is_empty());
+            for (i, x) in input.iter().enumerate() {
+                let key = if aux.is_empty() {
+                    1.0
+                } else {
+                    aux[i / channels * aux_channels + i % channels % aux_channels]
+                };
+                self.peak = self.peak.max((x * key).abs());
+            }
+        }
+
+        fn meters(&self, out: &mut [f32]) {
+            out[0] = self.peak;
+        }
+    }
+
+    export_node!(Peak {
+        abi_version: peak_abi_version,
+        new: peak_new,
+        free: peak_free,
+        param_count: peak_param_count,
+        param_descriptors: peak_param_descriptors,
+        set_param: peak_set_param,
+        get_param: peak_get_param,
+        param_events: peak_param_events,
+        process: peak_process,
+        process_offline: peak_process_offline,
+        latency_samples: peak_latency_samples,
+        io_layout: peak_get_io_layout,
+        process_io: peak_process_io,
+        reset: peak_reset,
+        state_size: peak_state_size,
+        save_state: peak_save_state,
+        load_state: peak_load_state,
+        preset_size: peak_preset_size,
+        save_preset: peak_save_preset,
+        load_preset: peak_load_preset,
+        last_error_code: peak_last_error_code,
+        last_error_message: peak_last_error_message_ptr,
+        process_aux: peak_process_keyed,
+        process_aux_offline: peak_process_offline_keyed,
+        process_io_aux: peak_process_io_keyed,
+    });
+
+    crate::test_params!(Peak);
+    crate::test_io_block!(Peak);
+
+    mod ramp_io {
+        use super::*;
+        crate::test_io_block!(Ramp, setup: |r: &mut Ramp| {
+            r.notes.push(NoteEvent {
+                kind: dsp_core::events::NOTE_ON,
+                note: 10,
+                value: 1.0,
+                frame: 300,
+            });
+        });
+    }
+
+    #[test]
+    fn exports_drive_the_node() {
+        assert_eq!(gain_abi_version(), ABI_VERSION);
+        let handle = gain_new(48_000.0);
+        gain_set_param(handle, 0, 0.5);
+        gain_set_param(handle, 1, 12.0);
+        gain_set_param(handle, 7, 3.0);
+        let input = [1.0, -1.0, 0.5, 0.25, 2.0, 4.0];
+        let mut output = [9.0; 6];
+        // Four channels clamp to the node's two, so three frames fill all six samples.
+        gain_process(handle, input.as_ptr(), output.as_mut_ptr(), 3, 4);
+        assert_eq!(output, [0.5, -0.5, 0.25, 0.125, 1.0, 2.0]);
+        assert_eq!(gain_latency_samples(handle), 12);
+        assert_eq!(
+            (gain_get_param(handle, 0), gain_get_param(handle, 1)),
+            (0.5, 12.0)
+        );
+        gain_free(handle);
+
+        // Freed, reissued under a new generation, null and made up: none reach a node.
+        let reissued = gain_new(48_000.0);
+        assert_ne!(reissued, handle);
+        for stale in [handle, NULL_HANDLE, 0xdead_beef] {
+            gain_set_param(stale, 0, 2.0);
+            gain_process(stale, input.as_ptr(), output.as_mut_ptr(), 3, 2);
+            assert_eq!(output, [0.0; 6]);
+            assert_eq!(gain_latency_samples(stale), 0);
+            assert!(gain_param_events(stale).is_null());
+            assert_eq!(gain_last_error_code(stale), ERROR_NONE);
+            gain_free(stale);
+        }
+        assert_eq!(gain_get_param(reissued, 0), 1.0);
+        gain_free(reissued);
+    }
+
+    #[test]
+    fn events_land_on_their_frames() {
+        let handle = gain_new(48_000.0);
+        let ring = unsafe { &mut *gain_param_events(handle) };
+        let event = |value, frame| ParamEvent {
+            index: 0,
+            value,
+            frame,
+        };
+        // Two on one frame (the later wins), one past the block.
+        for e in [event(2.0, 0), event(0.5, 3), event(0.25, 3), event(4.0, 99)] {
+            assert!(ring.push(e));
+        }
+        let input = [1.0; 8];
+        let mut output = [0.0; 8];
+        gain_process(handle, input.as_ptr(), output.as_mut_ptr(), 8, 1);
+        assert_eq!(output, [2.0, 2.0, 2.0, 0.25, 0.25, 0.25, 0.25, 4.0]);
+        assert_eq!(
+            unsafe { &(*registry::lookup::<Gain>(handle).unwrap()).node.calls },
+            &[3, 4, 1]
+        );
+        assert_eq!(ring.peek(), None);
+        gain_free(handle);
+    }
+
+    #[test]
+    fn io_block_processes_without_pointers() {
+        let handle = gain_new(48_000.0);
+        gain_process_io(handle, 4);
+        assert!(gain_get_io_layout(handle, 0, 2).is_null());
+        let ptr = gain_get_io_layout(handle, 4, 2);
+        let layout = unsafe { *ptr };
+        assert_eq!(
+            (layout.channels, layout.param_count, layout.meter_count),
+            (2, 2, 1)
+        );
+        let base = ptr as *mut f32;
+        let region = |offset: u32, len: usize| unsafe {
+            core::slice::from_raw_parts_mut(base.add(offset as usize / 4), len)
+        };
+        assert_eq!(region(layout.params_offset, 2), [1.0, 0.0]);
+
+        region(layout.input_offset, 8).copy_from_slice(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+        region(layout.params_offset, 1)[0] = 0.5;
+        gain_process_io(handle, 3);
+        assert_eq!(
+            region(layout.output_offset, 8),
+            [0.5, 1.0, 1.5, 0.0, 2.5, 3.0, 3.5, 0.0]
+        );
+        assert_eq!(gain_get_param(handle, 0), 0.5);
+        assert_eq!(region(layout.meters_offset, 1), [1.0]);
+
+        gain_set_param(handle, 2, 0.0);
+        gain_process_io(handle, 3);
+        assert_eq!(region(layout.output_offset, 8), [0.0; 8]);
+        gain_free(handle);
+    }
+
+    #[test]
+    fn generators_need_no_input() {
+        let handle = ramp_new(48_000.0);
+        let mut output = [9.0; 6];
+        ramp_process(handle, core::ptr::null(), output.as_mut_ptr(), 3, 2);
+        assert_eq!(output, [0.0, 0.0, 1.0, 1.0, 2.0, 2.0]);
+        ramp_process(handle, core::ptr::null(), core::ptr::null_mut(), 3, 2);
+
+        let ptr = ramp_get_io_layout(handle, 4, 1);
+        let layout = unsafe { *ptr };
+        assert_eq!(
+            (
+                layout.channels,
+                layout.input_channels,
+                layout.output_channels
+            ),
+            (1, 0, 1)
+        );
+        assert_eq!(layout.input_offset, layout.output_offset);
+        ramp_process_io(handle, 2);
+        let out = unsafe {
+            core::slice::from_raw_parts(
+                (ptr as *const f32).add(layout.output_offset as usize / 4),
+                2,
+            )
+        };
+        assert_eq!(out, [3.0, 4.0]);
+        ramp_free(handle);
+    }
+
+    #[test]
+    fn notes_land_on_their_frames() {
+        let handle = ramp_new(48_000.0);
+        let note = |note, frame| NoteEvent {
+            kind: dsp_core::events::NOTE_ON,
+            note,
+            value: 1.0,
+            frame,
+        };
+        guard(handle, |r: &mut Ramp| {
+            r.notes.push(note(10, 2));
+            r.notes.push(note(20, 9));
+        });
+        let mut output = [0.0; 4];
+        ramp_process(handle, core::ptr::null(), output.as_mut_ptr(), 4, 1);
+        assert_eq!(output, [0.0, 1.0, 10.0, 20.0]);
+        ramp_free(handle);
+    }
+
+    #[test]
+    fn meters_leave_the_output_alone() {
+        let handle = peak_new(48_000.0);
+        let node = || unsafe { &(*registry::lookup::<Peak>(handle).unwrap()).node };
+        let input = [0.5, -0.25, 0.125, 0.75];
+        peak_process(handle, input.as_ptr(), core::ptr::null_mut(), 2, 2);
+        assert_eq!(node().peak, 0.75);
+        peak_process(handle, core::ptr::null(), core::ptr::null_mut(), 2, 2);
+        assert_eq!(node().peak, 0.75);
+
+        let layout = unsafe { *peak_get_io_layout(handle, 4, 2) };
+        assert_eq!((layout.input_channels, layout.output_channels), (2, 0));
+        assert_eq!(layout.output_offset, layout.aux_offset);
+        peak_free(handle);
+    }
+
+    #[test]
+    fn aux_bus_reaches_process_aux() {
+        let handle = peak_new(48_000.0);
+        let node = || unsafe { &mut (*registry::lookup::<Peak>(handle).unwrap()).node };
+        let input = [0.5, 0.5, 0.5, 0.5];
+        // A mono key, reused for both channels; a null key is no key.
+        let key = [4.0, 1.0];
+        peak_process_keyed(
+            handle,
+            input.as_ptr(),
+            key.as_ptr(),
+            core::ptr::null_mut(),
+            2,
+            2,
+            1,
+        );
+        assert_eq!(node().peak, 2.0);
+        node().peak = 0.0;
+        peak_process_offline_keyed(
+            handle,
+            input.as_ptr(),
+            core::ptr::null(),
+            core::ptr::null_mut(),
+            2,
+            2,
+            1,
+        );
+        assert_eq!(node().peak, 0.5);
+
+        node().peak = 0.0;
+        let ptr = peak_get_io_layout(handle, 2, 2);
+        let layout = unsafe { *ptr };
+        assert_eq!(layout.aux_channels, 2);
+        let base = ptr as *mut f32;
+        let region = |offset: u32, len: usize| unsafe {
+            core::slice::from_raw_parts_mut(base.add(offset as usize / 4), len)
+        };
+        region(layout.input_offset, 4).copy_from_slice(&input);
+        region(layout.aux_offset, 4).copy_from_slice(&[1.0, 1.0, 3.0, 1.0]);
+        peak_process_io(handle, 2);
+        assert_eq!(region(layout.meters_offset, 1), [0.5]);
+        // The key's first plane only, read as a mono key.
+        peak_process_io_keyed(handle, 2, 1);
+        assert_eq!(region(layout.meters_offset, 1), [0.5]);
+        region(layout.aux_offset, 4)[1] = 2.0;
+        peak_process_io_keyed(handle, 2, 2);
+        assert_eq!(region(layout.meters_offset, 1), [1.5]);
+        peak_free(handle);
+    }
+
+    #[test]
+    fn offline_renders_in_quanta_with_absolute_event_frames() {
+        let handle = gain_new(48_000.0);
+        let ring = unsafe { &mut *gain_param_events(handle) };
+        assert!(ring.push(ParamEvent {
+            index: 0,
+            value: 0.5,
+            frame: 300,
+        }));
+        let input = vec![1.0; 400];
+        let mut output = vec![0.0; 400];
+        gain_process_offline(handle, input.as_ptr(), output.as_mut_ptr(), 400, 1);
+        assert!(output[..300].iter().all(|&y| y == 1.0));
+        assert!(output[300..].iter().all(|&y| y == 0.5));
+        let calls = unsafe { &(*registry::lookup::<Gain>(handle).unwrap()).node.calls };
+        assert_eq!(calls, &[128, 128, 44, 100]);
+        gain_free(handle);
+    }
+
+    #[test]
+    fn snapshots_restore_params_and_running_state() {
+        let handle = gain_new(48_000.0);
+        gain_set_param(handle, 0, 0.5);
+        gain_set_param(handle, 1, 12.0);
+        let input = [1.0; 10];
+        let mut output = [0.0; 10];
+        gain_process(handle, input.as_ptr(), output.as_mut_ptr(), 5, 2);
+        let size = gain_state_size(handle);
+        let mut blob = vec![0; size as usize];
+        assert_eq!(gain_save_state(handle, blob.as_mut_ptr(), size - 1), 0);
+        assert_eq!(gain_save_state(handle, blob.as_mut_ptr(), size), size);
+        gain_free(handle);
+
+        let restored = gain_new(48_000.0);
+        let load = |blob: &[u8]| gain_load_state(restored, blob.as_ptr(), blob.len() as u32);
+        assert_eq!(load(&blob[..blob.len() - 1]), STATE_PARAMS);
+        assert_eq!(load(&blob), STATE_FULL);
+        assert_eq!(
+            (gain_get_param(restored, 0), gain_latency_samples(restored)),
+            (0.5, 12)
+        );
+        let node = unsafe { &(*registry::lookup::<Gain>(restored).unwrap()).node };
+        assert_eq!(node.frames, 5);
+
+        let mut foreign = blob.clone();
+        foreign[4] = 9;
+        gain_set_param(restored, 0, 2.0);
+        assert_eq!(load(&foreign), STATE_REJECTED);
+        assert_eq!(load(&blob[..12]), STATE_REJECTED);
+        assert_eq!(gain_get_param(restored, 0), 2.0);
+        assert_eq!(gain_state_size(NULL_HANDLE), 0);
+        assert_eq!(gain_load_state(handle, blob.as_ptr(), size), STATE_REJECTED);
+        gain_free(restored);
+    }
+
+    #[test]
+    fn reset_clears_running_state_but_keeps_params_and_events() {
+        let handle = gain_new(48_000.0);
+        gain_set_param(handle, 0, 0.5);
+        let input = [1.0; 4];
+        let mut output = [0.0; 4];
+        gain_process(handle, input.as_ptr(), output.as_mut_ptr(), 4, 1);
+        let ring = unsafe { &mut *gain_param_events(handle) };
+        assert!(ring.push(ParamEvent {
+            index: 0,
+            value: 2.0,
+            frame: 2,
+        }));
+        gain_reset(handle);
+        let node = unsafe { &(*registry::lookup::<Gain>(handle).unwrap()).node };
+        assert_eq!((node.frames, gain_get_param(handle, 0)), (0, 0.5));
+        gain_process(handle, input.as_ptr(), output.as_mut_ptr(), 4, 1);
+        assert_eq!(output, [0.5, 0.5, 2.0, 2.0]);
+        gain_reset(NULL_HANDLE);
+        gain_free(handle);
+    }
+
+    #[test]
+    fn presets_apply_by_name_after_migrating() {
+        let handle = gain_new(48_000.0);
+        gain_set_param(handle, 0, 0.5);
+        gain_set_param(handle, 1, 12.0);
+        let size = gain_preset_size(handle);
+        let mut blob = vec![0; size as usize];
+        assert_eq!(gain_save_preset(handle, blob.as_mut_ptr(), size - 1), 0);
+        assert_eq!(gain_save_preset(handle, blob.as_mut_ptr(), size), size);
+        let preset = Preset::decode(&blob).unwrap();
+        assert_eq!(preset.version, 1);
+        assert_eq!(
+            preset.iter().collect::<Vec<_>>(),
+            [("Gain", 0.5), ("Delay", 12.0)]
+        );
+        gain_free(handle);
+
+        let restored = gain_new(48_000.0);
+        let load = |preset: &Preset| {
+            let blob = preset.encode();
+            gain_load_preset(restored, blob.as_ptr(), blob.len() as u32)
+        };
+        // Version 0 called the gain "Level" and had no delay, which goes to its default.
+        let mut old = Preset::new(0);
+        old.set("Level", 0.25);
+        old.set("Pan", 0.5);
+        gain_set_param(restored, 1, 5.0);
+        assert_eq!(load(&old), 1);
+        assert_eq!(
+            (gain_get_param(restored, 0), gain_get_param(restored, 1)),
+            (0.25, 0.0)
+        );
+
+        let mut newer = Preset::new(2);
+        newer.set("Gain", 2.0);
+        assert_eq!(load(&newer), 0);
+        assert_eq!(gain_load_preset(restored, blob.as_ptr(), size - 1), 0);
+        assert_eq!(gain_get_param(restored, 0), 0.25);
+        assert_eq!(gain_load_preset(restored, blob.as_ptr(), size), 1);
+        assert_eq!(gain_get_param(restored, 1), 12.0);
+        gain_free(restored);
+    }
+
+    #[test]
+    fn panics_mute_the_node() {
+        let handle = gain_new(48_000.0);
+        gain_set_param(handle, 0, 0.5);
+        assert_eq!(gain_last_error_code(handle), ERROR_NONE);
+        gain_set_param(handle, 2, 1.0);
+        assert_eq!(gain_last_error_code(handle), ERROR_PANIC);
+        let message =
+            unsafe { std::ffi::CStr::from_ptr(gain_last_error_message_ptr(handle).cast()) };
+        let message = message.to_str().unwrap();
+        assert!(message.starts_with("gain exploded at "), "{message}");
+
+        let input = [1.0; 4];
+        let mut output = [9.0; 4];
+        gain_process(handle, input.as_ptr(), output.as_mut_ptr(), 2, 2);
+        assert_eq!(output, [0.0; 4]);
+        assert_eq!(gain_get_param(handle, 0), 0.0);
+        assert!(unsafe { &(*registry::lookup::<Gain>(handle).unwrap()).node.calls }.is_empty());
+        gain_free(handle);
+    }
+
+    #[test]
+    fn error_messages_truncate_on_char_boundaries() {
+        let mut e = LastError::new();
+        e.set(ERROR_PANIC, &"é".repeat(100));
+        assert_eq!(e.message(), "é".repeat(63));
+    }
+
+    #[test]
+    fn descriptor_table_reads_back() {
+        assert_eq!(core::mem::size_of::<ParamDescriptor>(), 64);
+        assert_eq!(gain_param_count(), 2);
+        let table = unsafe { core::slice::from_raw_parts(gain_param_descriptors(), 2) };
+        assert_eq!((table[0].name(), table[0].unit()), ("Gain", ""));
+        assert_eq!((table[0].default, table[0].scale), (1.0, SCALE_LINEAR));
+        assert_eq!((table[1].name(), table[1].unit()), ("Delay", "samples"));
+        assert_eq!(table[1].flags, FLAG_STEPPED | FLAG_NO_AUTOMATION);
+        let toggle = ParamDescriptor::toggle("Bypass", true);
+        assert_eq!(
+            (toggle.max, toggle.default, toggle.flags & FLAG_TOGGLE),
+            (1.0, 1.0, FLAG_TOGGLE)
+        );
+    }
+
+    #[cfg(feature = "bindgen")]
+    bindgen_node!(Gain as GainNode);
+
+    #[cfg(feature = "bindgen")]
+    #[test]
+    fn bindgen_wrapper_matches_the_exports() {
+        let params = GainNode::params();
+        assert_eq!(params.len(), 2);
+        assert_eq!(
+            (params[1].name(), params[1].unit()),
+            ("Delay".into(), "samples".into())
+        );
+        assert_eq!(params[0].default_value(), 1.0);
+
+        let mut node = GainNode::new(48_000.0);
+        node.set_param(1, 12.0);
+        assert!(node.queue_param(0, 0.5, 2));
+        let output = node.render(&[1.0; 8], 2);
+        assert_eq!(output, [1.0, 1.0, 1.0, 1.0, 0.5, 0.5, 0.5, 0.5]);
+        assert_eq!((node.get_param(0), node.latency_samples()), (0.5, 12));
+        node.reset();
+        assert_eq!(node.get_param(0), 0.5);
+    }
+}
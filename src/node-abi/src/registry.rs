@@ -0,0 +1,192 @@
+//! Opaque instance handles. JS never holds a node's address: `p_new` returns a `Handle`, and
+//! every export looks it up in the node type's `Registry` before touching the node. A handle
+//! packs the slot index with the slot's generation, which moves on each time the slot is
+//! freed, so a stale handle (used after `p_free`), a double free or a made-up number finds
+//! nothing and the export falls back as it would for null.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ptr;
+
+use crate::io::IoBuffers;
+
+/// `generation << 16 | (slot + 1)`; 0 is never issued, and stands in for null.
+pub type Handle = u32;
+
+pub const NULL_HANDLE: Handle = 0;
+
+const SLOT_BITS: u32 = 16;
+const SLOT_MASK: u32 = (1 << SLOT_BITS) - 1;
+/// Live instances of one node type; slot numbers start at 1 in a handle.
+const MAX_SLOTS: usize = SLOT_MASK as usize;
+
+/// What a handle names: the node and the I/O block the loader negotiated for it, side by side
+/// so an export can hold both at once.
+pub struct Instance<N> {
+    pub node: N,
+    pub io: IoBuffers,
+}
+
+struct Slot<N> {
+    /// Wraps after 65536 reuses of the slot, so a handle that old could alias again.
+    generation: u16,
+    instance: *mut Instance<N>,
+}
+
+/// The live instances of one node type.
+pub struct Registry<N> {
+    slots: Vec<Slot<N>>,
+    free: Vec<usize>,
+}
+
+impl<N> Default for Registry<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N> Registry<N> {
+    pub const fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Takes ownership of `node`; `NULL_HANDLE` (and `node` dropped) once every slot is taken.
+    pub fn insert(&mut self, node: N) -> Handle {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None if self.slots.len() < MAX_SLOTS => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    instance: ptr::null_mut(),
+                });
+                self.slots.len() - 1
+            }
+            None => return NULL_HANDLE,
+        };
+        let slot = &mut self.slots[index];
+        slot.instance = Box::into_raw(Box::new(Instance {
+            node,
+            io: IoBuffers::new(),
+        }));
+        (u32::from(slot.generation) << SLOT_BITS) | (index as u32 + 1)
+    }
+
+    /// The instance `handle` names, if it is still the one in its slot. The pointer stays valid
+    /// until the handle is removed.
+    pub fn get(&self, handle: Handle) -> Option<*mut Instance<N>> {
+        let index = (handle & SLOT_MASK) as usize;
+        let slot = self.slots.get(index.checked_sub(1)?)?;
+        let current = u32::from(slot.generation) == handle >> SLOT_BITS;
+        (current && !slot.instance.is_null()).then_some(slot.instance)
+    }
+
+    /// Hands the instance back and retires `handle`.
+    pub fn remove(&mut self, handle: Handle) -> Option<Box<Instance<N>>> {
+        let instance = self.get(handle)?;
+        let index = (handle & SLOT_MASK) as usize - 1;
+        let slot = &mut self.slots[index];
+        slot.instance = ptr::null_mut();
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(index);
+        Some(unsafe { Box::from_raw(instance) })
+    }
+}
+
+impl<N> Drop for Registry<N> {
+    fn drop(&mut self) {
+        for slot in &self.slots {
+            if !slot.instance.is_null() {
+                drop(unsafe { Box::from_raw(slot.instance) });
+            }
+        }
+    }
+}
+
+/// A `static` holding `T` in a `RefCell`, read through `with` as a `thread_local!` is: one per
+/// thread with `std` (the host tests run in parallel), a plain static without it, where the
+/// only target is single-threaded wasm32.
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! registry_static {
+    ($name:ident: $t:ty) => {
+        ::std::thread_local! {
+            static $name: core::cell::RefCell<$t> = const { core::cell::RefCell::new(<$t>::new()) };
+        }
+    };
+}
+
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! registry_static {
+    ($name:ident: $t:ty) => {
+        static $name: $crate::registry::SingleThread<$t> =
+            $crate::registry::SingleThread(core::cell::RefCell::new(<$t>::new()));
+    };
+}
+
+/// What `registry_static!` declares without `std`.
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+pub struct SingleThread<T>(pub core::cell::RefCell<T>);
+
+// wasm32 without threads: nothing else can see the static.
+#[cfg(not(feature = "std"))]
+unsafe impl<T> Sync for SingleThread<T> {}
+
+#[cfg(not(feature = "std"))]
+impl<T> SingleThread<T> {
+    pub fn with<R>(&'static self, f: impl FnOnce(&core::cell::RefCell<T>) -> R) -> R {
+        f(&self.0)
+    }
+}
+
+/// A node type with its own registry; `export_node!` implements it.
+pub trait Registered: crate::Node {
+    fn with_registry<R>(f: impl FnOnce(&mut Registry<Self>) -> R) -> R;
+}
+
+pub fn register<N: Registered>(node: N) -> Handle {
+    N::with_registry(|r| r.insert(node))
+}
+
+pub fn lookup<N: Registered>(handle: Handle) -> Option<*mut Instance<N>> {
+    N::with_registry(|r| r.get(handle))
+}
+
+pub fn unregister<N: Registered>(handle: Handle) -> Option<Box<Instance<N>>> {
+    N::with_registry(|r| r.remove(handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_and_made_up_handles_find_nothing() {
+        let mut r = Registry::new();
+        let a = r.insert(1);
+        let b = r.insert(2);
+        assert_ne!(a, NULL_HANDLE);
+        assert_eq!(r.get(a).map(|p| unsafe { (*p).node }), Some(1));
+        assert_eq!(r.remove(a).map(|i| i.node), Some(1));
+        assert!(r.get(a).is_none());
+        assert!(r.remove(a).is_none());
+
+        // The freed slot comes back under a new generation.
+        let c = r.insert(3);
+        assert_eq!(c & SLOT_MASK, a & SLOT_MASK);
+        assert_ne!(c, a);
+        assert!(r.get(a).is_none());
+        assert_eq!(r.get(c).map(|p| unsafe { (*p).node }), Some(3));
+        assert_eq!(r.get(b).map(|p| unsafe { (*p).node }), Some(2));
+
+        for junk in [NULL_HANDLE, 0xffff_ffff, 3, b + (1 << SLOT_BITS)] {
+            assert!(r.get(junk).is_none(), "{junk:#x}");
+        }
+    }
+}
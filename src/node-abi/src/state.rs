@@ -0,0 +1,96 @@
+//! Snapshots of a node instance, for session save/load and for carrying a node across a
+//! worklet restart. A snapshot is a byte blob the loader stores as it is:
+//!
+//! - `STATE_MAGIC` and `STATE_VERSION` (u32 each)
+//! - the parameter count (u32) and every parameter's value (f32), in index order
+//! - the running state's length in bytes (u32) and the running state itself: whatever
+//!   `Node::save_state` writes (delay lines, envelopes, smoothers; see `dsp_core::state`)
+//!
+//! All little-endian. Loading applies the parameters through `Node::set_param` and then, if
+//! the running state is exactly as long as this instance's own (same shape, same capacities),
+//! restores that too; a snapshot from a node built differently (another sample rate,
+//! another oversampling factor) comes back as its settings alone.
+
+use dsp_core::state::{StateReader, StateWriter};
+
+use crate::Node;
+
+/// A snapshot's first four bytes, "NDST".
+pub const STATE_MAGIC: u32 = u32::from_le_bytes(*b"NDST");
+/// Bumped whenever the blob's layout changes.
+pub const STATE_VERSION: u32 = 1;
+
+/// `load_state` refused the blob: wrong magic or version, another parameter table, or cut short.
+pub const STATE_REJECTED: u32 = 0;
+/// Parameters restored; the running state didn't fit this instance and was skipped.
+pub const STATE_PARAMS: u32 = 1;
+/// Parameters and running state restored.
+pub const STATE_FULL: u32 = 2;
+
+fn write<N: Node>(node: &N, w: &mut StateWriter) {
+    w.u32(STATE_MAGIC);
+    w.u32(STATE_VERSION);
+    w.index(N::PARAMS.len());
+    for index in 0..N::PARAMS.len() as u32 {
+        w.f32(node.get_param(index));
+    }
+    w.index(runtime_size(node));
+    node.save_state(w);
+}
+
+fn runtime_size<N: Node>(node: &N) -> usize {
+    let mut w = StateWriter::sizing();
+    node.save_state(&mut w);
+    w.len()
+}
+
+/// Bytes `save_state` needs for `node` as it is now.
+pub fn state_size<N: Node>(node: &N) -> usize {
+    let mut w = StateWriter::sizing();
+    write(node, &mut w);
+    w.len()
+}
+
+/// Writes `node`'s snapshot to `dest`; the bytes written, or 0 if `dest` is too small.
+pub fn save_state<N: Node>(node: &N, dest: &mut [u8]) -> usize {
+    let mut w = StateWriter::new(dest);
+    write(node, &mut w);
+    if w.overflowed() {
+        0
+    } else {
+        w.len()
+    }
+}
+
+/// Restores `node` from a snapshot; `STATE_REJECTED`, `STATE_PARAMS` or `STATE_FULL`. A
+/// rejected blob leaves the node untouched. A running state that matches in length but
+/// doesn't read back (corrupt positions) can leave it partly restored, and reports
+/// `STATE_PARAMS`.
+pub fn load_state<N: Node>(node: &mut N, src: &[u8]) -> u32 {
+    let mut r = StateReader::new(src);
+    if r.u32() != STATE_MAGIC
+        || r.u32() != STATE_VERSION
+        || r.u32() as usize != N::PARAMS.len()
+        || r.remaining() < N::PARAMS.len() * 4
+    {
+        return STATE_REJECTED;
+    }
+    for index in 0..N::PARAMS.len() as u32 {
+        let value = r.f32();
+        node.set_param(index, value);
+    }
+    let len = r.u32() as usize;
+    if r.failed() || len != runtime_size(node) {
+        return STATE_PARAMS;
+    }
+    let Some(runtime) = r.take(len) else {
+        return STATE_PARAMS;
+    };
+    let mut r = StateReader::new(runtime);
+    node.load_state(&mut r);
+    if r.failed() || r.remaining() != 0 {
+        STATE_PARAMS
+    } else {
+        STATE_FULL
+    }
+}
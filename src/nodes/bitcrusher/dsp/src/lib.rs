@@ -0,0 +1,295 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Bitcrusher::PARAMS`, in the order of `bitcrusher_set_params`' arguments.
+pub const PARAM_BITS: u32 = 0;
+pub const PARAM_DITHER: u32 = 1;
+pub const PARAM_RATE_HZ: u32 = 2;
+pub const PARAM_JITTER: u32 = 3;
+pub const PARAM_MIX: u32 = 4;
+
+const SEED: u32 = 0x9e37_79b9;
+
+/// Lo-fi processor: sample-and-hold down to `rate_hz` (the hold instants are shared by all
+/// channels and can be jittered), then quantisation to `bits`, optionally with TPDF dither.
+#[repr(C)]
+pub struct Bitcrusher {
+    bits: f32,
+    /// Quantiser step (2 / 2^bits).
+    step: f32,
+    dither: bool,
+    rate_hz: f32,
+    jitter: f32,
+    mix: f32,
+    /// Progress towards the next hold instant, in held samples.
+    phase: f32,
+    /// Length of the current hold period in held samples (1 plus jitter).
+    period: f32,
+    held: [f32; MAX_CHANNELS],
+    rng: u32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Bitcrusher {
+    /// Uniform in [0, 1) from a xorshift32.
+    #[inline]
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    #[inline]
+    fn quantize(&mut self, x: f32) -> f32 {
+        let noise = if self.dither {
+            (self.random() - self.random()) * self.step
+        } else {
+            0.0
+        };
+        ((x + noise) / self.step).round() * self.step
+    }
+}
+
+impl Node for Bitcrusher {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Bits` 1–24 (fractional values give in-between step sizes); `Dither` adds TPDF noise of
+    /// one step before rounding; `Rate` is the hold rate (at or above the sample rate nothing
+    /// is held); `Jitter` 0–1 randomises each hold period by up to ±50%.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Bits", "bits", 1.0, 24.0, 8.0),
+        ParamDescriptor::toggle("Dither", false),
+        ParamDescriptor::log("Rate", "Hz", 100.0, 192000.0, 192000.0),
+        ParamDescriptor::linear("Jitter", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Mix", "", 0.0, 1.0, 1.0),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        Bitcrusher {
+            bits: 8.0,
+            step: 2.0 / 256.0,
+            dither: false,
+            rate_hz: 192000.0,
+            jitter: 0.0,
+            mix: 1.0,
+            phase: 0.0,
+            period: 1.0,
+            held: [0.0; MAX_CHANNELS],
+            rng: SEED,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_BITS => {
+                self.bits = clamp(value, 1.0, 24.0);
+                self.step = 2.0 / self.bits.exp2();
+            }
+            PARAM_DITHER => self.dither = clamp(value, 0.0, 1.0) >= 0.5,
+            PARAM_RATE_HZ => self.rate_hz = clamp(value, 100.0, 192000.0),
+            PARAM_JITTER => self.jitter = clamp(value, 0.0, 1.0),
+            PARAM_MIX => self.mix = clamp(value, 0.0, 1.0),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_BITS => self.bits,
+            PARAM_DITHER => u32::from(self.dither) as f32,
+            PARAM_RATE_HZ => self.rate_hz,
+            PARAM_JITTER => self.jitter,
+            PARAM_MIX => self.mix,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let advance = (self.rate_hz / self.sample_rate_hz).min(1.0);
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            self.phase += advance;
+            let sample = self.phase >= self.period;
+            if sample {
+                self.phase -= self.period;
+                self.period = 1.0 + self.jitter * (self.random() - 0.5);
+            }
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                if sample {
+                    self.held[ch] = self.quantize(*x);
+                }
+                *y = *x * (1.0 - self.mix) + self.held[ch] * self.mix;
+            }
+        }
+    }
+
+    /// Drops the held samples and restarts the hold clock and the jitter sequence.
+    fn reset(&mut self) {
+        self.phase = 0.0;
+        self.period = 1.0;
+        self.held = [0.0; MAX_CHANNELS];
+        self.rng = SEED;
+    }
+}
+
+node_abi::export_node!(Bitcrusher {
+    abi_version: bitcrusher_abi_version,
+    new: bitcrusher_new,
+    free: bitcrusher_free,
+    param_count: bitcrusher_param_count,
+    param_descriptors: bitcrusher_param_descriptors,
+    set_param: bitcrusher_set_param,
+    get_param: bitcrusher_get_param,
+    param_events: bitcrusher_param_events,
+    process: bitcrusher_process,
+    process_offline: bitcrusher_process_offline,
+    latency_samples: bitcrusher_latency_samples,
+    io_layout: bitcrusher_get_io_layout,
+    process_io: bitcrusher_process_io,
+    reset: bitcrusher_reset,
+    state_size: bitcrusher_state_size,
+    save_state: bitcrusher_save_state,
+    load_state: bitcrusher_load_state,
+    preset_size: bitcrusher_preset_size,
+    save_preset: bitcrusher_save_preset,
+    load_preset: bitcrusher_load_preset,
+    last_error_code: bitcrusher_last_error_code,
+    last_error_message: bitcrusher_last_error_message_ptr,
+});
+
+node_abi::test_params!(Bitcrusher);
+
+dsp_core::test_block_sizes!(
+    bitcrusher_new,
+    bitcrusher_process(input, output),
+    bitcrusher_free
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Bitcrusher as BitcrusherNode);
+
+/// Every parameter in one call, as `bitcrusher_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn bitcrusher_set_params(
+    handle: Handle,
+    bits: f32,
+    dither: u32,
+    rate_hz: f32,
+    jitter: f32,
+    mix: f32,
+) {
+    guard(handle, |b: &mut Bitcrusher| {
+        b.set_param(PARAM_BITS, bits);
+        b.set_param(PARAM_DITHER, dither as f32);
+        b.set_param(PARAM_RATE_HZ, rate_hz);
+        b.set_param(PARAM_JITTER, jitter);
+        b.set_param(PARAM_MIX, mix);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    fn bitcrusher(params: &[(u32, f32)]) -> Bitcrusher {
+        let mut b = Bitcrusher::new(RATE);
+        for &(index, value) in params {
+            b.set_param(index, value);
+        }
+        b
+    }
+
+    fn render(b: &mut Bitcrusher, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(b, &input[span], y, 1))
+    }
+
+    /// Lengths of the runs of equal samples in `y`, the first and last (cut off) dropped.
+    fn runs(y: &[f32]) -> Vec<usize> {
+        let mut runs = vec![1];
+        for w in y.windows(2) {
+            if w[0] == w[1] {
+                *runs.last_mut().unwrap() += 1;
+            } else {
+                runs.push(1);
+            }
+        }
+        runs[1..runs.len() - 1].to_vec()
+    }
+
+    /// `Bits` rounds to steps of 2 / 2^bits; dither trades the rounding's bias for noise, so
+    /// a level between steps survives on average.
+    #[test]
+    fn bits_quantise_and_dither_unbiases() {
+        let ramp: Vec<f32> = (0..4800).map(|i| i as f32 / 2400.0 - 1.0).collect();
+        let y = render(&mut bitcrusher(&[(PARAM_BITS, 3.0)]), &ramp);
+        for (x, y) in ramp.iter().zip(&y) {
+            assert_eq!(y % 0.25, 0.0);
+            assert!((x - y).abs() <= 0.125);
+        }
+
+        let level = [0.1; 48_000];
+        let plain = render(&mut bitcrusher(&[(PARAM_BITS, 3.0)]), &level);
+        assert!(plain.iter().all(|&v| v == 0.0));
+        let dithered = render(
+            &mut bitcrusher(&[(PARAM_BITS, 3.0), (PARAM_DITHER, 1.0)]),
+            &level,
+        );
+        let mean = dithered.iter().sum::<f32>() / dithered.len() as f32;
+        assert!((mean - 0.1).abs() < 0.005, "{mean}");
+    }
+
+    /// `Rate` holds each sample for sample rate / rate samples; `Jitter` varies the holds
+    /// around that length.
+    #[test]
+    fn rate_holds_and_jitter_varies_the_holds() {
+        let ramp: Vec<f32> = (0..4800).map(|i| i as f32 / 4800.0).collect();
+        let held = |params: &[(u32, f32)]| {
+            let mut b =
+                bitcrusher(&[&[(PARAM_BITS, 24.0), (PARAM_RATE_HZ, 12_000.0)], params].concat());
+            runs(&render(&mut b, &ramp))
+        };
+        assert!(held(&[]).iter().all(|&n| n == 4));
+        let jittered = held(&[(PARAM_JITTER, 1.0)]);
+        assert!(jittered.iter().any(|&n| n != 4));
+        assert!(jittered.iter().all(|&n| (2..=6).contains(&n)));
+        let mean = jittered.iter().sum::<usize>() as f32 / jittered.len() as f32;
+        assert!((mean - 4.0).abs() < 0.1, "{mean}");
+    }
+
+    /// `Mix` blends the crushed signal with the dry one.
+    #[test]
+    fn mix_blends_with_the_dry_signal() {
+        let y = render(
+            &mut bitcrusher(&[(PARAM_BITS, 1.0), (PARAM_MIX, 0.5)]),
+            &[0.3, -0.8],
+        );
+        assert_eq!(y, [0.15, -0.9]);
+    }
+}
@@ -0,0 +1,293 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::f32::consts::TAU;
+use dsp_core::biquad::{Biquad, Response};
+use dsp_core::delay::DelayLine;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_VOICES: usize = 3;
+
+// Indices into `Chorus::PARAMS`, in the order of `chorus_set_params`' arguments.
+pub const PARAM_VOICES: u32 = 0;
+pub const PARAM_RATE_HZ: u32 = 1;
+pub const PARAM_DEPTH_MS: u32 = 2;
+pub const PARAM_SPREAD: u32 = 3;
+pub const PARAM_MIX: u32 = 4;
+pub const PARAM_VINTAGE: u32 = 5;
+
+/// Centre of the modulated taps.
+const BASE_DELAY_MS: f32 = 15.0;
+const MAX_DEPTH_MS: f32 = 10.0;
+/// Corner of the vintage-mode wet low-pass (a rough stand-in for a BBD's anti-alias filters).
+const VINTAGE_HZ: f32 = 6000.0;
+
+/// 2–3 delay taps per channel, each swept by the same sine LFO at evenly spaced phases, read
+/// with cubic interpolation. `spread` offsets the odd channels' LFO by up to a quarter cycle.
+#[repr(C)]
+pub struct Chorus {
+    voices: usize,
+    rate_hz: f32,
+    depth_ms: f32,
+    spread: f32,
+    mix: f32,
+    vintage: bool,
+    /// LFO position in cycles (0..1), carried across process calls.
+    phase: f32,
+    lines: Vec<DelayLine>,
+    wet_filters: [Biquad; MAX_CHANNELS],
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Node for Chorus {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Depth` is the sweep around a 15 ms centre; `Spread` the stereo LFO offset; `Vintage`
+    /// low-passes the wet path.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Voices", "", 2.0, MAX_VOICES as f32, 2.0),
+        ParamDescriptor::log("Rate", "Hz", 0.05, 8.0, 0.8),
+        ParamDescriptor::linear("Depth", "ms", 0.0, MAX_DEPTH_MS, 3.0),
+        ParamDescriptor::linear("Spread", "", 0.0, 1.0, 1.0),
+        ParamDescriptor::linear("Mix", "", 0.0, 1.0, 0.5),
+        ParamDescriptor::toggle("Vintage", false),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let max_delay = ((BASE_DELAY_MS + MAX_DEPTH_MS) / 1000.0 * sample_rate_hz) as usize + 2;
+        let mut wet_filter = Biquad::default();
+        wet_filter.set(Response::Lowpass, VINTAGE_HZ, 0.707, sample_rate_hz);
+        Chorus {
+            voices: 2,
+            rate_hz: 0.8,
+            depth_ms: 3.0,
+            spread: 1.0,
+            mix: 0.5,
+            vintage: false,
+            phase: 0.0,
+            lines: (0..MAX_CHANNELS)
+                .map(|_| DelayLine::new(max_delay))
+                .collect(),
+            wet_filters: [wet_filter; MAX_CHANNELS],
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_VOICES => self.voices = clamp(value, 2.0, MAX_VOICES as f32).round() as usize,
+            PARAM_RATE_HZ => self.rate_hz = clamp(value, 0.05, 8.0),
+            PARAM_DEPTH_MS => self.depth_ms = clamp(value, 0.0, MAX_DEPTH_MS),
+            PARAM_SPREAD => self.spread = clamp(value, 0.0, 1.0),
+            PARAM_MIX => self.mix = clamp(value, 0.0, 1.0),
+            PARAM_VINTAGE => {
+                let vintage = clamp(value, 0.0, 1.0) >= 0.5;
+                if vintage && !self.vintage {
+                    self.wet_filters.iter_mut().for_each(Biquad::clear);
+                }
+                self.vintage = vintage;
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_VOICES => self.voices as f32,
+            PARAM_RATE_HZ => self.rate_hz,
+            PARAM_DEPTH_MS => self.depth_ms,
+            PARAM_SPREAD => self.spread,
+            PARAM_MIX => self.mix,
+            PARAM_VINTAGE => u32::from(self.vintage) as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let ms = self.sample_rate_hz / 1000.0;
+        let step = self.rate_hz / self.sample_rate_hz;
+        let voices = self.voices;
+        let norm = 1.0 / voices as f32;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let line = &mut self.lines[ch];
+                line.push(*x);
+                let offset = if ch % 2 == 1 { self.spread * 0.25 } else { 0.0 };
+                let mut wet = 0.0;
+                for v in 0..voices {
+                    let phase = self.phase + offset + v as f32 * norm;
+                    let d = BASE_DELAY_MS + self.depth_ms * (TAU * phase).sin();
+                    wet += line.read_cubic(d * ms);
+                }
+                wet *= norm;
+                if self.vintage {
+                    wet = self.wet_filters[ch].process(wet);
+                }
+                *y = *x * (1.0 - self.mix) + wet * self.mix;
+            }
+            self.phase += step;
+            if self.phase >= 1.0 {
+                self.phase -= 1.0;
+            }
+        }
+    }
+
+    /// Empties the delay lines and the wet filters and restarts the LFO.
+    fn reset(&mut self) {
+        self.phase = 0.0;
+        for line in &mut self.lines {
+            line.clear();
+        }
+        for f in &mut self.wet_filters {
+            f.clear();
+        }
+    }
+}
+
+node_abi::export_node!(Chorus {
+    abi_version: chorus_abi_version,
+    new: chorus_new,
+    free: chorus_free,
+    param_count: chorus_param_count,
+    param_descriptors: chorus_param_descriptors,
+    set_param: chorus_set_param,
+    get_param: chorus_get_param,
+    param_events: chorus_param_events,
+    process: chorus_process,
+    process_offline: chorus_process_offline,
+    latency_samples: chorus_latency_samples,
+    io_layout: chorus_get_io_layout,
+    process_io: chorus_process_io,
+    reset: chorus_reset,
+    state_size: chorus_state_size,
+    save_state: chorus_save_state,
+    load_state: chorus_load_state,
+    preset_size: chorus_preset_size,
+    save_preset: chorus_save_preset,
+    load_preset: chorus_load_preset,
+    last_error_code: chorus_last_error_code,
+    last_error_message: chorus_last_error_message_ptr,
+});
+
+node_abi::test_params!(Chorus);
+
+dsp_core::test_block_sizes!(chorus_new, chorus_process(input, output), chorus_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Chorus as ChorusNode);
+
+/// Every parameter in one call, as `chorus_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn chorus_set_params(
+    handle: Handle,
+    voices: u32,
+    rate_hz: f32,
+    depth_ms: f32,
+    spread: f32,
+    mix: f32,
+    vintage: u32,
+) {
+    guard(handle, |c: &mut Chorus| {
+        c.set_param(PARAM_VOICES, voices as f32);
+        c.set_param(PARAM_RATE_HZ, rate_hz);
+        c.set_param(PARAM_DEPTH_MS, depth_ms);
+        c.set_param(PARAM_SPREAD, spread);
+        c.set_param(PARAM_MIX, mix);
+        c.set_param(PARAM_VINTAGE, vintage as f32);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// Fully wet.
+    fn chorus(params: &[(u32, f32)]) -> Chorus {
+        let mut c = Chorus::new(RATE);
+        c.set_param(PARAM_MIX, 1.0);
+        for &(index, value) in params {
+            c.set_param(index, value);
+        }
+        c
+    }
+
+    fn render(c: &mut Chorus, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(c, &input[span], y, 1))
+    }
+
+    fn impulse(at: usize, frames: usize) -> Vec<f32> {
+        let mut x = vec![0.0; frames];
+        x[at] = 1.0;
+        x
+    }
+
+    /// Loudest sample within `width` of `at`.
+    fn peak_near(y: &[f32], at: usize, width: usize) -> f32 {
+        y[at - width..=at + width]
+            .iter()
+            .fold(0.0_f32, |m, y| m.max(y.abs()))
+    }
+
+    /// Without depth every tap sits at the 15 ms centre.
+    #[test]
+    fn taps_centre_on_15_ms() {
+        let y = render(&mut chorus(&[(PARAM_DEPTH_MS, 0.0)]), &impulse(0, 2_000));
+        assert!((y[720] - 1.0).abs() < 1e-6, "{}", y[720]);
+        assert!(y
+            .iter()
+            .enumerate()
+            .all(|(i, y)| i == 720 || y.abs() < 1e-6));
+    }
+
+    /// A quarter of the way round a 1 Hz LFO, two voices half a cycle apart sit at the top and
+    /// bottom of a 5 ms sweep: the impulse comes back twice, near 20 and 10 ms.
+    #[test]
+    fn depth_sweeps_the_voices_apart() {
+        let mut c = chorus(&[(PARAM_RATE_HZ, 1.0), (PARAM_DEPTH_MS, 5.0)]);
+        let y = render(&mut c, &impulse(12_000, 14_000));
+        assert!(peak_near(&y, 12_000 + 960, 10) > 0.3);
+        assert!(peak_near(&y, 12_000 + 480, 10) > 0.3);
+        assert!(peak_near(&y, 12_000 + 720, 10) < 0.01);
+    }
+
+    /// `Vintage` low-passes the wet path: 15 kHz comes through much quieter.
+    #[test]
+    fn vintage_darkens_the_wet_path() {
+        let w = TAU * 15_000.0 / RATE;
+        let input: Vec<f32> = (0..9_600).map(|i| (w * i as f32).sin()).collect();
+        let peak = |vintage| {
+            let mut c = chorus(&[(PARAM_DEPTH_MS, 0.0), (PARAM_VINTAGE, vintage)]);
+            let y = render(&mut c, &input);
+            y[4_800..].iter().fold(0.0_f32, |m, y| m.max(y.abs()))
+        };
+        assert!(peak(0.0) > 0.9);
+        assert!(peak(1.0) < 0.2, "{}", peak(1.0));
+    }
+}
@@ -0,0 +1,288 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::f32::consts::FRAC_PI_2;
+
+use dsp_core::gain::db_to_lin;
+use dsp_core::oversample::{Resampler, RESAMPLER_LATENCY};
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Clipper::PARAMS`, in the order of `clipper_set_params`' arguments.
+pub const PARAM_MODE: u32 = 0;
+pub const PARAM_DRIVE_DB: u32 = 1;
+pub const PARAM_OUTPUT_DB: u32 = 2;
+pub const PARAM_OVERSAMPLE: u32 = 3;
+
+const OVERSAMPLE: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+enum Mode {
+    Hard,
+    /// Cubic `1.5x - 0.5x^3`, reaching full scale with zero slope at |x| = 1.
+    Soft,
+    /// `sin(x * pi / 2)`: saturates at full scale, then folds back down.
+    SineFold,
+}
+
+impl Mode {
+    fn from_u32(v: u32) -> Self {
+        match v {
+            1 => Self::Soft,
+            2 => Self::SineFold,
+            _ => Self::Hard,
+        }
+    }
+
+    #[inline]
+    fn shape(self, x: f32) -> f32 {
+        match self {
+            Self::Hard => x.clamp(-1.0, 1.0),
+            Self::Soft => {
+                let x = x.clamp(-1.0, 1.0);
+                1.5 * x - 0.5 * x * x * x
+            }
+            Self::SineFold => (x * FRAC_PI_2).sin(),
+        }
+    }
+}
+
+#[repr(C)]
+pub struct Clipper {
+    mode: Mode,
+    drive_db: f32,
+    drive: f32,
+    output_db: f32,
+    output: f32,
+    oversample: u32,
+    resamplers: Vec<Resampler>,
+    events: ParamRing,
+    error: LastError,
+}
+
+impl Node for Clipper {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Mode`: 0 hard, 1 cubic soft, 2 sine fold. `Drive` is applied before the curve,
+    /// `Output` after it. `Oversample` runs the curve at 4x to keep the harmonics it generates
+    /// from aliasing, at the cost of `clipper_latency_samples`.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Mode", "", 0.0, 2.0, 0.0),
+        ParamDescriptor::linear("Drive", "dB", 0.0, 36.0, 0.0),
+        ParamDescriptor::linear("Output", "dB", -36.0, 12.0, 0.0),
+        ParamDescriptor::toggle("Oversample", false).with_flags(node_abi::FLAG_NO_AUTOMATION),
+    ];
+
+    fn new(_sample_rate_hz: f32) -> Self {
+        Clipper {
+            mode: Mode::Hard,
+            drive_db: 0.0,
+            drive: 1.0,
+            output_db: 0.0,
+            output: 1.0,
+            oversample: 0,
+            resamplers: (0..MAX_CHANNELS)
+                .map(|_| Resampler::new(OVERSAMPLE))
+                .collect(),
+            events: ParamRing::new(),
+            error: LastError::new(),
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_MODE => self.mode = Mode::from_u32(clamp(value, 0.0, 2.0).round() as u32),
+            PARAM_DRIVE_DB => {
+                self.drive_db = clamp(value, 0.0, 36.0);
+                self.drive = db_to_lin(self.drive_db);
+            }
+            PARAM_OUTPUT_DB => {
+                self.output_db = clamp(value, -36.0, 12.0);
+                self.output = db_to_lin(self.output_db);
+            }
+            PARAM_OVERSAMPLE => {
+                let oversample = u32::from(clamp(value, 0.0, 1.0) >= 0.5);
+                if oversample != self.oversample {
+                    self.oversample = oversample;
+                    for r in &mut self.resamplers {
+                        r.clear();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_MODE => self.mode as u32 as f32,
+            PARAM_DRIVE_DB => self.drive_db,
+            PARAM_OUTPUT_DB => self.output_db,
+            PARAM_OVERSAMPLE => self.oversample as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let frames = input.len() / channels;
+        let mut up = [0.0_f32; OVERSAMPLE];
+        for i in 0..frames {
+            for ch in 0..channels {
+                let x = input[i * channels + ch] * self.drive;
+                let y = if self.oversample != 0 {
+                    let r = &mut self.resamplers[ch];
+                    r.upsample(x, &mut up);
+                    for v in &mut up {
+                        *v = self.mode.shape(*v);
+                    }
+                    r.downsample(&up)
+                } else {
+                    self.mode.shape(x)
+                };
+                output[i * channels + ch] = y * self.output;
+            }
+        }
+    }
+
+    /// Delay of the oversampling filters in samples; 0 when oversampling is off.
+    fn latency_samples(&self) -> u32 {
+        if self.oversample != 0 {
+            RESAMPLER_LATENCY as u32
+        } else {
+            0
+        }
+    }
+
+    /// Clears the oversampling filters.
+    fn reset(&mut self) {
+        for r in &mut self.resamplers {
+            r.clear();
+        }
+    }
+}
+
+node_abi::export_node!(Clipper {
+    abi_version: clipper_abi_version,
+    new: clipper_new,
+    free: clipper_free,
+    param_count: clipper_param_count,
+    param_descriptors: clipper_param_descriptors,
+    set_param: clipper_set_param,
+    get_param: clipper_get_param,
+    param_events: clipper_param_events,
+    process: clipper_process,
+    process_offline: clipper_process_offline,
+    latency_samples: clipper_latency_samples,
+    io_layout: clipper_get_io_layout,
+    process_io: clipper_process_io,
+    reset: clipper_reset,
+    state_size: clipper_state_size,
+    save_state: clipper_save_state,
+    load_state: clipper_load_state,
+    preset_size: clipper_preset_size,
+    save_preset: clipper_save_preset,
+    load_preset: clipper_load_preset,
+    last_error_code: clipper_last_error_code,
+    last_error_message: clipper_last_error_message_ptr,
+});
+
+node_abi::test_params!(Clipper);
+
+dsp_core::test_block_sizes!(clipper_new, clipper_process(input, output), clipper_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Clipper as ClipperNode);
+
+/// Every parameter in one call, as `clipper_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn clipper_set_params(
+    handle: Handle,
+    mode: u32,
+    drive_db: f32,
+    output_db: f32,
+    oversample: u32,
+) {
+    guard(handle, |c: &mut Clipper| {
+        c.set_param(PARAM_MODE, mode as f32);
+        c.set_param(PARAM_DRIVE_DB, drive_db);
+        c.set_param(PARAM_OUTPUT_DB, output_db);
+        c.set_param(PARAM_OVERSAMPLE, oversample as f32);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    fn clipper(params: &[(u32, f32)]) -> Clipper {
+        let mut c = Clipper::new(RATE);
+        for &(index, value) in params {
+            c.set_param(index, value);
+        }
+        c
+    }
+
+    fn render(c: &mut Clipper, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(c, &input[span], y, 1))
+    }
+
+    /// Each mode's curve at a few points, with `Output` scaling the result.
+    #[test]
+    fn modes_shape_as_documented() {
+        let input = [-2.0, -1.0, -0.5, 0.0, 0.5, 1.0, 1.5, 2.0];
+        let hard = render(&mut clipper(&[]), &input);
+        assert_eq!(hard, [-1.0, -1.0, -0.5, 0.0, 0.5, 1.0, 1.0, 1.0]);
+        let soft = render(&mut clipper(&[(PARAM_MODE, 1.0)]), &input);
+        assert_eq!(soft, [-1.0, -1.0, -0.6875, 0.0, 0.6875, 1.0, 1.0, 1.0]);
+        let folded = render(&mut clipper(&[(PARAM_MODE, 2.0)]), &input);
+        let half = core::f32::consts::FRAC_1_SQRT_2;
+        let want = [0.0, -1.0, -half, 0.0, half, 1.0, half, 0.0];
+        for (got, want) in folded.iter().zip(want) {
+            assert!((got - want).abs() < 1e-5, "{folded:?}");
+        }
+
+        let mut c = clipper(&[
+            (PARAM_DRIVE_DB, 20.0 * 2f32.log10()),
+            (PARAM_OUTPUT_DB, -6.0),
+        ]);
+        let trimmed = render(&mut c, &[0.25, 1.0]);
+        assert!((trimmed[0] - 0.5 * db_to_lin(-6.0)).abs() < 1e-6);
+        assert!((trimmed[1] - db_to_lin(-6.0)).abs() < 1e-6);
+    }
+
+    /// Hard-clipping 3.5 kHz puts its 13th harmonic at 45.5 kHz, which aliases to 2.5 kHz at
+    /// 48 kHz; oversampling filters it out before it can fold back.
+    #[test]
+    fn oversampling_removes_aliases() {
+        let w = core::f32::consts::TAU * 3_500.0 / RATE;
+        let input: Vec<f32> = (0..72_000).map(|i| (w * i as f32).sin()).collect();
+        let drive = [(PARAM_DRIVE_DB, 24.0)];
+        let aliased = amplitude(&render(&mut clipper(&drive), &input), 2_500.0);
+        let mut c = clipper(&[drive[0], (PARAM_OVERSAMPLE, 1.0)]);
+        assert_eq!(Node::latency_samples(&c), RESAMPLER_LATENCY as u32);
+        let clean = amplitude(&render(&mut c, &input), 2_500.0);
+        assert!(aliased > 0.05, "{aliased}");
+        assert!(clean < aliased * db_to_lin(-30.0), "{clean} vs {aliased}");
+    }
+}
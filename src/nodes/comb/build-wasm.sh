@@ -0,0 +1,3 @@
+#!/usr/bin/env bash
+# See scripts/build-node-wasm.sh.
+exec bash "$(dirname -- "${BASH_SOURCE[0]}")/../../../scripts/build-node-wasm.sh" comb
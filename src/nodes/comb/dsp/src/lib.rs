@@ -0,0 +1,352 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use dsp_core::denormal::flush;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Comb::PARAMS`, in the order of `comb_set_params`' arguments.
+pub const PARAM_MODE: u32 = 0;
+pub const PARAM_TUNING: u32 = 1;
+pub const PARAM_PITCH: u32 = 2;
+pub const PARAM_FEEDBACK: u32 = 3;
+pub const PARAM_DAMPING: u32 = 4;
+pub const PARAM_INVERT: u32 = 5;
+
+/// Lowest tunable frequency; sets the delay line length.
+const MIN_HZ: f32 = 20.0;
+/// Delay changes glide over roughly this long so retuning doesn't click.
+const GLIDE_MS: f32 = 10.0;
+const MAX_FEEDBACK: f32 = 0.999;
+/// Highest `pitch`: Hz above the highest MIDI note, so both readings fit.
+const MAX_PITCH: f32 = 24000.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+enum Mode {
+    /// `y[n] = x[n] + g * x[n - D]`: notches, never rings.
+    Feedforward,
+    /// `y[n] = x[n] + g * y[n - D]`: resonant peaks at multiples of the tuned frequency.
+    Feedback,
+}
+
+/// Tunable comb filter. The period `D` is `sample_rate / freq` (fractional, linearly
+/// interpolated); a one-pole low-pass on the delayed signal damps the upper harmonics.
+#[repr(C)]
+pub struct Comb {
+    mode: Mode,
+    /// `pitch` is a MIDI note rather than Hz.
+    tuning_note: bool,
+    pitch: f32,
+    feedback: f32,
+    invert: bool,
+    /// Signed: negative polarity moves the peaks to the odd harmonics of half the frequency.
+    gain: f32,
+    damping: f32,
+    target_delay: f32,
+    delay: f32,
+    glide_coeff: f32,
+    lines: Vec<Vec<f32>>,
+    mask: usize,
+    write: usize,
+    lowpass: [f32; MAX_CHANNELS],
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+fn note_to_hz(note: f32) -> f32 {
+    440.0 * ((note - 69.0) / 12.0).exp2()
+}
+
+impl Comb {
+    fn update_delay(&mut self) {
+        let hz = if self.tuning_note {
+            note_to_hz(self.pitch.min(135.0))
+        } else {
+            self.pitch
+        };
+        let hz = clamp(hz, MIN_HZ, self.sample_rate_hz * 0.45);
+        self.target_delay = (self.sample_rate_hz / hz).max(2.0);
+    }
+
+    fn update_gain(&mut self) {
+        self.gain = if self.invert {
+            -self.feedback
+        } else {
+            self.feedback
+        };
+    }
+}
+
+impl Node for Comb {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Mode`: 0 feedforward, 1 feedback. `Tuning`: 0 reads `Pitch` as Hz, 1 as a
+    /// (fractional) MIDI note. `Feedback` is the comb gain, `Damping` the loop low-pass
+    /// amount, and `Invert` flips its polarity.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Mode", "", 0.0, 1.0, 1.0),
+        ParamDescriptor::stepped("Tuning", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Pitch", "", 0.0, MAX_PITCH, 220.0),
+        ParamDescriptor::linear("Feedback", "", 0.0, MAX_FEEDBACK, 0.9),
+        ParamDescriptor::linear("Damping", "", 0.0, 1.0, 0.2),
+        ParamDescriptor::toggle("Invert", false),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let len = ((sample_rate_hz / MIN_HZ) as usize + 4).next_power_of_two();
+        let delay = sample_rate_hz / 220.0;
+        let glide_n = GLIDE_MS / 1000.0 * sample_rate_hz;
+        Comb {
+            mode: Mode::Feedback,
+            tuning_note: false,
+            pitch: 220.0,
+            feedback: 0.9,
+            invert: false,
+            gain: 0.9,
+            damping: 0.2,
+            target_delay: delay,
+            delay,
+            glide_coeff: (-1.0 / glide_n.max(1.0)).exp(),
+            lines: (0..MAX_CHANNELS).map(|_| vec![0.0; len]).collect(),
+            mask: len - 1,
+            write: 0,
+            lowpass: [0.0; MAX_CHANNELS],
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        let on = clamp(value, 0.0, 1.0) >= 0.5;
+        match index {
+            PARAM_MODE => {
+                self.mode = if on {
+                    Mode::Feedback
+                } else {
+                    Mode::Feedforward
+                }
+            }
+            PARAM_TUNING => {
+                self.tuning_note = on;
+                self.update_delay();
+            }
+            PARAM_PITCH => {
+                self.pitch = clamp(value, 0.0, MAX_PITCH);
+                self.update_delay();
+            }
+            PARAM_FEEDBACK => {
+                self.feedback = clamp(value, 0.0, MAX_FEEDBACK);
+                self.update_gain();
+            }
+            PARAM_DAMPING => self.damping = clamp(value, 0.0, 1.0),
+            PARAM_INVERT => {
+                self.invert = on;
+                self.update_gain();
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_MODE => self.mode as u32 as f32,
+            PARAM_TUNING => u32::from(self.tuning_note) as f32,
+            PARAM_PITCH => self.pitch,
+            PARAM_FEEDBACK => self.feedback,
+            PARAM_DAMPING => self.damping,
+            PARAM_INVERT => u32::from(self.invert) as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let damping = self.damping;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            self.delay = self.target_delay + (self.delay - self.target_delay) * self.glide_coeff;
+            let read = self.write as f32 + self.lines[0].len() as f32 - self.delay;
+            let i0 = read as usize;
+            let frac = read - i0 as f32;
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let line = &mut self.lines[ch];
+                let a = line[i0 & self.mask];
+                let b = line[(i0 + 1) & self.mask];
+                let delayed = a + (b - a) * frac;
+                let lp = &mut self.lowpass[ch];
+                *lp = flush(*lp + (1.0 - damping) * (delayed - *lp));
+                let v = *x + self.gain * *lp;
+                line[self.write] = match self.mode {
+                    Mode::Feedforward => *x,
+                    Mode::Feedback => flush(v),
+                };
+                *y = v;
+            }
+            self.write = (self.write + 1) & self.mask;
+        }
+    }
+
+    /// Empties the delay lines and damping filters and finishes any delay glide.
+    fn reset(&mut self) {
+        for line in &mut self.lines {
+            line.fill(0.0);
+        }
+        self.write = 0;
+        self.lowpass = [0.0; MAX_CHANNELS];
+        self.delay = self.target_delay;
+    }
+}
+
+node_abi::export_node!(Comb {
+    abi_version: comb_abi_version,
+    new: comb_new,
+    free: comb_free,
+    param_count: comb_param_count,
+    param_descriptors: comb_param_descriptors,
+    set_param: comb_set_param,
+    get_param: comb_get_param,
+    param_events: comb_param_events,
+    process: comb_process,
+    process_offline: comb_process_offline,
+    latency_samples: comb_latency_samples,
+    io_layout: comb_get_io_layout,
+    process_io: comb_process_io,
+    reset: comb_reset,
+    state_size: comb_state_size,
+    save_state: comb_save_state,
+    load_state: comb_load_state,
+    preset_size: comb_preset_size,
+    save_preset: comb_save_preset,
+    load_preset: comb_load_preset,
+    last_error_code: comb_last_error_code,
+    last_error_message: comb_last_error_message_ptr,
+});
+
+node_abi::test_params!(Comb);
+
+dsp_core::test_block_sizes!(comb_new, comb_process(input, output), comb_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Comb as CombNode);
+
+/// Every parameter in one call, as `comb_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn comb_set_params(
+    handle: Handle,
+    mode: u32,
+    tuning: u32,
+    pitch: f32,
+    feedback: f32,
+    damping: f32,
+    invert: u32,
+) {
+    guard(handle, |c: &mut Comb| {
+        c.set_param(PARAM_MODE, mode as f32);
+        c.set_param(PARAM_TUNING, tuning as f32);
+        c.set_param(PARAM_PITCH, pitch);
+        c.set_param(PARAM_FEEDBACK, feedback);
+        c.set_param(PARAM_DAMPING, damping);
+        c.set_param(PARAM_INVERT, invert as f32);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// A comb with `params` applied and its glide finished.
+    fn comb(params: &[(u32, f32)]) -> Comb {
+        let mut c = Comb::new(RATE);
+        for &(index, value) in params {
+            c.set_param(index, value);
+        }
+        Node::reset(&mut c);
+        c
+    }
+
+    /// A unit impulse through `c`, mono in render quanta.
+    fn impulse_response(c: &mut Comb, frames: usize) -> Vec<f32> {
+        let mut input = vec![0.0; frames];
+        input[0] = 1.0;
+        render_quanta(frames, |span, y| Node::process(c, &input[span], y, 1))
+    }
+
+    /// At 480 Hz the period is 100 samples: a feedback comb repeats the impulse there, scaled
+    /// by `Feedback` each time round, and a feedforward comb echoes it once.
+    #[test]
+    fn impulse_repeats_at_the_tuned_period() {
+        let undamped = [
+            (PARAM_PITCH, 480.0),
+            (PARAM_FEEDBACK, 0.5),
+            (PARAM_DAMPING, 0.0),
+        ];
+        let taps = |y: &[f32]| [y[0], y[99], y[100], y[101], y[200], y[300]];
+
+        let y = impulse_response(&mut comb(&undamped), 400);
+        assert_eq!(taps(&y), [1.0, 0.0, 0.5, 0.0, 0.25, 0.125]);
+
+        let mut c = comb(&undamped);
+        c.set_param(PARAM_MODE, 0.0);
+        let y = impulse_response(&mut c, 400);
+        assert_eq!(taps(&y), [1.0, 0.0, 0.5, 0.0, 0.0, 0.0]);
+
+        let mut c = comb(&undamped);
+        c.set_param(PARAM_INVERT, 1.0);
+        let y = impulse_response(&mut c, 400);
+        assert_eq!(taps(&y), [1.0, 0.0, -0.5, 0.0, 0.25, -0.125]);
+    }
+
+    /// `Tuning` reads `Pitch` as a MIDI note: note 57 is 220 Hz.
+    #[test]
+    fn note_tuning_matches_hertz() {
+        let by_hz = impulse_response(&mut comb(&[(PARAM_PITCH, 220.0)]), 4_800);
+        let by_note = impulse_response(
+            &mut comb(&[(PARAM_TUNING, 1.0), (PARAM_PITCH, 57.0)]),
+            4_800,
+        );
+        for (a, b) in by_hz.iter().zip(&by_note) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    /// `Damping` takes the upper harmonics out of the ring first: the tail's first
+    /// difference (its highs) fades much faster than the tail itself.
+    #[test]
+    fn damping_darkens_the_tail() {
+        let tail = |damping| {
+            let mut c = comb(&[(PARAM_FEEDBACK, 0.99), (PARAM_DAMPING, damping)]);
+            let y = impulse_response(&mut c, 48_000);
+            let energy = |y: &[f32]| y.iter().map(|y| y * y).sum::<f32>();
+            let highs: Vec<f32> = y[24_000..].windows(2).map(|w| w[1] - w[0]).collect();
+            (energy(&y[24_000..]), energy(&highs))
+        };
+        let (plain, plain_highs) = tail(0.0);
+        let (damped, damped_highs) = tail(0.5);
+        assert!(damped < plain);
+        assert!(damped_highs / damped < 0.1 * plain_highs / plain);
+    }
+}
@@ -0,0 +1,387 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::envelope::{one_pole_coeff, EnvelopeFollower};
+use dsp_core::gain::{db_to_lin, lin_to_db_floor};
+use dsp_core::meter::WindowMin;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Compressor::PARAMS`, in the order of `compressor_set_params`' arguments.
+pub const PARAM_THRESHOLD_DB: u32 = 0;
+pub const PARAM_RATIO: u32 = 1;
+pub const PARAM_KNEE_DB: u32 = 2;
+pub const PARAM_ATTACK_MS: u32 = 3;
+pub const PARAM_RELEASE_MS: u32 = 4;
+pub const PARAM_MAKEUP_DB: u32 = 5;
+pub const PARAM_RMS: u32 = 6;
+pub const PARAM_LINK: u32 = 7;
+
+/// Averaging time of the RMS detector.
+const RMS_WINDOW_MS: f32 = 10.0;
+
+/// Feed-forward compressor: level detector -> static curve (dB) -> attack/release smoothing of
+/// the gain reduction -> makeup.
+#[repr(C)]
+pub struct Compressor {
+    threshold_db: f32,
+    ratio: f32,
+    knee_db: f32,
+    attack_ms: f32,
+    release_ms: f32,
+    rms_coeff: f32,
+    makeup_db: f32,
+    makeup: f32,
+    rms: bool,
+    link: bool,
+    /// Mean square (RMS mode) per channel.
+    power: [f32; MAX_CHANNELS],
+    /// Smoothed gain reduction in dB (>= 0) per channel; slot 0 is shared when linked.
+    reduction: [EnvelopeFollower; MAX_CHANNELS],
+    /// Deepest smoothed gain reduction (dB, <= 0) per meter window.
+    meter: WindowMin,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Compressor {
+    fn update_coeffs(&mut self) {
+        for r in &mut self.reduction {
+            r.set_times(self.attack_ms, self.release_ms, self.sample_rate_hz);
+        }
+        self.rms_coeff = one_pole_coeff(RMS_WINDOW_MS, self.sample_rate_hz);
+    }
+
+    /// Static curve with a quadratic knee `knee_db` wide centred on the threshold; returns the
+    /// gain change in dB (<= 0).
+    #[inline]
+    fn gain_computer_db(&self, level_db: f32) -> f32 {
+        let over = level_db - self.threshold_db;
+        let slope = 1.0 / self.ratio - 1.0;
+        let half_knee = self.knee_db * 0.5;
+        if over <= -half_knee {
+            0.0
+        } else if over < half_knee {
+            let x = over + half_knee;
+            slope * x * x / (2.0 * self.knee_db)
+        } else {
+            slope * over
+        }
+    }
+
+    #[inline]
+    fn detect(&mut self, ch: usize, x: f32) -> f32 {
+        if !self.rms {
+            return x.abs();
+        }
+        let p = &mut self.power[ch];
+        *p = *p * self.rms_coeff + (1.0 - self.rms_coeff) * x * x;
+        p.sqrt()
+    }
+
+    #[inline]
+    fn smooth(&mut self, slot: usize, target_db: f32) -> f32 {
+        -self.reduction[slot].process(-target_db)
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let frames = input.len() / channels;
+        let linked = self.link && channels > 1;
+        for i in 0..frames {
+            let frame = i * channels;
+            if linked {
+                let mut level = 0.0_f32;
+                for ch in 0..channels {
+                    level = level.max(self.detect(ch, input[frame + ch]));
+                }
+                let gr = self.smooth(0, self.gain_computer_db(lin_to_db_floor(level)));
+                self.meter.push(gr);
+                let g = db_to_lin(gr) * self.makeup;
+                for ch in 0..channels {
+                    output[frame + ch] = input[frame + ch] * g;
+                }
+            } else {
+                let mut deepest = 0.0_f32;
+                for ch in 0..channels {
+                    let x = input[frame + ch];
+                    let level = self.detect(ch, x);
+                    let gr = self.smooth(ch, self.gain_computer_db(lin_to_db_floor(level)));
+                    deepest = deepest.min(gr);
+                    output[frame + ch] = x * db_to_lin(gr) * self.makeup;
+                }
+                self.meter.push(deepest);
+            }
+        }
+    }
+}
+
+impl Node for Compressor {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `RMS` switches the detector from sample peak to a ~10 ms RMS average.
+    /// `Link` drives all channels from the loudest one.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Threshold", "dB", -60.0, 0.0, -18.0),
+        ParamDescriptor::linear("Ratio", "", 1.0, 20.0, 4.0),
+        ParamDescriptor::linear("Knee", "dB", 0.0, 24.0, 6.0),
+        ParamDescriptor::log("Attack", "ms", 0.01, 500.0, 10.0),
+        ParamDescriptor::log("Release", "ms", 1.0, 5000.0, 120.0),
+        ParamDescriptor::linear("Makeup", "dB", -24.0, 24.0, 0.0),
+        ParamDescriptor::toggle("RMS", false),
+        ParamDescriptor::toggle("Stereo link", true),
+    ];
+    const METER_COUNT: usize = 1;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut c = Compressor {
+            threshold_db: -18.0,
+            ratio: 4.0,
+            knee_db: 6.0,
+            attack_ms: 10.0,
+            release_ms: 120.0,
+            rms_coeff: 0.0,
+            makeup_db: 0.0,
+            makeup: 1.0,
+            rms: false,
+            link: true,
+            power: [0.0; MAX_CHANNELS],
+            reduction: [EnvelopeFollower::new(10.0, 120.0, sample_rate_hz); MAX_CHANNELS],
+            meter: WindowMin::new(0.0),
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        c.update_coeffs();
+        c
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        let on = clamp(value, 0.0, 1.0) >= 0.5;
+        match index {
+            PARAM_THRESHOLD_DB => self.threshold_db = clamp(value, -60.0, 0.0),
+            PARAM_RATIO => self.ratio = clamp(value, 1.0, 20.0),
+            PARAM_KNEE_DB => self.knee_db = clamp(value, 0.0, 24.0),
+            PARAM_ATTACK_MS => {
+                self.attack_ms = clamp(value, 0.01, 500.0);
+                self.update_coeffs();
+            }
+            PARAM_RELEASE_MS => {
+                self.release_ms = clamp(value, 1.0, 5000.0);
+                self.update_coeffs();
+            }
+            PARAM_MAKEUP_DB => {
+                self.makeup_db = clamp(value, -24.0, 24.0);
+                self.makeup = db_to_lin(self.makeup_db);
+            }
+            PARAM_RMS => self.rms = on,
+            PARAM_LINK => self.link = on,
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_THRESHOLD_DB => self.threshold_db,
+            PARAM_RATIO => self.ratio,
+            PARAM_KNEE_DB => self.knee_db,
+            PARAM_ATTACK_MS => self.attack_ms,
+            PARAM_RELEASE_MS => self.release_ms,
+            PARAM_MAKEUP_DB => self.makeup_db,
+            PARAM_RMS => u32::from(self.rms) as f32,
+            PARAM_LINK => u32::from(self.link) as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Gain reduction as `compressor_get_gain_reduction_db` reads it.
+    fn meters(&self, out: &mut [f32]) {
+        out[0] = -self.meter.reading();
+    }
+
+    /// Releases all gain reduction at once and clears the RMS detectors and the meter.
+    fn reset(&mut self) {
+        self.power = [0.0; MAX_CHANNELS];
+        for r in &mut self.reduction {
+            r.reset(0.0);
+        }
+        self.meter.reset();
+    }
+}
+
+node_abi::export_node!(Compressor {
+    abi_version: compressor_abi_version,
+    new: compressor_new,
+    free: compressor_free,
+    param_count: compressor_param_count,
+    param_descriptors: compressor_param_descriptors,
+    set_param: compressor_set_param,
+    get_param: compressor_get_param,
+    param_events: compressor_param_events,
+    process: compressor_process,
+    process_offline: compressor_process_offline,
+    latency_samples: compressor_latency_samples,
+    io_layout: compressor_get_io_layout,
+    process_io: compressor_process_io,
+    reset: compressor_reset,
+    state_size: compressor_state_size,
+    save_state: compressor_save_state,
+    load_state: compressor_load_state,
+    preset_size: compressor_preset_size,
+    save_preset: compressor_save_preset,
+    load_preset: compressor_load_preset,
+    last_error_code: compressor_last_error_code,
+    last_error_message: compressor_last_error_message_ptr,
+});
+
+node_abi::test_params!(Compressor);
+
+dsp_core::test_block_sizes!(
+    compressor_new,
+    compressor_process(input, output),
+    compressor_free,
+    read: |p| vec![compressor_get_gain_reduction_db(p)],
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Compressor as CompressorNode);
+
+/// Every parameter in one call, as `compressor_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn compressor_set_params(
+    handle: Handle,
+    threshold_db: f32,
+    ratio: f32,
+    knee_db: f32,
+    attack_ms: f32,
+    release_ms: f32,
+    makeup_db: f32,
+    rms: u32,
+    link: u32,
+) {
+    guard(handle, |c: &mut Compressor| {
+        c.set_param(PARAM_THRESHOLD_DB, threshold_db);
+        c.set_param(PARAM_RATIO, ratio);
+        c.set_param(PARAM_KNEE_DB, knee_db);
+        c.set_param(PARAM_ATTACK_MS, attack_ms);
+        c.set_param(PARAM_RELEASE_MS, release_ms);
+        c.set_param(PARAM_MAKEUP_DB, makeup_db);
+        c.set_param(PARAM_RMS, rms as f32);
+        c.set_param(PARAM_LINK, link as f32);
+    });
+}
+
+/// Deepest gain reduction over the last complete 128-frame window, in dB (positive = reducing).
+#[no_mangle]
+pub extern "C" fn compressor_get_gain_reduction_db(handle: Handle) -> f32 {
+    guard(handle, |c: &mut Compressor| -c.meter.reading()).unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+    use dsp_core::gain::lin_to_db;
+
+    const RATE: f32 = 48_000.0;
+
+    fn compressor(params: &[(u32, f32)]) -> Compressor {
+        let mut c = Compressor::new(RATE);
+        for &(index, value) in params {
+            c.set_param(index, value);
+        }
+        c
+    }
+
+    fn render(c: &mut Compressor, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(c, &input[span], y, 1))
+    }
+
+    /// Gain applied to each sample, in dB.
+    fn gain_db(input: &[f32], output: &[f32]) -> Vec<f32> {
+        input
+            .iter()
+            .zip(output)
+            .map(|(x, y)| lin_to_db(y / x))
+            .collect()
+    }
+
+    #[test]
+    fn steady_state_reduction_follows_the_ratio_outside_the_knee() {
+        for (level_db, ratio) in [(-6.0, 4.0), (-12.0, 2.0), (0.0, 20.0)] {
+            let mut c = compressor(&[
+                (PARAM_THRESHOLD_DB, -24.0),
+                (PARAM_RATIO, ratio),
+                (PARAM_KNEE_DB, 6.0),
+            ]);
+            let input = vec![db_to_lin(level_db); 48_000];
+            let output = render(&mut c, &input);
+            let over = level_db + 24.0;
+            let want = (1.0 / ratio - 1.0) * over;
+            let got = gain_db(&input, &output)[47_999];
+            assert!(
+                (got - want).abs() < 0.01,
+                "{level_db} dB at {ratio}:1: {got}"
+            );
+        }
+    }
+
+    #[test]
+    fn below_threshold_passes_unchanged() {
+        let mut c = compressor(&[(PARAM_THRESHOLD_DB, -18.0), (PARAM_KNEE_DB, 6.0)]);
+        // Peaks at -24 dB: under the threshold by more than half the knee.
+        let input: Vec<f32> = (0..9_600)
+            .map(|i| db_to_lin(-24.0) * (i as f32 * 0.03).sin())
+            .collect();
+        assert_eq!(render(&mut c, &input), input);
+        let mut meters = [0.0];
+        Node::meters(&c, &mut meters);
+        assert_eq!(meters[0], 0.0);
+    }
+
+    /// A step 20 dB over the threshold reaches ~63% of its reduction after the attack time,
+    /// and dropping back under it releases ~63% of that after the release time.
+    #[test]
+    fn attack_and_release_reach_63_percent_in_their_times() {
+        let (attack_ms, release_ms) = (10.0, 100.0);
+        let mut c = compressor(&[
+            (PARAM_THRESHOLD_DB, -20.0),
+            (PARAM_RATIO, 20.0),
+            (PARAM_KNEE_DB, 0.0),
+            (PARAM_ATTACK_MS, attack_ms),
+            (PARAM_RELEASE_MS, release_ms),
+        ]);
+        let full = 0.95 * 20.0;
+        let attack = (attack_ms * RATE / 1000.0) as usize;
+        let release = (release_ms * RATE / 1000.0) as usize;
+
+        let loud = vec![1.0; 48_000];
+        let reduction = gain_db(&loud, &render(&mut c, &loud));
+        let reached = -reduction[attack - 1] / full;
+        assert!((reached - 0.632).abs() < 0.01, "attack: {reached}");
+        assert!((-reduction[47_999] - full).abs() < 0.01);
+
+        let quiet = vec![db_to_lin(-40.0); 48_000];
+        let reduction = gain_db(&quiet, &render(&mut c, &quiet));
+        let released = 1.0 + reduction[release - 1] / full;
+        assert!((released - 0.632).abs() < 0.01, "release: {released}");
+    }
+}
@@ -0,0 +1,326 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::f32::consts::PI;
+use dsp_core::denormal::flush;
+use dsp_core::envelope::EnvelopeFollower;
+use dsp_core::gain::db_to_lin;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Console::PARAMS`, in the order of `console_set_params`' arguments.
+pub const PARAM_DRIVE_DB: u32 = 0;
+pub const PARAM_EVEN: u32 = 1;
+pub const PARAM_AUTO_GAIN: u32 = 2;
+pub const PARAM_OUTPUT_DB: u32 = 3;
+
+/// Offset into the curve at full `even`; sets how lopsided the even-harmonic shape gets.
+const EVEN_BIAS: f32 = 0.6;
+const DC_BLOCK_HZ: f32 = 8.0;
+/// Loudness window of the auto compensation; slow enough to leave transients alone.
+const LEVEL_MS: f32 = 300.0;
+const MIN_COMPENSATION_DB: f32 = -30.0;
+const MAX_COMPENSATION_DB: f32 = 6.0;
+
+/// Gentle console/tube-style saturation. A symmetric `tanh` gives odd harmonics; the same curve
+/// shifted off centre gives even ones, and `even` crossfades between the two. Both are scaled
+/// to unit slope at zero, so quiet material passes through as it is. With auto gain the output
+/// is matched to the input loudness (linked RMS), so turning up the drive adds harmonics
+/// rather than level.
+#[repr(C)]
+pub struct Console {
+    drive_db: f32,
+    drive: f32,
+    even: f32,
+    auto_gain: bool,
+    output_db: f32,
+    output_gain: f32,
+    /// `tanh(even * EVEN_BIAS)`, and the small-signal slope of the shifted curve.
+    bias_out: f32,
+    bias_slope: f32,
+    in_level: EnvelopeFollower,
+    out_level: EnvelopeFollower,
+    dc_coeff: f32,
+    dc_x1: [f32; MAX_CHANNELS],
+    dc_y1: [f32; MAX_CHANNELS],
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Console {
+    fn update_bias(&mut self) {
+        self.bias_out = (self.even * EVEN_BIAS).tanh();
+        self.bias_slope = 1.0 - self.bias_out * self.bias_out;
+    }
+
+    #[inline]
+    fn shape(&self, x: f32) -> f32 {
+        let odd = x.tanh();
+        let even = ((x + self.even * EVEN_BIAS).tanh() - self.bias_out) / self.bias_slope;
+        odd + (even - odd) * self.even
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let (min_gain, max_gain) = (
+            db_to_lin(MIN_COMPENSATION_DB),
+            db_to_lin(MAX_COMPENSATION_DB),
+        );
+        let mut shaped = [0.0_f32; MAX_CHANNELS];
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            let mut in_power = 0.0;
+            let mut out_power = 0.0;
+            for (ch, (x, s)) in inp.iter().zip(shaped.iter_mut()).enumerate() {
+                let v = self.shape(*x * self.drive);
+                let dc = v - self.dc_x1[ch] + self.dc_coeff * self.dc_y1[ch];
+                self.dc_x1[ch] = v;
+                self.dc_y1[ch] = flush(dc);
+                *s = dc;
+                in_power += x * x;
+                out_power += dc * dc;
+            }
+            let gain = if self.auto_gain {
+                let in_level = self.in_level.process(in_power);
+                let out_level = self.out_level.process(out_power);
+                (in_level / out_level.max(1e-12))
+                    .sqrt()
+                    .clamp(min_gain, max_gain)
+            } else {
+                1.0
+            } * self.output_gain;
+            for (y, s) in out.iter_mut().zip(&shaped) {
+                *y = s * gain;
+            }
+        }
+    }
+}
+
+impl Node for Console {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Drive` into the curve; `Even` 0 (odd harmonics only) to 1 (mostly even); `Auto gain`
+    /// holds the output loudness at the input's; `Output` on top.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Drive", "dB", 0.0, 24.0, 6.0),
+        ParamDescriptor::linear("Even", "", 0.0, 1.0, 0.5),
+        ParamDescriptor::toggle("Auto gain", true),
+        ParamDescriptor::linear("Output", "dB", -24.0, 12.0, 0.0),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut c = Console {
+            drive_db: 6.0,
+            drive: db_to_lin(6.0),
+            even: 0.5,
+            auto_gain: true,
+            output_db: 0.0,
+            output_gain: 1.0,
+            bias_out: 0.0,
+            bias_slope: 1.0,
+            in_level: EnvelopeFollower::new(LEVEL_MS, LEVEL_MS, sample_rate_hz),
+            out_level: EnvelopeFollower::new(LEVEL_MS, LEVEL_MS, sample_rate_hz),
+            dc_coeff: (-2.0 * PI * DC_BLOCK_HZ / sample_rate_hz).exp(),
+            dc_x1: [0.0; MAX_CHANNELS],
+            dc_y1: [0.0; MAX_CHANNELS],
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        c.update_bias();
+        c
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_DRIVE_DB => {
+                self.drive_db = clamp(value, 0.0, 24.0);
+                self.drive = db_to_lin(self.drive_db);
+            }
+            PARAM_EVEN => {
+                self.even = clamp(value, 0.0, 1.0);
+                self.update_bias();
+            }
+            PARAM_AUTO_GAIN => {
+                let auto_gain = clamp(value, 0.0, 1.0) >= 0.5;
+                if auto_gain && !self.auto_gain {
+                    self.in_level.reset(0.0);
+                    self.out_level.reset(0.0);
+                }
+                self.auto_gain = auto_gain;
+            }
+            PARAM_OUTPUT_DB => {
+                self.output_db = clamp(value, -24.0, 12.0);
+                self.output_gain = db_to_lin(self.output_db);
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_DRIVE_DB => self.drive_db,
+            PARAM_EVEN => self.even,
+            PARAM_AUTO_GAIN => u32::from(self.auto_gain) as f32,
+            PARAM_OUTPUT_DB => self.output_db,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Clears the DC blockers and the auto-gain level followers.
+    fn reset(&mut self) {
+        self.in_level.reset(0.0);
+        self.out_level.reset(0.0);
+        self.dc_x1 = [0.0; MAX_CHANNELS];
+        self.dc_y1 = [0.0; MAX_CHANNELS];
+    }
+}
+
+node_abi::export_node!(Console {
+    abi_version: console_abi_version,
+    new: console_new,
+    free: console_free,
+    param_count: console_param_count,
+    param_descriptors: console_param_descriptors,
+    set_param: console_set_param,
+    get_param: console_get_param,
+    param_events: console_param_events,
+    process: console_process,
+    process_offline: console_process_offline,
+    latency_samples: console_latency_samples,
+    io_layout: console_get_io_layout,
+    process_io: console_process_io,
+    reset: console_reset,
+    state_size: console_state_size,
+    save_state: console_save_state,
+    load_state: console_load_state,
+    preset_size: console_preset_size,
+    save_preset: console_save_preset,
+    load_preset: console_load_preset,
+    last_error_code: console_last_error_code,
+    last_error_message: console_last_error_message_ptr,
+});
+
+node_abi::test_params!(Console);
+
+dsp_core::test_block_sizes!(console_new, console_process(input, output), console_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Console as ConsoleNode);
+
+/// Every parameter in one call, as `console_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn console_set_params(
+    handle: Handle,
+    drive_db: f32,
+    even: f32,
+    auto_gain: u32,
+    output_db: f32,
+) {
+    guard(handle, |c: &mut Console| {
+        c.set_param(PARAM_DRIVE_DB, drive_db);
+        c.set_param(PARAM_EVEN, even);
+        c.set_param(PARAM_AUTO_GAIN, auto_gain as f32);
+        c.set_param(PARAM_OUTPUT_DB, output_db);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    fn console(params: &[(u32, f32)]) -> Console {
+        let mut c = Console::new(RATE);
+        for &(index, value) in params {
+            c.set_param(index, value);
+        }
+        c
+    }
+
+    fn render(c: &mut Console, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(c, &input[span], y, 1))
+    }
+
+    /// 2 s of a 1 kHz sine at `level`.
+    fn sine(level: f32) -> Vec<f32> {
+        let w = 2.0 * PI * 1000.0 / RATE;
+        (0..96_000).map(|i| level * (w * i as f32).sin()).collect()
+    }
+
+    fn rms(signal: &[f32]) -> f32 {
+        let tail = &signal[signal.len() - 48_000..];
+        (tail.iter().map(|v| v * v).sum::<f32>() / tail.len() as f32).sqrt()
+    }
+
+    /// `Even` 0 adds only odd harmonics; at 1 the second harmonic outweighs the third.
+    #[test]
+    fn even_balances_the_harmonics() {
+        let harmonics = |even: f32| {
+            let params = [
+                (PARAM_AUTO_GAIN, 0.0),
+                (PARAM_DRIVE_DB, 12.0),
+                (PARAM_EVEN, even),
+            ];
+            let y = render(&mut console(&params), &sine(0.5));
+            (amplitude(&y, 2000.0), amplitude(&y, 3000.0))
+        };
+        let (second, third) = harmonics(0.0);
+        assert!(second < 1e-4 && third > 0.05);
+        let (second, third) = harmonics(1.0);
+        assert!(second > third);
+    }
+
+    /// Quiet material passes as it is: the curves have unit slope at zero.
+    #[test]
+    fn quiet_signals_pass_unchanged() {
+        for even in [0.0, 0.5, 1.0] {
+            let params = [
+                (PARAM_AUTO_GAIN, 0.0),
+                (PARAM_DRIVE_DB, 0.0),
+                (PARAM_EVEN, even),
+            ];
+            let gain = amplitude(&render(&mut console(&params), &sine(0.001)), 1000.0) / 0.001;
+            assert!((gain - 1.0).abs() < 0.01, "{even}: {gain}");
+        }
+    }
+
+    /// Auto gain holds the output loudness at the input's however hard it is driven; without
+    /// it the drive raises the level.
+    #[test]
+    fn auto_gain_holds_the_level() {
+        let x = sine(0.25);
+        for drive_db in [0.0, 12.0, 24.0] {
+            let y = render(&mut console(&[(PARAM_DRIVE_DB, drive_db)]), &x);
+            let change_db = 20.0 * (rms(&y) / rms(&x)).log10();
+            assert!(change_db.abs() < 0.2, "{drive_db}: {change_db}");
+        }
+        let raw = render(
+            &mut console(&[(PARAM_AUTO_GAIN, 0.0), (PARAM_DRIVE_DB, 12.0)]),
+            &x,
+        );
+        assert!(rms(&raw) > 2.0 * rms(&x));
+    }
+}
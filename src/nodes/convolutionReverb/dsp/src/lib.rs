@@ -0,0 +1,361 @@
+// `convolver_load_ir` reads the IR through a pointer the worklet got from us; null is the
+// only invalid value we can detect.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use dsp_core::convolver::PartitionedConvolver;
+use dsp_core::delay::DelayLine;
+use dsp_core::gain::db_to_lin;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+/// Every channel keeps its own spectra of the whole IR, so this node stops at stereo.
+pub const MAX_CHANNELS: usize = 2;
+
+// Indices into `ConvolutionReverb::PARAMS`, in the order of `convolver_set_params`' arguments.
+pub const PARAM_MIX: u32 = 0;
+pub const PARAM_OUTPUT_DB: u32 = 1;
+
+/// Partition size; also the node's latency.
+pub const BLOCK: usize = 1024;
+const MAX_IR_SECONDS: f32 = 10.0;
+/// Loaded IRs lose leading samples below this (relative to their peak) ...
+const TRIM_HEAD_DB: f32 = -60.0;
+/// ... and the tail below this.
+const TRIM_TAIL_DB: f32 = -90.0;
+
+/// Convolution reverb: each channel runs a uniformly partitioned FFT convolver with one channel
+/// of the impulse response (a mono IR is shared). The dry path is delayed by the same `BLOCK`
+/// so the mix stays aligned.
+pub struct ConvolutionReverb {
+    convolvers: Vec<PartitionedConvolver>,
+    dry_lines: Vec<DelayLine>,
+    mix: f32,
+    output_db: f32,
+    output_gain: f32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+/// De-interleaves `ir`, trims silence off both ends and scales it to unit energy (in its
+/// loudest channel), so IRs of any level and length come out at a similar loudness.
+fn prepare_ir(ir: &[f32], channels: usize, max_frames: usize) -> Vec<Vec<f32>> {
+    let frames = (ir.len() / channels).min(max_frames);
+    let mut planar: Vec<Vec<f32>> = (0..channels)
+        .map(|ch| (0..frames).map(|i| ir[i * channels + ch]).collect())
+        .collect();
+    let peak = ir[..frames * channels].iter().fold(0.0_f32, |m, v| {
+        if v.is_finite() {
+            m.max(v.abs())
+        } else {
+            m
+        }
+    });
+    if peak <= 0.0 {
+        return vec![Vec::new(); channels];
+    }
+    let loud = |threshold_db: f32, i: usize| {
+        planar
+            .iter()
+            .any(|c| c[i].abs() > peak * db_to_lin(threshold_db))
+    };
+    let start = (0..frames).find(|&i| loud(TRIM_HEAD_DB, i)).unwrap_or(0);
+    let end = (start..frames)
+        .rev()
+        .find(|&i| loud(TRIM_TAIL_DB, i))
+        .unwrap_or(start)
+        + 1;
+    let energy = planar
+        .iter()
+        .map(|c| c[start..end].iter().map(|v| v * v).sum::<f32>())
+        .fold(0.0_f32, f32::max);
+    let scale = 1.0 / energy.sqrt().max(1e-9);
+    for c in &mut planar {
+        c.truncate(end);
+        c.drain(..start);
+        for v in c.iter_mut() {
+            *v = if v.is_finite() { *v * scale } else { 0.0 };
+        }
+    }
+    planar
+}
+
+impl ConvolutionReverb {
+    fn load(&mut self, ir: &[f32], channels: usize) {
+        let max_frames = (MAX_IR_SECONDS * self.sample_rate_hz) as usize;
+        let planar = prepare_ir(ir, channels, max_frames);
+        self.convolvers = (0..MAX_CHANNELS)
+            .map(|ch| {
+                let h = &planar[ch % planar.len()];
+                let mut conv = PartitionedConvolver::new(BLOCK, h.len());
+                conv.set_ir(h);
+                conv
+            })
+            .collect();
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let wet_gain = self.mix * self.output_gain;
+        let dry_gain = 1.0 - self.mix;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let dry = &mut self.dry_lines[ch];
+                dry.push(*x);
+                let wet = match self.convolvers.get_mut(ch) {
+                    Some(conv) => conv.process(*x),
+                    None => 0.0,
+                };
+                *y = dry.read_linear(BLOCK as f32) * dry_gain + wet * wet_gain;
+            }
+        }
+    }
+}
+
+impl Node for ConvolutionReverb {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Output` trims the wet level.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Mix", "", 0.0, 1.0, 0.3),
+        ParamDescriptor::linear("Output", "dB", -24.0, 24.0, 0.0),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        ConvolutionReverb {
+            convolvers: Vec::new(),
+            dry_lines: (0..MAX_CHANNELS).map(|_| DelayLine::new(BLOCK)).collect(),
+            mix: 0.3,
+            output_db: 0.0,
+            output_gain: 1.0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_MIX => self.mix = clamp(value, 0.0, 1.0),
+            PARAM_OUTPUT_DB => {
+                self.output_db = clamp(value, -24.0, 24.0);
+                self.output_gain = db_to_lin(self.output_db);
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_MIX => self.mix,
+            PARAM_OUTPUT_DB => self.output_db,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// One partition (`BLOCK` samples), for both the wet and the aligned dry path.
+    fn latency_samples(&self) -> u32 {
+        BLOCK as u32
+    }
+
+    /// Cuts the reverb tail and empties the dry delay; the loaded impulse response is kept.
+    fn reset(&mut self) {
+        for conv in &mut self.convolvers {
+            conv.clear();
+        }
+        for line in &mut self.dry_lines {
+            line.clear();
+        }
+    }
+}
+
+node_abi::export_node!(ConvolutionReverb {
+    abi_version: convolver_abi_version,
+    new: convolver_new,
+    free: convolver_free,
+    param_count: convolver_param_count,
+    param_descriptors: convolver_param_descriptors,
+    set_param: convolver_set_param,
+    get_param: convolver_get_param,
+    param_events: convolver_param_events,
+    process: convolver_process,
+    process_offline: convolver_process_offline,
+    latency_samples: convolver_latency_samples,
+    io_layout: convolver_get_io_layout,
+    process_io: convolver_process_io,
+    reset: convolver_reset,
+    state_size: convolver_state_size,
+    save_state: convolver_save_state,
+    load_state: convolver_load_state,
+    preset_size: convolver_preset_size,
+    save_preset: convolver_save_preset,
+    load_preset: convolver_load_preset,
+    last_error_code: convolver_last_error_code,
+    last_error_message: convolver_last_error_message_ptr,
+});
+
+node_abi::test_params!(ConvolutionReverb);
+
+dsp_core::test_block_sizes!(
+    convolver_new,
+    convolver_process(input, output),
+    convolver_free
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(ConvolutionReverb as ConvolutionReverbNode);
+
+/// Every parameter in one call, as `convolver_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn convolver_set_params(handle: Handle, mix: f32, output_db: f32) {
+    guard(handle, |c: &mut ConvolutionReverb| {
+        c.set_param(PARAM_MIX, mix);
+        c.set_param(PARAM_OUTPUT_DB, output_db);
+    });
+}
+
+/// Loads an impulse response the worklet copied into a `wasm_alloc`ed buffer: `frames` frames
+/// of `channels` (1 or 2) interleaved samples at the context rate, up to 10 s (longer IRs are
+/// cut). The IR is trimmed and normalised (see `prepare_ir`); the buffer can be freed as soon
+/// as this returns. Allocates, so call it from the message handler rather than mid-render.
+#[no_mangle]
+pub extern "C" fn convolver_load_ir(
+    handle: Handle,
+    ir_ptr: *const f32,
+    frames: usize,
+    channels: usize,
+) {
+    if ir_ptr.is_null() {
+        return;
+    }
+    let channels = channels.clamp(1, MAX_CHANNELS);
+    let ir = unsafe { core::slice::from_raw_parts(ir_ptr, frames.saturating_mul(channels)) };
+    guard(handle, |c: &mut ConvolutionReverb| c.load(ir, channels));
+}
+
+/// `convolver_load_ir` for the bindgen class: `ir` holds whole frames of `channels` (1 or 2)
+/// interleaved samples.
+#[cfg(feature = "bindgen")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+impl ConvolutionReverbNode {
+    #[wasm_bindgen(js_name = loadIr)]
+    pub fn load_ir(&mut self, ir: &[f32], channels: usize) {
+        let channels = channels.clamp(1, MAX_CHANNELS);
+        self.node
+            .load(&ir[..ir.len() / channels * channels], channels);
+    }
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 48_000.0;
+
+    /// A fully wet convolver with `ir` (`channels` interleaved) loaded.
+    fn convolver(ir: &[f32], channels: usize) -> ConvolutionReverb {
+        let mut c = ConvolutionReverb::new(RATE);
+        c.set_param(PARAM_MIX, 1.0);
+        c.load(ir, channels);
+        c
+    }
+
+    /// `frames` of an impulse on every channel, in render quanta.
+    fn impulse_response(c: &mut ConvolutionReverb, channels: usize, frames: usize) -> Vec<f32> {
+        let mut input = vec![0.0; frames * channels];
+        input[..channels].fill(1.0);
+        let mut output = vec![0.0; input.len()];
+        for (x, y) in input
+            .chunks(128 * channels)
+            .zip(output.chunks_mut(128 * channels))
+        {
+            Node::process(c, x, y, channels);
+        }
+        output
+    }
+
+    /// The IR loses its quiet lead-in and is scaled to unit energy; the wet output is it, one
+    /// `BLOCK` late.
+    #[test]
+    fn loaded_ir_is_trimmed_normalised_and_block_delayed() {
+        let mut ir = vec![0.0; 64];
+        ir[3] = 1e-4;
+        ir[10] = 0.5;
+        ir[20] = 0.25;
+        let mut c = convolver(&ir, 1);
+        assert_eq!(Node::latency_samples(&c), BLOCK as u32);
+        let y = impulse_response(&mut c, 1, 2048);
+        let scale = 1.0 / 0.3125_f32.sqrt();
+        for (i, v) in y.iter().enumerate() {
+            let expected = match i {
+                i if i == BLOCK => 0.5 * scale,
+                i if i == BLOCK + 10 => 0.25 * scale,
+                _ => 0.0,
+            };
+            assert!((v - expected).abs() < 1e-4, "{i}: {v}");
+        }
+    }
+
+    /// Taps a few partitions apart, and seconds into a long IR, all land where they should.
+    #[test]
+    fn long_irs_span_partitions() {
+        let mut ir = vec![0.0; 100_001];
+        ir[0] = 1.0;
+        ir[2500] = -0.5;
+        ir[100_000] = 0.25;
+        let mut c = convolver(&ir, 1);
+        let y = impulse_response(&mut c, 1, 102_400);
+        let scale = 1.0 / 1.3125_f32.sqrt();
+        for (at, tap) in [(0, 1.0), (2500, -0.5), (100_000, 0.25)] {
+            assert!((y[BLOCK + at] - tap * scale).abs() < 1e-3);
+        }
+        let energy = y.iter().map(|v| v * v).sum::<f32>();
+        assert!((energy - 1.0).abs() < 1e-3);
+    }
+
+    /// A stereo IR convolves each channel with its own side; a mono one is shared.
+    #[test]
+    fn stereo_irs_keep_their_sides() {
+        let ir = [1.0, 0.0, 0.0, 1.0];
+        let y = impulse_response(&mut convolver(&ir, 2), 2, 2048);
+        assert!((y[2 * BLOCK] - 1.0).abs() < 1e-4 && y[2 * BLOCK + 1].abs() < 1e-4);
+        assert!(y[2 * BLOCK + 2].abs() < 1e-4 && (y[2 * BLOCK + 3] - 1.0).abs() < 1e-4);
+
+        let y = impulse_response(&mut convolver(&[1.0, 0.5], 1), 2, 2048);
+        assert!(y.chunks(2).all(|frame| frame[0] == frame[1]));
+        assert!(y[2 * BLOCK + 2] > 0.0);
+    }
+
+    /// The dry path is delayed by the same block, so `Mix` 0 is the input `BLOCK` late.
+    #[test]
+    fn dry_path_is_aligned_with_the_wet() {
+        let mut c = convolver(&[1.0], 1);
+        c.set_param(PARAM_MIX, 0.0);
+        let y = impulse_response(&mut c, 1, 2048);
+        assert_eq!(y[BLOCK], 1.0);
+        assert_eq!(y.iter().map(|v| v.abs()).sum::<f32>(), 1.0);
+    }
+}
@@ -0,0 +1,193 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::f32::consts::PI;
+use dsp_core::denormal::flush;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `DcBlocker::PARAMS`, in the order of `dc_blocker_set_params`' arguments.
+pub const PARAM_CUTOFF_HZ: u32 = 0;
+
+/// First-order high-pass (`y[n] = x[n] - x[n-1] + r * y[n-1]`) for removing DC offset and
+/// sub-audio rumble.
+#[repr(C)]
+pub struct DcBlocker {
+    cutoff_hz: f32,
+    r: f32,
+    x1: [f32; MAX_CHANNELS],
+    y1: [f32; MAX_CHANNELS],
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl DcBlocker {
+    fn update(&mut self) {
+        self.r = (-2.0 * PI * self.cutoff_hz / self.sample_rate_hz).exp();
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let r = self.r;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let v = *x - self.x1[ch] + r * self.y1[ch];
+                self.x1[ch] = *x;
+                self.y1[ch] = flush(v);
+                *y = v;
+            }
+        }
+    }
+}
+
+impl Node for DcBlocker {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Cutoff` is (very nearly, at these low settings) the -3 dB point.
+    const PARAMS: &'static [ParamDescriptor] =
+        &[ParamDescriptor::linear("Cutoff", "Hz", 5.0, 40.0, 10.0)];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut d = DcBlocker {
+            cutoff_hz: 10.0,
+            r: 0.0,
+            x1: [0.0; MAX_CHANNELS],
+            y1: [0.0; MAX_CHANNELS],
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        d.update();
+        d
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        if index == PARAM_CUTOFF_HZ {
+            self.cutoff_hz = clamp(value, 5.0, 40.0);
+            self.update();
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_CUTOFF_HZ => self.cutoff_hz,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Clears the filter history.
+    fn reset(&mut self) {
+        self.x1 = [0.0; MAX_CHANNELS];
+        self.y1 = [0.0; MAX_CHANNELS];
+    }
+}
+
+node_abi::export_node!(DcBlocker {
+    abi_version: dc_blocker_abi_version,
+    new: dc_blocker_new,
+    free: dc_blocker_free,
+    param_count: dc_blocker_param_count,
+    param_descriptors: dc_blocker_param_descriptors,
+    set_param: dc_blocker_set_param,
+    get_param: dc_blocker_get_param,
+    param_events: dc_blocker_param_events,
+    process: dc_blocker_process,
+    process_offline: dc_blocker_process_offline,
+    latency_samples: dc_blocker_latency_samples,
+    io_layout: dc_blocker_get_io_layout,
+    process_io: dc_blocker_process_io,
+    reset: dc_blocker_reset,
+    state_size: dc_blocker_state_size,
+    save_state: dc_blocker_save_state,
+    load_state: dc_blocker_load_state,
+    preset_size: dc_blocker_preset_size,
+    save_preset: dc_blocker_save_preset,
+    load_preset: dc_blocker_load_preset,
+    last_error_code: dc_blocker_last_error_code,
+    last_error_message: dc_blocker_last_error_message_ptr,
+});
+
+node_abi::test_params!(DcBlocker);
+
+dsp_core::test_block_sizes!(
+    dc_blocker_new,
+    dc_blocker_process(input, output),
+    dc_blocker_free
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(DcBlocker as DcBlockerNode);
+
+/// Every parameter in one call, as `dc_blocker_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn dc_blocker_set_params(handle: Handle, cutoff_hz: f32) {
+    guard(handle, |d: &mut DcBlocker| {
+        d.set_param(PARAM_CUTOFF_HZ, cutoff_hz)
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// Two seconds of `offset` plus a `hz` sine of `amplitude`, through a blocker at
+    /// `cutoff_hz`, mono in render quanta; returns the last second.
+    fn render(cutoff_hz: f32, offset: f32, hz: f32, amplitude: f32) -> Vec<f32> {
+        let mut d = DcBlocker::new(RATE);
+        d.set_param(PARAM_CUTOFF_HZ, cutoff_hz);
+        let w = core::f32::consts::TAU * hz / RATE;
+        let input: Vec<f32> = (0..96_000)
+            .map(|i| offset + amplitude * (w * i as f32).sin())
+            .collect();
+        let mut output = render_quanta(input.len(), |span, y| {
+            Node::process(&mut d, &input[span], y, 1)
+        });
+        output.split_off(48_000)
+    }
+
+    fn peak(signal: &[f32]) -> f32 {
+        signal.iter().fold(0.0_f32, |m, y| m.max(y.abs()))
+    }
+
+    /// The offset goes; audio well above the cutoff stays as it was.
+    #[test]
+    fn removes_the_offset_and_keeps_the_audio() {
+        let output = render(10.0, 0.3, 1_000.0, 0.5);
+        let mean = output.iter().sum::<f32>() / output.len() as f32;
+        assert!(mean.abs() < 1e-4, "{mean}");
+        assert!((peak(&output) - 0.5).abs() < 0.001, "{}", peak(&output));
+    }
+
+    /// `Cutoff` is the -3 dB point.
+    #[test]
+    fn cutoff_is_the_half_power_point() {
+        for cutoff_hz in [5.0, 20.0, 40.0] {
+            let gain_db = 20.0 * peak(&render(cutoff_hz, 0.0, cutoff_hz, 1.0)).log10();
+            assert!((gain_db + 3.0).abs() < 0.1, "{cutoff_hz} Hz: {gain_db}");
+        }
+    }
+}
@@ -0,0 +1,335 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::biquad::{Biquad, Response};
+use dsp_core::crossover::{Crossover, MAX_BANDS};
+use dsp_core::envelope::one_pole_coeff;
+use dsp_core::gain::{db_to_lin, lin_to_db_floor};
+use dsp_core::meter::WindowMin;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `DeEsser::PARAMS`, in the order of `deesser_set_params`' arguments.
+pub const PARAM_FREQ_HZ: u32 = 0;
+pub const PARAM_THRESHOLD_DB: u32 = 1;
+pub const PARAM_RANGE_DB: u32 = 2;
+pub const PARAM_SPLIT_BAND: u32 = 3;
+pub const PARAM_LISTEN: u32 = 4;
+
+/// Width of the sidechain band-pass.
+const SIDECHAIN_Q: f32 = 1.4;
+const ATTACK_MS: f32 = 1.0;
+const RELEASE_MS: f32 = 60.0;
+
+/// Compressor keyed by a band-passed copy of the input. Above the threshold the gain is pulled
+/// down by the full overshoot (like a limiter on the sibilance band), at most `range` dB.
+#[repr(C)]
+pub struct DeEsser {
+    freq_hz: f32,
+    threshold_db: f32,
+    range_db: f32,
+    split_band: u32,
+    listen: u32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    sidechain: [Biquad; MAX_CHANNELS],
+    /// Two-band split at `freq_hz`; only the upper band is reduced in split-band mode.
+    split: [Crossover; MAX_CHANNELS],
+    /// Smoothed gain change in dB (<= 0), linked across channels.
+    env_db: f32,
+    /// Deepest `env_db` per meter window.
+    meter: WindowMin,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl DeEsser {
+    fn update_filters(&mut self) {
+        for bp in &mut self.sidechain {
+            bp.set(
+                Response::Bandpass,
+                self.freq_hz,
+                SIDECHAIN_Q,
+                self.sample_rate_hz,
+            );
+        }
+        for xo in &mut self.split {
+            xo.set(2, &[self.freq_hz], self.sample_rate_hz);
+        }
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let frames = input.len() / channels;
+        let mut keyed = [0.0_f32; MAX_CHANNELS];
+        let mut bands = [0.0_f32; MAX_BANDS];
+        for i in 0..frames {
+            let frame = i * channels;
+            let mut peak = 0.0_f32;
+            for ch in 0..channels {
+                keyed[ch] = self.sidechain[ch].process(input[frame + ch]);
+                peak = peak.max(keyed[ch].abs());
+            }
+            let over = lin_to_db_floor(peak) - self.threshold_db;
+            let target = -over.clamp(0.0, self.range_db);
+            let c = if target < self.env_db {
+                self.attack_coeff
+            } else {
+                self.release_coeff
+            };
+            self.env_db = self.env_db * c + (1.0 - c) * target;
+            self.meter.push(self.env_db);
+            let g = db_to_lin(self.env_db);
+
+            for ch in 0..channels {
+                let x = input[frame + ch];
+                output[frame + ch] = if self.listen != 0 {
+                    keyed[ch]
+                } else if self.split_band != 0 {
+                    self.split[ch].split(x, &mut bands);
+                    bands[0] + bands[1] * g
+                } else {
+                    x * g
+                };
+            }
+        }
+    }
+}
+
+impl Node for DeEsser {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Frequency` tunes both the sidechain band-pass and the split point. `Split band`
+    /// reduces only the content above it instead of the whole signal; `Listen` outputs the
+    /// band-passed sidechain so the frequency can be found by ear.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::log("Frequency", "Hz", 2000.0, 12000.0, 6000.0),
+        ParamDescriptor::linear("Threshold", "dB", -60.0, 0.0, -24.0),
+        ParamDescriptor::linear("Range", "dB", 0.0, 24.0, 12.0),
+        ParamDescriptor::toggle("Split band", true),
+        ParamDescriptor::toggle("Listen", false),
+    ];
+    const METER_COUNT: usize = 1;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut d = DeEsser {
+            freq_hz: 6000.0,
+            threshold_db: -24.0,
+            range_db: 12.0,
+            split_band: 1,
+            listen: 0,
+            attack_coeff: one_pole_coeff(ATTACK_MS, sample_rate_hz),
+            release_coeff: one_pole_coeff(RELEASE_MS, sample_rate_hz),
+            sidechain: [Biquad::default(); MAX_CHANNELS],
+            split: [Crossover::new(); MAX_CHANNELS],
+            env_db: 0.0,
+            meter: WindowMin::new(0.0),
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        d.update_filters();
+        d
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        let on = u32::from(clamp(value, 0.0, 1.0) >= 0.5);
+        match index {
+            PARAM_FREQ_HZ => {
+                let freq_hz = clamp(value, 2000.0, 12000.0);
+                if freq_hz != self.freq_hz {
+                    self.freq_hz = freq_hz;
+                    self.update_filters();
+                }
+            }
+            PARAM_THRESHOLD_DB => self.threshold_db = clamp(value, -60.0, 0.0),
+            PARAM_RANGE_DB => self.range_db = clamp(value, 0.0, 24.0),
+            PARAM_SPLIT_BAND => self.split_band = on,
+            PARAM_LISTEN => self.listen = on,
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_FREQ_HZ => self.freq_hz,
+            PARAM_THRESHOLD_DB => self.threshold_db,
+            PARAM_RANGE_DB => self.range_db,
+            PARAM_SPLIT_BAND => self.split_band as f32,
+            PARAM_LISTEN => self.listen as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Gain reduction as `deesser_get_gain_reduction_db` reads it.
+    fn meters(&self, out: &mut [f32]) {
+        out[0] = -self.meter.reading();
+    }
+
+    /// Clears the side-chain and band-split filters and releases all gain reduction.
+    fn reset(&mut self) {
+        for f in &mut self.sidechain {
+            f.clear();
+        }
+        for s in &mut self.split {
+            s.clear();
+        }
+        self.env_db = 0.0;
+        self.meter.reset();
+    }
+}
+
+node_abi::export_node!(DeEsser {
+    abi_version: deesser_abi_version,
+    new: deesser_new,
+    free: deesser_free,
+    param_count: deesser_param_count,
+    param_descriptors: deesser_param_descriptors,
+    set_param: deesser_set_param,
+    get_param: deesser_get_param,
+    param_events: deesser_param_events,
+    process: deesser_process,
+    process_offline: deesser_process_offline,
+    latency_samples: deesser_latency_samples,
+    io_layout: deesser_get_io_layout,
+    process_io: deesser_process_io,
+    reset: deesser_reset,
+    state_size: deesser_state_size,
+    save_state: deesser_save_state,
+    load_state: deesser_load_state,
+    preset_size: deesser_preset_size,
+    save_preset: deesser_save_preset,
+    load_preset: deesser_load_preset,
+    last_error_code: deesser_last_error_code,
+    last_error_message: deesser_last_error_message_ptr,
+});
+
+node_abi::test_params!(DeEsser);
+
+dsp_core::test_block_sizes!(
+    deesser_new,
+    deesser_process(input, output),
+    deesser_free,
+    read: |p| vec![deesser_get_gain_reduction_db(p)],
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(DeEsser as DeEsserNode);
+
+/// Every parameter in one call, as `deesser_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn deesser_set_params(
+    handle: Handle,
+    freq_hz: f32,
+    threshold_db: f32,
+    range_db: f32,
+    split_band: u32,
+    listen: u32,
+) {
+    guard(handle, |d: &mut DeEsser| {
+        d.set_param(PARAM_FREQ_HZ, freq_hz);
+        d.set_param(PARAM_THRESHOLD_DB, threshold_db);
+        d.set_param(PARAM_RANGE_DB, range_db);
+        d.set_param(PARAM_SPLIT_BAND, split_band as f32);
+        d.set_param(PARAM_LISTEN, listen as f32);
+    });
+}
+
+/// Deepest gain reduction over the last complete 128-frame window, in dB (positive = reducing).
+#[no_mangle]
+pub extern "C" fn deesser_get_gain_reduction_db(handle: Handle) -> f32 {
+    guard(handle, |d: &mut DeEsser| -d.meter.reading()).unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    fn deesser(params: &[(u32, f32)]) -> DeEsser {
+        let mut d = DeEsser::new(RATE);
+        for &(index, value) in params {
+            d.set_param(index, value);
+        }
+        d
+    }
+
+    /// 1.5 s of 200 Hz at `low` plus `hz` at `high`.
+    fn voice(low: f32, hz: f32, high: f32) -> Vec<f32> {
+        let w = core::f32::consts::TAU / RATE;
+        (0..72_000)
+            .map(|i| low * (w * 200.0 * i as f32).sin() + high * (w * hz * i as f32).sin())
+            .collect()
+    }
+
+    /// The last second of `input` through `d`, past the attack.
+    fn render(d: &mut DeEsser, input: &[f32]) -> Vec<f32> {
+        let mut output = render_quanta(input.len(), |span, y| Node::process(d, &input[span], y, 1));
+        output.split_off(input.len() - 48_000)
+    }
+
+    fn gain_reduction_db(d: &DeEsser) -> f32 {
+        let mut meters = [0.0];
+        Node::meters(d, &mut meters);
+        meters[0]
+    }
+
+    /// Loud sibilance pulls the band above `Frequency` down by up to `Range` and leaves the
+    /// voice under it alone; wideband mode turns both down.
+    #[test]
+    fn split_band_reduces_only_the_sibilant_band() {
+        let input = voice(0.25, 10_000.0, 0.25);
+        let mut d = deesser(&[(PARAM_THRESHOLD_DB, -40.0)]);
+        let output = render(&mut d, &input);
+        let low = amplitude(&output, 200.0);
+        let high = amplitude(&output, 10_000.0);
+        assert!((low - 0.25).abs() < 0.005, "low {low}");
+        assert!(high < 0.25 * db_to_lin(-9.0), "high {high}");
+        let reduction = gain_reduction_db(&d);
+        assert!((reduction - 12.0).abs() < 0.1, "{reduction}");
+
+        let mut d = deesser(&[(PARAM_THRESHOLD_DB, -40.0), (PARAM_SPLIT_BAND, 0.0)]);
+        let output = render(&mut d, &input);
+        let low = amplitude(&output, 200.0);
+        assert!((low - 0.25 * db_to_lin(-12.0)).abs() < 0.005, "low {low}");
+    }
+
+    /// Sibilance under the threshold passes through the split unchanged.
+    #[test]
+    fn quiet_sibilance_passes() {
+        let input = voice(0.25, 10_000.0, 0.01);
+        let mut d = deesser(&[]);
+        let output = render(&mut d, &input);
+        assert!((amplitude(&output, 200.0) - 0.25).abs() < 0.005);
+        assert!((amplitude(&output, 10_000.0) - 0.01).abs() < 0.001);
+        assert_eq!(gain_reduction_db(&d), 0.0);
+    }
+
+    /// `Listen` outputs the band-passed side-chain: the sibilance at `Frequency`, not the voice.
+    #[test]
+    fn listen_outputs_the_side_chain() {
+        let input = voice(0.25, 6_000.0, 0.25);
+        let mut d = deesser(&[(PARAM_LISTEN, 1.0)]);
+        let output = render(&mut d, &input);
+        let low = amplitude(&output, 200.0);
+        assert!(low < 0.25 * db_to_lin(-24.0), "low {low}");
+        assert!((amplitude(&output, 6_000.0) - 0.25).abs() < 0.005);
+    }
+}
@@ -0,0 +1,360 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::envelope::one_pole_coeff;
+use dsp_core::gain::db_to_lin;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Diagnostics::PARAMS`, in the order of `diagnostics_set_params`' arguments.
+pub const PARAM_THRESHOLD_DB: u32 = 0;
+pub const PARAM_DC_TIME_MS: u32 = 1;
+
+/// The noise floor is judged on blocks of this length.
+const BLOCK_MS: f32 = 50.0;
+/// Time constant of the faster mean taken out before the noise floor (a high-pass around
+/// 16 Hz), so it settles within a block or two of a DC step.
+const AC_TIME_MS: f32 = 10.0;
+
+#[derive(Clone, Copy, Default)]
+struct Channel {
+    dc: f32,
+    ac_mean: f32,
+    /// Sum of squares of the block in progress, high-passed.
+    block_energy: f64,
+    /// Energy and sample count of every quiet block since reset.
+    quiet_energy: f64,
+    quiet_samples: u64,
+    /// The last block was loud, so the high-pass may still be settling from it.
+    after_loud: bool,
+    nans: u32,
+    infs: u32,
+    subnormals: u32,
+}
+
+/// Signal diagnostics for debugging nodes. Per channel: the DC offset as a slow running mean;
+/// the noise floor as the RMS (high-passed, so DC and rumble don't count) over every 50 ms
+/// block whose RMS stays under the threshold (skipping the block after a loud one, while the
+/// high-pass settles), since creation or reset; and counts of NaN, infinite and subnormal
+/// samples. Non-finite samples are counted and then taken as 0 for the other readings. The
+/// audio is only read; the worklet passes it on. The UI polls the getters.
+pub struct Diagnostics {
+    channels: [Channel; MAX_CHANNELS],
+    threshold_db: f32,
+    dc_time_ms: f32,
+    dc_coeff: f32,
+    ac_coeff: f32,
+    /// Mean square a block has to stay under to count as quiet.
+    threshold: f64,
+    block_len: usize,
+    block_pos: usize,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Diagnostics {
+    fn measure(&mut self, input: &[f32], channels: usize) {
+        let (a, b) = (self.dc_coeff, self.ac_coeff);
+        for frame in input.chunks_exact(channels) {
+            for (c, &x) in self.channels.iter_mut().zip(frame) {
+                let x = if x.is_nan() {
+                    c.nans = c.nans.saturating_add(1);
+                    0.0
+                } else if x.is_infinite() {
+                    c.infs = c.infs.saturating_add(1);
+                    0.0
+                } else {
+                    if x.is_subnormal() {
+                        c.subnormals = c.subnormals.saturating_add(1);
+                    }
+                    x
+                };
+                c.dc = x + (c.dc - x) * a;
+                c.ac_mean = x + (c.ac_mean - x) * b;
+                let ac = (x - c.ac_mean) as f64;
+                c.block_energy += ac * ac;
+            }
+            self.block_pos += 1;
+            if self.block_pos == self.block_len {
+                self.block_pos = 0;
+                let limit = self.threshold * self.block_len as f64;
+                for c in &mut self.channels[..channels] {
+                    let quiet = c.block_energy < limit;
+                    if quiet && !c.after_loud {
+                        c.quiet_energy += c.block_energy;
+                        c.quiet_samples += self.block_len as u64;
+                    }
+                    c.after_loud = !quiet;
+                    c.block_energy = 0.0;
+                }
+            }
+        }
+    }
+}
+
+impl Node for Diagnostics {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Threshold` is the RMS under which a block counts towards the noise floor; `DC time`
+    /// the time constant of the DC offset's running mean.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Threshold", "dB", -140.0, 0.0, -60.0),
+        ParamDescriptor::log("DC time", "ms", 100.0, 10_000.0, 1000.0),
+    ];
+    /// The audio is only read; the worklet passes it on.
+    const OUTPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        Diagnostics {
+            channels: [Channel::default(); MAX_CHANNELS],
+            threshold_db: -60.0,
+            dc_time_ms: 1000.0,
+            dc_coeff: one_pole_coeff(1000.0, sample_rate_hz),
+            ac_coeff: one_pole_coeff(AC_TIME_MS, sample_rate_hz),
+            threshold: (db_to_lin(-60.0) as f64).powi(2),
+            block_len: ((BLOCK_MS / 1000.0 * sample_rate_hz) as usize).max(1),
+            block_pos: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_THRESHOLD_DB => {
+                self.threshold_db = clamp(value, -140.0, 0.0);
+                self.threshold = (db_to_lin(self.threshold_db) as f64).powi(2);
+            }
+            PARAM_DC_TIME_MS => {
+                self.dc_time_ms = clamp(value, 100.0, 10_000.0);
+                self.dc_coeff = one_pole_coeff(self.dc_time_ms, self.sample_rate_hz);
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_THRESHOLD_DB => self.threshold_db,
+            PARAM_DC_TIME_MS => self.dc_time_ms,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], _output: &mut [f32], channels: usize) {
+        self.measure(input, channels);
+    }
+
+    /// Clears the DC and noise-floor estimates and the counts.
+    fn reset(&mut self) {
+        self.channels = [Channel::default(); MAX_CHANNELS];
+        self.block_pos = 0;
+    }
+}
+
+node_abi::export_node!(Diagnostics {
+    abi_version: diagnostics_abi_version,
+    new: diagnostics_new,
+    free: diagnostics_free,
+    param_count: diagnostics_param_count,
+    param_descriptors: diagnostics_param_descriptors,
+    set_param: diagnostics_set_param,
+    get_param: diagnostics_get_param,
+    param_events: diagnostics_param_events,
+    process: diagnostics_process,
+    process_offline: diagnostics_process_offline,
+    latency_samples: diagnostics_latency_samples,
+    io_layout: diagnostics_get_io_layout,
+    process_io: diagnostics_process_io,
+    reset: diagnostics_reset,
+    state_size: diagnostics_state_size,
+    save_state: diagnostics_save_state,
+    load_state: diagnostics_load_state,
+    preset_size: diagnostics_preset_size,
+    save_preset: diagnostics_save_preset,
+    load_preset: diagnostics_load_preset,
+    last_error_code: diagnostics_last_error_code,
+    last_error_message: diagnostics_last_error_message_ptr,
+});
+
+node_abi::test_params!(Diagnostics);
+
+dsp_core::test_block_sizes!(
+    diagnostics_new,
+    diagnostics_process(input, output),
+    diagnostics_free,
+    read: |p| {
+        (0..2)
+            .flat_map(|c| [diagnostics_get_dc_offset(p, c), diagnostics_get_noise_floor_db(p, c)])
+            .collect::<Vec<_>>()
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Diagnostics as DiagnosticsNode);
+
+/// Every parameter in one call, as `diagnostics_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn diagnostics_set_params(handle: Handle, threshold_db: f32, dc_time_ms: f32) {
+    guard(handle, |d: &mut Diagnostics| {
+        d.set_param(PARAM_THRESHOLD_DB, threshold_db);
+        d.set_param(PARAM_DC_TIME_MS, dc_time_ms);
+    });
+}
+
+/// Runs `read` on `channel`'s readings, if the handle is live and the channel in range.
+fn read_channel<T>(handle: Handle, channel: u32, read: impl FnOnce(&Channel) -> T) -> Option<T> {
+    guard(handle, |d: &mut Diagnostics| {
+        d.channels.get(channel as usize).map(read)
+    })
+    .flatten()
+}
+
+/// DC offset of `channel` as a sample value (0 for a channel out of range).
+#[no_mangle]
+pub extern "C" fn diagnostics_get_dc_offset(handle: Handle, channel: u32) -> f32 {
+    read_channel(handle, channel, |c| c.dc).unwrap_or(0.0)
+}
+
+/// Noise floor of `channel` in dBFS RMS (-Infinity until a block has come in under the
+/// threshold, or for digital silence).
+#[no_mangle]
+pub extern "C" fn diagnostics_get_noise_floor_db(handle: Handle, channel: u32) -> f32 {
+    read_channel(handle, channel, |c| {
+        if c.quiet_samples == 0 || c.quiet_energy <= 0.0 {
+            return f32::NEG_INFINITY;
+        }
+        (10.0 * (c.quiet_energy / c.quiet_samples as f64).log10()) as f32
+    })
+    .unwrap_or(f32::NEG_INFINITY)
+}
+
+/// NaN samples seen on `channel` since creation or the last `diagnostics_reset`.
+#[no_mangle]
+pub extern "C" fn diagnostics_get_nan_count(handle: Handle, channel: u32) -> u32 {
+    read_channel(handle, channel, |c| c.nans).unwrap_or(0)
+}
+
+/// Infinite samples seen on `channel` since creation or the last `diagnostics_reset`.
+#[no_mangle]
+pub extern "C" fn diagnostics_get_inf_count(handle: Handle, channel: u32) -> u32 {
+    read_channel(handle, channel, |c| c.infs).unwrap_or(0)
+}
+
+/// Subnormal samples seen on `channel` since creation or the last `diagnostics_reset`; a
+/// steady count points at a decaying filter or feedback path that needs flushing.
+#[no_mangle]
+pub extern "C" fn diagnostics_get_subnormal_count(handle: Handle, channel: u32) -> u32 {
+    read_channel(handle, channel, |c| c.subnormals).unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// Feeds mono `x` to the node in render quanta.
+    fn feed(h: Handle, x: &[f32]) {
+        render_quanta(x.len(), |span, y| {
+            diagnostics_process(h, x[span].as_ptr(), y.as_mut_ptr(), y.len(), 1)
+        });
+    }
+
+    /// `frames` of uniform noise at `rms`.
+    fn noise(rms: f32, frames: usize, seed: &mut u32) -> Vec<f32> {
+        (0..frames)
+            .map(|_| {
+                *seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                let u = (*seed >> 8) as f32 / (1 << 23) as f32 - 1.0;
+                u * rms * 3.0_f32.sqrt()
+            })
+            .collect()
+    }
+
+    /// The DC offset is the running mean, settling at the set time constant.
+    #[test]
+    fn measures_dc_offset() {
+        let h = diagnostics_new(RATE);
+        let x: Vec<f32> = (0..480_000)
+            .map(|i| 0.1 + 0.5 * (2.0 * PI * 1_000.0 * i as f32 / RATE).sin())
+            .collect();
+        feed(h, &x);
+        assert!((diagnostics_get_dc_offset(h, 0) - 0.1).abs() < 1e-3);
+
+        // A step to 0.2 gets 1 - 1/e of the way in one time constant.
+        diagnostics_set_params(h, -60.0, 100.0);
+        feed(h, &[0.2; 4_800]);
+        let expected = 0.2 - 0.1 * (-1.0_f32).exp();
+        assert!((diagnostics_get_dc_offset(h, 0) - expected).abs() < 1e-3);
+        diagnostics_free(h);
+    }
+
+    /// The noise floor is the RMS of the blocks under the threshold, leaving out loud passages,
+    /// the high-pass settling after them and any DC offset.
+    #[test]
+    fn measures_noise_floor_between_loud_passages() {
+        let h = diagnostics_new(RATE);
+        let mut seed = 1;
+        for _ in 0..10 {
+            let quiet: Vec<f32> = noise(1e-4, 24_000, &mut seed)
+                .iter()
+                .map(|x| x + 0.01)
+                .collect();
+            feed(h, &quiet);
+            feed(h, &noise(0.3, 24_000, &mut seed));
+        }
+        let floor = diagnostics_get_noise_floor_db(h, 0);
+        assert!((floor + 80.0).abs() < 0.5, "{floor}");
+
+        diagnostics_reset(h);
+        assert_eq!(diagnostics_get_noise_floor_db(h, 0), f32::NEG_INFINITY);
+        diagnostics_free(h);
+    }
+
+    /// NaN, infinite and subnormal samples are counted on their own channel until reset.
+    #[test]
+    fn counts_bad_samples_per_channel() {
+        let h = diagnostics_new(RATE);
+        let tiny = f32::MIN_POSITIVE / 4.0;
+        let x = [
+            0.0,
+            f32::NAN,
+            0.5,
+            f32::INFINITY,
+            0.0,
+            f32::NEG_INFINITY,
+            0.0,
+            tiny,
+        ];
+        let mut out = [0.0; 8];
+        diagnostics_process(h, x.as_ptr(), out.as_mut_ptr(), 4, 2);
+        assert_eq!(diagnostics_get_nan_count(h, 1), 1);
+        assert_eq!(diagnostics_get_inf_count(h, 1), 2);
+        assert_eq!(diagnostics_get_subnormal_count(h, 1), 1);
+        assert_eq!(diagnostics_get_nan_count(h, 0), 0);
+        assert!(diagnostics_get_dc_offset(h, 1).is_finite());
+
+        diagnostics_reset(h);
+        assert_eq!(diagnostics_get_inf_count(h, 1), 0);
+        diagnostics_free(h);
+    }
+}
@@ -0,0 +1,386 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::f32::consts::{FRAC_2_PI, PI};
+use dsp_core::biquad::{Biquad, Response};
+use dsp_core::denormal::flush;
+use dsp_core::gain::db_to_lin;
+use dsp_core::halfband::HalfbandOversampler;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing, FLAG_NO_AUTOMATION};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Distortion::PARAMS`, in the order of `distortion_set_params`' arguments.
+pub const PARAM_CURVE: u32 = 0;
+pub const PARAM_DRIVE_DB: u32 = 1;
+pub const PARAM_OUTPUT_DB: u32 = 2;
+pub const PARAM_TONE_HZ: u32 = 3;
+pub const PARAM_TONE_POST: u32 = 4;
+pub const PARAM_OVERSAMPLE: u32 = 5;
+
+/// Corner of the DC blocker after the shaper (the asymmetric curve leaves an offset).
+const DC_BLOCK_HZ: f32 = 10.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+enum Curve {
+    Tanh,
+    Arctan,
+    Hard,
+    /// Forward-biased side saturates at 1, the reverse side at 0.5: even harmonics.
+    Diode,
+    /// `(x + T3/2 + T5/4) / 1.75` on the clipped input; odd harmonics up to the fifth.
+    Chebyshev,
+}
+
+impl Curve {
+    fn from_u32(v: u32) -> Self {
+        match v {
+            1 => Curve::Arctan,
+            2 => Curve::Hard,
+            3 => Curve::Diode,
+            4 => Curve::Chebyshev,
+            _ => Curve::Tanh,
+        }
+    }
+
+    #[inline]
+    fn shape(self, x: f32) -> f32 {
+        match self {
+            Curve::Tanh => x.tanh(),
+            Curve::Arctan => FRAC_2_PI * x.atan(),
+            Curve::Hard => x.clamp(-1.0, 1.0),
+            Curve::Diode => {
+                if x >= 0.0 {
+                    1.0 - (-x).exp()
+                } else {
+                    -0.5 * (1.0 - (2.0 * x).exp())
+                }
+            }
+            Curve::Chebyshev => {
+                let c = x.clamp(-1.0, 1.0);
+                let c2 = c * c;
+                let t3 = c * (4.0 * c2 - 3.0);
+                let t5 = c * (16.0 * c2 * c2 - 20.0 * c2 + 5.0);
+                (c + 0.5 * t3 + 0.25 * t5) / 1.75
+            }
+        }
+    }
+}
+
+/// Waveshaper with input drive and output gain around a selectable curve, optionally
+/// oversampled through half-band filters, and a low-pass tone control either before the drive
+/// (tames fizz going in) or after the output (darkens the result).
+#[repr(C)]
+pub struct Distortion {
+    curve: Curve,
+    drive_db: f32,
+    drive: f32,
+    output_db: f32,
+    output_gain: f32,
+    tone_hz: f32,
+    tone_post: bool,
+    tone: [Biquad; MAX_CHANNELS],
+    oversamplers: Vec<HalfbandOversampler>,
+    dc_coeff: f32,
+    dc_x1: [f32; MAX_CHANNELS],
+    dc_y1: [f32; MAX_CHANNELS],
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Distortion {
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let curve = self.curve;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let mut v = *x;
+                if !self.tone_post {
+                    v = self.tone[ch].process(v);
+                }
+                v = self.oversamplers[ch].process(v * self.drive, |s| curve.shape(s));
+                let dc = v - self.dc_x1[ch] + self.dc_coeff * self.dc_y1[ch];
+                self.dc_x1[ch] = v;
+                self.dc_y1[ch] = flush(dc);
+                v = dc * self.output_gain;
+                if self.tone_post {
+                    v = self.tone[ch].process(v);
+                }
+                *y = v;
+            }
+        }
+    }
+
+    fn update_tone(&mut self) {
+        let tone_hz = self.tone_hz.min(self.sample_rate_hz * 0.45);
+        for t in &mut self.tone {
+            t.set(Response::Lowpass, tone_hz, 0.707, self.sample_rate_hz);
+        }
+    }
+}
+
+impl Node for Distortion {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Curve`: 0 tanh, 1 arctan, 2 hard clip, 3 asymmetric diode, 4 Chebyshev. `Drive` into
+    /// the curve, `Output` after it. `Tone` is the low-pass corner, applied before the drive
+    /// when `Tone post` is off and after the output otherwise. `Oversample` 1, 2 or 4 (see
+    /// `latency_samples`).
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Curve", "", 0.0, 4.0, 0.0),
+        ParamDescriptor::linear("Drive", "dB", 0.0, 48.0, 12.0),
+        ParamDescriptor::linear("Output", "dB", -36.0, 12.0, -6.0),
+        ParamDescriptor::log("Tone", "Hz", 500.0, 20_000.0, 12_000.0),
+        ParamDescriptor::toggle("Tone post", true),
+        ParamDescriptor::stepped("Oversample", "x", 1.0, 4.0, 1.0).with_flags(FLAG_NO_AUTOMATION),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut d = Distortion {
+            curve: Curve::Tanh,
+            drive_db: 12.0,
+            drive: db_to_lin(12.0),
+            output_db: -6.0,
+            output_gain: db_to_lin(-6.0),
+            tone_hz: 12_000.0,
+            tone_post: true,
+            tone: [Biquad::default(); MAX_CHANNELS],
+            oversamplers: (0..MAX_CHANNELS)
+                .map(|_| HalfbandOversampler::new(1))
+                .collect(),
+            dc_coeff: (-2.0 * PI * DC_BLOCK_HZ / sample_rate_hz).exp(),
+            dc_x1: [0.0; MAX_CHANNELS],
+            dc_y1: [0.0; MAX_CHANNELS],
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        d.update_tone();
+        d
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_CURVE => self.curve = Curve::from_u32(clamp(value, 0.0, 4.0).round() as u32),
+            PARAM_DRIVE_DB => {
+                self.drive_db = clamp(value, 0.0, 48.0);
+                self.drive = db_to_lin(self.drive_db);
+            }
+            PARAM_OUTPUT_DB => {
+                self.output_db = clamp(value, -36.0, 12.0);
+                self.output_gain = db_to_lin(self.output_db);
+            }
+            PARAM_TONE_HZ => {
+                self.tone_hz = clamp(value, 500.0, 20_000.0);
+                self.update_tone();
+            }
+            PARAM_TONE_POST => self.tone_post = clamp(value, 0.0, 1.0) >= 0.5,
+            PARAM_OVERSAMPLE => {
+                let factor = clamp(value, 1.0, 4.0).round() as usize;
+                for os in &mut self.oversamplers {
+                    os.set_factor(factor);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_CURVE => self.curve as u32 as f32,
+            PARAM_DRIVE_DB => self.drive_db,
+            PARAM_OUTPUT_DB => self.output_db,
+            PARAM_TONE_HZ => self.tone_hz,
+            PARAM_TONE_POST => u32::from(self.tone_post) as f32,
+            PARAM_OVERSAMPLE => self.oversamplers[0].factor() as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Delay of the half-band filters in samples (17 at 2x, 21 at 4x); 0 without oversampling.
+    fn latency_samples(&self) -> u32 {
+        self.oversamplers[0].latency() as u32
+    }
+
+    /// Clears the tone filters, oversamplers and DC blockers.
+    fn reset(&mut self) {
+        for f in &mut self.tone {
+            f.clear();
+        }
+        for os in &mut self.oversamplers {
+            os.clear();
+        }
+        self.dc_x1 = [0.0; MAX_CHANNELS];
+        self.dc_y1 = [0.0; MAX_CHANNELS];
+    }
+}
+
+node_abi::export_node!(Distortion {
+    abi_version: distortion_abi_version,
+    new: distortion_new,
+    free: distortion_free,
+    param_count: distortion_param_count,
+    param_descriptors: distortion_param_descriptors,
+    set_param: distortion_set_param,
+    get_param: distortion_get_param,
+    param_events: distortion_param_events,
+    process: distortion_process,
+    process_offline: distortion_process_offline,
+    latency_samples: distortion_latency_samples,
+    io_layout: distortion_get_io_layout,
+    process_io: distortion_process_io,
+    reset: distortion_reset,
+    state_size: distortion_state_size,
+    save_state: distortion_save_state,
+    load_state: distortion_load_state,
+    preset_size: distortion_preset_size,
+    save_preset: distortion_save_preset,
+    load_preset: distortion_load_preset,
+    last_error_code: distortion_last_error_code,
+    last_error_message: distortion_last_error_message_ptr,
+});
+
+node_abi::test_params!(Distortion);
+
+dsp_core::test_block_sizes!(
+    distortion_new,
+    distortion_process(input, output),
+    distortion_free
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Distortion as DistortionNode);
+
+/// Every parameter in one call, as `distortion_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn distortion_set_params(
+    handle: Handle,
+    curve: u32,
+    drive_db: f32,
+    output_db: f32,
+    tone_hz: f32,
+    tone_post: u32,
+    oversample: u32,
+) {
+    guard(handle, |d: &mut Distortion| {
+        d.set_param(PARAM_CURVE, curve as f32);
+        d.set_param(PARAM_DRIVE_DB, drive_db);
+        d.set_param(PARAM_OUTPUT_DB, output_db);
+        d.set_param(PARAM_TONE_HZ, tone_hz);
+        d.set_param(PARAM_TONE_POST, tone_post as f32);
+        d.set_param(PARAM_OVERSAMPLE, oversample as f32);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    /// A distortion at unity output with the tone filter fully open, then `params`.
+    fn distortion(params: &[(u32, f32)]) -> Distortion {
+        let mut d = Distortion::new(RATE);
+        d.set_param(PARAM_OUTPUT_DB, 0.0);
+        d.set_param(PARAM_TONE_HZ, 20_000.0);
+        for &(index, value) in params {
+            d.set_param(index, value);
+        }
+        d
+    }
+
+    fn render(d: &mut Distortion, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(d, &input[span], y, 1))
+    }
+
+    /// 1.5 s of a full-scale sine at `hz`.
+    fn sine(hz: f32) -> Vec<f32> {
+        let w = core::f32::consts::TAU * hz / RATE;
+        (0..72_000).map(|i| (w * i as f32).sin()).collect()
+    }
+
+    /// The Chebyshev curve adds exactly its third and fifth harmonics at full scale; the
+    /// symmetric curves add no even harmonics and the diode does.
+    #[test]
+    fn curves_set_the_harmonics() {
+        let x = sine(1000.0);
+        let chebyshev = render(
+            &mut distortion(&[(PARAM_CURVE, 4.0), (PARAM_DRIVE_DB, 0.0)]),
+            &x,
+        );
+        for (hz, want) in [(1000.0, 1.0), (3000.0, 0.5), (5000.0, 0.25)] {
+            let got = amplitude(&chebyshev, hz) * 1.75;
+            assert!((got - want).abs() < 0.02 * want, "{hz}: {got}");
+        }
+        for curve in [0.0, 1.0, 2.0] {
+            let y = render(&mut distortion(&[(PARAM_CURVE, curve)]), &x);
+            assert!(amplitude(&y, 3000.0) > 0.05);
+            assert!(amplitude(&y, 2000.0) < 1e-3);
+        }
+        let diode = render(&mut distortion(&[(PARAM_CURVE, 3.0)]), &x);
+        assert!(amplitude(&diode, 2000.0) > 0.05);
+    }
+
+    /// A post low-pass removes the harmonics the curve made; a pre one only darkens what goes
+    /// in, and the curve makes them again.
+    #[test]
+    fn tone_position_decides_what_it_filters() {
+        let x = sine(500.0);
+        let tone = |post: f32| {
+            let mut d = distortion(&[
+                (PARAM_CURVE, 2.0),
+                (PARAM_DRIVE_DB, 24.0),
+                (PARAM_TONE_HZ, 1000.0),
+                (PARAM_TONE_POST, post),
+            ]);
+            amplitude(&render(&mut d, &x), 4500.0)
+        };
+        assert!(tone(1.0) < 0.1 * tone(0.0));
+    }
+
+    /// Hard-clipping 3.5 kHz folds its 13th harmonic back to 2.5 kHz; 2x oversampling filters
+    /// most of it out first and 4x more, at the latency they report.
+    #[test]
+    fn oversampling_removes_aliases() {
+        let x = sine(3500.0);
+        let alias = |factor: f32, latency: u32| {
+            let mut d = distortion(&[
+                (PARAM_CURVE, 2.0),
+                (PARAM_DRIVE_DB, 24.0),
+                (PARAM_OVERSAMPLE, factor),
+            ]);
+            assert_eq!(Node::latency_samples(&d), latency);
+            amplitude(&render(&mut d, &x), 2500.0)
+        };
+        let aliased = alias(1.0, 0);
+        assert!(aliased > 0.05);
+        let (twice, four_times) = (alias(2.0, 17), alias(4.0, 21));
+        assert!(twice < aliased * db_to_lin(-20.0));
+        assert!(four_times < twice && four_times < aliased * db_to_lin(-30.0));
+    }
+}
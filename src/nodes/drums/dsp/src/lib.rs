@@ -0,0 +1,536 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::f32::consts::PI;
+use dsp_core::events::{EventRing, NoteEvent, ALL_NOTES_OFF, NOTE_ON};
+use dsp_core::gain::db_to_lin;
+use dsp_core::oscillator::{Oscillator, Waveform};
+use dsp_core::param::clamp;
+use dsp_core::svf::{Coeffs, SvfState};
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+pub const KICK: usize = 0;
+pub const SNARE: usize = 1;
+pub const HAT: usize = 2;
+pub const VOICES: usize = 3;
+
+// Indices into `Drums::PARAMS`: voice `v`'s (`KICK`, `SNARE`, `HAT`) start at
+// `v * VOICE_PARAMS`, in the order of `drums_set_voice`'s arguments.
+pub const VOICE_PARAMS: u32 = 4;
+pub const PARAM_TUNE: u32 = 0;
+pub const PARAM_DECAY_MS: u32 = 1;
+pub const PARAM_TONE: u32 = 2;
+pub const PARAM_LEVEL_DB: u32 = 3;
+
+/// A voice is silent (and skipped) once its envelope falls below this (-80 dB).
+const SILENT: f32 = 1e-4;
+
+/// Kick pitch at tune 0, and how fast its sweep settles (time constant).
+const KICK_HZ: f32 = 50.0;
+const KICK_SWEEP_SECONDS: f32 = 0.03;
+/// Snare shell modes at tune 0, and the corner of the high-pass on its noise.
+const SNARE_HZ: [f32; 2] = [185.0, 330.0];
+const SNARE_NOISE_HZ: f32 = 1500.0;
+/// The six square waves of the TR-808 hat at tune 0: inharmonic, so they beat into a metallic
+/// clang.
+const HAT_HZ: [f32; 6] = [205.3, 304.4, 369.6, 522.7, 540.0, 800.0];
+/// An open hat (note 46) rings this many times longer than the set decay; a closed hat chokes it.
+const OPEN_HAT_FACTOR: f32 = 6.0;
+
+/// Which voice a note plays, on the General MIDI drum map: bass drums, snares (and rim and
+/// clap), hats. Other notes are ignored.
+fn voice_for(note: u32) -> Option<usize> {
+    match note {
+        35 | 36 => Some(KICK),
+        37..=40 => Some(SNARE),
+        42 | 44 | 46 => Some(HAT),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy)]
+struct VoiceParams {
+    tune: f32,
+    decay_ms: f32,
+    decay_s: f32,
+    tone: f32,
+    level_db: f32,
+    gain: f32,
+}
+
+/// Drum machine with three synthesized voices, each with its own tune, decay, tone and level:
+/// - kick: a sine swept down from up to four octaves above its pitch (`tone` sets how far);
+/// - snare: two sine shell modes and high-passed noise (`tone` cross-fades from shell to
+///   noise), the shell dying at twice the rate of the noise;
+/// - hat: six inharmonic band-limited square waves through a high-pass (`tone` moves it from
+///   2 to 11 kHz).
+///
+/// Decay is the time to fall 60 dB. Triggers come through the same event ring as the synths,
+/// mapped as General MIDI drums; velocity sets the level and note-offs are ignored.
+pub struct Drums {
+    notes: EventRing,
+    params: [VoiceParams; VOICES],
+    /// Amplitude envelopes and their per-sample decay factors.
+    env: [f32; VOICES],
+    decay: [f32; VOICES],
+    kick_phase: f32,
+    kick_sweep: f32,
+    kick_sweep_decay: f32,
+    snare_phase: [f32; 2],
+    snare_hp: SvfState,
+    snare_hp_coeffs: Coeffs,
+    hat_oscs: [Oscillator; 6],
+    hat_hp: SvfState,
+    hat_hp_coeffs: Coeffs,
+    rng: u32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Drums {
+    /// Uniform in [-1, 1) from a xorshift32.
+    #[inline]
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 7) as f32 / (1u32 << 24) as f32 - 1.0
+    }
+
+    /// Per-sample factor falling 60 dB in `seconds`.
+    fn decay_factor(&self, seconds: f32) -> f32 {
+        10.0_f32.powf(-3.0 / (seconds * self.sample_rate_hz))
+    }
+
+    /// Recomputes voice `index`'s filters and oscillator rates from its params.
+    fn update(&mut self, index: usize) {
+        let sr = self.sample_rate_hz;
+        let p = self.params[index];
+        let ratio = (p.tune / 12.0).exp2();
+        match index {
+            SNARE => self.snare_hp_coeffs = Coeffs::new(SNARE_NOISE_HZ * ratio, 1.414, sr),
+            HAT => {
+                for (osc, hz) in self.hat_oscs.iter_mut().zip(HAT_HZ) {
+                    osc.set_frequency(hz * ratio, sr);
+                }
+                self.hat_hp_coeffs = Coeffs::new(2000.0 * (2.5 * p.tone).exp2(), 1.0, sr);
+            }
+            _ => {}
+        }
+    }
+
+    fn trigger(&mut self, note: u32, velocity: f32) {
+        let Some(index) = voice_for(note) else {
+            return;
+        };
+        let p = self.params[index];
+        let decay_s = if note == 46 {
+            p.decay_s * OPEN_HAT_FACTOR
+        } else {
+            p.decay_s
+        };
+        self.decay[index] = self.decay_factor(decay_s);
+        self.env[index] = velocity;
+        // The sines restart from zero so every hit has the same attack.
+        match index {
+            KICK => {
+                self.kick_phase = 0.0;
+                self.kick_sweep = 1.0;
+            }
+            SNARE => self.snare_phase = [0.0; 2],
+            _ => {}
+        }
+    }
+
+    fn handle(&mut self, e: NoteEvent) {
+        match e.kind {
+            NOTE_ON if e.value > 0.0 => self.trigger(e.note, clamp(e.value, 0.0, 1.0)),
+            ALL_NOTES_OFF => self.env = [0.0; VOICES],
+            _ => {}
+        }
+    }
+
+    fn render(&mut self, output: &mut [f32], channels: usize) {
+        let sr = self.sample_rate_hz;
+        let kick_hz = KICK_HZ * (self.params[KICK].tune / 12.0).exp2();
+        let kick_octaves = 4.0 * self.params[KICK].tone;
+        let snare_ratio = (self.params[SNARE].tune / 12.0).exp2();
+        for out in output.chunks_exact_mut(channels) {
+            let mut sum = 0.0;
+            if self.env[KICK] > SILENT {
+                let hz = kick_hz * (kick_octaves * self.kick_sweep).exp2();
+                let y = (2.0 * PI * self.kick_phase).sin();
+                self.kick_phase = (self.kick_phase + hz / sr).fract();
+                self.kick_sweep *= self.kick_sweep_decay;
+                sum += y * self.env[KICK] * self.params[KICK].gain;
+                self.env[KICK] *= self.decay[KICK];
+            }
+            if self.env[SNARE] > SILENT {
+                let mut shell = 0.0;
+                for (phase, hz) in self.snare_phase.iter_mut().zip(SNARE_HZ) {
+                    shell += 0.5 * (2.0 * PI * *phase).sin();
+                    *phase = (*phase + hz * snare_ratio / sr).fract();
+                }
+                let noise = self.random();
+                let (_, _, noise) = self.snare_hp.process(noise, &self.snare_hp_coeffs);
+                let env = self.env[SNARE];
+                let tone = self.params[SNARE].tone;
+                let y = (1.0 - tone) * shell * env * env + tone * noise * env;
+                sum += y * self.params[SNARE].gain;
+                self.env[SNARE] *= self.decay[SNARE];
+            }
+            if self.env[HAT] > SILENT {
+                let metal = self
+                    .hat_oscs
+                    .iter_mut()
+                    .map(|osc| osc.next(Waveform::Square))
+                    .sum::<f32>()
+                    / HAT_HZ.len() as f32;
+                let (_, _, y) = self.hat_hp.process(metal, &self.hat_hp_coeffs);
+                sum += y * self.env[HAT] * self.params[HAT].gain;
+                self.env[HAT] *= self.decay[HAT];
+            }
+            out.fill(sum);
+        }
+    }
+}
+
+impl Node for Drums {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// Per voice (kick, snare, hat): `Tune`, `Decay` to fall 60 dB, `Tone` (kick sweep depth,
+    /// snare noise mix, hat brightness) and `Level`. Take effect from the next hit.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Kick tune", "st", -24.0, 24.0, 0.0),
+        ParamDescriptor::log("Kick decay", "ms", 10.0, 2000.0, 500.0),
+        ParamDescriptor::linear("Kick tone", "", 0.0, 1.0, 0.5),
+        ParamDescriptor::linear("Kick level", "dB", -60.0, 12.0, -6.0),
+        ParamDescriptor::linear("Snare tune", "st", -24.0, 24.0, 0.0),
+        ParamDescriptor::log("Snare decay", "ms", 10.0, 2000.0, 250.0),
+        ParamDescriptor::linear("Snare tone", "", 0.0, 1.0, 0.6),
+        ParamDescriptor::linear("Snare level", "dB", -60.0, 12.0, -6.0),
+        ParamDescriptor::linear("Hat tune", "st", -24.0, 24.0, 0.0),
+        ParamDescriptor::log("Hat decay", "ms", 10.0, 2000.0, 80.0),
+        ParamDescriptor::linear("Hat tone", "", 0.0, 1.0, 0.5),
+        ParamDescriptor::linear("Hat level", "dB", -60.0, 12.0, -6.0),
+    ];
+    const INPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let voice = |decay_ms: f32, tone: f32| VoiceParams {
+            tune: 0.0,
+            decay_ms,
+            decay_s: decay_ms / 1000.0,
+            tone,
+            level_db: -6.0,
+            gain: db_to_lin(-6.0),
+        };
+        let mut d = Drums {
+            notes: EventRing::new(),
+            params: [voice(500.0, 0.5), voice(250.0, 0.6), voice(80.0, 0.5)],
+            env: [0.0; VOICES],
+            decay: [0.0; VOICES],
+            kick_phase: 0.0,
+            kick_sweep: 0.0,
+            kick_sweep_decay: (-1.0 / (KICK_SWEEP_SECONDS * sample_rate_hz)).exp(),
+            snare_phase: [0.0; 2],
+            snare_hp: SvfState::default(),
+            snare_hp_coeffs: Coeffs::new(SNARE_NOISE_HZ, 1.414, sample_rate_hz),
+            hat_oscs: [Oscillator::new(); 6],
+            hat_hp: SvfState::default(),
+            hat_hp_coeffs: Coeffs::new(2000.0, 1.0, sample_rate_hz),
+            rng: 0x9e37_79b9,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        for index in 0..VOICES {
+            d.update(index);
+        }
+        d
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        let voice = (index / VOICE_PARAMS) as usize;
+        let Some(p) = self.params.get_mut(voice) else {
+            return;
+        };
+        match index % VOICE_PARAMS {
+            PARAM_TUNE => p.tune = clamp(value, -24.0, 24.0),
+            PARAM_DECAY_MS => {
+                p.decay_ms = clamp(value, 10.0, 2000.0);
+                p.decay_s = p.decay_ms / 1000.0;
+            }
+            PARAM_TONE => p.tone = clamp(value, 0.0, 1.0),
+            PARAM_LEVEL_DB => {
+                p.level_db = clamp(value, -60.0, 12.0);
+                p.gain = db_to_lin(p.level_db);
+            }
+            _ => return,
+        }
+        self.update(voice);
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        let Some(p) = self.params.get((index / VOICE_PARAMS) as usize) else {
+            return 0.0;
+        };
+        match index % VOICE_PARAMS {
+            PARAM_TUNE => p.tune,
+            PARAM_DECAY_MS => p.decay_ms,
+            PARAM_TONE => p.tone,
+            PARAM_LEVEL_DB => p.level_db,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn note_events(&mut self) -> Option<&mut EventRing> {
+        Some(&mut self.notes)
+    }
+
+    fn note(&mut self, event: NoteEvent) {
+        self.handle(event);
+    }
+
+    /// Renders the same mono mix on every channel.
+    fn process(&mut self, _input: &[f32], output: &mut [f32], channels: usize) {
+        self.render(output, channels);
+    }
+
+    /// Silences every voice and clears the noise filters; queued events are kept.
+    fn reset(&mut self) {
+        self.env = [0.0; VOICES];
+        self.kick_phase = 0.0;
+        self.kick_sweep = 0.0;
+        self.snare_phase = [0.0; 2];
+        self.snare_hp.reset();
+        for osc in &mut self.hat_oscs {
+            osc.reset(0.0);
+        }
+        self.hat_hp.reset();
+        self.rng = 0x9e37_79b9;
+    }
+}
+
+node_abi::export_node!(Drums {
+    abi_version: drums_abi_version,
+    new: drums_new,
+    free: drums_free,
+    param_count: drums_param_count,
+    param_descriptors: drums_param_descriptors,
+    set_param: drums_set_param,
+    get_param: drums_get_param,
+    param_events: drums_param_events,
+    process: drums_process,
+    process_offline: drums_process_offline,
+    latency_samples: drums_latency_samples,
+    io_layout: drums_get_io_layout,
+    process_io: drums_process_io,
+    reset: drums_reset,
+    state_size: drums_state_size,
+    save_state: drums_save_state,
+    load_state: drums_load_state,
+    preset_size: drums_preset_size,
+    save_preset: drums_save_preset,
+    load_preset: drums_load_preset,
+    last_error_code: drums_last_error_code,
+    last_error_message: drums_last_error_message_ptr,
+});
+
+node_abi::test_params!(Drums);
+
+dsp_core::test_block_sizes!(
+    drums_new,
+    drums_process(input, output),
+    drums_free,
+    setup: |p| {
+        for n in [36, 38, 46] {
+            let events = unsafe { &mut *drums_events(p) };
+            events.push(NoteEvent { kind: NOTE_ON, note: n, value: 1.0, frame: 0 });
+        }
+    },
+);
+
+node_abi::test_io_block!(
+    Drums,
+    setup: |d: &mut Drums| {
+        for n in [36, 38, 46] {
+            d.notes.push(NoteEvent { kind: NOTE_ON, note: n, value: 1.0, frame: 0 });
+        }
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Drums as DrumsNode);
+
+/// The note-event ring inside the node, for the worklet to write into (layout in
+/// `dsp_core::events`). Stays put for the node's lifetime.
+#[no_mangle]
+pub extern "C" fn drums_events(handle: Handle) -> *mut EventRing {
+    guard(handle, |d: &mut Drums| &mut d.notes as *mut EventRing).unwrap_or(core::ptr::null_mut())
+}
+
+/// Voice `index`'s parameters in one call (0 kick, 1 snare, 2 hat), as `drums_set_param` sets
+/// them.
+#[no_mangle]
+pub extern "C" fn drums_set_voice(
+    handle: Handle,
+    index: u32,
+    tune_semitones: f32,
+    decay_ms: f32,
+    tone: f32,
+    level_db: f32,
+) {
+    if index as usize >= VOICES {
+        return;
+    }
+    guard(handle, |d: &mut Drums| {
+        let base = index * VOICE_PARAMS;
+        d.set_param(base + PARAM_TUNE, tune_semitones);
+        d.set_param(base + PARAM_DECAY_MS, decay_ms);
+        d.set_param(base + PARAM_TONE, tone);
+        d.set_param(base + PARAM_LEVEL_DB, level_db);
+    });
+}
+
+/// Bit per voice (kick first) that is sounding, for the UI.
+#[no_mangle]
+pub extern "C" fn drums_active_voices(handle: Handle) -> u32 {
+    guard(handle, |d: &mut Drums| {
+        (0..VOICES)
+            .filter(|&i| d.env[i] > SILENT)
+            .fold(0, |bits, i| bits | 1 << i)
+    })
+    .unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    fn send(h: Handle, note: u32, velocity: f32) {
+        let events = unsafe { &mut *drums_events(h) };
+        assert!(events.push(NoteEvent {
+            kind: NOTE_ON,
+            note,
+            value: velocity,
+            frame: 0,
+        }));
+    }
+
+    fn render(h: Handle, frames: usize) -> Vec<f32> {
+        render_quanta(frames, |_, y| {
+            drums_process(h, core::ptr::null(), y.as_mut_ptr(), y.len(), 1)
+        })
+    }
+
+    /// `frames` of voice `index` set to `tune`, `decay_ms` and `tone` at 0 dB, hit by `note`.
+    fn hit(index: u32, note: u32, tune: f32, decay_ms: f32, tone: f32, frames: usize) -> Vec<f32> {
+        let h = drums_new(RATE);
+        drums_set_voice(h, index, tune, decay_ms, tone, 0.0);
+        send(h, note, 1.0);
+        let y = render(h, frames);
+        drums_free(h);
+        y
+    }
+
+    fn peak(x: &[f32]) -> f32 {
+        x.iter().fold(0.0, |m, v| m.max(v.abs()))
+    }
+
+    /// Notes map onto the voices by the General MIDI drum map; others are ignored.
+    #[test]
+    fn notes_follow_the_drum_map() {
+        for (note, bits) in [(36, 0b001), (35, 0b001), (38, 0b010), (42, 0b100), (60, 0)] {
+            let h = drums_new(RATE);
+            send(h, note, 1.0);
+            render(h, 128);
+            assert_eq!(drums_active_voices(h), bits, "note {note}");
+            drums_free(h);
+        }
+    }
+
+    /// The kick starts high and settles on its tuned pitch; `Tone` 0 doesn't sweep at all.
+    #[test]
+    fn kick_sweeps_down_to_its_pitch() {
+        // Samples to the first downward zero crossing: half a cycle.
+        let half_cycle = |y: &[f32]| (1..y.len()).find(|&i| y[i - 1] > 0.0 && y[i] <= 0.0);
+        let flat = hit(KICK as u32, 36, 0.0, 2000.0, 0.0, 4_800);
+        assert_eq!(half_cycle(&flat), Some(480));
+        let swept = hit(KICK as u32, 36, 0.0, 2000.0, 0.5, 48_000);
+        assert!(half_cycle(&swept).unwrap() < 200);
+        // 0.5 s on, one cycle of the settled 100 Hz (tune +12) kick is 480 samples.
+        let tuned = hit(KICK as u32, 36, 12.0, 2000.0, 0.5, 48_000);
+        let late = &tuned[24_000..];
+        let start = half_cycle(late).unwrap();
+        let next = start + half_cycle(&late[start..]).unwrap();
+        assert!((next - start).abs_diff(480) <= 1, "{}", next - start);
+    }
+
+    /// A voice falls 60 dB over its decay: 30 dB halfway.
+    #[test]
+    fn decay_sets_the_fall_time() {
+        let y = hit(KICK as u32, 36, 0.0, 500.0, 0.0, 24_000);
+        let (start, half) = (peak(&y[..960]), peak(&y[12_000..12_960]));
+        let drop = 20.0 * (half / start).log10();
+        assert!((drop + 30.0).abs() < 1.0, "{drop} dB");
+        // The first crest, a quarter cycle in, has already fallen 0.6 dB.
+        assert!((start - 10.0_f32.powf(-0.03)).abs() < 0.01, "{start}");
+    }
+
+    /// Velocity scales the hit.
+    #[test]
+    fn velocity_sets_the_level() {
+        let h = drums_new(RATE);
+        drums_set_voice(h, KICK as u32, 0.0, 500.0, 0.0, 0.0);
+        send(h, 36, 0.5);
+        let y = render(h, 960);
+        drums_free(h);
+        assert!((peak(&y) - 0.5 * 10.0_f32.powf(-0.03)).abs() < 0.01);
+    }
+
+    /// Snare `Tone` cross-fades from the tuned shell to bright noise.
+    #[test]
+    fn snare_tone_mixes_shell_and_noise() {
+        // Share of the signal's power in its sample-to-sample differences: high for noise.
+        let brightness = |y: &[f32]| {
+            let power = y.iter().map(|v| v * v).sum::<f32>();
+            let diff = y.windows(2).map(|w| (w[1] - w[0]).powi(2)).sum::<f32>();
+            diff / power
+        };
+        let shell = hit(SNARE as u32, 38, 0.0, 250.0, 0.0, 4_800);
+        let noise = hit(SNARE as u32, 38, 0.0, 250.0, 1.0, 4_800);
+        assert!(brightness(&shell) < 0.01, "{}", brightness(&shell));
+        assert!(brightness(&noise) > 1.0, "{}", brightness(&noise));
+    }
+
+    /// An open hat rings six times longer than a closed one, and a closed hat chokes it.
+    #[test]
+    fn closed_hat_chokes_the_open_one() {
+        let h = drums_new(RATE);
+        send(h, 46, 1.0);
+        render(h, 14_400);
+        assert_eq!(drums_active_voices(h), 0b100);
+        send(h, 42, 1.0);
+        render(h, 7_680);
+        assert_eq!(drums_active_voices(h), 0);
+        drums_free(h);
+    }
+}
@@ -0,0 +1,313 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::envelope::EnvelopeFollower;
+use dsp_core::gain::db_to_lin;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Ducker::PARAMS`, in the order of `ducker_set_params`' arguments.
+pub const PARAM_THRESHOLD_DB: u32 = 0;
+pub const PARAM_DEPTH_DB: u32 = 1;
+pub const PARAM_ATTACK_MS: u32 = 2;
+pub const PARAM_HOLD_MS: u32 = 3;
+pub const PARAM_RELEASE_MS: u32 = 4;
+
+/// Decay of the key detector so the level doesn't drop out at every zero crossing.
+const DETECTOR_RELEASE_MS: f32 = 20.0;
+
+/// Voice-over ducker: while the key is above the threshold (and for `hold` after) the program
+/// is pulled down by `depth`, fading with attack/release.
+#[repr(C)]
+pub struct Ducker {
+    threshold_db: f32,
+    threshold: f32,
+    depth_db: f32,
+    attack_ms: f32,
+    hold_ms: f32,
+    release_ms: f32,
+    hold_samples: u32,
+    hold_left: u32,
+    key_level: EnvelopeFollower,
+    /// Attenuation in dB (>= 0).
+    duck: EnvelopeFollower,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Ducker {
+    fn update_times(&mut self) {
+        self.duck
+            .set_times(self.attack_ms, self.release_ms, self.sample_rate_hz);
+        self.hold_samples = (self.hold_ms / 1000.0 * self.sample_rate_hz).round() as u32;
+    }
+
+    /// `key` is `None` when nothing is connected; the duck then releases as if the key were
+    /// silent.
+    fn duck(
+        &mut self,
+        input: &[f32],
+        key: Option<(&[f32], usize)>,
+        output: &mut [f32],
+        channels: usize,
+    ) {
+        let frames = input.len() / channels;
+        for i in 0..frames {
+            let peak = key.map_or(0.0, |(key, key_channels)| {
+                key[i * key_channels..(i + 1) * key_channels]
+                    .iter()
+                    .fold(0.0_f32, |m, x| m.max(x.abs()))
+            });
+            let level = self.key_level.process(peak);
+            let target = if level >= self.threshold {
+                self.hold_left = self.hold_samples;
+                self.depth_db
+            } else if self.hold_left > 0 {
+                self.hold_left -= 1;
+                self.depth_db
+            } else {
+                0.0
+            };
+            let g = db_to_lin(-self.duck.process(target));
+            for ch in 0..channels {
+                output[i * channels + ch] = input[i * channels + ch] * g;
+            }
+        }
+    }
+}
+
+impl Node for Ducker {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Depth` is how far the program is pulled down while the key is active.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Threshold", "dB", -80.0, 0.0, -30.0),
+        ParamDescriptor::linear("Depth", "dB", 0.0, 60.0, 12.0),
+        ParamDescriptor::log("Attack", "ms", 0.1, 1000.0, 10.0),
+        ParamDescriptor::linear("Hold", "ms", 0.0, 5000.0, 200.0),
+        ParamDescriptor::log("Release", "ms", 1.0, 10000.0, 400.0),
+    ];
+    /// The key (loudest channel wins).
+    const AUX_CHANNELS: usize = MAX_CHANNELS;
+    const METER_COUNT: usize = 1;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut d = Ducker {
+            threshold_db: -30.0,
+            threshold: db_to_lin(-30.0),
+            depth_db: 12.0,
+            attack_ms: 10.0,
+            hold_ms: 200.0,
+            release_ms: 400.0,
+            hold_samples: 0,
+            hold_left: 0,
+            key_level: EnvelopeFollower::new(0.0, DETECTOR_RELEASE_MS, sample_rate_hz),
+            duck: EnvelopeFollower::new(10.0, 400.0, sample_rate_hz),
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        d.update_times();
+        d
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_THRESHOLD_DB => {
+                self.threshold_db = clamp(value, -80.0, 0.0);
+                self.threshold = db_to_lin(self.threshold_db);
+            }
+            PARAM_DEPTH_DB => self.depth_db = clamp(value, 0.0, 60.0),
+            PARAM_ATTACK_MS => {
+                self.attack_ms = clamp(value, 0.1, 1000.0);
+                self.update_times();
+            }
+            PARAM_HOLD_MS => {
+                self.hold_ms = clamp(value, 0.0, 5000.0);
+                self.update_times();
+            }
+            PARAM_RELEASE_MS => {
+                self.release_ms = clamp(value, 1.0, 10000.0);
+                self.update_times();
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_THRESHOLD_DB => self.threshold_db,
+            PARAM_DEPTH_DB => self.depth_db,
+            PARAM_ATTACK_MS => self.attack_ms,
+            PARAM_HOLD_MS => self.hold_ms,
+            PARAM_RELEASE_MS => self.release_ms,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    /// Without a key the duck releases as if the key were silent.
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.duck(input, None, output, channels);
+    }
+
+    fn process_aux(
+        &mut self,
+        input: &[f32],
+        aux: &[f32],
+        aux_channels: usize,
+        output: &mut [f32],
+        channels: usize,
+    ) {
+        let key = (!aux.is_empty()).then_some((aux, aux_channels));
+        self.duck(input, key, output, channels);
+    }
+
+    /// Attenuation as `ducker_get_duck_db` reads it.
+    fn meters(&self, out: &mut [f32]) {
+        out[0] = self.duck.value();
+    }
+
+    /// Lifts the ducking at once and clears the key detector and hold.
+    fn reset(&mut self) {
+        self.key_level.reset(0.0);
+        self.duck.reset(0.0);
+        self.hold_left = 0;
+    }
+}
+
+node_abi::export_node!(Ducker {
+    abi_version: ducker_abi_version,
+    new: ducker_new,
+    free: ducker_free,
+    param_count: ducker_param_count,
+    param_descriptors: ducker_param_descriptors,
+    set_param: ducker_set_param,
+    get_param: ducker_get_param,
+    param_events: ducker_param_events,
+    process: ducker_process,
+    process_offline: ducker_process_offline,
+    latency_samples: ducker_latency_samples,
+    io_layout: ducker_get_io_layout,
+    process_io: ducker_process_io,
+    reset: ducker_reset,
+    state_size: ducker_state_size,
+    save_state: ducker_save_state,
+    load_state: ducker_load_state,
+    preset_size: ducker_preset_size,
+    save_preset: ducker_save_preset,
+    load_preset: ducker_load_preset,
+    last_error_code: ducker_last_error_code,
+    last_error_message: ducker_last_error_message_ptr,
+    process_aux: ducker_process_sidechained,
+    process_aux_offline: ducker_process_offline_sidechained,
+    process_io_aux: ducker_process_io_sidechained,
+});
+
+node_abi::test_params!(Ducker);
+
+dsp_core::test_block_sizes!(
+    ducker_new,
+    ducker_process_sidechained(input, aux, output),
+    ducker_free
+);
+
+node_abi::test_io_block!(Ducker);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Ducker as DuckerNode);
+
+/// Every parameter in one call, as `ducker_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn ducker_set_params(
+    handle: Handle,
+    threshold_db: f32,
+    depth_db: f32,
+    attack_ms: f32,
+    hold_ms: f32,
+    release_ms: f32,
+) {
+    guard(handle, |d: &mut Ducker| {
+        d.set_param(PARAM_THRESHOLD_DB, threshold_db);
+        d.set_param(PARAM_DEPTH_DB, depth_db);
+        d.set_param(PARAM_ATTACK_MS, attack_ms);
+        d.set_param(PARAM_HOLD_MS, hold_ms);
+        d.set_param(PARAM_RELEASE_MS, release_ms);
+    });
+}
+
+/// Current attenuation in dB (positive = ducking).
+#[no_mangle]
+pub extern "C" fn ducker_get_duck_db(handle: Handle) -> f32 {
+    guard(handle, |d: &mut Ducker| d.duck.value()).unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// A held 0.5 program keyed by `key`, mono, in render quanta; returns the gain per sample
+    /// in dB, with the meter reading after the last quantum.
+    fn duck_db(key: &[f32]) -> (Vec<f32>, f32) {
+        let mut d = Ducker::new(RATE);
+        let input = vec![0.5; key.len()];
+        let output = render_quanta(key.len(), |span, y| {
+            Node::process_aux(&mut d, &input[span.clone()], &key[span], 1, y, 1)
+        });
+        let mut meters = [0.0];
+        Node::meters(&d, &mut meters);
+        let gain_db = output.iter().map(|y| 20.0 * (y / 0.5).log10()).collect();
+        (gain_db, meters[0])
+    }
+
+    /// A second of key over the threshold, then three of silence.
+    fn voice_over(level: f32) -> Vec<f32> {
+        let mut key = vec![level; 48_000];
+        key.resize(4 * 48_000, 0.0);
+        key
+    }
+
+    /// The key pulls the program down by `Depth`, holds it there after it stops, then
+    /// releases.
+    #[test]
+    fn ducks_by_depth_while_the_key_talks() {
+        let (gain_db, meter) = duck_db(&voice_over(0.5));
+        assert!((gain_db[47_999] + 12.0).abs() < 0.01, "{}", gain_db[47_999]);
+        // 55 ms for the detector to fall under -30 dB, then the 200 ms hold.
+        assert!((gain_db[48_000 + 240 * 48] + 12.0).abs() < 0.01);
+        assert!(gain_db[48_000 + 700 * 48] > -6.0);
+        let end = gain_db[4 * 48_000 - 1];
+        assert!(end.abs() < 0.05 && meter < 0.05, "{end} {meter}");
+    }
+
+    /// A key under the threshold, or none at all, leaves the program alone.
+    #[test]
+    fn quiet_or_missing_key_passes_the_program() {
+        let (gain_db, meter) = duck_db(&voice_over(0.01));
+        assert!(gain_db.iter().all(|&g| g == 0.0));
+        assert_eq!(meter, 0.0);
+
+        let mut d = Ducker::new(RATE);
+        let input = vec![0.5; 128];
+        let mut output = vec![0.0; 128];
+        Node::process(&mut d, &input, &mut output, 1);
+        assert_eq!(output, input);
+    }
+}
@@ -0,0 +1,442 @@
+// `dyneq_set_params` reads the block through a pointer the worklet got from us; null is the
+// only invalid value we can detect.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::biquad::{Biquad, Response};
+use dsp_core::envelope::EnvelopeFollower;
+use dsp_core::gain::lin_to_db_floor;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_BANDS: usize = 4;
+
+// Indices into `DynamicEq::PARAMS`: band `b`'s start at `b * BAND_PARAMS`, in the order of
+// `DynEqBand`'s fields.
+pub const BAND_PARAMS: u32 = 9;
+pub const PARAM_KIND: u32 = 0;
+pub const PARAM_ENABLED: u32 = 1;
+pub const PARAM_FREQ_HZ: u32 = 2;
+pub const PARAM_Q: u32 = 3;
+pub const PARAM_THRESHOLD_DB: u32 = 4;
+pub const PARAM_RATIO: u32 = 5;
+pub const PARAM_ATTACK_MS: u32 = 6;
+pub const PARAM_RELEASE_MS: u32 = 7;
+pub const PARAM_RANGE_DB: u32 = 8;
+
+/// Band filters are redesigned from the envelopes every this many frames.
+const CONTROL_INTERVAL: usize = 16;
+const SHELF_SIDECHAIN_Q: f32 = 0.707;
+
+/// One band as laid out in shared memory. `kind`: 0 bell, 1 low shelf, 2 high shelf.
+/// Above `threshold_db` the band's gain moves by `(1 - 1/ratio)` dB per dB of overshoot, in the
+/// direction and up to the size of `range_db` (negative cuts, positive boosts).
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DynEqBand {
+    pub kind: u32,
+    pub enabled: u32,
+    pub freq_hz: f32,
+    pub q: f32,
+    pub threshold_db: f32,
+    pub ratio: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    pub range_db: f32,
+}
+
+/// The parameter block the worklet writes into a `wasm_alloc`ed buffer of
+/// `dyneq_params_size()` bytes before calling `dyneq_set_params`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DynEqParams {
+    pub bands: [DynEqBand; MAX_BANDS],
+}
+
+const DISABLED: DynEqBand = DynEqBand {
+    kind: 0,
+    enabled: 0,
+    freq_hz: 1000.0,
+    q: 1.0,
+    threshold_db: -24.0,
+    ratio: 2.0,
+    attack_ms: 5.0,
+    release_ms: 120.0,
+    range_db: -6.0,
+};
+
+fn response_for(kind: u32) -> Response {
+    match kind {
+        1 => Response::LowShelf,
+        2 => Response::HighShelf,
+        _ => Response::Peak,
+    }
+}
+
+/// The detector listens to the region the band acts on.
+fn sidechain_for(kind: u32) -> (Response, Option<f32>) {
+    match kind {
+        1 => (Response::Lowpass, Some(SHELF_SIDECHAIN_Q)),
+        2 => (Response::Highpass, Some(SHELF_SIDECHAIN_Q)),
+        _ => (Response::Bandpass, None),
+    }
+}
+
+#[repr(C)]
+pub struct DynamicEq {
+    params: DynEqParams,
+    active: [usize; MAX_BANDS],
+    active_count: usize,
+    sidechains: [[Biquad; MAX_BANDS]; MAX_CHANNELS],
+    filters: [[Biquad; MAX_BANDS]; MAX_CHANNELS],
+    /// Smoothed gain change magnitude in dB (>= 0), linked across channels.
+    envelopes: [EnvelopeFollower; MAX_BANDS],
+    countdown: usize,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl DynamicEq {
+    fn apply(&mut self, params: &DynEqParams) {
+        let sr = self.sample_rate_hz;
+        let nyquist = sr * 0.5;
+        self.active_count = 0;
+        for (i, band) in params.bands.iter().enumerate() {
+            let band = DynEqBand {
+                kind: band.kind.min(2),
+                enabled: if band.enabled != 0 { 1 } else { 0 },
+                freq_hz: clamp(band.freq_hz, 20.0, (nyquist * 0.98).min(20_000.0)),
+                q: clamp(band.q, 0.1, 24.0),
+                threshold_db: clamp(band.threshold_db, -60.0, 0.0),
+                ratio: clamp(band.ratio, 1.0, 20.0),
+                attack_ms: clamp(band.attack_ms, 0.1, 500.0),
+                release_ms: clamp(band.release_ms, 1.0, 5000.0),
+                range_db: clamp(band.range_db, -24.0, 24.0),
+            };
+            let was_enabled = self.params.bands[i].enabled != 0;
+            self.params.bands[i] = band;
+            self.envelopes[i].set_times(band.attack_ms, band.release_ms, sr);
+            if band.enabled == 0 {
+                continue;
+            }
+            self.active[self.active_count] = i;
+            self.active_count += 1;
+            let (sc_response, sc_q) = sidechain_for(band.kind);
+            for (sc, f) in self.sidechains.iter_mut().zip(&mut self.filters) {
+                if !was_enabled {
+                    sc[i].clear();
+                    f[i].clear();
+                    self.envelopes[i].reset(0.0);
+                }
+                sc[i].set(sc_response, band.freq_hz, sc_q.unwrap_or(band.q), sr);
+            }
+            self.update_filter(i);
+        }
+    }
+
+    fn gain_db(&self, band: usize) -> f32 {
+        let p = &self.params.bands[band];
+        self.envelopes[band].value().min(p.range_db.abs()) * p.range_db.signum()
+    }
+
+    fn update_filter(&mut self, band: usize) {
+        let p = self.params.bands[band];
+        let gain_db = self.gain_db(band);
+        for f in &mut self.filters {
+            f[band].set_with_gain(
+                response_for(p.kind),
+                p.freq_hz,
+                p.q,
+                gain_db,
+                self.sample_rate_hz,
+            );
+        }
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            for a in 0..self.active_count {
+                let b = self.active[a];
+                let p = self.params.bands[b];
+                let mut peak = 0.0_f32;
+                for (x, sc) in inp.iter().zip(&mut self.sidechains) {
+                    peak = peak.max(sc[b].process(*x).abs());
+                }
+                let over = (lin_to_db_floor(peak) - p.threshold_db).max(0.0);
+                self.envelopes[b].process(over * (1.0 - 1.0 / p.ratio));
+            }
+
+            if self.countdown == 0 {
+                self.countdown = CONTROL_INTERVAL;
+                for a in 0..self.active_count {
+                    self.update_filter(self.active[a]);
+                }
+            }
+            self.countdown -= 1;
+
+            let active = &self.active[..self.active_count];
+            for ((x, y), filters) in inp.iter().zip(out.iter_mut()).zip(&mut self.filters) {
+                let mut v = *x;
+                for &b in active {
+                    v = filters[b].process(v);
+                }
+                *y = v;
+            }
+        }
+    }
+}
+
+/// `DynEqBand`'s fields for band `$n`, in order.
+macro_rules! band_params {
+    ($($n:literal),*) => {
+        &[$(
+            ParamDescriptor::stepped(concat!("Band ", $n, " type"), "", 0.0, 2.0, 0.0),
+            ParamDescriptor::toggle(concat!("Band ", $n, " on"), false),
+            ParamDescriptor::log(concat!("Band ", $n, " frequency"), "Hz", 20.0, 20_000.0, 1000.0),
+            ParamDescriptor::log(concat!("Band ", $n, " Q"), "", 0.1, 24.0, 1.0),
+            ParamDescriptor::linear(concat!("Band ", $n, " threshold"), "dB", -60.0, 0.0, -24.0),
+            ParamDescriptor::linear(concat!("Band ", $n, " ratio"), "", 1.0, 20.0, 2.0),
+            ParamDescriptor::log(concat!("Band ", $n, " attack"), "ms", 0.1, 500.0, 5.0),
+            ParamDescriptor::log(concat!("Band ", $n, " release"), "ms", 1.0, 5000.0, 120.0),
+            ParamDescriptor::linear(concat!("Band ", $n, " range"), "dB", -24.0, 24.0, -6.0),
+        )*]
+    };
+}
+
+impl Node for DynamicEq {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `DynEqBand`'s fields, band after band.
+    const PARAMS: &'static [ParamDescriptor] = band_params!(1, 2, 3, 4);
+
+    fn new(sample_rate_hz: f32) -> Self {
+        DynamicEq {
+            params: DynEqParams {
+                bands: [DISABLED; MAX_BANDS],
+            },
+            active: [0; MAX_BANDS],
+            active_count: 0,
+            sidechains: [[Biquad::default(); MAX_BANDS]; MAX_CHANNELS],
+            filters: [[Biquad::default(); MAX_BANDS]; MAX_CHANNELS],
+            envelopes: [EnvelopeFollower::new(
+                DISABLED.attack_ms,
+                DISABLED.release_ms,
+                sample_rate_hz,
+            ); MAX_BANDS],
+            countdown: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        let mut params = self.params;
+        let Some(band) = params.bands.get_mut((index / BAND_PARAMS) as usize) else {
+            return;
+        };
+        match index % BAND_PARAMS {
+            PARAM_KIND => band.kind = clamp(value, 0.0, 2.0).round() as u32,
+            PARAM_ENABLED => band.enabled = u32::from(clamp(value, 0.0, 1.0) >= 0.5),
+            PARAM_FREQ_HZ => band.freq_hz = value,
+            PARAM_Q => band.q = value,
+            PARAM_THRESHOLD_DB => band.threshold_db = value,
+            PARAM_RATIO => band.ratio = value,
+            PARAM_ATTACK_MS => band.attack_ms = value,
+            PARAM_RELEASE_MS => band.release_ms = value,
+            PARAM_RANGE_DB => band.range_db = value,
+            _ => return,
+        }
+        self.apply(&params);
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        let Some(band) = self.params.bands.get((index / BAND_PARAMS) as usize) else {
+            return 0.0;
+        };
+        match index % BAND_PARAMS {
+            PARAM_KIND => band.kind as f32,
+            PARAM_ENABLED => band.enabled as f32,
+            PARAM_FREQ_HZ => band.freq_hz,
+            PARAM_Q => band.q,
+            PARAM_THRESHOLD_DB => band.threshold_db,
+            PARAM_RATIO => band.ratio,
+            PARAM_ATTACK_MS => band.attack_ms,
+            PARAM_RELEASE_MS => band.release_ms,
+            PARAM_RANGE_DB => band.range_db,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Clears the side-chain and band filters and releases every band's gain change.
+    fn reset(&mut self) {
+        for f in self
+            .sidechains
+            .iter_mut()
+            .chain(&mut self.filters)
+            .flatten()
+        {
+            f.clear();
+        }
+        for e in &mut self.envelopes {
+            e.reset(0.0);
+        }
+        // Recompute the band filters for the released envelopes on the next sample.
+        self.countdown = 0;
+    }
+}
+
+node_abi::export_node!(DynamicEq {
+    abi_version: dyneq_abi_version,
+    new: dyneq_new,
+    free: dyneq_free,
+    param_count: dyneq_param_count,
+    param_descriptors: dyneq_param_descriptors,
+    set_param: dyneq_set_param,
+    get_param: dyneq_get_param,
+    param_events: dyneq_param_events,
+    process: dyneq_process,
+    process_offline: dyneq_process_offline,
+    latency_samples: dyneq_latency_samples,
+    io_layout: dyneq_get_io_layout,
+    process_io: dyneq_process_io,
+    reset: dyneq_reset,
+    state_size: dyneq_state_size,
+    save_state: dyneq_save_state,
+    load_state: dyneq_load_state,
+    preset_size: dyneq_preset_size,
+    save_preset: dyneq_save_preset,
+    load_preset: dyneq_load_preset,
+    last_error_code: dyneq_last_error_code,
+    last_error_message: dyneq_last_error_message_ptr,
+});
+
+node_abi::test_params!(DynamicEq);
+
+dsp_core::test_block_sizes!(dyneq_new, dyneq_process(input, output), dyneq_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(DynamicEq as DynamicEqNode);
+
+/// Size in bytes of the `DynEqParams` block `dyneq_set_params` reads.
+#[no_mangle]
+pub extern "C" fn dyneq_params_size() -> usize {
+    core::mem::size_of::<DynEqParams>()
+}
+
+/// Reads a `DynEqParams` block from `params_ptr` (copied; the buffer can be reused right away):
+/// every parameter in one call, as `dyneq_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn dyneq_set_params(handle: Handle, params_ptr: *const DynEqParams) {
+    if params_ptr.is_null() {
+        return;
+    }
+    let params = unsafe { core::ptr::read_unaligned(params_ptr) };
+    guard(handle, |d: &mut DynamicEq| d.apply(&params));
+}
+
+/// Current dynamic gain of `band` in dB (negative = cut); 0 for disabled bands.
+#[no_mangle]
+pub extern "C" fn dyneq_get_band_gain_db(handle: Handle, band: u32) -> f32 {
+    guard(handle, |d: &mut DynamicEq| {
+        let band = band as usize;
+        if band >= MAX_BANDS || d.params.bands[band].enabled == 0 {
+            return 0.0;
+        }
+        d.gain_db(band)
+    })
+    .unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// One bell at 3 kHz, Q 2, threshold -30 dB, 2:1, with `range_db`.
+    fn dyneq(range_db: f32) -> DynamicEq {
+        let mut d = DynamicEq::new(RATE);
+        for (index, value) in [
+            (PARAM_ENABLED, 1.0),
+            (PARAM_FREQ_HZ, 3_000.0),
+            (PARAM_Q, 2.0),
+            (PARAM_THRESHOLD_DB, -30.0),
+            (PARAM_RANGE_DB, range_db),
+        ] {
+            d.set_param(index, value);
+        }
+        d
+    }
+
+    /// Settled gain in dB on a `hz` sine of `amplitude`, mono in render quanta.
+    fn gain_db_at(d: &mut DynamicEq, hz: f32, amplitude: f32) -> f32 {
+        let w = core::f32::consts::TAU * hz / RATE;
+        let input: Vec<f32> = (0..48_000)
+            .map(|i| amplitude * (w * i as f32).sin())
+            .collect();
+        let output = render_quanta(input.len(), |span, y| Node::process(d, &input[span], y, 1));
+        let peak = output[24_000..].iter().fold(0.0_f32, |m, y| m.max(y.abs()));
+        20.0 * (peak / amplitude).log10()
+    }
+
+    /// Over the threshold the band's gain follows the overshoot at `1 - 1/ratio` dB per dB.
+    #[test]
+    fn overshoot_moves_the_band_gain_by_the_ratio() {
+        for ratio in [2.0, 4.0] {
+            let gain_db = |amplitude| {
+                let mut d = dyneq(-24.0);
+                d.set_param(PARAM_RATIO, ratio);
+                gain_db_at(&mut d, 3_000.0, amplitude)
+            };
+            // 0.5 is 14 dB louder than 0.1, and both are over the threshold.
+            let got = gain_db(0.5) - gain_db(0.1);
+            let want = -(1.0 - 1.0 / ratio) * 20.0 * 5f32.log10();
+            assert!((got - want).abs() < 0.3, "{ratio}:1: {got}");
+        }
+    }
+
+    /// `Range` caps the change and says which way it goes.
+    #[test]
+    fn range_caps_and_signs_the_gain() {
+        for range_db in [-6.0, 6.0] {
+            let mut d = dyneq(range_db);
+            let got = gain_db_at(&mut d, 3_000.0, 1.0);
+            assert!((got - range_db).abs() < 0.1, "range {range_db}: {got}");
+            assert_eq!(d.gain_db(0), range_db);
+        }
+    }
+
+    /// Under the threshold, or outside the band's side-chain, the band stays flat.
+    #[test]
+    fn quiet_or_out_of_band_content_is_left_alone() {
+        let mut d = dyneq(-12.0);
+        assert!(gain_db_at(&mut d, 3_000.0, 0.01).abs() < 0.01);
+        let mut d = dyneq(-12.0);
+        assert!(gain_db_at(&mut d, 200.0, 0.5).abs() < 0.01);
+        assert_eq!(d.gain_db(0), 0.0);
+    }
+}
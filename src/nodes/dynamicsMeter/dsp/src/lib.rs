@@ -0,0 +1,417 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use dsp_core::gain::lin_to_db;
+use dsp_core::loudness::Loudness;
+use dsp_core::param::clamp;
+use dsp_core::true_peak::{TruePeakCoeffs, TruePeakDetector};
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `DynamicsMeter::PARAMS`, in the order of `dynamics_set_params`' arguments.
+pub const PARAM_CREST_WINDOW_MS: u32 = 0;
+
+/// Everything is tallied on 100 ms blocks, in step with the loudness meter's; 30 make the 3 s
+/// short-term window.
+const SHORT_TERM_BLOCKS: usize = 30;
+/// History entries (one per block, two minutes' worth), three floats each.
+pub const HISTORY_LEN: usize = 1200;
+/// Floor of the history, standing in for silence so the UI only sees finite values.
+const MIN_DB: f32 = -160.0;
+
+#[derive(Clone, Copy, Default)]
+struct Block {
+    /// Highest true peak over the channels.
+    peak: f32,
+    /// Sum of squares over the block's samples, all channels.
+    energy: f64,
+    samples: u32,
+}
+
+/// Dynamics statistics: the crest factor (true peak over RMS) across a short window, the
+/// peak-to-loudness ratio (highest true peak since reset over the integrated loudness) and
+/// the peak-to-short-term-loudness ratio (true peak over the last 3 s over the short-term
+/// loudness), with loudness per `dsp_core::loudness` and true peak per `dsp_core::true_peak`.
+/// Every 100 ms the short-term loudness, 3 s true peak and crest factor are appended to a
+/// history ring the UI reads in place, so a limiter's squashing shows as the two curves
+/// closing in. The audio is only read; the worklet passes it on.
+pub struct DynamicsMeter {
+    loudness: Loudness,
+    coeffs: TruePeakCoeffs,
+    detectors: [TruePeakDetector; MAX_CHANNELS],
+    block_len: usize,
+    block_pos: usize,
+    current: Block,
+    blocks: [Block; SHORT_TERM_BLOCKS],
+    blocks_pos: usize,
+    blocks_done: u64,
+    crest_blocks: usize,
+    max_peak: f32,
+    crest_db: f32,
+    psr_db: f32,
+    /// Short-term LUFS, 3 s peak dBTP and crest dB per entry.
+    history: Vec<f32>,
+    history_write: usize,
+    entries: u32,
+    events: ParamRing,
+    error: LastError,
+}
+
+impl DynamicsMeter {
+    /// The newest `n` blocks, newest first.
+    fn recent(&self, n: usize) -> impl Iterator<Item = &Block> {
+        let done = (self.blocks_done as usize).min(SHORT_TERM_BLOCKS);
+        (1..=n.min(done)).map(move |k| {
+            &self.blocks[(self.blocks_pos + SHORT_TERM_BLOCKS - k) % SHORT_TERM_BLOCKS]
+        })
+    }
+
+    fn end_block(&mut self) {
+        self.blocks[self.blocks_pos] = core::mem::take(&mut self.current);
+        self.blocks_pos = (self.blocks_pos + 1) % SHORT_TERM_BLOCKS;
+        self.blocks_done += 1;
+        self.block_pos = 0;
+
+        let (peak, energy, samples) = self
+            .recent(self.crest_blocks)
+            .fold((0.0_f32, 0.0_f64, 0_u64), |(p, e, s), b| {
+                (p.max(b.peak), e + b.energy, s + b.samples as u64)
+            });
+        let rms = if samples > 0 {
+            (energy / samples as f64).sqrt() as f32
+        } else {
+            0.0
+        };
+        self.crest_db = if rms > 0.0 {
+            lin_to_db(peak / rms)
+        } else {
+            0.0
+        };
+        let short_peak = self
+            .recent(SHORT_TERM_BLOCKS)
+            .fold(0.0_f32, |p, b| p.max(b.peak));
+        let short_peak_db = lin_to_db(short_peak);
+        let short_term = self.loudness.short_term();
+        self.psr_db = if short_term.is_finite() && short_peak > 0.0 {
+            short_peak_db - short_term
+        } else {
+            0.0
+        };
+
+        let entry = &mut self.history[3 * self.history_write..3 * self.history_write + 3];
+        entry[0] = short_term.max(MIN_DB);
+        entry[1] = short_peak_db.max(MIN_DB);
+        entry[2] = self.crest_db;
+        self.history_write = (self.history_write + 1) % HISTORY_LEN;
+        self.entries = self.entries.wrapping_add(1);
+    }
+
+    /// Peak-to-loudness ratio in dB, 0 until the integrated loudness reads.
+    fn plr_db(&self) -> f32 {
+        let integrated = self.loudness.integrated();
+        if integrated.is_finite() && self.max_peak > 0.0 {
+            lin_to_db(self.max_peak) - integrated
+        } else {
+            0.0
+        }
+    }
+
+    fn measure(&mut self, input: &[f32], channels: usize) {
+        self.loudness.process(input, channels);
+        for frame in input.chunks_exact(channels) {
+            for (ch, &x) in frame.iter().enumerate() {
+                let x = if x.is_finite() { x } else { 0.0 };
+                let peak = self.detectors[ch].process(x, &self.coeffs);
+                self.current.peak = self.current.peak.max(peak);
+                self.current.energy += (x * x) as f64;
+            }
+            self.current.samples += channels as u32;
+            self.max_peak = self.max_peak.max(self.current.peak);
+            self.block_pos += 1;
+            if self.block_pos == self.block_len {
+                self.end_block();
+            }
+        }
+    }
+}
+
+impl Node for DynamicsMeter {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Crest window` is what the crest factor looks back over, in 100 ms steps.
+    const PARAMS: &'static [ParamDescriptor] = &[ParamDescriptor::linear(
+        "Crest window",
+        "ms",
+        100.0,
+        3000.0,
+        300.0,
+    )];
+    /// The audio is only read; the worklet passes it on.
+    const OUTPUT: bool = false;
+    /// The getters' readings, in their order below.
+    const METER_COUNT: usize = 5;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        DynamicsMeter {
+            loudness: Loudness::new(sample_rate_hz),
+            coeffs: TruePeakCoeffs::new(),
+            detectors: [TruePeakDetector::new(); MAX_CHANNELS],
+            block_len: ((sample_rate_hz * 0.1).round() as usize).max(1),
+            block_pos: 0,
+            current: Block::default(),
+            blocks: [Block::default(); SHORT_TERM_BLOCKS],
+            blocks_pos: 0,
+            blocks_done: 0,
+            crest_blocks: 3,
+            max_peak: 0.0,
+            crest_db: 0.0,
+            psr_db: 0.0,
+            history: vec![MIN_DB; 3 * HISTORY_LEN],
+            history_write: 0,
+            entries: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        if index == PARAM_CREST_WINDOW_MS {
+            self.crest_blocks = (clamp(value, 100.0, 3000.0) / 100.0).round() as usize;
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_CREST_WINDOW_MS => self.crest_blocks as f32 * 100.0,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], _output: &mut [f32], channels: usize) {
+        self.measure(input, channels);
+    }
+
+    fn meters(&self, out: &mut [f32]) {
+        out[0] = self.crest_db;
+        out[1] = self.plr_db();
+        out[2] = self.psr_db;
+        out[3] = lin_to_db(self.max_peak);
+        out[4] = self.loudness.integrated();
+    }
+
+    /// Starts a new measurement: clears every reading and the history.
+    fn reset(&mut self) {
+        self.loudness.reset();
+        for d in &mut self.detectors {
+            d.clear();
+        }
+        self.block_pos = 0;
+        self.current = Block::default();
+        self.blocks = [Block::default(); SHORT_TERM_BLOCKS];
+        self.blocks_pos = 0;
+        self.blocks_done = 0;
+        self.max_peak = 0.0;
+        self.crest_db = 0.0;
+        self.psr_db = 0.0;
+        self.history.fill(MIN_DB);
+        self.history_write = 0;
+        self.entries = 0;
+    }
+}
+
+node_abi::export_node!(DynamicsMeter {
+    abi_version: dynamics_abi_version,
+    new: dynamics_new,
+    free: dynamics_free,
+    param_count: dynamics_param_count,
+    param_descriptors: dynamics_param_descriptors,
+    set_param: dynamics_set_param,
+    get_param: dynamics_get_param,
+    param_events: dynamics_param_events,
+    process: dynamics_process,
+    process_offline: dynamics_process_offline,
+    latency_samples: dynamics_latency_samples,
+    io_layout: dynamics_get_io_layout,
+    process_io: dynamics_process_io,
+    reset: dynamics_reset,
+    state_size: dynamics_state_size,
+    save_state: dynamics_save_state,
+    load_state: dynamics_load_state,
+    preset_size: dynamics_preset_size,
+    save_preset: dynamics_save_preset,
+    load_preset: dynamics_load_preset,
+    last_error_code: dynamics_last_error_code,
+    last_error_message: dynamics_last_error_message_ptr,
+});
+
+node_abi::test_params!(DynamicsMeter);
+
+dsp_core::test_block_sizes!(
+    dynamics_new,
+    dynamics_process(input, output),
+    dynamics_free,
+    read: |p| {
+        vec![
+            dynamics_get_crest_db(p),
+            dynamics_get_plr_db(p),
+            dynamics_get_psr_db(p),
+            dynamics_get_max_peak_dbtp(p),
+            dynamics_get_integrated_lufs(p),
+        ]
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(DynamicsMeter as DynamicsMeterNode);
+
+/// Every parameter in one call, as `dynamics_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn dynamics_set_params(handle: Handle, crest_window_ms: f32) {
+    guard(handle, |d: &mut DynamicsMeter| {
+        d.set_param(PARAM_CREST_WINDOW_MS, crest_window_ms)
+    });
+}
+
+/// Crest factor over the crest window in dB (0 for silence).
+#[no_mangle]
+pub extern "C" fn dynamics_get_crest_db(handle: Handle) -> f32 {
+    guard(handle, |d: &mut DynamicsMeter| d.crest_db).unwrap_or(0.0)
+}
+
+/// Peak-to-loudness ratio since creation or the last `dynamics_reset`, in dB (0 until the
+/// integrated loudness reads).
+#[no_mangle]
+pub extern "C" fn dynamics_get_plr_db(handle: Handle) -> f32 {
+    guard(handle, |d: &mut DynamicsMeter| d.plr_db()).unwrap_or(0.0)
+}
+
+/// Peak-to-short-term-loudness ratio over the last 3 s in dB (0 until the short-term
+/// loudness reads).
+#[no_mangle]
+pub extern "C" fn dynamics_get_psr_db(handle: Handle) -> f32 {
+    guard(handle, |d: &mut DynamicsMeter| d.psr_db).unwrap_or(0.0)
+}
+
+/// Highest true peak since creation or the last `dynamics_reset`, in dBTP.
+#[no_mangle]
+pub extern "C" fn dynamics_get_max_peak_dbtp(handle: Handle) -> f32 {
+    guard(handle, |d: &mut DynamicsMeter| lin_to_db(d.max_peak)).unwrap_or(f32::NEG_INFINITY)
+}
+
+/// Gated loudness since creation or the last `dynamics_reset`, in LUFS.
+#[no_mangle]
+pub extern "C" fn dynamics_get_integrated_lufs(handle: Handle) -> f32 {
+    guard(handle, |d: &mut DynamicsMeter| d.loudness.integrated()).unwrap_or(f32::NEG_INFINITY)
+}
+
+/// The history ring, `HISTORY_LEN` entries of three floats: short-term loudness (LUFS), true
+/// peak over the last 3 s (dBTP) and crest factor (dB), the levels floored at -160. For the
+/// UI to read in place; stays put for the node's lifetime.
+#[no_mangle]
+pub extern "C" fn dynamics_history(handle: Handle) -> *const f32 {
+    guard(handle, |d: &mut DynamicsMeter| d.history.as_ptr()).unwrap_or(core::ptr::null())
+}
+
+/// Index of the entry that will be written next; the newest is just before it.
+#[no_mangle]
+pub extern "C" fn dynamics_history_write_index(handle: Handle) -> u32 {
+    guard(handle, |d: &mut DynamicsMeter| d.history_write as u32).unwrap_or(0)
+}
+
+/// Counts the entries written (wrapping), so the UI knows how many are new.
+#[no_mangle]
+pub extern "C" fn dynamics_history_count(handle: Handle) -> u32 {
+    guard(handle, |d: &mut DynamicsMeter| d.entries).unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+
+    const RATE: f32 = 48_000.0;
+
+    /// Feeds `frames` of `f(i)` on both of two channels to the meter in render quanta.
+    fn feed(h: Handle, frames: usize, f: impl Fn(usize) -> f32) {
+        let x: Vec<f32> = (0..frames).flat_map(|i| [f(i), f(i)]).collect();
+        let mut out = [0.0; 256];
+        for block in x.chunks(256) {
+            dynamics_process(h, block.as_ptr(), out.as_mut_ptr(), block.len() / 2, 2);
+        }
+    }
+
+    /// The EBU reference tone, a stereo 1 kHz sine peaking at -23 dBFS, reads -23 LUFS and
+    /// -23 dBTP, so no peak-to-loudness ratio, and a sine's 3 dB crest factor; each 100 ms
+    /// adds an entry of the same to the history.
+    #[test]
+    fn reference_tone_reads_sine_crest_and_no_plr() {
+        let h = dynamics_new(RATE);
+        let a = 10.0_f32.powf(-23.0 / 20.0);
+        feed(h, 480_000, |i| {
+            a * (2.0 * PI * 1_000.0 * i as f32 / RATE).sin()
+        });
+        assert!((dynamics_get_max_peak_dbtp(h) + 23.0).abs() < 0.05);
+        assert!((dynamics_get_integrated_lufs(h) + 23.0).abs() < 0.1);
+        assert!(
+            dynamics_get_plr_db(h).abs() < 0.1,
+            "{}",
+            dynamics_get_plr_db(h)
+        );
+        assert!(
+            dynamics_get_psr_db(h).abs() < 0.1,
+            "{}",
+            dynamics_get_psr_db(h)
+        );
+        assert!(
+            (dynamics_get_crest_db(h) - 3.01).abs() < 0.05,
+            "{}",
+            dynamics_get_crest_db(h)
+        );
+
+        assert_eq!(dynamics_history_count(h), 100);
+        assert_eq!(dynamics_history_write_index(h), 100);
+        let history = unsafe { core::slice::from_raw_parts(dynamics_history(h), 3 * HISTORY_LEN) };
+        let newest = &history[3 * 99..3 * 100];
+        assert!((newest[0] + 23.0).abs() < 0.1 && (newest[1] + 23.0).abs() < 0.05);
+        assert_eq!(newest[2], dynamics_get_crest_db(h));
+        assert_eq!(history[3 * 100], MIN_DB);
+
+        dynamics_reset(h);
+        assert_eq!(dynamics_history_count(h), 0);
+        assert_eq!(dynamics_get_max_peak_dbtp(h), f32::NEG_INFINITY);
+        dynamics_free(h);
+    }
+
+    /// A click every 100 samples has a 20 dB crest factor; once the crest window has moved
+    /// past the clicks the reading follows what came after.
+    #[test]
+    fn crest_factor_follows_the_window() {
+        let h = dynamics_new(RATE);
+        dynamics_set_params(h, 300.0);
+        feed(h, 48_000, |i| if i % 100 == 0 { 0.5 } else { 0.0 });
+        let crest = dynamics_get_crest_db(h);
+        assert!((crest - 20.0).abs() < 0.3, "{crest}");
+        feed(h, 14_400, |i| {
+            0.5 * (2.0 * PI * 1_000.0 * i as f32 / RATE).sin()
+        });
+        let crest = dynamics_get_crest_db(h);
+        assert!((crest - 3.01).abs() < 0.05, "{crest}");
+        dynamics_free(h);
+    }
+}
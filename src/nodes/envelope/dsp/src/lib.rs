@@ -0,0 +1,538 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::events::{EventRing, NoteEvent, ALL_NOTES_OFF, NOTE_OFF, NOTE_ON};
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Envelope::PARAMS`, in the order of `envelope_set_params`' arguments.
+pub const PARAM_ATTACK_MS: u32 = 0;
+pub const PARAM_DECAY_MS: u32 = 1;
+pub const PARAM_SUSTAIN: u32 = 2;
+pub const PARAM_RELEASE_MS: u32 = 3;
+pub const PARAM_SHAPE: u32 = 4;
+pub const PARAM_LOOPING: u32 = 5;
+pub const PARAM_MIN: u32 = 6;
+pub const PARAM_MAX: u32 = 7;
+pub const PARAM_GATE: u32 = 8;
+
+pub const LINEAR: u32 = 0;
+/// Fast at first, then settling, like a capacitor charging (see `curve`).
+pub const EXPONENTIAL: u32 = 1;
+
+/// Time constants fitted into an exponential segment: it covers 1 - e^-5 (99.3%) of its
+/// distance along the exponential and is then stretched to land on the target.
+const CURVE_K: f32 = 5.0;
+
+/// Progress along a segment at `t` (0..1 through it), 0 to 1.
+#[inline]
+fn curve(exponential: bool, t: f32) -> f32 {
+    if exponential {
+        (1.0 - (-CURVE_K * t).exp()) / (1.0 - (-CURVE_K).exp())
+    } else {
+        t
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// ADSR envelope generator rendering a control signal, scaled to `min..max` and the same on
+/// every channel. Each segment takes exactly its set time, along a straight line or an
+/// exponential curve, and starts from wherever the level is, so retriggers and early releases
+/// don't jump. In loop mode a held gate cycles attack and decay instead of sustaining.
+///
+/// The gate is the gate parameter or any note held in the event ring; a new note-on
+/// retriggers the attack.
+pub struct Envelope {
+    notes: EventRing,
+    stage: Stage,
+    /// Position through the current segment (0..1), and the level it started from.
+    t: f32,
+    from: f32,
+    level: f32,
+    attack_ms: f32,
+    decay_ms: f32,
+    release_ms: f32,
+    /// Per-sample advance through the attack, decay and release.
+    attack_step: f32,
+    decay_step: f32,
+    release_step: f32,
+    sustain: f32,
+    exponential: bool,
+    looping: bool,
+    min: f32,
+    max: f32,
+    gate_param: bool,
+    /// Bit per note held in the event ring.
+    held: u128,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Envelope {
+    fn step_for(&self, ms: f32) -> f32 {
+        let samples = clamp(ms, 0.0, 20_000.0) / 1000.0 * self.sample_rate_hz;
+        if samples > 1.0 {
+            1.0 / samples
+        } else {
+            1.0
+        }
+    }
+
+    fn gate(&self) -> bool {
+        self.gate_param || self.held != 0
+    }
+
+    fn enter(&mut self, stage: Stage) {
+        self.stage = stage;
+        self.t = 0.0;
+        self.from = self.level;
+    }
+
+    fn gate_on(&mut self) {
+        self.enter(Stage::Attack);
+    }
+
+    /// Releases once nothing holds the gate any more.
+    fn gate_off(&mut self) {
+        if !self.gate() && self.stage != Stage::Idle {
+            self.enter(Stage::Release);
+        }
+    }
+
+    fn handle(&mut self, e: NoteEvent) {
+        let bit = 1u128 << e.note.min(127);
+        match e.kind {
+            NOTE_ON if e.value > 0.0 => {
+                self.held |= bit;
+                self.gate_on();
+            }
+            // Velocity 0 is a note-off, as in MIDI.
+            NOTE_ON | NOTE_OFF => {
+                self.held &= !bit;
+                self.gate_off();
+            }
+            ALL_NOTES_OFF => {
+                self.held = 0;
+                self.gate_off();
+            }
+            _ => {}
+        }
+    }
+
+    /// Advances one sample.
+    #[inline]
+    fn tick(&mut self) {
+        let (step, to) = match self.stage {
+            Stage::Idle => return,
+            Stage::Sustain => {
+                self.level = self.sustain;
+                return;
+            }
+            Stage::Attack => (self.attack_step, 1.0),
+            Stage::Decay => (self.decay_step, self.sustain),
+            Stage::Release => (self.release_step, 0.0),
+        };
+        // Summed steps fall a hair short of 1; within half a step counts as there, so a
+        // segment lasts its set number of samples.
+        self.t += step;
+        if self.t > 1.0 - 0.5 * step {
+            self.t = 1.0;
+        }
+        self.level = self.from + (to - self.from) * curve(self.exponential, self.t);
+        if self.t < 1.0 {
+            return;
+        }
+        match self.stage {
+            Stage::Attack => self.enter(Stage::Decay),
+            Stage::Decay if self.looping => self.enter(Stage::Attack),
+            Stage::Decay => self.stage = Stage::Sustain,
+            _ => self.stage = Stage::Idle,
+        }
+    }
+
+    fn render(&mut self, output: &mut [f32], channels: usize) {
+        for out in output.chunks_exact_mut(channels) {
+            self.tick();
+            out.fill(self.min + self.level * (self.max - self.min));
+        }
+    }
+}
+
+impl Node for Envelope {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Attack`, `Decay` and `Release` for each whole segment; `Shape` `LINEAR` or
+    /// `EXPONENTIAL`; `Loop` cycles attack and decay while the gate is held. Times apply to a
+    /// segment already under way. Silence maps to `Min` and the peak to `Max` (either way
+    /// round). `Gate` is held alongside any notes from the event ring; opening it retriggers
+    /// the attack.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Attack", "ms", 0.0, 20_000.0, 5.0),
+        ParamDescriptor::linear("Decay", "ms", 0.0, 20_000.0, 200.0),
+        ParamDescriptor::linear("Sustain", "", 0.0, 1.0, 0.7),
+        ParamDescriptor::linear("Release", "ms", 0.0, 20_000.0, 300.0),
+        ParamDescriptor::stepped("Shape", "", 0.0, 1.0, EXPONENTIAL as f32),
+        ParamDescriptor::toggle("Loop", false),
+        ParamDescriptor::linear("Min", "", -100_000.0, 100_000.0, 0.0),
+        ParamDescriptor::linear("Max", "", -100_000.0, 100_000.0, 1.0),
+        ParamDescriptor::toggle("Gate", false),
+    ];
+    const INPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut e = Envelope {
+            notes: EventRing::new(),
+            stage: Stage::Idle,
+            t: 0.0,
+            from: 0.0,
+            level: 0.0,
+            attack_ms: 5.0,
+            decay_ms: 200.0,
+            release_ms: 300.0,
+            attack_step: 1.0,
+            decay_step: 1.0,
+            release_step: 1.0,
+            sustain: 0.7,
+            exponential: true,
+            looping: false,
+            min: 0.0,
+            max: 1.0,
+            gate_param: false,
+            held: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        e.attack_step = e.step_for(e.attack_ms);
+        e.decay_step = e.step_for(e.decay_ms);
+        e.release_step = e.step_for(e.release_ms);
+        e
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        let on = clamp(value, 0.0, 1.0) >= 0.5;
+        match index {
+            PARAM_ATTACK_MS => {
+                self.attack_ms = clamp(value, 0.0, 20_000.0);
+                self.attack_step = self.step_for(self.attack_ms);
+            }
+            PARAM_DECAY_MS => {
+                self.decay_ms = clamp(value, 0.0, 20_000.0);
+                self.decay_step = self.step_for(self.decay_ms);
+            }
+            PARAM_SUSTAIN => self.sustain = clamp(value, 0.0, 1.0),
+            PARAM_RELEASE_MS => {
+                self.release_ms = clamp(value, 0.0, 20_000.0);
+                self.release_step = self.step_for(self.release_ms);
+            }
+            PARAM_SHAPE => self.exponential = on,
+            PARAM_LOOPING => {
+                self.looping = on;
+                // A loop switched on mid-sustain starts cycling; switched off, the decay then
+                // sustains.
+                if self.looping && self.stage == Stage::Sustain {
+                    self.enter(Stage::Attack);
+                }
+            }
+            PARAM_MIN => self.min = clamp(value, -100_000.0, 100_000.0),
+            PARAM_MAX => self.max = clamp(value, -100_000.0, 100_000.0),
+            PARAM_GATE => {
+                if on == self.gate_param {
+                    return;
+                }
+                self.gate_param = on;
+                if on {
+                    self.gate_on();
+                } else {
+                    self.gate_off();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_ATTACK_MS => self.attack_ms,
+            PARAM_DECAY_MS => self.decay_ms,
+            PARAM_SUSTAIN => self.sustain,
+            PARAM_RELEASE_MS => self.release_ms,
+            PARAM_SHAPE => u32::from(self.exponential) as f32,
+            PARAM_LOOPING => u32::from(self.looping) as f32,
+            PARAM_MIN => self.min,
+            PARAM_MAX => self.max,
+            PARAM_GATE => u32::from(self.gate_param) as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn note_events(&mut self) -> Option<&mut EventRing> {
+        Some(&mut self.notes)
+    }
+
+    fn note(&mut self, event: NoteEvent) {
+        self.handle(event);
+    }
+
+    /// Renders the same value on every channel.
+    fn process(&mut self, _input: &[f32], output: &mut [f32], channels: usize) {
+        self.render(output, channels);
+    }
+
+    /// Drops to zero and forgets held notes; with the gate parameter open the attack starts
+    /// again.
+    fn reset(&mut self) {
+        self.held = 0;
+        self.level = 0.0;
+        self.stage = Stage::Idle;
+        self.t = 0.0;
+        self.from = 0.0;
+        if self.gate_param {
+            self.gate_on();
+        }
+    }
+}
+
+node_abi::export_node!(Envelope {
+    abi_version: envelope_abi_version,
+    new: envelope_new,
+    free: envelope_free,
+    param_count: envelope_param_count,
+    param_descriptors: envelope_param_descriptors,
+    set_param: envelope_set_param,
+    get_param: envelope_get_param,
+    param_events: envelope_param_events,
+    process: envelope_process,
+    process_offline: envelope_process_offline,
+    latency_samples: envelope_latency_samples,
+    io_layout: envelope_get_io_layout,
+    process_io: envelope_process_io,
+    reset: envelope_reset,
+    state_size: envelope_state_size,
+    save_state: envelope_save_state,
+    load_state: envelope_load_state,
+    preset_size: envelope_preset_size,
+    save_preset: envelope_save_preset,
+    load_preset: envelope_load_preset,
+    last_error_code: envelope_last_error_code,
+    last_error_message: envelope_last_error_message_ptr,
+});
+
+node_abi::test_params!(Envelope);
+
+dsp_core::test_block_sizes!(
+    envelope_new,
+    envelope_process(input, output),
+    envelope_free,
+    setup: |p| envelope_set_gate(p, 1),
+);
+
+node_abi::test_io_block!(Envelope, setup: |e: &mut Envelope| e.set_param(PARAM_GATE, 1.0));
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Envelope as EnvelopeNode);
+
+/// The note-event ring inside the node, for the worklet to write into (layout in
+/// `dsp_core::events`). Stays put for the node's lifetime.
+#[no_mangle]
+pub extern "C" fn envelope_events(handle: Handle) -> *mut EventRing {
+    guard(handle, |e: &mut Envelope| &mut e.notes as *mut EventRing)
+        .unwrap_or(core::ptr::null_mut())
+}
+
+/// The envelope's parameters in one call, as `envelope_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn envelope_set_params(
+    handle: Handle,
+    attack_ms: f32,
+    decay_ms: f32,
+    sustain: f32,
+    release_ms: f32,
+    shape: u32,
+    looping: u32,
+) {
+    guard(handle, |e: &mut Envelope| {
+        e.set_param(PARAM_ATTACK_MS, attack_ms);
+        e.set_param(PARAM_DECAY_MS, decay_ms);
+        e.set_param(PARAM_SUSTAIN, sustain);
+        e.set_param(PARAM_RELEASE_MS, release_ms);
+        e.set_param(PARAM_SHAPE, shape as f32);
+        e.set_param(PARAM_LOOPING, looping as f32);
+    });
+}
+
+/// `Min` and `Max` in one call.
+#[no_mangle]
+pub extern "C" fn envelope_set_output(handle: Handle, min: f32, max: f32) {
+    guard(handle, |e: &mut Envelope| {
+        e.set_param(PARAM_MIN, min);
+        e.set_param(PARAM_MAX, max);
+    });
+}
+
+/// `Gate`, as `envelope_set_param` sets it (straight away; queue a parameter event to land it
+/// on a frame).
+#[no_mangle]
+pub extern "C" fn envelope_set_gate(handle: Handle, gate: u32) {
+    guard(handle, |e: &mut Envelope| {
+        e.set_param(PARAM_GATE, gate as f32)
+    });
+}
+
+/// Current stage (0 idle, 1 attack, 2 decay, 3 sustain, 4 release), for the UI.
+#[no_mangle]
+pub extern "C" fn envelope_stage(handle: Handle) -> u32 {
+    guard(handle, |e: &mut Envelope| match e.stage {
+        Stage::Idle => 0,
+        Stage::Attack => 1,
+        Stage::Decay => 2,
+        Stage::Sustain => 3,
+        Stage::Release => 4,
+    })
+    .unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// An envelope with 1 ms attack (48 samples), 2 ms decay, sustain 0.5 and 1 ms release.
+    fn envelope(shape: u32, looping: u32) -> Handle {
+        let h = envelope_new(RATE);
+        envelope_set_params(h, 1.0, 2.0, 0.5, 1.0, shape, looping);
+        h
+    }
+
+    fn send(h: Handle, kind: u32, note: u32) {
+        let events = unsafe { &mut *envelope_events(h) };
+        assert!(events.push(NoteEvent {
+            kind,
+            note,
+            value: 1.0,
+            frame: 0,
+        }));
+    }
+
+    fn render(h: Handle, frames: usize) -> Vec<f32> {
+        render_quanta(frames, |_, y| {
+            envelope_process(h, core::ptr::null(), y.as_mut_ptr(), y.len(), 1)
+        })
+    }
+
+    /// Linear segments take exactly their set times, then the level sustains and releases to
+    /// silence.
+    #[test]
+    fn segments_take_their_times() {
+        let h = envelope(LINEAR, 0);
+        envelope_set_gate(h, 1);
+        let y = render(h, 256);
+        assert_eq!(envelope_stage(h), 3);
+        for (i, &v) in y.iter().enumerate() {
+            let expected = match i {
+                0..48 => (i + 1) as f32 / 48.0,
+                48..144 => 1.0 - 0.5 * (i - 47) as f32 / 96.0,
+                _ => 0.5,
+            };
+            assert!((v - expected).abs() < 1e-4, "{i}: {v}");
+        }
+
+        envelope_set_gate(h, 0);
+        let y = render(h, 128);
+        for (i, &v) in y.iter().enumerate() {
+            let expected = 0.5 * (1.0 - ((i + 1) as f32 / 48.0).min(1.0));
+            assert!((v - expected).abs() < 1e-4, "{i}: {v}");
+        }
+        assert_eq!(envelope_stage(h), 0);
+        envelope_free(h);
+    }
+
+    /// The exponential shape is most of the way there halfway through a segment and still
+    /// lands on time.
+    #[test]
+    fn exponential_segments_curve() {
+        let h = envelope(EXPONENTIAL, 0);
+        envelope_set_gate(h, 1);
+        let y = render(h, 128);
+        envelope_free(h);
+        let half = (1.0 - (-2.5_f32).exp()) / (1.0 - (-5.0_f32).exp());
+        assert!((y[23] - half).abs() < 1e-4, "{}", y[23]);
+        assert!((y[47] - 1.0).abs() < 1e-6);
+    }
+
+    /// In loop mode a held gate cycles attack and decay instead of sustaining.
+    #[test]
+    fn loop_cycles_while_held() {
+        let h = envelope(LINEAR, 1);
+        envelope_set_gate(h, 1);
+        let y = render(h, 384);
+        // Up to 1, down to 0.5, back up over the attack time.
+        assert!((y[143] - 0.5).abs() < 1e-4);
+        assert!((y[191] - 1.0).abs() < 1e-4);
+        assert!((y[287] - 0.5).abs() < 1e-4);
+        assert!(y[144..].iter().all(|&v| v >= 0.5 - 1e-4));
+        assert_ne!(envelope_stage(h), 3);
+        envelope_free(h);
+    }
+
+    /// Held notes keep the gate open until the last is released; each note-on retriggers
+    /// from the current level.
+    #[test]
+    fn notes_hold_the_gate() {
+        let h = envelope(LINEAR, 0);
+        send(h, NOTE_ON, 60);
+        send(h, NOTE_ON, 64);
+        render(h, 256);
+        send(h, NOTE_OFF, 60);
+        render(h, 128);
+        assert_eq!(envelope_stage(h), 3);
+
+        send(h, NOTE_ON, 67);
+        let y = render(h, 128);
+        // From the sustain back up to the peak in the attack time.
+        assert!((y[0] - (0.5 + 0.5 / 48.0)).abs() < 1e-4, "{}", y[0]);
+        assert!((y[47] - 1.0).abs() < 1e-4);
+
+        send(h, NOTE_OFF, 64);
+        send(h, NOTE_OFF, 67);
+        render(h, 128);
+        assert_eq!(envelope_stage(h), 0);
+        envelope_free(h);
+    }
+
+    /// The level maps onto `Min..Max`, either way round.
+    #[test]
+    fn output_spans_min_to_max() {
+        let h = envelope(LINEAR, 0);
+        envelope_set_output(h, 200.0, 100.0);
+        assert_eq!(render(h, 128)[127], 200.0);
+        envelope_set_gate(h, 1);
+        let y = render(h, 256);
+        assert!((y[47] - 100.0).abs() < 1e-2 && (y[255] - 150.0).abs() < 1e-2);
+        envelope_free(h);
+    }
+}
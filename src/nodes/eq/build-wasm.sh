@@ -0,0 +1,5 @@
+#!/usr/bin/env bash
+# See scripts/build-node-wasm.sh.
+# f64 in both builds: the simd128 biquad bank is f32, so there the bank runs scalar while gains
+# and mixing stay simd128.
+exec bash "$(dirname -- "${BASH_SOURCE[0]}")/../../../scripts/build-node-wasm.sh" eq --simd --features f64
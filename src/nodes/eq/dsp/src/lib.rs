@@ -0,0 +1,494 @@
+// `eq_set_params` reads the block through a pointer the worklet got from us; null is the only
+// invalid value we can detect.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use core::f32::consts::PI;
+use dsp_core::biquad::{Biquad, Response};
+use dsp_core::convolver::PartitionedConvolver;
+use dsp_core::fft::{Complex, Fft};
+use dsp_core::param::clamp;
+use dsp_core::simd::{BiquadBank, LANES};
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing, FLAG_NO_AUTOMATION};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_BANDS: usize = 8;
+/// Linear-phase FIR length; about 11.7 Hz bin spacing at 48 kHz.
+pub const FIR_LEN: usize = 4096;
+/// Partition size of the FFT convolver (one render quantum).
+pub const FIR_BLOCK: usize = 128;
+
+// Indices into `Eq::PARAMS`: band `b`'s start at `b * BAND_PARAMS`, in the order of `EqBand`'s
+// fields; `PARAM_LINEAR_PHASE` follows the last band.
+pub const BAND_PARAMS: u32 = 5;
+pub const PARAM_KIND: u32 = 0;
+pub const PARAM_ENABLED: u32 = 1;
+pub const PARAM_FREQ_HZ: u32 = 2;
+pub const PARAM_GAIN_DB: u32 = 3;
+pub const PARAM_Q: u32 = 4;
+pub const PARAM_LINEAR_PHASE: u32 = MAX_BANDS as u32 * BAND_PARAMS;
+
+/// One band as laid out in shared memory. `kind`: 0 bell, 1 low shelf, 2 high shelf,
+/// 3 low pass, 4 high pass, 5 notch.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct EqBand {
+    pub kind: u32,
+    pub enabled: u32,
+    pub freq_hz: f32,
+    pub gain_db: f32,
+    pub q: f32,
+}
+
+/// The whole parameter block the worklet writes into a `wasm_alloc`ed buffer of
+/// `eq_params_size()` bytes before calling `eq_set_params`. `linear_phase` != 0 swaps the
+/// biquad cascade for an FIR with the same magnitude response (see `Eq::latency_samples`).
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct EqParams {
+    pub bands: [EqBand; MAX_BANDS],
+    pub linear_phase: u32,
+}
+
+const DISABLED: EqBand = EqBand {
+    kind: 0,
+    enabled: 0,
+    freq_hz: 1000.0,
+    gain_db: 0.0,
+    q: 0.707,
+};
+
+fn response_for(kind: u32) -> Response {
+    match kind {
+        1 => Response::LowShelf,
+        2 => Response::HighShelf,
+        3 => Response::Lowpass,
+        4 => Response::Highpass,
+        5 => Response::Notch,
+        _ => Response::Peak,
+    }
+}
+
+#[repr(C)]
+pub struct Eq {
+    params: EqParams,
+    /// Indices of the enabled bands, in order.
+    active: [usize; MAX_BANDS],
+    active_count: usize,
+    /// Each band's coefficients, for designing the FIR.
+    bands: [Biquad; MAX_BANDS],
+    /// Per band, the channels' filter state, `LANES` channels to a bank.
+    banks: [[BiquadBank; MAX_CHANNELS / LANES]; MAX_BANDS],
+    linear_phase: bool,
+    fft: Fft,
+    spectrum: Vec<Complex>,
+    fir: Vec<f32>,
+    convolvers: Vec<PartitionedConvolver>,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Eq {
+    /// Sanitises `params` and recomputes coefficients; filter state is kept so sweeps stay
+    /// smooth.
+    fn apply(&mut self, params: &EqParams) {
+        let nyquist = self.sample_rate_hz * 0.5;
+        self.active_count = 0;
+        for (i, band) in params.bands.iter().enumerate() {
+            let band = EqBand {
+                kind: band.kind.min(5),
+                enabled: if band.enabled != 0 { 1 } else { 0 },
+                freq_hz: clamp(band.freq_hz, 10.0, (nyquist * 0.98).min(20_000.0)),
+                gain_db: clamp(band.gain_db, -24.0, 24.0),
+                q: clamp(band.q, 0.1, 24.0),
+            };
+            let was_enabled = self.params.bands[i].enabled != 0;
+            self.params.bands[i] = band;
+            if band.enabled == 0 {
+                continue;
+            }
+            self.active[self.active_count] = i;
+            self.active_count += 1;
+            self.bands[i].set_with_gain(
+                response_for(band.kind),
+                band.freq_hz,
+                band.q,
+                band.gain_db,
+                self.sample_rate_hz,
+            );
+            for bank in &mut self.banks[i] {
+                if !was_enabled {
+                    bank.clear();
+                }
+                bank.set_all(&self.bands[i]);
+            }
+        }
+
+        let linear_phase = params.linear_phase != 0;
+        self.params.linear_phase = u32::from(linear_phase);
+        if linear_phase != self.linear_phase {
+            self.linear_phase = linear_phase;
+            for band in &mut self.banks {
+                band.iter_mut().for_each(BiquadBank::clear);
+            }
+            self.convolvers
+                .iter_mut()
+                .for_each(PartitionedConvolver::clear);
+        }
+        if self.linear_phase {
+            self.design_fir();
+        }
+    }
+
+    /// Frequency sampling: the cascade's magnitude on `FIR_LEN` bins, inverse-transformed to a
+    /// zero-phase kernel, centred at `FIR_LEN / 2` and Blackman-windowed.
+    fn design_fir(&mut self) {
+        let sr = self.sample_rate_hz;
+        let bands = &self.bands;
+        let active = &self.active[..self.active_count];
+        for k in 0..=FIR_LEN / 2 {
+            let freq = k as f32 * sr / FIR_LEN as f32;
+            let mag: f32 = active
+                .iter()
+                .map(|&b| bands[b].magnitude_at(freq, sr))
+                .product();
+            self.spectrum[k] = Complex::new(mag, 0.0);
+            if k > 0 && k < FIR_LEN / 2 {
+                self.spectrum[FIR_LEN - k] = Complex::new(mag, 0.0);
+            }
+        }
+        self.fft.inverse(&mut self.spectrum);
+        let half = FIR_LEN / 2;
+        for (n, tap) in self.fir.iter_mut().enumerate() {
+            let phase = 2.0 * PI * n as f32 / FIR_LEN as f32;
+            let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+            *tap = self.spectrum[(n + half) % FIR_LEN].re * window;
+        }
+        for conv in &mut self.convolvers {
+            conv.set_ir(&self.fir);
+        }
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        if self.linear_phase {
+            for (inp, out) in input
+                .chunks_exact(channels)
+                .zip(output.chunks_exact_mut(channels))
+            {
+                for ((x, y), conv) in inp.iter().zip(out.iter_mut()).zip(&mut self.convolvers) {
+                    *y = conv.process(*x);
+                }
+            }
+            return;
+        }
+        let active = &self.active[..self.active_count];
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            for (group, (xs, ys)) in inp.chunks(LANES).zip(out.chunks_mut(LANES)).enumerate() {
+                let mut v = [0.0; LANES];
+                v[..xs.len()].copy_from_slice(xs);
+                for &b in active {
+                    v = self.banks[b][group].process(v);
+                }
+                ys.copy_from_slice(&v[..ys.len()]);
+            }
+        }
+    }
+}
+
+/// `EqBand`'s fields for band `$n`, in order.
+macro_rules! band_params {
+    ($($n:literal),*) => {
+        &[
+            $(
+                ParamDescriptor::stepped(concat!("Band ", $n, " type"), "", 0.0, 5.0, 0.0),
+                ParamDescriptor::toggle(concat!("Band ", $n, " on"), false),
+                ParamDescriptor::log(
+                    concat!("Band ", $n, " frequency"),
+                    "Hz",
+                    10.0,
+                    20_000.0,
+                    1000.0,
+                ),
+                ParamDescriptor::linear(concat!("Band ", $n, " gain"), "dB", -24.0, 24.0, 0.0),
+                ParamDescriptor::log(concat!("Band ", $n, " Q"), "", 0.1, 24.0, 0.707),
+            )*
+            ParamDescriptor::toggle("Linear phase", false).with_flags(FLAG_NO_AUTOMATION),
+        ]
+    };
+}
+
+impl Node for Eq {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `EqBand`'s fields, band after band, then `Linear phase` (see `latency_samples`).
+    const PARAMS: &'static [ParamDescriptor] = band_params!(1, 2, 3, 4, 5, 6, 7, 8);
+
+    fn new(sample_rate_hz: f32) -> Self {
+        Eq {
+            params: EqParams {
+                bands: [DISABLED; MAX_BANDS],
+                linear_phase: 0,
+            },
+            active: [0; MAX_BANDS],
+            active_count: 0,
+            bands: [Biquad::default(); MAX_BANDS],
+            banks: [[BiquadBank::default(); MAX_CHANNELS / LANES]; MAX_BANDS],
+            linear_phase: false,
+            fft: Fft::new(FIR_LEN),
+            spectrum: vec![Complex::default(); FIR_LEN],
+            fir: vec![0.0; FIR_LEN],
+            convolvers: (0..MAX_CHANNELS)
+                .map(|_| PartitionedConvolver::crossfading(FIR_BLOCK, FIR_LEN))
+                .collect(),
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        let mut params = self.params;
+        if index == PARAM_LINEAR_PHASE {
+            params.linear_phase = u32::from(clamp(value, 0.0, 1.0) >= 0.5);
+        } else if let Some(band) = params.bands.get_mut((index / BAND_PARAMS) as usize) {
+            match index % BAND_PARAMS {
+                PARAM_KIND => band.kind = clamp(value, 0.0, 5.0).round() as u32,
+                PARAM_ENABLED => band.enabled = u32::from(clamp(value, 0.0, 1.0) >= 0.5),
+                PARAM_FREQ_HZ => band.freq_hz = value,
+                PARAM_GAIN_DB => band.gain_db = value,
+                PARAM_Q => band.q = value,
+                _ => return,
+            }
+        } else {
+            return;
+        }
+        self.apply(&params);
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        if index == PARAM_LINEAR_PHASE {
+            return self.params.linear_phase as f32;
+        }
+        let Some(band) = self.params.bands.get((index / BAND_PARAMS) as usize) else {
+            return 0.0;
+        };
+        match index % BAND_PARAMS {
+            PARAM_KIND => band.kind as f32,
+            PARAM_ENABLED => band.enabled as f32,
+            PARAM_FREQ_HZ => band.freq_hz,
+            PARAM_GAIN_DB => band.gain_db,
+            PARAM_Q => band.q,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Delay the current mode adds: 0 for the biquads, `FIR_LEN / 2 + FIR_BLOCK` when
+    /// linear-phase (the FIR's centre tap plus one convolver partition).
+    fn latency_samples(&self) -> u32 {
+        if self.linear_phase {
+            (FIR_LEN / 2 + FIR_BLOCK) as u32
+        } else {
+            0
+        }
+    }
+
+    /// Clears the band filters and the linear-phase convolvers.
+    fn reset(&mut self) {
+        for bank in self.banks.iter_mut().flatten() {
+            bank.clear();
+        }
+        for conv in &mut self.convolvers {
+            conv.clear();
+        }
+    }
+}
+
+node_abi::export_node!(Eq {
+    abi_version: eq_abi_version,
+    new: eq_new,
+    free: eq_free,
+    param_count: eq_param_count,
+    param_descriptors: eq_param_descriptors,
+    set_param: eq_set_param,
+    get_param: eq_get_param,
+    param_events: eq_param_events,
+    process: eq_process,
+    process_offline: eq_process_offline,
+    latency_samples: eq_latency_samples,
+    io_layout: eq_get_io_layout,
+    process_io: eq_process_io,
+    reset: eq_reset,
+    state_size: eq_state_size,
+    save_state: eq_save_state,
+    load_state: eq_load_state,
+    preset_size: eq_preset_size,
+    save_preset: eq_save_preset,
+    load_preset: eq_load_preset,
+    last_error_code: eq_last_error_code,
+    last_error_message: eq_last_error_message_ptr,
+});
+
+node_abi::test_params!(Eq);
+
+dsp_core::test_block_sizes!(eq_new, eq_process(input, output), eq_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Eq as EqNode);
+
+/// Size in bytes of the `EqParams` block `eq_set_params` reads.
+#[no_mangle]
+pub extern "C" fn eq_params_size() -> usize {
+    core::mem::size_of::<EqParams>()
+}
+
+/// Reads an `EqParams` block from `params_ptr` (copied; the buffer can be reused right away):
+/// every parameter in one call, as `eq_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn eq_set_params(handle: Handle, params_ptr: *const EqParams) {
+    if params_ptr.is_null() {
+        return;
+    }
+    let params = unsafe { core::ptr::read_unaligned(params_ptr) };
+    guard(handle, |e: &mut Eq| e.apply(&params));
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shelf(gain_db: f32) -> EqParams {
+        let mut bands = [EqBand {
+            kind: 0,
+            enabled: 0,
+            freq_hz: 1000.0,
+            gain_db: 0.0,
+            q: 0.7,
+        }; MAX_BANDS];
+        bands[0] = EqBand {
+            kind: 1,
+            enabled: 1,
+            freq_hz: 200.0,
+            gain_db,
+            q: 0.7,
+        };
+        EqParams {
+            bands,
+            linear_phase: 1,
+        }
+    }
+
+    /// Moving a linear-phase band crossfades the kernels instead of stepping the output.
+    #[test]
+    fn linear_phase_gain_change_does_not_click() {
+        let eq = eq_new(48_000.0);
+        eq_set_params(eq, &shelf(0.0));
+        let input = vec![1.0; 2 * FIR_LEN];
+        let mut output = vec![0.0; 2 * FIR_LEN];
+        eq_process(eq, input.as_ptr(), output.as_mut_ptr(), input.len(), 1);
+        let before = output[output.len() - 1];
+        eq_set_params(eq, &shelf(12.0));
+        let mut after = vec![0.0; 4 * FIR_BLOCK];
+        eq_process(eq, input.as_ptr(), after.as_mut_ptr(), after.len(), 1);
+        eq_free(eq);
+
+        assert!((before - 1.0).abs() < 0.01, "{before}");
+        let end = after[after.len() - 1];
+        assert!((end - 10f32.powf(12.0 / 20.0)).abs() < 0.05, "{end}");
+        let steepest = core::iter::once(before)
+            .chain(after)
+            .collect::<Vec<_>>()
+            .windows(2)
+            .fold(0.0_f32, |m, w| m.max((w[1] - w[0]).abs()));
+        assert!(
+            steepest < 2.0 * (end - before) / FIR_BLOCK as f32,
+            "{steepest}"
+        );
+    }
+
+    /// `band` in the first slot, the rest disabled, minimum-phase.
+    fn one_band(kind: u32, freq_hz: f32, gain_db: f32) -> EqParams {
+        let mut bands = [DISABLED; MAX_BANDS];
+        bands[0] = EqBand {
+            kind,
+            enabled: 1,
+            freq_hz,
+            gain_db,
+            q: 0.707,
+        };
+        EqParams {
+            bands,
+            linear_phase: 0,
+        }
+    }
+
+    /// Gain in dB the biquads settle to on a `hz` sine.
+    fn gain_db_at(params: &EqParams, hz: f32) -> f32 {
+        let eq = eq_new(48_000.0);
+        eq_set_params(eq, params);
+        let w = core::f32::consts::TAU * hz / 48_000.0;
+        let input: Vec<f32> = (0..96_000).map(|i| (w * i as f32).sin()).collect();
+        let mut output = vec![0.0; input.len()];
+        eq_process(eq, input.as_ptr(), output.as_mut_ptr(), input.len(), 1);
+        eq_free(eq);
+        let peak = |s: &[f32]| s[48_000..].iter().fold(0.0_f32, |m, x| m.max(x.abs()));
+        20.0 * (peak(&output) / peak(&input)).log10()
+    }
+
+    /// Each band type does what its name says at and away from its frequency.
+    #[test]
+    fn band_types_shape_the_response() {
+        let cases = [
+            // (kind, gain, probe Hz, dB there)
+            (0, 6.0, 1_000.0, 6.0),
+            (0, 6.0, 50.0, 0.0),
+            (1, -9.0, 50.0, -9.0),
+            (1, -9.0, 10_000.0, 0.0),
+            (2, 9.0, 15_000.0, 9.0),
+            (3, 0.0, 1_000.0, -3.0),
+            (3, 0.0, 100.0, 0.0),
+            (4, 0.0, 1_000.0, -3.0),
+            (4, 0.0, 10_000.0, 0.0),
+        ];
+        for (kind, gain_db, hz, want) in cases {
+            let got = gain_db_at(&one_band(kind, 1_000.0, gain_db), hz);
+            assert!((got - want).abs() < 0.5, "kind {kind} at {hz} Hz: {got} dB");
+        }
+        // 12 dB per octave, a decade into the stop band; the notch takes its centre out.
+        for (kind, hz) in [(3, 10_000.0), (4, 100.0), (5, 1_000.0)] {
+            let got = gain_db_at(&one_band(kind, 1_000.0, 0.0), hz);
+            assert!(got < -36.0, "kind {kind} at {hz} Hz: {got} dB");
+        }
+    }
+
+    /// With every band disabled the signal passes untouched.
+    #[test]
+    fn disabled_bands_pass_through() {
+        let mut params = one_band(0, 1_000.0, 12.0);
+        params.bands[0].enabled = 0;
+        assert_eq!(gain_db_at(&params, 1_000.0), 0.0);
+    }
+}
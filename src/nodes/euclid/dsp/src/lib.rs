@@ -0,0 +1,430 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::events::{EventRing, NoteEvent, NOTE_OFF, NOTE_ON};
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_STEPS: u32 = 64;
+
+// Indices into `Euclid::PARAMS`, in the order of the arguments of `euclid_set_pattern`,
+// `euclid_set_output` and `euclid_set_transport`.
+pub const PARAM_STEPS: u32 = 0;
+pub const PARAM_PULSES: u32 = 1;
+pub const PARAM_ROTATION: u32 = 2;
+pub const PARAM_NOTE: u32 = 3;
+pub const PARAM_VELOCITY: u32 = 4;
+pub const PARAM_GATE: u32 = 5;
+pub const PARAM_BPM: u32 = 6;
+pub const PARAM_STEPS_PER_BEAT: u32 = 7;
+pub const PARAM_PLAYING: u32 = 8;
+
+/// Length of the trigger pulse on the click output.
+const TRIGGER_MS: f32 = 1.0;
+
+/// `pulses` hits spread as evenly as they go over `steps` (Bresenham's form of Bjorklund's
+/// algorithm; E(3, 8) is x..x..x.), bit `i` set for a hit on step `i`.
+fn euclidean(steps: u32, pulses: u32) -> u64 {
+    (0..steps)
+        .filter(|&i| (i * pulses) % steps < pulses)
+        .fold(0, |bits, i| bits | 1 << i)
+}
+
+/// Euclidean rhythm generator: plays `pulses` hits spread evenly over `steps`, turned by
+/// `rotation` steps, at `steps_per_beat` steps to the beat of the transport tempo. Each hit is
+/// a note event into an outgoing ring (for the worklet to pass on to the drum synth or
+/// sampler) and shows on the control outputs as a gate held for `gate` of the step and a
+/// 1 ms trigger click.
+pub struct Euclid {
+    out_events: EventRing,
+    hits: u64,
+    steps: u32,
+    pulses: u32,
+    rotation: u32,
+    note: u32,
+    velocity: f32,
+    gate: f32,
+    playing: bool,
+    bpm: f32,
+    steps_per_beat: u32,
+    step: u32,
+    next_step: u32,
+    /// Samples until the next step, until the gate closes and until the trigger ends.
+    until_step: f64,
+    gate_left: f64,
+    trigger_left: u32,
+    sounding: Option<u32>,
+    /// Frames of the block already rendered, so outgoing events are stamped from its start.
+    block_frame: usize,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Euclid {
+    fn samples_per_step(&self) -> f64 {
+        self.sample_rate_hz as f64 * 60.0 / (self.bpm as f64 * self.steps_per_beat as f64)
+    }
+
+    fn is_hit(&self, step: u32) -> bool {
+        let rotation = self.rotation % self.steps;
+        self.hits & 1 << ((step + self.steps - rotation) % self.steps) != 0
+    }
+
+    /// Queues an outgoing event; dropped if the worklet has let the ring fill up.
+    fn emit(&mut self, kind: u32, note: u32, value: f32, frame: usize) {
+        self.out_events.push(NoteEvent {
+            kind,
+            note,
+            value,
+            frame: (self.block_frame + frame) as u32,
+        });
+    }
+
+    fn release(&mut self, frame: usize) {
+        if let Some(note) = self.sounding.take() {
+            self.emit(NOTE_OFF, note, 0.0, frame);
+        }
+    }
+
+    fn start_step(&mut self, frame: usize) {
+        self.step = self.next_step % self.steps;
+        self.next_step = self.step + 1;
+        if !self.is_hit(self.step) {
+            return;
+        }
+        self.release(frame);
+        self.emit(NOTE_ON, self.note, self.velocity, frame);
+        self.sounding = Some(self.note);
+        self.gate_left = self.gate as f64 * self.samples_per_step();
+        self.trigger_left = (TRIGGER_MS / 1000.0 * self.sample_rate_hz) as u32;
+    }
+
+    fn render(&mut self, output: &mut [f32], channels: usize) {
+        for (i, out) in output.chunks_exact_mut(channels).enumerate() {
+            if self.playing {
+                self.until_step -= 1.0;
+                if self.until_step <= 0.0 {
+                    self.start_step(i);
+                    self.until_step += self.samples_per_step();
+                }
+            }
+            if self.sounding.is_some() {
+                self.gate_left -= 1.0;
+                if self.gate_left < 0.0 {
+                    self.release(i);
+                }
+            }
+            let gate = if self.sounding.is_some() { 1.0 } else { 0.0 };
+            let trigger = if self.trigger_left > 0 { 1.0 } else { 0.0 };
+            self.trigger_left = self.trigger_left.saturating_sub(1);
+            let cv = [gate, trigger];
+            for (ch, y) in out.iter_mut().enumerate() {
+                *y = cv.get(ch).copied().unwrap_or(0.0);
+            }
+        }
+        self.block_frame += output.len() / channels;
+    }
+}
+
+impl Node for Euclid {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Steps`; `Pulses` (up to `Steps`); `Rotation` moves the pattern that many steps later
+    /// (wrapping). `Note` and `Velocity` of the hits' note events; `Gate` is the fraction of a
+    /// step for the gate output and note length. `Steps per beat` 4 plays sixteenths;
+    /// `Playing` runs the pattern: starting plays step 1 at the next frame, stopping releases
+    /// the note. The pattern takes effect from the next step.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Steps", "", 1.0, MAX_STEPS as f32, 16.0),
+        ParamDescriptor::stepped("Pulses", "", 0.0, MAX_STEPS as f32, 4.0),
+        ParamDescriptor::stepped("Rotation", "", 0.0, MAX_STEPS as f32 - 1.0, 0.0),
+        ParamDescriptor::stepped("Note", "", 0.0, 127.0, 36.0),
+        ParamDescriptor::linear("Velocity", "", 0.0, 1.0, 1.0),
+        ParamDescriptor::linear("Gate", "", 0.01, 1.0, 0.5),
+        ParamDescriptor::linear("Tempo", "bpm", 20.0, 300.0, 120.0),
+        ParamDescriptor::stepped("Steps per beat", "", 1.0, 8.0, 4.0),
+        ParamDescriptor::toggle("Playing", false),
+    ];
+    const INPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        Euclid {
+            out_events: EventRing::new(),
+            hits: euclidean(16, 4),
+            steps: 16,
+            pulses: 4,
+            rotation: 0,
+            note: 36,
+            velocity: 1.0,
+            gate: 0.5,
+            playing: false,
+            bpm: 120.0,
+            steps_per_beat: 4,
+            step: 0,
+            next_step: 0,
+            until_step: 1.0,
+            gate_left: 0.0,
+            trigger_left: 0,
+            sounding: None,
+            block_frame: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        let whole = |max: f32| clamp(value, 0.0, max).round() as u32;
+        match index {
+            PARAM_STEPS => {
+                self.steps = whole(MAX_STEPS as f32).max(1);
+                self.hits = euclidean(self.steps, self.pulses.min(self.steps));
+            }
+            PARAM_PULSES => {
+                self.pulses = whole(MAX_STEPS as f32);
+                self.hits = euclidean(self.steps, self.pulses.min(self.steps));
+            }
+            PARAM_ROTATION => self.rotation = whole(MAX_STEPS as f32 - 1.0),
+            PARAM_NOTE => self.note = whole(127.0),
+            PARAM_VELOCITY => self.velocity = clamp(value, 0.0, 1.0),
+            PARAM_GATE => self.gate = clamp(value, 0.01, 1.0),
+            PARAM_BPM => self.bpm = clamp(value, 20.0, 300.0),
+            PARAM_STEPS_PER_BEAT => self.steps_per_beat = whole(8.0).max(1),
+            PARAM_PLAYING => {
+                let playing = clamp(value, 0.0, 1.0) >= 0.5;
+                if playing && !self.playing {
+                    self.next_step = 0;
+                    self.until_step = 1.0;
+                } else if !playing && self.playing {
+                    self.release(0);
+                }
+                self.playing = playing;
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_STEPS => self.steps as f32,
+            PARAM_PULSES => self.pulses as f32,
+            PARAM_ROTATION => self.rotation as f32,
+            PARAM_NOTE => self.note as f32,
+            PARAM_VELOCITY => self.velocity,
+            PARAM_GATE => self.gate,
+            PARAM_BPM => self.bpm,
+            PARAM_STEPS_PER_BEAT => self.steps_per_beat as f32,
+            PARAM_PLAYING => u32::from(self.playing) as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn start_block(&mut self) {
+        self.block_frame = 0;
+    }
+
+    /// Renders the gate on channel 0 and the trigger click on 1; any further channels are
+    /// silent.
+    fn process(&mut self, _input: &[f32], output: &mut [f32], channels: usize) {
+        self.render(output, channels);
+    }
+
+    /// Rewinds to the first step and ends any sounding note (sending its note-off); the
+    /// transport state is kept.
+    fn reset(&mut self) {
+        self.release(0);
+        self.step = 0;
+        self.next_step = 0;
+        self.until_step = 1.0;
+        self.gate_left = 0.0;
+        self.trigger_left = 0;
+    }
+}
+
+node_abi::export_node!(Euclid {
+    abi_version: euclid_abi_version,
+    new: euclid_new,
+    free: euclid_free,
+    param_count: euclid_param_count,
+    param_descriptors: euclid_param_descriptors,
+    set_param: euclid_set_param,
+    get_param: euclid_get_param,
+    param_events: euclid_param_events,
+    process: euclid_process,
+    process_offline: euclid_process_offline,
+    latency_samples: euclid_latency_samples,
+    io_layout: euclid_get_io_layout,
+    process_io: euclid_process_io,
+    reset: euclid_reset,
+    state_size: euclid_state_size,
+    save_state: euclid_save_state,
+    load_state: euclid_load_state,
+    preset_size: euclid_preset_size,
+    save_preset: euclid_save_preset,
+    load_preset: euclid_load_preset,
+    last_error_code: euclid_last_error_code,
+    last_error_message: euclid_last_error_message_ptr,
+});
+
+node_abi::test_params!(Euclid);
+
+dsp_core::test_block_sizes!(
+    euclid_new,
+    euclid_process(input, output),
+    euclid_free,
+    setup: |p| euclid_set_transport(p, 300.0, 8, 1),
+);
+
+node_abi::test_io_block!(
+    Euclid,
+    setup: |e: &mut Euclid| {
+        e.set_param(PARAM_BPM, 300.0);
+        e.set_param(PARAM_STEPS_PER_BEAT, 8.0);
+        e.set_param(PARAM_PLAYING, 1.0);
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Euclid as EuclidNode);
+
+/// The ring the generator writes its note events into, for the worklet to read (layout in
+/// `dsp_core::events`; the worklet advances the read count). Frames are offsets into the
+/// block just rendered. Stays put for the node's lifetime.
+#[no_mangle]
+pub extern "C" fn euclid_out_events(handle: Handle) -> *mut EventRing {
+    guard(handle, |e: &mut Euclid| &mut e.out_events as *mut EventRing)
+        .unwrap_or(core::ptr::null_mut())
+}
+
+/// `Steps`, `Pulses` and `Rotation` in one call, as `euclid_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn euclid_set_pattern(handle: Handle, steps: u32, pulses: u32, rotation: u32) {
+    guard(handle, |e: &mut Euclid| {
+        e.set_param(PARAM_STEPS, steps as f32);
+        e.set_param(PARAM_PULSES, pulses as f32);
+        e.set_param(PARAM_ROTATION, rotation as f32);
+    });
+}
+
+/// `Note`, `Velocity` and `Gate` in one call.
+#[no_mangle]
+pub extern "C" fn euclid_set_output(handle: Handle, note: u32, velocity: f32, gate: f32) {
+    guard(handle, |e: &mut Euclid| {
+        e.set_param(PARAM_NOTE, note as f32);
+        e.set_param(PARAM_VELOCITY, velocity);
+        e.set_param(PARAM_GATE, gate);
+    });
+}
+
+/// `Tempo`, `Steps per beat` and `Playing` in one call.
+#[no_mangle]
+pub extern "C" fn euclid_set_transport(
+    handle: Handle,
+    bpm: f32,
+    steps_per_beat: u32,
+    playing: u32,
+) {
+    guard(handle, |e: &mut Euclid| {
+        e.set_param(PARAM_BPM, bpm);
+        e.set_param(PARAM_STEPS_PER_BEAT, steps_per_beat as f32);
+        e.set_param(PARAM_PLAYING, playing as f32);
+    });
+}
+
+/// Step playing now (0-based), for the UI.
+#[no_mangle]
+pub extern "C" fn euclid_current_step(handle: Handle) -> u32 {
+    guard(handle, |e: &mut Euclid| e.step).unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 48_000.0;
+    /// Sixteenths at 120 bpm.
+    const STEP: usize = 6_000;
+
+    /// Renders `frames` on `channels` channels in render quanta, returning the output and the
+    /// events sent as (kind, note, frame from the start).
+    fn render(h: Handle, frames: usize, channels: usize) -> (Vec<f32>, Vec<(u32, u32, usize)>) {
+        let mut output = vec![0.0; frames * channels];
+        let mut events = Vec::new();
+        for (block, y) in output.chunks_mut(128 * channels).enumerate() {
+            euclid_process(
+                h,
+                core::ptr::null(),
+                y.as_mut_ptr(),
+                y.len() / channels,
+                channels,
+            );
+            let ring = unsafe { &mut *euclid_out_events(h) };
+            while let Some(e) = ring.pop() {
+                events.push((e.kind, e.note, block * 128 + e.frame as usize));
+            }
+        }
+        (output, events)
+    }
+
+    /// Hits spread as evenly as they go.
+    #[test]
+    fn pulses_spread_evenly() {
+        assert_eq!(euclidean(8, 3), 0b0100_1001);
+        assert_eq!(euclidean(8, 5), 0b1011_0101);
+        assert_eq!(euclidean(16, 4), 0x1111);
+        assert_eq!(euclidean(12, 0), 0);
+        assert_eq!(euclidean(7, 7), 0b111_1111);
+    }
+
+    /// Rotated by a step, E(3, 8) hits steps 1, 4 and 7, each note held for its gate.
+    #[test]
+    fn hits_play_on_their_steps() {
+        let h = euclid_new(RATE);
+        euclid_set_pattern(h, 8, 3, 1);
+        euclid_set_output(h, 38, 0.8, 0.5);
+        euclid_set_transport(h, 120.0, 4, 1);
+        let (_, events) = render(h, 8 * STEP, 1);
+        assert_eq!(euclid_current_step(h), 7);
+        euclid_free(h);
+        let expected: Vec<_> = [1, 4, 7]
+            .into_iter()
+            .flat_map(|step| {
+                [
+                    (NOTE_ON, 38, step * STEP),
+                    (NOTE_OFF, 38, step * STEP + STEP / 2),
+                ]
+            })
+            .collect();
+        assert_eq!(events, expected);
+    }
+
+    /// Each hit opens the gate output for its share of the step and clicks the trigger
+    /// output for 1 ms.
+    #[test]
+    fn gate_and_trigger_outputs() {
+        let h = euclid_new(RATE);
+        euclid_set_pattern(h, 4, 1, 0);
+        euclid_set_output(h, 36, 1.0, 0.25);
+        euclid_set_transport(h, 120.0, 4, 1);
+        let (y, _) = render(h, STEP, 2);
+        euclid_free(h);
+        let (gate, trigger): (Vec<f32>, Vec<f32>) = y.chunks(2).map(|f| (f[0], f[1])).unzip();
+        assert_eq!(gate.iter().filter(|&&g| g == 1.0).count(), STEP / 4);
+        assert!(gate[..STEP / 4].iter().all(|&g| g == 1.0));
+        assert!(trigger[..48].iter().all(|&t| t == 1.0));
+        assert!(trigger[48..].iter().all(|&t| t == 0.0));
+    }
+}
@@ -0,0 +1,268 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::biquad::Response;
+use dsp_core::crossover::{Crossover, Lr4, MAX_BANDS};
+use dsp_core::gain::db_to_lin;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Exciter::PARAMS`, in the order of `exciter_set_params`' arguments.
+pub const PARAM_FREQ_HZ: u32 = 0;
+pub const PARAM_DRIVE_DB: u32 = 1;
+pub const PARAM_EVEN: u32 = 2;
+pub const PARAM_AMOUNT: u32 = 3;
+
+/// Offset into the curve at full `even`, as in the console saturator.
+const EVEN_BIAS: f32 = 0.6;
+
+/// Harmonic exciter. An LR4 crossover (the multiband compressor's) splits off the top end;
+/// that band is driven into a soft curve and only the distortion products are kept (the
+/// curve's linear part is subtracted), high-passed again at the split so nothing lands below
+/// it, and added back to the band at `amount`. The bands then sum to an allpassed copy of the
+/// input plus the new harmonics.
+#[repr(C)]
+pub struct Exciter {
+    freq_hz: f32,
+    drive_db: f32,
+    drive: f32,
+    even: f32,
+    amount: f32,
+    /// `tanh(even * EVEN_BIAS)`, and the small-signal slope of the shifted curve.
+    bias_out: f32,
+    bias_slope: f32,
+    crossovers: [Crossover; MAX_CHANNELS],
+    cleanup: [Lr4; MAX_CHANNELS],
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Exciter {
+    fn update(&mut self) {
+        let sr = self.sample_rate_hz;
+        for xo in &mut self.crossovers {
+            xo.set(2, &[self.freq_hz], sr);
+        }
+        for f in &mut self.cleanup {
+            f.set(Response::Highpass, self.freq_hz, sr);
+        }
+        self.bias_out = (self.even * EVEN_BIAS).tanh();
+        self.bias_slope = 1.0 - self.bias_out * self.bias_out;
+    }
+
+    /// Distortion products of the curve at `x` (already driven): the curve minus its
+    /// unit-slope linear part.
+    #[inline]
+    fn harmonics(&self, x: f32) -> f32 {
+        let odd = x.tanh();
+        let even = ((x + self.even * EVEN_BIAS).tanh() - self.bias_out) / self.bias_slope;
+        odd + (even - odd) * self.even - x
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let mut bands = [0.0; MAX_BANDS];
+        let makeup = self.amount / self.drive;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let x = if x.is_finite() { *x } else { 0.0 };
+                self.crossovers[ch].split(x, &mut bands);
+                let h = self.harmonics(bands[1] * self.drive);
+                let h = self.cleanup[ch].process(h);
+                *y = bands[0] + bands[1] + h * makeup;
+            }
+        }
+    }
+}
+
+impl Node for Exciter {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Frequency` is where the excited band starts; `Drive` into the curve (harmonics grow
+    /// with it, their level is normalised back down); `Even` 0 (odd harmonics) to 1 (mostly
+    /// even); `Amount` of the harmonics added.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::log("Frequency", "Hz", 1000.0, 12_000.0, 3000.0),
+        ParamDescriptor::linear("Drive", "dB", 0.0, 36.0, 12.0),
+        ParamDescriptor::linear("Even", "", 0.0, 1.0, 0.5),
+        ParamDescriptor::linear("Amount", "", 0.0, 2.0, 0.5),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut e = Exciter {
+            freq_hz: 3000.0,
+            drive_db: 12.0,
+            drive: db_to_lin(12.0),
+            even: 0.5,
+            amount: 0.5,
+            bias_out: 0.0,
+            bias_slope: 1.0,
+            crossovers: [Crossover::new(); MAX_CHANNELS],
+            cleanup: [Lr4::default(); MAX_CHANNELS],
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        e.update();
+        e
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_FREQ_HZ => {
+                self.freq_hz = clamp(value, 1000.0, 12000.0).min(self.sample_rate_hz * 0.4);
+            }
+            PARAM_DRIVE_DB => {
+                self.drive_db = clamp(value, 0.0, 36.0);
+                self.drive = db_to_lin(self.drive_db);
+            }
+            PARAM_EVEN => self.even = clamp(value, 0.0, 1.0),
+            PARAM_AMOUNT => self.amount = clamp(value, 0.0, 2.0),
+            _ => return,
+        }
+        self.update();
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_FREQ_HZ => self.freq_hz,
+            PARAM_DRIVE_DB => self.drive_db,
+            PARAM_EVEN => self.even,
+            PARAM_AMOUNT => self.amount,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Clears the band-split and clean-up filters.
+    fn reset(&mut self) {
+        for c in &mut self.crossovers {
+            c.clear();
+        }
+        for f in &mut self.cleanup {
+            f.clear();
+        }
+    }
+}
+
+node_abi::export_node!(Exciter {
+    abi_version: exciter_abi_version,
+    new: exciter_new,
+    free: exciter_free,
+    param_count: exciter_param_count,
+    param_descriptors: exciter_param_descriptors,
+    set_param: exciter_set_param,
+    get_param: exciter_get_param,
+    param_events: exciter_param_events,
+    process: exciter_process,
+    process_offline: exciter_process_offline,
+    latency_samples: exciter_latency_samples,
+    io_layout: exciter_get_io_layout,
+    process_io: exciter_process_io,
+    reset: exciter_reset,
+    state_size: exciter_state_size,
+    save_state: exciter_save_state,
+    load_state: exciter_load_state,
+    preset_size: exciter_preset_size,
+    save_preset: exciter_save_preset,
+    load_preset: exciter_load_preset,
+    last_error_code: exciter_last_error_code,
+    last_error_message: exciter_last_error_message_ptr,
+});
+
+node_abi::test_params!(Exciter);
+
+dsp_core::test_block_sizes!(exciter_new, exciter_process(input, output), exciter_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Exciter as ExciterNode);
+
+/// Every parameter in one call, as `exciter_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn exciter_set_params(
+    handle: Handle,
+    freq_hz: f32,
+    drive_db: f32,
+    even: f32,
+    amount: f32,
+) {
+    guard(handle, |e: &mut Exciter| {
+        e.set_param(PARAM_FREQ_HZ, freq_hz);
+        e.set_param(PARAM_DRIVE_DB, drive_db);
+        e.set_param(PARAM_EVEN, even);
+        e.set_param(PARAM_AMOUNT, amount);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::TAU;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    fn exciter(params: &[(u32, f32)]) -> Exciter {
+        let mut e = Exciter::new(RATE);
+        for &(index, value) in params {
+            e.set_param(index, value);
+        }
+        e
+    }
+
+    /// 1.5 s of a sine at `hz` through `e`.
+    fn render(e: &mut Exciter, hz: f32) -> Vec<f32> {
+        let w = TAU * hz / RATE;
+        let input: Vec<f32> = (0..72_000).map(|i| 0.5 * (w * i as f32).sin()).collect();
+        render_quanta(input.len(), |span, y| Node::process(e, &input[span], y, 1))
+    }
+
+    /// Below the split the signal passes at its level with nothing added.
+    #[test]
+    fn below_the_split_is_untouched() {
+        let y = render(&mut exciter(&[(PARAM_AMOUNT, 2.0)]), 200.0);
+        assert!((amplitude(&y, 200.0) - 0.5).abs() < 0.005);
+        assert!(amplitude(&y, 400.0) < 1e-3 && amplitude(&y, 600.0) < 1e-3);
+    }
+
+    /// Above the split the band gains harmonics, odd ones at `Even` 0 and mostly even ones at
+    /// 1, in proportion to `Amount`.
+    #[test]
+    fn top_band_gains_harmonics() {
+        let harmonics = |even: f32, amount: f32| {
+            let y = render(
+                &mut exciter(&[(PARAM_EVEN, even), (PARAM_AMOUNT, amount)]),
+                4000.0,
+            );
+            (amplitude(&y, 8000.0), amplitude(&y, 12_000.0))
+        };
+        let (second, third) = harmonics(0.0, 1.0);
+        assert!(second < 1e-3 && third > 0.01, "{second} {third}");
+        let (second, third) = harmonics(1.0, 1.0);
+        assert!(second > third, "{second} {third}");
+        let (half, _) = harmonics(1.0, 0.5);
+        assert!((half / second - 0.5).abs() < 0.01);
+        let (second, third) = harmonics(1.0, 0.0);
+        assert!(second < 1e-4 && third < 1e-4);
+    }
+}
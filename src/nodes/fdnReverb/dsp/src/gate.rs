@@ -0,0 +1,76 @@
+use dsp_core::denormal::flush;
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+/// Decay of the key's peak detector, long enough to ride over the gaps between cycles.
+const DETECTOR_RELEASE_MS: f32 = 20.0;
+/// Opening ramp; short enough to sound instant without clicking on an already-ringing tail.
+const ATTACK_MS: f32 = 1.0;
+
+/// Gate for the reverb tail, keyed from the dry input: open while the key is above the
+/// threshold, held for `hold` after it drops below, then ramped shut linearly over `release`
+/// (the abrupt, straight-line cut of the classic gated drum room).
+pub struct TailGate {
+    pub enabled: bool,
+    threshold: f32,
+    hold_samples: u32,
+    hold_left: u32,
+    level: f32,
+    detector_coeff: f32,
+    attack_step: f32,
+    release_step: f32,
+    gain: f32,
+}
+
+impl TailGate {
+    pub fn new(sample_rate_hz: f32) -> Self {
+        let mut g = Self {
+            enabled: false,
+            threshold: 0.0,
+            hold_samples: 0,
+            hold_left: 0,
+            level: 0.0,
+            detector_coeff: 0.0,
+            attack_step: 1.0,
+            release_step: 1.0,
+            gain: 1.0,
+        };
+        g.set(-30.0, 250.0, 60.0, sample_rate_hz);
+        g
+    }
+
+    pub fn set(&mut self, threshold_db: f32, hold_ms: f32, release_ms: f32, sample_rate_hz: f32) {
+        let ms = sample_rate_hz / 1000.0;
+        self.threshold = (10.0_f32).powf(threshold_db / 20.0);
+        self.hold_samples = (hold_ms * ms) as u32;
+        self.detector_coeff = (-1.0 / (DETECTOR_RELEASE_MS * ms)).exp();
+        self.attack_step = 1.0 / (ATTACK_MS * ms).max(1.0);
+        self.release_step = 1.0 / (release_ms * ms).max(1.0);
+    }
+
+    /// Forgets the detector level and hold, and opens fully.
+    pub fn clear(&mut self) {
+        self.hold_left = 0;
+        self.level = 0.0;
+        self.gain = 1.0;
+    }
+
+    /// Advances one sample on the key and returns the tail gain (1 when disabled).
+    #[inline]
+    pub fn process(&mut self, key: f32) -> f32 {
+        if !self.enabled {
+            return 1.0;
+        }
+        self.level = flush(key.abs().max(self.level * self.detector_coeff));
+        if self.level > self.threshold {
+            self.hold_left = self.hold_samples;
+            self.gain = (self.gain + self.attack_step).min(1.0);
+        } else if self.hold_left > 0 {
+            self.hold_left -= 1;
+        } else {
+            self.gain = (self.gain - self.release_step).max(0.0);
+        }
+        self.gain
+    }
+}
@@ -0,0 +1,620 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod gate;
+mod pitch;
+
+use alloc::vec::Vec;
+use core::f32::consts::TAU;
+use dsp_core::delay::DelayLine;
+use dsp_core::denormal::flush;
+use dsp_core::envelope::one_pole_coeff;
+use dsp_core::param::clamp;
+use gate::TailGate;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+use pitch::PitchShifter;
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+pub const LINES: usize = 8;
+
+// Indices into `Reverb::PARAMS`, in the order of the arguments of `reverb_set_params`,
+// `reverb_set_shimmer` and `reverb_set_gate`.
+pub const PARAM_PREDELAY_MS: u32 = 0;
+pub const PARAM_SIZE: u32 = 1;
+pub const PARAM_DECAY_S: u32 = 2;
+pub const PARAM_DAMPING: u32 = 3;
+pub const PARAM_MODULATION: u32 = 4;
+pub const PARAM_EARLY: u32 = 5;
+pub const PARAM_MIX: u32 = 6;
+pub const PARAM_FREEZE: u32 = 7;
+pub const PARAM_SHIMMER: u32 = 8;
+pub const PARAM_SHIMMER_INTERVAL: u32 = 9;
+pub const PARAM_GATE: u32 = 10;
+pub const PARAM_GATE_THRESHOLD_DB: u32 = 11;
+pub const PARAM_GATE_HOLD_MS: u32 = 12;
+pub const PARAM_GATE_RELEASE_MS: u32 = 13;
+
+/// FDN line lengths at `size` 0.5 (scale 1), mutually prime-ish so the modes don't stack.
+const LINE_MS: [f32; LINES] = [29.7, 37.1, 41.1, 43.7, 53.0, 59.9, 67.7, 73.1];
+/// Per-line modulation rates; depth is at most `MOD_MS` either way.
+const MOD_HZ: [f32; LINES] = [0.31, 0.43, 0.53, 0.61, 0.73, 0.83, 0.97, 1.09];
+const MOD_MS: f32 = 0.3;
+/// Early reflections as `(ms, left gain, right gain)`, scaled in time by the size.
+const EARLY_TAPS: [(f32, f32, f32); 8] = [
+    (7.0, 0.8, 0.6),
+    (11.3, 0.5, 0.7),
+    (17.9, 0.6, 0.4),
+    (23.1, 0.35, 0.5),
+    (29.4, 0.4, 0.3),
+    (37.7, 0.25, 0.33),
+    (43.2, 0.22, 0.18),
+    (53.9, 0.12, 0.15),
+];
+const MIN_SCALE: f32 = 0.4;
+const MAX_SCALE: f32 = 2.0;
+const MAX_PREDELAY_MS: f32 = 500.0;
+/// Shimmer intervals selectable by `Shimmer interval`.
+const SHIMMER_SEMITONES: [f32; 2] = [12.0, 7.0];
+
+/// Share of each line's feedback replaced by its shifted copy at full shimmer.
+const MAX_SHIMMER: f32 = 0.6;
+
+/// Size changes glide over roughly this long (the lines pitch-bend instead of clicking).
+const SIZE_GLIDE_MS: f32 = 100.0;
+
+/// 8x8 feedback delay network with a Householder feedback matrix. The input is summed to mono,
+/// pre-delayed, tapped for early reflections and injected into every line; each line has its
+/// own decay gain (for an RT60 of `decay_s`), a one-pole damping low-pass and a slow sine
+/// modulation of its length. Even lines feed the left output, odd lines the right. With shimmer
+/// on, part of each line's feedback is swapped for a pitch-shifted copy, so each pass comes
+/// back higher; it is a crossfade rather than an extra loop, so the decay can't run away.
+/// The optional tail gate, keyed from the dry input, cuts the whole wet signal.
+#[repr(C)]
+pub struct Reverb {
+    predelay_ms: f32,
+    predelay: f32,
+    size: f32,
+    /// Length multiplier from `size`; `scale` glides towards `target_scale`.
+    target_scale: f32,
+    scale: f32,
+    scale_coeff: f32,
+    decay_s: f32,
+    /// `Damping` as set; `damping` is the low-pass coefficient it gives (0 when frozen).
+    damping_amount: f32,
+    damping: f32,
+    modulation: f32,
+    early: f32,
+    mix: f32,
+    freeze: bool,
+    shimmer_amount: f32,
+    shimmer: f32,
+    shimmer_interval: u32,
+    shifters: Vec<PitchShifter>,
+    gate: TailGate,
+    gate_threshold_db: f32,
+    gate_hold_ms: f32,
+    gate_release_ms: f32,
+    gains: [f32; LINES],
+    lowpass: [f32; LINES],
+    mod_phase: [f32; LINES],
+    input_line: DelayLine,
+    lines: Vec<DelayLine>,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Reverb {
+    fn update_damping(&mut self) {
+        // Frozen tails keep their colour instead of dulling further on every pass.
+        self.damping = if self.freeze {
+            0.0
+        } else {
+            self.damping_amount * 0.9
+        };
+    }
+
+    fn update_gate(&mut self) {
+        self.gate.set(
+            self.gate_threshold_db,
+            self.gate_hold_ms,
+            self.gate_release_ms,
+            self.sample_rate_hz,
+        );
+    }
+
+    /// Per-line gain for -60 dB after `decay_s` at the current size; 1 when frozen.
+    fn update_gains(&mut self) {
+        for (g, ms) in self.gains.iter_mut().zip(LINE_MS) {
+            *g = if self.freeze {
+                1.0
+            } else {
+                let len_s = ms * self.scale / 1000.0;
+                (10.0_f32).powf(-3.0 * len_s / self.decay_s)
+            };
+        }
+    }
+
+    /// Returns the wet `(left, right)` for one mono input sample.
+    #[inline]
+    fn tick(&mut self, x: f32) -> (f32, f32) {
+        let sr = self.sample_rate_hz;
+        let ms = sr / 1000.0;
+        let moving = (self.scale - self.target_scale).abs() > 1e-5;
+        if moving {
+            self.scale = self.target_scale + (self.scale - self.target_scale) * self.scale_coeff;
+            self.update_gains();
+        }
+
+        self.input_line.push(x);
+        let mut early = (0.0, 0.0);
+        if self.early > 0.0 {
+            for &(t, gl, gr) in &EARLY_TAPS {
+                let v = self
+                    .input_line
+                    .read_linear(self.predelay + t * self.scale * ms);
+                early.0 += v * gl;
+                early.1 += v * gr;
+            }
+            early.0 *= self.early;
+            early.1 *= self.early;
+        }
+        let injected = if self.freeze {
+            0.0
+        } else {
+            self.input_line.read_linear(self.predelay)
+        };
+
+        let mut outs = [0.0_f32; LINES];
+        let depth = self.modulation * MOD_MS * ms;
+        for (i, out) in outs.iter_mut().enumerate() {
+            let wobble = depth * (TAU * self.mod_phase[i]).sin();
+            self.mod_phase[i] = (self.mod_phase[i] + MOD_HZ[i] / sr).fract();
+            let v = self.lines[i].read_cubic(LINE_MS[i] * self.scale * ms + wobble - 1.0);
+            let lp = &mut self.lowpass[i];
+            *lp = flush(v + (*lp - v) * self.damping);
+            *out = *lp * self.gains[i];
+        }
+
+        // Householder: reflect about the all-ones vector, lossless and fully mixing.
+        let reflect = outs.iter().sum::<f32>() * (2.0 / LINES as f32);
+        let shimmer = self.shimmer;
+        for (i, (line, out)) in self.lines.iter_mut().zip(&outs).enumerate() {
+            let sign = if i % 2 == 0 { 1.0 } else { -1.0 };
+            let mut v = out - reflect;
+            if shimmer > 0.0 {
+                v += (self.shifters[i].process(v) - v) * shimmer;
+            }
+            line.push(v + sign * injected * 0.5);
+        }
+
+        let mut wet = early;
+        for (i, out) in outs.iter().enumerate() {
+            if i % 2 == 0 {
+                wet.0 += out * 0.5;
+            } else {
+                wet.1 += out * 0.5;
+            }
+        }
+        let gain = self.gate.process(x);
+        (wet.0 * gain, wet.1 * gain)
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let norm = 1.0 / channels as f32;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            let mono = inp.iter().sum::<f32>() * norm;
+            let (l, r) = self.tick(mono);
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let wet = if ch % 2 == 0 || channels == 1 { l } else { r };
+                *y = *x * (1.0 - self.mix) + wet * self.mix;
+            }
+        }
+    }
+}
+
+impl Node for Reverb {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Size` scales the room (line lengths 0.4x–2x); `Decay` is the RT60; `Damping` darkens
+    /// the tail; `Modulation` choruses the lines; `Early` is the early-reflection level.
+    /// `Freeze` stops input and decay so the current tail sustains indefinitely. `Shimmer` is
+    /// how much of the tail is pitch-shifted on every pass, `Shimmer interval` 0 an octave up,
+    /// 1 a fifth. `Gate` cuts the wet signal, keyed from the dry input: open above
+    /// `Gate threshold`, held `Gate hold` after the input falls below it, then cut over
+    /// `Gate release`.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Pre-delay", "ms", 0.0, MAX_PREDELAY_MS, 0.0),
+        ParamDescriptor::linear("Size", "", 0.0, 1.0, 0.5),
+        ParamDescriptor::log("Decay", "s", 0.1, 30.0, 2.0),
+        ParamDescriptor::linear("Damping", "", 0.0, 1.0, 0.3),
+        ParamDescriptor::linear("Modulation", "", 0.0, 1.0, 0.5),
+        ParamDescriptor::linear("Early", "", 0.0, 1.0, 0.5),
+        ParamDescriptor::linear("Mix", "", 0.0, 1.0, 0.3),
+        ParamDescriptor::toggle("Freeze", false),
+        ParamDescriptor::linear("Shimmer", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::stepped("Shimmer interval", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::toggle("Gate", false),
+        ParamDescriptor::linear("Gate threshold", "dB", -80.0, 0.0, -30.0),
+        ParamDescriptor::linear("Gate hold", "ms", 0.0, 2000.0, 250.0),
+        ParamDescriptor::log("Gate release", "ms", 1.0, 1000.0, 60.0),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let ms = sample_rate_hz / 1000.0;
+        let longest_early = EARLY_TAPS[EARLY_TAPS.len() - 1].0;
+        let input_len = ((MAX_PREDELAY_MS + longest_early * MAX_SCALE) * ms) as usize + 2;
+        let scale = MIN_SCALE + (MAX_SCALE - MIN_SCALE) * 0.5;
+        let mut r = Reverb {
+            predelay_ms: 0.0,
+            predelay: 0.0,
+            size: 0.5,
+            target_scale: scale,
+            scale,
+            scale_coeff: one_pole_coeff(SIZE_GLIDE_MS, sample_rate_hz),
+            decay_s: 2.0,
+            damping_amount: 0.3,
+            damping: 0.0,
+            modulation: 0.5,
+            early: 0.5,
+            mix: 0.3,
+            freeze: false,
+            shimmer_amount: 0.0,
+            shimmer: 0.0,
+            shimmer_interval: 0,
+            shifters: (0..LINES)
+                .map(|_| PitchShifter::new(sample_rate_hz))
+                .collect(),
+            gate: TailGate::new(sample_rate_hz),
+            gate_threshold_db: -30.0,
+            gate_hold_ms: 250.0,
+            gate_release_ms: 60.0,
+            gains: [0.0; LINES],
+            lowpass: [0.0; LINES],
+            mod_phase: core::array::from_fn(|i| i as f32 / LINES as f32),
+            input_line: DelayLine::new(input_len),
+            lines: LINE_MS
+                .iter()
+                .map(|&t| DelayLine::new(((t * MAX_SCALE + MOD_MS) * ms) as usize + 2))
+                .collect(),
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        r.update_damping();
+        r.update_gains();
+        r
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_PREDELAY_MS => {
+                self.predelay_ms = clamp(value, 0.0, MAX_PREDELAY_MS);
+                self.predelay = self.predelay_ms / 1000.0 * self.sample_rate_hz;
+            }
+            PARAM_SIZE => {
+                self.size = clamp(value, 0.0, 1.0);
+                self.target_scale = MIN_SCALE + (MAX_SCALE - MIN_SCALE) * self.size;
+            }
+            PARAM_DECAY_S => {
+                self.decay_s = clamp(value, 0.1, 30.0);
+                self.update_gains();
+            }
+            PARAM_DAMPING => {
+                self.damping_amount = clamp(value, 0.0, 1.0);
+                self.update_damping();
+            }
+            PARAM_MODULATION => self.modulation = clamp(value, 0.0, 1.0),
+            PARAM_EARLY => self.early = clamp(value, 0.0, 1.0),
+            PARAM_MIX => self.mix = clamp(value, 0.0, 1.0),
+            PARAM_FREEZE => {
+                self.freeze = clamp(value, 0.0, 1.0) >= 0.5;
+                self.update_damping();
+                self.update_gains();
+            }
+            PARAM_SHIMMER => {
+                self.shimmer_amount = clamp(value, 0.0, 1.0);
+                self.shimmer = self.shimmer_amount * MAX_SHIMMER;
+            }
+            PARAM_SHIMMER_INTERVAL => {
+                let last = SHIMMER_SEMITONES.len() - 1;
+                self.shimmer_interval = clamp(value, 0.0, last as f32).round() as u32;
+                let semitones = SHIMMER_SEMITONES[self.shimmer_interval as usize];
+                for shifter in &mut self.shifters {
+                    shifter.set_semitones(semitones);
+                }
+            }
+            PARAM_GATE => self.gate.enabled = clamp(value, 0.0, 1.0) >= 0.5,
+            PARAM_GATE_THRESHOLD_DB => {
+                self.gate_threshold_db = clamp(value, -80.0, 0.0);
+                self.update_gate();
+            }
+            PARAM_GATE_HOLD_MS => {
+                self.gate_hold_ms = clamp(value, 0.0, 2000.0);
+                self.update_gate();
+            }
+            PARAM_GATE_RELEASE_MS => {
+                self.gate_release_ms = clamp(value, 1.0, 1000.0);
+                self.update_gate();
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_PREDELAY_MS => self.predelay_ms,
+            PARAM_SIZE => self.size,
+            PARAM_DECAY_S => self.decay_s,
+            PARAM_DAMPING => self.damping_amount,
+            PARAM_MODULATION => self.modulation,
+            PARAM_EARLY => self.early,
+            PARAM_MIX => self.mix,
+            PARAM_FREEZE => u32::from(self.freeze) as f32,
+            PARAM_SHIMMER => self.shimmer_amount,
+            PARAM_SHIMMER_INTERVAL => self.shimmer_interval as f32,
+            PARAM_GATE => u32::from(self.gate.enabled) as f32,
+            PARAM_GATE_THRESHOLD_DB => self.gate_threshold_db,
+            PARAM_GATE_HOLD_MS => self.gate_hold_ms,
+            PARAM_GATE_RELEASE_MS => self.gate_release_ms,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Cuts the tail: empties the pre-delay, the feedback network and the shimmer shifters,
+    /// opens the tail gate and finishes any size glide.
+    fn reset(&mut self) {
+        self.input_line.clear();
+        for line in &mut self.lines {
+            line.clear();
+        }
+        self.lowpass = [0.0; LINES];
+        self.mod_phase = core::array::from_fn(|i| i as f32 / LINES as f32);
+        self.scale = self.target_scale;
+        self.update_gains();
+        for s in &mut self.shifters {
+            s.clear();
+        }
+        self.gate.clear();
+    }
+}
+
+node_abi::export_node!(Reverb {
+    abi_version: reverb_abi_version,
+    new: reverb_new,
+    free: reverb_free,
+    param_count: reverb_param_count,
+    param_descriptors: reverb_param_descriptors,
+    set_param: reverb_set_param,
+    get_param: reverb_get_param,
+    param_events: reverb_param_events,
+    process: reverb_process,
+    process_offline: reverb_process_offline,
+    latency_samples: reverb_latency_samples,
+    io_layout: reverb_get_io_layout,
+    process_io: reverb_process_io,
+    reset: reverb_reset,
+    state_size: reverb_state_size,
+    save_state: reverb_save_state,
+    load_state: reverb_load_state,
+    preset_size: reverb_preset_size,
+    save_preset: reverb_save_preset,
+    load_preset: reverb_load_preset,
+    last_error_code: reverb_last_error_code,
+    last_error_message: reverb_last_error_message_ptr,
+});
+
+node_abi::test_params!(Reverb);
+
+dsp_core::test_block_sizes!(reverb_new, reverb_process(input, output), reverb_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Reverb as ReverbNode);
+
+/// `Pre-delay` through `Freeze` in one call, as `reverb_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn reverb_set_params(
+    handle: Handle,
+    predelay_ms: f32,
+    size: f32,
+    decay_s: f32,
+    damping: f32,
+    modulation: f32,
+    early: f32,
+    mix: f32,
+    freeze: u32,
+) {
+    guard(handle, |r: &mut Reverb| {
+        r.set_param(PARAM_PREDELAY_MS, predelay_ms);
+        r.set_param(PARAM_SIZE, size);
+        r.set_param(PARAM_DECAY_S, decay_s);
+        r.set_param(PARAM_DAMPING, damping);
+        r.set_param(PARAM_MODULATION, modulation);
+        r.set_param(PARAM_EARLY, early);
+        r.set_param(PARAM_MIX, mix);
+        r.set_param(PARAM_FREEZE, freeze as f32);
+    });
+}
+
+/// `Shimmer` and `Shimmer interval` in one call.
+#[no_mangle]
+pub extern "C" fn reverb_set_shimmer(handle: Handle, amount: f32, interval: u32) {
+    guard(handle, |r: &mut Reverb| {
+        r.set_param(PARAM_SHIMMER, amount);
+        r.set_param(PARAM_SHIMMER_INTERVAL, interval as f32);
+    });
+}
+
+/// The four `Gate` parameters in one call.
+#[no_mangle]
+pub extern "C" fn reverb_set_gate(
+    handle: Handle,
+    enabled: u32,
+    threshold_db: f32,
+    hold_ms: f32,
+    release_ms: f32,
+) {
+    guard(handle, |r: &mut Reverb| {
+        r.set_param(PARAM_GATE, enabled as f32);
+        r.set_param(PARAM_GATE_THRESHOLD_DB, threshold_db);
+        r.set_param(PARAM_GATE_HOLD_MS, hold_ms);
+        r.set_param(PARAM_GATE_RELEASE_MS, release_ms);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    /// A fully wet reverb with no early reflections or modulation, then `params`.
+    fn reverb(params: &[(u32, f32)]) -> Reverb {
+        let mut r = Reverb::new(RATE);
+        r.set_param(PARAM_MIX, 1.0);
+        r.set_param(PARAM_EARLY, 0.0);
+        r.set_param(PARAM_MODULATION, 0.0);
+        for &(index, value) in params {
+            r.set_param(index, value);
+        }
+        Node::reset(&mut r);
+        r
+    }
+
+    fn render(r: &mut Reverb, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(r, &input[span], y, 1))
+    }
+
+    fn impulse(frames: usize) -> Vec<f32> {
+        let mut x = vec![0.0; frames];
+        x[0] = 1.0;
+        x
+    }
+
+    /// RMS level of `y` over `from_s..to_s`, in dB.
+    fn level_db(y: &[f32], from_s: f32, to_s: f32) -> f32 {
+        let part = &y[(from_s * RATE) as usize..(to_s * RATE) as usize];
+        let power = part.iter().map(|v| v * v).sum::<f32>() / part.len() as f32;
+        10.0 * power.log10()
+    }
+
+    /// `Decay` is the RT60: the tail falls about 60 dB over that long.
+    #[test]
+    fn decay_sets_the_rt60() {
+        let mut r = reverb(&[(PARAM_DECAY_S, 1.0), (PARAM_DAMPING, 0.0)]);
+        let y = render(&mut r, &impulse(72_000));
+        let drop = level_db(&y, 1.2, 1.4) - level_db(&y, 0.2, 0.4);
+        assert!((-66.0..-54.0).contains(&drop), "{drop}");
+    }
+
+    /// `Freeze` holds the tail at its level instead of letting it decay, and keeps new input
+    /// out of it.
+    #[test]
+    fn freeze_holds_the_tail() {
+        let mut r = reverb(&[(PARAM_DECAY_S, 1.0), (PARAM_DAMPING, 0.0)]);
+        let mut y = render(&mut r, &impulse(24_000));
+        r.set_param(PARAM_FREEZE, 1.0);
+        y.extend(render(&mut r, &[1.0; 96_000]));
+        let drift = level_db(&y, 2.2, 2.4) - level_db(&y, 0.6, 0.8);
+        assert!(drift.abs() < 3.0, "{drift}");
+    }
+
+    /// Nothing comes out before the pre-delay plus the first early reflection, whose time
+    /// scales with `Size` (7 ms at 0.4x to 2x).
+    #[test]
+    fn predelay_and_size_place_the_first_reflection() {
+        let first = |params: &[(u32, f32)]| {
+            let mut r = reverb(params);
+            let y = render(&mut r, &impulse(9600));
+            y.iter().position(|v| v.abs() > 1e-6).unwrap()
+        };
+        assert_eq!(first(&[(PARAM_EARLY, 1.0), (PARAM_SIZE, 0.0)]), 134);
+        assert_eq!(first(&[(PARAM_EARLY, 1.0), (PARAM_SIZE, 1.0)]), 672);
+        let late = [
+            (PARAM_EARLY, 1.0),
+            (PARAM_SIZE, 1.0),
+            (PARAM_PREDELAY_MS, 100.0),
+        ];
+        assert_eq!(first(&late), 5472);
+    }
+
+    /// Damping darkens the tail: less of its energy is in the sample-to-sample difference.
+    #[test]
+    fn damping_darkens_the_tail() {
+        let brightness = |damping: f32| {
+            let mut r = reverb(&[(PARAM_DAMPING, damping)]);
+            let y = render(&mut r, &impulse(48_000));
+            let tail = &y[24_000..];
+            let diff = tail.windows(2).map(|w| (w[1] - w[0]).powi(2)).sum::<f32>();
+            diff / tail.iter().map(|v| v * v).sum::<f32>()
+        };
+        assert!(brightness(1.0) < 0.25 * brightness(0.0));
+    }
+
+    /// Shimmer feeds the tail back shifted up by its interval, an octave or a fifth, so a
+    /// 500 Hz tone's tail grows a 1 kHz or a 750 Hz component that the plain tail lacks.
+    #[test]
+    fn shimmer_pitches_the_tail_up() {
+        let tail = |params: &[(u32, f32)]| {
+            let mut r = reverb(&[&[(PARAM_DECAY_S, 5.0), (PARAM_DAMPING, 0.0)], params].concat());
+            let mut x: Vec<f32> = (0..9600)
+                .map(|n| (TAU * 500.0 * n as f32 / RATE).sin())
+                .collect();
+            x.resize(96_000, 0.0);
+            render(&mut r, &x).split_off(48_000)
+        };
+        let ratio = |y: &[f32], hz: f32| amplitude(y, hz) / amplitude(y, 500.0);
+        let plain = tail(&[]);
+        let octave = tail(&[(PARAM_SHIMMER, 1.0)]);
+        let fifth = tail(&[(PARAM_SHIMMER, 1.0), (PARAM_SHIMMER_INTERVAL, 1.0)]);
+        assert!(ratio(&plain, 1000.0) < 0.01);
+        assert!(ratio(&octave, 1000.0) > 0.2);
+        assert!(ratio(&plain, 750.0) < 0.01);
+        assert!(ratio(&fifth, 750.0) > 0.2);
+    }
+
+    /// The gate passes the tail while the dry input plays, then after `Gate hold` and
+    /// `Gate release` cuts it to silence, long before the 5 s decay would.
+    #[test]
+    fn gate_cuts_the_tail_after_the_input_stops() {
+        let run = |gate: f32| {
+            let mut r = reverb(&[
+                (PARAM_DECAY_S, 5.0),
+                (PARAM_GATE, gate),
+                (PARAM_GATE_HOLD_MS, 100.0),
+                (PARAM_GATE_RELEASE_MS, 20.0),
+            ]);
+            let mut x: Vec<f32> = (0..4800)
+                .map(|n| 0.5 * (TAU * 500.0 * n as f32 / RATE).sin())
+                .collect();
+            x.resize(48_000, 0.0);
+            render(&mut r, &x)
+        };
+        let (open, gated) = (run(0.0), run(1.0));
+        assert_eq!(open[..4800], gated[..4800]);
+        assert!(level_db(&open, 0.35, 1.0) > -40.0);
+        assert!(gated[(0.35 * RATE) as usize..].iter().all(|&v| v == 0.0));
+    }
+}
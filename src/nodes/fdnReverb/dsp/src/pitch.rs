@@ -0,0 +1,52 @@
+use core::f32::consts::PI;
+use dsp_core::delay::DelayLine;
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+/// Grain length of the pitch shifter.
+pub const WINDOW_MS: f32 = 60.0;
+
+/// Delay-line pitch shifter: two read taps sweep through a `WINDOW_MS` window at the shifted
+/// speed, half a window apart, each faded with a sine-squared envelope so the jump back is
+/// silent and the pair sums to constant gain. Cheap and smeary, which suits a reverb tail.
+pub struct PitchShifter {
+    line: DelayLine,
+    window: f32,
+    ratio: f32,
+    phase: f32,
+}
+
+impl PitchShifter {
+    pub fn new(sample_rate_hz: f32) -> Self {
+        let window = WINDOW_MS / 1000.0 * sample_rate_hz;
+        Self {
+            line: DelayLine::new(window as usize + 2),
+            window,
+            ratio: 2.0,
+            phase: 0.0,
+        }
+    }
+
+    pub fn set_semitones(&mut self, semitones: f32) {
+        self.ratio = (semitones / 12.0).exp2();
+    }
+
+    pub fn clear(&mut self) {
+        self.line.clear();
+        self.phase = 0.0;
+    }
+
+    #[inline]
+    pub fn process(&mut self, x: f32) -> f32 {
+        self.line.push(x);
+        self.phase = (self.phase + (self.ratio - 1.0) / self.window).rem_euclid(1.0);
+        let mut y = 0.0;
+        for offset in [0.0, 0.5] {
+            let p = (self.phase + offset).fract();
+            let gain = (PI * p).sin();
+            y += self.line.read_cubic((1.0 - p) * self.window) * gain * gain;
+        }
+        y
+    }
+}
@@ -0,0 +1,301 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::f32::consts::TAU;
+use dsp_core::delay::DelayLine;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing, FLAG_NO_AUTOMATION};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Flanger::PARAMS`, in the order of `flanger_set_params`' arguments.
+pub const PARAM_RATE_HZ: u32 = 0;
+pub const PARAM_DEPTH: u32 = 1;
+pub const PARAM_DELAY_MS: u32 = 2;
+pub const PARAM_FEEDBACK: u32 = 3;
+pub const PARAM_STEREO_PHASE_DEG: u32 = 4;
+pub const PARAM_MIX: u32 = 5;
+pub const PARAM_THROUGH_ZERO: u32 = 6;
+
+const MAX_DELAY_MS: f32 = 10.0;
+const MAX_FEEDBACK: f32 = 0.95;
+
+/// Short sine-swept delay mixed with the dry signal. Normally the tap sweeps from `delay_ms`
+/// down towards zero; in through-zero mode the dry path is delayed by `delay_ms` and the tap
+/// sweeps either side of it, so the two cross and the comb passes through zero delay.
+#[repr(C)]
+pub struct Flanger {
+    rate_hz: f32,
+    depth: f32,
+    delay_ms: f32,
+    /// Centre delay in samples, whole so the through-zero dry path is an exact latency.
+    delay: usize,
+    feedback: f32,
+    mix: f32,
+    stereo_phase: f32,
+    through_zero: bool,
+    /// LFO position in cycles (0..1), carried across process calls.
+    phase: f32,
+    lines: Vec<DelayLine>,
+    dry_lines: Vec<DelayLine>,
+    last_wet: [f32; MAX_CHANNELS],
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Flanger {
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let step = self.rate_hz / self.sample_rate_hz;
+        let center = self.delay as f32;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let offset = if ch % 2 == 1 { self.stereo_phase } else { 0.0 };
+                let lfo = (TAU * (self.phase + offset)).sin();
+                let line = &mut self.lines[ch];
+                line.push(*x + self.feedback * self.last_wet[ch]);
+                let (d, dry) = if self.through_zero {
+                    let dry_line = &mut self.dry_lines[ch];
+                    dry_line.push(*x);
+                    (
+                        center * (1.0 + self.depth * lfo),
+                        dry_line.read_linear(center),
+                    )
+                } else {
+                    (center * (1.0 - self.depth * (0.5 + 0.5 * lfo)), *x)
+                };
+                let wet = line.read_cubic(d);
+                self.last_wet[ch] = wet;
+                *y = dry * (1.0 - self.mix) + wet * self.mix;
+            }
+            self.phase += step;
+            if self.phase >= 1.0 {
+                self.phase -= 1.0;
+            }
+        }
+    }
+}
+
+impl Node for Flanger {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Delay` is the sweep's upper end, or its centre in through-zero mode; negative
+    /// `Feedback` hollows the sound out; `Stereo phase` offsets the odd channels' LFO.
+    /// `Through zero` enables the delayed dry path (see `latency_samples`).
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::log("Rate", "Hz", 0.01, 10.0, 0.25),
+        ParamDescriptor::linear("Depth", "", 0.0, 1.0, 0.8),
+        ParamDescriptor::log("Delay", "ms", 0.1, MAX_DELAY_MS, 2.0),
+        ParamDescriptor::linear("Feedback", "", -MAX_FEEDBACK, MAX_FEEDBACK, 0.5),
+        ParamDescriptor::linear("Stereo phase", "deg", 0.0, 180.0, 90.0),
+        ParamDescriptor::linear("Mix", "", 0.0, 1.0, 0.5),
+        ParamDescriptor::toggle("Through zero", false).with_flags(FLAG_NO_AUTOMATION),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let max_delay = (2.0 * MAX_DELAY_MS / 1000.0 * sample_rate_hz) as usize + 2;
+        Flanger {
+            rate_hz: 0.25,
+            depth: 0.8,
+            delay_ms: 2.0,
+            delay: (2.0 / 1000.0 * sample_rate_hz) as usize,
+            feedback: 0.5,
+            mix: 0.5,
+            stereo_phase: 0.25,
+            through_zero: false,
+            phase: 0.0,
+            lines: (0..MAX_CHANNELS)
+                .map(|_| DelayLine::new(max_delay))
+                .collect(),
+            dry_lines: (0..MAX_CHANNELS)
+                .map(|_| DelayLine::new(max_delay))
+                .collect(),
+            last_wet: [0.0; MAX_CHANNELS],
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_RATE_HZ => self.rate_hz = clamp(value, 0.01, 10.0),
+            PARAM_DEPTH => self.depth = clamp(value, 0.0, 1.0),
+            PARAM_DELAY_MS => {
+                self.delay_ms = clamp(value, 0.1, MAX_DELAY_MS);
+                self.delay = ((self.delay_ms / 1000.0 * self.sample_rate_hz) as usize).max(1);
+            }
+            PARAM_FEEDBACK => self.feedback = clamp(value, -MAX_FEEDBACK, MAX_FEEDBACK),
+            PARAM_STEREO_PHASE_DEG => self.stereo_phase = clamp(value, 0.0, 180.0) / 360.0,
+            PARAM_MIX => self.mix = clamp(value, 0.0, 1.0),
+            PARAM_THROUGH_ZERO => {
+                let through_zero = clamp(value, 0.0, 1.0) >= 0.5;
+                if through_zero && !self.through_zero {
+                    self.dry_lines.iter_mut().for_each(DelayLine::clear);
+                }
+                self.through_zero = through_zero;
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_RATE_HZ => self.rate_hz,
+            PARAM_DEPTH => self.depth,
+            PARAM_DELAY_MS => self.delay_ms,
+            PARAM_FEEDBACK => self.feedback,
+            PARAM_STEREO_PHASE_DEG => self.stereo_phase * 360.0,
+            PARAM_MIX => self.mix,
+            PARAM_THROUGH_ZERO => u32::from(self.through_zero) as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Delay of the dry path in through-zero mode (the whole output lags by it); 0 otherwise.
+    fn latency_samples(&self) -> u32 {
+        if self.through_zero {
+            self.delay as u32
+        } else {
+            0
+        }
+    }
+
+    /// Empties the delay lines and the feedback path and restarts the LFO.
+    fn reset(&mut self) {
+        self.phase = 0.0;
+        for line in self.lines.iter_mut().chain(&mut self.dry_lines) {
+            line.clear();
+        }
+        self.last_wet = [0.0; MAX_CHANNELS];
+    }
+}
+
+node_abi::export_node!(Flanger {
+    abi_version: flanger_abi_version,
+    new: flanger_new,
+    free: flanger_free,
+    param_count: flanger_param_count,
+    param_descriptors: flanger_param_descriptors,
+    set_param: flanger_set_param,
+    get_param: flanger_get_param,
+    param_events: flanger_param_events,
+    process: flanger_process,
+    process_offline: flanger_process_offline,
+    latency_samples: flanger_latency_samples,
+    io_layout: flanger_get_io_layout,
+    process_io: flanger_process_io,
+    reset: flanger_reset,
+    state_size: flanger_state_size,
+    save_state: flanger_save_state,
+    load_state: flanger_load_state,
+    preset_size: flanger_preset_size,
+    save_preset: flanger_save_preset,
+    load_preset: flanger_load_preset,
+    last_error_code: flanger_last_error_code,
+    last_error_message: flanger_last_error_message_ptr,
+});
+
+node_abi::test_params!(Flanger);
+
+dsp_core::test_block_sizes!(flanger_new, flanger_process(input, output), flanger_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Flanger as FlangerNode);
+
+/// Every parameter in one call, as `flanger_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn flanger_set_params(
+    handle: Handle,
+    rate_hz: f32,
+    depth: f32,
+    delay_ms: f32,
+    feedback: f32,
+    stereo_phase_deg: f32,
+    mix: f32,
+    through_zero: u32,
+) {
+    guard(handle, |f: &mut Flanger| {
+        f.set_param(PARAM_RATE_HZ, rate_hz);
+        f.set_param(PARAM_DEPTH, depth);
+        f.set_param(PARAM_DELAY_MS, delay_ms);
+        f.set_param(PARAM_FEEDBACK, feedback);
+        f.set_param(PARAM_STEREO_PHASE_DEG, stereo_phase_deg);
+        f.set_param(PARAM_MIX, mix);
+        f.set_param(PARAM_THROUGH_ZERO, through_zero as f32);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// An impulse through a flanger held still (no depth) at a 1 ms delay, mono in render
+    /// quanta.
+    fn impulse_response(params: &[(u32, f32)]) -> Vec<f32> {
+        let mut f = Flanger::new(RATE);
+        f.set_param(PARAM_DEPTH, 0.0);
+        f.set_param(PARAM_DELAY_MS, 1.0);
+        for &(index, value) in params {
+            f.set_param(index, value);
+        }
+        let mut input = vec![0.0; 256];
+        input[0] = 1.0;
+        render_quanta(input.len(), |span, y| {
+            Node::process(&mut f, &input[span], y, 1)
+        })
+    }
+
+    /// `Feedback` sends the tap round again, a sample later each lap (the tap is fed back
+    /// on the next sample), inverted when negative.
+    #[test]
+    fn feedback_repeats_the_tap() {
+        let taps = |y: Vec<f32>| [y[0], y[48], y[97], y[146]];
+        let wet = [(PARAM_MIX, 1.0), (PARAM_FEEDBACK, 0.5)];
+        assert_eq!(taps(impulse_response(&wet)), [0.0, 1.0, 0.5, 0.25]);
+        let wet = [(PARAM_MIX, 1.0), (PARAM_FEEDBACK, -0.5)];
+        assert_eq!(taps(impulse_response(&wet)), [0.0, 1.0, -0.5, 0.25]);
+    }
+
+    /// Normally the dry signal leads the tap; through zero it is delayed to the tap's centre,
+    /// so the two line up there, and the node reports that delay as latency.
+    #[test]
+    fn through_zero_delays_the_dry_path_to_the_centre() {
+        let taps = |y: Vec<f32>| [y[0], y[48], y.iter().map(|v| v.abs()).sum()];
+        let still = [(PARAM_FEEDBACK, 0.0)];
+        assert_eq!(taps(impulse_response(&still)), [0.5, 0.5, 1.0]);
+        let through_zero = [still[0], (PARAM_THROUGH_ZERO, 1.0)];
+        assert_eq!(taps(impulse_response(&through_zero)), [0.0, 1.0, 1.0]);
+
+        let mut f = Flanger::new(RATE);
+        f.set_param(PARAM_DELAY_MS, 1.0);
+        assert_eq!(Node::latency_samples(&f), 0);
+        f.set_param(PARAM_THROUGH_ZERO, 1.0);
+        assert_eq!(Node::latency_samples(&f), 48);
+    }
+}
@@ -0,0 +1,675 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::f32::consts::PI;
+use dsp_core::envelope::{Adsr, AdsrParams};
+use dsp_core::events::{EventRing, NoteEvent, ALL_NOTES_OFF, NOTE_OFF, NOTE_ON, PITCH_BEND};
+use dsp_core::gain::db_to_lin;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_VOICES: usize = 16;
+pub const OPERATORS: usize = 4;
+
+// Indices into `FmSynth::PARAMS`: operator `i`'s start at `i * OPERATOR_PARAMS`, in the order
+// of `fm_set_operator`'s and then `fm_set_envelope`'s arguments; the arguments of
+// `fm_set_params` follow the last operator.
+pub const OPERATOR_PARAMS: u32 = 7;
+pub const PARAM_RATIO: u32 = 0;
+pub const PARAM_OP_LEVEL: u32 = 1;
+pub const PARAM_FEEDBACK: u32 = 2;
+pub const PARAM_ATTACK_MS: u32 = 3;
+pub const PARAM_DECAY_MS: u32 = 4;
+pub const PARAM_SUSTAIN: u32 = 5;
+pub const PARAM_RELEASE_MS: u32 = 6;
+pub const PARAM_ALGORITHM: u32 = OPERATORS as u32 * OPERATOR_PARAMS;
+pub const PARAM_POLYPHONY: u32 = PARAM_ALGORITHM + 1;
+pub const PARAM_LEVEL_DB: u32 = PARAM_ALGORITHM + 2;
+
+/// Phase modulation, in radians, from a full-level modulator.
+const MOD_INDEX: f32 = 8.0;
+/// Self-modulation, in radians, at full feedback (about where it turns to noise).
+const FEEDBACK_INDEX: f32 = PI;
+
+/// Which operators modulate each operator (bit `j` of `mods[i]`: operator `j` into `i`), and
+/// which are heard. Modulators always have a higher index, so operators run from 4 down to 1.
+struct Algorithm {
+    mods: [u8; OPERATORS],
+    carriers: u8,
+}
+
+/// The eight four-operator algorithms of the classic 4-op synths (operators numbered from 1
+/// in the comments, from 0 in the masks).
+const ALGORITHMS: [Algorithm; 8] = [
+    // 4 -> 3 -> 2 -> 1
+    Algorithm {
+        mods: [0b0010, 0b0100, 0b1000, 0],
+        carriers: 0b0001,
+    },
+    // (3 + 4) -> 2 -> 1
+    Algorithm {
+        mods: [0b0010, 0b1100, 0, 0],
+        carriers: 0b0001,
+    },
+    // (2 + (4 -> 3)) -> 1
+    Algorithm {
+        mods: [0b0110, 0, 0b1000, 0],
+        carriers: 0b0001,
+    },
+    // ((4 -> 2) + 3) -> 1
+    Algorithm {
+        mods: [0b0110, 0b1000, 0, 0],
+        carriers: 0b0001,
+    },
+    // 2 -> 1, 4 -> 3
+    Algorithm {
+        mods: [0b0010, 0, 0b1000, 0],
+        carriers: 0b0101,
+    },
+    // 4 -> 1, 2 and 3
+    Algorithm {
+        mods: [0b1000, 0b1000, 0b1000, 0],
+        carriers: 0b0111,
+    },
+    // 4 -> 3, with 1 and 2 alone
+    Algorithm {
+        mods: [0, 0, 0b1000, 0],
+        carriers: 0b0111,
+    },
+    // All four as sines
+    Algorithm {
+        mods: [0, 0, 0, 0],
+        carriers: 0b1111,
+    },
+];
+
+#[derive(Clone, Copy)]
+struct OperatorParams {
+    ratio: f32,
+    level: f32,
+    feedback: f32,
+    attack_ms: f32,
+    decay_ms: f32,
+    sustain: f32,
+    release_ms: f32,
+    envelope: AdsrParams,
+}
+
+/// One operator of a voice: a sine with its own envelope and the last two outputs for
+/// feedback (averaged, which keeps high feedback from hunting between two values).
+#[derive(Clone, Copy, Default)]
+struct Operator {
+    phase: f32,
+    inc: f32,
+    env: Adsr,
+    prev: [f32; 2],
+}
+
+#[derive(Clone, Copy, Default)]
+struct Voice {
+    note: u8,
+    velocity: f32,
+    gate: bool,
+    /// Note-on order, for stealing the oldest voice.
+    age: u64,
+    ops: [Operator; OPERATORS],
+}
+
+/// Four-operator FM (phase modulation) synth. Every operator has a frequency ratio to the
+/// note, an output level (its modulation depth when it modulates, its loudness when it is a
+/// carrier), self-feedback and an ADSR; the algorithm wires them together. Notes come through
+/// the same event ring as the subtractive synth (`fm_events`) and are allocated the same way:
+/// the voice already playing the note, an idle voice, the quietest released voice, the oldest
+/// held one.
+pub struct FmSynth {
+    notes: EventRing,
+    voices: [Voice; MAX_VOICES],
+    polyphony: usize,
+    ops: [OperatorParams; OPERATORS],
+    algorithm: usize,
+    bend: f32,
+    level_db: f32,
+    gain: f32,
+    /// Note-ons so far, for the voices' `age`.
+    started: u64,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Voice {
+    /// Sounding while any carrier's envelope is; a modulator alone makes no sound.
+    fn is_active(&self, carriers: u8) -> bool {
+        (0..OPERATORS).any(|i| carriers & (1 << i) != 0 && !self.ops[i].env.is_idle())
+    }
+
+    fn release(&mut self) {
+        self.gate = false;
+        for op in &mut self.ops {
+            op.env.gate_off();
+        }
+    }
+}
+
+impl FmSynth {
+    fn carriers(&self) -> u8 {
+        ALGORITHMS[self.algorithm].carriers
+    }
+
+    fn note_on(&mut self, note: u8, velocity: f32) {
+        let carriers = self.carriers();
+        let pool = &self.voices[..self.polyphony];
+        let index = pool
+            .iter()
+            .position(|v| v.is_active(carriers) && v.note == note)
+            .or_else(|| pool.iter().position(|v| !v.is_active(carriers)))
+            .or_else(|| {
+                let level = |v: &Voice| v.ops.iter().map(|op| op.env.level()).sum::<f32>();
+                (0..pool.len())
+                    .filter(|&i| !pool[i].gate)
+                    .min_by(|&a, &b| level(&pool[a]).total_cmp(&level(&pool[b])))
+            })
+            .or_else(|| (0..pool.len()).min_by_key(|&i| pool[i].age))
+            .unwrap_or(0);
+        self.started += 1;
+        let v = &mut self.voices[index];
+        if !v.is_active(carriers) {
+            // Start a silent voice clean; a sounding one keeps its phases so it doesn't click.
+            for op in &mut v.ops {
+                op.phase = 0.0;
+                op.prev = [0.0; 2];
+            }
+        }
+        v.note = note;
+        v.velocity = velocity;
+        v.gate = true;
+        v.age = self.started;
+        for op in &mut v.ops {
+            op.env.gate_on();
+        }
+        self.update_pitch(index);
+    }
+
+    fn note_off(&mut self, note: u8) {
+        for v in &mut self.voices {
+            if v.gate && v.note == note {
+                v.release();
+            }
+        }
+    }
+
+    fn handle(&mut self, e: NoteEvent) {
+        let note = e.note.min(127) as u8;
+        match e.kind {
+            NOTE_ON if e.value > 0.0 => self.note_on(note, clamp(e.value, 0.0, 1.0)),
+            // Velocity 0 is a note-off, as in MIDI.
+            NOTE_ON | NOTE_OFF => self.note_off(note),
+            ALL_NOTES_OFF => self.voices.iter_mut().for_each(Voice::release),
+            PITCH_BEND => {
+                self.bend = clamp(e.value, -48.0, 48.0);
+                for index in 0..MAX_VOICES {
+                    self.update_pitch(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn update_pitch(&mut self, index: usize) {
+        let v = &mut self.voices[index];
+        let pitch = v.note as f32 + self.bend;
+        let base = 440.0 * ((pitch - 69.0) / 12.0).exp2() / self.sample_rate_hz;
+        for (op, params) in v.ops.iter_mut().zip(&self.ops) {
+            op.inc = (base * params.ratio).min(0.5);
+        }
+    }
+
+    fn update_envelope(&mut self, index: usize) {
+        let p = &mut self.ops[index];
+        p.envelope = AdsrParams::new(
+            p.attack_ms,
+            p.decay_ms,
+            p.sustain,
+            p.release_ms,
+            self.sample_rate_hz,
+        );
+    }
+
+    fn render(&mut self, output: &mut [f32], channels: usize) {
+        for out in output.chunks_exact_mut(channels) {
+            let algorithm = &ALGORITHMS[self.algorithm];
+            let mut y = 0.0;
+            for v in &mut self.voices {
+                if !v.is_active(algorithm.carriers) {
+                    continue;
+                }
+                let mut outs = [0.0; OPERATORS];
+                for k in (0..OPERATORS).rev() {
+                    let params = &self.ops[k];
+                    let modulation = (0..OPERATORS)
+                        .filter(|&j| algorithm.mods[k] & (1 << j) != 0)
+                        .map(|j| outs[j])
+                        .sum::<f32>()
+                        * MOD_INDEX;
+                    let op = &mut v.ops[k];
+                    let feedback = (op.prev[0] + op.prev[1]) * 0.5 * params.feedback;
+                    let theta = 2.0 * PI * op.phase + modulation + feedback * FEEDBACK_INDEX;
+                    let s = theta.sin();
+                    op.prev = [s, op.prev[0]];
+                    op.phase = (op.phase + op.inc).fract();
+                    outs[k] = s * op.env.process(&params.envelope) * params.level;
+                }
+                let carried = (0..OPERATORS)
+                    .filter(|&k| algorithm.carriers & (1 << k) != 0)
+                    .map(|k| outs[k])
+                    .sum::<f32>();
+                y += carried * v.velocity;
+            }
+            let count = algorithm.carriers.count_ones() as f32;
+            out.fill(y * self.gain / count.sqrt());
+        }
+    }
+}
+
+/// `OperatorParams`' fields for each `($n, ratio, level, decay_ms, sustain)`, in order, then
+/// the voice-wide parameters.
+macro_rules! operator_params {
+    ($(($n:literal, $ratio:literal, $level:literal, $decay:literal, $sustain:literal)),*) => {
+        &[
+            $(
+                ParamDescriptor::log(concat!("Op ", $n, " ratio"), "", 0.125, 32.0, $ratio),
+                ParamDescriptor::linear(concat!("Op ", $n, " level"), "", 0.0, 1.0, $level),
+                ParamDescriptor::linear(concat!("Op ", $n, " feedback"), "", 0.0, 1.0, 0.0),
+                ParamDescriptor::linear(concat!("Op ", $n, " attack"), "ms", 0.0, 10_000.0, 2.0),
+                ParamDescriptor::log(concat!("Op ", $n, " decay"), "ms", 1.0, 20_000.0, $decay),
+                ParamDescriptor::linear(concat!("Op ", $n, " sustain"), "", 0.0, 1.0, $sustain),
+                ParamDescriptor::log(concat!("Op ", $n, " release"), "ms", 1.0, 20_000.0, 400.0),
+            )*
+            ParamDescriptor::stepped("Algorithm", "", 0.0, 7.0, 4.0),
+            ParamDescriptor::stepped("Polyphony", "", 1.0, MAX_VOICES as f32, 8.0),
+            ParamDescriptor::linear("Level", "dB", -60.0, 0.0, -18.0),
+        ]
+    };
+}
+
+impl Node for FmSynth {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// Per operator: `Ratio` of the note frequency; `Level` (depth as a modulator, loudness
+    /// as a carrier); `Feedback` of self-modulation; its envelope, `Decay` and `Release` to
+    /// -60 dB. Then `Algorithm` (see `ALGORITHMS`), `Polyphony` and `Level`. The defaults are
+    /// a plain electric-piano-ish patch on algorithm 5: two modulator/carrier pairs.
+    const PARAMS: &'static [ParamDescriptor] = operator_params!(
+        (1, 1.0, 1.0, 2000.0, 0.4),
+        (2, 1.0, 0.25, 800.0, 0.1),
+        (3, 1.0, 0.5, 2000.0, 0.3),
+        (4, 14.0, 0.1, 200.0, 0.0)
+    );
+    const INPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let op = |ratio, level, decay_ms, sustain| OperatorParams {
+            ratio,
+            level,
+            feedback: 0.0,
+            attack_ms: 2.0,
+            decay_ms,
+            sustain,
+            release_ms: 400.0,
+            envelope: AdsrParams::new(2.0, decay_ms, sustain, 400.0, sample_rate_hz),
+        };
+        FmSynth {
+            notes: EventRing::new(),
+            voices: [Voice::default(); MAX_VOICES],
+            polyphony: 8,
+            ops: [
+                op(1.0, 1.0, 2000.0, 0.4),
+                op(1.0, 0.25, 800.0, 0.1),
+                op(1.0, 0.5, 2000.0, 0.3),
+                op(14.0, 0.1, 200.0, 0.0),
+            ],
+            algorithm: 4,
+            bend: 0.0,
+            level_db: -18.0,
+            gain: db_to_lin(-18.0),
+            started: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_ALGORITHM => {
+                self.algorithm = clamp(value, 0.0, (ALGORITHMS.len() - 1) as f32).round() as usize;
+            }
+            PARAM_POLYPHONY => {
+                self.polyphony = clamp(value, 1.0, MAX_VOICES as f32).round() as usize;
+            }
+            PARAM_LEVEL_DB => {
+                self.level_db = clamp(value, -60.0, 0.0);
+                self.gain = db_to_lin(self.level_db);
+            }
+            _ => {
+                let op = (index / OPERATOR_PARAMS) as usize;
+                let Some(p) = self.ops.get_mut(op) else {
+                    return;
+                };
+                match index % OPERATOR_PARAMS {
+                    PARAM_RATIO => p.ratio = clamp(value, 0.125, 32.0),
+                    PARAM_OP_LEVEL => p.level = clamp(value, 0.0, 1.0),
+                    PARAM_FEEDBACK => p.feedback = clamp(value, 0.0, 1.0),
+                    PARAM_ATTACK_MS => p.attack_ms = clamp(value, 0.0, 10_000.0),
+                    PARAM_DECAY_MS => p.decay_ms = clamp(value, 1.0, 20_000.0),
+                    PARAM_SUSTAIN => p.sustain = clamp(value, 0.0, 1.0),
+                    PARAM_RELEASE_MS => p.release_ms = clamp(value, 1.0, 20_000.0),
+                    _ => return,
+                }
+                self.update_envelope(op);
+                for index in 0..MAX_VOICES {
+                    self.update_pitch(index);
+                }
+            }
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_ALGORITHM => self.algorithm as f32,
+            PARAM_POLYPHONY => self.polyphony as f32,
+            PARAM_LEVEL_DB => self.level_db,
+            _ => {
+                let Some(p) = self.ops.get((index / OPERATOR_PARAMS) as usize) else {
+                    return 0.0;
+                };
+                match index % OPERATOR_PARAMS {
+                    PARAM_RATIO => p.ratio,
+                    PARAM_OP_LEVEL => p.level,
+                    PARAM_FEEDBACK => p.feedback,
+                    PARAM_ATTACK_MS => p.attack_ms,
+                    PARAM_DECAY_MS => p.decay_ms,
+                    PARAM_SUSTAIN => p.sustain,
+                    PARAM_RELEASE_MS => p.release_ms,
+                    _ => 0.0,
+                }
+            }
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn note_events(&mut self) -> Option<&mut EventRing> {
+        Some(&mut self.notes)
+    }
+
+    fn note(&mut self, event: NoteEvent) {
+        self.handle(event);
+    }
+
+    /// Renders the same mono mix on every channel.
+    fn process(&mut self, _input: &[f32], output: &mut [f32], channels: usize) {
+        self.render(output, channels);
+    }
+
+    /// Silences every voice at once (no release tails); queued events are kept.
+    fn reset(&mut self) {
+        self.voices = [Voice::default(); MAX_VOICES];
+        self.started = 0;
+    }
+}
+
+node_abi::export_node!(FmSynth {
+    abi_version: fm_abi_version,
+    new: fm_new,
+    free: fm_free,
+    param_count: fm_param_count,
+    param_descriptors: fm_param_descriptors,
+    set_param: fm_set_param,
+    get_param: fm_get_param,
+    param_events: fm_param_events,
+    process: fm_process,
+    process_offline: fm_process_offline,
+    latency_samples: fm_latency_samples,
+    io_layout: fm_get_io_layout,
+    process_io: fm_process_io,
+    reset: fm_reset,
+    state_size: fm_state_size,
+    save_state: fm_save_state,
+    load_state: fm_load_state,
+    preset_size: fm_preset_size,
+    save_preset: fm_save_preset,
+    load_preset: fm_load_preset,
+    last_error_code: fm_last_error_code,
+    last_error_message: fm_last_error_message_ptr,
+});
+
+node_abi::test_params!(FmSynth);
+
+dsp_core::test_block_sizes!(
+    fm_new,
+    fm_process(input, output),
+    fm_free,
+    setup: |p| {
+        let events = unsafe { &mut *fm_events(p) };
+        events.push(NoteEvent { kind: NOTE_ON, note: 60, value: 1.0, frame: 0 });
+    },
+);
+
+node_abi::test_io_block!(
+    FmSynth,
+    setup: |s: &mut FmSynth| {
+        s.notes.push(NoteEvent { kind: NOTE_ON, note: 60, value: 1.0, frame: 0 });
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(FmSynth as FmSynthNode);
+
+/// The note-event ring inside the synth, for the worklet to write into (layout in
+/// `dsp_core::events`). Stays put for the synth's lifetime.
+#[no_mangle]
+pub extern "C" fn fm_events(handle: Handle) -> *mut EventRing {
+    guard(handle, |s: &mut FmSynth| &mut s.notes as *mut EventRing).unwrap_or(core::ptr::null_mut())
+}
+
+/// Operator `index`'s `Ratio`, `Level` and `Feedback` in one call (0–3 for operators 1–4), as
+/// `fm_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn fm_set_operator(
+    handle: Handle,
+    index: u32,
+    ratio: f32,
+    level: f32,
+    feedback: f32,
+) {
+    if index as usize >= OPERATORS {
+        return;
+    }
+    guard(handle, |s: &mut FmSynth| {
+        let base = index * OPERATOR_PARAMS;
+        s.set_param(base + PARAM_RATIO, ratio);
+        s.set_param(base + PARAM_OP_LEVEL, level);
+        s.set_param(base + PARAM_FEEDBACK, feedback);
+    });
+}
+
+/// Operator `index`'s envelope in one call.
+#[no_mangle]
+pub extern "C" fn fm_set_envelope(
+    handle: Handle,
+    index: u32,
+    attack_ms: f32,
+    decay_ms: f32,
+    sustain: f32,
+    release_ms: f32,
+) {
+    if index as usize >= OPERATORS {
+        return;
+    }
+    guard(handle, |s: &mut FmSynth| {
+        let base = index * OPERATOR_PARAMS;
+        s.set_param(base + PARAM_ATTACK_MS, attack_ms);
+        s.set_param(base + PARAM_DECAY_MS, decay_ms);
+        s.set_param(base + PARAM_SUSTAIN, sustain);
+        s.set_param(base + PARAM_RELEASE_MS, release_ms);
+    });
+}
+
+/// `Algorithm`, `Polyphony` and `Level` in one call.
+#[no_mangle]
+pub extern "C" fn fm_set_params(handle: Handle, algorithm: u32, polyphony: u32, level_db: f32) {
+    guard(handle, |s: &mut FmSynth| {
+        s.set_param(PARAM_ALGORITHM, algorithm as f32);
+        s.set_param(PARAM_POLYPHONY, polyphony as f32);
+        s.set_param(PARAM_LEVEL_DB, level_db);
+    });
+}
+
+/// Voices currently sounding (held or releasing), for the UI.
+#[no_mangle]
+pub extern "C" fn fm_active_voices(handle: Handle) -> u32 {
+    guard(handle, |s: &mut FmSynth| {
+        let carriers = s.carriers();
+        s.voices.iter().filter(|v| v.is_active(carriers)).count() as u32
+    })
+    .unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    /// An FM synth at 0 dB whose operators all hold at full level once struck, with `params`
+    /// on top.
+    fn fm(params: &[(u32, f32)]) -> Handle {
+        let h = fm_new(RATE);
+        fm_set_params(h, 0, 8, 0.0);
+        for op in 0..OPERATORS as u32 {
+            fm_set_envelope(h, op, 0.0, 1.0, 1.0, 10.0);
+        }
+        for &(index, value) in params {
+            fm_set_param(h, index, value);
+        }
+        h
+    }
+
+    /// Operator `op`'s parameter `param` (0-based operators).
+    fn op(op: u32, param: u32) -> u32 {
+        op * OPERATOR_PARAMS + param
+    }
+
+    fn send(h: Handle, kind: u32, note: u32) {
+        let events = unsafe { &mut *fm_events(h) };
+        assert!(events.push(NoteEvent {
+            kind,
+            note,
+            value: 1.0,
+            frame: 0,
+        }));
+    }
+
+    fn render(h: Handle, frames: usize) -> Vec<f32> {
+        render_quanta(frames, |_, y| {
+            fm_process(h, core::ptr::null(), y.as_mut_ptr(), y.len(), 1)
+        })
+    }
+
+    /// 1.5 s of A4 (440 Hz).
+    fn play(h: Handle) -> Vec<f32> {
+        send(h, NOTE_ON, 69);
+        let y = render(h, 72_000);
+        fm_free(h);
+        y
+    }
+
+    /// With every operator a carrier, each sounds a sine at its ratio of the note, at its
+    /// level.
+    #[test]
+    fn carriers_play_their_ratios() {
+        let y = play(fm(&[
+            (PARAM_ALGORITHM, 7.0),
+            (op(1, PARAM_RATIO), 2.0),
+            (op(1, PARAM_OP_LEVEL), 0.5),
+            (op(2, PARAM_OP_LEVEL), 0.0),
+            (op(3, PARAM_OP_LEVEL), 0.0),
+        ]));
+        // Four carriers share the output at 1/sqrt(4).
+        assert!((amplitude(&y, 440.0) - 0.5).abs() < 0.01);
+        assert!((amplitude(&y, 880.0) - 0.25).abs() < 0.01);
+        assert!(amplitude(&y, 1320.0) < 1e-3);
+    }
+
+    /// The same operator 2 is heard as a sine when it is a carrier and as sidebands on
+    /// operator 1 when the algorithm routes it in as a modulator.
+    #[test]
+    fn algorithm_routes_modulators() {
+        let patch = |algorithm: f32| {
+            play(fm(&[
+                (PARAM_ALGORITHM, algorithm),
+                (op(1, PARAM_RATIO), 3.0),
+                (op(1, PARAM_OP_LEVEL), 0.1),
+                (op(2, PARAM_OP_LEVEL), 0.0),
+                (op(3, PARAM_OP_LEVEL), 0.0),
+            ]))
+        };
+        // 2 -> 1: sidebands at 440 ± 1320 Hz, J1(0.8) / J0(0.8) = 0.44 of the carrier.
+        let fm = patch(0.0);
+        let (carrier, sideband) = (amplitude(&fm, 440.0), amplitude(&fm, 1760.0));
+        assert!(
+            (sideband / carrier - 0.44).abs() < 0.05,
+            "{sideband} {carrier}"
+        );
+        assert!(amplitude(&fm, 880.0) > 0.1);
+        // All sines: just the two partials.
+        let sines = patch(7.0);
+        assert!(amplitude(&sines, 1760.0) < 1e-3 && amplitude(&sines, 880.0) < 1e-3);
+        assert!(amplitude(&sines, 1320.0) > 0.04);
+    }
+
+    /// Feedback turns a lone sine into a saw-like series that grows with the amount.
+    #[test]
+    fn feedback_adds_harmonics() {
+        let second = |feedback: f32| {
+            let y = play(fm(&[
+                (op(0, PARAM_FEEDBACK), feedback),
+                (op(1, PARAM_OP_LEVEL), 0.0),
+            ]));
+            amplitude(&y, 880.0) / amplitude(&y, 440.0)
+        };
+        assert!(second(0.0) < 1e-3);
+        let (low, high) = (second(0.2), second(0.6));
+        assert!(low > 0.05 && high > 1.5 * low, "{low} {high}");
+    }
+
+    /// Note-off runs the release to silence and frees the voice.
+    #[test]
+    fn note_off_releases() {
+        let h = fm(&[(op(1, PARAM_OP_LEVEL), 0.0)]);
+        send(h, NOTE_ON, 69);
+        send(h, NOTE_ON, 72);
+        render(h, 4_800);
+        assert_eq!(fm_active_voices(h), 2);
+        send(h, NOTE_OFF, 69);
+        render(h, 4_800);
+        assert_eq!(fm_active_voices(h), 1);
+        send(h, ALL_NOTES_OFF, 0);
+        render(h, 4_800);
+        assert_eq!(fm_active_voices(h), 0);
+        assert!(render(h, 128).iter().all(|&y| y == 0.0));
+        fm_free(h);
+    }
+}
@@ -0,0 +1,331 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::biquad::{Biquad, Response};
+use dsp_core::envelope::one_pole_coeff;
+use dsp_core::gain::db_to_lin;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_FORMANTS: usize = 5;
+
+// Indices into `Formant::PARAMS`, in the order of `formant_set_params`' arguments.
+pub const PARAM_VOWEL: u32 = 0;
+pub const PARAM_FORMANTS: u32 = 1;
+pub const PARAM_MORPH_MS: u32 = 2;
+pub const PARAM_OUTPUT_DB: u32 = 3;
+
+/// Filters are redesigned every this many frames while the vowel is still gliding.
+const CONTROL_INTERVAL: usize = 16;
+
+/// `(freq_hz, bandwidth_hz, gain_db)` per formant, tenor voice, for A E I O U.
+const VOWELS: [[(f32, f32, f32); MAX_FORMANTS]; 5] = [
+    [
+        (650.0, 80.0, 0.0),
+        (1080.0, 90.0, -6.0),
+        (2650.0, 120.0, -7.0),
+        (2900.0, 130.0, -8.0),
+        (3250.0, 140.0, -22.0),
+    ],
+    [
+        (400.0, 70.0, 0.0),
+        (1700.0, 80.0, -14.0),
+        (2600.0, 100.0, -12.0),
+        (3200.0, 120.0, -14.0),
+        (3580.0, 120.0, -20.0),
+    ],
+    [
+        (290.0, 40.0, 0.0),
+        (1870.0, 90.0, -15.0),
+        (2800.0, 100.0, -18.0),
+        (3250.0, 120.0, -20.0),
+        (3540.0, 120.0, -30.0),
+    ],
+    [
+        (400.0, 40.0, 0.0),
+        (800.0, 80.0, -10.0),
+        (2600.0, 100.0, -12.0),
+        (2800.0, 120.0, -12.0),
+        (3000.0, 120.0, -26.0),
+    ],
+    [
+        (350.0, 40.0, 0.0),
+        (600.0, 60.0, -20.0),
+        (2700.0, 100.0, -17.0),
+        (2900.0, 120.0, -14.0),
+        (3300.0, 120.0, -26.0),
+    ],
+];
+
+/// Parallel band-passes tuned to vowel formants. `vowel` runs continuously 0–4 (A E I O U);
+/// in-between positions interpolate frequency (in octaves), bandwidth and level between the
+/// neighbouring presets.
+#[repr(C)]
+pub struct Formant {
+    target_vowel: f32,
+    vowel: f32,
+    /// Vowel position the filters were last designed for.
+    designed_vowel: f32,
+    morph_ms: f32,
+    morph_coeff: f32,
+    formant_count: usize,
+    gains: [f32; MAX_FORMANTS],
+    output_db: f32,
+    output_gain: f32,
+    filters: [[Biquad; MAX_FORMANTS]; MAX_CHANNELS],
+    countdown: usize,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Formant {
+    fn design(&mut self) {
+        let lo = (self.vowel.floor() as usize).min(VOWELS.len() - 2);
+        let t = self.vowel - lo as f32;
+        let nyquist = self.sample_rate_hz * 0.49;
+        for k in 0..self.formant_count {
+            let (f0, bw0, g0) = VOWELS[lo][k];
+            let (f1, bw1, g1) = VOWELS[lo + 1][k];
+            let freq = (f0 * (f1 / f0).powf(t)).min(nyquist);
+            let bw = bw0 + (bw1 - bw0) * t;
+            self.gains[k] = db_to_lin(g0 + (g1 - g0) * t);
+            for ch in &mut self.filters {
+                ch[k].set(Response::Bandpass, freq, freq / bw, self.sample_rate_hz);
+            }
+        }
+        self.designed_vowel = self.vowel;
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let count = self.formant_count;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            self.vowel = self.target_vowel + (self.vowel - self.target_vowel) * self.morph_coeff;
+            if self.countdown == 0 {
+                self.countdown = CONTROL_INTERVAL;
+                if (self.vowel - self.designed_vowel).abs() > 1e-4 {
+                    self.design();
+                }
+            }
+            self.countdown -= 1;
+
+            for ((x, y), filters) in inp.iter().zip(out.iter_mut()).zip(&mut self.filters) {
+                let sum: f32 = filters[..count]
+                    .iter_mut()
+                    .zip(&self.gains)
+                    .map(|(f, g)| f.process(*x) * g)
+                    .sum();
+                *y = sum * self.output_gain;
+            }
+        }
+    }
+}
+
+impl Node for Formant {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Vowel` 0–4 is A E I O U (fractional values blend neighbours); `Formants` is how many
+    /// band-passes run; `Morph` is the glide time toward a new vowel (0 jumps).
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Vowel", "", 0.0, (VOWELS.len() - 1) as f32, 0.0),
+        ParamDescriptor::stepped(
+            "Formants",
+            "",
+            3.0,
+            MAX_FORMANTS as f32,
+            MAX_FORMANTS as f32,
+        ),
+        ParamDescriptor::linear("Morph", "ms", 0.0, 2000.0, 50.0),
+        ParamDescriptor::linear("Output", "dB", -24.0, 24.0, 0.0),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut f = Formant {
+            target_vowel: 0.0,
+            vowel: 0.0,
+            designed_vowel: 0.0,
+            morph_ms: 50.0,
+            morph_coeff: one_pole_coeff(50.0, sample_rate_hz),
+            formant_count: MAX_FORMANTS,
+            gains: [0.0; MAX_FORMANTS],
+            output_db: 0.0,
+            output_gain: 1.0,
+            filters: [[Biquad::default(); MAX_FORMANTS]; MAX_CHANNELS],
+            countdown: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        f.design();
+        f
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_VOWEL => self.target_vowel = clamp(value, 0.0, (VOWELS.len() - 1) as f32),
+            PARAM_FORMANTS => {
+                let count = clamp(value, 3.0, MAX_FORMANTS as f32).round() as usize;
+                if count != self.formant_count {
+                    for ch in &mut self.filters {
+                        ch[self.formant_count.min(count)..]
+                            .iter_mut()
+                            .for_each(Biquad::clear);
+                    }
+                    self.formant_count = count;
+                    self.design();
+                }
+            }
+            PARAM_MORPH_MS => {
+                self.morph_ms = clamp(value, 0.0, 2000.0);
+                self.morph_coeff = one_pole_coeff(self.morph_ms, self.sample_rate_hz);
+            }
+            PARAM_OUTPUT_DB => {
+                self.output_db = clamp(value, -24.0, 24.0);
+                self.output_gain = db_to_lin(self.output_db);
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_VOWEL => self.target_vowel,
+            PARAM_FORMANTS => self.formant_count as f32,
+            PARAM_MORPH_MS => self.morph_ms,
+            PARAM_OUTPUT_DB => self.output_db,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Finishes any vowel morph and clears the formant filters.
+    fn reset(&mut self) {
+        self.vowel = self.target_vowel;
+        self.design();
+        for filter in self.filters.iter_mut().flatten() {
+            filter.clear();
+        }
+        self.countdown = 0;
+    }
+}
+
+node_abi::export_node!(Formant {
+    abi_version: formant_abi_version,
+    new: formant_new,
+    free: formant_free,
+    param_count: formant_param_count,
+    param_descriptors: formant_param_descriptors,
+    set_param: formant_set_param,
+    get_param: formant_get_param,
+    param_events: formant_param_events,
+    process: formant_process,
+    process_offline: formant_process_offline,
+    latency_samples: formant_latency_samples,
+    io_layout: formant_get_io_layout,
+    process_io: formant_process_io,
+    reset: formant_reset,
+    state_size: formant_state_size,
+    save_state: formant_save_state,
+    load_state: formant_load_state,
+    preset_size: formant_preset_size,
+    save_preset: formant_save_preset,
+    load_preset: formant_load_preset,
+    last_error_code: formant_last_error_code,
+    last_error_message: formant_last_error_message_ptr,
+});
+
+node_abi::test_params!(Formant);
+
+dsp_core::test_block_sizes!(formant_new, formant_process(input, output), formant_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Formant as FormantNode);
+
+/// Every parameter in one call, as `formant_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn formant_set_params(
+    handle: Handle,
+    vowel: f32,
+    formants: u32,
+    morph_ms: f32,
+    output_db: f32,
+) {
+    guard(handle, |f: &mut Formant| {
+        f.set_param(PARAM_VOWEL, vowel);
+        f.set_param(PARAM_FORMANTS, formants as f32);
+        f.set_param(PARAM_MORPH_MS, morph_ms);
+        f.set_param(PARAM_OUTPUT_DB, output_db);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    fn render(f: &mut Formant, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(f, &input[span], y, 1))
+    }
+
+    /// Settled gain in dB on a `hz` sine with `Vowel` at `vowel`.
+    fn gain_db_at(vowel: f32, hz: f32) -> f32 {
+        let mut f = Formant::new(RATE);
+        f.set_param(PARAM_VOWEL, vowel);
+        Node::reset(&mut f);
+        let w = core::f32::consts::TAU * hz / RATE;
+        let input: Vec<f32> = (0..24_000).map(|i| (w * i as f32).sin()).collect();
+        let output = render(&mut f, &input);
+        let peak = output[12_000..].iter().fold(0.0_f32, |m, y| m.max(y.abs()));
+        20.0 * peak.log10()
+    }
+
+    /// Each vowel passes its first formant and not the other's: A's at 650 Hz, I's at 290 Hz.
+    #[test]
+    fn vowels_put_the_peaks_at_their_formants() {
+        let (a_at_650, a_at_290) = (gain_db_at(0.0, 650.0), gain_db_at(0.0, 290.0));
+        let (i_at_650, i_at_290) = (gain_db_at(2.0, 650.0), gain_db_at(2.0, 290.0));
+        assert!(
+            a_at_650.abs() < 1.0 && i_at_290.abs() < 1.0,
+            "{a_at_650} {i_at_290}"
+        );
+        assert!(i_at_650 < a_at_650 - 12.0, "{i_at_650}");
+        assert!(a_at_290 < i_at_290 - 12.0, "{a_at_290}");
+    }
+
+    /// A new vowel is reached 63% of the way after `Morph`, or at once with `Morph` at 0.
+    #[test]
+    fn morph_glides_between_vowels() {
+        let silence = vec![0.0; 4_800];
+        let mut f = Formant::new(RATE);
+        f.set_param(PARAM_MORPH_MS, 100.0);
+        f.set_param(PARAM_VOWEL, 2.0);
+        render(&mut f, &silence);
+        assert!((f.vowel - 2.0 * 0.632).abs() < 0.01, "{}", f.vowel);
+
+        f.set_param(PARAM_MORPH_MS, 0.0);
+        f.set_param(PARAM_VOWEL, 4.0);
+        render(&mut f, &silence[..1]);
+        assert_eq!(f.vowel, 4.0);
+    }
+}
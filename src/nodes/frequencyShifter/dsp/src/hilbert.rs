@@ -0,0 +1,50 @@
+use dsp_core::denormal::flush;
+
+/// Allpass coefficients of the two paths (Olli Niemitalo's design); each path's phase tracks
+/// the other's to within a degree of 90 over roughly 15 Hz to 0.47 fs.
+const PATH_I: [f32; 4] = [0.692_387_8, 0.936_065_4, 0.988_229_5, 0.998_748_8];
+const PATH_Q: [f32; 4] = [0.402_192_1, 0.856_171_1, 0.972_291, 0.995_288_5];
+
+/// One path: four second-order allpasses in `z^-2`, `y[n] = a^2 (x[n] + y[n-2]) - x[n-2]`.
+#[derive(Clone, Copy, Default)]
+struct AllpassChain {
+    x1: [f32; 4],
+    x2: [f32; 4],
+    y1: [f32; 4],
+    y2: [f32; 4],
+}
+
+impl AllpassChain {
+    #[inline]
+    fn process(&mut self, coeffs: &[f32; 4], x: f32) -> f32 {
+        let mut v = x;
+        for (i, a) in coeffs.iter().enumerate() {
+            let y = flush(a * a * (v + self.y2[i]) - self.x2[i]);
+            self.x2[i] = self.x1[i];
+            self.x1[i] = v;
+            self.y2[i] = self.y1[i];
+            self.y1[i] = y;
+            v = y;
+        }
+        v
+    }
+}
+
+/// IIR Hilbert pair: turns a real signal into an (approximately) analytic one, `(i, q)` with
+/// `q` a quarter cycle ahead of `i`. Both outputs share the same group delay.
+#[derive(Clone, Copy, Default)]
+pub struct Hilbert {
+    i: AllpassChain,
+    q: AllpassChain,
+    i_delay: f32,
+}
+
+impl Hilbert {
+    #[inline]
+    pub fn process(&mut self, x: f32) -> (f32, f32) {
+        let i = self.i_delay;
+        self.i_delay = self.i.process(&PATH_I, x);
+        let q = self.q.process(&PATH_Q, x);
+        (i, q)
+    }
+}
@@ -0,0 +1,286 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod hilbert;
+
+use alloc::vec::Vec;
+use core::f32::consts::TAU;
+use dsp_core::delay::DelayLine;
+use dsp_core::param::clamp;
+use hilbert::Hilbert;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `FreqShifter::PARAMS`, in the order of `freqshift_set_params`' arguments.
+pub const PARAM_SHIFT_HZ: u32 = 0;
+pub const PARAM_STEREO_OFFSET_HZ: u32 = 1;
+pub const PARAM_FEEDBACK: u32 = 2;
+pub const PARAM_FEEDBACK_MS: u32 = 3;
+pub const PARAM_MIX: u32 = 4;
+
+const MAX_SHIFT_HZ: f32 = 5000.0;
+const MAX_FEEDBACK_MS: f32 = 1000.0;
+
+/// Single-sideband frequency shifter: every component moves by the same number of Hz, so
+/// harmonics stop being harmonic. The input is split into quadrature by a Hilbert pair and
+/// mixed with a quadrature oscillator; the sign of the shift picks the sideband. Feeding the
+/// output back through a delay shifts each echo further (the barberpole effect).
+#[repr(C)]
+pub struct FreqShifter {
+    shift_hz: f32,
+    stereo_offset_hz: f32,
+    feedback: f32,
+    feedback_ms: f32,
+    /// Feedback loop length in samples, at least 1.
+    feedback_delay: f32,
+    mix: f32,
+    hilbert: [Hilbert; MAX_CHANNELS],
+    /// Oscillator positions in cycles.
+    phase: [f32; MAX_CHANNELS],
+    loops: Vec<DelayLine>,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl FreqShifter {
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let mut step = [0.0_f32; MAX_CHANNELS];
+        for (ch, s) in step.iter_mut().enumerate().take(channels) {
+            let offset = if ch % 2 == 1 {
+                self.stereo_offset_hz
+            } else {
+                0.0
+            };
+            *s = (self.shift_hz + offset) / self.sample_rate_hz;
+        }
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let line = &mut self.loops[ch];
+                let fed = *x + line.read_linear(self.feedback_delay - 1.0) * self.feedback;
+                let (i, q) = self.hilbert[ch].process(fed);
+                let (sin, cos) = (TAU * self.phase[ch]).sin_cos();
+                self.phase[ch] = (self.phase[ch] + step[ch]).rem_euclid(1.0);
+                let shifted = i * cos + q * sin;
+                line.push(shifted);
+                *y = *x + (shifted - *x) * self.mix;
+            }
+        }
+    }
+}
+
+impl Node for FreqShifter {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// Negative `Shift` shifts down; odd channels shift by `Stereo offset` more. `Feedback`
+    /// returns the output through a `Feedback delay`, so each pass is shifted again; `Mix` runs
+    /// from dry to fully shifted.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Shift", "Hz", -MAX_SHIFT_HZ, MAX_SHIFT_HZ, 100.0),
+        ParamDescriptor::linear("Stereo offset", "Hz", -500.0, 500.0, 0.0),
+        ParamDescriptor::linear("Feedback", "", 0.0, 0.95, 0.0),
+        ParamDescriptor::linear("Feedback delay", "ms", 0.0, MAX_FEEDBACK_MS, 0.0),
+        ParamDescriptor::linear("Mix", "", 0.0, 1.0, 1.0),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let max_loop = (MAX_FEEDBACK_MS / 1000.0 * sample_rate_hz).ceil() as usize;
+        FreqShifter {
+            shift_hz: 100.0,
+            stereo_offset_hz: 0.0,
+            feedback: 0.0,
+            feedback_ms: 0.0,
+            feedback_delay: 1.0,
+            mix: 1.0,
+            hilbert: [Hilbert::default(); MAX_CHANNELS],
+            phase: [0.0; MAX_CHANNELS],
+            loops: (0..MAX_CHANNELS)
+                .map(|_| DelayLine::new(max_loop))
+                .collect(),
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_SHIFT_HZ => self.shift_hz = clamp(value, -MAX_SHIFT_HZ, MAX_SHIFT_HZ),
+            PARAM_STEREO_OFFSET_HZ => self.stereo_offset_hz = clamp(value, -500.0, 500.0),
+            PARAM_FEEDBACK => self.feedback = clamp(value, 0.0, 0.95),
+            PARAM_FEEDBACK_MS => {
+                self.feedback_ms = clamp(value, 0.0, MAX_FEEDBACK_MS);
+                self.feedback_delay = (self.feedback_ms / 1000.0 * self.sample_rate_hz).max(1.0);
+            }
+            PARAM_MIX => self.mix = clamp(value, 0.0, 1.0),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_SHIFT_HZ => self.shift_hz,
+            PARAM_STEREO_OFFSET_HZ => self.stereo_offset_hz,
+            PARAM_FEEDBACK => self.feedback,
+            PARAM_FEEDBACK_MS => self.feedback_ms,
+            PARAM_MIX => self.mix,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Clears the Hilbert filters and feedback loops and restarts the oscillators.
+    fn reset(&mut self) {
+        self.hilbert = [Hilbert::default(); MAX_CHANNELS];
+        self.phase = [0.0; MAX_CHANNELS];
+        for line in &mut self.loops {
+            line.clear();
+        }
+    }
+}
+
+node_abi::export_node!(FreqShifter {
+    abi_version: freqshift_abi_version,
+    new: freqshift_new,
+    free: freqshift_free,
+    param_count: freqshift_param_count,
+    param_descriptors: freqshift_param_descriptors,
+    set_param: freqshift_set_param,
+    get_param: freqshift_get_param,
+    param_events: freqshift_param_events,
+    process: freqshift_process,
+    process_offline: freqshift_process_offline,
+    latency_samples: freqshift_latency_samples,
+    io_layout: freqshift_get_io_layout,
+    process_io: freqshift_process_io,
+    reset: freqshift_reset,
+    state_size: freqshift_state_size,
+    save_state: freqshift_save_state,
+    load_state: freqshift_load_state,
+    preset_size: freqshift_preset_size,
+    save_preset: freqshift_save_preset,
+    load_preset: freqshift_load_preset,
+    last_error_code: freqshift_last_error_code,
+    last_error_message: freqshift_last_error_message_ptr,
+});
+
+node_abi::test_params!(FreqShifter);
+
+dsp_core::test_block_sizes!(
+    freqshift_new,
+    freqshift_process(input, output),
+    freqshift_free
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(FreqShifter as FreqShifterNode);
+
+/// Every parameter in one call, as `freqshift_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn freqshift_set_params(
+    handle: Handle,
+    shift_hz: f32,
+    stereo_offset_hz: f32,
+    feedback: f32,
+    feedback_ms: f32,
+    mix: f32,
+) {
+    guard(handle, |f: &mut FreqShifter| {
+        f.set_param(PARAM_SHIFT_HZ, shift_hz);
+        f.set_param(PARAM_STEREO_OFFSET_HZ, stereo_offset_hz);
+        f.set_param(PARAM_FEEDBACK, feedback);
+        f.set_param(PARAM_FEEDBACK_MS, feedback_ms);
+        f.set_param(PARAM_MIX, mix);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::amplitude;
+
+    const RATE: f32 = 48_000.0;
+
+    fn shifter(params: &[(u32, f32)]) -> FreqShifter {
+        let mut f = FreqShifter::new(RATE);
+        for &(index, value) in params {
+            f.set_param(index, value);
+        }
+        f
+    }
+
+    /// 2 s of a stereo 1 kHz sine in render quanta; returns the left and right outputs.
+    fn render(f: &mut FreqShifter) -> (Vec<f32>, Vec<f32>) {
+        let w = TAU * 1000.0 / RATE;
+        let input: Vec<f32> = (0..96_000)
+            .flat_map(|i| [(w * i as f32).sin(); 2])
+            .collect();
+        let mut output = vec![0.0; input.len()];
+        for (x, y) in input.chunks(256).zip(output.chunks_mut(256)) {
+            Node::process(f, x, y, 2);
+        }
+        let side = |ch: usize| output.iter().skip(ch).step_by(2).copied().collect();
+        (side(0), side(1))
+    }
+
+    /// The tone moves by `Shift` Hz, up or down, with the other sideband and the original
+    /// suppressed; odd channels move by `Stereo offset` more.
+    #[test]
+    fn shift_moves_a_single_sideband() {
+        let (left, right) = render(&mut shifter(&[
+            (PARAM_SHIFT_HZ, 100.0),
+            (PARAM_STEREO_OFFSET_HZ, -300.0),
+        ]));
+        for (y, wanted, unwanted) in [(&left, 1100.0, 900.0), (&right, 800.0, 1200.0)] {
+            let level = amplitude(y, wanted);
+            assert!((level - 1.0).abs() < 0.02, "{wanted}: {level}");
+            assert!(amplitude(y, unwanted) < 0.02 && amplitude(y, 1000.0) < 0.01);
+        }
+    }
+
+    /// Feedback sends the shifted output round again, so the echoes climb by the shift each
+    /// pass.
+    #[test]
+    fn feedback_shifts_each_echo_further() {
+        let (y, _) = render(&mut shifter(&[
+            (PARAM_SHIFT_HZ, 100.0),
+            (PARAM_FEEDBACK, 0.5),
+            (PARAM_FEEDBACK_MS, 50.0),
+        ]));
+        for (hz, level) in [(1100.0, 1.0), (1200.0, 0.5), (1300.0, 0.25)] {
+            let got = amplitude(&y, hz);
+            assert!((got - level).abs() < 0.05 * level, "{hz}: {got}");
+        }
+    }
+
+    /// `Mix` 0 is the dry signal.
+    #[test]
+    fn mix_zero_is_dry() {
+        let (y, _) = render(&mut shifter(&[(PARAM_MIX, 0.0)]));
+        let w = TAU * 1000.0 / RATE;
+        for (i, v) in y.iter().enumerate() {
+            assert_eq!(*v, (w * i as f32).sin());
+        }
+    }
+}
@@ -0,0 +1,398 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::denormal::flush;
+use dsp_core::envelope::one_pole_coeff;
+use dsp_core::gain::db_to_lin;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Gate::PARAMS`, in the order of `gate_set_params`' arguments.
+pub const PARAM_THRESHOLD_DB: u32 = 0;
+pub const PARAM_RANGE_DB: u32 = 1;
+pub const PARAM_ATTACK_MS: u32 = 2;
+pub const PARAM_HOLD_MS: u32 = 3;
+pub const PARAM_RELEASE_MS: u32 = 4;
+pub const PARAM_HYSTERESIS_DB: u32 = 5;
+
+/// Decay of the peak detector, long enough to ride over the gaps between cycles of low notes.
+const DETECTOR_RELEASE_MS: f32 = 20.0;
+
+/// Gate/expander with one shared open/closed state for all channels (the loudest channel opens
+/// it). When closed the signal is attenuated by `range` rather than muted outright. With a
+/// side-chain connected the key opens it instead of the program.
+#[repr(C)]
+pub struct Gate {
+    threshold_db: f32,
+    hysteresis_db: f32,
+    open_level: f32,
+    close_level: f32,
+    range_db: f32,
+    range: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    detector_coeff: f32,
+    hold_samples: u32,
+    attack_ms: f32,
+    hold_ms: f32,
+    release_ms: f32,
+    level: f32,
+    hold_left: u32,
+    open: u32,
+    gain: f32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Gate {
+    fn update_timing(&mut self) {
+        self.attack_coeff = one_pole_coeff(self.attack_ms, self.sample_rate_hz);
+        self.release_coeff = one_pole_coeff(self.release_ms, self.sample_rate_hz);
+        self.detector_coeff = one_pole_coeff(DETECTOR_RELEASE_MS, self.sample_rate_hz);
+        self.hold_samples = (self.hold_ms / 1000.0 * self.sample_rate_hz).round() as u32;
+    }
+
+    /// Opens above `open_level`; closes once the level has stayed under `close_level` for the
+    /// hold time.
+    #[inline]
+    fn update_state(&mut self, peak: f32) {
+        self.level = flush(peak.max(self.level * self.detector_coeff));
+        if self.level >= self.open_level {
+            self.open = 1;
+            self.hold_left = self.hold_samples;
+        } else if self.open != 0 && self.level < self.close_level {
+            if self.hold_left > 0 {
+                self.hold_left -= 1;
+            } else {
+                self.open = 0;
+            }
+        }
+    }
+
+    fn update_levels(&mut self) {
+        self.open_level = db_to_lin(self.threshold_db);
+        self.close_level = db_to_lin(self.threshold_db - self.hysteresis_db);
+    }
+
+    /// Gates `input` on its own level, or on `key`'s (`(samples, channels)`) when given.
+    fn gate(
+        &mut self,
+        input: &[f32],
+        key: Option<(&[f32], usize)>,
+        output: &mut [f32],
+        channels: usize,
+    ) {
+        let frames = input.len() / channels;
+        for i in 0..frames {
+            let frame = &input[i * channels..(i + 1) * channels];
+            let detected = match key {
+                Some((key, key_channels)) => &key[i * key_channels..(i + 1) * key_channels],
+                None => frame,
+            };
+            let peak = detected.iter().fold(0.0_f32, |m, x| m.max(x.abs()));
+            self.update_state(peak);
+            let (target, c) = if self.open != 0 {
+                (1.0, self.attack_coeff)
+            } else {
+                (self.range, self.release_coeff)
+            };
+            self.gain = self.gain * c + (1.0 - c) * target;
+            for (ch, x) in frame.iter().enumerate() {
+                output[i * channels + ch] = x * self.gain;
+            }
+        }
+    }
+}
+
+impl Node for Gate {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Range` is the attenuation while closed (-80 behaves as a hard gate, smaller values as
+    /// an expander). The gate closes at `Threshold` minus `Hysteresis`.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Threshold", "dB", -90.0, 0.0, -50.0),
+        ParamDescriptor::linear("Range", "dB", -80.0, 0.0, -80.0),
+        ParamDescriptor::log("Attack", "ms", 0.01, 100.0, 1.0),
+        ParamDescriptor::linear("Hold", "ms", 0.0, 2000.0, 20.0),
+        ParamDescriptor::log("Release", "ms", 1.0, 5000.0, 100.0),
+        ParamDescriptor::linear("Hysteresis", "dB", 0.0, 20.0, 4.0),
+    ];
+    /// The side-chain key (loudest channel wins).
+    const AUX_CHANNELS: usize = MAX_CHANNELS;
+    const METER_COUNT: usize = 1;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut g = Gate {
+            threshold_db: -50.0,
+            hysteresis_db: 4.0,
+            open_level: 0.0,
+            close_level: 0.0,
+            range_db: -80.0,
+            range: 0.0,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            detector_coeff: 0.0,
+            hold_samples: 0,
+            attack_ms: 1.0,
+            hold_ms: 20.0,
+            release_ms: 100.0,
+            level: 0.0,
+            hold_left: 0,
+            open: 0,
+            gain: 0.0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        g.update_levels();
+        g.update_timing();
+        g
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_THRESHOLD_DB => {
+                self.threshold_db = clamp(value, -90.0, 0.0);
+                self.update_levels();
+            }
+            PARAM_RANGE_DB => {
+                self.range_db = clamp(value, -80.0, 0.0);
+                self.range = if self.range_db <= -80.0 {
+                    0.0
+                } else {
+                    db_to_lin(self.range_db)
+                };
+            }
+            PARAM_ATTACK_MS => {
+                self.attack_ms = clamp(value, 0.01, 100.0);
+                self.update_timing();
+            }
+            PARAM_HOLD_MS => {
+                self.hold_ms = clamp(value, 0.0, 2000.0);
+                self.update_timing();
+            }
+            PARAM_RELEASE_MS => {
+                self.release_ms = clamp(value, 1.0, 5000.0);
+                self.update_timing();
+            }
+            PARAM_HYSTERESIS_DB => {
+                self.hysteresis_db = clamp(value, 0.0, 20.0);
+                self.update_levels();
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_THRESHOLD_DB => self.threshold_db,
+            PARAM_RANGE_DB => self.range_db,
+            PARAM_ATTACK_MS => self.attack_ms,
+            PARAM_HOLD_MS => self.hold_ms,
+            PARAM_RELEASE_MS => self.release_ms,
+            PARAM_HYSTERESIS_DB => self.hysteresis_db,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    /// Without a side-chain the program keys itself.
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.gate(input, None, output, channels);
+    }
+
+    fn process_aux(
+        &mut self,
+        input: &[f32],
+        aux: &[f32],
+        aux_channels: usize,
+        output: &mut [f32],
+        channels: usize,
+    ) {
+        let key = (!aux.is_empty()).then_some((aux, aux_channels));
+        self.gate(input, key, output, channels);
+    }
+
+    /// Open state as `gate_is_open` reads it.
+    fn meters(&self, out: &mut [f32]) {
+        out[0] = self.open as f32;
+    }
+
+    /// Closes the gate and clears the detector and hold.
+    fn reset(&mut self) {
+        self.level = 0.0;
+        self.hold_left = 0;
+        self.open = 0;
+        self.gain = self.range;
+    }
+}
+
+node_abi::export_node!(Gate {
+    abi_version: gate_abi_version,
+    new: gate_new,
+    free: gate_free,
+    param_count: gate_param_count,
+    param_descriptors: gate_param_descriptors,
+    set_param: gate_set_param,
+    get_param: gate_get_param,
+    param_events: gate_param_events,
+    process: gate_process,
+    process_offline: gate_process_offline,
+    latency_samples: gate_latency_samples,
+    io_layout: gate_get_io_layout,
+    process_io: gate_process_io,
+    reset: gate_reset,
+    state_size: gate_state_size,
+    save_state: gate_save_state,
+    load_state: gate_load_state,
+    preset_size: gate_preset_size,
+    save_preset: gate_save_preset,
+    load_preset: gate_load_preset,
+    last_error_code: gate_last_error_code,
+    last_error_message: gate_last_error_message_ptr,
+    process_aux: gate_process_sidechained,
+    process_aux_offline: gate_process_offline_sidechained,
+    process_io_aux: gate_process_io_sidechained,
+});
+
+node_abi::test_params!(Gate);
+
+dsp_core::test_block_sizes!(
+    gate_new,
+    gate_process_sidechained(input, aux, output),
+    gate_free
+);
+
+node_abi::test_io_block!(Gate);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Gate as GateNode);
+
+/// Every parameter in one call, as `gate_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn gate_set_params(
+    handle: Handle,
+    threshold_db: f32,
+    range_db: f32,
+    attack_ms: f32,
+    hold_ms: f32,
+    release_ms: f32,
+    hysteresis_db: f32,
+) {
+    guard(handle, |g: &mut Gate| {
+        g.set_param(PARAM_THRESHOLD_DB, threshold_db);
+        g.set_param(PARAM_RANGE_DB, range_db);
+        g.set_param(PARAM_ATTACK_MS, attack_ms);
+        g.set_param(PARAM_HOLD_MS, hold_ms);
+        g.set_param(PARAM_RELEASE_MS, release_ms);
+        g.set_param(PARAM_HYSTERESIS_DB, hysteresis_db);
+    });
+}
+
+/// 1 while the gate is open (including the hold time), 0 when closed; for the UI LED.
+#[no_mangle]
+pub extern "C" fn gate_is_open(handle: Handle) -> u32 {
+    guard(handle, |g: &mut Gate| g.open).unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    fn gate(params: &[(u32, f32)]) -> Gate {
+        let mut g = Gate::new(RATE);
+        for &(index, value) in params {
+            g.set_param(index, value);
+        }
+        g
+    }
+
+    /// `input` through `g`, side-chained by `key` when given.
+    fn render(g: &mut Gate, input: &[f32], key: Option<&[f32]>) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| match key {
+            Some(key) => Node::process_aux(g, &input[span.clone()], &key[span], 1, y, 1),
+            None => Node::process(g, &input[span], y, 1),
+        })
+    }
+
+    fn is_open(g: &Gate) -> bool {
+        let mut meters = [0.0];
+        Node::meters(g, &mut meters);
+        meters[0] == 1.0
+    }
+
+    /// -20 dB opens a -30 dB gate; dropping to -40 dB closes it once the detector has fallen
+    /// and the hold has run out, and the release then takes the signal down to `Range`.
+    #[test]
+    fn closes_below_the_threshold_after_the_hold() {
+        for (range_db, floor) in [(-80.0, 0.0), (-20.0, 0.1)] {
+            let mut g = gate(&[
+                (PARAM_THRESHOLD_DB, -30.0),
+                (PARAM_HYSTERESIS_DB, 0.0),
+                (PARAM_HOLD_MS, 10.0),
+                (PARAM_RANGE_DB, range_db),
+            ]);
+            let loud = vec![0.1; 4_800];
+            let output = render(&mut g, &loud, None);
+            assert!(is_open(&g));
+            assert!((output[4_799] - 0.1).abs() < 1e-4, "{}", output[4_799]);
+
+            let quiet = vec![0.01; 24_000];
+            render(&mut g, &quiet[..960], None);
+            assert!(is_open(&g), "closed before the hold ran out");
+            let output = render(&mut g, &quiet[960..], None);
+            assert!(!is_open(&g));
+            let last = output[output.len() - 1];
+            assert!((last - 0.01 * floor).abs() < 1e-4, "{range_db} dB: {last}");
+        }
+    }
+
+    /// Once open, the gate stays open until the level falls `Hysteresis` under the threshold.
+    #[test]
+    fn hysteresis_holds_it_open_under_the_threshold() {
+        let mut g = gate(&[
+            (PARAM_THRESHOLD_DB, -30.0),
+            (PARAM_HYSTERESIS_DB, 12.0),
+            (PARAM_HOLD_MS, 0.0),
+        ]);
+        render(&mut g, &[0.1; 4_800], None);
+        let between = vec![db_to_lin(-36.0); 24_000];
+        let last = render(&mut g, &between, None)[23_999];
+        assert!((last - between[0]).abs() < 1e-6, "{last}");
+        assert!(is_open(&g));
+        render(&mut g, &[db_to_lin(-45.0); 24_000], None);
+        assert!(!is_open(&g));
+    }
+
+    /// A loud key opens the gate on a program under the threshold; a quiet one keeps it shut.
+    #[test]
+    fn side_chain_key_opens_it() {
+        let program = vec![0.01; 9_600];
+        let mut g = gate(&[(PARAM_THRESHOLD_DB, -30.0)]);
+        let output = render(&mut g, &program, Some(&[0.5; 9_600]));
+        assert!(is_open(&g));
+        assert!((output[9_599] - 0.01).abs() < 1e-6);
+
+        let mut g = gate(&[(PARAM_THRESHOLD_DB, -30.0)]);
+        let output = render(&mut g, &[0.5; 9_600], Some(&program));
+        assert!(!is_open(&g));
+        assert_eq!(output[9_599], 0.0);
+    }
+}
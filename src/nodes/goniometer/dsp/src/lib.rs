@@ -0,0 +1,335 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use core::f32::consts::FRAC_1_SQRT_2;
+use dsp_core::envelope::one_pole_coeff;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+/// Any width reads: channels past the first two are ignored.
+pub const MAX_CHANNELS: usize = usize::MAX;
+
+// Indices into `Goniometer::PARAMS`, in the order of `goniometer_set_params`' arguments.
+pub const PARAM_DECIMATION: u32 = 0;
+pub const PARAM_PERSISTENCE_MS: u32 = 1;
+pub const PARAM_CORRELATION_MS: u32 = 2;
+
+/// Points kept in the ring; the persistence can't show more than this.
+pub const MAX_POINTS: usize = 8192;
+
+/// Below this mean-square level on either side the correlation reads 0 rather than noise.
+const SILENCE: f32 = 1e-10;
+
+/// Stereo goniometer and phase-correlation meter. Every `decimation`th frame goes into a ring
+/// of points rotated 45 degrees (side across, mid up, so a mono signal is a vertical line);
+/// the UI draws the newest ones, as many as the persistence covers, fading the older. The
+/// correlation is the normalized cross-product of the channels averaged over a set time: +1
+/// for mono, 0 for unrelated channels, -1 for one channel inverted. A mono input counts as
+/// both channels. The audio is only read; the worklet passes it on.
+pub struct Goniometer {
+    /// Interleaved side/mid pairs.
+    points: Vec<f32>,
+    write: usize,
+    written: usize,
+    decimation: u32,
+    skip: u32,
+    persistence_ms: f32,
+    correlation_ms: f32,
+    lr: f32,
+    ll: f32,
+    rr: f32,
+    average: f32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Goniometer {
+    fn trail(&self) -> usize {
+        let points = self.persistence_ms / 1000.0 * self.sample_rate_hz / self.decimation as f32;
+        (points as usize).clamp(1, MAX_POINTS).min(self.written)
+    }
+
+    fn measure(&mut self, input: &[f32], channels: usize) {
+        let a = self.average;
+        for frame in input.chunks_exact(channels) {
+            let l = if frame[0].is_finite() { frame[0] } else { 0.0 };
+            let r = match frame.get(1) {
+                Some(r) if r.is_finite() => *r,
+                Some(_) => 0.0,
+                None => l,
+            };
+            self.lr = l * r + (self.lr - l * r) * a;
+            self.ll = l * l + (self.ll - l * l) * a;
+            self.rr = r * r + (self.rr - r * r) * a;
+
+            if self.skip == 0 {
+                self.skip = self.decimation;
+                self.points[2 * self.write] = (l - r) * FRAC_1_SQRT_2;
+                self.points[2 * self.write + 1] = (l + r) * FRAC_1_SQRT_2;
+                self.write = (self.write + 1) % MAX_POINTS;
+                self.written = (self.written + 1).min(MAX_POINTS);
+            }
+            self.skip -= 1;
+        }
+    }
+
+    fn correlation(&self) -> f32 {
+        if self.ll < SILENCE || self.rr < SILENCE {
+            return 0.0;
+        }
+        (self.lr / (self.ll * self.rr).sqrt()).clamp(-1.0, 1.0)
+    }
+}
+
+impl Node for Goniometer {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Decimation` keeps every that-many-th frame as a point; `Persistence` is how far back
+    /// the drawn trail reaches (up to `MAX_POINTS` points); `Correlation` is the correlation's
+    /// averaging time.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Decimation", "", 1.0, 64.0, 1.0),
+        ParamDescriptor::log("Persistence", "ms", 1.0, 2000.0, 50.0),
+        ParamDescriptor::log("Correlation", "ms", 10.0, 3000.0, 300.0),
+    ];
+    /// The audio is only read; the worklet passes it on.
+    const OUTPUT: bool = false;
+    const METER_COUNT: usize = 1;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        Goniometer {
+            points: vec![0.0; 2 * MAX_POINTS],
+            write: 0,
+            written: 0,
+            decimation: 1,
+            skip: 0,
+            persistence_ms: 50.0,
+            correlation_ms: 300.0,
+            lr: 0.0,
+            ll: 0.0,
+            rr: 0.0,
+            average: one_pole_coeff(300.0, sample_rate_hz),
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_DECIMATION => {
+                self.decimation = clamp(value, 1.0, 64.0).round() as u32;
+                self.skip = self.skip.min(self.decimation - 1);
+            }
+            PARAM_PERSISTENCE_MS => self.persistence_ms = clamp(value, 1.0, 2000.0),
+            PARAM_CORRELATION_MS => {
+                self.correlation_ms = clamp(value, 10.0, 3000.0);
+                self.average = one_pole_coeff(self.correlation_ms, self.sample_rate_hz);
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_DECIMATION => self.decimation as f32,
+            PARAM_PERSISTENCE_MS => self.persistence_ms,
+            PARAM_CORRELATION_MS => self.correlation_ms,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    /// Channels past the first two are ignored.
+    fn process(&mut self, input: &[f32], _output: &mut [f32], channels: usize) {
+        self.measure(input, channels);
+    }
+
+    /// Correlation as `goniometer_get_correlation` reads it.
+    fn meters(&self, out: &mut [f32]) {
+        out[0] = self.correlation();
+    }
+
+    /// Empties the trail and the correlation averages.
+    fn reset(&mut self) {
+        self.points.fill(0.0);
+        self.write = 0;
+        self.written = 0;
+        self.skip = 0;
+        self.lr = 0.0;
+        self.ll = 0.0;
+        self.rr = 0.0;
+    }
+}
+
+node_abi::export_node!(Goniometer {
+    abi_version: goniometer_abi_version,
+    new: goniometer_new,
+    free: goniometer_free,
+    param_count: goniometer_param_count,
+    param_descriptors: goniometer_param_descriptors,
+    set_param: goniometer_set_param,
+    get_param: goniometer_get_param,
+    param_events: goniometer_param_events,
+    process: goniometer_process,
+    process_offline: goniometer_process_offline,
+    latency_samples: goniometer_latency_samples,
+    io_layout: goniometer_get_io_layout,
+    process_io: goniometer_process_io,
+    reset: goniometer_reset,
+    state_size: goniometer_state_size,
+    save_state: goniometer_save_state,
+    load_state: goniometer_load_state,
+    preset_size: goniometer_preset_size,
+    save_preset: goniometer_save_preset,
+    load_preset: goniometer_load_preset,
+    last_error_code: goniometer_last_error_code,
+    last_error_message: goniometer_last_error_message_ptr,
+});
+
+node_abi::test_params!(Goniometer);
+
+dsp_core::test_block_sizes!(
+    goniometer_new,
+    goniometer_process(input, output),
+    goniometer_free,
+    read: |p| {
+        let points = unsafe { core::slice::from_raw_parts(goniometer_points(p), MAX_POINTS * 2) };
+        let mut out = points.to_vec();
+        out.extend([goniometer_get_correlation(p), goniometer_write_index(p) as f32]);
+        out
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Goniometer as GoniometerNode);
+
+/// Every parameter in one call, as `goniometer_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn goniometer_set_params(
+    handle: Handle,
+    decimation: u32,
+    persistence_ms: f32,
+    correlation_ms: f32,
+) {
+    guard(handle, |g: &mut Goniometer| {
+        g.set_param(PARAM_DECIMATION, decimation as f32);
+        g.set_param(PARAM_PERSISTENCE_MS, persistence_ms);
+        g.set_param(PARAM_CORRELATION_MS, correlation_ms);
+    });
+}
+
+/// The point ring, `MAX_POINTS` side/mid pairs (full scale on one channel is about 0.71), for
+/// the UI to read in place. Stays put for the node's lifetime.
+#[no_mangle]
+pub extern "C" fn goniometer_points(handle: Handle) -> *const f32 {
+    guard(handle, |g: &mut Goniometer| g.points.as_ptr()).unwrap_or(core::ptr::null())
+}
+
+/// Index of the point that will be written next; the newest is just before it.
+#[no_mangle]
+pub extern "C" fn goniometer_write_index(handle: Handle) -> u32 {
+    guard(handle, |g: &mut Goniometer| g.write as u32).unwrap_or(0)
+}
+
+/// How many points before the write index the persistence covers, for the UI.
+#[no_mangle]
+pub extern "C" fn goniometer_trail_points(handle: Handle) -> u32 {
+    guard(handle, |g: &mut Goniometer| g.trail() as u32).unwrap_or(0)
+}
+
+/// Phase correlation, -1 to +1 (0 while either channel is silent).
+#[no_mangle]
+pub extern "C" fn goniometer_get_correlation(handle: Handle) -> f32 {
+    guard(handle, |g: &mut Goniometer| g.correlation()).unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// Feeds `frames` of `f(i)` as (left, right) to the goniometer in render quanta.
+    fn feed(h: Handle, frames: usize, f: impl Fn(f32) -> (f32, f32)) {
+        let x: Vec<f32> = (0..frames)
+            .flat_map(|i| {
+                let (l, r) = f(i as f32);
+                [l, r]
+            })
+            .collect();
+        let mut out = [0.0; 256];
+        for block in x.chunks(256) {
+            goniometer_process(h, block.as_ptr(), out.as_mut_ptr(), block.len() / 2, 2);
+        }
+    }
+
+    fn sine(i: f32, phase: f32) -> f32 {
+        (2.0 * PI * 1_000.0 * i / RATE + phase).sin()
+    }
+
+    /// The correlation reads +1 for mono, -1 for one channel inverted, about 0 for channels in
+    /// quadrature, and 0 while a channel is silent.
+    #[test]
+    fn correlation_reads_phase_relationship() {
+        // Expected reading, then the right channel's gain and phase against the left.
+        for (expected, gain, phase) in [
+            (1.0, 0.5, 0.0),
+            (-1.0, -1.0, 0.0),
+            (0.0, 1.0, PI / 2.0),
+            (0.0, 0.0, 0.0),
+        ] {
+            let h = goniometer_new(RATE);
+            feed(h, 96_000, |i| (sine(i, 0.0), gain * sine(i, phase)));
+            let c = goniometer_get_correlation(h);
+            assert!((c - expected).abs() < 0.01, "{expected}: {c}");
+            goniometer_free(h);
+        }
+
+        // A mono input counts as both channels.
+        let h = goniometer_new(RATE);
+        let x: Vec<f32> = (0..48_000).map(|i| sine(i as f32, 0.0)).collect();
+        render_quanta(x.len(), |span, y| {
+            goniometer_process(h, x[span].as_ptr(), y.as_mut_ptr(), y.len(), 1)
+        });
+        assert!((goniometer_get_correlation(h) - 1.0).abs() < 1e-3);
+        goniometer_free(h);
+    }
+
+    /// Points are rotated 45 degrees: mono lies on the vertical, one channel alone on the
+    /// diagonal; every `decimation`th frame becomes one.
+    #[test]
+    fn plots_decimated_rotated_points() {
+        let h = goniometer_new(RATE);
+        goniometer_set_params(h, 4, 50.0, 300.0);
+        feed(h, 100, |i| if i < 50.0 { (0.5, 0.5) } else { (1.0, 0.0) });
+        assert_eq!(goniometer_write_index(h), 25);
+        assert_eq!(goniometer_trail_points(h), 25);
+        let points = unsafe { core::slice::from_raw_parts(goniometer_points(h), 50) };
+        let (mono, left) = (&points[2 * 5..2 * 5 + 2], &points[2 * 20..2 * 20 + 2]);
+        assert!(mono[0].abs() < 1e-6 && (mono[1] - FRAC_1_SQRT_2).abs() < 1e-6);
+        assert!((left[0] - FRAC_1_SQRT_2).abs() < 1e-6 && (left[1] - FRAC_1_SQRT_2).abs() < 1e-6);
+
+        // 50 ms at every 4th frame is 600 points.
+        feed(h, 4_800, |_| (0.0, 0.0));
+        assert_eq!(goniometer_trail_points(h), 600);
+        goniometer_free(h);
+    }
+}
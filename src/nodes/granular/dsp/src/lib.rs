@@ -0,0 +1,586 @@
+// `granular_load` reads the buffer through a pointer the worklet got from us; null is the only
+// invalid value we can detect.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::f32::consts::{FRAC_PI_4, PI};
+
+use dsp_core::gain::db_to_lin;
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_GRAINS: usize = 64;
+
+// Indices into `Granular::PARAMS`, in the order of the arguments of `granular_set_params` and
+// `granular_set_shape`.
+pub const PARAM_SIZE_MS: u32 = 0;
+pub const PARAM_DENSITY: u32 = 1;
+pub const PARAM_POSITION: u32 = 2;
+pub const PARAM_POSITION_JITTER: u32 = 3;
+pub const PARAM_PITCH_SEMITONES: u32 = 4;
+pub const PARAM_PITCH_JITTER: u32 = 5;
+pub const PARAM_SHAPE: u32 = 6;
+pub const PARAM_SPRAY: u32 = 7;
+pub const PARAM_LEVEL_DB: u32 = 8;
+
+/// Longest buffer accepted, in seconds at its own rate (longer uploads are cut).
+const MAX_BUFFER_SECONDS: f32 = 60.0;
+
+pub const HANN: u32 = 0;
+pub const TRIANGLE: u32 = 1;
+/// Flat top with short raised-cosine edges (Tukey, 20% taper).
+pub const TUKEY: u32 = 2;
+/// Instant attack, exponential decay.
+pub const PERCUSSIVE: u32 = 3;
+
+/// Grain envelope at `t` (0..1 through the grain).
+#[inline]
+fn envelope(shape: u32, t: f32) -> f32 {
+    match shape {
+        TRIANGLE => 1.0 - (2.0 * t - 1.0).abs(),
+        TUKEY => {
+            const TAPER: f32 = 0.1;
+            let edge = t.min(1.0 - t);
+            if edge >= TAPER {
+                1.0
+            } else {
+                0.5 - 0.5 * (PI * edge / TAPER).cos()
+            }
+        }
+        // exp(-5t), with a few-percent fade at the end so it doesn't click off.
+        PERCUSSIVE => (-5.0 * t).exp() * ((1.0 - t) * 20.0).min(1.0),
+        _ => 0.5 - 0.5 * (2.0 * PI * t).cos(),
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Grain {
+    active: bool,
+    /// Read position in buffer frames and the advance per output sample.
+    pos: f64,
+    rate: f64,
+    /// Position through the grain (0..1) and its step per sample.
+    t: f32,
+    step: f32,
+    left: f32,
+    right: f32,
+}
+
+/// Granulator. Grains of `size` are started `density` times a second from an uploaded buffer
+/// (mixed to mono): each reads from `position` (plus up to `position_jitter` of the buffer
+/// either way) at `pitch` (plus up to `pitch_jitter` semitones either way), shaped by the
+/// grain envelope and panned up to `spray` of the way to either side. Start times are
+/// tracked to the sample, with the fractional remainder carried into the grain's first read.
+pub struct Granular {
+    data: Vec<f32>,
+    /// Buffer rate over the context's.
+    rate_ratio: f64,
+    grains: [Grain; MAX_GRAINS],
+    /// Samples until the next grain starts (counts down, fractional).
+    next_grain: f64,
+    size_ms: f32,
+    size_samples: f32,
+    density: f32,
+    interval: f64,
+    position: f32,
+    position_jitter: f32,
+    pitch: f32,
+    pitch_jitter: f32,
+    shape: u32,
+    spray: f32,
+    level_db: f32,
+    gain: f32,
+    /// Applied to the grain sum: grains overlap `density * size` deep on average (up to the
+    /// grain limit).
+    overlap_gain: f32,
+    rng: u32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Granular {
+    /// Uniform in [-1, 1) from a xorshift32.
+    #[inline]
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 7) as f32 / (1u32 << 24) as f32 - 1.0
+    }
+
+    /// Buffer sample at `pos`, wrapping around the ends (4-point Hermite).
+    #[inline]
+    fn read(&self, pos: f64) -> f32 {
+        let n = self.data.len() as isize;
+        let i = pos.floor() as isize;
+        let t = (pos - i as f64) as f32;
+        let tap = |k: isize| self.data[(i + k).rem_euclid(n) as usize];
+        let (ym1, y0, y1, y2) = (tap(-1), tap(0), tap(1), tap(2));
+        let c1 = 0.5 * (y1 - ym1);
+        let c2 = ym1 - 2.5 * y0 + 2.0 * y1 - 0.5 * y2;
+        let c3 = 0.5 * (y2 - ym1) + 1.5 * (y0 - y1);
+        ((c3 * t + c2) * t + c1) * t + y0
+    }
+
+    /// Starts a grain `late` samples (0..1) after the sample it belongs to. Dropped when all
+    /// grains are busy.
+    fn spawn(&mut self, late: f64) {
+        let Some(index) = self.grains.iter().position(|g| !g.active) else {
+            return;
+        };
+        let n = self.data.len() as f64;
+        let pos = (self.position + self.position_jitter * self.random()) as f64 * n;
+        let semitones = self.pitch + self.pitch_jitter * self.random();
+        let rate = self.rate_ratio * (semitones / 12.0).exp2() as f64;
+        let angle = FRAC_PI_4 * (1.0 + self.spray * self.random());
+        let step = 1.0 / self.size_samples;
+        self.grains[index] = Grain {
+            active: true,
+            pos: pos.rem_euclid(n) + rate * late,
+            rate,
+            t: step * late as f32,
+            step,
+            left: angle.cos(),
+            right: angle.sin(),
+        };
+    }
+
+    fn render(&mut self, output: &mut [f32], channels: usize) {
+        if self.data.is_empty() {
+            output.fill(0.0);
+            return;
+        }
+        for out in output.chunks_exact_mut(channels) {
+            self.next_grain -= 1.0;
+            while self.next_grain <= 0.0 {
+                self.spawn(-self.next_grain);
+                self.next_grain += self.interval;
+            }
+            let (mut left, mut right) = (0.0, 0.0);
+            for index in 0..MAX_GRAINS {
+                let g = self.grains[index];
+                if !g.active {
+                    continue;
+                }
+                let s = self.read(g.pos) * envelope(self.shape, g.t);
+                left += s * g.left;
+                right += s * g.right;
+                let g = &mut self.grains[index];
+                g.pos += g.rate;
+                g.t += g.step;
+                g.active = g.t < 1.0;
+            }
+            let gain = self.gain * self.overlap_gain;
+            if channels == 1 {
+                out[0] = (left + right) * FRAC_PI_4.cos() * gain;
+            } else {
+                for (ch, y) in out.iter_mut().enumerate() {
+                    *y = if ch % 2 == 0 { left } else { right } * gain;
+                }
+            }
+        }
+    }
+
+    fn load(&mut self, input: &[f32], channels: usize, rate: f32) {
+        let scale = 1.0 / channels as f32;
+        self.data = input
+            .chunks_exact(channels)
+            .map(|frame| {
+                let v = frame.iter().sum::<f32>() * scale;
+                if v.is_finite() {
+                    v
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        self.rate_ratio = (rate / self.sample_rate_hz) as f64;
+        for grain in &mut self.grains {
+            grain.active = false;
+        }
+    }
+
+    fn update_overlap(&mut self) {
+        let overlap = self.size_samples as f64 / self.interval;
+        self.overlap_gain = 1.0 / (overlap.clamp(1.0, MAX_GRAINS as f64) as f32).sqrt();
+    }
+}
+
+impl Node for Granular {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Size` per grain; `Density` in grains a second; `Position` through the buffer,
+    /// `Position jitter` of the buffer either side; `Pitch jitter` in semitones either side.
+    /// `Shape`: 0 Hann, 1 triangle, 2 Tukey (flat top), 3 percussive; `Spray` is the random
+    /// pan width.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::log("Size", "ms", 5.0, 1000.0, 100.0),
+        ParamDescriptor::log("Density", "Hz", 1.0, 500.0, 20.0),
+        ParamDescriptor::linear("Position", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Position jitter", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Pitch", "st", -24.0, 24.0, 0.0),
+        ParamDescriptor::linear("Pitch jitter", "st", 0.0, 12.0, 0.0),
+        ParamDescriptor::stepped("Shape", "", 0.0, PERCUSSIVE as f32, HANN as f32),
+        ParamDescriptor::linear("Spray", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Level", "dB", -60.0, 12.0, 0.0),
+    ];
+    const INPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut g = Granular {
+            data: Vec::new(),
+            rate_ratio: 1.0,
+            grains: [Grain::default(); MAX_GRAINS],
+            next_grain: 0.0,
+            size_ms: 100.0,
+            size_samples: 0.1 * sample_rate_hz,
+            density: 20.0,
+            interval: sample_rate_hz as f64 / 20.0,
+            position: 0.0,
+            position_jitter: 0.0,
+            pitch: 0.0,
+            pitch_jitter: 0.0,
+            shape: HANN,
+            spray: 0.0,
+            level_db: 0.0,
+            gain: 1.0,
+            overlap_gain: 1.0,
+            rng: 0x9e37_79b9,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        g.update_overlap();
+        g
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_SIZE_MS => {
+                self.size_ms = clamp(value, 5.0, 1000.0);
+                self.size_samples = (self.size_ms / 1000.0 * self.sample_rate_hz).max(1.0);
+                self.update_overlap();
+            }
+            PARAM_DENSITY => {
+                self.density = clamp(value, 1.0, 500.0);
+                let interval = self.sample_rate_hz as f64 / self.density as f64;
+                // Pull a pending grain in if the new rate is faster, so raising the density
+                // is heard now.
+                self.next_grain = self.next_grain.min(interval);
+                self.interval = interval;
+                self.update_overlap();
+            }
+            PARAM_POSITION => self.position = clamp(value, 0.0, 1.0),
+            PARAM_POSITION_JITTER => self.position_jitter = clamp(value, 0.0, 1.0),
+            PARAM_PITCH_SEMITONES => self.pitch = clamp(value, -24.0, 24.0),
+            PARAM_PITCH_JITTER => self.pitch_jitter = clamp(value, 0.0, 12.0),
+            PARAM_SHAPE => self.shape = clamp(value, 0.0, PERCUSSIVE as f32).round() as u32,
+            PARAM_SPRAY => self.spray = clamp(value, 0.0, 1.0),
+            PARAM_LEVEL_DB => {
+                self.level_db = clamp(value, -60.0, 12.0);
+                self.gain = db_to_lin(self.level_db);
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_SIZE_MS => self.size_ms,
+            PARAM_DENSITY => self.density,
+            PARAM_POSITION => self.position,
+            PARAM_POSITION_JITTER => self.position_jitter,
+            PARAM_PITCH_SEMITONES => self.pitch,
+            PARAM_PITCH_JITTER => self.pitch_jitter,
+            PARAM_SHAPE => self.shape as f32,
+            PARAM_SPRAY => self.spray,
+            PARAM_LEVEL_DB => self.level_db,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    /// Grains are panned into channel pairs (even channels left, odd right); a mono output
+    /// gets their equal-power sum.
+    fn process(&mut self, _input: &[f32], output: &mut [f32], channels: usize) {
+        self.render(output, channels);
+    }
+
+    /// Stops every grain and restarts the grain clock and random sequence; the loaded buffer
+    /// is kept.
+    fn reset(&mut self) {
+        self.grains = [Grain::default(); MAX_GRAINS];
+        self.next_grain = 0.0;
+        self.rng = 0x9e37_79b9;
+    }
+}
+
+node_abi::export_node!(Granular {
+    abi_version: granular_abi_version,
+    new: granular_new,
+    free: granular_free,
+    param_count: granular_param_count,
+    param_descriptors: granular_param_descriptors,
+    set_param: granular_set_param,
+    get_param: granular_get_param,
+    param_events: granular_param_events,
+    process: granular_process,
+    process_offline: granular_process_offline,
+    latency_samples: granular_latency_samples,
+    io_layout: granular_get_io_layout,
+    process_io: granular_process_io,
+    reset: granular_reset,
+    state_size: granular_state_size,
+    save_state: granular_save_state,
+    load_state: granular_load_state,
+    preset_size: granular_preset_size,
+    save_preset: granular_save_preset,
+    load_preset: granular_load_preset,
+    last_error_code: granular_last_error_code,
+    last_error_message: granular_last_error_message_ptr,
+});
+
+node_abi::test_params!(Granular);
+
+dsp_core::test_block_sizes!(
+    granular_new,
+    granular_process(input, output),
+    granular_free,
+    setup: |p| {
+        let sample = dsp_core::blocksize::test_signal(4_800, 2, 2);
+        granular_load(p, sample.as_ptr(), 4_800, 2, 44_100.0);
+    },
+);
+
+node_abi::test_io_block!(
+    Granular,
+    setup: |g: &mut Granular| {
+        g.load(&dsp_core::blocksize::test_signal(4_800, 2, 2), 2, 44_100.0);
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Granular as GranularNode);
+
+/// Loads the buffer to granulate, copied by the worklet into a `wasm_alloc`ed buffer:
+/// `frames` frames of `channels` interleaved samples (mixed to mono) recorded at
+/// `sample_rate_hz`, up to 60 s. Running grains stop. The buffer can be freed as soon as this
+/// returns. Allocates, so call it from the message handler rather than mid-render.
+#[no_mangle]
+pub extern "C" fn granular_load(
+    handle: Handle,
+    data_ptr: *const f32,
+    frames: usize,
+    channels: usize,
+    sample_rate_hz: f32,
+) {
+    if data_ptr.is_null() || channels == 0 {
+        return;
+    }
+    let rate = clamp(sample_rate_hz, 1000.0, 384_000.0);
+    let frames = frames.min((MAX_BUFFER_SECONDS * rate) as usize);
+    let input = unsafe { core::slice::from_raw_parts(data_ptr, frames.saturating_mul(channels)) };
+    guard(handle, |g: &mut Granular| g.load(input, channels, rate));
+}
+
+/// `granular_load` for the bindgen class: `data` holds whole frames of `channels`
+/// interleaved samples.
+#[cfg(feature = "bindgen")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+impl GranularNode {
+    pub fn load(&mut self, data: &[f32], channels: usize, sample_rate_hz: f32) {
+        if channels == 0 {
+            return;
+        }
+        let rate = clamp(sample_rate_hz, 1000.0, 384_000.0);
+        let frames = (data.len() / channels).min((MAX_BUFFER_SECONDS * rate) as usize);
+        self.node.load(&data[..frames * channels], channels, rate);
+    }
+}
+
+/// `Size` through `Pitch jitter` in one call, as `granular_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn granular_set_params(
+    handle: Handle,
+    size_ms: f32,
+    density: f32,
+    position: f32,
+    position_jitter: f32,
+    pitch_semitones: f32,
+    pitch_jitter: f32,
+) {
+    guard(handle, |g: &mut Granular| {
+        g.set_param(PARAM_SIZE_MS, size_ms);
+        g.set_param(PARAM_DENSITY, density);
+        g.set_param(PARAM_POSITION, position);
+        g.set_param(PARAM_POSITION_JITTER, position_jitter);
+        g.set_param(PARAM_PITCH_SEMITONES, pitch_semitones);
+        g.set_param(PARAM_PITCH_JITTER, pitch_jitter);
+    });
+}
+
+/// `Shape`, `Spray` and `Level` in one call.
+#[no_mangle]
+pub extern "C" fn granular_set_shape(handle: Handle, shape: u32, spray: f32, level_db: f32) {
+    guard(handle, |g: &mut Granular| {
+        g.set_param(PARAM_SHAPE, shape as f32);
+        g.set_param(PARAM_SPRAY, spray);
+        g.set_param(PARAM_LEVEL_DB, level_db);
+    });
+}
+
+/// Grains currently sounding, for the UI.
+#[no_mangle]
+pub extern "C" fn granular_active_grains(handle: Handle) -> u32 {
+    guard(handle, |g: &mut Granular| {
+        g.grains.iter().filter(|g| g.active).count() as u32
+    })
+    .unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::TAU;
+    use dsp_core::blocksize::amplitude;
+
+    const RATE: f32 = 48_000.0;
+
+    /// A granulator over one second of `buffer` at the context rate, with `params` applied.
+    fn granular(buffer: impl Fn(f32) -> f32, params: &[(u32, f32)]) -> Granular {
+        let mut g = Granular::new(RATE);
+        let data: Vec<f32> = (0..48_000).map(|i| buffer(i as f32 / RATE)).collect();
+        g.load(&data, 1, RATE);
+        for &(index, value) in params {
+            g.set_param(index, value);
+        }
+        g
+    }
+
+    /// `frames` frames on `channels` channels, in render quanta.
+    fn render(g: &mut Granular, frames: usize, channels: usize) -> Vec<f32> {
+        let mut output = vec![0.0; frames * channels];
+        for y in output.chunks_mut(128 * channels) {
+            Node::process(g, &[], y, channels);
+        }
+        output
+    }
+
+    /// Grains start on the sample their fractional schedule falls in, with no drift over
+    /// many intervals.
+    #[test]
+    fn grains_start_on_schedule() {
+        // Percussive grains are at full level from their first sample.
+        let mut g = granular(
+            |_| 1.0,
+            &[
+                (PARAM_SIZE_MS, 5.0),
+                (PARAM_DENSITY, 37.0),
+                (PARAM_SHAPE, PERCUSSIVE as f32),
+            ],
+        );
+        let y = render(&mut g, 480_000, 1);
+        let onsets: Vec<usize> = (0..y.len())
+            .filter(|&i| y[i] > 0.0 && (i == 0 || y[i - 1] == 0.0))
+            .collect();
+        // 37 a second for ten seconds, and the next one due on the last sample.
+        assert_eq!(onsets.len(), 371);
+        let interval = RATE as f64 / 37.0;
+        for (k, &onset) in onsets.iter().enumerate() {
+            let due = k as f64 * interval;
+            assert!(
+                (due - onset as f64 - 0.5).abs() <= 0.5,
+                "{k}: {onset} vs {due}"
+            );
+            assert!(y[onset] > 0.8, "{k}: {}", y[onset]);
+        }
+    }
+
+    /// `Position` picks where in the buffer a grain reads from.
+    #[test]
+    fn position_picks_the_read_point() {
+        let mut g = granular(
+            |t| t,
+            &[
+                (PARAM_SIZE_MS, 5.0),
+                (PARAM_DENSITY, 100.0),
+                (PARAM_POSITION, 0.5),
+            ],
+        );
+        let y = render(&mut g, 480, 1);
+        // The grain's middle, 120 samples in, at full envelope.
+        let (i, peak) = y
+            .iter()
+            .enumerate()
+            .fold((0, 0.0), |m, (i, &v)| if v > m.1 { (i, v) } else { m });
+        assert!((119..=121).contains(&i), "{i}");
+        assert!((peak - (0.5 + 120.0 / RATE)).abs() < 1e-3, "{peak}");
+    }
+
+    /// `Pitch` resamples what a grain reads: +12 st moves a 480 Hz buffer to 960 Hz.
+    #[test]
+    fn pitch_transposes_grains() {
+        let sine = |t: f32| (TAU * 480.0 * t).sin();
+        let y = render(
+            &mut granular(sine, &[(PARAM_PITCH_SEMITONES, 12.0)]),
+            96_000,
+            1,
+        );
+        let (octave, unison) = (amplitude(&y, 960.0), amplitude(&y, 480.0));
+        assert!(octave > 0.3 && unison < 0.05 * octave, "{octave} {unison}");
+    }
+
+    /// With `Spray` each grain takes its own equal-power pan; without it all sit centred.
+    #[test]
+    fn spray_pans_grains() {
+        let pans = |spray: f32| {
+            let mut g = granular(
+                |_| 1.0,
+                &[
+                    (PARAM_SIZE_MS, 5.0),
+                    (PARAM_DENSITY, 100.0),
+                    (PARAM_SHAPE, TUKEY as f32),
+                    (PARAM_SPRAY, spray),
+                ],
+            );
+            let y = render(&mut g, 4_800, 2);
+            // Each grain's flat top, 120 frames in.
+            (0..10)
+                .map(|k| (y[(k * 480 + 119) * 2], y[(k * 480 + 119) * 2 + 1]))
+                .collect::<Vec<_>>()
+        };
+        let centred = FRAC_PI_4.cos();
+        for (l, r) in pans(0.0) {
+            assert!((l - centred).abs() < 1e-4 && (r - centred).abs() < 1e-4);
+        }
+        let sprayed = pans(1.0);
+        for &(l, r) in &sprayed {
+            assert!((l * l + r * r - 1.0).abs() < 1e-3, "{l} {r}");
+        }
+        assert!(sprayed.iter().any(|&(l, r)| l > r + 0.5));
+        assert!(sprayed.iter().any(|&(l, r)| r > l + 0.5));
+    }
+
+    /// Dense long grains stop at the 64-grain limit.
+    #[test]
+    fn grains_are_capped() {
+        let mut g = granular(|_| 1.0, &[(PARAM_SIZE_MS, 1000.0), (PARAM_DENSITY, 500.0)]);
+        render(&mut g, 24_000, 1);
+        assert_eq!(g.grains.iter().filter(|g| g.active).count(), MAX_GRAINS);
+    }
+}
@@ -0,0 +1,354 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::envelope::one_pole_coeff;
+use dsp_core::gain::lin_to_db;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `LevelMeter::PARAMS`, in the order of `level_meter_set_params`' arguments.
+pub const PARAM_MODE: u32 = 0;
+pub const PARAM_HOLD_MS: u32 = 1;
+
+/// Sample peak, jumping to each new peak and falling at 20 dB/s.
+pub const PEAK: u32 = 0;
+/// Digital PPM after IEC 60268-10 type I: a 5 ms burst reads 2 dB low, falling 20 dB in 1.7 s.
+pub const PPM: u32 = 1;
+/// VU-style RMS, reaching 99% of a step in 300 ms.
+pub const VU: u32 = 2;
+
+const PEAK_FALL_DB_PER_SECOND: f32 = 20.0;
+/// PPM attack time constant, set so a 5 ms 1 kHz burst reads 2 dB under the steady tone (the
+/// rectified sine's dips make it shorter than 5 ms alone would suggest), and fall rate.
+const PPM_ATTACK_MS: f32 = 1.4;
+const PPM_FALL_DB_PER_SECOND: f32 = 20.0 / 1.7;
+/// Mean-square time constant putting the RMS at 99% of a step after 300 ms (300 / ln 50).
+const VU_TIME_CONSTANT_MS: f32 = 76.7;
+/// Samples at or over full scale count as clipped.
+const CLIP_LEVEL: f32 = 1.0;
+
+fn fall_factor(db_per_second: f32, sample_rate_hz: f32) -> f32 {
+    10.0_f32.powf(-db_per_second / 20.0 / sample_rate_hz)
+}
+
+#[derive(Clone, Copy, Default)]
+struct Channel {
+    /// The ballistics' state: amplitude for peak and PPM, mean square for VU.
+    env: f32,
+    hold: f32,
+    hold_left: u32,
+    clips: u32,
+}
+
+/// Level meter with selectable ballistics per channel, a hold on each reading for a set time
+/// (after which it drops back to the reading), and a count of clipped samples. The audio is
+/// only read; the worklet passes it on. The UI polls the getters.
+pub struct LevelMeter {
+    mode: u32,
+    channels: [Channel; MAX_CHANNELS],
+    hold_ms: f32,
+    hold_samples: u32,
+    peak_fall: f32,
+    ppm_attack: f32,
+    ppm_fall: f32,
+    vu_coeff: f32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl LevelMeter {
+    fn level(&self, c: &Channel) -> f32 {
+        if self.mode == VU {
+            c.env.sqrt()
+        } else {
+            c.env
+        }
+    }
+
+    fn measure(&mut self, input: &[f32], channels: usize) {
+        for frame in input.chunks_exact(channels) {
+            for (ch, &x) in frame.iter().enumerate() {
+                let x = if x.is_finite() { x.abs() } else { 0.0 };
+                let mut c = self.channels[ch];
+                if x >= CLIP_LEVEL {
+                    c.clips = c.clips.saturating_add(1);
+                }
+                c.env = match self.mode {
+                    PPM if x > c.env => x + (c.env - x) * self.ppm_attack,
+                    PPM => c.env * self.ppm_fall,
+                    VU => x * x + (c.env - x * x) * self.vu_coeff,
+                    _ => (c.env * self.peak_fall).max(x),
+                };
+                let level = self.level(&c);
+                if level >= c.hold {
+                    c.hold = level;
+                    c.hold_left = self.hold_samples;
+                } else if c.hold_left > 0 {
+                    c.hold_left -= 1;
+                } else {
+                    c.hold = level;
+                }
+                self.channels[ch] = c;
+            }
+        }
+    }
+}
+
+impl Node for LevelMeter {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Mode` 0 peak, 1 digital PPM, 2 VU (switching restarts the readings); `Hold` is how long
+    /// a new high is held.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Mode", "", 0.0, VU as f32, PEAK as f32),
+        ParamDescriptor::linear("Hold", "ms", 0.0, 10_000.0, 1500.0),
+    ];
+    /// The audio is only read; the worklet passes it on.
+    const OUTPUT: bool = false;
+    const METER_COUNT: usize = MAX_CHANNELS;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        LevelMeter {
+            mode: PEAK,
+            channels: [Channel::default(); MAX_CHANNELS],
+            hold_ms: 1500.0,
+            hold_samples: (sample_rate_hz * 1.5) as u32,
+            peak_fall: fall_factor(PEAK_FALL_DB_PER_SECOND, sample_rate_hz),
+            ppm_attack: one_pole_coeff(PPM_ATTACK_MS, sample_rate_hz),
+            ppm_fall: fall_factor(PPM_FALL_DB_PER_SECOND, sample_rate_hz),
+            vu_coeff: one_pole_coeff(VU_TIME_CONSTANT_MS, sample_rate_hz),
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_MODE => {
+                let mode = clamp(value, 0.0, VU as f32).round() as u32;
+                if mode != self.mode {
+                    for c in &mut self.channels {
+                        c.env = 0.0;
+                        c.hold = 0.0;
+                    }
+                }
+                self.mode = mode;
+            }
+            PARAM_HOLD_MS => {
+                self.hold_ms = clamp(value, 0.0, 10_000.0);
+                self.hold_samples = (self.hold_ms / 1000.0 * self.sample_rate_hz) as u32;
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_MODE => self.mode as f32,
+            PARAM_HOLD_MS => self.hold_ms,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], _output: &mut [f32], channels: usize) {
+        self.measure(input, channels);
+    }
+
+    /// Every channel's reading, as `level_meter_get_level_db` reads it.
+    fn meters(&self, out: &mut [f32]) {
+        for (m, c) in out.iter_mut().zip(&self.channels) {
+            *m = lin_to_db(self.level(c));
+        }
+    }
+
+    /// Clears the readings, holds and clip counts.
+    fn reset(&mut self) {
+        self.channels = [Channel::default(); MAX_CHANNELS];
+    }
+}
+
+node_abi::export_node!(LevelMeter {
+    abi_version: level_meter_abi_version,
+    new: level_meter_new,
+    free: level_meter_free,
+    param_count: level_meter_param_count,
+    param_descriptors: level_meter_param_descriptors,
+    set_param: level_meter_set_param,
+    get_param: level_meter_get_param,
+    param_events: level_meter_param_events,
+    process: level_meter_process,
+    process_offline: level_meter_process_offline,
+    latency_samples: level_meter_latency_samples,
+    io_layout: level_meter_get_io_layout,
+    process_io: level_meter_process_io,
+    reset: level_meter_reset,
+    state_size: level_meter_state_size,
+    save_state: level_meter_save_state,
+    load_state: level_meter_load_state,
+    preset_size: level_meter_preset_size,
+    save_preset: level_meter_save_preset,
+    load_preset: level_meter_load_preset,
+    last_error_code: level_meter_last_error_code,
+    last_error_message: level_meter_last_error_message_ptr,
+});
+
+node_abi::test_params!(LevelMeter);
+
+dsp_core::test_block_sizes!(
+    level_meter_new,
+    level_meter_process(input, output),
+    level_meter_free,
+    read: |p| {
+        (0..2)
+            .flat_map(|c| [level_meter_get_level_db(p, c), level_meter_get_hold_db(p, c)])
+            .collect::<Vec<_>>()
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(LevelMeter as LevelMeterNode);
+
+/// Every parameter in one call, as `level_meter_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn level_meter_set_params(handle: Handle, mode: u32, hold_ms: f32) {
+    guard(handle, |m: &mut LevelMeter| {
+        m.set_param(PARAM_MODE, mode as f32);
+        m.set_param(PARAM_HOLD_MS, hold_ms);
+    });
+}
+
+/// Runs `read` on the meter and `channel`'s readings, if the handle is live and the channel in
+/// range.
+fn read_channel<T>(
+    handle: Handle,
+    channel: u32,
+    read: impl FnOnce(&LevelMeter, &Channel) -> T,
+) -> Option<T> {
+    guard(handle, |m: &mut LevelMeter| {
+        m.channels.get(channel as usize).map(|c| read(m, c))
+    })
+    .flatten()
+}
+
+/// Reading of `channel` in dBFS (-Infinity for silence or a channel out of range).
+#[no_mangle]
+pub extern "C" fn level_meter_get_level_db(handle: Handle, channel: u32) -> f32 {
+    read_channel(handle, channel, |m, c| lin_to_db(m.level(c))).unwrap_or(f32::NEG_INFINITY)
+}
+
+/// Held reading of `channel` in dBFS.
+#[no_mangle]
+pub extern "C" fn level_meter_get_hold_db(handle: Handle, channel: u32) -> f32 {
+    read_channel(handle, channel, |_, c| lin_to_db(c.hold)).unwrap_or(f32::NEG_INFINITY)
+}
+
+/// Samples of `channel` at or over full scale since creation or the last `level_meter_reset`.
+#[no_mangle]
+pub extern "C" fn level_meter_get_clip_count(handle: Handle, channel: u32) -> u32 {
+    read_channel(handle, channel, |_, c| c.clips).unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    fn sine(amplitude: f32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|i| amplitude * (2.0 * PI * 1_000.0 * i as f32 / RATE).sin())
+            .collect()
+    }
+
+    /// Feeds mono `x` to the meter in render quanta.
+    fn feed(h: Handle, x: &[f32]) {
+        render_quanta(x.len(), |span, y| {
+            level_meter_process(h, x[span].as_ptr(), y.as_mut_ptr(), y.len(), 1)
+        });
+    }
+
+    /// A meter in `mode` with `hold_ms` of hold.
+    fn meter(mode: u32, hold_ms: f32) -> Handle {
+        let h = level_meter_new(RATE);
+        level_meter_set_params(h, mode, hold_ms);
+        h
+    }
+
+    /// The peak reading falls at 20 dB/s; the hold keeps the peak for its time, then drops back
+    /// to the reading.
+    #[test]
+    fn peak_falls_and_hold_drops_back() {
+        let h = meter(PEAK, 500.0);
+        feed(h, &sine(0.5, 480));
+        assert!((level_meter_get_level_db(h, 0) + 6.02).abs() < 0.01);
+        feed(h, &[0.0; 19_200]);
+        let level = level_meter_get_level_db(h, 0);
+        assert!((level + 6.02 + 8.0).abs() < 0.1, "{level}");
+        assert!((level_meter_get_hold_db(h, 0) + 6.02).abs() < 0.01);
+        feed(h, &[0.0; 9_600]);
+        assert_eq!(
+            level_meter_get_hold_db(h, 0),
+            level_meter_get_level_db(h, 0)
+        );
+        level_meter_free(h);
+    }
+
+    /// The PPM reads a 5 ms tone burst 2 dB under the steady tone and falls 20 dB in 1.7 s.
+    #[test]
+    fn ppm_follows_iec_type_i() {
+        let h = meter(PPM, 0.0);
+        feed(h, &sine(1.0, 48_000));
+        let steady = level_meter_get_level_db(h, 0);
+        // The reading dips a little between crests.
+        assert!(steady.abs() < 0.2, "{steady}");
+        feed(h, &[0.0; 81_600]);
+        let fall = steady - level_meter_get_level_db(h, 0);
+        assert!((fall - 20.0).abs() < 0.2, "{fall}");
+
+        level_meter_reset(h);
+        feed(h, &sine(1.0, 240));
+        let burst = level_meter_get_hold_db(h, 0);
+        assert!((burst - steady + 2.0).abs() < 0.5, "{burst}");
+        level_meter_free(h);
+    }
+
+    /// The VU reading reaches 99% of a step in 300 ms (checked 10 ms either side).
+    #[test]
+    fn vu_reaches_99_percent_in_300_ms() {
+        let h = meter(VU, 0.0);
+        feed(h, &[1.0; 13_920]);
+        assert!(level_meter_get_level_db(h, 0) < 20.0 * 0.99_f32.log10());
+        feed(h, &[1.0; 960]);
+        assert!(level_meter_get_level_db(h, 0) >= 20.0 * 0.99_f32.log10());
+        level_meter_free(h);
+    }
+
+    /// Samples at or over full scale are counted until reset.
+    #[test]
+    fn counts_clipped_samples() {
+        let h = meter(PEAK, 0.0);
+        feed(h, &[0.5, 1.0, -1.2, 0.99, f32::NAN, 1.5]);
+        assert_eq!(level_meter_get_clip_count(h, 0), 3);
+        level_meter_reset(h);
+        assert_eq!(level_meter_get_clip_count(h, 0), 0);
+        level_meter_free(h);
+    }
+}
@@ -0,0 +1,492 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::f32::consts::PI;
+use dsp_core::events::{EventRing, NoteEvent, NOTE_ON};
+use dsp_core::oscillator::Waveform;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+/// Longest control period: the output is recomputed at least this often.
+pub const MAX_DECIMATION: u32 = 128;
+
+// Indices into `Lfo::PARAMS`, in the order of the arguments of `lfo_set_params`,
+// `lfo_set_output` and `lfo_set_tempo`.
+pub const PARAM_WAVE: u32 = 0;
+pub const PARAM_RATE_HZ: u32 = 1;
+pub const PARAM_SYNC: u32 = 2;
+pub const PARAM_DIVISION: u32 = 3;
+pub const PARAM_RETRIGGER: u32 = 4;
+pub const PARAM_START_PHASE: u32 = 5;
+pub const PARAM_MIN: u32 = 6;
+pub const PARAM_MAX: u32 = 7;
+pub const PARAM_DECIMATION: u32 = 8;
+pub const PARAM_BPM: u32 = 9;
+
+pub const SINE: u32 = 0;
+pub const TRIANGLE: u32 = 1;
+pub const SAW: u32 = 2;
+pub const SQUARE: u32 = 3;
+/// A new random level every cycle, held.
+pub const SAMPLE_HOLD: u32 = 4;
+/// A new random level every cycle, glided to along a raised cosine.
+pub const SMOOTH_RANDOM: u32 = 5;
+
+/// Synced cycle lengths in quarter notes, indexed as the stereo delay's divisions (1/1, 1/2,
+/// 1/2 dotted, 1/2 triplet, then the same for 1/4, 1/8 and 1/16, then 1/32), plus 2 and 4 bars
+/// at the end.
+const DIVISIONS: [f32; 16] = [
+    4.0,
+    2.0,
+    3.0,
+    4.0 / 3.0,
+    1.0,
+    1.5,
+    2.0 / 3.0,
+    0.5,
+    0.75,
+    1.0 / 3.0,
+    0.25,
+    0.375,
+    1.0 / 6.0,
+    0.125,
+    8.0,
+    16.0,
+];
+
+/// Low-frequency oscillator rendering a control signal: a classic shape, sample and hold or
+/// smooth random, scaled to `min..max` and the same on every channel, so it can be wired
+/// into any parameter input. The rate is free in Hz or synced to a division of the tempo.
+/// At a decimation above 1 the value is only recomputed (and held) every that many samples,
+/// for cheaper control-rate modulation. With retrigger on, note-ons from the event ring
+/// restart the cycle at the start phase, to the sample.
+pub struct Lfo {
+    notes: EventRing,
+    wave: u32,
+    rate_hz: f32,
+    sync: bool,
+    division: u32,
+    bpm: f32,
+    retrigger: bool,
+    start_phase: f32,
+    min: f32,
+    max: f32,
+    decimation: u32,
+    /// Samples until the value is next recomputed.
+    countdown: u32,
+    held: f32,
+    phase: f32,
+    /// Phase advance per sample.
+    inc: f32,
+    /// Random levels the current cycle starts from and heads to.
+    random_from: f32,
+    random_to: f32,
+    rng: u32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Lfo {
+    /// Uniform in [-1, 1) from a xorshift32.
+    #[inline]
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 7) as f32 / (1u32 << 24) as f32 - 1.0
+    }
+
+    fn update_rate(&mut self) {
+        let hz = if self.sync {
+            let beats = DIVISIONS[self.division as usize];
+            self.bpm / 60.0 / beats
+        } else {
+            self.rate_hz
+        };
+        self.inc = hz / self.sample_rate_hz;
+    }
+
+    /// Starts a new random segment (sample and hold and smooth random only need it).
+    fn next_random(&mut self) {
+        self.random_from = self.random_to;
+        self.random_to = self.random();
+    }
+
+    fn restart(&mut self) {
+        self.phase = self.start_phase;
+        self.next_random();
+        self.countdown = 0;
+    }
+
+    /// Raw value in -1..1 at the current phase.
+    fn value(&self) -> f32 {
+        match self.wave {
+            TRIANGLE => Waveform::Triangle.value(self.phase),
+            SAW => Waveform::Saw.value(self.phase),
+            SQUARE => Waveform::Square.value(self.phase),
+            SAMPLE_HOLD => self.random_to,
+            SMOOTH_RANDOM => {
+                let t = 0.5 - 0.5 * (PI * self.phase).cos();
+                self.random_from + (self.random_to - self.random_from) * t
+            }
+            _ => Waveform::Sine.value(self.phase),
+        }
+    }
+
+    fn handle(&mut self, e: NoteEvent) {
+        if self.retrigger && e.kind == NOTE_ON && e.value > 0.0 {
+            self.restart();
+        }
+    }
+
+    fn render(&mut self, output: &mut [f32], channels: usize) {
+        for out in output.chunks_exact_mut(channels) {
+            if self.countdown == 0 {
+                let v = self.value();
+                self.held = self.min + (v + 1.0) * 0.5 * (self.max - self.min);
+                self.countdown = self.decimation;
+                self.phase += self.inc * self.decimation as f32;
+                while self.phase >= 1.0 {
+                    self.phase -= 1.0;
+                    self.next_random();
+                }
+            }
+            self.countdown -= 1;
+            out.fill(self.held);
+        }
+    }
+}
+
+impl Node for Lfo {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Wave` 0 sine, 1 triangle, 2 saw, 3 square, 4 sample and hold, 5 smooth random. `Rate`
+    /// is used with `Sync` off; otherwise `Division` indexes the musical divisions (0 1/1,
+    /// 1 1/2, 2 1/2 dotted, 3 1/2 triplet, 4 1/4, 5 1/4 dotted, 6 1/4 triplet, 7 1/8, 8 1/8
+    /// dotted, 9 1/8 triplet, 10 1/16, 11 1/16 dotted, 12 1/16 triplet, 13 1/32, 14 2 bars,
+    /// 15 4 bars) at `Tempo`. `Retrigger` restarts the cycle at `Start phase` on every
+    /// note-on. The wave's bottom maps to `Min` and its top to `Max` (either way round); the
+    /// value is recomputed every `Decimation` samples (1 for audio rate).
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Wave", "", 0.0, SMOOTH_RANDOM as f32, SINE as f32),
+        ParamDescriptor::log("Rate", "Hz", 0.01, 100.0, 1.0),
+        ParamDescriptor::toggle("Sync", false),
+        ParamDescriptor::stepped("Division", "", 0.0, (DIVISIONS.len() - 1) as f32, 4.0),
+        ParamDescriptor::toggle("Retrigger", false),
+        ParamDescriptor::linear("Start phase", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Min", "", -100_000.0, 100_000.0, -1.0),
+        ParamDescriptor::linear("Max", "", -100_000.0, 100_000.0, 1.0),
+        ParamDescriptor::stepped("Decimation", "", 1.0, MAX_DECIMATION as f32, 1.0),
+        ParamDescriptor::linear("Tempo", "bpm", 20.0, 300.0, 120.0),
+    ];
+    const INPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut l = Lfo {
+            notes: EventRing::new(),
+            wave: SINE,
+            rate_hz: 1.0,
+            sync: false,
+            division: 4,
+            bpm: 120.0,
+            retrigger: false,
+            start_phase: 0.0,
+            min: -1.0,
+            max: 1.0,
+            decimation: 1,
+            countdown: 0,
+            held: 0.0,
+            phase: 0.0,
+            inc: 0.0,
+            random_from: 0.0,
+            random_to: 0.0,
+            rng: 0x9e37_79b9,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        l.update_rate();
+        l.next_random();
+        l
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        let toggle = clamp(value, 0.0, 1.0) >= 0.5;
+        match index {
+            PARAM_WAVE => self.wave = clamp(value, 0.0, SMOOTH_RANDOM as f32).round() as u32,
+            PARAM_RATE_HZ => self.rate_hz = clamp(value, 0.01, 100.0),
+            PARAM_SYNC => self.sync = toggle,
+            PARAM_DIVISION => {
+                self.division = clamp(value, 0.0, (DIVISIONS.len() - 1) as f32).round() as u32;
+            }
+            PARAM_RETRIGGER => self.retrigger = toggle,
+            PARAM_START_PHASE => self.start_phase = clamp(value, 0.0, 0.999_999),
+            PARAM_MIN => self.min = clamp(value, -100_000.0, 100_000.0),
+            PARAM_MAX => self.max = clamp(value, -100_000.0, 100_000.0),
+            PARAM_DECIMATION => {
+                self.decimation = clamp(value, 1.0, MAX_DECIMATION as f32).round() as u32;
+                self.countdown = self.countdown.min(self.decimation);
+            }
+            PARAM_BPM => self.bpm = clamp(value, 20.0, 300.0),
+            _ => return,
+        }
+        self.update_rate();
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_WAVE => self.wave as f32,
+            PARAM_RATE_HZ => self.rate_hz,
+            PARAM_SYNC => u32::from(self.sync) as f32,
+            PARAM_DIVISION => self.division as f32,
+            PARAM_RETRIGGER => u32::from(self.retrigger) as f32,
+            PARAM_START_PHASE => self.start_phase,
+            PARAM_MIN => self.min,
+            PARAM_MAX => self.max,
+            PARAM_DECIMATION => self.decimation as f32,
+            PARAM_BPM => self.bpm,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn note_events(&mut self) -> Option<&mut EventRing> {
+        Some(&mut self.notes)
+    }
+
+    fn note(&mut self, event: NoteEvent) {
+        self.handle(event);
+    }
+
+    /// Renders the same value on every channel.
+    fn process(&mut self, _input: &[f32], output: &mut [f32], channels: usize) {
+        self.render(output, channels);
+    }
+
+    /// Restarts the cycle at `Start phase` with the random sequence from its seed, as a fresh
+    /// node would.
+    fn reset(&mut self) {
+        self.rng = 0x9e37_79b9;
+        self.random_to = 0.0;
+        self.held = 0.0;
+        self.restart();
+    }
+}
+
+node_abi::export_node!(Lfo {
+    abi_version: lfo_abi_version,
+    new: lfo_new,
+    free: lfo_free,
+    param_count: lfo_param_count,
+    param_descriptors: lfo_param_descriptors,
+    set_param: lfo_set_param,
+    get_param: lfo_get_param,
+    param_events: lfo_param_events,
+    process: lfo_process,
+    process_offline: lfo_process_offline,
+    latency_samples: lfo_latency_samples,
+    io_layout: lfo_get_io_layout,
+    process_io: lfo_process_io,
+    reset: lfo_reset,
+    state_size: lfo_state_size,
+    save_state: lfo_save_state,
+    load_state: lfo_load_state,
+    preset_size: lfo_preset_size,
+    save_preset: lfo_save_preset,
+    load_preset: lfo_load_preset,
+    last_error_code: lfo_last_error_code,
+    last_error_message: lfo_last_error_message_ptr,
+});
+
+node_abi::test_params!(Lfo);
+
+dsp_core::test_block_sizes!(lfo_new, lfo_process(input, output), lfo_free);
+
+node_abi::test_io_block!(Lfo);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Lfo as LfoNode);
+
+/// The note-event ring inside the node, for the worklet to write into (layout in
+/// `dsp_core::events`); note-ons retrigger. Stays put for the node's lifetime.
+#[no_mangle]
+pub extern "C" fn lfo_events(handle: Handle) -> *mut EventRing {
+    guard(handle, |l: &mut Lfo| &mut l.notes as *mut EventRing).unwrap_or(core::ptr::null_mut())
+}
+
+/// `Wave` through `Start phase` in one call, as `lfo_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn lfo_set_params(
+    handle: Handle,
+    wave: u32,
+    rate_hz: f32,
+    sync: u32,
+    division: u32,
+    retrigger: u32,
+    start_phase: f32,
+) {
+    guard(handle, |l: &mut Lfo| {
+        l.set_param(PARAM_WAVE, wave as f32);
+        l.set_param(PARAM_RATE_HZ, rate_hz);
+        l.set_param(PARAM_SYNC, sync as f32);
+        l.set_param(PARAM_DIVISION, division as f32);
+        l.set_param(PARAM_RETRIGGER, retrigger as f32);
+        l.set_param(PARAM_START_PHASE, start_phase);
+    });
+}
+
+/// `Min`, `Max` and `Decimation` in one call.
+#[no_mangle]
+pub extern "C" fn lfo_set_output(handle: Handle, min: f32, max: f32, decimation: u32) {
+    guard(handle, |l: &mut Lfo| {
+        l.set_param(PARAM_MIN, min);
+        l.set_param(PARAM_MAX, max);
+        l.set_param(PARAM_DECIMATION, decimation as f32);
+    });
+}
+
+/// Host tempo for synced rates, as `Tempo`.
+#[no_mangle]
+pub extern "C" fn lfo_set_tempo(handle: Handle, bpm: f32) {
+    guard(handle, |l: &mut Lfo| l.set_param(PARAM_BPM, bpm));
+}
+
+/// Current phase (0–1), for the UI.
+#[no_mangle]
+pub extern "C" fn lfo_phase(handle: Handle) -> f32 {
+    guard(handle, |l: &mut Lfo| l.phase).unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// An LFO at 100 Hz (480 samples a cycle) of `wave`, scaled to 0..10.
+    fn lfo(wave: u32) -> Handle {
+        let h = lfo_new(RATE);
+        lfo_set_params(h, wave, 100.0, 0, 4, 0, 0.0);
+        lfo_set_output(h, 0.0, 10.0, 1);
+        h
+    }
+
+    fn render(h: Handle, frames: usize) -> Vec<f32> {
+        render_quanta(frames, |_, y| {
+            lfo_process(h, core::ptr::null(), y.as_mut_ptr(), y.len(), 1)
+        })
+    }
+
+    /// Each classic shape runs from `Min` to `Max` over the cycle.
+    #[test]
+    fn shapes_span_min_to_max() {
+        let shape = |wave: u32, p: f32| match wave {
+            SINE => 5.0 + 5.0 * (2.0 * PI * p).sin(),
+            TRIANGLE => 10.0 - 20.0 * (p - 0.5).abs(),
+            SAW => 10.0 * p,
+            _ => {
+                if p < 0.5 {
+                    10.0
+                } else {
+                    0.0
+                }
+            }
+        };
+        for wave in [SINE, TRIANGLE, SAW, SQUARE] {
+            let h = lfo(wave);
+            let y = render(h, 960);
+            lfo_free(h);
+            // Rounding decides which side of the square's edges lands on.
+            for (i, &v) in y.iter().enumerate().filter(|&(i, _)| i % 240 != 0) {
+                let expected = shape(wave, (i % 480) as f32 / 480.0);
+                assert!((v - expected).abs() < 0.01, "wave {wave} at {i}: {v}");
+            }
+        }
+    }
+
+    /// Synced, a cycle lasts its division at the tempo: a dotted eighth at 120 bpm is 0.375 s.
+    #[test]
+    fn sync_follows_the_tempo() {
+        let h = lfo(SAW);
+        lfo_set_params(h, SAW, 100.0, 1, 8, 0, 0.0);
+        lfo_set_tempo(h, 120.0);
+        render(h, 4_480);
+        assert!((lfo_phase(h) - 4_480.0 / 18_000.0).abs() < 1e-4);
+        lfo_set_tempo(h, 60.0);
+        render(h, 4_480);
+        assert!((lfo_phase(h) - 4_480.0 / 18_000.0 * 1.5).abs() < 1e-4);
+        lfo_free(h);
+    }
+
+    /// With `Retrigger` on a note-on restarts the cycle at `Start phase` on its own sample.
+    #[test]
+    fn note_on_retriggers() {
+        for retrigger in [0, 1] {
+            let h = lfo(SAW);
+            lfo_set_params(h, SAW, 100.0, 0, 4, retrigger, 0.5);
+            let events = unsafe { &mut *lfo_events(h) };
+            assert!(events.push(NoteEvent {
+                kind: NOTE_ON,
+                note: 60,
+                value: 1.0,
+                frame: 100,
+            }));
+            let y = render(h, 128);
+            lfo_free(h);
+            // The cycle began at 0; `Start phase` only applies when it restarts.
+            let expected = if retrigger == 1 { 5.0 } else { 1000.0 / 480.0 };
+            assert!((y[100] - expected).abs() < 0.01, "{retrigger}: {}", y[100]);
+        }
+    }
+
+    /// Above 1, `Decimation` holds each value for that many samples.
+    #[test]
+    fn decimation_holds_the_value() {
+        let h = lfo(SAW);
+        lfo_set_output(h, 0.0, 10.0, 32);
+        let y = render(h, 480);
+        lfo_free(h);
+        for (i, &v) in y.iter().enumerate() {
+            let expected = 10.0 * (i / 32 * 32) as f32 / 480.0;
+            assert!((v - expected).abs() < 0.01, "{i}: {v}");
+        }
+    }
+
+    /// Sample and hold keeps one level per cycle; smooth random glides between them.
+    #[test]
+    fn random_waves_step_and_glide() {
+        let h = lfo(SAMPLE_HOLD);
+        let y = render(h, 4_800);
+        lfo_free(h);
+        let levels: Vec<f32> = y.chunks(480).map(|c| c[0]).collect();
+        for (cycle, level) in y.chunks(480).zip(&levels) {
+            assert!(cycle.iter().all(|v| v == level));
+        }
+        assert!(levels.windows(2).all(|w| w[0] != w[1]));
+        assert!(levels.iter().all(|v| (0.0..=10.0).contains(v)));
+
+        let h = lfo(SMOOTH_RANDOM);
+        let y = render(h, 4_800);
+        lfo_free(h);
+        // At most the full range over half a cycle of raised cosine.
+        let steepest = y
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs())
+            .fold(0.0, f32::max);
+        assert!(steepest < 10.0 * PI / 960.0 + 1e-4, "{steepest}");
+    }
+}
@@ -3,11 +3,25 @@ import type { AudioNodeFactory, AudioNodeInstance } from "@/types/audioRuntime";
 import type { AudioNodeServices } from "@/types/nodeModule";
 import { rmsFromAnalyser } from "@utils/audio";
 import { clamp } from "@utils/math";
+import { wasmSimdSupported } from "@utils/wasmSimd";
 import limiterProcessorUrl from "./processor.ts?worklet";
 import limiterWasmUrl from "./limiter.wasm?url";
+import limiterSimdWasmUrl from "./limiter.simd.wasm?url";
 
 type LimiterGraphNode = Extract<GraphNode, { type: "limiter" }>;
 
+export type LimiterRuntimeState = {
+  module: "loading" | "ready" | "error";
+  worklet: "none" | "ready" | "error";
+  wasm: "loading" | "ready" | "missing" | "error";
+  cpuLoad: number;
+  latencySamples: number;
+  gainReductionDb: number;
+  peakGainReductionDb: number;
+  /** Why the DSP stopped (it outputs silence from then on), or null while it's healthy. */
+  error: string | null;
+};
+
 function base64ToArrayBuffer(b64: string): ArrayBuffer {
   const binary = atob(b64);
   const bytes = new Uint8Array(binary.length);
@@ -26,8 +40,9 @@ let limiterWasmBytesPromise: Promise<ArrayBuffer | null> | null = null;
 function loadLimiterWasmBytes(): Promise<ArrayBuffer | null> {
   limiterWasmBytesPromise ??= (async () => {
     try {
-      if (limiterWasmUrl.startsWith("data:")) return dataUrlToArrayBuffer(limiterWasmUrl);
-      const res = await fetch(limiterWasmUrl);
+      const url = wasmSimdSupported() ? limiterSimdWasmUrl : limiterWasmUrl;
+      if (url.startsWith("data:")) return dataUrlToArrayBuffer(url);
+      const res = await fetch(url);
       if (!res.ok) return null;
       return await res.arrayBuffer();
     } catch {
@@ -49,6 +64,7 @@ function ensureLimiterWorkletModuleLoaded(ctx: AudioContext): Promise<void> {
 
 function createLimiterRuntime(ctx: AudioContext, _nodeId: NodeId): AudioNodeInstance<LimiterGraphNode> {
   const input = ctx.createGain();
+  const keyInput = ctx.createGain();
   const output = ctx.createGain();
 
   const meter = ctx.createAnalyser();
@@ -62,11 +78,15 @@ function createLimiterRuntime(ctx: AudioContext, _nodeId: NodeId): AudioNodeInst
   let disposed = false;
   let initPromise: Promise<void> | null = null;
 
-  const debug = {
-    module: "loading" as "loading" | "ready" | "error",
-    worklet: "none" as "none" | "ready" | "error",
-    wasm: "loading" as "loading" | "ready" | "missing" | "error",
+  const debug: LimiterRuntimeState = {
+    module: "loading",
+    worklet: "none",
+    wasm: "loading",
     cpuLoad: 0,
+    latencySamples: 0,
+    gainReductionDb: 0,
+    peakGainReductionDb: 0,
+    error: null,
   };
 
   const params = {
@@ -75,6 +95,14 @@ function createLimiterRuntime(ctx: AudioContext, _nodeId: NodeId): AudioNodeInst
     makeupDb: 0,
     bypass: false,
     stereoLink: true,
+    lookaheadMs: 0,
+    truePeak: false,
+    autoRelease: false,
+    kneeDb: 0,
+    oversample: 1 as 1 | 2 | 4,
+    mix: 1,
+    attackMs: 0.01,
+    releaseShape: "exponential" as LimiterGraphNode["state"]["releaseShape"],
   };
 
   input.connect(output);
@@ -95,12 +123,18 @@ function createLimiterRuntime(ctx: AudioContext, _nodeId: NodeId): AudioNodeInst
     } catch {
       // ignore
     }
+    try {
+      keyInput.disconnect();
+    } catch {
+      // ignore
+    }
     try {
       worklet?.disconnect();
     } catch {
       // ignore
     }
     input.connect(node);
+    keyInput.connect(node, 0, 1);
     node.connect(output);
     worklet = node;
     debug.worklet = "ready";
@@ -113,15 +147,31 @@ function createLimiterRuntime(ctx: AudioContext, _nodeId: NodeId): AudioNodeInst
         if (typeof data.wasm === "string") debug.wasm = data.wasm;
         return;
       }
+      if (data.type === "error") {
+        debug.error = typeof data.message === "string" && data.message ? data.message : "DSP failed";
+        console.error(`[limiter] DSP failed (code ${data.code}): ${debug.error}`);
+        return;
+      }
       if (data.type === "cpu" && typeof data.load === "number") {
         debug.cpuLoad = Math.max(0, data.load);
+        return;
+      }
+      if (data.type === "latency" && typeof data.samples === "number") {
+        debug.latencySamples = Math.max(0, data.samples);
+        return;
+      }
+      if (data.type === "meter") {
+        if (typeof data.gainReductionDb === "number")
+          debug.gainReductionDb = Math.max(0, data.gainReductionDb);
+        if (typeof data.peakGainReductionDb === "number")
+          debug.peakGainReductionDb = Math.max(0, data.peakGainReductionDb);
       }
     };
   };
 
   const buildWorklet = (channelCount: 1 | 2) => {
     const node = new AudioWorkletNode(ctx, "limiter", {
-      numberOfInputs: 1,
+      numberOfInputs: 2,
       numberOfOutputs: 1,
       channelCountMode: "explicit",
       channelCount,
@@ -141,6 +191,14 @@ function createLimiterRuntime(ctx: AudioContext, _nodeId: NodeId): AudioNodeInst
         makeupDb: params.makeupDb,
         bypass: params.bypass,
         stereoLink: params.stereoLink,
+        lookaheadMs: params.lookaheadMs,
+        truePeak: params.truePeak,
+        autoRelease: params.autoRelease,
+        kneeDb: params.kneeDb,
+        oversample: params.oversample,
+        mix: params.mix,
+        attackMs: params.attackMs,
+        releaseShape: params.releaseShape,
       },
     });
   };
@@ -160,7 +218,7 @@ function createLimiterRuntime(ctx: AudioContext, _nodeId: NodeId): AudioNodeInst
           }
           debug.wasm = "ready";
           const node = new AudioWorkletNode(ctx, "limiter", {
-            numberOfInputs: 1,
+            numberOfInputs: 2,
             numberOfOutputs: 1,
             channelCountMode: "explicit",
             channelCount: desiredChannels,
@@ -194,6 +252,15 @@ function createLimiterRuntime(ctx: AudioContext, _nodeId: NodeId): AudioNodeInst
       params.makeupDb = clamp(state.makeupDb, -24, 24);
       params.bypass = !!state.bypass;
       params.stereoLink = nextChannels === 2 ? !!state.stereoLink : false;
+      params.lookaheadMs = clamp(state.lookaheadMs ?? 0, 0, 10);
+      params.truePeak = !!state.truePeak;
+      params.autoRelease = !!state.autoRelease;
+      params.kneeDb = clamp(state.kneeDb ?? 0, 0, 12);
+      params.oversample =
+        state.oversample === 4 ? 4 : state.oversample === 2 ? 2 : 1;
+      params.mix = clamp(state.mix ?? 1, 0, 1);
+      params.attackMs = clamp(state.attackMs ?? 0.01, 0.01, 10);
+      params.releaseShape = state.releaseShape ?? "exponential";
 
       if (!worklet) {
         ensureWorkletReady();
@@ -212,7 +279,7 @@ function createLimiterRuntime(ctx: AudioContext, _nodeId: NodeId): AudioNodeInst
             }
             debug.wasm = "ready";
             const node = new AudioWorkletNode(ctx, "limiter", {
-              numberOfInputs: 1,
+              numberOfInputs: 2,
               numberOfOutputs: 1,
               channelCountMode: "explicit",
               channelCount: nextChannels,
@@ -234,6 +301,7 @@ function createLimiterRuntime(ctx: AudioContext, _nodeId: NodeId): AudioNodeInst
     },
     getAudioInputs: (portId) => {
       if (portId === "audio_in") return [input];
+      if (portId === "sidechain_in") return [keyInput];
       return [];
     },
     getAudioOutputs: (portId) => {
@@ -262,6 +330,11 @@ function createLimiterRuntime(ctx: AudioContext, _nodeId: NodeId): AudioNodeInst
       } catch {
         // ignore
       }
+      try {
+        keyInput.disconnect();
+      } catch {
+        // ignore
+      }
       worklet = null;
     },
     getLevel: () => rmsFromAnalyser(meter, meterBuffer),
@@ -0,0 +1,155 @@
+use alloc::vec::Vec;
+
+/// Sample access for one processing call, so the limiter core doesn't care about buffer layout.
+pub trait AudioBlock {
+    fn channels(&self) -> usize;
+    fn frames(&self) -> usize;
+    fn input(&self, ch: usize, i: usize) -> f32;
+    fn set_output(&mut self, ch: usize, i: usize, v: f32);
+    fn output(&self, ch: usize, i: usize) -> f32;
+
+    /// External detector signal for `ch`, if the block carries one.
+    #[inline]
+    fn key(&self, _ch: usize, _i: usize) -> Option<f32> {
+        None
+    }
+}
+
+pub struct Interleaved<'a> {
+    pub input: &'a [f32],
+    pub output: &'a mut [f32],
+    pub channels: usize,
+}
+
+impl AudioBlock for Interleaved<'_> {
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn frames(&self) -> usize {
+        self.input.len() / self.channels
+    }
+
+    #[inline]
+    fn input(&self, ch: usize, i: usize) -> f32 {
+        self.input[i * self.channels + ch]
+    }
+
+    #[inline]
+    fn set_output(&mut self, ch: usize, i: usize, v: f32) {
+        self.output[i * self.channels + ch] = v;
+    }
+
+    #[inline]
+    fn output(&self, ch: usize, i: usize) -> f32 {
+        self.output[i * self.channels + ch]
+    }
+}
+
+/// Up to two non-interleaved channels; `channels` says how many of the slots are in use.
+pub struct Planar<'a> {
+    pub inputs: [&'a [f32]; 2],
+    pub outputs: [&'a mut [f32]; 2],
+    pub channels: usize,
+}
+
+impl AudioBlock for Planar<'_> {
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn frames(&self) -> usize {
+        self.inputs[0].len()
+    }
+
+    #[inline]
+    fn input(&self, ch: usize, i: usize) -> f32 {
+        self.inputs[ch][i]
+    }
+
+    #[inline]
+    fn set_output(&mut self, ch: usize, i: usize, v: f32) {
+        self.outputs[ch][i] = v;
+    }
+
+    #[inline]
+    fn output(&self, ch: usize, i: usize) -> f32 {
+        self.outputs[ch][i]
+    }
+}
+
+/// Interleaved program plus an interleaved key signal driving the detector.
+pub struct Keyed<'a> {
+    pub program: Interleaved<'a>,
+    pub key: &'a [f32],
+    pub key_channels: usize,
+}
+
+impl AudioBlock for Keyed<'_> {
+    fn channels(&self) -> usize {
+        self.program.channels()
+    }
+
+    fn frames(&self) -> usize {
+        self.program.frames()
+    }
+
+    #[inline]
+    fn input(&self, ch: usize, i: usize) -> f32 {
+        self.program.input(ch, i)
+    }
+
+    #[inline]
+    fn set_output(&mut self, ch: usize, i: usize, v: f32) {
+        self.program.set_output(ch, i, v);
+    }
+
+    #[inline]
+    fn output(&self, ch: usize, i: usize) -> f32 {
+        self.program.output(ch, i)
+    }
+
+    #[inline]
+    fn key(&self, ch: usize, i: usize) -> Option<f32> {
+        Some(self.key[i * self.key_channels + ch % self.key_channels])
+    }
+}
+
+/// Owned per-channel buffers (the oversampling scratch), optionally with a key per channel.
+pub struct Scratch<'a> {
+    pub inputs: &'a [Vec<f32>],
+    pub outputs: &'a mut [Vec<f32>],
+    pub keys: Option<&'a [Vec<f32>]>,
+    pub channels: usize,
+    pub frames: usize,
+}
+
+impl AudioBlock for Scratch<'_> {
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn frames(&self) -> usize {
+        self.frames
+    }
+
+    #[inline]
+    fn input(&self, ch: usize, i: usize) -> f32 {
+        self.inputs[ch][i]
+    }
+
+    #[inline]
+    fn set_output(&mut self, ch: usize, i: usize, v: f32) {
+        self.outputs[ch][i] = v;
+    }
+
+    #[inline]
+    fn output(&self, ch: usize, i: usize) -> f32 {
+        self.outputs[ch][i]
+    }
+
+    #[inline]
+    fn key(&self, ch: usize, i: usize) -> Option<f32> {
+        self.keys.map(|k| k[ch][i])
+    }
+}
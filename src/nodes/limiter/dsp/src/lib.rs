@@ -1,182 +1,1212 @@
+// The exports are called from the worklet with pointers it got from us; null is the only
+// invalid value we can detect.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod block;
+mod lookahead;
+mod oversample;
+
+use alloc::vec::Vec;
+use dsp_core::envelope::one_pole_coeff;
+use dsp_core::gain::{db_to_lin, lin_to_db};
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+use dsp_core::param::clamp;
+use dsp_core::simd;
+use dsp_core::smooth::{Crossfade, Smoothed};
+use dsp_core::state::{State, StateReader, StateWriter};
+use dsp_core::true_peak::{TruePeakCoeffs, TruePeakDetector, TRUE_PEAK_DELAY};
+use node_abi::{
+    guard, split_at_events, Handle, LastError, Node, ParamDescriptor, ParamRing, FLAG_NO_AUTOMATION,
+};
+
+use block::{AudioBlock, Interleaved, Keyed, Planar, Scratch};
+use lookahead::{
+    max_lookahead_samples, DelayLine, GainStage, Release, ReleaseShape, MAX_LOOKAHEAD_MS,
+};
+use oversample::{Oversampler, CHUNK_FRAMES, MAX_OVERSAMPLE, RESAMPLER_LATENCY};
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Limiter::PARAMS`, in the order of `limiter_set_params`' arguments. Booleans
+// are on at 0.5 and up; `PARAM_OVERSAMPLE` takes 1, 2 or 4 (3 reads as 2) and
+// `PARAM_RELEASE_SHAPE` 0–2.
+pub const PARAM_CEILING_DB: u32 = 0;
+pub const PARAM_RELEASE_MS: u32 = 1;
+pub const PARAM_MAKEUP_DB: u32 = 2;
+pub const PARAM_BYPASS: u32 = 3;
+pub const PARAM_LINK: u32 = 4;
+pub const PARAM_LOOKAHEAD_MS: u32 = 5;
+pub const PARAM_TRUE_PEAK: u32 = 6;
+pub const PARAM_AUTO_RELEASE: u32 = 7;
+pub const PARAM_KNEE_DB: u32 = 8;
+pub const PARAM_OVERSAMPLE: u32 = 9;
+pub const PARAM_MIX: u32 = 10;
+pub const PARAM_ATTACK_MS: u32 = 11;
+pub const PARAM_RELEASE_SHAPE: u32 = 12;
+
+/// Per-channel detector, gain envelope (unlinked mode) and look-ahead delay.
+struct Channel {
+    gain: GainStage,
+    delay: DelayLine,
+    tp: TruePeakDetector,
+}
+
+impl Channel {
+    fn new(max_lookahead: usize) -> Self {
+        Self {
+            gain: GainStage::new(max_lookahead),
+            delay: DelayLine::new(max_delay(max_lookahead)),
+            tp: TruePeakDetector::new(),
+        }
+    }
+}
+
+impl State for Channel {
+    fn save(&self, w: &mut StateWriter) {
+        self.gain.save(w);
+        self.delay.save(w);
+        self.tp.save(w);
+    }
+
+    fn load(&mut self, r: &mut StateReader) {
+        self.gain.load(r);
+        self.delay.load(r);
+        self.tp.load(r);
+    }
+}
+
+/// Look-ahead capacity in core-rate samples, covering the highest oversampling factor.
+fn lookahead_capacity(sample_rate_hz: f32) -> usize {
+    max_lookahead_samples(sample_rate_hz) * MAX_OVERSAMPLE
+}
+
+/// The delay line also covers the true-peak interpolator, rounded up to a whole host sample.
+fn max_delay(max_lookahead: usize) -> usize {
+    max_lookahead + TRUE_PEAK_DELAY + MAX_OVERSAMPLE
+}
+
+/// Longest `latency_samples()` at this host rate, for the dry path.
+fn max_latency(sample_rate_hz: f32) -> usize {
+    max_lookahead_samples(sample_rate_hz) + TRUE_PEAK_DELAY + RESAMPLER_LATENCY
+}
+
 #[repr(C)]
 pub struct Limiter {
-    ceiling_lin: f32,
-    makeup_lin: f32,
-    release_coeff: f32,
+    ceiling: Smoothed,
+    knee_db: f32,
+    makeup: Smoothed,
+    attack_ms: f32,
+    release_ms: f32,
+    release: Release,
     bypass: u32,
-    stereo_link: u32,
-    gain_linked: f32,
-    gain_ch0: f32,
-    gain_ch1: f32,
+    bypass_fade: Crossfade,
+    mix: Smoothed,
+    link: u32,
+    lookahead_ms: f32,
+    lookahead_samples: u32,
+    max_lookahead: usize,
+    true_peak: u32,
+    gain_linked: GainStage,
+    channels: Vec<Channel>,
+    tp_coeffs: TruePeakCoeffs,
+    os: Oversampler,
+    /// Host-rate input delayed by the reported latency, so bypass lines up with the wet path.
+    dry: Vec<DelayLine>,
+    meter_gain: f32,
+    meter_peak_gain: f32,
+    events: ParamRing,
+    error: LastError,
     sample_rate_hz: f32,
 }
 
-fn clamp(v: f32, min: f32, max: f32) -> f32 {
-    if !v.is_finite() {
-        return min;
+fn gain_reduction_db(gain: f32) -> f32 {
+    if gain >= 1.0 {
+        0.0
+    } else {
+        -20.0 * gain.max(1e-6).log10()
     }
-    if v < min {
-        min
-    } else if v > max {
-        max
+}
+
+/// In auto mode the release knob sets the centre of a fast (x0.25) / slow (x4) pair; the slow
+/// stage only dips when limiting lasts longer than about one release time. The coefficients
+/// are for the gain computer's rate, `sample_rate_hz` oversampled `factor` times.
+fn release_for_ms(
+    attack_ms: f32,
+    release_ms: f32,
+    auto: bool,
+    sample_rate_hz: f32,
+    factor: usize,
+) -> Release {
+    let rate = sample_rate_hz * factor as f32;
+    // Attack times no longer than one host-rate sample snap instantly, however far the gain
+    // computer is oversampled, so the 0.01 ms minimum behaves like the old hard attack.
+    let attack_ms = clamp(attack_ms, 0.01, 10.0);
+    let attack = if attack_ms / 1000.0 * sample_rate_hz <= 1.0 {
+        0.0
     } else {
-        v
+        one_pole_coeff(attack_ms, rate)
+    };
+    let r_ms = clamp(release_ms, 0.1, 5000.0);
+    if !auto {
+        return Release {
+            attack,
+            coeff: one_pole_coeff(r_ms, rate),
+            slow_attack: 0.0,
+            slow_coeff: 0.0,
+            auto: false,
+            shape: ReleaseShape::Exponential,
+        };
+    }
+    Release {
+        attack,
+        coeff: one_pole_coeff(r_ms * 0.25, rate),
+        slow_attack: one_pole_coeff(r_ms, rate),
+        slow_coeff: one_pole_coeff(r_ms * 4.0, rate),
+        auto: true,
+        shape: ReleaseShape::Exponential,
     }
 }
 
-fn db_to_lin(db: f32) -> f32 {
-    (10.0_f32).powf(db / 20.0)
+/// Static gain curve: a hard ceiling, optionally softened by a quadratic knee `knee_db` wide
+/// centred on the ceiling (still never exceeding it).
+#[derive(Clone, Copy)]
+struct GainComputer {
+    ceiling: f32,
+    knee_db: f32,
+    knee_ratio: f32,
+    knee_start: f32,
 }
 
-fn release_coeff_for_ms(release_ms: f32, sample_rate_hz: f32) -> f32 {
-    let r_ms = clamp(release_ms, 0.1, 5000.0);
-    let r_sec = r_ms / 1000.0;
-    let n = (r_sec * sample_rate_hz).max(1.0);
-    (-1.0 / n).exp()
+impl GainComputer {
+    fn new(ceiling: f32, knee_db: f32) -> Self {
+        let knee_ratio = if knee_db > 0.0 {
+            db_to_lin(-0.5 * knee_db)
+        } else {
+            1.0
+        };
+        Self {
+            ceiling,
+            knee_db,
+            knee_ratio,
+            knee_start: ceiling * knee_ratio,
+        }
+    }
+
+    #[inline]
+    fn set_ceiling(&mut self, ceiling: f32) {
+        self.ceiling = ceiling;
+        self.knee_start = ceiling * self.knee_ratio;
+    }
+
+    #[inline]
+    fn target(&self, peak: f32) -> f32 {
+        if peak <= self.knee_start {
+            return 1.0;
+        }
+        if self.knee_db <= 0.0 {
+            return self.ceiling / peak;
+        }
+        let over_db = 20.0 * (peak / self.ceiling).log10();
+        let half = 0.5 * self.knee_db;
+        let reduction_db = if over_db >= half {
+            over_db
+        } else {
+            let t = over_db + half;
+            t * t / (2.0 * self.knee_db)
+        };
+        db_to_lin(-reduction_db)
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn limiter_new(sample_rate_hz: f32) -> *mut Limiter {
-    let mut l = Limiter {
-        ceiling_lin: db_to_lin(-0.3),
-        makeup_lin: 1.0,
-        release_coeff: release_coeff_for_ms(120.0, sample_rate_hz),
-        bypass: 0,
-        stereo_link: 1,
-        gain_linked: 1.0,
-        gain_ch0: 1.0,
-        gain_ch1: 1.0,
-        sample_rate_hz,
-    };
-    l.release_coeff = release_coeff_for_ms(120.0, l.sample_rate_hz);
-    Box::into_raw(Box::new(l))
+impl Limiter {
+    fn set_detection(&mut self, lookahead_samples: u32, true_peak: u32) {
+        if lookahead_samples == self.lookahead_samples && true_peak == self.true_peak {
+            return;
+        }
+        self.lookahead_samples = lookahead_samples;
+        self.true_peak = true_peak;
+        self.apply_lookahead();
+        self.gain_linked.clear();
+        for ch in &mut self.channels {
+            ch.gain.clear();
+            ch.delay.clear();
+            ch.tp.clear();
+        }
+    }
+
+    /// Rate the gain computer runs at (host rate times the oversampling factor).
+    fn core_rate(&self) -> f32 {
+        self.sample_rate_hz * self.os.factor() as f32
+    }
+
+    fn lookahead_samples_for(&self, lookahead_ms: f32) -> u32 {
+        let lookahead_s = clamp(lookahead_ms, 0.0, MAX_LOOKAHEAD_MS) / 1000.0;
+        let max = max_lookahead_samples(self.sample_rate_hz) as u32;
+        ((lookahead_s * self.sample_rate_hz).round() as u32).min(max)
+    }
+
+    /// True-peak interpolator delay in host samples (rounded up at the core rate).
+    fn true_peak_delay(&self) -> usize {
+        if self.true_peak == 0 {
+            return 0;
+        }
+        TRUE_PEAK_DELAY.div_ceil(self.os.factor())
+    }
+
+    /// Pushes the host-rate look-ahead into the core-rate windows and delay lines.
+    fn apply_lookahead(&mut self) {
+        let f = self.os.factor();
+        let n = self.lookahead_samples as usize * f;
+        let delay = (self.lookahead_samples as usize + self.true_peak_delay()) * f;
+        self.gain_linked.set_lookahead(n);
+        for ch in &mut self.channels {
+            ch.gain.set_lookahead(n);
+            ch.delay.set_delay(delay);
+        }
+        let latency = self.latency_samples() as usize;
+        for dry in &mut self.dry {
+            dry.set_delay(latency);
+        }
+    }
+
+    /// Retimes everything for the current host rate and oversampling factor without resetting
+    /// the gain envelopes.
+    fn retime(&mut self) {
+        let rate = self.core_rate();
+        self.update_release();
+        self.ceiling.set_sample_rate(rate);
+        self.makeup.set_sample_rate(rate);
+        self.bypass_fade.set_sample_rate(self.sample_rate_hz);
+        self.mix.set_sample_rate(self.sample_rate_hz);
+        for dry in &mut self.dry {
+            dry.grow(max_latency(self.sample_rate_hz));
+        }
+
+        let max_lookahead = lookahead_capacity(self.sample_rate_hz);
+        if max_lookahead > self.max_lookahead {
+            self.max_lookahead = max_lookahead;
+            self.gain_linked.grow(max_lookahead);
+            for ch in &mut self.channels {
+                ch.gain.grow(max_lookahead);
+                ch.delay.grow(max_delay(max_lookahead));
+            }
+        }
+
+        self.lookahead_samples = self.lookahead_samples_for(self.lookahead_ms);
+        self.apply_lookahead();
+    }
+
+    /// Recomputes the release from the attack and release times, keeping its mode and shape.
+    fn update_release(&mut self) {
+        self.release = Release {
+            shape: self.release.shape,
+            ..release_for_ms(
+                self.attack_ms,
+                self.release_ms,
+                self.release.auto,
+                self.sample_rate_hz,
+                self.os.factor(),
+            )
+        };
+    }
+
+    fn set_sample_rate(&mut self, sample_rate_hz: f32) {
+        self.sample_rate_hz = sample_rate_hz;
+        self.retime();
+    }
+
+    fn set_oversample(&mut self, factor: usize) {
+        let before = self.os.factor();
+        self.os.set_factor(factor);
+        if self.os.factor() != before {
+            self.retime();
+            for ch in &mut self.channels {
+                ch.delay.clear();
+                ch.tp.clear();
+            }
+        }
+    }
+
+    fn latency_samples(&self) -> u32 {
+        let resampler = if self.os.factor() > 1 {
+            RESAMPLER_LATENCY
+        } else {
+            0
+        };
+        (self.lookahead_samples as usize + self.true_peak_delay() + resampler) as u32
+    }
+
+    /// The wet path keeps running while bypassed so it is warm when the fade comes back.
+    fn process<B: AudioBlock>(&mut self, block: &mut B) {
+        let min_gain = if self.os.factor() > 1 {
+            self.process_oversampled(block)
+        } else {
+            self.process_core(block)
+        };
+        self.mix_dry(block);
+        let bypassed = self.bypass_fade.is_second();
+        self.note_block_gain(if bypassed { 1.0 } else { min_gain });
+    }
+
+    /// Runs the dry delay, blends it under the processed output by `mix`, then crossfades
+    /// towards it while bypass is engaged or fading.
+    fn mix_dry<B: AudioBlock>(&mut self, block: &mut B) {
+        let channels = block.channels().min(MAX_CHANNELS);
+        let mut dry_frame = [0.0_f32; MAX_CHANNELS];
+        let mut frame = [0.0_f32; MAX_CHANNELS];
+        for i in 0..block.frames() {
+            let (wet, dry) = self.bypass_fade.next_gains();
+            let mix = self.mix.next_value();
+            for (ch, d) in dry_frame.iter_mut().enumerate().take(channels) {
+                *d = self.dry[ch].process(block.input(ch, i));
+            }
+            if dry != 0.0 || mix != 1.0 {
+                for (ch, v) in frame.iter_mut().enumerate().take(channels) {
+                    *v = block.output(ch, i);
+                }
+                // (processed * mix + d * (1 - mix)) * wet + d * dry, as one scale and one mix.
+                simd::scale(&mut frame[..channels], mix * wet);
+                simd::mix_into(
+                    &mut frame[..channels],
+                    &dry_frame[..channels],
+                    (1.0 - mix) * wet + dry,
+                );
+                for (ch, v) in frame.iter().enumerate().take(channels) {
+                    block.set_output(ch, i, *v);
+                }
+            }
+        }
+    }
+
+    fn process_oversampled<B: AudioBlock>(&mut self, block: &mut B) -> f32 {
+        let mut os = core::mem::take(&mut self.os);
+        let f = os.factor();
+        let frames = block.frames();
+        let channels = block.channels().min(MAX_CHANNELS);
+        let keyed = frames > 0 && block.key(0, 0).is_some();
+        let mut min_gain = 1.0_f32;
+        let mut start = 0;
+        while start < frames {
+            let n = (frames - start).min(CHUNK_FRAMES);
+            os.upsample(block, start, n);
+            let g = self.process_core(&mut Scratch {
+                inputs: &os.input,
+                outputs: &mut os.output,
+                keys: if keyed { Some(&os.key) } else { None },
+                channels,
+                frames: n * f,
+            });
+            min_gain = min_gain.min(g);
+            os.downsample(block, start, n);
+            start += n;
+        }
+        self.os = os;
+        min_gain
+    }
+
+    /// Runs the gain computer and delay lines over `block` at the core rate; returns the
+    /// lowest gain applied.
+    fn process_core<B: AudioBlock>(&mut self, block: &mut B) -> f32 {
+        let frames = block.frames();
+        let mut computer = GainComputer::new(self.ceiling.value(), self.knee_db);
+        let mut makeup = self.makeup.value();
+        let rel = self.release;
+        let mut min_gain = 1.0_f32;
+
+        let channels = block.channels().min(MAX_CHANNELS);
+        let mut frame = [0.0_f32; MAX_CHANNELS];
+
+        if self.link != 0 && channels > 1 {
+            for i in 0..frames {
+                self.advance_smoothers(&mut computer, &mut makeup);
+                let mut peak = 0.0_f32;
+                for (ch, v) in frame.iter_mut().enumerate().take(channels) {
+                    *v = block.input(ch, i) * makeup;
+                    let d = block.key(ch, i).unwrap_or(*v);
+                    peak = peak.max(self.detect(ch, d));
+                }
+                let g = self.gain_linked.process(computer.target(peak), &rel);
+                min_gain = min_gain.min(g);
+                for (ch, v) in frame.iter_mut().enumerate().take(channels) {
+                    *v = self.channels[ch].delay.process(*v);
+                }
+                simd::scale(&mut frame[..channels], g);
+                for (ch, v) in frame.iter().enumerate().take(channels) {
+                    block.set_output(ch, i, *v);
+                }
+            }
+        } else {
+            let mut gains = [1.0_f32; MAX_CHANNELS];
+            for i in 0..frames {
+                self.advance_smoothers(&mut computer, &mut makeup);
+                for ch in 0..channels {
+                    let v = block.input(ch, i) * makeup;
+                    let d = block.key(ch, i).unwrap_or(v);
+                    let peak = self.detect(ch, d);
+                    let state = &mut self.channels[ch];
+                    gains[ch] = state.gain.process(computer.target(peak), &rel);
+                    min_gain = min_gain.min(gains[ch]);
+                    frame[ch] = state.delay.process(v);
+                }
+                simd::multiply(&mut frame[..channels], &gains[..channels]);
+                for (ch, v) in frame.iter().enumerate().take(channels) {
+                    block.set_output(ch, i, *v);
+                }
+            }
+        }
+
+        min_gain
+    }
+
+    #[inline]
+    fn advance_smoothers(&mut self, computer: &mut GainComputer, makeup: &mut f32) {
+        if !self.ceiling.is_settled() {
+            computer.set_ceiling(self.ceiling.next_value());
+        }
+        if !self.makeup.is_settled() {
+            *makeup = self.makeup.next_value();
+        }
+    }
+
+    /// Per piece; `start_block` resets the block's reading.
+    fn note_block_gain(&mut self, min_gain: f32) {
+        self.meter_gain = self.meter_gain.min(min_gain);
+        self.meter_peak_gain = self.meter_peak_gain.min(min_gain);
+    }
+
+    #[inline]
+    fn detect(&mut self, ch: usize, v: f32) -> f32 {
+        if self.true_peak != 0 {
+            self.channels[ch].tp.process(v, &self.tp_coeffs)
+        } else {
+            v.abs()
+        }
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn limiter_free(ptr: *mut Limiter) {
-    if ptr.is_null() {
-        return;
+impl Node for Limiter {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Ceiling", "dB", -60.0, 0.0, -0.3),
+        ParamDescriptor::log("Release", "ms", 0.1, 5000.0, 120.0),
+        ParamDescriptor::linear("Makeup", "dB", -24.0, 24.0, 0.0),
+        ParamDescriptor::toggle("Bypass", false),
+        ParamDescriptor::toggle("Stereo link", true),
+        ParamDescriptor::linear("Look-ahead", "ms", 0.0, MAX_LOOKAHEAD_MS, 0.0)
+            .with_flags(FLAG_NO_AUTOMATION),
+        ParamDescriptor::toggle("True peak", false).with_flags(FLAG_NO_AUTOMATION),
+        ParamDescriptor::toggle("Auto release", false),
+        ParamDescriptor::linear("Knee", "dB", 0.0, 12.0, 0.0),
+        ParamDescriptor::stepped("Oversample", "x", 1.0, 4.0, 1.0).with_flags(FLAG_NO_AUTOMATION),
+        ParamDescriptor::linear("Mix", "", 0.0, 1.0, 1.0),
+        ParamDescriptor::log("Attack", "ms", 0.01, 10.0, 0.01),
+        ParamDescriptor::stepped("Release shape", "", 0.0, 2.0, 0.0),
+    ];
+    /// The side-chain key.
+    const AUX_CHANNELS: usize = MAX_CHANNELS;
+    /// Gain reduction of the last block and since the last reset, in dB.
+    const METER_COUNT: usize = 2;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let max_lookahead = lookahead_capacity(sample_rate_hz);
+        Limiter {
+            ceiling: Smoothed::new(db_to_lin(-0.3), sample_rate_hz),
+            knee_db: 0.0,
+            makeup: Smoothed::new(1.0, sample_rate_hz),
+            attack_ms: 0.01,
+            release_ms: 120.0,
+            release: release_for_ms(0.01, 120.0, false, sample_rate_hz, 1),
+            bypass: 0,
+            bypass_fade: Crossfade::new(sample_rate_hz),
+            mix: Smoothed::new(1.0, sample_rate_hz),
+            link: 1,
+            lookahead_ms: 0.0,
+            lookahead_samples: 0,
+            max_lookahead,
+            true_peak: 0,
+            gain_linked: GainStage::new(max_lookahead),
+            channels: (0..MAX_CHANNELS)
+                .map(|_| Channel::new(max_lookahead))
+                .collect(),
+            tp_coeffs: TruePeakCoeffs::new(),
+            os: Oversampler::new(),
+            dry: (0..MAX_CHANNELS)
+                .map(|_| DelayLine::new(max_latency(sample_rate_hz)))
+                .collect(),
+            meter_gain: 1.0,
+            meter_peak_gain: 1.0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        let on = clamp(value, 0.0, 1.0) >= 0.5;
+        match index {
+            PARAM_CEILING_DB => self.ceiling.set_target(db_to_lin(clamp(value, -60.0, 0.0))),
+            PARAM_RELEASE_MS => {
+                self.release_ms = clamp(value, 0.1, 5000.0);
+                self.update_release();
+            }
+            PARAM_MAKEUP_DB => self.makeup.set_target(db_to_lin(clamp(value, -24.0, 24.0))),
+            PARAM_BYPASS => {
+                self.bypass = on as u32;
+                self.bypass_fade.set_target(on);
+            }
+            PARAM_LINK => self.link = on as u32,
+            PARAM_LOOKAHEAD_MS => {
+                self.lookahead_ms = clamp(value, 0.0, MAX_LOOKAHEAD_MS);
+                let lookahead_samples = self.lookahead_samples_for(self.lookahead_ms);
+                self.set_detection(lookahead_samples, self.true_peak);
+            }
+            PARAM_TRUE_PEAK => self.set_detection(self.lookahead_samples, on as u32),
+            PARAM_AUTO_RELEASE => {
+                self.release.auto = on;
+                self.update_release();
+            }
+            PARAM_KNEE_DB => self.knee_db = clamp(value, 0.0, 12.0),
+            PARAM_OVERSAMPLE => self.set_oversample(clamp(value, 1.0, 4.0) as usize),
+            PARAM_MIX => self.mix.set_target(clamp(value, 0.0, 1.0)),
+            PARAM_ATTACK_MS => {
+                self.attack_ms = clamp(value, 0.01, 10.0);
+                self.update_release();
+            }
+            PARAM_RELEASE_SHAPE => {
+                self.release.shape = ReleaseShape::from_u32(clamp(value, 0.0, 2.0).round() as u32)
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_CEILING_DB => lin_to_db(self.ceiling.target()),
+            PARAM_RELEASE_MS => self.release_ms,
+            PARAM_MAKEUP_DB => lin_to_db(self.makeup.target()),
+            PARAM_BYPASS => self.bypass as f32,
+            PARAM_LINK => self.link as f32,
+            PARAM_LOOKAHEAD_MS => self.lookahead_ms,
+            PARAM_TRUE_PEAK => self.true_peak as f32,
+            PARAM_AUTO_RELEASE => u32::from(self.release.auto) as f32,
+            PARAM_KNEE_DB => self.knee_db,
+            PARAM_OVERSAMPLE => self.os.factor() as f32,
+            PARAM_MIX => self.mix.target(),
+            PARAM_ATTACK_MS => self.attack_ms,
+            PARAM_RELEASE_SHAPE => self.release.shape as u32 as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn start_block(&mut self) {
+        self.meter_gain = 1.0;
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process(&mut Interleaved {
+            input,
+            output,
+            channels,
+        });
+    }
+
+    /// The gain computer follows the key instead of the program. The key is not affected by
+    /// makeup gain; with fewer key channels than program channels they are reused round-robin.
+    fn process_aux(
+        &mut self,
+        input: &[f32],
+        key: &[f32],
+        key_channels: usize,
+        output: &mut [f32],
+        channels: usize,
+    ) {
+        if key.is_empty() {
+            Node::process(self, input, output, channels);
+            return;
+        }
+        self.process(&mut Keyed {
+            program: Interleaved {
+                input,
+                output,
+                channels,
+            },
+            key,
+            key_channels,
+        });
+    }
+
+    /// Delay introduced by the look-ahead buffer, the true-peak interpolator and the
+    /// oversampling filters, in host-rate samples.
+    fn latency_samples(&self) -> u32 {
+        Limiter::latency_samples(self)
+    }
+
+    fn meters(&self, out: &mut [f32]) {
+        out[0] = gain_reduction_db(self.meter_gain);
+        out[1] = gain_reduction_db(self.meter_peak_gain);
+    }
+
+    /// Envelopes, delay lines (wet and dry), detector and resampler memories, smoother
+    /// positions and the meters.
+    fn save_state(&self, w: &mut StateWriter) {
+        self.ceiling.save(w);
+        self.makeup.save(w);
+        self.mix.save(w);
+        self.bypass_fade.save(w);
+        self.gain_linked.save(w);
+        self.channels.save(w);
+        self.os.save(w);
+        self.dry.save(w);
+        w.f32(self.meter_gain);
+        w.f32(self.meter_peak_gain);
     }
-    unsafe {
-        drop(Box::from_raw(ptr));
+
+    fn load_state(&mut self, r: &mut StateReader) {
+        self.ceiling.load(r);
+        self.makeup.load(r);
+        self.mix.load(r);
+        self.bypass_fade.load(r);
+        self.gain_linked.load(r);
+        self.channels.load(r);
+        self.os.load(r);
+        self.dry.load(r);
+        self.meter_gain = r.f32();
+        self.meter_peak_gain = r.f32();
+    }
+
+    /// Lands the smoothers and the bypass fade on their targets, and clears the envelopes,
+    /// the delay lines (wet and dry), the detector and resampler memories and the meters.
+    fn reset(&mut self) {
+        self.ceiling.set_immediate(self.ceiling.target());
+        self.makeup.set_immediate(self.makeup.target());
+        self.mix.set_immediate(self.mix.target());
+        self.bypass_fade.settle();
+        self.gain_linked.clear();
+        for ch in &mut self.channels {
+            ch.gain.clear();
+            ch.delay.clear();
+            ch.tp.clear();
+        }
+        self.os.clear();
+        for dry in &mut self.dry {
+            dry.clear();
+        }
+        self.meter_gain = 1.0;
+        self.meter_peak_gain = 1.0;
     }
 }
 
+node_abi::export_node!(Limiter {
+    abi_version: limiter_abi_version,
+    new: limiter_new,
+    free: limiter_free,
+    param_count: limiter_param_count,
+    param_descriptors: limiter_param_descriptors,
+    set_param: limiter_set_param,
+    get_param: limiter_get_param,
+    param_events: limiter_param_events,
+    process: limiter_process,
+    process_offline: limiter_process_offline,
+    latency_samples: limiter_latency_samples,
+    io_layout: limiter_get_io_layout,
+    process_io: limiter_process_io,
+    reset: limiter_reset,
+    state_size: limiter_state_size,
+    save_state: limiter_save_state,
+    load_state: limiter_load_state,
+    preset_size: limiter_preset_size,
+    save_preset: limiter_save_preset,
+    load_preset: limiter_load_preset,
+    last_error_code: limiter_last_error_code,
+    last_error_message: limiter_last_error_message_ptr,
+    process_aux: limiter_process_sidechained,
+    process_aux_offline: limiter_process_offline_sidechained,
+    process_io_aux: limiter_process_io_sidechained,
+});
+
+node_abi::test_params!(Limiter);
+
+dsp_core::test_block_sizes!(
+    limiter_new,
+    limiter_process(input, output),
+    limiter_free,
+    setup: |p| {
+        limiter_set_param(p, PARAM_CEILING_DB, -9.0);
+        limiter_set_param(p, PARAM_MAKEUP_DB, 6.0);
+        limiter_set_param(p, PARAM_TRUE_PEAK, 1.0);
+        limiter_set_param(p, PARAM_OVERSAMPLE, 2.0);
+        limiter_set_param(p, PARAM_MIX, 0.8);
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Limiter as LimiterNode);
+
+/// `bypass` crossfades (equal power, ~20 ms) to the input, delayed by the reported latency.
+/// `link` drives all channels from one shared gain envelope (loudest channel wins).
+/// `oversample` runs the whole signal path at 1x, 2x or 4x the host rate.
+/// `attack_ms` (0.01–10) slows how fast gain reduction engages; with less look-ahead than
+/// attack, peaks overshoot the ceiling.
+/// `release_shape` picks the release curve: 0 exponential, 1 linear, 2 logarithmic.
+/// `mix` (0–1) blends the limited signal with the latency-aligned dry input.
 #[no_mangle]
 pub extern "C" fn limiter_set_params(
-    ptr: *mut Limiter,
+    handle: Handle,
     ceiling_db: f32,
     release_ms: f32,
     makeup_db: f32,
     bypass: u32,
-    stereo_link: u32,
+    link: u32,
+    lookahead_ms: f32,
+    true_peak: u32,
+    auto_release: u32,
+    knee_db: f32,
+    oversample: u32,
+    mix: f32,
+    attack_ms: f32,
+    release_shape: u32,
 ) {
-    if ptr.is_null() {
+    // Oversampling first, so the times below are computed at the new core rate.
+    let params = [
+        (PARAM_OVERSAMPLE, oversample as f32),
+        (PARAM_CEILING_DB, ceiling_db),
+        (PARAM_KNEE_DB, knee_db),
+        (PARAM_MAKEUP_DB, makeup_db),
+        (PARAM_RELEASE_MS, release_ms),
+        (PARAM_ATTACK_MS, attack_ms),
+        (PARAM_AUTO_RELEASE, auto_release as f32),
+        (PARAM_RELEASE_SHAPE, release_shape as f32),
+        (PARAM_BYPASS, bypass as f32),
+        (PARAM_MIX, mix),
+        (PARAM_LINK, link as f32),
+        (PARAM_TRUE_PEAK, true_peak as f32),
+        (PARAM_LOOKAHEAD_MS, lookahead_ms),
+    ];
+    guard(handle, |l: &mut Limiter| {
+        for (index, value) in params {
+            l.set_param(index, value);
+        }
+    });
+}
+
+/// Changes the sample rate in place: coefficients and the look-ahead length are recomputed and
+/// buffers only grow if the new rate needs more room, so the gain history survives.
+#[no_mangle]
+pub extern "C" fn limiter_set_sample_rate(handle: Handle, sample_rate_hz: f32) {
+    if !sample_rate_hz.is_finite() || sample_rate_hz <= 0.0 {
         return;
     }
-    let l = unsafe { &mut *ptr };
-    l.ceiling_lin = db_to_lin(clamp(ceiling_db, -60.0, 0.0));
-    l.makeup_lin = db_to_lin(clamp(makeup_db, -24.0, 24.0));
-    l.release_coeff = release_coeff_for_ms(release_ms, l.sample_rate_hz);
-    l.bypass = if bypass != 0 { 1 } else { 0 };
-    l.stereo_link = if stereo_link != 0 { 1 } else { 0 };
+    guard(handle, |l: &mut Limiter| l.set_sample_rate(sample_rate_hz));
 }
 
+/// Non-interleaved variant matching the worklet's channel layout. Pass null `ch1_in`/`ch1_out`
+/// for mono. Input and output buffers must not overlap.
 #[no_mangle]
-pub extern "C" fn limiter_process_interleaved(
-    ptr: *mut Limiter,
-    in_ptr: *const f32,
-    out_ptr: *mut f32,
+pub extern "C" fn limiter_process_planar(
+    handle: Handle,
+    ch0_in: *const f32,
+    ch1_in: *const f32,
+    ch0_out: *mut f32,
+    ch1_out: *mut f32,
     frames: usize,
-    channels: usize,
 ) {
-    if ptr.is_null() || in_ptr.is_null() || out_ptr.is_null() {
+    if ch0_in.is_null() || ch0_out.is_null() {
         return;
     }
-    let l = unsafe { &mut *ptr };
-    let channels = channels.min(2).max(1);
-    let n = frames.saturating_mul(channels);
-    let input = unsafe { core::slice::from_raw_parts(in_ptr, n) };
-    let output = unsafe { core::slice::from_raw_parts_mut(out_ptr, n) };
+    let stereo = !ch1_in.is_null() && !ch1_out.is_null();
+    let in0 = unsafe { core::slice::from_raw_parts(ch0_in, frames) };
+    let out0 = unsafe { core::slice::from_raw_parts_mut(ch0_out, frames) };
+    let (in1, out1): (&[f32], &mut [f32]) = if stereo {
+        unsafe {
+            (
+                core::slice::from_raw_parts(ch1_in, frames),
+                core::slice::from_raw_parts_mut(ch1_out, frames),
+            )
+        }
+    } else {
+        (&[], &mut [])
+    };
+    let channels = if stereo { 2 } else { 1 };
+    let done = guard(handle, |l: &mut Limiter| {
+        split_at_events(l, frames, |l, span| {
+            let second = if stereo { span.clone() } else { 0..0 };
+            l.process(&mut Planar {
+                inputs: [&in0[span.clone()], &in1[second.clone()]],
+                outputs: [&mut out0[span], &mut out1[second]],
+                channels,
+            });
+        })
+    });
+    if done.is_none() {
+        out0.fill(0.0);
+        out1.fill(0.0);
+    }
+}
 
-    if l.bypass != 0 {
-        output.copy_from_slice(input);
-        return;
+/// Deepest gain reduction of the last processed block, in dB (positive = reducing).
+#[no_mangle]
+pub extern "C" fn limiter_get_gain_reduction_db(handle: Handle) -> f32 {
+    guard(handle, |l: &mut Limiter| gain_reduction_db(l.meter_gain)).unwrap_or(0.0)
+}
+
+/// Deepest gain reduction since creation or the last `limiter_reset_meters`, in dB.
+#[no_mangle]
+pub extern "C" fn limiter_get_peak_gain_reduction_db(handle: Handle) -> f32 {
+    guard(handle, |l: &mut Limiter| {
+        gain_reduction_db(l.meter_peak_gain)
+    })
+    .unwrap_or(0.0)
+}
+
+#[no_mangle]
+pub extern "C" fn limiter_reset_meters(handle: Handle) {
+    guard(handle, |l: &mut Limiter| {
+        l.meter_gain = 1.0;
+        l.meter_peak_gain = 1.0;
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+    use dsp_core::blocksize::test_signal;
+
+    const RATE: f32 = 48_000.0;
+
+    /// A limiter with `params` applied and its smoothers landed.
+    fn limiter(params: &[(u32, f32)]) -> Limiter {
+        let mut l = Limiter::new(RATE);
+        for &(index, value) in params {
+            l.set_param(index, value);
+        }
+        Node::reset(&mut l);
+        l
+    }
+
+    fn render(l: &mut Limiter, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(l, &input[span], y, 1))
+    }
+
+    /// Highest 4x-interpolated level of `signal`.
+    fn true_peak(signal: &[f32]) -> f32 {
+        let (mut detector, coeffs) = (TruePeakDetector::new(), TruePeakCoeffs::new());
+        signal
+            .iter()
+            .fold(0.0, |peak: f32, &x| peak.max(detector.process(x, &coeffs)))
     }
 
-    let ceiling = l.ceiling_lin;
-    let makeup = l.makeup_lin;
-    let rel = l.release_coeff;
+    /// The test signal made loud enough to limit hard, in mono.
+    fn loud(frames: usize) -> Vec<f32> {
+        test_signal(frames, 1, 3).iter().map(|x| 4.0 * x).collect()
+    }
 
-    if l.stereo_link != 0 && channels == 2 {
-        let mut g = l.gain_linked;
-        for i in 0..frames {
-            let idx = i * 2;
-            let l0 = input[idx] * makeup;
-            let r0 = input[idx + 1] * makeup;
-            let peak = l0.abs().max(r0.abs());
-            let target = if peak > ceiling { ceiling / peak } else { 1.0 };
-            g = if target < g { target } else { g * rel + (1.0 - rel) * target };
-            output[idx] = l0 * g;
-            output[idx + 1] = r0 * g;
+    #[test]
+    fn peaks_stay_under_the_ceiling_with_look_ahead() {
+        let input = loud(24_000);
+        for (lookahead_ms, knee_db) in [(1.0, 0.0), (5.0, 0.0), (5.0, 6.0)] {
+            let mut l = limiter(&[
+                (PARAM_CEILING_DB, -6.0),
+                (PARAM_LOOKAHEAD_MS, lookahead_ms),
+                (PARAM_KNEE_DB, knee_db),
+                (PARAM_RELEASE_MS, 20.0),
+            ]);
+            let ceiling = db_to_lin(-6.0);
+            let output = render(&mut l, &input);
+            let peak = output.iter().fold(0.0_f32, |p, y| p.max(y.abs()));
+            assert!(peak <= ceiling * 1.000_01, "{lookahead_ms} ms: {peak}");
+            assert!(peak > ceiling * 0.9);
         }
-        l.gain_linked = g;
-        return;
     }
 
-    // per-channel limiting (also covers mono)
-    let mut g0 = l.gain_ch0;
-    let mut g1 = l.gain_ch1;
+    #[test]
+    fn reported_latency_is_the_impulse_delay() {
+        for (lookahead_ms, true_peak, oversample) in [
+            (0.0, 0.0, 1.0),
+            (3.0, 0.0, 1.0),
+            (0.0, 1.0, 1.0),
+            (2.0, 1.0, 2.0),
+            (10.0, 0.0, 4.0),
+        ] {
+            let mut l = limiter(&[
+                (PARAM_LOOKAHEAD_MS, lookahead_ms),
+                (PARAM_TRUE_PEAK, true_peak),
+                (PARAM_OVERSAMPLE, oversample),
+            ]);
+            let mut input = vec![0.0; 2_048];
+            input[100] = 0.5;
+            let output = render(&mut l, &input);
+            let (at, _) = output
+                .iter()
+                .enumerate()
+                .fold((0, 0.0), |(at, max), (i, y)| {
+                    if y.abs() > max {
+                        (i, y.abs())
+                    } else {
+                        (at, max)
+                    }
+                });
+            let latency = Node::latency_samples(&l) as usize;
+            assert_eq!(
+                at - 100,
+                latency,
+                "{lookahead_ms} ms, tp {true_peak}, {oversample}x"
+            );
+        }
+    }
+
+    #[test]
+    fn dry_mix_is_the_input_delayed_by_the_latency() {
+        let input = loud(4_800);
+        let mut l = limiter(&[(PARAM_LOOKAHEAD_MS, 4.0), (PARAM_MIX, 0.0)]);
+        let latency = Node::latency_samples(&l) as usize;
+        let output = render(&mut l, &input);
+        assert_eq!(output[latency..], input[..input.len() - latency]);
+    }
+
+    /// Toggling bypass on a hard-limited signal moves no faster than the signal itself does,
+    /// and lands on the input delayed by the latency.
+    #[test]
+    fn bypass_fades_continuously_to_the_aligned_input() {
+        let frames = 9_600;
+        let input: Vec<f32> = (0..frames).map(|i| (i as f32 * 0.01).sin()).collect();
+        let mut l = limiter(&[(PARAM_CEILING_DB, -12.0), (PARAM_LOOKAHEAD_MS, 2.0)]);
+        let latency = Node::latency_samples(&l) as usize;
+        let mut output = render(&mut l, &input[..4_800]);
+        l.set_param(PARAM_BYPASS, 1.0);
+        output.extend(render(&mut l, &input[4_800..]));
 
-    if channels == 1 {
-        for i in 0..frames {
-            let v = input[i] * makeup;
-            let a = v.abs();
-            let target = if a > ceiling { ceiling / a } else { 1.0 };
-            g0 = if target < g0 { target } else { g0 * rel + (1.0 - rel) * target };
-            output[i] = v * g0;
+        let steepest = output
+            .windows(2)
+            .fold(0.0_f32, |m, w| m.max((w[1] - w[0]).abs()));
+        assert!(steepest < 0.02, "{steepest}");
+        for i in 7_000..frames {
+            assert_eq!(output[i], input[i - latency], "{i}");
         }
-        l.gain_ch0 = g0;
-        return;
     }
 
-    for i in 0..frames {
-        let idx = i * 2;
-        let lv = input[idx] * makeup;
-        let rv = input[idx + 1] * makeup;
+    #[test]
+    fn true_peak_mode_bounds_inter_sample_overshoot() {
+        // A quarter-rate sine sampled 45 degrees off its peaks: every sample is 3 dB under
+        // the true peak.
+        let input: Vec<f32> = (0..24_000)
+            .map(|i| 2.0 * (core::f32::consts::FRAC_PI_2 * i as f32 + 0.785).sin())
+            .collect();
+        let ceiling = db_to_lin(-1.0);
+        let overshoot = |mode: f32| {
+            let mut l = limiter(&[
+                (PARAM_CEILING_DB, -1.0),
+                (PARAM_LOOKAHEAD_MS, 2.0),
+                (PARAM_TRUE_PEAK, mode),
+            ]);
+            let output = render(&mut l, &input);
+            lin_to_db(true_peak(&output[4_800..]) / ceiling)
+        };
+        let (sample_mode, true_peak_mode) = (overshoot(0.0), overshoot(1.0));
+        assert!(sample_mode > 2.5, "{sample_mode}");
+        assert!(true_peak_mode < 0.2, "{true_peak_mode}");
+    }
 
-        let la = lv.abs();
-        let ra = rv.abs();
+    #[test]
+    fn soft_knee_starts_early_and_never_passes_the_ceiling() {
+        let ceiling = db_to_lin(-3.0);
+        let computer = GainComputer::new(ceiling, 6.0);
+        assert_eq!(computer.target(db_to_lin(-6.1)), 1.0);
+        assert!(computer.target(db_to_lin(-5.0)) < 1.0);
+        let mut last = 1.0;
+        for db in -60..=240 {
+            let peak = db_to_lin(db as f32 * 0.1);
+            let gain = computer.target(peak);
+            assert!(
+                gain <= last + 1e-6 && peak * gain <= ceiling * 1.000_01,
+                "{db}"
+            );
+            last = gain;
+        }
+    }
 
-        let lt = if la > ceiling { ceiling / la } else { 1.0 };
-        let rt = if ra > ceiling { ceiling / ra } else { 1.0 };
+    #[test]
+    fn attack_within_one_host_sample_is_instant_at_any_oversampling() {
+        for factor in [1, 2, 4] {
+            let release = release_for_ms(0.01, 120.0, false, 48_000.0, factor);
+            assert_eq!(release.attack, 0.0, "{factor}x");
+            assert!(release_for_ms(0.05, 120.0, true, 48_000.0, factor).attack > 0.0);
+        }
+        let mut l = Limiter::new(48_000.0);
+        l.set_param(PARAM_OVERSAMPLE, 4.0);
+        assert_eq!(l.release.attack, 0.0);
+        l.set_param(PARAM_ATTACK_MS, 1.0);
+        assert_eq!(l.release.attack, one_pole_coeff(1.0, 192_000.0));
+    }
 
-        g0 = if lt < g0 { lt } else { g0 * rel + (1.0 - rel) * lt };
-        g1 = if rt < g1 { rt } else { g1 * rel + (1.0 - rel) * rt };
+    #[test]
+    fn meters_read_the_block_and_held_gain_reduction() {
+        let mut l = limiter(&[(PARAM_CEILING_DB, -12.0), (PARAM_RELEASE_MS, 5.0)]);
+        let mut meters = [0.0; 2];
+        let tone: Vec<f32> = (0..128).map(|i| (i as f32 * 0.1).sin()).collect();
+        Node::start_block(&mut l);
+        render(&mut l, &tone);
+        Node::meters(&l, &mut meters);
+        assert!((meters[0] - 12.0).abs() < 0.5, "{meters:?}");
+        assert_eq!(meters[0], meters[1]);
 
-        output[idx] = lv * g0;
-        output[idx + 1] = rv * g1;
+        for _ in 0..50 {
+            Node::start_block(&mut l);
+            render(&mut l, &[0.01; 128]);
+        }
+        Node::meters(&l, &mut meters);
+        assert!(meters[0] < 0.01, "{meters:?}");
+        assert!((meters[1] - 12.0).abs() < 0.5, "{meters:?}");
+        Node::reset(&mut l);
+        Node::meters(&l, &mut meters);
+        assert_eq!(meters, [0.0, 0.0]);
     }
 
-    l.gain_ch0 = g0;
-    l.gain_ch1 = g1;
-}
+    #[test]
+    fn planar_matches_interleaved() {
+        let frames = 1_000;
+        let input = test_signal(frames, 2, 3);
+        let (left, right): (Vec<f32>, Vec<f32>) =
+            input.chunks(2).map(|f| (4.0 * f[0], 4.0 * f[1])).unzip();
+        let loud: Vec<f32> = input.iter().map(|x| 4.0 * x).collect();
+        let params = [(PARAM_CEILING_DB, -6.0), (PARAM_LOOKAHEAD_MS, 1.0)];
 
-#[no_mangle]
-pub extern "C" fn wasm_alloc(bytes: usize) -> *mut u8 {
-    let mut buf = Vec::<u8>::with_capacity(bytes);
-    let ptr = buf.as_mut_ptr();
-    core::mem::forget(buf);
-    ptr
-}
+        let interleaved = limiter_new(RATE);
+        let planar = limiter_new(RATE);
+        for &(index, value) in &params {
+            limiter_set_param(interleaved, index, value);
+            limiter_set_param(planar, index, value);
+        }
+        let mut expected = vec![0.0; frames * 2];
+        limiter_process(interleaved, loud.as_ptr(), expected.as_mut_ptr(), frames, 2);
+        let (mut out_l, mut out_r) = (vec![0.0; frames], vec![0.0; frames]);
+        limiter_process_planar(
+            planar,
+            left.as_ptr(),
+            right.as_ptr(),
+            out_l.as_mut_ptr(),
+            out_r.as_mut_ptr(),
+            frames,
+        );
+        for (i, f) in expected.chunks(2).enumerate() {
+            assert_eq!((out_l[i], out_r[i]), (f[0], f[1]), "{i}");
+        }
+        limiter_free(interleaved);
+        limiter_free(planar);
+    }
 
-#[no_mangle]
-pub extern "C" fn wasm_free(ptr: *mut u8, bytes: usize) {
-    if ptr.is_null() || bytes == 0 {
-        return;
+    /// Six channels with a burst on the last one only: linked, every channel ducks with it;
+    /// unlinked, the others pass untouched.
+    #[test]
+    fn links_and_unlinks_more_than_two_channels() {
+        let (frames, channels) = (4_800, 6);
+        let mut input = vec![0.0; frames * channels];
+        for (i, frame) in input.chunks_mut(channels).enumerate() {
+            let x = 0.25 * (i as f32 * 0.05).sin();
+            frame.fill(x);
+            frame[5] = 8.0 * x;
+        }
+        for link in [1.0, 0.0] {
+            let mut l = limiter(&[(PARAM_CEILING_DB, -6.0), (PARAM_LINK, link)]);
+            let mut output = vec![0.0; input.len()];
+            for (x, y) in input
+                .chunks(128 * channels)
+                .zip(output.chunks_mut(128 * channels))
+            {
+                Node::process(&mut l, x, y, channels);
+            }
+            let peak = |ch: usize| {
+                output
+                    .iter()
+                    .skip(ch)
+                    .step_by(channels)
+                    .fold(0.0_f32, |p, y| p.max(y.abs()))
+            };
+            assert!(peak(5) <= db_to_lin(-6.0) * 1.000_01, "{}", peak(5));
+            for ch in 0..5 {
+                if link != 0.0 {
+                    assert!(peak(ch) < 0.1, "linked {ch}: {}", peak(ch));
+                } else {
+                    assert!(
+                        (peak(ch) - 0.25).abs() < 1e-3,
+                        "unlinked {ch}: {}",
+                        peak(ch)
+                    );
+                }
+            }
+        }
     }
-    unsafe {
-        drop(Vec::<u8>::from_raw_parts(ptr, 0, bytes));
+
+    #[test]
+    fn sample_rate_change_retimes_the_look_ahead() {
+        let mut l = limiter(&[(PARAM_LOOKAHEAD_MS, 2.0), (PARAM_TRUE_PEAK, 1.0)]);
+        let tp = TRUE_PEAK_DELAY as u32;
+        assert_eq!(Node::latency_samples(&l), 96 + tp);
+        l.set_sample_rate(96_000.0);
+        assert_eq!(Node::latency_samples(&l), 192 + tp);
+        l.set_sample_rate(22_050.0);
+        assert_eq!(Node::latency_samples(&l), 44 + tp);
+
+        // Grown past the capacity made at 48 kHz, the longest look-ahead still holds peaks.
+        l.set_sample_rate(192_000.0);
+        l.set_param(PARAM_LOOKAHEAD_MS, MAX_LOOKAHEAD_MS);
+        l.set_param(PARAM_CEILING_DB, -6.0);
+        Node::reset(&mut l);
+        let output = render(&mut l, &loud(48_000));
+        let peak = true_peak(&output);
+        assert!(peak <= db_to_lin(-6.0) * 1.001, "{peak}");
     }
-}
 
+    /// A makeup jump of 12 dB on a steady input glides over the smoother's time instead of
+    /// stepping at the block edge.
+    #[test]
+    fn makeup_changes_glide_per_sample() {
+        let mut l = limiter(&[(PARAM_CEILING_DB, 0.0)]);
+        let input = [0.1; 128];
+        render(&mut l, &input);
+        l.set_param(PARAM_MAKEUP_DB, 12.0);
+        let mut output = Vec::new();
+        for _ in 0..40 {
+            output.extend(render(&mut l, &input));
+        }
+        let steepest = output
+            .windows(2)
+            .fold(0.0_f32, |m, w| m.max((w[1] - w[0]).abs()));
+        assert!(steepest < 0.005, "{steepest}");
+        assert!(output[0] < 0.11, "{}", output[0]);
+        let last = *output.last().unwrap();
+        assert!((last - 0.1 * db_to_lin(12.0)).abs() < 1e-3, "{last}");
+    }
+
+    /// A quiet program ducks under a loud key, and a quiet key leaves a loud program alone,
+    /// past the ceiling.
+    #[test]
+    fn side_chain_key_drives_the_gain() {
+        let render_keyed = |program: f32, key: f32| {
+            let mut l = limiter(&[(PARAM_CEILING_DB, -12.0)]);
+            let mut output = vec![0.0; 128];
+            for _ in 0..20 {
+                Node::process_aux(&mut l, &[program; 128], &[key; 128], 1, &mut output, 1);
+            }
+            output[127]
+        };
+        let ducked = render_keyed(0.1, 1.0);
+        assert!((ducked - 0.1 * db_to_lin(-12.0)).abs() < 1e-4, "{ducked}");
+        assert_eq!(render_keyed(1.0, 0.1), 1.0);
+    }
+}
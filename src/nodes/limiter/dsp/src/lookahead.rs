@@ -0,0 +1,452 @@
+use alloc::{vec, vec::Vec};
+use dsp_core::denormal::flush;
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+use dsp_core::state::{State, StateReader, StateWriter};
+
+pub const MAX_LOOKAHEAD_MS: f32 = 10.0;
+
+pub fn max_lookahead_samples(sample_rate_hz: f32) -> usize {
+    let n = (MAX_LOOKAHEAD_MS / 1000.0 * sample_rate_hz).ceil();
+    if n.is_finite() && n > 0.0 {
+        n as usize
+    } else {
+        0
+    }
+}
+
+/// `i` (below `2 * len`) as a ring index: `i % len` without the division, per sample.
+#[inline]
+fn wrap(i: usize, len: usize) -> usize {
+    if i >= len {
+        i - len
+    } else {
+        i
+    }
+}
+
+/// Fixed-capacity circular delay; the delay can change without reallocating.
+pub struct DelayLine {
+    buf: Vec<f32>,
+    pos: usize,
+    delay: usize,
+}
+
+impl DelayLine {
+    pub fn new(max_delay: usize) -> Self {
+        Self {
+            buf: vec![0.0; max_delay + 1],
+            pos: 0,
+            delay: 0,
+        }
+    }
+
+    /// Reallocates for a larger maximum delay; the buffered audio is dropped.
+    pub fn grow(&mut self, max_delay: usize) {
+        if max_delay + 1 > self.buf.len() {
+            self.buf = vec![0.0; max_delay + 1];
+            self.pos = 0;
+        }
+    }
+
+    pub fn set_delay(&mut self, delay: usize) {
+        self.delay = delay.min(self.buf.len() - 1);
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.fill(0.0);
+        self.pos = 0;
+    }
+
+    #[inline]
+    pub fn process(&mut self, x: f32) -> f32 {
+        let len = self.buf.len();
+        self.buf[self.pos] = x;
+        let read = wrap(self.pos + len - self.delay, len);
+        self.pos = wrap(self.pos + 1, len);
+        self.buf[read]
+    }
+}
+
+impl State for DelayLine {
+    fn save(&self, w: &mut StateWriter) {
+        w.f32s(&self.buf);
+        w.index(self.pos);
+    }
+
+    fn load(&mut self, r: &mut StateReader) {
+        r.f32s(&mut self.buf);
+        self.pos = r.index(self.buf.len());
+    }
+}
+
+/// Running minimum over the last `window` values (monotonic deque in a ring).
+struct SlidingMin {
+    vals: Vec<f32>,
+    stamps: Vec<u64>,
+    head: usize,
+    len: usize,
+    window: u64,
+    n: u64,
+}
+
+impl SlidingMin {
+    fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            vals: vec![0.0; capacity],
+            stamps: vec![0; capacity],
+            head: 0,
+            len: 0,
+            window: 1,
+            n: 0,
+        }
+    }
+
+    fn set_window(&mut self, window: usize) {
+        self.window = window.clamp(1, self.vals.len()) as u64;
+        self.clear();
+    }
+
+    fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+        self.n = 0;
+    }
+
+    /// Expires before inserting, so a full window of rising values doesn't overwrite the
+    /// oldest.
+    #[inline]
+    fn push(&mut self, v: f32) -> f32 {
+        let cap = self.vals.len();
+        while self.len > 0 && self.stamps[self.head] + self.window <= self.n {
+            self.head = wrap(self.head + 1, cap);
+            self.len -= 1;
+        }
+        while self.len > 0 {
+            let back = wrap(self.head + self.len - 1, cap);
+            if self.vals[back] >= v {
+                self.len -= 1;
+            } else {
+                break;
+            }
+        }
+        let slot = wrap(self.head + self.len, cap);
+        self.vals[slot] = v;
+        self.stamps[slot] = self.n;
+        self.len += 1;
+        self.n += 1;
+        self.vals[self.head]
+    }
+}
+
+impl State for SlidingMin {
+    fn save(&self, w: &mut StateWriter) {
+        w.f32s(&self.vals);
+        w.u64s(&self.stamps);
+        w.index(self.head);
+        w.index(self.len);
+        w.u64(self.n);
+    }
+
+    fn load(&mut self, r: &mut StateReader) {
+        r.f32s(&mut self.vals);
+        r.u64s(&mut self.stamps);
+        self.head = r.index(self.vals.len());
+        self.len = r.index(self.vals.len() + 1);
+        self.n = r.u64();
+    }
+}
+
+/// Box filter over the last `window` values.
+struct MovingAverage {
+    buf: Vec<f32>,
+    pos: usize,
+    window: usize,
+    sum: f64,
+}
+
+impl MovingAverage {
+    fn new(capacity: usize) -> Self {
+        Self {
+            buf: vec![1.0; capacity.max(1)],
+            pos: 0,
+            window: 1,
+            sum: 1.0,
+        }
+    }
+
+    fn set_window(&mut self, window: usize) {
+        self.window = window.clamp(1, self.buf.len());
+        self.clear();
+    }
+
+    fn clear(&mut self) {
+        self.fill(1.0);
+    }
+
+    fn fill(&mut self, v: f32) {
+        self.buf.fill(v);
+        self.pos = 0;
+        self.sum = v as f64 * self.window as f64;
+    }
+
+    #[inline]
+    fn push(&mut self, v: f32) -> f32 {
+        self.sum += v as f64 - self.buf[self.pos] as f64;
+        self.buf[self.pos] = v;
+        self.pos = wrap(self.pos + 1, self.window);
+        (self.sum / self.window as f64) as f32
+    }
+}
+
+impl State for MovingAverage {
+    fn save(&self, w: &mut StateWriter) {
+        w.f32s(&self.buf);
+        w.index(self.pos);
+        w.f64(self.sum);
+    }
+
+    fn load(&mut self, r: &mut StateReader) {
+        r.f32s(&mut self.buf);
+        self.pos = r.index(self.window);
+        self.sum = r.f64();
+    }
+}
+
+/// How the (fast) release stage climbs back towards the held target, per sample.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseShape {
+    /// One-pole: rate proportional to the remaining distance.
+    Exponential,
+    /// Constant rate: a full 0-to-unity recovery takes one release time.
+    Linear,
+    /// Square-root law: quicker than exponential early on, easing in at the end.
+    Logarithmic,
+}
+
+impl ReleaseShape {
+    pub fn from_u32(v: u32) -> Self {
+        match v {
+            1 => Self::Linear,
+            2 => Self::Logarithmic,
+            _ => Self::Exponential,
+        }
+    }
+
+    #[inline]
+    fn recover(self, env: f32, target: f32, coeff: f32) -> f32 {
+        let rate = 1.0 - coeff;
+        match self {
+            Self::Exponential => env * coeff + rate * target,
+            Self::Linear => (env + rate).min(target),
+            Self::Logarithmic => (env + rate * (target - env).sqrt()).min(target),
+        }
+    }
+}
+
+/// Envelope coefficients for `GainStage`. `attack` smooths gain reduction onto the held target;
+/// with `auto` set, `coeff` is the fast release stage and a slow stage (smoothed with
+/// `slow_attack`/`slow_coeff`) holds the gain down under sustained limiting.
+#[derive(Clone, Copy)]
+pub struct Release {
+    pub attack: f32,
+    pub coeff: f32,
+    pub slow_attack: f32,
+    pub slow_coeff: f32,
+    pub auto: bool,
+    pub shape: ReleaseShape,
+}
+
+/// Gain envelope for one gain channel.
+///
+/// The target gain is min-held over `lookahead + 1` samples, released with a one-pole and then
+/// averaged over `lookahead` samples, so the gain is fully down by the time the peak leaves the
+/// matching `DelayLine`.
+pub struct GainStage {
+    hold: SlidingMin,
+    avg: MovingAverage,
+    env: f32,
+    env_slow: f32,
+}
+
+impl GainStage {
+    pub fn new(max_lookahead: usize) -> Self {
+        Self {
+            hold: SlidingMin::new(max_lookahead + 1),
+            avg: MovingAverage::new(max_lookahead),
+            env: 1.0,
+            env_slow: 1.0,
+        }
+    }
+
+    /// Reallocates the look-ahead windows for a larger maximum, keeping the envelope.
+    pub fn grow(&mut self, max_lookahead: usize) {
+        if max_lookahead + 1 > self.hold.vals.len() {
+            self.hold = SlidingMin::new(max_lookahead + 1);
+            self.avg = MovingAverage::new(max_lookahead);
+        }
+    }
+
+    /// Resizes the look-ahead windows; the average restarts from the current envelope so the
+    /// gain doesn't jump.
+    pub fn set_lookahead(&mut self, lookahead: usize) {
+        self.hold.set_window(lookahead + 1);
+        self.avg.set_window(lookahead);
+        self.avg.fill(self.env.min(self.env_slow));
+    }
+
+    pub fn clear(&mut self) {
+        self.hold.clear();
+        self.avg.clear();
+        self.env = 1.0;
+        self.env_slow = 1.0;
+    }
+
+    #[inline]
+    pub fn process(&mut self, target: f32, release: &Release) -> f32 {
+        let held = self.hold.push(target);
+        self.env = flush(if held < self.env {
+            self.env * release.attack + (1.0 - release.attack) * held
+        } else {
+            release.shape.recover(self.env, held, release.coeff)
+        });
+        if !release.auto {
+            return self.avg.push(self.env);
+        }
+        let c = if held < self.env_slow {
+            release.slow_attack
+        } else {
+            release.slow_coeff
+        };
+        self.env_slow = flush(self.env_slow * c + (1.0 - c) * held);
+        self.avg.push(self.env.min(self.env_slow))
+    }
+}
+
+impl State for GainStage {
+    fn save(&self, w: &mut StateWriter) {
+        self.hold.save(w);
+        self.avg.save(w);
+        w.f32(self.env);
+        w.f32(self.env_slow);
+    }
+
+    fn load(&mut self, r: &mut StateReader) {
+        self.hold.load(r);
+        self.avg.load(r);
+        self.env = r.f32();
+        self.env_slow = r.f32();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hard(shape: ReleaseShape) -> Release {
+        Release {
+            attack: 0.0,
+            coeff: 0.99,
+            slow_attack: 0.0,
+            slow_coeff: 0.0,
+            auto: false,
+            shape,
+        }
+    }
+
+    #[test]
+    fn delay_line_delays_by_whole_samples() {
+        let mut d = DelayLine::new(8);
+        d.set_delay(3);
+        let out: Vec<f32> = (1..=6).map(|x| d.process(x as f32)).collect();
+        assert_eq!(out, [0.0, 0.0, 0.0, 1.0, 2.0, 3.0]);
+        d.set_delay(100);
+        assert_eq!(d.delay, 8);
+    }
+
+    /// Rising input keeps every value in the deque; with the window at full capacity the
+    /// newest must not land on the oldest.
+    #[test]
+    fn sliding_min_holds_a_full_window_of_rising_values() {
+        let mut m = SlidingMin::new(4);
+        m.set_window(4);
+        let out: Vec<f32> = (1..=8).map(|x| m.push(x as f32)).collect();
+        assert_eq!(out, [1.0, 1.0, 1.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+
+    /// A step from silence to twice the ceiling, delayed by the look-ahead and scaled by the
+    /// gain envelope, never gets past the ceiling.
+    #[test]
+    fn gain_is_down_before_the_peak_leaves_the_delay() {
+        for lookahead in [1, 16, 240] {
+            let mut stage = GainStage::new(lookahead);
+            stage.set_lookahead(lookahead);
+            let mut delay = DelayLine::new(lookahead);
+            delay.set_delay(lookahead);
+            let release = hard(ReleaseShape::Exponential);
+            for i in 0..2_000 {
+                let x = if (500..900).contains(&i) { 2.0 } else { 0.1 };
+                let target = if x > 1.0 { 1.0 / x } else { 1.0 };
+                let y = delay.process(x) * stage.process(target, &release);
+                assert!(y <= 1.0 + 1e-6, "look-ahead {lookahead}, sample {i}: {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn every_release_shape_recovers_steadily_to_unity() {
+        for shape in [
+            ReleaseShape::Exponential,
+            ReleaseShape::Linear,
+            ReleaseShape::Logarithmic,
+        ] {
+            let mut stage = GainStage::new(0);
+            stage.set_lookahead(0);
+            let release = hard(shape);
+            assert_eq!(stage.process(0.25, &release), 0.25);
+            let mut last = 0.25;
+            let mut recovered = None;
+            for i in 0..10_000 {
+                let g = stage.process(1.0, &release);
+                assert!(g >= last && g <= 1.0, "{i}: {g} after {last}");
+                last = g;
+                if g > 0.999 {
+                    recovered.get_or_insert(i);
+                }
+            }
+            let recovered = recovered.unwrap();
+            // Linear takes 0.75 / (1 - coeff) samples; the others ease in.
+            if shape == ReleaseShape::Linear {
+                assert!((74..=76).contains(&recovered), "{recovered}");
+            }
+            assert!(recovered < 1_000, "{recovered}");
+        }
+    }
+
+    #[test]
+    fn auto_release_holds_down_under_sustained_limiting() {
+        let release = Release {
+            slow_attack: 0.999,
+            slow_coeff: 0.9999,
+            auto: true,
+            ..hard(ReleaseShape::Exponential)
+        };
+        let (mut short, mut long) = (GainStage::new(0), GainStage::new(0));
+        short.set_lookahead(0);
+        long.set_lookahead(0);
+        for i in 0..20_000 {
+            long.process(0.5, &release);
+            if i >= 19_900 {
+                short.process(0.5, &release);
+            }
+        }
+        // After the same 200 samples of release, the long burst is still further down.
+        let (mut a, mut b) = (0.0, 0.0);
+        for _ in 0..200 {
+            a = short.process(1.0, &release);
+            b = long.process(1.0, &release);
+        }
+        assert!(b < a, "{b} vs {a}");
+    }
+}
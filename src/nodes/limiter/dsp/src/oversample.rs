@@ -0,0 +1,166 @@
+use alloc::{vec, vec::Vec};
+use dsp_core::oversample::Resampler;
+use dsp_core::state::{State, StateReader, StateWriter};
+
+use crate::block::AudioBlock;
+use crate::MAX_CHANNELS;
+
+pub use dsp_core::oversample::RESAMPLER_LATENCY;
+
+pub const MAX_OVERSAMPLE: usize = 4;
+
+/// Host-rate frames upsampled per pass through the scratch buffers.
+pub const CHUNK_FRAMES: usize = 128;
+
+/// Resamplers and scratch buffers for running the limiter core at 2x/4x. Both factors' resamplers
+/// are built up front so switching doesn't allocate.
+pub struct Oversampler {
+    factor: usize,
+    /// Per channel, 2x then 4x.
+    channels: Vec<[Resampler; 2]>,
+    pub input: Vec<Vec<f32>>,
+    pub output: Vec<Vec<f32>>,
+    pub key: Vec<Vec<f32>>,
+}
+
+impl Default for Oversampler {
+    fn default() -> Self {
+        Self {
+            factor: 1,
+            channels: Vec::new(),
+            input: Vec::new(),
+            output: Vec::new(),
+            key: Vec::new(),
+        }
+    }
+}
+
+impl Oversampler {
+    pub fn new() -> Self {
+        let scratch = || {
+            (0..MAX_CHANNELS)
+                .map(|_| vec![0.0; CHUNK_FRAMES * MAX_OVERSAMPLE])
+                .collect()
+        };
+        Self {
+            factor: 1,
+            channels: (0..MAX_CHANNELS)
+                .map(|_| [Resampler::new(2), Resampler::new(4)])
+                .collect(),
+            input: scratch(),
+            output: scratch(),
+            key: scratch(),
+        }
+    }
+
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    /// Accepts 1, 2 or 4; anything else rounds down to the nearest of those.
+    pub fn set_factor(&mut self, factor: usize) {
+        let factor = if factor >= 4 {
+            4
+        } else if factor >= 2 {
+            2
+        } else {
+            1
+        };
+        if factor != self.factor {
+            self.factor = factor;
+            self.clear();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for r in self.channels.iter_mut().flatten() {
+            r.clear();
+        }
+    }
+
+    /// Upsamples `frames` host frames starting at `start` into the scratch buffers. The key is
+    /// held rather than interpolated; the detector only needs its level.
+    pub fn upsample<B: AudioBlock>(&mut self, block: &B, start: usize, frames: usize) {
+        let f = self.factor;
+        let which = usize::from(f == 4);
+        for ch in 0..block.channels().min(MAX_CHANNELS) {
+            for i in 0..frames {
+                let x = block.input(ch, start + i);
+                let out = &mut self.input[ch][i * f..(i + 1) * f];
+                self.channels[ch][which].upsample(x, out);
+                if let Some(k) = block.key(ch, start + i) {
+                    self.key[ch][i * f..(i + 1) * f].fill(k);
+                }
+            }
+        }
+    }
+
+    /// Decimates the processed scratch output back into `block`.
+    pub fn downsample<B: AudioBlock>(&mut self, block: &mut B, start: usize, frames: usize) {
+        let f = self.factor;
+        let which = usize::from(f == 4);
+        for ch in 0..block.channels().min(MAX_CHANNELS) {
+            for i in 0..frames {
+                let chunk = &self.output[ch][i * f..(i + 1) * f];
+                let y = self.channels[ch][which].downsample(chunk);
+                block.set_output(ch, start + i, y);
+            }
+        }
+    }
+}
+
+/// The resamplers' filter memories; the factor is a parameter and the scratch buffers hold
+/// nothing between calls.
+impl State for Oversampler {
+    fn save(&self, w: &mut StateWriter) {
+        self.channels.save(w);
+    }
+
+    fn load(&mut self, r: &mut StateReader) {
+        self.channels.load(r);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Interleaved;
+
+    /// Up and straight back down is the input delayed by `RESAMPLER_LATENCY`, within the
+    /// filters' ripple.
+    #[test]
+    fn round_trip_delays_by_the_resampler_latency() {
+        let frames = CHUNK_FRAMES * 8;
+        let input: Vec<f32> = (0..frames).map(|i| 0.8 * (i as f32 * 0.05).sin()).collect();
+        for factor in [2, 4] {
+            let mut os = Oversampler::new();
+            os.set_factor(factor);
+            assert_eq!(os.factor(), factor);
+            let mut output = vec![0.0; frames];
+            let mut block = Interleaved {
+                input: &input,
+                output: &mut output,
+                channels: 1,
+            };
+            for start in (0..frames).step_by(CHUNK_FRAMES) {
+                os.upsample(&block, start, CHUNK_FRAMES);
+                let (up, down) = (&os.input[0], &mut os.output[0]);
+                down.copy_from_slice(up);
+                os.downsample(&mut block, start, CHUNK_FRAMES);
+            }
+            for i in 64..frames {
+                let want = input[i - RESAMPLER_LATENCY];
+                assert!((output[i] - want).abs() < 1e-3, "{factor}x, {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn factors_round_down_to_one_two_or_four() {
+        let mut os = Oversampler::new();
+        for (asked, got) in [(0, 1), (1, 1), (2, 2), (3, 2), (4, 4), (16, 4)] {
+            os.set_factor(asked);
+            assert_eq!(os.factor(), got);
+        }
+    }
+}
@@ -1,8 +1,17 @@
+import { useEffect, useState } from "react";
+import { useRuntimeStateGetter } from "@graph/hooks/useNodeRuntimeState";
 import type { GraphNode } from "@graph/types";
 import type { NodeDefinition, NodeUiProps } from "@/types/graphNodeDefinition";
 import { Knob, RadioGroup } from "@ui/components";
-import { db, ms } from "@ui/units";
+import { db, ms, percent } from "@ui/units";
+import type { Unit } from "@ui/units";
 import { clamp } from "@utils/math";
+import type { LimiterRuntimeState } from "./audio";
+
+const msFine: Unit = {
+  format: (v) => `${v.toFixed(1)} ms`,
+  parse: (s) => parseFloat(s),
+};
 
 type LimiterNode = Extract<GraphNode, { type: "limiter" }>;
 
@@ -15,16 +24,52 @@ function defaultState(): LimiterNode["state"] {
     stereoLink: true,
     channelCount: 2,
     lookaheadMs: 0,
+    truePeak: false,
+    autoRelease: false,
+    kneeDb: 0,
+    oversample: 1,
+    mix: 1,
+    attackMs: 0.01,
+    releaseShape: "exponential",
   };
 }
 
-const LimiterUi: React.FC<NodeUiProps<LimiterNode>> = ({ node, onPatchNode }) => {
+const LimiterUi: React.FC<NodeUiProps<LimiterNode>> = ({ node, onPatchNode, audioState }) => {
+  const getRuntimeState = useRuntimeStateGetter<LimiterRuntimeState>(node.id);
+  const [gr, setGr] = useState<{ current: number; peak: number } | null>(null);
+
+  useEffect(() => {
+    if (audioState !== "running") {
+      setGr(null);
+      return;
+    }
+
+    let raf = 0;
+    const tick = () => {
+      const state = getRuntimeState();
+      if (state) {
+        setGr({ current: state.gainReductionDb, peak: state.peakGainReductionDb });
+      }
+      raf = requestAnimationFrame(tick);
+    };
+    raf = requestAnimationFrame(tick);
+    return () => cancelAnimationFrame(raf);
+  }, [getRuntimeState, audioState]);
+
   const ceilingDb = clamp(node.state.ceilingDb, -60, 0);
   const releaseMs = clamp(node.state.releaseMs, 1, 5000);
   const makeupDb = clamp(node.state.makeupDb, -24, 24);
   const stereoLink = !!node.state.stereoLink;
   const bypass = !!node.state.bypass;
+  const truePeak = !!node.state.truePeak;
+  const autoRelease = !!node.state.autoRelease;
   const channelCount = node.state.channelCount === 1 ? 1 : 2;
+  const lookaheadMs = clamp(node.state.lookaheadMs ?? 0, 0, 10);
+  const kneeDb = clamp(node.state.kneeDb ?? 0, 0, 12);
+  const mix = clamp(node.state.mix ?? 1, 0, 1);
+  const releaseShape = node.state.releaseShape ?? "exponential";
+  const attackMs = clamp(node.state.attackMs ?? 0.01, 0.01, 10);
+  const oversample = node.state.oversample === 4 ? 4 : node.state.oversample === 2 ? 2 : 1;
 
   return (
     <div style={{ display: "grid", gap: 12 }}>
@@ -60,6 +105,15 @@ const LimiterUi: React.FC<NodeUiProps<LimiterNode>> = ({ node, onPatchNode }) =>
           indicator="arc"
           unit={db}
         />
+        <Knob
+          value={attackMs}
+          onChange={(v) => onPatchNode(node.id, { attackMs: v })}
+          min={0.01}
+          max={10}
+          label="Attack"
+          indicator="arc"
+          unit={msFine}
+        />
         <Knob
           value={releaseMs}
           onChange={(v) => onPatchNode(node.id, { releaseMs: v })}
@@ -78,9 +132,76 @@ const LimiterUi: React.FC<NodeUiProps<LimiterNode>> = ({ node, onPatchNode }) =>
           indicator="bipolar"
           unit={db}
         />
+        <Knob
+          value={lookaheadMs}
+          onChange={(v) => onPatchNode(node.id, { lookaheadMs: v })}
+          min={0}
+          max={10}
+          label="Lookahead"
+          indicator="arc"
+          unit={msFine}
+        />
+        <Knob
+          value={kneeDb}
+          onChange={(v) => onPatchNode(node.id, { kneeDb: v })}
+          min={0}
+          max={12}
+          label="Knee"
+          indicator="arc"
+          unit={db}
+        />
+        <Knob
+          value={mix}
+          onChange={(v) => onPatchNode(node.id, { mix: v })}
+          min={0}
+          max={1}
+          label="Mix"
+          indicator="arc"
+          unit={percent}
+        />
       </div>
 
-      <div style={{ display: "flex", justifyContent: "center" }}>
+      <div style={{ display: "flex", gap: 16, justifyContent: "center" }}>
+        <RadioGroup
+          value={truePeak ? "on" : "off"}
+          onChange={(v) => onPatchNode(node.id, { truePeak: v === "on" })}
+          options={[
+            { value: "off", content: "Sample" },
+            { value: "on", content: "True" },
+          ]}
+          label="Peak"
+        />
+        <RadioGroup
+          value={oversample}
+          onChange={(v) => onPatchNode(node.id, { oversample: v as 1 | 2 | 4 })}
+          options={[
+            { value: 1, content: "1x" },
+            { value: 2, content: "2x" },
+            { value: 4, content: "4x" },
+          ]}
+          label="Oversample"
+        />
+        <RadioGroup
+          value={autoRelease ? "auto" : "manual"}
+          onChange={(v) => onPatchNode(node.id, { autoRelease: v === "auto" })}
+          options={[
+            { value: "manual", content: "Manual" },
+            { value: "auto", content: "Auto" },
+          ]}
+          label="Release"
+        />
+        <RadioGroup
+          value={releaseShape}
+          onChange={(v) =>
+            onPatchNode(node.id, { releaseShape: v as LimiterNode["state"]["releaseShape"] })
+          }
+          options={[
+            { value: "exponential", content: "Exp" },
+            { value: "linear", content: "Lin" },
+            { value: "logarithmic", content: "Log" },
+          ]}
+          label="Curve"
+        />
         <RadioGroup
           value={bypass ? "on" : "off"}
           onChange={(v) => onPatchNode(node.id, { bypass: v === "on" })}
@@ -91,6 +212,10 @@ const LimiterUi: React.FC<NodeUiProps<LimiterNode>> = ({ node, onPatchNode }) =>
           label="Bypass"
         />
       </div>
+
+      <div style={{ textAlign: "center", fontSize: 11, opacity: 0.8, fontVariantNumeric: "tabular-nums" }}>
+        GR {gr ? gr.current.toFixed(1) : "–"} dB · peak {gr ? gr.peak.toFixed(1) : "–"} dB
+      </div>
     </div>
   );
 };
@@ -101,6 +226,7 @@ export const limiterGraph: NodeDefinition<LimiterNode> = {
   defaultState,
   ports: () => [
     { id: "audio_in", name: "In", kind: "audio", direction: "in" },
+    { id: "sidechain_in", name: "Key", kind: "audio", direction: "in" },
     { id: "audio_out", name: "Out", kind: "audio", direction: "out" },
   ],
   ui: LimiterUi,
@@ -115,7 +241,14 @@ export const limiterGraph: NodeDefinition<LimiterNode> = {
       bypass: s.bypass ?? d.bypass,
       stereoLink: s.stereoLink ?? d.stereoLink,
       channelCount,
-      lookaheadMs: 0,
+      lookaheadMs: s.lookaheadMs ?? d.lookaheadMs,
+      truePeak: s.truePeak ?? d.truePeak,
+      autoRelease: s.autoRelease ?? d.autoRelease,
+      kneeDb: s.kneeDb ?? d.kneeDb,
+      oversample: s.oversample ?? d.oversample,
+      mix: s.mix ?? d.mix,
+      attackMs: s.attackMs ?? d.attackMs,
+      releaseShape: s.releaseShape ?? d.releaseShape,
     };
   },
 };
@@ -1,4 +1,14 @@
 import { clamp } from "@utils/math";
+import {
+  NODE_ERROR_NONE,
+  readNodeError,
+  readNodeIo,
+  type NodeIoViews,
+} from "@utils/nodeParams";
+
+type ReleaseShape = "exponential" | "linear" | "logarithmic";
+
+const RELEASE_SHAPES: readonly ReleaseShape[] = ["exponential", "linear", "logarithmic"];
 
 type LimiterParams = Readonly<{
   ceilingDb: number;
@@ -6,27 +16,94 @@ type LimiterParams = Readonly<{
   makeupDb: number;
   bypass: boolean;
   stereoLink: boolean;
+  lookaheadMs: number;
+  truePeak: boolean;
+  autoRelease: boolean;
+  kneeDb: number;
+  oversample: 1 | 2 | 4;
+  mix: number;
+  attackMs: number;
+  releaseShape: ReleaseShape;
 }>;
 
+// Version of the standard node exports (`node-abi`) this processor was written against.
+const NODE_ABI_VERSION = 11;
+// The DSP's `MAX_CHANNELS`: the I/O block is negotiated this wide once, at init, so no channel
+// count process() sees needs a new one.
+const MAX_CHANNELS = 8;
+// Frames the I/O block holds; the render quantum.
+const MAX_FRAMES = 128;
+
 type WasmExports = {
   readonly memory: WebAssembly.Memory;
+  limiter_abi_version: () => number;
   limiter_new: (sampleRateHz: number) => number;
-  limiter_free: (ptr: number) => void;
+  limiter_free: (handle: number) => void;
   limiter_set_params: (
-    ptr: number,
+    handle: number,
     ceilingDb: number,
     releaseMs: number,
     makeupDb: number,
     bypass: number,
-    stereoLink: number
+    stereoLink: number,
+    lookaheadMs: number,
+    truePeak: number,
+    autoRelease: number,
+    kneeDb: number,
+    oversample: number,
+    mix: number,
+    attackMs: number,
+    releaseShape: number
   ) => void;
-  limiter_process_interleaved: (
-    ptr: number,
+  limiter_param_count: () => number;
+  limiter_param_descriptors: () => number;
+  limiter_set_param: (handle: number, index: number, value: number) => void;
+  limiter_get_param: (handle: number, index: number) => number;
+  limiter_param_events: (handle: number) => number;
+  limiter_latency_samples: (handle: number) => number;
+  limiter_get_io_layout: (handle: number, maxFrames: number, channels: number) => number;
+  limiter_process_io: (handle: number, frames: number) => void;
+  limiter_process_io_sidechained: (
+    handle: number,
+    frames: number,
+    keyChannels: number
+  ) => void;
+  limiter_reset: (handle: number) => void;
+  limiter_state_size: (handle: number) => number;
+  limiter_save_state: (handle: number, dest: number, bytes: number) => number;
+  limiter_load_state: (handle: number, src: number, bytes: number) => number;
+  limiter_preset_size: (handle: number) => number;
+  limiter_save_preset: (handle: number, dest: number, bytes: number) => number;
+  limiter_load_preset: (handle: number, src: number, bytes: number) => number;
+  limiter_last_error_code: (handle: number) => number;
+  limiter_last_error_message_ptr: (handle: number) => number;
+  limiter_get_gain_reduction_db: (handle: number) => number;
+  limiter_get_peak_gain_reduction_db: (handle: number) => number;
+  limiter_reset_meters: (handle: number) => void;
+  limiter_process: (
+    handle: number,
     inPtr: number,
     outPtr: number,
     frames: number,
     channels: number
   ) => void;
+  limiter_process_sidechained: (
+    handle: number,
+    inPtr: number,
+    keyPtr: number,
+    outPtr: number,
+    frames: number,
+    channels: number,
+    keyChannels: number
+  ) => void;
+  limiter_process_planar: (
+    handle: number,
+    ch0InPtr: number,
+    ch1InPtr: number,
+    ch0OutPtr: number,
+    ch1OutPtr: number,
+    frames: number
+  ) => void;
   wasm_alloc: (bytes: number) => number;
   wasm_free: (ptr: number, bytes: number) => void;
 };
@@ -46,16 +123,32 @@ class LimiterProcessor extends AudioWorkletProcessor {
 
   private bypass = false;
   private stereoLink = true;
+  private lookaheadMs = 0;
+  private truePeak = false;
+  private autoRelease = false;
+  private kneeDb = 0;
+  private oversample: 1 | 2 | 4 = 1;
+  private mix = 1;
+  private attackMs = 0.01;
+  private releaseShape: ReleaseShape = "exponential";
+  private latencySamples = 0;
 
   private cpuEma = 0;
   private cpuEmaInitialized = false;
   private cpuPostCountdown = 0;
+  private meterPostCountdown = 0;
 
   private wasm: WasmExports | null = null;
-  private wasmLimiterPtr = 0;
-  private wasmInPtr = 0;
-  private wasmOutPtr = 0;
-  private wasmCapacityFloats = 0;
+  // Set once the DSP has failed (panicked or trapped); the node is silent from then on.
+  private failed = false;
+  private wasmLimiterHandle = 0;
+  // The instance's I/O block (`limiter_get_io_layout`) and views over it, rebuilt when WASM
+  // memory grows. `ioChannels` is how many input planes process() last filled; the rest are
+  // kept silent.
+  private ioPtr = 0;
+  private ioChannels = MAX_CHANNELS;
+  private io: NodeIoViews | null = null;
+  private ioBuffer: ArrayBuffer | null = null;
 
   constructor(options?: any) {
     super();
@@ -74,8 +167,31 @@ class LimiterProcessor extends AudioWorkletProcessor {
       const data = event.data as {
         type?: string;
         params?: Partial<LimiterParams>;
+        state?: ArrayBuffer;
       } | null;
-      if (!data || data.type !== "params" || !data.params) return;
+      if (!data) return;
+      if (data.type === "resetMeters") {
+        if (this.wasm && this.wasmLimiterHandle)
+          this.wasm.limiter_reset_meters(this.wasmLimiterHandle);
+        return;
+      }
+      if (data.type === "reset") {
+        if (this.wasm && this.wasmLimiterHandle)
+          this.wasm.limiter_reset(this.wasmLimiterHandle);
+        return;
+      }
+      if (data.type === "saveState") {
+        this.port.postMessage({ type: "state", state: this.saveState() });
+        return;
+      }
+      if (data.type === "loadState" && data.state) {
+        this.port.postMessage({
+          type: "stateLoaded",
+          result: this.loadState(data.state),
+        });
+        return;
+      }
+      if (data.type !== "params" || !data.params) return;
       this.applyParams(data.params);
     };
   }
@@ -85,18 +201,39 @@ class LimiterProcessor extends AudioWorkletProcessor {
       const { instance } = await WebAssembly.instantiate(wasmBytes, {});
       const exports = instance.exports as any as WasmExports;
       if (!exports?.memory || !exports.limiter_new) return;
+      if (exports.limiter_abi_version?.() !== NODE_ABI_VERSION) {
+        this.port.postMessage({
+          type: "status",
+          worklet: "ready",
+          wasm: "error",
+        });
+        return;
+      }
       this.wasm = exports;
-      this.wasmLimiterPtr = exports.limiter_new(sampleRate);
+      this.wasmLimiterHandle = exports.limiter_new(sampleRate);
       exports.limiter_set_params(
-        this.wasmLimiterPtr,
+        this.wasmLimiterHandle,
         this.ceilingDb,
         this.releaseMs,
         this.makeupDb,
         this.bypass ? 1 : 0,
-        this.stereoLink ? 1 : 0
+        this.stereoLink ? 1 : 0,
+        this.lookaheadMs,
+        this.truePeak ? 1 : 0,
+        this.autoRelease ? 1 : 0,
+        this.kneeDb,
+        this.oversample,
+        this.mix,
+        this.attackMs,
+        Math.max(0, RELEASE_SHAPES.indexOf(this.releaseShape))
+      );
+      this.postLatency();
+      // Negotiate the I/O block here, off the audio thread: process() never allocates in WASM.
+      this.ioPtr = exports.limiter_get_io_layout(
+        this.wasmLimiterHandle,
+        MAX_FRAMES,
+        MAX_CHANNELS
       );
-      // Pre-allocate buffers off the audio thread (process()).
-      this.ensureWasmBuffers(128, 2);
       this.port.postMessage({
         type: "status",
         worklet: "ready",
@@ -124,75 +261,136 @@ class LimiterProcessor extends AudioWorkletProcessor {
     }
     if (patch.bypass != null) this.bypass = !!patch.bypass;
     if (patch.stereoLink != null) this.stereoLink = !!patch.stereoLink;
+    if (patch.lookaheadMs != null) {
+      this.lookaheadMs = clamp(patch.lookaheadMs, 0, 10);
+    }
+    if (patch.truePeak != null) this.truePeak = !!patch.truePeak;
+    if (patch.autoRelease != null) this.autoRelease = !!patch.autoRelease;
+    if (patch.kneeDb != null) this.kneeDb = clamp(patch.kneeDb, 0, 12);
+    if (patch.oversample != null) {
+      this.oversample =
+        patch.oversample >= 4 ? 4 : patch.oversample >= 2 ? 2 : 1;
+    }
+    if (patch.mix != null) this.mix = clamp(patch.mix, 0, 1);
+    if (patch.attackMs != null) {
+      this.attackMs = clamp(patch.attackMs, 0.01, 10);
+    }
+    if (patch.releaseShape != null) this.releaseShape = patch.releaseShape;
 
-    if (this.wasm && this.wasmLimiterPtr) {
+    if (this.wasm && this.wasmLimiterHandle) {
       this.wasm.limiter_set_params(
-        this.wasmLimiterPtr,
+        this.wasmLimiterHandle,
         this.ceilingDb,
         this.releaseMs,
         this.makeupDb,
         this.bypass ? 1 : 0,
-        this.stereoLink ? 1 : 0
+        this.stereoLink ? 1 : 0,
+        this.lookaheadMs,
+        this.truePeak ? 1 : 0,
+        this.autoRelease ? 1 : 0,
+        this.kneeDb,
+        this.oversample,
+        this.mix,
+        this.attackMs,
+        Math.max(0, RELEASE_SHAPES.indexOf(this.releaseShape))
       );
+      this.postLatency();
     }
   }
 
-  private ensureWasmBuffers(frames: number, channels: number): boolean {
+  // Snapshot of the DSP instance (parameters, delay lines, envelopes), for session save or
+  // to carry the node across a worklet restart; null without a live instance.
+  private saveState(): ArrayBuffer | null {
     const wasm = this.wasm;
-    if (!wasm || !this.wasmLimiterPtr) return false;
-    const neededFloats = Math.max(0, frames * channels);
-    if (
-      neededFloats <= this.wasmCapacityFloats &&
-      this.wasmInPtr &&
-      this.wasmOutPtr
-    )
-      return true;
+    if (!wasm || !this.wasmLimiterHandle) return null;
+    const size = wasm.limiter_state_size(this.wasmLimiterHandle);
+    if (!size) return null;
+    const ptr = wasm.wasm_alloc(size);
+    if (!ptr) return null;
+    const written = wasm.limiter_save_state(this.wasmLimiterHandle, ptr, size);
+    const state = written
+      ? new Uint8Array(wasm.memory.buffer, ptr, written).slice().buffer
+      : null;
+    wasm.wasm_free(ptr, size);
+    return state;
+  }
 
-    if (this.wasmInPtr && this.wasmCapacityFloats)
-      wasm.wasm_free(this.wasmInPtr, this.wasmCapacityFloats * 4);
-    if (this.wasmOutPtr && this.wasmCapacityFloats)
-      wasm.wasm_free(this.wasmOutPtr, this.wasmCapacityFloats * 4);
+  // Restores a `saveState` snapshot: 0 refused, 1 parameters only (the snapshot came from an
+  // instance built differently, e.g. at another sample rate), 2 everything. The parameters
+  // mirrored here are read back so later patches don't undo the restored ones.
+  private loadState(state: ArrayBuffer): number {
+    const wasm = this.wasm;
+    if (!wasm || !this.wasmLimiterHandle || !state.byteLength) return 0;
+    const ptr = wasm.wasm_alloc(state.byteLength);
+    if (!ptr) return 0;
+    new Uint8Array(wasm.memory.buffer, ptr, state.byteLength).set(new Uint8Array(state));
+    const result = wasm.limiter_load_state(this.wasmLimiterHandle, ptr, state.byteLength);
+    wasm.wasm_free(ptr, state.byteLength);
+    if (result) {
+      const get = (index: number) => wasm.limiter_get_param(this.wasmLimiterHandle, index);
+      this.ceilingDb = get(0);
+      this.releaseMs = get(1);
+      this.makeupDb = get(2);
+      this.bypass = get(3) >= 0.5;
+      this.stereoLink = get(4) >= 0.5;
+      this.lookaheadMs = get(5);
+      this.truePeak = get(6) >= 0.5;
+      this.autoRelease = get(7) >= 0.5;
+      this.kneeDb = get(8);
+      const oversample = get(9);
+      this.oversample = oversample >= 4 ? 4 : oversample >= 2 ? 2 : 1;
+      this.mix = get(10);
+      this.attackMs = get(11);
+      this.releaseShape = RELEASE_SHAPES[get(12)] ?? "exponential";
+      this.postLatency();
+    }
+    return result;
+  }
+
+  private postLatency() {
+    const wasm = this.wasm;
+    if (!wasm || !this.wasmLimiterHandle) return;
+    const samples = wasm.limiter_latency_samples(this.wasmLimiterHandle);
+    if (samples === this.latencySamples) return;
+    this.latencySamples = samples;
+    this.port.postMessage({ type: "latency", samples });
+  }
 
-    this.wasmCapacityFloats = Math.max(neededFloats, 128 * 2);
-    this.wasmInPtr = wasm.wasm_alloc(this.wasmCapacityFloats * 4);
-    this.wasmOutPtr = wasm.wasm_alloc(this.wasmCapacityFloats * 4);
-    return !!this.wasmInPtr && !!this.wasmOutPtr;
+  private ioViews(): NodeIoViews | null {
+    const wasm = this.wasm;
+    if (!wasm || !this.wasmLimiterHandle || !this.ioPtr) return null;
+    if (!this.io || this.ioBuffer !== wasm.memory.buffer) {
+      this.io = readNodeIo(wasm.memory, this.ioPtr);
+      this.ioBuffer = wasm.memory.buffer;
+    }
+    return this.io;
   }
 
   private processWithWasm(
     input: Float32Array[],
+    key: Float32Array[] | undefined,
     output: Float32Array[],
     frames: number,
     channels: number
   ): boolean {
     const wasm = this.wasm;
     if (!wasm) return false;
-    if (!this.ensureWasmBuffers(frames, channels)) return false;
-
-    const memF32 = new Float32Array(wasm.memory.buffer);
-    const inBase = this.wasmInPtr >>> 2;
-    const outBase = this.wasmOutPtr >>> 2;
+    const io = this.ioViews();
+    if (!io || frames > io.maxFrames || channels > io.inputs.length) return false;
 
-    let idx = 0;
-    for (let i = 0; i < frames; i++) {
-      for (let c = 0; c < channels; c++)
-        memF32[inBase + idx++] = input[c]![i] ?? 0;
+    // The block is as wide as the DSP goes; planes past the active channels carry silence.
+    if (channels !== this.ioChannels) {
+      for (let c = channels; c < io.inputs.length; c++) io.inputs[c]!.fill(0);
+      this.ioChannels = channels;
     }
+    for (let c = 0; c < channels; c++) io.inputs[c]!.set(input[c]!.subarray(0, frames));
+    const keyChannels = key ? Math.min(key.length, io.aux.length) : 0;
+    for (let c = 0; c < keyChannels; c++) io.aux[c]!.set(key![c]!.subarray(0, frames));
 
-    wasm.limiter_process_interleaved(
-      this.wasmLimiterPtr,
-      this.wasmInPtr,
-      this.wasmOutPtr,
-      frames,
-      channels
-    );
-
-    idx = 0;
-    for (let i = 0; i < frames; i++) {
-      for (let c = 0; c < channels; c++)
-        output[c]![i] = memF32[outBase + idx++] ?? 0;
-    }
+    wasm.limiter_process_io_sidechained(this.wasmLimiterHandle, frames, keyChannels);
 
+    // Processing never allocates, so the views are still live.
+    for (let c = 0; c < channels; c++) output[c]!.set(io.outputs[c]!.subarray(0, frames));
     return true;
   }
 
@@ -218,21 +416,72 @@ class LimiterProcessor extends AudioWorkletProcessor {
     const channels = Math.min(input.length, outputChannels);
     for (let c = channels; c < outputChannels; c++) output[c]!.fill(0);
 
-    if (this.bypass) {
-      for (let c = 0; c < channels; c++) output[c]!.set(input[c]!);
+    // Bypass goes to the DSP too: it fades to the dry signal delayed by the look-ahead, so
+    // toggling it neither clicks nor jumps in time.
+    if (this.failed) {
+      for (let c = 0; c < channels; c++) output[c]!.fill(0);
       this.noteCpu(t0, frames);
       return true;
     }
 
-    const ok = this.processWithWasm(input, output, frames, channels);
-    if (!ok) {
+    // Second input is the optional sidechain key; unconnected inputs have no channels.
+    const key = inputs[1];
+    let ok = false;
+    try {
+      ok = this.processWithWasm(
+        input,
+        key && key.length > 0 ? key : undefined,
+        output,
+        frames,
+        channels
+      );
+    } catch {
+      // A panic traps on wasm32; the node records why before it does.
+      this.fail();
+      for (let c = 0; c < channels; c++) output[c]!.fill(0);
+      this.noteCpu(t0, frames);
+      return true;
+    }
+    if (ok && this.wasm!.limiter_last_error_code(this.wasmLimiterHandle) !== NODE_ERROR_NONE) {
+      // Caught inside the module (builds that unwind); it has already written silence.
+      this.fail();
+    } else if (!ok) {
       // Until WASM is ready (or if it failed to init), pass through.
       for (let c = 0; c < channels; c++) output[c]!.set(input[c]!);
+    } else {
+      this.postMeters();
     }
     this.noteCpu(t0, frames);
     return true;
   }
 
+  private fail() {
+    this.failed = true;
+    const wasm = this.wasm;
+    if (!wasm || !this.wasmLimiterHandle) return;
+    const error = readNodeError(
+      wasm.memory,
+      wasm.limiter_last_error_code(this.wasmLimiterHandle),
+      wasm.limiter_last_error_message_ptr(this.wasmLimiterHandle)
+    );
+    this.port.postMessage({ type: "error", code: error.code, message: error.message });
+    this.port.postMessage({ type: "status", worklet: "ready", wasm: "error" });
+  }
+
+  private postMeters() {
+    const io = this.io;
+    if (!io) return;
+    if (this.meterPostCountdown-- > 0) return;
+    // ~30Hz is enough for a smooth readout.
+    this.meterPostCountdown = 12;
+    // The I/O block's meters: last block's and peak gain reduction, in dB.
+    this.port.postMessage({
+      type: "meter",
+      gainReductionDb: io.meters[0] ?? 0,
+      peakGainReductionDb: io.meters[1] ?? 0,
+    });
+  }
+
   private noteCpu(t0: number, frames: number) {
     const t1 = nowMs();
     const elapsedMs = Math.max(0, t1 - t0);
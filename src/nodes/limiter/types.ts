@@ -5,7 +5,14 @@ export type LimiterState = {
   bypass: boolean;
   stereoLink: boolean;
   channelCount: 1 | 2;
-  lookaheadMs: number; // reserved (v1 keeps at 0)
+  lookaheadMs: number; // 0..10
+  truePeak: boolean;
+  autoRelease: boolean;
+  kneeDb: number; // 0..12 (0 = hard knee)
+  oversample: 1 | 2 | 4;
+  mix: number; // 0..1 (1 = fully limited)
+  attackMs: number; // 0.01..10
+  releaseShape: "exponential" | "linear" | "logarithmic";
 };
 
 declare module "../../graph/types" {
@@ -0,0 +1,212 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::loudness::Loudness;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+pub const MAX_CHANNELS: usize = 8;
+
+/// EBU R128 loudness meter: K-weighted momentary, short-term and gated integrated loudness and
+/// the loudness range, per BS.1770 and EBU Tech 3341/3342 (see `dsp_core::loudness`). The
+/// audio is only read; the worklet passes it on. The UI polls the getters.
+pub struct LoudnessMeter {
+    meter: Loudness,
+    events: ParamRing,
+    error: LastError,
+}
+
+impl Node for LoudnessMeter {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    const PARAMS: &'static [ParamDescriptor] = &[];
+    /// The audio is only read; the worklet passes it on.
+    const OUTPUT: bool = false;
+    const METER_COUNT: usize = 4;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        LoudnessMeter {
+            meter: Loudness::new(sample_rate_hz),
+            events: ParamRing::new(),
+            error: LastError::new(),
+        }
+    }
+
+    fn set_param(&mut self, _index: u32, _value: f32) {}
+
+    fn get_param(&self, _index: u32) -> f32 {
+        0.0
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    /// Six channels are taken as 5.1 (L R C LFE Ls Rs: the LFE is left out, the surrounds
+    /// weighted +1.5 dB).
+    fn process(&mut self, input: &[f32], _output: &mut [f32], channels: usize) {
+        self.meter.process(input, channels);
+    }
+
+    /// Momentary, short-term and integrated loudness and the loudness range, as the getters
+    /// read them.
+    fn meters(&self, out: &mut [f32]) {
+        out[0] = self.meter.momentary();
+        out[1] = self.meter.short_term();
+        out[2] = self.meter.integrated();
+        out[3] = self.meter.range();
+    }
+
+    /// Starts a new measurement: clears every reading and the integration history.
+    fn reset(&mut self) {
+        self.meter.reset();
+    }
+}
+
+node_abi::export_node!(LoudnessMeter {
+    abi_version: loudness_abi_version,
+    new: loudness_new,
+    free: loudness_free,
+    param_count: loudness_param_count,
+    param_descriptors: loudness_param_descriptors,
+    set_param: loudness_set_param,
+    get_param: loudness_get_param,
+    param_events: loudness_param_events,
+    process: loudness_process,
+    process_offline: loudness_process_offline,
+    latency_samples: loudness_latency_samples,
+    io_layout: loudness_get_io_layout,
+    process_io: loudness_process_io,
+    reset: loudness_reset,
+    state_size: loudness_state_size,
+    save_state: loudness_save_state,
+    load_state: loudness_load_state,
+    preset_size: loudness_preset_size,
+    save_preset: loudness_save_preset,
+    load_preset: loudness_load_preset,
+    last_error_code: loudness_last_error_code,
+    last_error_message: loudness_last_error_message_ptr,
+});
+
+node_abi::test_params!(LoudnessMeter);
+
+dsp_core::test_block_sizes!(
+    loudness_new,
+    loudness_process(input, output),
+    loudness_free,
+    read: |p| {
+        vec![
+            loudness_get_momentary_lufs(p),
+            loudness_get_short_term_lufs(p),
+            loudness_get_integrated_lufs(p),
+            loudness_get_range_lu(p),
+        ]
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(LoudnessMeter as LoudnessMeterNode);
+
+/// Loudness over the last 400 ms in LUFS, updated every 100 ms; -Infinity before the first
+/// 400 ms.
+#[no_mangle]
+pub extern "C" fn loudness_get_momentary_lufs(handle: Handle) -> f32 {
+    guard(handle, |m: &mut LoudnessMeter| m.meter.momentary()).unwrap_or(f32::NEG_INFINITY)
+}
+
+/// Loudness over the last 3 s in LUFS, updated every 100 ms; -Infinity before the first 3 s.
+#[no_mangle]
+pub extern "C" fn loudness_get_short_term_lufs(handle: Handle) -> f32 {
+    guard(handle, |m: &mut LoudnessMeter| m.meter.short_term()).unwrap_or(f32::NEG_INFINITY)
+}
+
+/// Gated loudness since creation or the last `loudness_reset`, in LUFS; -Infinity until
+/// something passes the -70 LUFS gate.
+#[no_mangle]
+pub extern "C" fn loudness_get_integrated_lufs(handle: Handle) -> f32 {
+    guard(handle, |m: &mut LoudnessMeter| m.meter.integrated()).unwrap_or(f32::NEG_INFINITY)
+}
+
+/// Loudness range (LRA) since creation or the last `loudness_reset`, in LU.
+#[no_mangle]
+pub extern "C" fn loudness_get_range_lu(handle: Handle) -> f32 {
+    guard(handle, |m: &mut LoudnessMeter| m.meter.range()).unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+
+    const RATE: f32 = 48_000.0;
+
+    /// A 1 kHz sine peaking at `db` dBFS on the channels in `on`, `channels` interleaved.
+    fn tone(db: f32, seconds: f32, channels: usize, on: &[usize]) -> Vec<f32> {
+        let a = 10.0_f32.powf(db / 20.0);
+        let mut x = vec![0.0; (seconds * RATE) as usize * channels];
+        for (i, frame) in x.chunks_mut(channels).enumerate() {
+            let s = a * (2.0 * PI * 1_000.0 * i as f32 / RATE).sin();
+            for &ch in on {
+                frame[ch] = s;
+            }
+        }
+        x
+    }
+
+    /// Feeds `x` to the meter in render quanta.
+    fn feed(h: Handle, x: &[f32], channels: usize) {
+        let mut out = vec![0.0; 128 * channels];
+        for block in x.chunks(128 * channels) {
+            let frames = block.len() / channels;
+            loudness_process(h, block.as_ptr(), out.as_mut_ptr(), frames, channels);
+        }
+    }
+
+    /// The EBU Tech 3341 reference, a stereo 1 kHz sine at -23 dBFS, reads -23 LUFS on every
+    /// meter once its window has filled, with no loudness range.
+    #[test]
+    fn reference_tone_reads_minus_23_lufs() {
+        let h = loudness_new(RATE);
+        feed(h, &tone(-23.0, 0.3, 2, &[0, 1]), 2);
+        assert_eq!(loudness_get_momentary_lufs(h), f32::NEG_INFINITY);
+        feed(h, &tone(-23.0, 9.7, 2, &[0, 1]), 2);
+        for lufs in [
+            loudness_get_momentary_lufs(h),
+            loudness_get_short_term_lufs(h),
+            loudness_get_integrated_lufs(h),
+        ] {
+            assert!((lufs + 23.0).abs() < 0.1, "{lufs}");
+        }
+        assert!(loudness_get_range_lu(h) < 0.5);
+
+        loudness_reset(h);
+        assert_eq!(loudness_get_integrated_lufs(h), f32::NEG_INFINITY);
+        assert_eq!(loudness_get_range_lu(h), 0.0);
+        loudness_free(h);
+    }
+
+    /// Six channels are weighed as 5.1: the LFE is left out and a surround reads 1.5 dB over a
+    /// front channel.
+    #[test]
+    fn five_one_weights_lfe_and_surrounds() {
+        let measure = |ch: usize| {
+            let h = loudness_new(RATE);
+            feed(h, &tone(-20.0, 1.0, 6, &[ch]), 6);
+            let lufs = loudness_get_momentary_lufs(h);
+            loudness_free(h);
+            lufs
+        };
+        let front = measure(0);
+        assert!((front - measure(2)).abs() < 0.01);
+        assert!(measure(3) < -70.0);
+        assert!(
+            (measure(4) - front - 1.5).abs() < 0.05,
+            "{}",
+            measure(4) - front
+        );
+    }
+}
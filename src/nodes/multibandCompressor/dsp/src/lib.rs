@@ -0,0 +1,488 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::crossover::{Crossover, MAX_BANDS};
+use dsp_core::envelope::one_pole_coeff;
+use dsp_core::gain::{db_to_lin, lin_to_db_floor};
+use dsp_core::meter::WindowMin;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Multiband::PARAMS`: band `b`'s start at `b * BAND_PARAMS`, in the order of
+// `multiband_set_band`'s arguments; the arguments of `multiband_set_crossovers` follow the
+// last band.
+pub const BAND_PARAMS: u32 = 7;
+pub const PARAM_THRESHOLD_DB: u32 = 0;
+pub const PARAM_RATIO: u32 = 1;
+pub const PARAM_ATTACK_MS: u32 = 2;
+pub const PARAM_RELEASE_MS: u32 = 3;
+pub const PARAM_MAKEUP_DB: u32 = 4;
+pub const PARAM_SOLO: u32 = 5;
+pub const PARAM_BYPASS: u32 = 6;
+pub const PARAM_BANDS: u32 = MAX_BANDS as u32 * BAND_PARAMS;
+pub const PARAM_LOW_HZ: u32 = PARAM_BANDS + 1;
+pub const PARAM_MID_HZ: u32 = PARAM_BANDS + 2;
+pub const PARAM_HIGH_HZ: u32 = PARAM_BANDS + 3;
+
+/// Fixed soft-knee width of every band's gain computer.
+const KNEE_DB: f32 = 6.0;
+
+/// One band's compressor. Detection is linked across channels so the stereo image holds.
+#[derive(Clone, Copy)]
+struct Band {
+    threshold_db: f32,
+    ratio: f32,
+    attack_ms: f32,
+    release_ms: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    makeup_db: f32,
+    makeup: f32,
+    solo: bool,
+    bypass: bool,
+    /// Smoothed gain change in dB (<= 0).
+    env_db: f32,
+    /// Deepest `env_db` per meter window (0 while bypassed or muted by a solo).
+    meter: WindowMin,
+}
+
+impl Band {
+    fn new() -> Self {
+        Self {
+            threshold_db: -18.0,
+            ratio: 3.0,
+            attack_ms: 10.0,
+            release_ms: 120.0,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            makeup_db: 0.0,
+            makeup: 1.0,
+            solo: false,
+            bypass: false,
+            env_db: 0.0,
+            meter: WindowMin::new(0.0),
+        }
+    }
+
+    fn update_coeffs(&mut self, sample_rate_hz: f32) {
+        self.attack_coeff = one_pole_coeff(self.attack_ms, sample_rate_hz);
+        self.release_coeff = one_pole_coeff(self.release_ms, sample_rate_hz);
+    }
+
+    /// Returns the linear gain for this sample given the linked band peak.
+    #[inline]
+    fn gain(&mut self, peak: f32) -> f32 {
+        let over = lin_to_db_floor(peak) - self.threshold_db;
+        let slope = 1.0 / self.ratio - 1.0;
+        let half_knee = KNEE_DB * 0.5;
+        let target = if over <= -half_knee {
+            0.0
+        } else if over < half_knee {
+            let x = over + half_knee;
+            slope * x * x / (2.0 * KNEE_DB)
+        } else {
+            slope * over
+        };
+        let c = if target < self.env_db {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.env_db = self.env_db * c + (1.0 - c) * target;
+        db_to_lin(self.env_db) * self.makeup
+    }
+}
+
+#[repr(C)]
+pub struct Multiband {
+    bands: [Band; MAX_BANDS],
+    band_count: usize,
+    /// Split points as set, and as used (each at least an octave above the one before).
+    crossover_set_hz: [f32; MAX_BANDS - 1],
+    crossover_hz: [f32; MAX_BANDS - 1],
+    crossovers: [Crossover; MAX_CHANNELS],
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Multiband {
+    fn update_crossovers(&mut self) {
+        let [low, mid, high] = self.crossover_set_hz;
+        let nyquist_ish = self.sample_rate_hz * 0.45;
+        let low = clamp(low, 20.0, nyquist_ish / 4.0);
+        let mid = clamp(mid, low * 2.0, nyquist_ish / 2.0);
+        let high = clamp(high, mid * 2.0, nyquist_ish);
+        self.crossover_hz = [low, mid, high];
+        let freqs = &self.crossover_hz[..self.band_count - 1];
+        for xo in &mut self.crossovers {
+            xo.set(self.band_count, freqs, self.sample_rate_hz);
+        }
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let frames = input.len() / channels;
+        let bands = self.band_count;
+        let any_solo = self.bands[..bands].iter().any(|b| b.solo);
+        let mut split = [[0.0_f32; MAX_BANDS]; MAX_CHANNELS];
+        for i in 0..frames {
+            let frame = i * channels;
+            let mut peaks = [0.0_f32; MAX_BANDS];
+            for ch in 0..channels {
+                self.crossovers[ch].split(input[frame + ch], &mut split[ch]);
+                for (p, x) in peaks.iter_mut().zip(&split[ch][..bands]) {
+                    *p = p.max(x.abs());
+                }
+            }
+            let mut gains = [0.0_f32; MAX_BANDS];
+            for (b, band) in self.bands[..bands].iter_mut().enumerate() {
+                if any_solo && !band.solo {
+                    band.meter.push(0.0);
+                    continue;
+                }
+                gains[b] = if band.bypass {
+                    1.0
+                } else {
+                    band.gain(peaks[b])
+                };
+                band.meter.push(if band.bypass { 0.0 } else { band.env_db });
+            }
+            for ch in 0..channels {
+                output[frame + ch] = split[ch][..bands]
+                    .iter()
+                    .zip(&gains)
+                    .map(|(x, g)| x * g)
+                    .sum();
+            }
+        }
+    }
+}
+
+/// `Band`'s settings for band `$n`, in order, then the crossover.
+macro_rules! band_params {
+    ($($n:literal),*) => {
+        &[
+            $(
+                ParamDescriptor::linear(
+                    concat!("Band ", $n, " threshold"),
+                    "dB",
+                    -60.0,
+                    0.0,
+                    -18.0,
+                ),
+                ParamDescriptor::log(concat!("Band ", $n, " ratio"), "", 1.0, 20.0, 3.0),
+                ParamDescriptor::log(concat!("Band ", $n, " attack"), "ms", 0.01, 500.0, 10.0),
+                ParamDescriptor::log(concat!("Band ", $n, " release"), "ms", 1.0, 5000.0, 120.0),
+                ParamDescriptor::linear(concat!("Band ", $n, " makeup"), "dB", -24.0, 24.0, 0.0),
+                ParamDescriptor::toggle(concat!("Band ", $n, " solo"), false),
+                ParamDescriptor::toggle(concat!("Band ", $n, " bypass"), false),
+            )*
+            ParamDescriptor::stepped("Bands", "", 3.0, MAX_BANDS as f32, 3.0),
+            ParamDescriptor::log("Low split", "Hz", 20.0, 5000.0, 200.0),
+            ParamDescriptor::log("Mid split", "Hz", 40.0, 10_000.0, 2000.0),
+            ParamDescriptor::log("High split", "Hz", 80.0, 20_000.0, 8000.0),
+        ]
+    };
+}
+
+impl Node for Multiband {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// Per band, counting from the lowest: its compressor, then `Solo` (soloing any band mutes
+    /// the unsoloed ones) and `Bypass` (the band passes through its crossover output
+    /// unprocessed). Then `Bands`, 3 or 4, and the split points (`High split` is unused with
+    /// 3 bands), each kept at least an octave above the previous one.
+    const PARAMS: &'static [ParamDescriptor] = band_params!(1, 2, 3, 4);
+    const METER_COUNT: usize = MAX_BANDS;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut m = Multiband {
+            bands: [Band::new(); MAX_BANDS],
+            band_count: 3,
+            crossover_set_hz: [200.0, 2000.0, 8000.0],
+            crossover_hz: [200.0, 2000.0, 8000.0],
+            crossovers: [Crossover::new(); MAX_CHANNELS],
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        for band in &mut m.bands {
+            band.update_coeffs(sample_rate_hz);
+        }
+        m.update_crossovers();
+        m
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_BANDS => {
+                self.band_count = clamp(value, 3.0, MAX_BANDS as f32).round() as usize;
+                for band in &mut self.bands[self.band_count..] {
+                    band.meter.reset();
+                }
+            }
+            PARAM_LOW_HZ => self.crossover_set_hz[0] = clamp(value, 20.0, 5000.0),
+            PARAM_MID_HZ => self.crossover_set_hz[1] = clamp(value, 40.0, 10_000.0),
+            PARAM_HIGH_HZ => self.crossover_set_hz[2] = clamp(value, 80.0, 20_000.0),
+            _ => {
+                let sample_rate_hz = self.sample_rate_hz;
+                let Some(b) = self.bands.get_mut((index / BAND_PARAMS) as usize) else {
+                    return;
+                };
+                match index % BAND_PARAMS {
+                    PARAM_THRESHOLD_DB => b.threshold_db = clamp(value, -60.0, 0.0),
+                    PARAM_RATIO => b.ratio = clamp(value, 1.0, 20.0),
+                    PARAM_ATTACK_MS => b.attack_ms = clamp(value, 0.01, 500.0),
+                    PARAM_RELEASE_MS => b.release_ms = clamp(value, 1.0, 5000.0),
+                    PARAM_MAKEUP_DB => {
+                        b.makeup_db = clamp(value, -24.0, 24.0);
+                        b.makeup = db_to_lin(b.makeup_db);
+                    }
+                    PARAM_SOLO => b.solo = clamp(value, 0.0, 1.0) >= 0.5,
+                    PARAM_BYPASS => b.bypass = clamp(value, 0.0, 1.0) >= 0.5,
+                    _ => return,
+                }
+                b.update_coeffs(sample_rate_hz);
+                return;
+            }
+        }
+        self.update_crossovers();
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_BANDS => self.band_count as f32,
+            PARAM_LOW_HZ => self.crossover_set_hz[0],
+            PARAM_MID_HZ => self.crossover_set_hz[1],
+            PARAM_HIGH_HZ => self.crossover_set_hz[2],
+            _ => {
+                let Some(b) = self.bands.get((index / BAND_PARAMS) as usize) else {
+                    return 0.0;
+                };
+                match index % BAND_PARAMS {
+                    PARAM_THRESHOLD_DB => b.threshold_db,
+                    PARAM_RATIO => b.ratio,
+                    PARAM_ATTACK_MS => b.attack_ms,
+                    PARAM_RELEASE_MS => b.release_ms,
+                    PARAM_MAKEUP_DB => b.makeup_db,
+                    PARAM_SOLO => u32::from(b.solo) as f32,
+                    PARAM_BYPASS => u32::from(b.bypass) as f32,
+                    _ => 0.0,
+                }
+            }
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Every band's gain reduction, as `multiband_get_band_gain_reduction_db` reads it.
+    fn meters(&self, out: &mut [f32]) {
+        for (m, b) in out.iter_mut().zip(&self.bands) {
+            *m = -b.meter.reading();
+        }
+    }
+
+    /// Clears the crossovers and releases every band's gain reduction.
+    fn reset(&mut self) {
+        for c in &mut self.crossovers {
+            c.clear();
+        }
+        for band in &mut self.bands {
+            band.env_db = 0.0;
+            band.meter.reset();
+        }
+    }
+}
+
+node_abi::export_node!(Multiband {
+    abi_version: multiband_abi_version,
+    new: multiband_new,
+    free: multiband_free,
+    param_count: multiband_param_count,
+    param_descriptors: multiband_param_descriptors,
+    set_param: multiband_set_param,
+    get_param: multiband_get_param,
+    param_events: multiband_param_events,
+    process: multiband_process,
+    process_offline: multiband_process_offline,
+    latency_samples: multiband_latency_samples,
+    io_layout: multiband_get_io_layout,
+    process_io: multiband_process_io,
+    reset: multiband_reset,
+    state_size: multiband_state_size,
+    save_state: multiband_save_state,
+    load_state: multiband_load_state,
+    preset_size: multiband_preset_size,
+    save_preset: multiband_save_preset,
+    load_preset: multiband_load_preset,
+    last_error_code: multiband_last_error_code,
+    last_error_message: multiband_last_error_message_ptr,
+});
+
+node_abi::test_params!(Multiband);
+
+dsp_core::test_block_sizes!(
+    multiband_new,
+    multiband_process(input, output),
+    multiband_free,
+    read: |p| (0..4).map(|b| multiband_get_band_gain_reduction_db(p, b)).collect::<Vec<_>>(),
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Multiband as MultibandNode);
+
+/// `Bands` and the split points in one call, as `multiband_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn multiband_set_crossovers(
+    handle: Handle,
+    bands: u32,
+    low_hz: f32,
+    mid_hz: f32,
+    high_hz: f32,
+) {
+    guard(handle, |m: &mut Multiband| {
+        m.set_param(PARAM_BANDS, bands as f32);
+        m.set_param(PARAM_LOW_HZ, low_hz);
+        m.set_param(PARAM_MID_HZ, mid_hz);
+        m.set_param(PARAM_HIGH_HZ, high_hz);
+    });
+}
+
+/// Band `band`'s settings in one call.
+#[no_mangle]
+pub extern "C" fn multiband_set_band(
+    handle: Handle,
+    band: u32,
+    threshold_db: f32,
+    ratio: f32,
+    attack_ms: f32,
+    release_ms: f32,
+    makeup_db: f32,
+    solo: u32,
+    bypass: u32,
+) {
+    if band as usize >= MAX_BANDS {
+        return;
+    }
+    guard(handle, |m: &mut Multiband| {
+        let base = band * BAND_PARAMS;
+        m.set_param(base + PARAM_THRESHOLD_DB, threshold_db);
+        m.set_param(base + PARAM_RATIO, ratio);
+        m.set_param(base + PARAM_ATTACK_MS, attack_ms);
+        m.set_param(base + PARAM_RELEASE_MS, release_ms);
+        m.set_param(base + PARAM_MAKEUP_DB, makeup_db);
+        m.set_param(base + PARAM_SOLO, solo as f32);
+        m.set_param(base + PARAM_BYPASS, bypass as f32);
+    });
+}
+
+/// Deepest gain reduction of `band` over the last complete 128-frame window, in dB
+/// (positive = reducing).
+#[no_mangle]
+pub extern "C" fn multiband_get_band_gain_reduction_db(handle: Handle, band: u32) -> f32 {
+    guard(handle, |m: &mut Multiband| {
+        m.bands
+            .get(band as usize)
+            .map_or(0.0, |b| -b.meter.reading())
+    })
+    .unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    /// Every band's threshold at 0 dB, so only the bands a test turns down compress.
+    fn multiband(params: &[(u32, f32)]) -> Multiband {
+        let mut m = Multiband::new(RATE);
+        for band in 0..MAX_BANDS as u32 {
+            m.set_param(band * BAND_PARAMS + PARAM_THRESHOLD_DB, 0.0);
+        }
+        for &(index, value) in params {
+            m.set_param(index, value);
+        }
+        m
+    }
+
+    /// Two seconds of 50 Hz (the low band) plus 10 kHz (the high band), each at `amplitude`.
+    fn low_and_high(amplitude: f32) -> Vec<f32> {
+        let w = core::f32::consts::TAU / RATE;
+        (0..96_000)
+            .map(|i| amplitude * ((w * 50.0 * i as f32).sin() + (w * 10_000.0 * i as f32).sin()))
+            .collect()
+    }
+
+    /// The second half of `input` through `m`, past the attack.
+    fn render(m: &mut Multiband, input: &[f32]) -> Vec<f32> {
+        let mut output = render_quanta(input.len(), |span, y| Node::process(m, &input[span], y, 1));
+        output.split_off(input.len() / 2)
+    }
+
+    fn gain_reduction_db(m: &Multiband) -> [f32; MAX_BANDS] {
+        let mut meters = [0.0; MAX_BANDS];
+        Node::meters(m, &mut meters);
+        meters
+    }
+
+    /// Turning the low band down compresses the 50 Hz tone and leaves the 10 kHz one alone.
+    #[test]
+    fn each_band_compresses_only_its_own_range() {
+        let mut m = multiband(&[(PARAM_THRESHOLD_DB, -30.0), (PARAM_RATIO, 10.0)]);
+        let output = render(&mut m, &low_and_high(0.25));
+        let low = amplitude(&output, 50.0);
+        let high = amplitude(&output, 10_000.0);
+        assert!(low < 0.1, "low {low}");
+        assert!((high - 0.25).abs() < 0.01, "high {high}");
+        let [low_db, mid_db, high_db, _] = gain_reduction_db(&m);
+        assert!(low_db > 6.0, "{low_db}");
+        assert_eq!((mid_db, high_db), (0.0, 0.0));
+    }
+
+    /// A bypassed band passes its crossover output as is and meters no reduction.
+    #[test]
+    fn bypass_skips_the_band() {
+        let mut m = multiband(&[
+            (PARAM_THRESHOLD_DB, -30.0),
+            (PARAM_RATIO, 10.0),
+            (PARAM_BYPASS, 1.0),
+        ]);
+        let output = render(&mut m, &low_and_high(0.25));
+        let low = amplitude(&output, 50.0);
+        assert!((low - 0.25).abs() < 0.01, "low {low}");
+        assert_eq!(gain_reduction_db(&m)[0], 0.0);
+    }
+
+    /// Soloing a band mutes the others, whichever band it is.
+    #[test]
+    fn solo_mutes_the_other_bands() {
+        let input = low_and_high(0.25);
+        let mut m = multiband(&[(2 * BAND_PARAMS + PARAM_SOLO, 1.0)]);
+        let output = render(&mut m, &input);
+        assert!(amplitude(&output, 50.0) < 0.005);
+        assert!((amplitude(&output, 10_000.0) - 0.25).abs() < 0.01);
+
+        let mut m = multiband(&[(PARAM_SOLO, 1.0)]);
+        let output = render(&mut m, &input);
+        assert!((amplitude(&output, 50.0) - 0.25).abs() < 0.01);
+        assert!(amplitude(&output, 10_000.0) < 0.005);
+    }
+}
@@ -0,0 +1,345 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::gain::db_to_lin;
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Noise::PARAMS`, in the order of `noise_set_params`' arguments.
+pub const PARAM_COLOR: u32 = 0;
+pub const PARAM_LEVEL_DB: u32 = 1;
+pub const PARAM_WIDTH: u32 = 2;
+
+pub const WHITE: u32 = 0;
+pub const PINK: u32 = 1;
+pub const BROWN: u32 = 2;
+pub const BLUE: u32 = 3;
+
+/// Pole of the brown integrator: -6 dB/octave from about 15 Hz at 48 kHz up, with no drift.
+const BROWN_POLE: f32 = 0.998;
+
+/// Gain per colour bringing each to the same RMS as white (pink and blue measured; brown is
+/// `sqrt(1 - BROWN_POLE^2)`, its integrator's RMS gain).
+const COLOR_GAIN: [f32; 4] = [1.0, 0.3263, 0.063_214, 0.5513];
+
+/// One noise stream: a xorshift32 and the colouring filters' state.
+#[derive(Clone, Copy)]
+struct Generator {
+    rng: u32,
+    pink: [f32; 7],
+    brown: f32,
+    prev_pink: f32,
+}
+
+impl Generator {
+    fn new(seed: u32) -> Self {
+        Self {
+            rng: seed,
+            pink: [0.0; 7],
+            brown: 0.0,
+            prev_pink: 0.0,
+        }
+    }
+
+    /// Uniform in [-1, 1) from a xorshift32.
+    #[inline]
+    fn white(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 7) as f32 / (1u32 << 24) as f32 - 1.0
+    }
+
+    /// Paul Kellet's refined pink filter: -3 dB/octave within 0.05 dB from 9 Hz up.
+    #[inline]
+    fn pink(&mut self) -> f32 {
+        let w = self.white();
+        let b = &mut self.pink;
+        b[0] = 0.99886 * b[0] + w * 0.055_517_9;
+        b[1] = 0.99332 * b[1] + w * 0.075_075_9;
+        b[2] = 0.96900 * b[2] + w * 0.153_852;
+        b[3] = 0.86650 * b[3] + w * 0.310_485_6;
+        b[4] = 0.55000 * b[4] + w * 0.532_952_2;
+        b[5] = -0.7616 * b[5] - w * 0.016_898;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + w * 0.5362;
+        b[6] = w * 0.115_926;
+        pink
+    }
+
+    #[inline]
+    fn next(&mut self, color: u32) -> f32 {
+        let v = match color {
+            PINK => self.pink(),
+            // Leaky-integrated white.
+            BROWN => {
+                self.brown = BROWN_POLE * self.brown + self.white();
+                self.brown
+            }
+            // Differentiated pink: +3 dB/octave.
+            BLUE => {
+                let pink = self.pink();
+                let blue = pink - self.prev_pink;
+                self.prev_pink = pink;
+                blue
+            }
+            _ => self.white(),
+        };
+        v * COLOR_GAIN[color as usize]
+    }
+}
+
+/// Noise source in four colours, white (flat), pink (-3 dB/octave), brown (-6 dB/octave) and
+/// blue (+3 dB/octave), all at the same RMS. `width` cross-fades every channel from one shared
+/// stream to a stream of its own, keeping the level constant.
+pub struct Noise {
+    color: u32,
+    level_db: f32,
+    gain: f32,
+    width: f32,
+    /// Gains of the shared and the channel's own stream.
+    shared_gain: f32,
+    own_gain: f32,
+    shared: Generator,
+    own: [Generator; MAX_CHANNELS],
+    events: ParamRing,
+    error: LastError,
+}
+
+impl Noise {
+    fn render(&mut self, output: &mut [f32], channels: usize) {
+        for out in output.chunks_exact_mut(channels) {
+            let shared = if self.shared_gain > 0.0 {
+                self.shared.next(self.color) * self.shared_gain
+            } else {
+                0.0
+            };
+            for (ch, y) in out.iter_mut().enumerate() {
+                let own = if self.own_gain > 0.0 {
+                    self.own[ch].next(self.color) * self.own_gain
+                } else {
+                    0.0
+                };
+                *y = (shared + own) * self.gain;
+            }
+        }
+    }
+}
+
+impl Node for Noise {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Color` 0 white, 1 pink, 2 brown, 3 blue; `Level` is the RMS relative to full-scale
+    /// white; `Width` 0 makes every channel identical, 1 every channel independent.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Color", "", 0.0, BLUE as f32, WHITE as f32),
+        ParamDescriptor::linear("Level", "dB", -96.0, 0.0, -18.0),
+        ParamDescriptor::linear("Width", "", 0.0, 1.0, 1.0),
+    ];
+    const INPUT: bool = false;
+
+    fn new(_sample_rate_hz: f32) -> Self {
+        let seed = 0x9e37_79b9_u32;
+        Noise {
+            color: WHITE,
+            level_db: -18.0,
+            gain: db_to_lin(-18.0),
+            width: 1.0,
+            shared_gain: 0.0,
+            own_gain: 1.0,
+            shared: Generator::new(seed),
+            own: core::array::from_fn(|ch| Generator::new(seed.wrapping_mul(ch as u32 + 2))),
+            events: ParamRing::new(),
+            error: LastError::new(),
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_COLOR => self.color = clamp(value, 0.0, BLUE as f32).round() as u32,
+            PARAM_LEVEL_DB => {
+                self.level_db = clamp(value, -96.0, 0.0);
+                self.gain = db_to_lin(self.level_db);
+            }
+            PARAM_WIDTH => {
+                // Uncorrelated streams add in power, so these keep the sum's RMS where either
+                // alone is.
+                let width = clamp(value, 0.0, 1.0);
+                let norm = ((1.0 - width).powi(2) + width * width).sqrt();
+                self.width = width;
+                self.shared_gain = (1.0 - width) / norm;
+                self.own_gain = width / norm;
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_COLOR => self.color as f32,
+            PARAM_LEVEL_DB => self.level_db,
+            PARAM_WIDTH => self.width,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, _input: &[f32], output: &mut [f32], channels: usize) {
+        self.render(output, channels);
+    }
+
+    /// Restarts every stream from its seed and clears the colour filters, so the output
+    /// repeats from the start.
+    fn reset(&mut self) {
+        let seed = 0x9e37_79b9_u32;
+        self.shared = Generator::new(seed);
+        self.own = core::array::from_fn(|ch| Generator::new(seed.wrapping_mul(ch as u32 + 2)));
+    }
+}
+
+node_abi::export_node!(Noise {
+    abi_version: noise_abi_version,
+    new: noise_new,
+    free: noise_free,
+    param_count: noise_param_count,
+    param_descriptors: noise_param_descriptors,
+    set_param: noise_set_param,
+    get_param: noise_get_param,
+    param_events: noise_param_events,
+    process: noise_process,
+    process_offline: noise_process_offline,
+    latency_samples: noise_latency_samples,
+    io_layout: noise_get_io_layout,
+    process_io: noise_process_io,
+    reset: noise_reset,
+    state_size: noise_state_size,
+    save_state: noise_save_state,
+    load_state: noise_load_state,
+    preset_size: noise_preset_size,
+    save_preset: noise_save_preset,
+    load_preset: noise_load_preset,
+    last_error_code: noise_last_error_code,
+    last_error_message: noise_last_error_message_ptr,
+});
+
+node_abi::test_params!(Noise);
+
+dsp_core::test_block_sizes!(noise_new, noise_process(input, output), noise_free);
+
+node_abi::test_io_block!(Noise);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Noise as NoiseNode);
+
+/// Every parameter in one call, as `noise_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn noise_set_params(handle: Handle, color: u32, level_db: f32, width: f32) {
+    guard(handle, |n: &mut Noise| {
+        n.set_param(PARAM_COLOR, color as f32);
+        n.set_param(PARAM_LEVEL_DB, level_db);
+        n.set_param(PARAM_WIDTH, width);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::fft::{Complex, Fft};
+
+    const RATE: f32 = 48_000.0;
+    const BLOCK: usize = 4096;
+
+    /// Two seconds of `color` at 0 dB, stereo at `width`, in render quanta.
+    fn render(color: u32, width: f32) -> Vec<f32> {
+        let h = noise_new(RATE);
+        noise_set_params(h, color, 0.0, width);
+        let mut output = vec![0.0; 192_000];
+        for y in output.chunks_mut(256) {
+            noise_process(h, core::ptr::null(), y.as_mut_ptr(), y.len() / 2, 2);
+        }
+        noise_free(h);
+        output
+    }
+
+    fn left(stereo: &[f32]) -> Vec<f32> {
+        stereo.iter().step_by(2).copied().collect()
+    }
+
+    fn rms(x: &[f32]) -> f32 {
+        (x.iter().map(|v| v * v).sum::<f32>() / x.len() as f32).sqrt()
+    }
+
+    /// Power per bin around `hz` (a third of an octave wide), averaged over blocks, in dB.
+    fn density_db(x: &[f32], hz: f32) -> f32 {
+        let fft = Fft::new(BLOCK);
+        let bin = |f: f32| (f * BLOCK as f32 / RATE) as usize;
+        let (lo, hi) = (bin(hz * 0.891), bin(hz * 1.122));
+        let mut power = 0.0;
+        for block in x.chunks_exact(BLOCK) {
+            let mut buf: Vec<Complex> = block.iter().map(|&v| Complex::new(v, 0.0)).collect();
+            fft.forward(&mut buf);
+            power += buf[lo..=hi]
+                .iter()
+                .map(|c| c.norm() * c.norm())
+                .sum::<f32>();
+        }
+        10.0 * (power / (hi - lo + 1) as f32).log10()
+    }
+
+    /// Every colour comes out at white's RMS.
+    #[test]
+    fn colors_share_the_level() {
+        let white = rms(&left(&render(WHITE, 1.0)));
+        assert!((white - 1.0 / 3.0_f32.sqrt()).abs() < 0.01, "{white}");
+        for color in [PINK, BROWN, BLUE] {
+            let ratio = rms(&left(&render(color, 1.0))) / white;
+            assert!((ratio - 1.0).abs() < 0.12, "color {color}: {ratio}");
+        }
+    }
+
+    /// Two octaves up from 1 kHz white stays flat, pink drops 6 dB, brown 12 dB and blue
+    /// rises 6 dB.
+    #[test]
+    fn colors_have_their_slopes() {
+        for (color, slope) in [(WHITE, 0.0), (PINK, -6.0), (BROWN, -12.0), (BLUE, 6.0)] {
+            let x = left(&render(color, 1.0));
+            let change = density_db(&x, 4000.0) - density_db(&x, 1000.0);
+            assert!((change - slope).abs() < 1.0, "color {color}: {change} dB");
+        }
+    }
+
+    /// `Width` 0 gives identical channels, 1 uncorrelated ones, and 0.5 shares half the power.
+    #[test]
+    fn width_decorrelates_channels() {
+        let correlation = |width: f32| {
+            let y = render(PINK, width);
+            let (l, r): (Vec<f32>, Vec<f32>) = y.chunks_exact(2).map(|f| (f[0], f[1])).unzip();
+            let dot = l.iter().zip(&r).map(|(a, b)| a * b).sum::<f32>() / l.len() as f32;
+            dot / (rms(&l) * rms(&r))
+        };
+        assert!((correlation(0.0) - 1.0).abs() < 1e-6);
+        assert!((correlation(0.5) - 0.5).abs() < 0.05);
+        assert!(correlation(1.0).abs() < 0.05);
+    }
+
+    /// `Level` sets the RMS in dB relative to full-scale white.
+    #[test]
+    fn level_scales_the_output() {
+        let h = noise_new(RATE);
+        noise_set_params(h, WHITE, -20.0, 1.0);
+        let mut output = vec![0.0; 48_000];
+        noise_process(h, core::ptr::null(), output.as_mut_ptr(), 48_000, 1);
+        noise_free(h);
+        assert!((rms(&output) * 3.0_f32.sqrt() - 0.1).abs() < 0.002);
+    }
+}
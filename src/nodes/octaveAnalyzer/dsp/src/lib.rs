@@ -0,0 +1,401 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::biquad::Biquad;
+use dsp_core::envelope::one_pole_coeff;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `OctaveAnalyzer::PARAMS`, in the order of `octave_set_params`' arguments.
+pub const PARAM_WEIGHTING: u32 = 0;
+pub const PARAM_INTEGRATION_MS: u32 = 1;
+
+/// Third-octave bands 20 Hz to 20 kHz (base-ten centres, 1000 Hz * 10^(k/10)).
+pub const BANDS: usize = 31;
+const FIRST_BAND: i32 = -17;
+
+pub const Z_WEIGHTING: u32 = 0;
+pub const A_WEIGHTING: u32 = 1;
+pub const C_WEIGHTING: u32 = 2;
+
+/// Bands whose upper edge lies above this fraction of the sample rate can't be built and read
+/// the floor.
+const MAX_EDGE: f64 = 0.49;
+/// Floor of the readings, standing in for silence so the UI only sees finite values.
+const MIN_DB: f32 = -160.0;
+
+fn band_hz(band: usize) -> f32 {
+    1000.0 * 10.0_f32.powf((FIRST_BAND + band as i32) as f32 / 10.0)
+}
+
+/// IEC 61672 A or C weighting at `hz` in dB (0 at 1 kHz); 0 for Z.
+fn weighting_db(weighting: u32, hz: f32) -> f32 {
+    let f2 = hz * hz;
+    let (p1, p2, p3, p4) = (20.6_f32, 107.7_f32, 737.9_f32, 12_194.0_f32);
+    match weighting {
+        A_WEIGHTING => {
+            let r = p4 * p4 * f2 * f2
+                / ((f2 + p1 * p1) * ((f2 + p2 * p2) * (f2 + p3 * p3)).sqrt() * (f2 + p4 * p4));
+            20.0 * r.log10() + 2.0
+        }
+        C_WEIGHTING => {
+            let r = p4 * p4 * f2 / ((f2 + p1 * p1) * (f2 + p4 * p4));
+            20.0 * r.log10() + 0.06
+        }
+        _ => 0.0,
+    }
+}
+
+/// Sixth-order Butterworth band-pass with its 3 dB points at the third-octave edges around
+/// `hz` (the order-3 shape IEC 61260 class 1 filters are usually built as): the third-order
+/// low-pass prototype's poles moved to the band by s -> (s^2 + w0^2) / (B s), prewarped, and
+/// split into three bilinear sections of one pole pair each.
+fn band_sections(hz: f64, sample_rate_hz: f64) -> [Biquad; 3] {
+    let k = 2.0 * sample_rate_hz;
+    let warp = |f: f64| k * (core::f64::consts::PI * f / sample_rate_hz).tan();
+    let (w1, w2) = (
+        warp(hz * 2.0_f64.powf(-1.0 / 6.0)),
+        warp(hz * 2.0_f64.powf(1.0 / 6.0)),
+    );
+    let (w0_sq, b) = (w1 * w2, w2 - w1);
+    let half_sqrt3 = 3.0_f64.sqrt() / 2.0;
+    // Upper-half-plane band-pass poles: each prototype pole p gives the roots of
+    // s^2 - p B s + w0^2, and the conjugate prototype pole their conjugates.
+    let mut poles = [(0.0, 0.0); 3];
+    for (i, p) in [(-1.0, 0.0), (-0.5, half_sqrt3), (-0.5, -half_sqrt3)]
+        .into_iter()
+        .enumerate()
+    {
+        let (hr, hi) = (p.0 * b / 2.0, p.1 * b / 2.0);
+        let (dr, di) = csqrt(hr * hr - hi * hi - w0_sq, 2.0 * hr * hi);
+        poles[i] = if hi + di >= 0.0 {
+            (hr + dr, hi + di)
+        } else {
+            (hr - dr, hi - di)
+        };
+    }
+    poles.map(|(re, im)| {
+        // B s / (s^2 + a1 s + a0) through s = K (1 - z^-1) / (1 + z^-1).
+        let (a1, a0) = (-2.0 * re, re * re + im * im);
+        let d0 = k * k + a1 * k + a0;
+        Biquad::from_coeffs(
+            (b * k / d0) as f32,
+            0.0,
+            (-b * k / d0) as f32,
+            ((2.0 * a0 - 2.0 * k * k) / d0) as f32,
+            ((k * k - a1 * k + a0) / d0) as f32,
+        )
+    })
+}
+
+/// Principal square root of re + i im.
+fn csqrt(re: f64, im: f64) -> (f64, f64) {
+    let m = (re * re + im * im).sqrt();
+    let r = ((m + re) / 2.0).max(0.0).sqrt();
+    let i = ((m - re) / 2.0).max(0.0).sqrt();
+    (r, if im < 0.0 { -i } else { i })
+}
+
+#[derive(Clone, Copy, Default)]
+struct Band {
+    sections: [Biquad; 3],
+    /// Mean square of the band's output.
+    power: f32,
+    active: bool,
+}
+
+/// Third-octave analyzer for bar-graph displays. The channels are summed to mono and split
+/// into 31 bands by sixth-order Butterworth band-passes 3 dB down at the band edges; each
+/// band's mean square is averaged over the integration time and written as dB
+/// RMS (a full-scale sine in a band reads -3 dB) into a buffer the UI reads in place, with
+/// A or C weighting applied at the band centres. The spectral tilt is the least-squares slope
+/// of the band levels in dB per octave. The audio is only read; the worklet passes it on.
+pub struct OctaveAnalyzer {
+    bands: [Band; BANDS],
+    offsets: [f32; BANDS],
+    levels: [f32; BANDS],
+    average: f32,
+    integration_ms: f32,
+    weighting: u32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl OctaveAnalyzer {
+    fn measure(&mut self, input: &[f32], channels: usize) {
+        let scale = 1.0 / channels as f32;
+        let a = self.average;
+        for frame in input.chunks_exact(channels) {
+            let x = frame.iter().filter(|x| x.is_finite()).sum::<f32>() * scale;
+            for b in self.bands.iter_mut().filter(|b| b.active) {
+                let y = b.sections.iter_mut().fold(x, |y, s| s.process(y));
+                b.power = y * y + (b.power - y * y) * a;
+            }
+        }
+        for ((level, b), offset) in self.levels.iter_mut().zip(&self.bands).zip(&self.offsets) {
+            *level = if b.active && b.power > 0.0 {
+                (10.0 * b.power.log10() + offset).max(MIN_DB)
+            } else {
+                MIN_DB
+            };
+        }
+    }
+
+    /// Least-squares slope of the band levels against octaves, over the bands above the floor.
+    fn tilt(&self) -> f32 {
+        let points = self
+            .levels
+            .iter()
+            .enumerate()
+            .filter(|(_, &l)| l > MIN_DB)
+            .map(|(i, &l)| (i as f32 / 3.0, l));
+        let (mut n, mut sx, mut sy, mut sxx, mut sxy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for (x, y) in points {
+            n += 1.0;
+            sx += x;
+            sy += y;
+            sxx += x * x;
+            sxy += x * y;
+        }
+        let den = n * sxx - sx * sx;
+        if n < 2.0 || den <= 0.0 {
+            return 0.0;
+        }
+        (n * sxy - sx * sy) / den
+    }
+}
+
+impl Node for OctaveAnalyzer {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Weighting` 0 Z (flat), 1 A, 2 C; `Integration` is the averaging time (125 ms is
+    /// "fast", 1000 "slow").
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Weighting", "", 0.0, C_WEIGHTING as f32, 0.0),
+        ParamDescriptor::log("Integration", "ms", 10.0, 10_000.0, 125.0),
+    ];
+    /// The audio is only read; the worklet passes it on.
+    const OUTPUT: bool = false;
+    const METER_COUNT: usize = BANDS + 1;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut bands = [Band::default(); BANDS];
+        for (i, b) in bands.iter_mut().enumerate() {
+            let hz = band_hz(i) as f64;
+            let sr = sample_rate_hz as f64;
+            b.active = hz * 2.0_f64.powf(1.0 / 6.0) < MAX_EDGE * sr;
+            if b.active {
+                b.sections = band_sections(hz, sr);
+            }
+        }
+        OctaveAnalyzer {
+            bands,
+            offsets: [0.0; BANDS],
+            levels: [MIN_DB; BANDS],
+            average: one_pole_coeff(125.0, sample_rate_hz),
+            integration_ms: 125.0,
+            weighting: Z_WEIGHTING,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_WEIGHTING => {
+                self.weighting = clamp(value, 0.0, C_WEIGHTING as f32).round() as u32;
+                for (i, offset) in self.offsets.iter_mut().enumerate() {
+                    *offset = weighting_db(self.weighting, band_hz(i));
+                }
+            }
+            PARAM_INTEGRATION_MS => {
+                self.integration_ms = clamp(value, 10.0, 10_000.0);
+                self.average = one_pole_coeff(self.integration_ms, self.sample_rate_hz);
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_WEIGHTING => self.weighting as f32,
+            PARAM_INTEGRATION_MS => self.integration_ms,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], _output: &mut [f32], channels: usize) {
+        self.measure(input, channels);
+    }
+
+    /// The band levels as `octave_levels` holds them, then the tilt.
+    fn meters(&self, out: &mut [f32]) {
+        out[..BANDS].copy_from_slice(&self.levels);
+        out[BANDS] = self.tilt();
+    }
+
+    /// Clears the band filters and drops every reading to the floor.
+    fn reset(&mut self) {
+        for b in &mut self.bands {
+            for s in &mut b.sections {
+                s.clear();
+            }
+            b.power = 0.0;
+        }
+        self.levels = [MIN_DB; BANDS];
+    }
+}
+
+node_abi::export_node!(OctaveAnalyzer {
+    abi_version: octave_abi_version,
+    new: octave_new,
+    free: octave_free,
+    param_count: octave_param_count,
+    param_descriptors: octave_param_descriptors,
+    set_param: octave_set_param,
+    get_param: octave_get_param,
+    param_events: octave_param_events,
+    process: octave_process,
+    process_offline: octave_process_offline,
+    latency_samples: octave_latency_samples,
+    io_layout: octave_get_io_layout,
+    process_io: octave_process_io,
+    reset: octave_reset,
+    state_size: octave_state_size,
+    save_state: octave_save_state,
+    load_state: octave_load_state,
+    preset_size: octave_preset_size,
+    save_preset: octave_save_preset,
+    load_preset: octave_load_preset,
+    last_error_code: octave_last_error_code,
+    last_error_message: octave_last_error_message_ptr,
+});
+
+node_abi::test_params!(OctaveAnalyzer);
+
+dsp_core::test_block_sizes!(
+    octave_new,
+    octave_process(input, output),
+    octave_free,
+    read: |p| {
+        let mut out = unsafe { core::slice::from_raw_parts(octave_levels(p), BANDS) }.to_vec();
+        out.push(octave_get_tilt_db_per_octave(p));
+        out
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(OctaveAnalyzer as OctaveAnalyzerNode);
+
+/// Every parameter in one call, as `octave_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn octave_set_params(handle: Handle, weighting: u32, integration_ms: f32) {
+    guard(handle, |o: &mut OctaveAnalyzer| {
+        o.set_param(PARAM_WEIGHTING, weighting as f32);
+        o.set_param(PARAM_INTEGRATION_MS, integration_ms);
+    });
+}
+
+/// The `BANDS` band levels in dB (floored at -160, as are bands too close to Nyquist), lowest
+/// band first, for the UI to read in place. Stays put for the node's lifetime.
+#[no_mangle]
+pub extern "C" fn octave_levels(handle: Handle) -> *const f32 {
+    guard(handle, |o: &mut OctaveAnalyzer| o.levels.as_ptr()).unwrap_or(core::ptr::null())
+}
+
+/// Exact centre of `band` in Hz (the UI labels it with the nominal value), for the UI.
+#[no_mangle]
+pub extern "C" fn octave_band_hz(band: u32) -> f32 {
+    if band as usize >= BANDS {
+        return 0.0;
+    }
+    band_hz(band as usize)
+}
+
+/// Spectral tilt in dB per octave (negative falls towards the top), for the UI.
+#[no_mangle]
+pub extern "C" fn octave_get_tilt_db_per_octave(handle: Handle) -> f32 {
+    guard(handle, |o: &mut OctaveAnalyzer| o.tilt()).unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// Band levels after two seconds of a full-scale sine at `hz`.
+    fn levels(hz: f32, weighting: u32) -> [f32; BANDS] {
+        let h = octave_new(RATE);
+        octave_set_params(h, weighting, 125.0);
+        let x: Vec<f32> = (0..96_000)
+            .map(|i| (2.0 * PI * hz * i as f32 / RATE).sin())
+            .collect();
+        render_quanta(x.len(), |span, y| {
+            octave_process(h, x[span].as_ptr(), y.as_mut_ptr(), y.len(), 1)
+        });
+        let mut levels = [0.0; BANDS];
+        levels.copy_from_slice(unsafe { core::slice::from_raw_parts(octave_levels(h), BANDS) });
+        octave_free(h);
+        levels
+    }
+
+    /// A full-scale sine at a band's centre reads -3 dB RMS there and over 30 dB down two bands
+    /// away; at a band edge it reads 3 dB lower in both bands either side.
+    #[test]
+    fn bands_split_at_their_edges() {
+        for band in [7, 17, 27] {
+            let l = levels(band_hz(band), Z_WEIGHTING);
+            assert!((l[band] + 3.01).abs() < 0.1, "{band}: {}", l[band]);
+            assert!(l[band - 2] < -30.0 && l[band + 2] < -30.0, "{band}");
+        }
+        let edge = band_hz(17) * 2.0_f32.powf(1.0 / 6.0);
+        let l = levels(edge, Z_WEIGHTING);
+        assert!((l[17] + 6.02).abs() < 0.2, "{}", l[17]);
+        assert!((l[18] + 6.02).abs() < 0.2, "{}", l[18]);
+    }
+
+    /// A and C weighting follow IEC 61672 at the band centres.
+    #[test]
+    fn weights_band_levels() {
+        // 100 Hz: A -19.1 dB, C -0.3 dB.
+        let (a, c) = (
+            levels(band_hz(7), A_WEIGHTING),
+            levels(band_hz(7), C_WEIGHTING),
+        );
+        assert!((a[7] + 3.01 + 19.1).abs() < 0.15, "{}", a[7]);
+        assert!((c[7] + 3.01 + 0.3).abs() < 0.15, "{}", c[7]);
+        for weighting in [A_WEIGHTING, C_WEIGHTING] {
+            assert!(weighting_db(weighting, 1000.0).abs() < 0.01);
+        }
+        assert!((weighting_db(A_WEIGHTING, 10_000.0) + 2.5).abs() < 0.1);
+    }
+
+    /// The tilt is the slope of the band levels in dB per octave, skipping floored bands.
+    #[test]
+    fn tilt_fits_band_levels() {
+        let mut o = OctaveAnalyzer::new(RATE);
+        for (i, level) in o.levels.iter_mut().enumerate() {
+            *level = if i < 3 { MIN_DB } else { -20.0 - i as f32 };
+        }
+        assert!((o.tilt() + 3.0).abs() < 1e-4, "{}", o.tilt());
+    }
+}
@@ -0,0 +1,354 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::biquad::{Biquad, Response};
+use dsp_core::envelope::{one_pole_coeff, EnvelopeFollower};
+use dsp_core::gain::db_to_lin;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Octaver::PARAMS`, in the order of `octaver_set_params`' arguments.
+pub const PARAM_SUB1: u32 = 0;
+pub const PARAM_SUB2: u32 = 1;
+pub const PARAM_DRY: u32 = 2;
+pub const PARAM_CUTOFF_HZ: u32 = 3;
+pub const PARAM_GATE_DB: u32 = 4;
+
+/// Tracking band: the detector only looks at what is left after these filters.
+const TRACK_LOW_HZ: f32 = 30.0;
+const TRACK_HIGH_HZ: f32 = 400.0;
+/// Schmitt trigger thresholds as a fraction of the tracked envelope.
+const HYSTERESIS: f32 = 0.15;
+const MIN_HZ: f32 = 20.0;
+const MAX_HZ: f32 = 1000.0;
+const GATE_MS: f32 = 5.0;
+
+/// Analog-style octaver. A mono sum of the input is band-limited to the bass range and its
+/// upward zero crossings (with hysteresis) clock two flip-flops, one dividing by two and one
+/// by four. Their square waves, scaled by the input envelope and low-passed, are the -1 and
+/// -2 octave voices; they stay phase-locked to the input because they are clocked by it.
+#[repr(C)]
+pub struct Octaver {
+    sub1: f32,
+    sub2: f32,
+    dry: f32,
+    cutoff_hz: f32,
+    gate_db: f32,
+    gate: f32,
+    track: [Biquad; 2],
+    envelope: EnvelopeFollower,
+    /// Schmitt trigger state: true once the signal has gone above the upper threshold.
+    high: bool,
+    flip1: bool,
+    flip2: bool,
+    since_crossing: u32,
+    frequency_hz: f32,
+    open: f32,
+    gate_coeff: f32,
+    tone1: [Biquad; 2],
+    tone2: [Biquad; 2],
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Octaver {
+    fn set_tone(&mut self, cutoff_hz: f32) {
+        let sr = self.sample_rate_hz;
+        for f in self.tone1.iter_mut().chain(self.tone2.iter_mut()) {
+            f.set(Response::Lowpass, cutoff_hz, 0.707, sr);
+        }
+    }
+
+    /// Advances the tracker by one mono sample and returns the envelope.
+    fn track(&mut self, x: f32) -> f32 {
+        let v = self.track[0].process(x);
+        let v = self.track[1].process(v);
+        let env = self.envelope.process(v.abs());
+        self.since_crossing = self.since_crossing.saturating_add(1);
+        if self.high {
+            if v < -HYSTERESIS * env {
+                self.high = false;
+            }
+        } else if v > HYSTERESIS * env {
+            self.high = true;
+            let hz = self.sample_rate_hz / self.since_crossing as f32;
+            self.since_crossing = 0;
+            if (MIN_HZ..=MAX_HZ).contains(&hz) {
+                self.frequency_hz = hz;
+            }
+            self.flip1 = !self.flip1;
+            if self.flip1 {
+                self.flip2 = !self.flip2;
+            }
+        }
+        env
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let scale = 1.0 / channels as f32;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            let env = self.track(inp.iter().sum::<f32>() * scale);
+            let target = if env > self.gate { 1.0 } else { 0.0 };
+            self.open = target + (self.open - target) * self.gate_coeff;
+
+            let level = env * self.open;
+            let mut s1 = if self.flip1 { level } else { -level };
+            let mut s2 = if self.flip2 { level } else { -level };
+            for f in &mut self.tone1 {
+                s1 = f.process(s1);
+            }
+            for f in &mut self.tone2 {
+                s2 = f.process(s2);
+            }
+            let sub = s1 * self.sub1 + s2 * self.sub2;
+            for (x, y) in inp.iter().zip(out.iter_mut()) {
+                *y = *x * self.dry + sub;
+            }
+        }
+    }
+}
+
+impl Node for Octaver {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Sub 1`, `Sub 2` and `Dry` are the -1 octave, -2 octave and input levels; `Cutoff` is
+    /// the low-pass on the sub voices (12 dB/oct twice); below `Gate` of tracked level the subs
+    /// fade out instead of following noise.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Sub 1", "", 0.0, 1.0, 0.7),
+        ParamDescriptor::linear("Sub 2", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Dry", "", 0.0, 1.0, 1.0),
+        ParamDescriptor::log("Cutoff", "Hz", 40.0, 2000.0, 200.0),
+        ParamDescriptor::linear("Gate", "dB", -90.0, 0.0, -50.0),
+    ];
+    const METER_COUNT: usize = 1;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut high = Biquad::default();
+        high.set(Response::Highpass, TRACK_LOW_HZ, 0.707, sample_rate_hz);
+        let mut low = Biquad::default();
+        low.set(Response::Lowpass, TRACK_HIGH_HZ, 0.707, sample_rate_hz);
+        let mut o = Octaver {
+            sub1: 0.7,
+            sub2: 0.0,
+            dry: 1.0,
+            cutoff_hz: 200.0,
+            gate_db: -50.0,
+            gate: db_to_lin(-50.0),
+            track: [high, low],
+            envelope: EnvelopeFollower::new(2.0, 60.0, sample_rate_hz),
+            high: false,
+            flip1: false,
+            flip2: false,
+            since_crossing: 0,
+            frequency_hz: 0.0,
+            open: 0.0,
+            gate_coeff: one_pole_coeff(GATE_MS, sample_rate_hz),
+            tone1: [Biquad::default(); 2],
+            tone2: [Biquad::default(); 2],
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        o.set_tone(200.0);
+        o
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_SUB1 => self.sub1 = clamp(value, 0.0, 1.0),
+            PARAM_SUB2 => self.sub2 = clamp(value, 0.0, 1.0),
+            PARAM_DRY => self.dry = clamp(value, 0.0, 1.0),
+            PARAM_CUTOFF_HZ => {
+                self.cutoff_hz = clamp(value, 40.0, 2000.0);
+                self.set_tone(self.cutoff_hz.min(self.sample_rate_hz * 0.45));
+            }
+            PARAM_GATE_DB => {
+                self.gate_db = clamp(value, -90.0, 0.0);
+                self.gate = db_to_lin(self.gate_db);
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_SUB1 => self.sub1,
+            PARAM_SUB2 => self.sub2,
+            PARAM_DRY => self.dry,
+            PARAM_CUTOFF_HZ => self.cutoff_hz,
+            PARAM_GATE_DB => self.gate_db,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Tracked frequency as `octaver_get_frequency_hz` reads it.
+    fn meters(&self, out: &mut [f32]) {
+        out[0] = self.frequency_hz;
+    }
+
+    /// Forgets the tracked pitch and clears the tracking and tone filters and the gate.
+    fn reset(&mut self) {
+        for f in self
+            .track
+            .iter_mut()
+            .chain(&mut self.tone1)
+            .chain(&mut self.tone2)
+        {
+            f.clear();
+        }
+        self.envelope.reset(0.0);
+        self.high = false;
+        self.flip1 = false;
+        self.flip2 = false;
+        self.since_crossing = 0;
+        self.frequency_hz = 0.0;
+        self.open = 0.0;
+    }
+}
+
+node_abi::export_node!(Octaver {
+    abi_version: octaver_abi_version,
+    new: octaver_new,
+    free: octaver_free,
+    param_count: octaver_param_count,
+    param_descriptors: octaver_param_descriptors,
+    set_param: octaver_set_param,
+    get_param: octaver_get_param,
+    param_events: octaver_param_events,
+    process: octaver_process,
+    process_offline: octaver_process_offline,
+    latency_samples: octaver_latency_samples,
+    io_layout: octaver_get_io_layout,
+    process_io: octaver_process_io,
+    reset: octaver_reset,
+    state_size: octaver_state_size,
+    save_state: octaver_save_state,
+    load_state: octaver_load_state,
+    preset_size: octaver_preset_size,
+    save_preset: octaver_save_preset,
+    load_preset: octaver_load_preset,
+    last_error_code: octaver_last_error_code,
+    last_error_message: octaver_last_error_message_ptr,
+});
+
+node_abi::test_params!(Octaver);
+
+dsp_core::test_block_sizes!(octaver_new, octaver_process(input, output), octaver_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Octaver as OctaverNode);
+
+/// Every parameter in one call, as `octaver_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn octaver_set_params(
+    handle: Handle,
+    sub1: f32,
+    sub2: f32,
+    dry: f32,
+    cutoff_hz: f32,
+    gate_db: f32,
+) {
+    guard(handle, |o: &mut Octaver| {
+        o.set_param(PARAM_SUB1, sub1);
+        o.set_param(PARAM_SUB2, sub2);
+        o.set_param(PARAM_DRY, dry);
+        o.set_param(PARAM_CUTOFF_HZ, cutoff_hz);
+        o.set_param(PARAM_GATE_DB, gate_db);
+    });
+}
+
+/// Last tracked input fundamental in Hz (0 before anything has been tracked).
+#[no_mangle]
+pub extern "C" fn octaver_get_frequency_hz(handle: Handle) -> f32 {
+    guard(handle, |o: &mut Octaver| o.frequency_hz).unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::TAU;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    /// An octaver with no dry signal, then `params`.
+    fn octaver(params: &[(u32, f32)]) -> Octaver {
+        let mut o = Octaver::new(RATE);
+        o.set_param(PARAM_DRY, 0.0);
+        for &(index, value) in params {
+            o.set_param(index, value);
+        }
+        o
+    }
+
+    fn render(o: &mut Octaver, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(o, &input[span], y, 1))
+    }
+
+    /// 2 s of a sine at `hz` and `level`.
+    fn sine(hz: f32, level: f32) -> Vec<f32> {
+        let w = TAU * hz / RATE;
+        (0..96_000).map(|i| level * (w * i as f32).sin()).collect()
+    }
+
+    /// The tracker reads the input's fundamental, as the meter reports it.
+    #[test]
+    fn tracks_the_fundamental() {
+        let mut o = octaver(&[]);
+        render(&mut o, &sine(120.0, 0.5));
+        let mut meters = [0.0];
+        Node::meters(&o, &mut meters);
+        assert!((meters[0] - 120.0).abs() < 0.5, "{}", meters[0]);
+    }
+
+    /// `Sub 1` sounds an octave below the input and `Sub 2` two octaves below.
+    #[test]
+    fn subs_sound_one_and_two_octaves_down() {
+        let x = sine(120.0, 0.5);
+        let sub1 = render(&mut octaver(&[(PARAM_SUB1, 1.0)]), &x);
+        let sub2 = render(&mut octaver(&[(PARAM_SUB1, 0.0), (PARAM_SUB2, 1.0)]), &x);
+        for (y, hz) in [(sub1, 60.0), (sub2, 30.0)] {
+            let main = amplitude(&y, hz);
+            assert!(main > 0.1, "{hz}: {main}");
+            for other in [30.0, 60.0, 120.0].into_iter().filter(|&f| f != hz) {
+                assert!(amplitude(&y, other) < 0.2 * main, "{hz}: {other}");
+            }
+        }
+    }
+
+    /// Below `Gate` the subs fade out instead of following the noise floor.
+    #[test]
+    fn gate_silences_quiet_input() {
+        let quiet = sine(120.0, db_to_lin(-60.0));
+        let y = render(
+            &mut octaver(&[(PARAM_SUB1, 1.0), (PARAM_GATE_DB, -50.0)]),
+            &quiet,
+        );
+        assert!(y[48_000..].iter().all(|v| v.abs() < 1e-6));
+        let y = render(
+            &mut octaver(&[(PARAM_SUB1, 1.0), (PARAM_GATE_DB, -70.0)]),
+            &quiet,
+        );
+        assert!(amplitude(&y, 60.0) > 1e-4);
+    }
+}
@@ -0,0 +1,425 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+/// Longest capture per channel, in frames.
+pub const MAX_FRAMES: usize = 32_768;
+
+// Indices into `Oscilloscope::PARAMS`, in the order of `scope_set_params`' arguments.
+pub const PARAM_WINDOW_MS: u32 = 0;
+pub const PARAM_MODE: u32 = 1;
+pub const PARAM_LEVEL: u32 = 2;
+pub const PARAM_HOLD_OFF_MS: u32 = 3;
+pub const PARAM_TRIGGER_CHANNEL: u32 = 4;
+
+/// Captures back to back without waiting for a trigger.
+pub const FREE_RUN: u32 = 0;
+pub const RISING: u32 = 1;
+pub const FALLING: u32 = 2;
+
+/// How far the signal has to go back past the level before the next edge counts, so noise
+/// riding on a slow edge doesn't trigger twice.
+const HYSTERESIS: f32 = 0.01;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum State {
+    /// Frames left before the trigger is armed again.
+    HoldOff(usize),
+    Waiting,
+    /// Frames captured so far.
+    Capturing(usize),
+}
+
+/// Oscilloscope capture. Once a trigger fires (or straight away when free-running), the
+/// next `window` frames of every channel are captured; a finished capture is copied to the
+/// display buffer the UI reads in place, and the trigger re-arms after the hold-off. Edges
+/// are found on one channel with a little hysteresis, and the capture starts on the frame
+/// just before the crossing, with the crossing's fractional position reported so the UI can
+/// shift the trace by it and keep a periodic waveform still. The audio is only read; the
+/// worklet passes it on.
+pub struct Oscilloscope {
+    mode: u32,
+    level: f32,
+    trigger_channel: usize,
+    window_ms: f32,
+    window: usize,
+    hold_off_ms: f32,
+    hold_off: usize,
+    state: State,
+    armed: bool,
+    prev: [f32; MAX_CHANNELS],
+    offset: f32,
+    /// Planar, `MAX_FRAMES` per channel.
+    capture: Vec<f32>,
+    display: Vec<f32>,
+    display_frames: usize,
+    display_channels: usize,
+    display_offset: f32,
+    captures: u32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Oscilloscope {
+    /// Whether `x` after `prev` crosses the level in the trigger's direction; arms on the way
+    /// back.
+    fn edge(&mut self, prev: f32, x: f32) -> bool {
+        let (prev, x, level) = if self.mode == FALLING {
+            (-prev, -x, -self.level)
+        } else {
+            (prev, x, self.level)
+        };
+        if x < level - HYSTERESIS {
+            self.armed = true;
+        }
+        if self.armed && prev < level && x >= level {
+            self.armed = false;
+            self.offset = (level - prev) / (x - prev);
+            return true;
+        }
+        false
+    }
+
+    fn store(&mut self, pos: usize, frame: &[f32]) {
+        for (ch, &x) in frame.iter().enumerate() {
+            self.capture[ch * MAX_FRAMES + pos] = x;
+        }
+    }
+
+    fn measure(&mut self, input: &[f32], channels: usize) {
+        for frame in input.chunks_exact(channels) {
+            let mut clean = [0.0; MAX_CHANNELS];
+            for (c, &x) in clean.iter_mut().zip(frame) {
+                *c = if x.is_finite() { x } else { 0.0 };
+            }
+            let frame = &clean[..channels];
+            let prev = self.prev;
+            self.state = match self.state {
+                State::HoldOff(0) => State::Waiting,
+                State::HoldOff(n) => State::HoldOff(n - 1),
+                s => s,
+            };
+            if self.state == State::Waiting {
+                let ch = self.trigger_channel.min(channels - 1);
+                if self.mode == FREE_RUN {
+                    self.offset = 0.0;
+                    self.state = State::Capturing(0);
+                } else if self.edge(prev[ch], frame[ch]) {
+                    self.store(0, &prev[..channels]);
+                    self.state = State::Capturing(1);
+                }
+            }
+            if let State::Capturing(pos) = self.state {
+                self.store(pos, frame);
+                if pos + 1 == self.window {
+                    for ch in 0..channels {
+                        let trace = ch * MAX_FRAMES..ch * MAX_FRAMES + self.window;
+                        self.display[trace.clone()].copy_from_slice(&self.capture[trace]);
+                    }
+                    self.display_frames = self.window;
+                    self.display_channels = channels;
+                    self.display_offset = self.offset;
+                    self.captures = self.captures.wrapping_add(1);
+                    self.state = State::HoldOff(self.hold_off);
+                } else {
+                    self.state = State::Capturing(pos + 1);
+                }
+            }
+            self.prev[..channels].copy_from_slice(frame);
+        }
+    }
+}
+
+impl Node for Oscilloscope {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Window` is captured per trace (at most `MAX_FRAMES` frames); `Mode` 0 free-run, 1
+    /// rising edge, 2 falling edge; `Level` is what the edge has to cross on `Trigger channel`;
+    /// `Hold-off` runs after a capture before the trigger re-arms. Changing the window or mode
+    /// abandons a capture in progress.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::log("Window", "ms", 1.0, 500.0, 20.0),
+        ParamDescriptor::stepped("Mode", "", 0.0, FALLING as f32, RISING as f32),
+        ParamDescriptor::linear("Level", "", -1.0, 1.0, 0.0),
+        ParamDescriptor::linear("Hold-off", "ms", 0.0, 1000.0, 0.0),
+        ParamDescriptor::stepped("Trigger channel", "", 0.0, (MAX_CHANNELS - 1) as f32, 0.0),
+    ];
+    /// The audio is only read; the worklet passes it on.
+    const OUTPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        Oscilloscope {
+            mode: RISING,
+            level: 0.0,
+            trigger_channel: 0,
+            window_ms: 20.0,
+            window: ((sample_rate_hz * 0.02) as usize).clamp(2, MAX_FRAMES),
+            hold_off_ms: 0.0,
+            hold_off: 0,
+            state: State::Waiting,
+            armed: false,
+            prev: [0.0; MAX_CHANNELS],
+            offset: 0.0,
+            capture: vec![0.0; MAX_CHANNELS * MAX_FRAMES],
+            display: vec![0.0; MAX_CHANNELS * MAX_FRAMES],
+            display_frames: 0,
+            display_channels: 0,
+            display_offset: 0.0,
+            captures: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_WINDOW_MS => {
+                self.window_ms = clamp(value, 1.0, 500.0);
+                let window =
+                    ((self.window_ms / 1000.0 * self.sample_rate_hz) as usize).clamp(2, MAX_FRAMES);
+                if window != self.window {
+                    self.state = State::Waiting;
+                    self.armed = false;
+                }
+                self.window = window;
+            }
+            PARAM_MODE => {
+                let mode = clamp(value, 0.0, FALLING as f32).round() as u32;
+                if mode != self.mode {
+                    self.state = State::Waiting;
+                    self.armed = false;
+                }
+                self.mode = mode;
+            }
+            PARAM_LEVEL => self.level = clamp(value, -1.0, 1.0),
+            PARAM_HOLD_OFF_MS => {
+                self.hold_off_ms = clamp(value, 0.0, 1000.0);
+                self.hold_off = (self.hold_off_ms / 1000.0 * self.sample_rate_hz) as usize;
+            }
+            PARAM_TRIGGER_CHANNEL => {
+                self.trigger_channel =
+                    clamp(value, 0.0, (MAX_CHANNELS - 1) as f32).round() as usize;
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_WINDOW_MS => self.window_ms,
+            PARAM_MODE => self.mode as f32,
+            PARAM_LEVEL => self.level,
+            PARAM_HOLD_OFF_MS => self.hold_off_ms,
+            PARAM_TRIGGER_CHANNEL => self.trigger_channel as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], _output: &mut [f32], channels: usize) {
+        self.measure(input, channels);
+    }
+
+    /// Abandons any capture under way and waits for a fresh trigger; the trace on display is
+    /// kept.
+    fn reset(&mut self) {
+        self.state = State::Waiting;
+        self.armed = false;
+        self.prev = [0.0; MAX_CHANNELS];
+        self.offset = 0.0;
+    }
+}
+
+node_abi::export_node!(Oscilloscope {
+    abi_version: scope_abi_version,
+    new: scope_new,
+    free: scope_free,
+    param_count: scope_param_count,
+    param_descriptors: scope_param_descriptors,
+    set_param: scope_set_param,
+    get_param: scope_get_param,
+    param_events: scope_param_events,
+    process: scope_process,
+    process_offline: scope_process_offline,
+    latency_samples: scope_latency_samples,
+    io_layout: scope_get_io_layout,
+    process_io: scope_process_io,
+    reset: scope_reset,
+    state_size: scope_state_size,
+    save_state: scope_save_state,
+    load_state: scope_load_state,
+    preset_size: scope_preset_size,
+    save_preset: scope_save_preset,
+    load_preset: scope_load_preset,
+    last_error_code: scope_last_error_code,
+    last_error_message: scope_last_error_message_ptr,
+});
+
+node_abi::test_params!(Oscilloscope);
+
+dsp_core::test_block_sizes!(
+    scope_new,
+    scope_process(input, output),
+    scope_free,
+    read: |p| {
+        let trace = unsafe { core::slice::from_raw_parts(scope_buffer(p), MAX_FRAMES * 2) };
+        let mut out = trace.to_vec();
+        out.extend([scope_trigger_offset(p), scope_capture_count(p) as f32]);
+        out
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Oscilloscope as OscilloscopeNode);
+
+/// Every parameter in one call, as `scope_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn scope_set_params(
+    handle: Handle,
+    window_ms: f32,
+    mode: u32,
+    level: f32,
+    hold_off_ms: f32,
+    trigger_channel: u32,
+) {
+    guard(handle, |s: &mut Oscilloscope| {
+        s.set_param(PARAM_WINDOW_MS, window_ms);
+        s.set_param(PARAM_MODE, mode as f32);
+        s.set_param(PARAM_LEVEL, level);
+        s.set_param(PARAM_HOLD_OFF_MS, hold_off_ms);
+        s.set_param(PARAM_TRIGGER_CHANNEL, trigger_channel as f32);
+    });
+}
+
+/// The last complete trace, planar: channel `c` starts at float `c * MAX_FRAMES`. Stays put
+/// for the node's lifetime; only rewritten whole when a capture completes.
+#[no_mangle]
+pub extern "C" fn scope_buffer(handle: Handle) -> *const f32 {
+    guard(handle, |s: &mut Oscilloscope| s.display.as_ptr()).unwrap_or(core::ptr::null())
+}
+
+/// Frames per channel in the last trace (0 before the first).
+#[no_mangle]
+pub extern "C" fn scope_trace_frames(handle: Handle) -> u32 {
+    guard(handle, |s: &mut Oscilloscope| s.display_frames as u32).unwrap_or(0)
+}
+
+/// Channels in the last trace.
+#[no_mangle]
+pub extern "C" fn scope_trace_channels(handle: Handle) -> u32 {
+    guard(handle, |s: &mut Oscilloscope| s.display_channels as u32).unwrap_or(0)
+}
+
+/// Where the trigger crossing falls after the trace's first frame, 0–1 frames; drawing the
+/// trace shifted left by it keeps the edge in one place.
+#[no_mangle]
+pub extern "C" fn scope_trigger_offset(handle: Handle) -> f32 {
+    guard(handle, |s: &mut Oscilloscope| s.display_offset).unwrap_or(0.0)
+}
+
+/// Counts the traces completed (wrapping), so the UI can tell a new one from the last.
+#[no_mangle]
+pub extern "C" fn scope_capture_count(handle: Handle) -> u32 {
+    guard(handle, |s: &mut Oscilloscope| s.captures).unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+
+    const RATE: f32 = 48_000.0;
+
+    /// One second of a 100 Hz sine, inverted on channel 0 and upright on channel 1.
+    fn stereo_sine() -> Vec<f32> {
+        (0..48_000)
+            .flat_map(|i| {
+                let x = (2.0 * PI * 100.0 * i as f32 / RATE).sin();
+                [-x, x]
+            })
+            .collect()
+    }
+
+    /// Feeds stereo `x` to the scope in render quanta.
+    fn feed(h: Handle, x: &[f32]) {
+        let mut out = [0.0; 256];
+        for block in x.chunks(256) {
+            scope_process(h, block.as_ptr(), out.as_mut_ptr(), block.len() / 2, 2);
+        }
+    }
+
+    fn trace(h: Handle, channel: usize) -> &'static [f32] {
+        let frames = scope_trace_frames(h) as usize;
+        unsafe { core::slice::from_raw_parts(scope_buffer(h).add(channel * MAX_FRAMES), frames) }
+    }
+
+    /// An edge on the trigger channel starts the trace on the frame before the crossing, with
+    /// the crossing's fractional position reported; every channel is captured.
+    #[test]
+    fn triggers_on_edges_at_the_level() {
+        for (mode, sign) in [(RISING, 1.0), (FALLING, -1.0)] {
+            let h = scope_new(RATE);
+            scope_set_params(h, 10.0, mode, 0.5 * sign, 0.0, 1);
+            feed(h, &stereo_sine());
+            assert_eq!(scope_trace_frames(h), 480);
+            assert_eq!(scope_trace_channels(h), 2);
+            let t = trace(h, 1);
+            assert!(
+                sign * t[0] < 0.5 && sign * t[1] >= 0.5,
+                "{mode}: {} {}",
+                t[0],
+                t[1]
+            );
+            let crossing = t[0] + (t[1] - t[0]) * scope_trigger_offset(h);
+            assert!((crossing - 0.5 * sign).abs() < 1e-5, "{mode}: {crossing}");
+            assert_eq!(trace(h, 0)[100], -t[100]);
+            scope_free(h);
+        }
+    }
+
+    /// After a capture the trigger waits out the hold-off: a 10 ms trace and 25 ms hold-off
+    /// catch every fourth cycle of 100 Hz.
+    #[test]
+    fn hold_off_skips_edges() {
+        let h = scope_new(RATE);
+        scope_set_params(h, 10.0, RISING, 0.5, 25.0, 1);
+        feed(h, &stereo_sine());
+        assert_eq!(scope_capture_count(h), 25);
+        scope_free(h);
+    }
+
+    /// Free-running captures back to back; a signal that never crosses the level never
+    /// triggers.
+    #[test]
+    fn free_runs_or_waits_for_an_edge() {
+        let h = scope_new(RATE);
+        scope_set_params(h, 10.0, FREE_RUN, 0.0, 0.0, 0);
+        feed(h, &[0.25; 96_000]);
+        assert_eq!(scope_capture_count(h), 100);
+        assert_eq!(scope_trigger_offset(h), 0.0);
+
+        scope_set_params(h, 10.0, RISING, 0.5, 0.0, 0);
+        feed(h, &[0.25; 96_000]);
+        assert_eq!(scope_capture_count(h), 100);
+        scope_free(h);
+    }
+}
@@ -0,0 +1,689 @@
+// The push and pull exports go through buffers the worklet got from us; null is the only
+// invalid value we can detect.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod voice;
+
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use dsp_core::fft::Fft;
+use dsp_core::offline::{self, CHUNK_FRAMES};
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+use voice::Voice;
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+/// Every channel carries its own spectra and FIFOs, so this node stops at stereo.
+pub const MAX_CHANNELS: usize = 2;
+
+// Indices into `PhaseVocoder::PARAMS`, in the order of `pvoc_set_params`' arguments.
+pub const PARAM_SEMITONES: u32 = 0;
+pub const PARAM_STRETCH: u32 = 1;
+pub const PARAM_PHASE_LOCK: u32 = 2;
+
+/// Analysis window length in seconds (rounded up to a power of two in samples).
+const WINDOW_SECONDS: f32 = 0.04;
+/// Frames overlap by 4x, where a squared Hann window sums to a constant.
+const OVERLAP: usize = 4;
+/// `process` runs this many windows behind its input: enough for the frames behind any output
+/// sample to be complete at every pitch.
+const LATENCY_WINDOWS: usize = 3;
+
+/// Phase vocoder with independent pitch and time. Frames are resynthesised at a fixed hop
+/// while the analysis hop follows `stretch * pitch`, which stretches the input by that much;
+/// the result is then resampled by `pitch`, leaving the duration scaled by `stretch` alone.
+/// Output length differs from input length, so besides `process`, which runs at a stretch of
+/// 1 behind a fixed latency, the caller can push input and pull whatever output is ready.
+pub struct PhaseVocoder {
+    fft: Fft,
+    window: Vec<f32>,
+    hop: usize,
+    channels: usize,
+    voices: Vec<Voice>,
+    /// Pending input per channel; `input[..][0]` is absolute sample `consumed`.
+    input: Vec<Vec<f32>>,
+    consumed: u64,
+    /// Where the next analysis frame starts, in `input` samples.
+    analysis_pos: f64,
+    prev_start: Option<u64>,
+    /// Vocoder output waiting to be resampled, and the read position in it.
+    stretched: Vec<Vec<f32>>,
+    read_pos: f64,
+    semitones: f32,
+    pitch: f32,
+    stretch: f32,
+    /// Stretch the stream runs at: `stretch` through the push and pull exports, 1 through
+    /// `process`.
+    rate: f32,
+    phase_lock: bool,
+    /// Output frames owed for the input pushed so far, and those already pulled.
+    expected: f64,
+    emitted: u64,
+    finished: bool,
+    /// Frames of silence `process` still owes before its first pulled frame.
+    warmup: usize,
+    events: ParamRing,
+    error: LastError,
+}
+
+fn hermite(buf: &[f32], pos: f64) -> f32 {
+    let i = pos as usize;
+    let t = (pos - i as f64) as f32;
+    let ym1 = buf[i.saturating_sub(1)];
+    let y0 = buf[i];
+    let y1 = buf[i + 1];
+    let y2 = buf[i + 2];
+    let c1 = 0.5 * (y1 - ym1);
+    let c2 = ym1 - 2.5 * y0 + 2.0 * y1 - 0.5 * y2;
+    let c3 = 0.5 * (y2 - ym1) + 1.5 * (y0 - y1);
+    ((c3 * t + c2) * t + c1) * t + y0
+}
+
+impl PhaseVocoder {
+    fn n(&self) -> usize {
+        self.fft.len()
+    }
+
+    fn input_capacity(&self) -> usize {
+        self.n() * 4
+    }
+
+    fn stretched_capacity(&self) -> usize {
+        self.n() * 2 + self.hop
+    }
+
+    /// Starts over. The input is primed with half a window of silence so the first frame is
+    /// centred on the first real sample, and the read position skips the matching half
+    /// window of output: pulled sample 0 lines up with pushed sample 0.
+    fn restart(&mut self) {
+        let half = self.n() / 2;
+        for v in &mut self.voices {
+            v.clear();
+        }
+        for buf in &mut self.input {
+            buf.clear();
+            buf.resize(half, 0.0);
+        }
+        for buf in &mut self.stretched {
+            buf.clear();
+        }
+        self.consumed = 0;
+        self.analysis_pos = 0.0;
+        self.prev_start = None;
+        self.read_pos = half as f64;
+        self.expected = 0.0;
+        self.emitted = 0;
+        self.finished = false;
+        self.warmup = LATENCY_WINDOWS * self.n();
+    }
+
+    fn compact_input(&mut self) {
+        let len = self.input[0].len();
+        let drop = (self.analysis_pos.floor() as usize).min(len);
+        if drop == 0 {
+            return;
+        }
+        for buf in &mut self.input {
+            buf.drain(..drop);
+        }
+        self.consumed += drop as u64;
+        self.analysis_pos -= drop as f64;
+    }
+
+    fn compact_stretched(&mut self) {
+        let len = self.stretched[0].len();
+        let drop = (self.read_pos as usize).saturating_sub(1).min(len);
+        if drop == 0 {
+            return;
+        }
+        for buf in &mut self.stretched {
+            buf.drain(..drop);
+        }
+        self.read_pos -= drop as f64;
+    }
+
+    fn push_interleaved(&mut self, input: &[f32], channels: usize, stretch: f32) -> usize {
+        if self.finished {
+            return 0;
+        }
+        self.compact_input();
+        let free = self.input_capacity() - self.input[0].len();
+        let frames = (input.len() / channels).min(free);
+        for (ch, buf) in self.input.iter_mut().enumerate() {
+            let src = ch % channels;
+            buf.extend(input[..frames * channels].chunks_exact(channels).map(|f| {
+                if f[src].is_finite() {
+                    f[src]
+                } else {
+                    0.0
+                }
+            }));
+        }
+        self.rate = stretch;
+        self.expected += frames as f64 * stretch as f64;
+        frames
+    }
+
+    /// A frame can run once a full window is buffered, or at any time after `finish` (the
+    /// missing tail is read as silence).
+    fn frame_ready(&mut self) -> bool {
+        self.compact_input();
+        let start = self.analysis_pos.floor() as usize;
+        self.finished || self.input[0].len() >= start + self.n()
+    }
+
+    fn run_frame(&mut self) {
+        let n = self.n();
+        let start = self.analysis_pos.floor() as usize;
+        let abs = self.consumed + start as u64;
+        let analysis_hop = self.prev_start.map_or(self.hop, |p| (abs - p) as usize);
+        self.prev_start = Some(abs);
+        for ((voice, input), out) in self
+            .voices
+            .iter_mut()
+            .zip(&self.input)
+            .zip(&mut self.stretched)
+        {
+            let from = start.min(input.len());
+            let to = (start + n).min(input.len());
+            voice.process(
+                &self.fft,
+                &self.window,
+                &input[from..to],
+                analysis_hop,
+                self.phase_lock,
+            );
+            voice.take(out);
+        }
+        self.analysis_pos += self.hop as f64 / (self.rate * self.pitch) as f64;
+    }
+
+    fn pull_interleaved(&mut self, output: &mut [f32], channels: usize) -> usize {
+        let owed = if self.finished {
+            self.expected.round()
+        } else {
+            self.expected.floor()
+        } as u64;
+        let mut written = 0;
+        for frame in output.chunks_exact_mut(channels) {
+            if self.emitted >= owed {
+                break;
+            }
+            self.compact_stretched();
+            while self.read_pos as usize + 2 >= self.stretched[0].len() {
+                let room = self.stretched_capacity() - self.stretched[0].len();
+                if room < self.hop || !self.frame_ready() {
+                    return written;
+                }
+                self.run_frame();
+            }
+            for (ch, y) in frame.iter_mut().enumerate() {
+                *y = hermite(&self.stretched[ch % self.channels], self.read_pos);
+            }
+            self.read_pos += self.pitch as f64;
+            self.emitted += 1;
+            written += 1;
+        }
+        written
+    }
+
+    /// Pushes a block at a stretch of 1 and pulls as much back, `LATENCY_WINDOWS` windows
+    /// behind; output that isn't ready is silence.
+    fn stream(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let frames = input.len() / channels;
+        let (mut pushed, mut pulled) = (0, 0);
+        while pushed < frames || pulled < frames {
+            let silence = self.warmup.min(frames - pulled);
+            output[pulled * channels..(pulled + silence) * channels].fill(0.0);
+            self.warmup -= silence;
+            pulled += silence;
+            let taken = self.push_interleaved(&input[pushed * channels..], channels, 1.0);
+            pushed += taken;
+            let n = self.pull_interleaved(&mut output[pulled * channels..], channels);
+            pulled += n;
+            if taken == 0 && n == 0 {
+                output[pulled * channels..].fill(0.0);
+                break;
+            }
+        }
+    }
+}
+
+impl Node for PhaseVocoder {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Pitch` shifts by semitones; `Stretch` scales the duration (2 = twice as long) of input
+    /// pushed from then on, and is ignored by `process`; `Phase lock` locks each peak's
+    /// neighbouring bins to it.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Pitch", "st", -24.0, 24.0, 0.0),
+        ParamDescriptor::log("Stretch", "x", 0.25, 4.0, 1.0),
+        ParamDescriptor::toggle("Phase lock", true),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let fft = Fft::new((WINDOW_SECONDS * sample_rate_hz.max(8000.0)) as usize);
+        let n = fft.len();
+        let window: Vec<f32> = (0..n)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / n as f32).cos())
+            .collect();
+        let voices = (0..MAX_CHANNELS)
+            .map(|_| Voice::new(&window, n / OVERLAP))
+            .collect();
+        let mut p = PhaseVocoder {
+            window,
+            hop: n / OVERLAP,
+            channels: MAX_CHANNELS,
+            voices,
+            input: (0..MAX_CHANNELS)
+                .map(|_| Vec::with_capacity(n * 4))
+                .collect(),
+            consumed: 0,
+            analysis_pos: 0.0,
+            prev_start: None,
+            stretched: (0..MAX_CHANNELS)
+                .map(|_| Vec::with_capacity(n * 2 + n / OVERLAP))
+                .collect(),
+            read_pos: 0.0,
+            semitones: 0.0,
+            pitch: 1.0,
+            stretch: 1.0,
+            rate: 1.0,
+            phase_lock: true,
+            expected: 0.0,
+            emitted: 0,
+            finished: false,
+            warmup: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            fft,
+        };
+        p.restart();
+        p
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_SEMITONES => {
+                self.semitones = clamp(value, -24.0, 24.0);
+                self.pitch = (self.semitones / 12.0).exp2();
+            }
+            PARAM_STRETCH => self.stretch = clamp(value, 0.25, 4.0),
+            PARAM_PHASE_LOCK => self.phase_lock = clamp(value, 0.0, 1.0) >= 0.5,
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_SEMITONES => self.semitones,
+            PARAM_STRETCH => self.stretch,
+            PARAM_PHASE_LOCK => u32::from(self.phase_lock) as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.stream(input, output, channels);
+    }
+
+    /// `process`'s delay, `LATENCY_WINDOWS` windows. Pulled sample 0 lines up with pushed
+    /// sample 0, so the push and pull exports have nothing to compensate (output just becomes
+    /// ready about a window after its input is pushed).
+    fn latency_samples(&self) -> u32 {
+        (LATENCY_WINDOWS * self.n()) as u32
+    }
+
+    /// Drops all buffered input and output and starts a new stream.
+    fn reset(&mut self) {
+        self.restart();
+    }
+}
+
+node_abi::export_node!(PhaseVocoder {
+    abi_version: pvoc_abi_version,
+    new: pvoc_new,
+    free: pvoc_free,
+    param_count: pvoc_param_count,
+    param_descriptors: pvoc_param_descriptors,
+    set_param: pvoc_set_param,
+    get_param: pvoc_get_param,
+    param_events: pvoc_param_events,
+    process: pvoc_process,
+    process_offline: pvoc_process_offline,
+    latency_samples: pvoc_latency_samples,
+    io_layout: pvoc_get_io_layout,
+    process_io: pvoc_process_io,
+    reset: pvoc_reset,
+    state_size: pvoc_state_size,
+    save_state: pvoc_save_state,
+    load_state: pvoc_load_state,
+    preset_size: pvoc_preset_size,
+    save_preset: pvoc_save_preset,
+    load_preset: pvoc_load_preset,
+    last_error_code: pvoc_last_error_code,
+    last_error_message: pvoc_last_error_message_ptr,
+});
+
+node_abi::test_params!(PhaseVocoder);
+
+dsp_core::test_block_sizes!(
+    pvoc_new,
+    pvoc_process(input, output),
+    pvoc_free,
+    setup: |p| pvoc_set_params(p, 3.0, 1.0, 1),
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(PhaseVocoder as PhaseVocoderNode);
+
+/// Every parameter in one call, as `pvoc_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn pvoc_set_params(handle: Handle, semitones: f32, stretch: f32, phase_lock: u32) {
+    guard(handle, |p: &mut PhaseVocoder| {
+        p.set_param(PARAM_SEMITONES, semitones);
+        p.set_param(PARAM_STRETCH, stretch);
+        p.set_param(PARAM_PHASE_LOCK, phase_lock as f32);
+    });
+}
+
+/// Queues up to `frames` interleaved frames at the current `Stretch` and returns how many were
+/// taken; the rest have to be pushed again after pulling. `channels` is the layout of `in_ptr`
+/// (a mono buffer feeds both channels). Nothing is taken after `pvoc_finish`.
+#[no_mangle]
+pub extern "C" fn pvoc_push_interleaved(
+    handle: Handle,
+    in_ptr: *const f32,
+    frames: usize,
+    channels: usize,
+) -> u32 {
+    if in_ptr.is_null() {
+        return 0;
+    }
+    let channels = channels.clamp(1, MAX_CHANNELS);
+    let n = frames.saturating_mul(channels);
+    let input = unsafe { core::slice::from_raw_parts(in_ptr, n) };
+    guard(handle, |p: &mut PhaseVocoder| {
+        p.push_interleaved(input, channels, p.stretch) as u32
+    })
+    .unwrap_or(0)
+}
+
+/// Marks the end of the input so the last frames can be flushed; pulls then run until the
+/// output is `Stretch` times the input length.
+#[no_mangle]
+pub extern "C" fn pvoc_finish(handle: Handle) {
+    guard(handle, |p: &mut PhaseVocoder| p.finished = true);
+}
+
+/// Writes up to `frames` interleaved frames of output and returns how many were ready. Fewer
+/// than asked means more input is needed (or, after `pvoc_finish`, that the stream is done).
+#[no_mangle]
+pub extern "C" fn pvoc_pull_interleaved(
+    handle: Handle,
+    out_ptr: *mut f32,
+    frames: usize,
+    channels: usize,
+) -> u32 {
+    if out_ptr.is_null() {
+        return 0;
+    }
+    let channels = channels.clamp(1, MAX_CHANNELS);
+    let n = frames.saturating_mul(channels);
+    let output = unsafe { core::slice::from_raw_parts_mut(out_ptr, n) };
+    guard(handle, |p: &mut PhaseVocoder| {
+        p.pull_interleaved(output, channels) as u32
+    })
+    .unwrap_or(0)
+}
+
+/// The whole stream in one call: pushes `in_frames` frames from `in_ptr` in
+/// `dsp_core::offline::CHUNK_FRAMES` pieces, pulling as it goes, then finishes and drains.
+/// Output is `Stretch` times as long as the input, so `out_ptr` has room for `out_frames` and
+/// the number written is returned. `pvoc_process_offline` renders `process`, whose output is
+/// as long as its input; this takes both lengths.
+#[no_mangle]
+pub extern "C" fn pvoc_stretch_offline(
+    handle: Handle,
+    in_ptr: *const f32,
+    in_frames: usize,
+    out_ptr: *mut f32,
+    out_frames: usize,
+    channels: usize,
+) -> u32 {
+    if in_ptr.is_null() || out_ptr.is_null() {
+        return 0;
+    }
+    let channels = channels.clamp(1, MAX_CHANNELS);
+    let input = unsafe { core::slice::from_raw_parts(in_ptr, in_frames.saturating_mul(channels)) };
+    let output =
+        unsafe { core::slice::from_raw_parts_mut(out_ptr, out_frames.saturating_mul(channels)) };
+    guard(handle, |p: &mut PhaseVocoder| {
+        let mut written = 0;
+        let mut pull = |p: &mut PhaseVocoder| loop {
+            let end = output.len().min((written + CHUNK_FRAMES) * channels);
+            let n = p.pull_interleaved(&mut output[written * channels..end], channels);
+            written += n;
+            if n < CHUNK_FRAMES {
+                break;
+            }
+        };
+        let mut at = 0;
+        for span in offline::chunks(in_frames) {
+            while at < span.end {
+                let stretch = p.stretch;
+                at += p.push_interleaved(
+                    &input[at * channels..span.end * channels],
+                    channels,
+                    stretch,
+                );
+                pull(p);
+            }
+        }
+        p.finished = true;
+        pull(p);
+        written as u32
+    })
+    .unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::{self, Buffers, CHANNELS};
+    use dsp_core::fft::Complex;
+
+    /// Bins in `dominant_bin`'s FFT: 5.86 Hz apart at 48 kHz.
+    const BINS: usize = 8_192;
+
+    /// Both channels carrying a sine at FFT bin `bin`.
+    fn tone(bin: f32, frames: usize) -> Vec<f32> {
+        let w = core::f32::consts::TAU * bin / BINS as f32;
+        (0..frames)
+            .flat_map(|i| [(w * i as f32).sin(); CHANNELS])
+            .collect()
+    }
+
+    /// The loudest bin of the left channel's `BINS` frames from frame `at`.
+    fn dominant_bin(interleaved: &[f32], at: usize) -> usize {
+        let mut buf: Vec<Complex> = interleaved[at * CHANNELS..]
+            .chunks(CHANNELS)
+            .take(BINS)
+            .map(|frame| Complex::new(frame[0], 0.0))
+            .collect();
+        Fft::new(BINS).forward(&mut buf);
+        (1..BINS / 2)
+            .max_by(|&a, &b| buf[a].norm().total_cmp(&buf[b].norm()))
+            .unwrap()
+    }
+
+    fn stream(semitones: f32, stretch: f32, input: &[f32]) -> Vec<f32> {
+        stream_locked(semitones, stretch, 1, input)
+    }
+
+    fn stream_locked(semitones: f32, stretch: f32, phase_lock: u32, input: &[f32]) -> Vec<f32> {
+        let frames = input.len() / CHANNELS;
+        let p = pvoc_new(blocksize::SAMPLE_RATE);
+        pvoc_set_params(p, semitones, stretch, phase_lock);
+        let mut out = vec![0.0; 2 * frames * CHANNELS];
+        let mut written = 0;
+        let mut at = 0;
+        while at < frames {
+            let taken =
+                pvoc_push_interleaved(p, input[at * CHANNELS..].as_ptr(), frames - at, CHANNELS);
+            at += taken as usize;
+            written += pvoc_pull_interleaved(
+                p,
+                out[written * CHANNELS..].as_mut_ptr(),
+                2 * frames - written,
+                CHANNELS,
+            ) as usize;
+        }
+        pvoc_finish(p);
+        written += pvoc_pull_interleaved(
+            p,
+            out[written * CHANNELS..].as_mut_ptr(),
+            2 * frames - written,
+            CHANNELS,
+        ) as usize;
+        pvoc_free(p);
+        out.truncate(written * CHANNELS);
+        out
+    }
+
+    /// Pulled output only depends on the input, not on how it was pushed and pulled.
+    #[test]
+    fn streaming_is_independent_of_block_size() {
+        blocksize::assert_independent(|spans| {
+            let p = pvoc_new(blocksize::SAMPLE_RATE);
+            pvoc_set_params(p, 3.0, 1.3, 1);
+            let io = Buffers::new();
+            let mut out = Vec::new();
+            let mut chunk = vec![0.0; 4_096 * CHANNELS];
+            let mut pull = |out: &mut Vec<f32>| loop {
+                let n = pvoc_pull_interleaved(p, chunk.as_mut_ptr(), 4_096, CHANNELS) as usize;
+                out.extend_from_slice(&chunk[..n * CHANNELS]);
+                if n == 0 {
+                    break;
+                }
+            };
+            for span in spans {
+                let mut at = span.start;
+                while at < span.end {
+                    let taken = pvoc_push_interleaved(p, io.input_at(at), span.end - at, CHANNELS);
+                    at += taken as usize;
+                    pull(&mut out);
+                }
+            }
+            pvoc_finish(p);
+            pull(&mut out);
+            pvoc_free(p);
+            out
+        });
+    }
+
+    /// The stretching offline export renders what pushing and pulling by hand does, at
+    /// `Stretch` times the input's length.
+    #[test]
+    fn stretch_offline_matches_streaming() {
+        let input = blocksize::test_signal(3_000, CHANNELS, 7);
+        let frames = input.len() / CHANNELS;
+        let streamed = stream(-5.0, 1.5, &input);
+
+        let p = pvoc_new(blocksize::SAMPLE_RATE);
+        pvoc_set_params(p, -5.0, 1.5, 1);
+        let mut out = vec![0.0; 2 * frames * CHANNELS];
+        let written = pvoc_stretch_offline(
+            p,
+            input.as_ptr(),
+            frames,
+            out.as_mut_ptr(),
+            2 * frames,
+            CHANNELS,
+        );
+        pvoc_free(p);
+        assert_eq!(written as usize, (frames as f32 * 1.5).round() as usize);
+        assert_eq!(&out[..written as usize * CHANNELS], &streamed[..]);
+    }
+
+    /// `process` is the stream at a stretch of 1, delayed by its latency, even two octaves
+    /// down where the frames lag furthest; `Stretch` doesn't apply.
+    #[test]
+    fn process_is_the_stream_behind_its_latency() {
+        let input = blocksize::test_signal(20_000, CHANNELS, 3);
+        let frames = input.len() / CHANNELS;
+        let streamed = stream(-24.0, 1.0, &input);
+
+        let p = pvoc_new(blocksize::SAMPLE_RATE);
+        pvoc_set_params(p, -24.0, 2.0, 1);
+        let latency = pvoc_latency_samples(p) as usize;
+        let mut out = vec![1.0; frames * CHANNELS];
+        pvoc_process_offline(p, input.as_ptr(), out.as_mut_ptr(), frames, CHANNELS);
+        pvoc_free(p);
+        assert!(out[..latency * CHANNELS].iter().all(|&y| y == 0.0));
+        assert_eq!(
+            &out[latency * CHANNELS..],
+            &streamed[..(frames - latency) * CHANNELS]
+        );
+    }
+
+    /// `Semitones` moves a tone's frequency by `2^(semitones / 12)`, stretched or not.
+    #[test]
+    fn semitones_shift_the_dominant_bin() {
+        let input = tone(96.0, 48_000);
+        for (semitones, stretch) in [(7.0, 1.0), (-12.0, 1.0), (5.0, 1.5), (-3.0, 0.75)] {
+            let output = stream(semitones, stretch, &input);
+            let want = 96.0 * (semitones / 12.0_f32).exp2();
+            let got = dominant_bin(&output, 16_000) as f32;
+            assert!(
+                (got - want).abs() <= 1.0,
+                "{semitones} st at {stretch}x: bin {got}, not {want}"
+            );
+        }
+    }
+
+    /// The stream's output runs `Stretch` times as long as its input, at any pitch.
+    #[test]
+    fn stretch_scales_the_output_length() {
+        let input = blocksize::test_signal(20_000, CHANNELS, 5);
+        for (semitones, stretch) in [(0.0, 0.5), (0.0, 2.0), (-7.0, 1.25), (12.0, 1.75)] {
+            let frames = stream(semitones, stretch, &input).len() / CHANNELS;
+            assert_eq!(
+                frames,
+                (20_000.0 * stretch).round() as usize,
+                "{semitones} st at {stretch}x"
+            );
+        }
+    }
+
+    /// `Phase lock` changes the shifted output but not its pitch.
+    #[test]
+    fn phase_lock_changes_the_output() {
+        let input = tone(96.0, 48_000);
+        let locked = stream_locked(4.0, 1.3, 1, &input);
+        let free = stream_locked(4.0, 1.3, 0, &input);
+        assert_eq!(locked.len(), free.len());
+        let difference = locked
+            .iter()
+            .zip(&free)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max);
+        assert!(difference > 0.01, "{difference}");
+        assert_eq!(dominant_bin(&locked, 16_000), dominant_bin(&free, 16_000));
+    }
+}
@@ -0,0 +1,156 @@
+use alloc::{vec, vec::Vec};
+use core::f32::consts::PI;
+
+use dsp_core::fft::{Complex, Fft};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+/// Local maxima quieter than this (relative to the loudest bin) don't anchor a phase-locked
+/// region; they are mostly window sidelobes and noise.
+const PEAK_FLOOR: f32 = 1e-4;
+
+fn wrap(p: f32) -> f32 {
+    p - 2.0 * PI * (p / (2.0 * PI)).round()
+}
+
+/// One channel of the phase vocoder: analyses a windowed frame, advances each bin's phase by
+/// its instantaneous frequency over the synthesis hop and overlap-adds the resynthesis.
+pub struct Voice {
+    buf: Vec<Complex>,
+    mag: Vec<f32>,
+    phase: Vec<f32>,
+    prev_phase: Vec<f32>,
+    syn_phase: Vec<f32>,
+    peaks: Vec<usize>,
+    ola: Vec<f32>,
+    hop: usize,
+    /// Overlap-add normalisation for the squared window at `hop`.
+    scale: f32,
+    primed: bool,
+}
+
+impl Voice {
+    /// `window` is the analysis and synthesis window, `hop` the synthesis hop.
+    pub fn new(window: &[f32], hop: usize) -> Self {
+        let n = window.len();
+        let bins = n / 2 + 1;
+        Self {
+            buf: vec![Complex::default(); n],
+            mag: vec![0.0; bins],
+            phase: vec![0.0; bins],
+            prev_phase: vec![0.0; bins],
+            syn_phase: vec![0.0; bins],
+            peaks: Vec::with_capacity(bins),
+            ola: vec![0.0; n],
+            hop,
+            scale: hop as f32 / window.iter().map(|w| w * w).sum::<f32>(),
+            primed: false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.prev_phase.fill(0.0);
+        self.syn_phase.fill(0.0);
+        self.ola.fill(0.0);
+        self.primed = false;
+    }
+
+    /// Runs one frame. `frame` holds up to `n` input samples (missing ones are zeros) that
+    /// start `analysis_hop` samples after the previous frame's.
+    pub fn process(
+        &mut self,
+        fft: &Fft,
+        window: &[f32],
+        frame: &[f32],
+        analysis_hop: usize,
+        phase_lock: bool,
+    ) {
+        let n = self.buf.len();
+        for (i, (c, w)) in self.buf.iter_mut().zip(window).enumerate() {
+            *c = Complex::new(frame.get(i).copied().unwrap_or(0.0) * w, 0.0);
+        }
+        fft.forward(&mut self.buf);
+        for (k, c) in self.buf.iter().take(self.mag.len()).enumerate() {
+            self.mag[k] = c.norm();
+            self.phase[k] = c.im.atan2(c.re);
+        }
+
+        if self.primed {
+            self.advance(n, analysis_hop.max(1) as f32, phase_lock);
+        } else {
+            self.syn_phase.copy_from_slice(&self.phase);
+            self.primed = true;
+        }
+        self.prev_phase.copy_from_slice(&self.phase);
+
+        let half = n / 2;
+        for k in 0..=half {
+            let (s, c) = self.syn_phase[k].sin_cos();
+            self.buf[k] = Complex::new(self.mag[k] * c, self.mag[k] * s);
+        }
+        for k in half + 1..n {
+            let c = self.buf[n - k];
+            self.buf[k] = Complex::new(c.re, -c.im);
+        }
+        fft.inverse(&mut self.buf);
+        for ((o, c), w) in self.ola.iter_mut().zip(&self.buf).zip(window) {
+            *o += c.re * w * self.scale;
+        }
+    }
+
+    /// With phase locking, only spectral peaks get their own phase advance; the bins around
+    /// each peak keep their analysed phase offset from it, which holds a partial's main lobe
+    /// together instead of letting it smear ("phasiness").
+    fn advance(&mut self, n: usize, ha: f32, phase_lock: bool) {
+        let hs = self.hop as f32;
+        let bin_rad = 2.0 * PI / n as f32;
+        let inst = |k: usize, phase: &[f32], prev: &[f32]| {
+            let omega = bin_rad * k as f32;
+            let dev = wrap(phase[k] - prev[k] - omega * ha);
+            (omega + dev / ha) * hs
+        };
+
+        self.peaks.clear();
+        if phase_lock {
+            let floor = self.mag.iter().fold(0.0_f32, |m, v| m.max(*v)) * PEAK_FLOOR;
+            let last = self.mag.len() - 1;
+            self.peaks.extend((1..last).filter(|&k| {
+                let m = self.mag[k];
+                m > floor && m > self.mag[k - 1] && m >= self.mag[k + 1]
+            }));
+        }
+        if self.peaks.is_empty() {
+            for k in 0..self.syn_phase.len() {
+                self.syn_phase[k] =
+                    wrap(self.syn_phase[k] + inst(k, &self.phase, &self.prev_phase));
+            }
+            return;
+        }
+
+        for &p in &self.peaks {
+            self.syn_phase[p] = wrap(self.syn_phase[p] + inst(p, &self.phase, &self.prev_phase));
+        }
+        let mut region = 0;
+        for k in 0..self.syn_phase.len() {
+            while region + 1 < self.peaks.len()
+                && k > (self.peaks[region] + self.peaks[region + 1]) / 2
+            {
+                region += 1;
+            }
+            let p = self.peaks[region];
+            if k != p {
+                self.syn_phase[k] = wrap(self.syn_phase[p] + self.phase[k] - self.phase[p]);
+            }
+        }
+    }
+
+    /// Moves the `hop` samples that no later frame will add to into `out`.
+    pub fn take(&mut self, out: &mut Vec<f32>) {
+        let hop = self.hop;
+        out.extend_from_slice(&self.ola[..hop]);
+        self.ola.copy_within(hop.., 0);
+        let n = self.ola.len();
+        self.ola[n - hop..].fill(0.0);
+    }
+}
@@ -0,0 +1,305 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::f32::consts::{PI, TAU};
+use dsp_core::denormal::flush;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_STAGES: usize = 8;
+
+// Indices into `Phaser::PARAMS`, in the order of `phaser_set_params`' arguments.
+pub const PARAM_STAGES: u32 = 0;
+pub const PARAM_RATE_HZ: u32 = 1;
+pub const PARAM_DEPTH: u32 = 2;
+pub const PARAM_STEREO_PHASE_DEG: u32 = 3;
+pub const PARAM_CENTER_HZ: u32 = 4;
+pub const PARAM_FEEDBACK: u32 = 5;
+pub const PARAM_MIX: u32 = 6;
+
+/// The LFO sweeps this many octaves either side of the centre at full depth.
+const SWEEP_OCTAVES: f32 = 2.5;
+const MAX_FEEDBACK: f32 = 0.95;
+
+/// First-order all-pass, `H(z) = (a + z^-1) / (1 + a z^-1)`.
+#[derive(Clone, Copy, Default)]
+struct Allpass {
+    x1: f32,
+    y1: f32,
+}
+
+impl Allpass {
+    #[inline]
+    fn process(&mut self, x: f32, a: f32) -> f32 {
+        let y = flush(a * x + self.x1 - a * self.y1);
+        self.x1 = x;
+        self.y1 = y;
+        y
+    }
+}
+
+/// Cascaded all-pass stages swept by a sine LFO; mixing with the dry signal turns their phase
+/// shift into moving notches. Odd channels run `stereo_phase` ahead of even ones.
+#[repr(C)]
+pub struct Phaser {
+    stages: usize,
+    rate_hz: f32,
+    depth: f32,
+    /// Offset for odd channels, in cycles (0–0.5).
+    stereo_phase: f32,
+    center_hz: f32,
+    feedback: f32,
+    mix: f32,
+    /// LFO position in cycles (0..1), carried across process calls.
+    phase: f32,
+    allpasses: [[Allpass; MAX_STAGES]; MAX_CHANNELS],
+    last_wet: [f32; MAX_CHANNELS],
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Phaser {
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let step = self.rate_hz / self.sample_rate_hz;
+        let max_hz = self.sample_rate_hz * 0.45;
+        let sweep = self.depth * SWEEP_OCTAVES;
+        let stages = self.stages;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let phase = self.phase + if ch % 2 == 1 { self.stereo_phase } else { 0.0 };
+                let hz = (self.center_hz * (sweep * (TAU * phase).sin()).exp2()).min(max_hz);
+                let t = (PI * hz / self.sample_rate_hz).tan();
+                let a = (t - 1.0) / (t + 1.0);
+                let mut v = *x + self.feedback * self.last_wet[ch];
+                for ap in &mut self.allpasses[ch][..stages] {
+                    v = ap.process(v, a);
+                }
+                self.last_wet[ch] = v;
+                *y = *x * (1.0 - self.mix) + v * self.mix;
+            }
+            self.phase += step;
+            if self.phase >= 1.0 {
+                self.phase -= 1.0;
+            }
+        }
+    }
+}
+
+impl Node for Phaser {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Stages` 4, 6 or 8 (odd counts round down); `Depth` is the fraction of a ±2.5 octave
+    /// sweep around `Center`; `Mix` 0.5 gives the deepest notches.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Stages", "", 4.0, MAX_STAGES as f32, 4.0),
+        ParamDescriptor::log("Rate", "Hz", 0.01, 10.0, 0.5),
+        ParamDescriptor::linear("Depth", "", 0.0, 1.0, 0.7),
+        ParamDescriptor::linear("Stereo phase", "deg", 0.0, 180.0, 90.0),
+        ParamDescriptor::log("Center", "Hz", 50.0, 5000.0, 800.0),
+        ParamDescriptor::linear("Feedback", "", -MAX_FEEDBACK, MAX_FEEDBACK, 0.0),
+        ParamDescriptor::linear("Mix", "", 0.0, 1.0, 0.5),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        Phaser {
+            stages: 4,
+            rate_hz: 0.5,
+            depth: 0.7,
+            stereo_phase: 0.25,
+            center_hz: 800.0,
+            feedback: 0.0,
+            mix: 0.5,
+            phase: 0.0,
+            allpasses: [[Allpass::default(); MAX_STAGES]; MAX_CHANNELS],
+            last_wet: [0.0; MAX_CHANNELS],
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_STAGES => {
+                let stages = (clamp(value, 4.0, MAX_STAGES as f32).round() as usize / 2) * 2;
+                if stages > self.stages {
+                    for ch in &mut self.allpasses {
+                        ch[self.stages..stages].fill(Allpass::default());
+                    }
+                }
+                self.stages = stages;
+            }
+            PARAM_RATE_HZ => self.rate_hz = clamp(value, 0.01, 10.0),
+            PARAM_DEPTH => self.depth = clamp(value, 0.0, 1.0),
+            PARAM_STEREO_PHASE_DEG => self.stereo_phase = clamp(value, 0.0, 180.0) / 360.0,
+            PARAM_CENTER_HZ => self.center_hz = clamp(value, 50.0, 5000.0),
+            PARAM_FEEDBACK => self.feedback = clamp(value, -MAX_FEEDBACK, MAX_FEEDBACK),
+            PARAM_MIX => self.mix = clamp(value, 0.0, 1.0),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_STAGES => self.stages as f32,
+            PARAM_RATE_HZ => self.rate_hz,
+            PARAM_DEPTH => self.depth,
+            PARAM_STEREO_PHASE_DEG => self.stereo_phase * 360.0,
+            PARAM_CENTER_HZ => self.center_hz,
+            PARAM_FEEDBACK => self.feedback,
+            PARAM_MIX => self.mix,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Clears the allpass stages and the feedback path and restarts the LFO.
+    fn reset(&mut self) {
+        self.phase = 0.0;
+        self.allpasses = [[Allpass::default(); MAX_STAGES]; MAX_CHANNELS];
+        self.last_wet = [0.0; MAX_CHANNELS];
+    }
+}
+
+node_abi::export_node!(Phaser {
+    abi_version: phaser_abi_version,
+    new: phaser_new,
+    free: phaser_free,
+    param_count: phaser_param_count,
+    param_descriptors: phaser_param_descriptors,
+    set_param: phaser_set_param,
+    get_param: phaser_get_param,
+    param_events: phaser_param_events,
+    process: phaser_process,
+    process_offline: phaser_process_offline,
+    latency_samples: phaser_latency_samples,
+    io_layout: phaser_get_io_layout,
+    process_io: phaser_process_io,
+    reset: phaser_reset,
+    state_size: phaser_state_size,
+    save_state: phaser_save_state,
+    load_state: phaser_load_state,
+    preset_size: phaser_preset_size,
+    save_preset: phaser_save_preset,
+    load_preset: phaser_load_preset,
+    last_error_code: phaser_last_error_code,
+    last_error_message: phaser_last_error_message_ptr,
+});
+
+node_abi::test_params!(Phaser);
+
+dsp_core::test_block_sizes!(phaser_new, phaser_process(input, output), phaser_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Phaser as PhaserNode);
+
+/// Every parameter in one call, as `phaser_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn phaser_set_params(
+    handle: Handle,
+    stages: u32,
+    rate_hz: f32,
+    depth: f32,
+    stereo_phase_deg: f32,
+    center_hz: f32,
+    feedback: f32,
+    mix: f32,
+) {
+    guard(handle, |p: &mut Phaser| {
+        p.set_param(PARAM_STAGES, stages as f32);
+        p.set_param(PARAM_RATE_HZ, rate_hz);
+        p.set_param(PARAM_DEPTH, depth);
+        p.set_param(PARAM_STEREO_PHASE_DEG, stereo_phase_deg);
+        p.set_param(PARAM_CENTER_HZ, center_hz);
+        p.set_param(PARAM_FEEDBACK, feedback);
+        p.set_param(PARAM_MIX, mix);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 48_000.0;
+
+    fn phaser(params: &[(u32, f32)]) -> Phaser {
+        let mut p = Phaser::new(RATE);
+        for &(index, value) in params {
+            p.set_param(index, value);
+        }
+        p
+    }
+
+    /// In render quanta.
+    fn render(p: &mut Phaser, input: &[f32], channels: usize) -> Vec<f32> {
+        let mut output = vec![0.0; input.len()];
+        for (x, y) in input
+            .chunks(128 * channels)
+            .zip(output.chunks_mut(128 * channels))
+        {
+            Node::process(p, x, y, channels);
+        }
+        output
+    }
+
+    /// Settled gain in dB on a `hz` sine, mono.
+    fn gain_db_at(p: &mut Phaser, hz: f32) -> f32 {
+        let w = TAU * hz / RATE;
+        let input: Vec<f32> = (0..24_000).map(|i| (w * i as f32).sin()).collect();
+        let output = render(p, &input, 1);
+        let peak = output[12_000..].iter().fold(0.0_f32, |m, y| m.max(y.abs()));
+        20.0 * peak.log10()
+    }
+
+    /// Held still, four stages turn a full cycle at the centre and half a cycle at
+    /// `tan(22.5°)` of it, where the even mix cancels.
+    #[test]
+    fn stages_notch_where_they_shift_half_a_cycle() {
+        let still = [(PARAM_DEPTH, 0.0), (PARAM_CENTER_HZ, 1_000.0)];
+        let notch_hz = 1_000.0 * (PI / 8.0).tan();
+        assert!(gain_db_at(&mut phaser(&still), 1_000.0).abs() < 0.01);
+        let notch = gain_db_at(&mut phaser(&still), notch_hz);
+        assert!(notch < -40.0, "{notch}");
+        let dry = gain_db_at(
+            &mut phaser(&[still[0], still[1], (PARAM_MIX, 0.0)]),
+            notch_hz,
+        );
+        assert!(dry.abs() < 0.01);
+    }
+
+    /// `Stereo phase` runs odd channels' sweep ahead of even ones'.
+    #[test]
+    fn stereo_phase_offsets_the_channels() {
+        let mono = dsp_core::blocksize::test_signal(24_000, 1, 9);
+        let stereo: Vec<f32> = mono.iter().flat_map(|&x| [x, x]).collect();
+        let differs = |degrees| {
+            let mut p = phaser(&[(PARAM_RATE_HZ, 2.0), (PARAM_STEREO_PHASE_DEG, degrees)]);
+            let output = render(&mut p, &stereo, 2);
+            output
+                .chunks(2)
+                .fold(0.0_f32, |m, lr| m.max((lr[0] - lr[1]).abs()))
+        };
+        assert_eq!(differs(0.0), 0.0);
+        assert!(differs(180.0) > 0.1);
+    }
+}
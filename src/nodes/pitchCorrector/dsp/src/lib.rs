@@ -0,0 +1,519 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use core::f32::consts::PI;
+use dsp_core::envelope::one_pole_coeff;
+use dsp_core::param::clamp;
+use dsp_core::yin::Yin;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing, FLAG_NO_AUTOMATION};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+/// The grains are cut at the same marks in every channel, so a stereo source stays coherent.
+pub const MAX_CHANNELS: usize = 2;
+
+// Indices into `PitchCorrector::PARAMS`, in the order of `pitchcorr_set_params`' arguments.
+pub const PARAM_KEY: u32 = 0;
+pub const PARAM_SCALE_MASK: u32 = 1;
+pub const PARAM_SPEED_MS: u32 = 2;
+pub const PARAM_AMOUNT: u32 = 3;
+pub const PARAM_MIX: u32 = 4;
+
+const MIN_HZ: f32 = 80.0;
+const MAX_HZ: f32 = 1000.0;
+/// YIN runs once per this many samples.
+const DETECT_HOP: usize = 256;
+/// Below this confidence the input counts as unvoiced and passes through uncorrected.
+const VOICED_CONFIDENCE: f32 = 0.8;
+/// Mark spacing (and half the grain length) while unvoiced.
+const UNVOICED_MS: f32 = 5.0;
+const MAX_MARKS: usize = 32;
+
+/// An analysis pitch mark: a waveform peak one period after the previous one, with the
+/// period and correction ratio in effect when it was found.
+#[derive(Clone, Copy, Default)]
+struct Mark {
+    pos: u64,
+    period: f32,
+    ratio: f32,
+}
+
+/// Power-of-two ring addressed by absolute sample index.
+struct Ring {
+    buf: Vec<f32>,
+    mask: usize,
+}
+
+impl Ring {
+    fn new(len: usize) -> Self {
+        let len = len.next_power_of_two();
+        Self {
+            buf: vec![0.0; len],
+            mask: len - 1,
+        }
+    }
+
+    #[inline]
+    fn at(&self, i: u64) -> f32 {
+        self.buf[i as usize & self.mask]
+    }
+
+    #[inline]
+    fn at_mut(&mut self, i: u64) -> &mut f32 {
+        &mut self.buf[i as usize & self.mask]
+    }
+}
+
+/// Monophonic pitch corrector. YIN tracks the mono sum; each detection is snapped to the
+/// nearest note of the chosen key/scale and the correction ratio glides there at `speed`.
+/// Pitch marks are placed one period apart on waveform peaks, and TD-PSOLA re-spaces
+/// two-period Hann grains cut at those marks by `period / ratio`, changing pitch without
+/// moving formants or duration. The output runs `latency` samples behind the input so a
+/// grain is always fully recorded before it's needed.
+pub struct PitchCorrector {
+    key: u32,
+    scale_mask: u32,
+    speed_ms: f32,
+    speed_coeff: f32,
+    amount: f32,
+    mix: f32,
+    yin: Yin,
+    scratch: Vec<f32>,
+    since_detect: usize,
+    detected_hz: f32,
+    target_hz: f32,
+    /// Current period in samples, 0 while unvoiced.
+    period: f32,
+    /// Smoothed and target correction, in octaves.
+    shift: f32,
+    target_shift: f32,
+    mono: Ring,
+    input: Vec<Ring>,
+    ola: Vec<Ring>,
+    marks: [Mark; MAX_MARKS],
+    mark_head: usize,
+    last_mark: u64,
+    next_syn: f64,
+    /// Absolute index of the next input sample.
+    time: u64,
+    max_period: f32,
+    unvoiced_period: f32,
+    latency: u64,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+fn hz_to_note(hz: f32) -> f32 {
+    69.0 + 12.0 * (hz / 440.0).log2()
+}
+
+fn note_to_hz(note: f32) -> f32 {
+    440.0 * ((note - 69.0) / 12.0).exp2()
+}
+
+/// Nearest note to `note` whose pitch class, counted up from `key`, has its bit set in
+/// `scale_mask` (an empty mask allows all twelve).
+fn quantize(note: f32, key: u32, scale_mask: u32) -> f32 {
+    let mask = if scale_mask & 0xfff == 0 {
+        0xfff
+    } else {
+        scale_mask & 0xfff
+    };
+    let centre = note.round() as i32;
+    (-6..=6)
+        .map(|d| centre + d)
+        .filter(|n| mask & (1 << (n - key as i32).rem_euclid(12)) != 0)
+        .map(|n| n as f32)
+        .min_by(|a, b| (a - note).abs().total_cmp(&(b - note).abs()))
+        .unwrap_or(note)
+}
+
+impl PitchCorrector {
+    fn detect(&mut self) {
+        let len = self.scratch.len();
+        let start = self.time - len as u64;
+        for (i, s) in self.scratch.iter_mut().enumerate() {
+            *s = self.mono.at(start + i as u64);
+        }
+        match self.yin.detect(&self.scratch) {
+            Some(p) if p.confidence >= VOICED_CONFIDENCE => {
+                let note = hz_to_note(p.hz);
+                let target = quantize(note, self.key, self.scale_mask);
+                self.detected_hz = p.hz;
+                self.target_hz = note_to_hz(target);
+                self.period = (self.sample_rate_hz / p.hz).min(self.max_period - 1.0);
+                self.target_shift = (target - note) / 12.0 * self.amount;
+            }
+            _ => {
+                self.detected_hz = 0.0;
+                self.target_hz = 0.0;
+                self.period = 0.0;
+                self.target_shift = 0.0;
+            }
+        }
+    }
+
+    /// Places the next mark once the search range after the last one has been recorded:
+    /// the highest peak 0.75–1.25 periods on while voiced, exactly one period on otherwise.
+    fn place_mark(&mut self) {
+        let voiced = self.period > 0.0;
+        let period = if voiced {
+            self.period
+        } else {
+            self.unvoiced_period
+        };
+        let lo = self.last_mark + (0.75 * period) as u64;
+        let hi = self.last_mark + (1.25 * period) as u64;
+        if self.time <= hi {
+            return;
+        }
+        let pos = if voiced {
+            (lo..=hi)
+                .max_by(|&a, &b| self.mono.at(a).total_cmp(&self.mono.at(b)))
+                .unwrap_or(lo)
+        } else {
+            self.last_mark + period as u64
+        };
+        self.marks[self.mark_head] = Mark {
+            pos,
+            period,
+            ratio: self.shift.exp2(),
+        };
+        self.mark_head = (self.mark_head + 1) % MAX_MARKS;
+        self.last_mark = pos;
+    }
+
+    /// The fully recorded mark closest to `s`.
+    fn nearest_mark(&self, s: u64) -> Option<Mark> {
+        self.marks
+            .iter()
+            .filter(|m| m.period > 0.0 && (m.pos + m.period.ceil() as u64) < self.time)
+            .min_by_key(|m| m.pos.abs_diff(s))
+            .copied()
+    }
+
+    /// Overlap-adds grains for every synthesis mark whose grain starts at or after `o`, the
+    /// output sample about to be read.
+    fn schedule(&mut self, o: u64, channels: usize) {
+        while self.next_syn <= o as f64 + self.max_period as f64 {
+            let s = self.next_syn.ceil() as u64;
+            let Some(m) = self.nearest_mark(s) else {
+                self.next_syn += self.unvoiced_period as f64;
+                continue;
+            };
+            let half = m.period as u64;
+            let step = PI / (2 * half) as f32;
+            for k in 1..2 * half {
+                let w = (step * k as f32).sin();
+                let w = w * w;
+                for ch in 0..channels {
+                    let x = self.input[ch].at(m.pos - half + k);
+                    *self.ola[ch].at_mut(s - half + k) += x * w;
+                }
+            }
+            self.next_syn += (m.period / m.ratio) as f64;
+        }
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let scale = 1.0 / channels as f32;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            let t = self.time;
+            let mut mono = 0.0;
+            for (ch, x) in inp.iter().enumerate() {
+                let x = if x.is_finite() { *x } else { 0.0 };
+                *self.input[ch].at_mut(t) = x;
+                mono += x;
+            }
+            *self.mono.at_mut(t) = mono * scale;
+            self.time += 1;
+
+            self.since_detect += 1;
+            if self.since_detect >= DETECT_HOP {
+                self.since_detect = 0;
+                self.detect();
+            }
+            self.shift = self.target_shift + (self.shift - self.target_shift) * self.speed_coeff;
+            self.place_mark();
+
+            let o = t - self.latency;
+            self.schedule(o, channels);
+            for (ch, y) in out.iter_mut().enumerate() {
+                let wet = core::mem::take(self.ola[ch].at_mut(o));
+                let dry = self.input[ch].at(o);
+                *y = dry + (wet - dry) * self.mix;
+            }
+        }
+    }
+}
+
+impl Node for PitchCorrector {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Key` 0–11 (C–B); `Scale` has bit `i` set for each allowed note `i` semitones above the
+    /// key (0xAB5 major, 0x5AD natural minor, 0 chromatic); `Speed` is how long the correction
+    /// takes to glide to a new note (0 is the hard, stepped effect); `Amount` scales the
+    /// correction.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Key", "", 0.0, 11.0, 0.0),
+        ParamDescriptor::stepped("Scale", "", 0.0, 4095.0, 0.0).with_flags(FLAG_NO_AUTOMATION),
+        ParamDescriptor::linear("Speed", "ms", 0.0, 500.0, 20.0),
+        ParamDescriptor::linear("Amount", "", 0.0, 1.0, 1.0),
+        ParamDescriptor::linear("Mix", "", 0.0, 1.0, 1.0),
+    ];
+    const METER_COUNT: usize = 2;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let yin = Yin::new(MIN_HZ, MAX_HZ, sample_rate_hz);
+        let max_period = (sample_rate_hz / MIN_HZ).ceil();
+        let latency = 3 * max_period as u64;
+        let ring = 8 * max_period as usize;
+        // Start the clock a ring's length in so every look-back stays at a valid index.
+        let time = ring.next_power_of_two() as u64;
+        PitchCorrector {
+            key: 0,
+            scale_mask: 0,
+            speed_ms: 20.0,
+            speed_coeff: one_pole_coeff(20.0, sample_rate_hz),
+            amount: 1.0,
+            mix: 1.0,
+            scratch: vec![0.0; yin.frame_len()],
+            yin,
+            since_detect: 0,
+            detected_hz: 0.0,
+            target_hz: 0.0,
+            period: 0.0,
+            shift: 0.0,
+            target_shift: 0.0,
+            mono: Ring::new(ring),
+            input: (0..MAX_CHANNELS).map(|_| Ring::new(ring)).collect(),
+            ola: (0..MAX_CHANNELS).map(|_| Ring::new(ring)).collect(),
+            marks: [Mark::default(); MAX_MARKS],
+            mark_head: 0,
+            last_mark: time,
+            next_syn: (time - latency) as f64,
+            time,
+            max_period,
+            unvoiced_period: UNVOICED_MS / 1000.0 * sample_rate_hz,
+            latency,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_KEY => self.key = clamp(value, 0.0, 11.0).round() as u32,
+            PARAM_SCALE_MASK => self.scale_mask = clamp(value, 0.0, 4095.0).round() as u32,
+            PARAM_SPEED_MS => {
+                self.speed_ms = clamp(value, 0.0, 500.0);
+                self.speed_coeff = one_pole_coeff(self.speed_ms, self.sample_rate_hz);
+            }
+            PARAM_AMOUNT => self.amount = clamp(value, 0.0, 1.0),
+            PARAM_MIX => self.mix = clamp(value, 0.0, 1.0),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_KEY => self.key as f32,
+            PARAM_SCALE_MASK => self.scale_mask as f32,
+            PARAM_SPEED_MS => self.speed_ms,
+            PARAM_AMOUNT => self.amount,
+            PARAM_MIX => self.mix,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Three periods of the lowest trackable pitch: enough to find a mark and cut a grain
+    /// around it before the output needs it. The dry path is delayed to match.
+    fn latency_samples(&self) -> u32 {
+        self.latency as u32
+    }
+
+    /// Detected and target pitch as `pitchcorr_get_detected_hz` and `pitchcorr_get_target_hz`
+    /// read them.
+    fn meters(&self, out: &mut [f32]) {
+        out[0] = self.detected_hz;
+        out[1] = self.target_hz;
+    }
+
+    /// Empties the analysis and overlap-add buffers, forgets the detected pitch and restarts
+    /// the clock as a fresh node would.
+    fn reset(&mut self) {
+        for ring in core::iter::once(&mut self.mono)
+            .chain(&mut self.input)
+            .chain(&mut self.ola)
+        {
+            ring.buf.fill(0.0);
+        }
+        self.since_detect = 0;
+        self.detected_hz = 0.0;
+        self.target_hz = 0.0;
+        self.period = 0.0;
+        self.shift = 0.0;
+        self.target_shift = 0.0;
+        self.marks = [Mark::default(); MAX_MARKS];
+        self.mark_head = 0;
+        self.time = self.mono.buf.len() as u64;
+        self.last_mark = self.time;
+        self.next_syn = (self.time - self.latency) as f64;
+    }
+}
+
+node_abi::export_node!(PitchCorrector {
+    abi_version: pitchcorr_abi_version,
+    new: pitchcorr_new,
+    free: pitchcorr_free,
+    param_count: pitchcorr_param_count,
+    param_descriptors: pitchcorr_param_descriptors,
+    set_param: pitchcorr_set_param,
+    get_param: pitchcorr_get_param,
+    param_events: pitchcorr_param_events,
+    process: pitchcorr_process,
+    process_offline: pitchcorr_process_offline,
+    latency_samples: pitchcorr_latency_samples,
+    io_layout: pitchcorr_get_io_layout,
+    process_io: pitchcorr_process_io,
+    reset: pitchcorr_reset,
+    state_size: pitchcorr_state_size,
+    save_state: pitchcorr_save_state,
+    load_state: pitchcorr_load_state,
+    preset_size: pitchcorr_preset_size,
+    save_preset: pitchcorr_save_preset,
+    load_preset: pitchcorr_load_preset,
+    last_error_code: pitchcorr_last_error_code,
+    last_error_message: pitchcorr_last_error_message_ptr,
+});
+
+node_abi::test_params!(PitchCorrector);
+
+dsp_core::test_block_sizes!(
+    pitchcorr_new,
+    pitchcorr_process(input, output),
+    pitchcorr_free
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(PitchCorrector as PitchCorrectorNode);
+
+/// Every parameter in one call, as `pitchcorr_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn pitchcorr_set_params(
+    handle: Handle,
+    key: u32,
+    scale_mask: u32,
+    speed_ms: f32,
+    amount: f32,
+    mix: f32,
+) {
+    guard(handle, |p: &mut PitchCorrector| {
+        p.set_param(PARAM_KEY, (key % 12) as f32);
+        p.set_param(PARAM_SCALE_MASK, (scale_mask & 0xfff) as f32);
+        p.set_param(PARAM_SPEED_MS, speed_ms);
+        p.set_param(PARAM_AMOUNT, amount);
+        p.set_param(PARAM_MIX, mix);
+    });
+}
+
+/// Last detected input pitch in Hz (0 while unvoiced), for the UI's tuner display.
+#[no_mangle]
+pub extern "C" fn pitchcorr_get_detected_hz(handle: Handle) -> f32 {
+    guard(handle, |p: &mut PitchCorrector| p.detected_hz).unwrap_or(0.0)
+}
+
+/// The scale note the detected pitch is being pulled to, in Hz (0 while unvoiced).
+#[no_mangle]
+pub extern "C" fn pitchcorr_get_target_hz(handle: Handle) -> f32 {
+    guard(handle, |p: &mut PitchCorrector| p.target_hz).unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::TAU;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+    const MAJOR: u32 = 0xab5;
+
+    fn corrector(params: &[(u32, f32)]) -> PitchCorrector {
+        let mut p = PitchCorrector::new(RATE);
+        for &(index, value) in params {
+            p.set_param(index, value);
+        }
+        p
+    }
+
+    fn render(p: &mut PitchCorrector, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(p, &input[span], y, 1))
+    }
+
+    /// 3 s of a sine at `hz`.
+    fn sine(hz: f32) -> Vec<f32> {
+        let w = TAU * hz / RATE;
+        (0..144_000).map(|i| 0.5 * (w * i as f32).sin()).collect()
+    }
+
+    /// Notes snap to the nearest one in the key's scale, or to the nearest semitone with no
+    /// scale.
+    #[test]
+    fn quantize_snaps_to_the_scale() {
+        assert_eq!(quantize(61.4, 0, MAJOR), 62.0);
+        assert_eq!(quantize(65.6, 0, MAJOR), 65.0);
+        assert_eq!(quantize(65.2, 2, MAJOR), 66.0);
+        assert_eq!(quantize(65.6, 0, 0), 66.0);
+    }
+
+    /// A slightly sharp A is detected, pulled to 440 Hz and reported through the meters;
+    /// with `Amount` 0 it is left alone.
+    #[test]
+    fn corrects_a_sharp_note_to_the_scale() {
+        let x = sine(450.0);
+        let mut p = corrector(&[(PARAM_SCALE_MASK, MAJOR as f32), (PARAM_SPEED_MS, 0.0)]);
+        let y = render(&mut p, &x);
+        let mut meters = [0.0; 2];
+        Node::meters(&p, &mut meters);
+        assert!((meters[0] - 450.0).abs() < 1.0, "{meters:?}");
+        assert!((meters[1] - 440.0).abs() < 0.01, "{meters:?}");
+        assert!(amplitude(&y, 440.0) > 4.0 * amplitude(&y, 450.0));
+
+        let y = render(&mut corrector(&[(PARAM_AMOUNT, 0.0)]), &x);
+        assert!(amplitude(&y, 450.0) > 4.0 * amplitude(&y, 440.0));
+    }
+
+    /// The dry path runs the reported latency behind the input, three periods of 80 Hz.
+    #[test]
+    fn dry_path_runs_at_the_latency() {
+        let mut p = corrector(&[(PARAM_MIX, 0.0)]);
+        assert_eq!(Node::latency_samples(&p), 1800);
+        let mut x = vec![0.0; 2048];
+        x[10] = 1.0;
+        let y = render(&mut p, &x);
+        assert_eq!(y[1810], 1.0);
+        assert_eq!(y.iter().sum::<f32>(), 1.0);
+    }
+}
@@ -0,0 +1,336 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod lpc;
+
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use dsp_core::delay::DelayLine;
+use dsp_core::param::clamp;
+use lpc::Lpc;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing, FLAG_NO_AUTOMATION};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_GRAINS: usize = 8;
+
+// Indices into `PitchShifter::PARAMS`, in the order of `pitchshift_set_params`' arguments.
+pub const PARAM_SEMITONES: u32 = 0;
+pub const PARAM_GRAIN_MS: u32 = 1;
+pub const PARAM_GRAINS: u32 = 2;
+pub const PARAM_FORMANT_PRESERVE: u32 = 3;
+pub const PARAM_MIX: u32 = 4;
+
+const MIN_GRAIN_MS: f32 = 10.0;
+const MAX_GRAIN_MS: f32 = 200.0;
+
+/// Granular pitch shifter. `grains` read taps sweep back through a window of recent input at
+/// the shifted speed, evenly staggered and each faded with a Hann envelope so its jump back
+/// to the far end is silent. With formant preservation on, the shift is applied to the LPC
+/// residual and the input's current envelope is put back afterwards, so voices move in
+/// pitch without the chipmunk/giant timbre.
+#[repr(C)]
+pub struct PitchShifter {
+    semitones: f32,
+    ratio: f32,
+    grain_ms: f32,
+    /// Window length in samples; the taps sweep delays from 1 to `1 + window`.
+    window: f32,
+    grains: usize,
+    formant: bool,
+    mix: f32,
+    /// Sweep position in window lengths, shared by every channel so the image stays put.
+    phase: f32,
+    lines: Vec<DelayLine>,
+    lpc: Vec<Lpc>,
+    /// The dry path, delayed to line up with the grains.
+    dry: Vec<DelayLine>,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl PitchShifter {
+    /// Centre of the sweep: the average delay of every grain.
+    fn latency(&self) -> f32 {
+        1.0 + self.window * 0.5
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let step = (1.0 - self.ratio) / self.window;
+        let spacing = 1.0 / self.grains as f32;
+        let norm = 2.0 / self.grains as f32;
+        let latency = self.latency();
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            self.phase = (self.phase + step).rem_euclid(1.0);
+            let mut taps = [(0.0_f32, 0.0_f32); MAX_GRAINS];
+            for (g, tap) in taps.iter_mut().enumerate().take(self.grains) {
+                let p = (self.phase + g as f32 * spacing).fract();
+                let gain = (PI * p).sin();
+                *tap = (1.0 + p * self.window, gain * gain * norm);
+            }
+            let taps = &taps[..self.grains];
+
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let source = if self.formant {
+                    self.lpc[ch].whiten(*x)
+                } else {
+                    *x
+                };
+                let line = &mut self.lines[ch];
+                line.push(source);
+                let mut wet: f32 = taps.iter().map(|(d, g)| line.read_cubic(*d) * g).sum();
+                if self.formant {
+                    wet = self.lpc[ch].color(wet);
+                }
+                let dry = &mut self.dry[ch];
+                dry.push(*x);
+                let d = dry.read_linear(latency);
+                *y = d + (wet - d) * self.mix;
+            }
+        }
+    }
+}
+
+impl Node for PitchShifter {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Grain` is the window the taps sweep (longer is smoother on sustained material, shorter
+    /// smears transients less) and sets the latency; `Grains` overlapping taps; `Formant`
+    /// keeps the spectral envelope in place.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Pitch", "st", -24.0, 24.0, 0.0),
+        ParamDescriptor::log("Grain", "ms", MIN_GRAIN_MS, MAX_GRAIN_MS, 50.0)
+            .with_flags(FLAG_NO_AUTOMATION),
+        ParamDescriptor::stepped("Grains", "", 2.0, MAX_GRAINS as f32, 4.0),
+        ParamDescriptor::toggle("Formant", false),
+        ParamDescriptor::linear("Mix", "", 0.0, 1.0, 1.0),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let max_window = (MAX_GRAIN_MS / 1000.0 * sample_rate_hz).ceil() as usize + 2;
+        PitchShifter {
+            semitones: 0.0,
+            ratio: 1.0,
+            grain_ms: 50.0,
+            window: 50.0 / 1000.0 * sample_rate_hz,
+            grains: 4,
+            formant: false,
+            mix: 1.0,
+            phase: 0.0,
+            lines: (0..MAX_CHANNELS)
+                .map(|_| DelayLine::new(max_window))
+                .collect(),
+            lpc: (0..MAX_CHANNELS).map(|_| Lpc::new()).collect(),
+            dry: (0..MAX_CHANNELS)
+                .map(|_| DelayLine::new(max_window))
+                .collect(),
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_SEMITONES => {
+                self.semitones = clamp(value, -24.0, 24.0);
+                self.ratio = (self.semitones / 12.0).exp2();
+            }
+            PARAM_GRAIN_MS => {
+                self.grain_ms = clamp(value, MIN_GRAIN_MS, MAX_GRAIN_MS);
+                self.window = self.grain_ms / 1000.0 * self.sample_rate_hz;
+            }
+            PARAM_GRAINS => self.grains = clamp(value, 2.0, MAX_GRAINS as f32).round() as usize,
+            PARAM_FORMANT_PRESERVE => {
+                let formant = clamp(value, 0.0, 1.0) >= 0.5;
+                if formant != self.formant {
+                    for lpc in &mut self.lpc {
+                        lpc.clear();
+                    }
+                }
+                self.formant = formant;
+            }
+            PARAM_MIX => self.mix = clamp(value, 0.0, 1.0),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_SEMITONES => self.semitones,
+            PARAM_GRAIN_MS => self.grain_ms,
+            PARAM_GRAINS => self.grains as f32,
+            PARAM_FORMANT_PRESERVE => u32::from(self.formant) as f32,
+            PARAM_MIX => self.mix,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Average delay of the grains (and of the dry path, which is delayed to match): half the
+    /// grain window plus one sample.
+    fn latency_samples(&self) -> u32 {
+        self.latency().round() as u32
+    }
+
+    /// Empties the grain and dry delay lines and the formant filters and restarts the sweep.
+    fn reset(&mut self) {
+        self.phase = 0.0;
+        for line in self.lines.iter_mut().chain(&mut self.dry) {
+            line.clear();
+        }
+        for lpc in &mut self.lpc {
+            lpc.clear();
+        }
+    }
+}
+
+node_abi::export_node!(PitchShifter {
+    abi_version: pitchshift_abi_version,
+    new: pitchshift_new,
+    free: pitchshift_free,
+    param_count: pitchshift_param_count,
+    param_descriptors: pitchshift_param_descriptors,
+    set_param: pitchshift_set_param,
+    get_param: pitchshift_get_param,
+    param_events: pitchshift_param_events,
+    process: pitchshift_process,
+    process_offline: pitchshift_process_offline,
+    latency_samples: pitchshift_latency_samples,
+    io_layout: pitchshift_get_io_layout,
+    process_io: pitchshift_process_io,
+    reset: pitchshift_reset,
+    state_size: pitchshift_state_size,
+    save_state: pitchshift_save_state,
+    load_state: pitchshift_load_state,
+    preset_size: pitchshift_preset_size,
+    save_preset: pitchshift_save_preset,
+    load_preset: pitchshift_load_preset,
+    last_error_code: pitchshift_last_error_code,
+    last_error_message: pitchshift_last_error_message_ptr,
+});
+
+node_abi::test_params!(PitchShifter);
+
+dsp_core::test_block_sizes!(
+    pitchshift_new,
+    pitchshift_process(input, output),
+    pitchshift_free
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(PitchShifter as PitchShifterNode);
+
+/// Every parameter in one call, as `pitchshift_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn pitchshift_set_params(
+    handle: Handle,
+    semitones: f32,
+    grain_ms: f32,
+    grains: u32,
+    formant_preserve: u32,
+    mix: f32,
+) {
+    guard(handle, |p: &mut PitchShifter| {
+        p.set_param(PARAM_SEMITONES, semitones);
+        p.set_param(PARAM_GRAIN_MS, grain_ms);
+        p.set_param(PARAM_GRAINS, grains as f32);
+        p.set_param(PARAM_FORMANT_PRESERVE, formant_preserve as f32);
+        p.set_param(PARAM_MIX, mix);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::TAU;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    fn shifter(params: &[(u32, f32)]) -> PitchShifter {
+        let mut p = PitchShifter::new(RATE);
+        for &(index, value) in params {
+            p.set_param(index, value);
+        }
+        p
+    }
+
+    fn render(p: &mut PitchShifter, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(p, &input[span], y, 1))
+    }
+
+    /// An octave up or down moves a 240 Hz tone to 480 or 120 Hz. (The grains, a quarter
+    /// window apart, add up in phase for inputs at multiples of 80 Hz at the default 50 ms.)
+    #[test]
+    fn semitones_shift_the_pitch() {
+        let w = TAU * 240.0 / RATE;
+        let tone: Vec<f32> = (0..72_000).map(|i| (w * i as f32).sin()).collect();
+        for (semitones, hz) in [(12.0, 480.0), (-12.0, 120.0)] {
+            let y = render(&mut shifter(&[(PARAM_SEMITONES, semitones)]), &tone);
+            let shifted = amplitude(&y, hz);
+            assert!(shifted > 0.5, "{semitones}: {shifted}");
+            assert!(amplitude(&y, 240.0) < 0.1 * shifted);
+        }
+    }
+
+    /// The dry path arrives after the reported latency, where the grains are centred: with
+    /// no shift, the strongest of four grains reads that far back.
+    #[test]
+    fn dry_path_lines_up_with_the_grains() {
+        let mut x = vec![0.0; 2048];
+        x[0] = 1.0;
+        let latency = Node::latency_samples(&shifter(&[])) as usize;
+        assert_eq!(latency, 1201);
+        let dry = render(&mut shifter(&[(PARAM_MIX, 0.0)]), &x);
+        assert_eq!(dry[latency], 1.0);
+        assert_eq!(dry.iter().sum::<f32>(), 1.0);
+        let wet = render(&mut shifter(&[]), &x);
+        assert!((wet[latency] - 0.5).abs() < 1e-6);
+        assert!((wet[latency - 600] - 0.25).abs() < 1e-6);
+        assert!((wet[latency + 600] - 0.25).abs() < 1e-6);
+    }
+
+    /// Formant preservation keeps a resonance where it was: shifting an 80 Hz buzz through
+    /// a 1 kHz resonance up an octave moves the peak to 2 kHz, unless the formant is kept.
+    #[test]
+    fn formant_preserve_keeps_the_resonance() {
+        let (r, theta) = (0.995_f32, TAU * 1000.0 / RATE);
+        let (mut y1, mut y2) = (0.0, 0.0);
+        let buzz: Vec<f32> = (0..96_000)
+            .map(|i| {
+                let pulse = if i % 600 == 0 { 0.05 } else { 0.0 };
+                let y = pulse + 2.0 * r * theta.cos() * y1 - r * r * y2;
+                (y2, y1) = (y1, y);
+                y
+            })
+            .collect();
+        let peak = |formant: f32| {
+            let mut p = shifter(&[(PARAM_SEMITONES, 12.0), (PARAM_FORMANT_PRESERVE, formant)]);
+            let y = render(&mut p, &buzz);
+            amplitude(&y, 960.0) / amplitude(&y, 1920.0)
+        };
+        assert!(peak(0.0) < 0.5);
+        assert!(peak(1.0) > 2.0);
+    }
+}
@@ -0,0 +1,126 @@
+/// Order of the all-pole envelope; enough for four or five formants.
+use alloc::{vec, vec::Vec};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const ORDER: usize = 16;
+/// Analysis window and update interval in samples.
+const WINDOW: usize = 1024;
+const HOP: usize = 256;
+/// Per-order pole shrink (bandwidth expansion) keeping the synthesis filter well inside the
+/// unit circle.
+const EXPANSION: f32 = 0.994;
+
+/// Linear-prediction envelope tracker. `whiten` removes the current spectral envelope from the
+/// input (leaving roughly the excitation) and `color` puts it back on another signal, so a
+/// pitch-shifted excitation keeps the original formants.
+pub struct Lpc {
+    history: Vec<f32>,
+    pos: usize,
+    since_update: usize,
+    window: Vec<f32>,
+    coeffs: [f32; ORDER],
+    fir: [f32; ORDER],
+    iir: [f32; ORDER],
+}
+
+impl Lpc {
+    pub fn new() -> Self {
+        let window = (0..WINDOW)
+            .map(|i| {
+                let s = (core::f32::consts::PI * (i as f32 + 0.5) / WINDOW as f32).sin();
+                s * s
+            })
+            .collect();
+        Self {
+            history: vec![0.0; WINDOW],
+            pos: 0,
+            since_update: 0,
+            window,
+            coeffs: [0.0; ORDER],
+            fir: [0.0; ORDER],
+            iir: [0.0; ORDER],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.history.fill(0.0);
+        self.coeffs = [0.0; ORDER];
+        self.fir = [0.0; ORDER];
+        self.iir = [0.0; ORDER];
+    }
+
+    /// Autocorrelation of the windowed history, then Levinson-Durbin.
+    fn update(&mut self) {
+        let mut frame = [0.0_f32; WINDOW];
+        for (i, (f, w)) in frame.iter_mut().zip(&self.window).enumerate() {
+            *f = self.history[(self.pos + i) % WINDOW] * w;
+        }
+        let mut r = [0.0_f32; ORDER + 1];
+        for (lag, r) in r.iter_mut().enumerate() {
+            *r = frame[lag..].iter().zip(&frame).map(|(a, b)| a * b).sum();
+        }
+        if r[0] < 1e-9 {
+            return;
+        }
+        r[0] *= 1.0001;
+
+        let mut a = [0.0_f32; ORDER + 1];
+        a[0] = 1.0;
+        let mut err = r[0];
+        for i in 1..=ORDER {
+            let acc: f32 = (1..i).map(|j| a[j] * r[i - j]).sum::<f32>() + r[i];
+            let k = -acc / err;
+            let prev = a;
+            for j in 1..i {
+                a[j] = prev[j] + k * prev[i - j];
+            }
+            a[i] = k;
+            err *= 1.0 - k * k;
+            if err <= 0.0 {
+                return;
+            }
+        }
+        let mut g = 1.0;
+        for (c, a) in self.coeffs.iter_mut().zip(&a[1..]) {
+            g *= EXPANSION;
+            *c = a * g;
+        }
+    }
+
+    /// Prediction error of `x` against the current envelope.
+    #[inline]
+    pub fn whiten(&mut self, x: f32) -> f32 {
+        self.history[self.pos] = x;
+        self.pos = (self.pos + 1) % WINDOW;
+        self.since_update += 1;
+        if self.since_update >= HOP {
+            self.since_update = 0;
+            self.update();
+        }
+        let e = x + self
+            .coeffs
+            .iter()
+            .zip(&self.fir)
+            .map(|(c, s)| c * s)
+            .sum::<f32>();
+        self.fir.copy_within(..ORDER - 1, 1);
+        self.fir[0] = x;
+        e
+    }
+
+    /// All-pole synthesis of `e` through the current envelope.
+    #[inline]
+    pub fn color(&mut self, e: f32) -> f32 {
+        let y = e - self
+            .coeffs
+            .iter()
+            .zip(&self.iir)
+            .map(|(c, s)| c * s)
+            .sum::<f32>();
+        self.iir.copy_within(..ORDER - 1, 1);
+        self.iir[0] = y;
+        y
+    }
+}
@@ -0,0 +1,587 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use dsp_core::delay::DelayLine;
+use dsp_core::denormal::flush;
+use dsp_core::events::{EventRing, NoteEvent, ALL_NOTES_OFF, NOTE_OFF, NOTE_ON, PITCH_BEND};
+use dsp_core::gain::db_to_lin;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_STRINGS: usize = 8;
+
+// Indices into `Pluck::PARAMS`, in the order of `pluck_set_params`' arguments.
+pub const PARAM_DECAY_S: u32 = 0;
+pub const PARAM_DAMPING: u32 = 1;
+pub const PARAM_BRIGHTNESS: u32 = 2;
+pub const PARAM_STIFFNESS: u32 = 3;
+pub const PARAM_LEVEL_DB: u32 = 4;
+
+/// Lowest string pitch; sets the delay-line length.
+const MIN_HZ: f32 = 20.0;
+/// A string is silent (and skipped) once its level falls below this (-80 dB).
+const SILENT: f32 = 1e-4;
+/// Decay time after a note-off, like a fretting hand muting the string.
+const RELEASE_SECONDS: f32 = 0.15;
+/// Dispersion all-passes in the loop; more stages make the stiffness stronger.
+const DISPERSION_STAGES: usize = 2;
+/// Loop gain cap, as a multiple of the decay time (see `Pluck::tune`).
+const DC_DECAY_FACTOR: f32 = 4.0;
+/// Output DC blocker corner (the noise burst leaves an offset circulating).
+const DC_HZ: f32 = 10.0;
+
+/// Phase delay in samples at `omega` (rad/sample) of the damping low-pass
+/// `y = (1 - d) x + d y[n-1]` and its gain there.
+fn lowpass_response(d: f32, omega: f32) -> (f32, f32) {
+    let phase = (d * omega.sin()).atan2(1.0 - d * omega.cos());
+    let gain = (1.0 - d) / (1.0 - 2.0 * d * omega.cos() + d * d).sqrt();
+    (phase / omega, gain)
+}
+
+/// Phase delay in samples at `omega` of the first-order all-pass `(a + z^-1) / (1 + a z^-1)`.
+fn allpass_delay(a: f32, omega: f32) -> f32 {
+    let phase = -omega + 2.0 * (a * omega.sin()).atan2(1.0 + a * omega.cos());
+    -phase / omega
+}
+
+/// One string: the delay loop (damping low-pass and dispersion all-passes inside it), the
+/// noise burst still being injected, and a peak level for spotting when it has died away.
+struct Str {
+    delay: DelayLine,
+    note: u8,
+    gate: bool,
+    /// Note-on order, for stealing the oldest string.
+    age: u64,
+    /// Read delay (the loop minus its other delays), and loop gain held and released.
+    read: f32,
+    gain: f32,
+    release_gain: f32,
+    lowpass: f32,
+    allpass: [(f32, f32); DISPERSION_STAGES],
+    burst_left: usize,
+    burst_level: f32,
+    burst_state: f32,
+    level: f32,
+}
+
+/// Karplus-Strong plucked strings, up to eight at once. A note-on fills a string's delay loop
+/// with a burst of low-passed noise one period long (`brightness` sets the low-pass, velocity
+/// the level); the loop then circulates it through a damping low-pass (`damping` — high
+/// harmonics die first) and all-passes that stretch the upper partials sharp (`stiffness`).
+/// Loop gain is set so the fundamental falls 60 dB in `decay` (sooner under heavy damping); a
+/// note-off mutes the string.
+/// The delay is tuned for the filters' phase delay at the fundamental, so strings stay in tune
+/// whatever the settings. Notes come through the same event ring as the synths.
+pub struct Pluck {
+    notes: EventRing,
+    strings: Vec<Str>,
+    decay_s: f32,
+    damping: f32,
+    brightness: f32,
+    stiffness: f32,
+    bend: f32,
+    level_db: f32,
+    gain: f32,
+    dc_r: f32,
+    dc_x1: f32,
+    dc_y1: f32,
+    rng: u32,
+    started: u64,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Str {
+    fn new(sample_rate_hz: f32) -> Self {
+        Self {
+            delay: DelayLine::new((sample_rate_hz / MIN_HZ) as usize + 2),
+            note: 0,
+            gate: false,
+            age: 0,
+            read: 1.0,
+            gain: 0.0,
+            release_gain: 0.0,
+            lowpass: 0.0,
+            allpass: [(0.0, 0.0); DISPERSION_STAGES],
+            burst_left: 0,
+            burst_level: 0.0,
+            burst_state: 0.0,
+            level: 0.0,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.burst_left > 0 || self.level > SILENT
+    }
+}
+
+impl Pluck {
+    /// Uniform in [-1, 1) from a xorshift32.
+    #[inline]
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 7) as f32 / (1u32 << 24) as f32 - 1.0
+    }
+
+    fn damping_coeff(&self) -> f32 {
+        0.05 + 0.9 * self.damping
+    }
+
+    fn allpass_coeff(&self) -> f32 {
+        -0.7 * self.stiffness
+    }
+
+    /// Tunes string `index` to its note and sets its loop gains.
+    fn tune(&mut self, index: usize) {
+        let sr = self.sample_rate_hz;
+        let d = self.damping_coeff();
+        let a = self.allpass_coeff();
+        let s = &mut self.strings[index];
+        let hz = clamp(
+            440.0 * ((s.note as f32 + self.bend - 69.0) / 12.0).exp2(),
+            MIN_HZ,
+            sr * 0.25,
+        );
+        let omega = 2.0 * PI * hz / sr;
+        let (lp_delay, lp_gain) = lowpass_response(d, omega);
+        let ap_delay = allpass_delay(a, omega) * DISPERSION_STAGES as f32;
+        // The read itself adds one sample: `read(0)` is the sample pushed last time round.
+        s.read = (sr / hz - 1.0 - lp_delay - ap_delay).max(1.0);
+        // Making up the low-pass's loss at the fundamental raises the loop gain at DC (also a
+        // loop resonance) above the fundamental's; it is capped there so DC still dies away,
+        // within DC_DECAY_FACTOR times the decay. Heavy damping on high notes shortens the
+        // decay instead.
+        let per_period = |seconds: f32| 10.0_f32.powf(-3.0 / (hz * seconds));
+        let cap = per_period(self.decay_s * DC_DECAY_FACTOR);
+        s.gain = (per_period(self.decay_s) / lp_gain).min(cap);
+        s.release_gain = (per_period(RELEASE_SECONDS.min(self.decay_s)) / lp_gain).min(cap);
+    }
+
+    fn note_on(&mut self, note: u8, velocity: f32) {
+        let strings = &self.strings;
+        let index = (0..MAX_STRINGS)
+            .find(|&i| strings[i].is_active() && strings[i].note == note)
+            .or_else(|| (0..MAX_STRINGS).find(|&i| !strings[i].is_active()))
+            .or_else(|| (0..MAX_STRINGS).min_by_key(|&i| strings[i].age))
+            .unwrap_or(0);
+        self.started += 1;
+        let s = &mut self.strings[index];
+        s.note = note;
+        s.gate = true;
+        s.age = self.started;
+        self.tune(index);
+        let s = &mut self.strings[index];
+        // Re-plucking adds to what is still ringing, as on a real string.
+        s.burst_left = (s.read.round() as usize).max(1);
+        s.burst_level = velocity;
+        s.burst_state = 0.0;
+    }
+
+    fn note_off(&mut self, note: u8) {
+        for s in &mut self.strings {
+            if s.gate && s.note == note {
+                s.gate = false;
+            }
+        }
+    }
+
+    fn handle(&mut self, e: NoteEvent) {
+        let note = e.note.min(127) as u8;
+        match e.kind {
+            NOTE_ON if e.value > 0.0 => self.note_on(note, clamp(e.value, 0.0, 1.0)),
+            // Velocity 0 is a note-off, as in MIDI.
+            NOTE_ON | NOTE_OFF => self.note_off(note),
+            ALL_NOTES_OFF => self.strings.iter_mut().for_each(|s| s.gate = false),
+            PITCH_BEND => {
+                self.bend = clamp(e.value, -48.0, 48.0);
+                self.retune_all();
+            }
+            _ => {}
+        }
+    }
+
+    fn retune_all(&mut self) {
+        for index in 0..MAX_STRINGS {
+            self.tune(index);
+        }
+    }
+
+    fn render(&mut self, output: &mut [f32], channels: usize) {
+        let d = self.damping_coeff();
+        let a = self.allpass_coeff();
+        // Burst low-pass: dark at 0, unfiltered at 1.
+        let burst_coeff = 0.95 * (1.0 - self.brightness);
+        for out in output.chunks_exact_mut(channels) {
+            let mut sum = 0.0;
+            for index in 0..MAX_STRINGS {
+                if !self.strings[index].is_active() {
+                    continue;
+                }
+                let noise = if self.strings[index].burst_left > 0 {
+                    self.random()
+                } else {
+                    0.0
+                };
+                let s = &mut self.strings[index];
+                let mut y = s.delay.read_cubic(s.read);
+                for (x1, y1) in &mut s.allpass {
+                    let v = flush(a * y + *x1 - a * *y1);
+                    *x1 = y;
+                    *y1 = v;
+                    y = v;
+                }
+                s.lowpass = flush((1.0 - d) * y + d * s.lowpass);
+                let gain = if s.gate { s.gain } else { s.release_gain };
+                let mut fed = s.lowpass * gain;
+                if s.burst_left > 0 {
+                    s.burst_left -= 1;
+                    s.burst_state = (1.0 - burst_coeff) * noise + burst_coeff * s.burst_state;
+                    fed += s.burst_state * s.burst_level;
+                }
+                s.delay.push(fed);
+                s.level = fed.abs().max(s.level * 0.9995);
+                sum += fed;
+            }
+
+            let v = sum - self.dc_x1 + self.dc_r * self.dc_y1;
+            self.dc_x1 = sum;
+            self.dc_y1 = flush(v);
+            out.fill(v * self.gain);
+        }
+    }
+}
+
+impl Node for Pluck {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Decay` for the fundamental to fall 60 dB; `Damping` is how much faster the upper
+    /// harmonics die; `Brightness` of the pluck's burst; `Stiffness` stretches the partials (0
+    /// is an ideal string).
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::log("Decay", "s", 0.1, 20.0, 3.0),
+        ParamDescriptor::linear("Damping", "", 0.0, 1.0, 0.3),
+        ParamDescriptor::linear("Brightness", "", 0.0, 1.0, 0.7),
+        ParamDescriptor::linear("Stiffness", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Level", "dB", -60.0, 12.0, -6.0),
+    ];
+    const INPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut p = Pluck {
+            notes: EventRing::new(),
+            strings: (0..MAX_STRINGS).map(|_| Str::new(sample_rate_hz)).collect(),
+            decay_s: 3.0,
+            damping: 0.3,
+            brightness: 0.7,
+            stiffness: 0.0,
+            bend: 0.0,
+            level_db: -6.0,
+            gain: db_to_lin(-6.0),
+            dc_r: (-2.0 * PI * DC_HZ / sample_rate_hz).exp(),
+            dc_x1: 0.0,
+            dc_y1: 0.0,
+            rng: 0x9e37_79b9,
+            started: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        p.retune_all();
+        p
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_DECAY_S => {
+                self.decay_s = clamp(value, 0.1, 20.0);
+                self.retune_all();
+            }
+            PARAM_DAMPING => {
+                self.damping = clamp(value, 0.0, 1.0);
+                self.retune_all();
+            }
+            PARAM_BRIGHTNESS => self.brightness = clamp(value, 0.0, 1.0),
+            PARAM_STIFFNESS => {
+                self.stiffness = clamp(value, 0.0, 1.0);
+                self.retune_all();
+            }
+            PARAM_LEVEL_DB => {
+                self.level_db = clamp(value, -60.0, 12.0);
+                self.gain = db_to_lin(self.level_db);
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_DECAY_S => self.decay_s,
+            PARAM_DAMPING => self.damping,
+            PARAM_BRIGHTNESS => self.brightness,
+            PARAM_STIFFNESS => self.stiffness,
+            PARAM_LEVEL_DB => self.level_db,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn note_events(&mut self) -> Option<&mut EventRing> {
+        Some(&mut self.notes)
+    }
+
+    fn note(&mut self, event: NoteEvent) {
+        self.handle(event);
+    }
+
+    /// Renders the same mono mix on every channel.
+    fn process(&mut self, _input: &[f32], output: &mut [f32], channels: usize) {
+        self.render(output, channels);
+    }
+
+    /// Damps every string at once and clears the DC blocker; queued events are kept.
+    fn reset(&mut self) {
+        for s in &mut self.strings {
+            s.delay.clear();
+            s.gate = false;
+            s.age = 0;
+            s.lowpass = 0.0;
+            s.allpass = [(0.0, 0.0); DISPERSION_STAGES];
+            s.burst_left = 0;
+            s.burst_level = 0.0;
+            s.burst_state = 0.0;
+            s.level = 0.0;
+        }
+        self.dc_x1 = 0.0;
+        self.dc_y1 = 0.0;
+        self.rng = 0x9e37_79b9;
+        self.started = 0;
+    }
+}
+
+node_abi::export_node!(Pluck {
+    abi_version: pluck_abi_version,
+    new: pluck_new,
+    free: pluck_free,
+    param_count: pluck_param_count,
+    param_descriptors: pluck_param_descriptors,
+    set_param: pluck_set_param,
+    get_param: pluck_get_param,
+    param_events: pluck_param_events,
+    process: pluck_process,
+    process_offline: pluck_process_offline,
+    latency_samples: pluck_latency_samples,
+    io_layout: pluck_get_io_layout,
+    process_io: pluck_process_io,
+    reset: pluck_reset,
+    state_size: pluck_state_size,
+    save_state: pluck_save_state,
+    load_state: pluck_load_state,
+    preset_size: pluck_preset_size,
+    save_preset: pluck_save_preset,
+    load_preset: pluck_load_preset,
+    last_error_code: pluck_last_error_code,
+    last_error_message: pluck_last_error_message_ptr,
+});
+
+node_abi::test_params!(Pluck);
+
+dsp_core::test_block_sizes!(
+    pluck_new,
+    pluck_process(input, output),
+    pluck_free,
+    setup: |p| {
+        let events = unsafe { &mut *pluck_events(p) };
+        events.push(NoteEvent { kind: NOTE_ON, note: 60, value: 1.0, frame: 0 });
+    },
+);
+
+node_abi::test_io_block!(
+    Pluck,
+    setup: |s: &mut Pluck| {
+        s.notes.push(NoteEvent { kind: NOTE_ON, note: 60, value: 1.0, frame: 0 });
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Pluck as PluckNode);
+
+/// The note-event ring inside the node, for the worklet to write into (layout in
+/// `dsp_core::events`). Stays put for the node's lifetime.
+#[no_mangle]
+pub extern "C" fn pluck_events(handle: Handle) -> *mut EventRing {
+    guard(handle, |p: &mut Pluck| &mut p.notes as *mut EventRing).unwrap_or(core::ptr::null_mut())
+}
+
+/// Every parameter in one call, as `pluck_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn pluck_set_params(
+    handle: Handle,
+    decay_s: f32,
+    damping: f32,
+    brightness: f32,
+    stiffness: f32,
+    level_db: f32,
+) {
+    guard(handle, |p: &mut Pluck| {
+        p.set_param(PARAM_DECAY_S, decay_s);
+        p.set_param(PARAM_DAMPING, damping);
+        p.set_param(PARAM_BRIGHTNESS, brightness);
+        p.set_param(PARAM_STIFFNESS, stiffness);
+        p.set_param(PARAM_LEVEL_DB, level_db);
+    });
+}
+
+/// Strings currently ringing, for the UI.
+#[no_mangle]
+pub extern "C" fn pluck_active_strings(handle: Handle) -> u32 {
+    guard(handle, |p: &mut Pluck| {
+        p.strings.iter().filter(|s| s.is_active()).count() as u32
+    })
+    .unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    fn pluck(params: &[(u32, f32)]) -> Handle {
+        let h = pluck_new(RATE);
+        for &(index, value) in params {
+            pluck_set_param(h, index, value);
+        }
+        h
+    }
+
+    fn send(h: Handle, kind: u32, note: u32) {
+        let events = unsafe { &mut *pluck_events(h) };
+        assert!(events.push(NoteEvent {
+            kind,
+            note,
+            value: 1.0,
+            frame: 0,
+        }));
+    }
+
+    fn render(h: Handle, frames: usize) -> Vec<f32> {
+        render_quanta(frames, |_, y| {
+            pluck_process(h, core::ptr::null(), y.as_mut_ptr(), y.len(), 1)
+        })
+    }
+
+    /// 1.5 s of A4 (440 Hz) plucked on a string set up with `params`.
+    fn play(params: &[(u32, f32)]) -> Vec<f32> {
+        let h = pluck(params);
+        send(h, NOTE_ON, 69);
+        let y = render(h, 72_000);
+        pluck_free(h);
+        y
+    }
+
+    /// The strongest frequency within `hz` ± 2%, to the nearest hertz and then to 0.1 Hz.
+    fn peak_hz(signal: &[f32], hz: f32) -> f32 {
+        let strongest = |candidates: &mut dyn Iterator<Item = f32>| {
+            candidates
+                .max_by(|&a, &b| amplitude(signal, a).total_cmp(&amplitude(signal, b)))
+                .unwrap()
+        };
+        let span = (hz * 0.02) as i32;
+        let coarse = strongest(&mut (-span..=span).map(|k| hz + k as f32));
+        strongest(&mut (-10..=10).map(|k| coarse + k as f32 * 0.1))
+    }
+
+    /// The loop is tuned for its filters' delay, so the note stays at pitch with any damping
+    /// or stiffness.
+    #[test]
+    fn strings_stay_in_tune() {
+        for (damping, stiffness) in [(0.0, 0.0), (0.3, 0.0), (0.9, 0.0), (0.3, 1.0)] {
+            let y = play(&[(PARAM_DAMPING, damping), (PARAM_STIFFNESS, stiffness)]);
+            let hz = peak_hz(&y[4_800..52_800], 440.0);
+            assert!((hz - 440.0).abs() < 0.5, "{damping} {stiffness}: {hz}");
+        }
+    }
+
+    /// The fundamental falls 60 dB over `Decay`: 30 dB in a second at 2 s.
+    #[test]
+    fn fundamental_decays_in_time() {
+        let y = play(&[(PARAM_DECAY_S, 2.0), (PARAM_DAMPING, 0.0)]);
+        let early = amplitude(&y[9_600..19_200], 440.0);
+        let late = amplitude(&y[57_600..67_200], 440.0);
+        let drop = 20.0 * (late / early).log10();
+        assert!((drop + 30.0).abs() < 2.0, "{drop} dB");
+    }
+
+    /// `Damping` makes the upper harmonics die away faster than the fundamental.
+    #[test]
+    fn damping_darkens_the_tail() {
+        let fourth = |damping: f32| {
+            let y = play(&[(PARAM_DAMPING, damping)]);
+            let tail = &y[24_000..48_000];
+            amplitude(tail, 1760.0) / amplitude(tail, 440.0)
+        };
+        let (light, heavy) = (fourth(0.0), fourth(0.9));
+        assert!(heavy < 0.5 * light, "{light} {heavy}");
+    }
+
+    /// `Stiffness` stretches the upper partials sharp of the harmonic series.
+    #[test]
+    fn stiffness_stretches_partials() {
+        let fourth = |stiffness: f32| {
+            let y = play(&[(PARAM_DAMPING, 0.0), (PARAM_STIFFNESS, stiffness)]);
+            peak_hz(&y[4_800..52_800], 1760.0)
+        };
+        assert!((fourth(0.0) - 1760.0).abs() < 1.0);
+        let stretched = fourth(1.0);
+        assert!(stretched > 1770.0, "{stretched}");
+    }
+
+    /// A note-off mutes its string; a held one rings on.
+    #[test]
+    fn note_off_mutes_the_string() {
+        let h = pluck(&[]);
+        send(h, NOTE_ON, 60);
+        send(h, NOTE_ON, 64);
+        render(h, 4_800);
+        send(h, NOTE_OFF, 60);
+        render(h, 48_000);
+        assert_eq!(pluck_active_strings(h), 1);
+        pluck_free(h);
+    }
+
+    /// Up to eight strings ring at once; a ninth note takes the oldest.
+    #[test]
+    fn eight_strings_at_once() {
+        let h = pluck(&[]);
+        for note in 60..69 {
+            send(h, NOTE_ON, note);
+            render(h, 128);
+        }
+        assert_eq!(pluck_active_strings(h), 8);
+        let notes = guard(h, |p: &mut Pluck| {
+            p.strings.iter().map(|s| s.note).collect::<Vec<_>>()
+        })
+        .unwrap();
+        assert_eq!(notes, [68, 61, 62, 63, 64, 65, 66, 67]);
+        pluck_free(h);
+    }
+}
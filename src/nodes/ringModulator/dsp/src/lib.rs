@@ -0,0 +1,348 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::f32::consts::TAU;
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `RingMod::PARAMS`, in the order of `ringmod_set_params`' arguments.
+pub const PARAM_WAVEFORM: u32 = 0;
+pub const PARAM_FREQ_HZ: u32 = 1;
+pub const PARAM_FINE_CENTS: u32 = 2;
+pub const PARAM_MIX: u32 = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+enum Waveform {
+    Sine,
+    Triangle,
+    Square,
+}
+
+impl Waveform {
+    fn from_u32(v: u32) -> Self {
+        match v {
+            1 => Self::Triangle,
+            2 => Self::Square,
+            _ => Self::Sine,
+        }
+    }
+
+    /// `phase` in cycles, 0..1.
+    #[inline]
+    fn at(self, phase: f32) -> f32 {
+        match self {
+            Self::Sine => (TAU * phase).sin(),
+            Self::Triangle => 4.0 * ((phase + 0.75).fract() - 0.5).abs() - 1.0,
+            Self::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+        }
+    }
+}
+
+/// Ring modulator: the input times a carrier, blended with the dry signal. The carrier is the
+/// internal oscillator, or whatever the graph feeds into `ringmod_process_with_carrier`.
+#[repr(C)]
+pub struct RingMod {
+    waveform: Waveform,
+    freq_hz: f32,
+    fine_cents: f32,
+    /// Carrier increment in cycles per sample.
+    step: f32,
+    mix: f32,
+    phase: f32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl RingMod {
+    fn update_step(&mut self) {
+        let hz = self.freq_hz * (2.0_f32).powf(self.fine_cents / 1200.0);
+        self.step = (hz / self.sample_rate_hz).min(0.5);
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            let carrier = self.waveform.at(self.phase);
+            self.phase = (self.phase + self.step).fract();
+            for (x, y) in inp.iter().zip(out.iter_mut()) {
+                *y = *x + (*x * carrier - *x) * self.mix;
+            }
+        }
+    }
+
+    fn process_with_carrier(
+        &mut self,
+        input: &[f32],
+        carrier: &[f32],
+        output: &mut [f32],
+        channels: usize,
+        carrier_channels: usize,
+    ) {
+        for ((inp, car), out) in input
+            .chunks_exact(channels)
+            .zip(carrier.chunks_exact(carrier_channels))
+            .zip(output.chunks_exact_mut(channels))
+        {
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let c = car[ch % carrier_channels];
+                *y = *x + (*x * c - *x) * self.mix;
+            }
+        }
+    }
+}
+
+impl Node for RingMod {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Waveform` 0 sine, 1 triangle, 2 square. The carrier runs at `Frequency` detuned by
+    /// `Fine`; `Mix` goes from dry to fully modulated.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Waveform", "", 0.0, 2.0, 0.0),
+        ParamDescriptor::log("Frequency", "Hz", 0.1, 5000.0, 440.0),
+        ParamDescriptor::linear("Fine", "cents", -100.0, 100.0, 0.0),
+        ParamDescriptor::linear("Mix", "", 0.0, 1.0, 1.0),
+    ];
+    /// An external carrier in place of the oscillator (reused round-robin when it has fewer
+    /// channels than the input).
+    const AUX_CHANNELS: usize = MAX_CHANNELS;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        RingMod {
+            waveform: Waveform::Sine,
+            freq_hz: 440.0,
+            fine_cents: 0.0,
+            step: 440.0 / sample_rate_hz,
+            mix: 1.0,
+            phase: 0.0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_WAVEFORM => {
+                self.waveform = Waveform::from_u32(clamp(value, 0.0, 2.0).round() as u32);
+            }
+            PARAM_FREQ_HZ => {
+                self.freq_hz = clamp(value, 0.1, 5000.0);
+                self.update_step();
+            }
+            PARAM_FINE_CENTS => {
+                self.fine_cents = clamp(value, -100.0, 100.0);
+                self.update_step();
+            }
+            PARAM_MIX => self.mix = clamp(value, 0.0, 1.0),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_WAVEFORM => self.waveform as u32 as f32,
+            PARAM_FREQ_HZ => self.freq_hz,
+            PARAM_FINE_CENTS => self.fine_cents,
+            PARAM_MIX => self.mix,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// With a carrier, the internal oscillator holds its phase.
+    fn process_aux(
+        &mut self,
+        input: &[f32],
+        aux: &[f32],
+        aux_channels: usize,
+        output: &mut [f32],
+        channels: usize,
+    ) {
+        if aux.is_empty() {
+            self.process_interleaved(input, output, channels);
+        } else {
+            self.process_with_carrier(input, aux, output, channels, aux_channels);
+        }
+    }
+
+    /// Restarts the carrier at phase 0.
+    fn reset(&mut self) {
+        self.phase = 0.0;
+    }
+}
+
+node_abi::export_node!(RingMod {
+    abi_version: ringmod_abi_version,
+    new: ringmod_new,
+    free: ringmod_free,
+    param_count: ringmod_param_count,
+    param_descriptors: ringmod_param_descriptors,
+    set_param: ringmod_set_param,
+    get_param: ringmod_get_param,
+    param_events: ringmod_param_events,
+    process: ringmod_process,
+    process_offline: ringmod_process_offline,
+    latency_samples: ringmod_latency_samples,
+    io_layout: ringmod_get_io_layout,
+    process_io: ringmod_process_io,
+    reset: ringmod_reset,
+    state_size: ringmod_state_size,
+    save_state: ringmod_save_state,
+    load_state: ringmod_load_state,
+    preset_size: ringmod_preset_size,
+    save_preset: ringmod_save_preset,
+    load_preset: ringmod_load_preset,
+    last_error_code: ringmod_last_error_code,
+    last_error_message: ringmod_last_error_message_ptr,
+    process_aux: ringmod_process_with_carrier,
+    process_aux_offline: ringmod_process_offline_with_carrier,
+    process_io_aux: ringmod_process_io_with_carrier,
+});
+
+node_abi::test_params!(RingMod);
+
+dsp_core::test_block_sizes!(
+    ringmod_new,
+    ringmod_process_with_carrier(input, aux, output),
+    ringmod_free
+);
+
+node_abi::test_io_block!(RingMod);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(RingMod as RingModNode);
+
+/// Every parameter in one call, as `ringmod_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn ringmod_set_params(
+    handle: Handle,
+    waveform: u32,
+    freq_hz: f32,
+    fine_cents: f32,
+    mix: f32,
+) {
+    guard(handle, |r: &mut RingMod| {
+        r.set_param(PARAM_WAVEFORM, waveform as f32);
+        r.set_param(PARAM_FREQ_HZ, freq_hz);
+        r.set_param(PARAM_FINE_CENTS, fine_cents);
+        r.set_param(PARAM_MIX, mix);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    fn ringmod(params: &[(u32, f32)]) -> RingMod {
+        let mut r = RingMod::new(RATE);
+        for &(index, value) in params {
+            r.set_param(index, value);
+        }
+        r
+    }
+
+    fn render(r: &mut RingMod, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(r, &input[span], y, 1))
+    }
+
+    /// A constant input lets the carrier straight through: each waveform over one 100-sample
+    /// period at 480 Hz, and `Fine` detuning it by a semitone at most.
+    #[test]
+    fn carrier_waveforms_and_tuning() {
+        let carrier = |params: &[(u32, f32)], frames: usize| {
+            let mut r = ringmod(&[&[(PARAM_FREQ_HZ, 480.0)], params].concat());
+            render(&mut r, &vec![1.0; frames])
+        };
+        let sine = carrier(&[], 100);
+        let triangle = carrier(&[(PARAM_WAVEFORM, 1.0)], 100);
+        let square = carrier(&[(PARAM_WAVEFORM, 2.0)], 100);
+        for i in 0..100 {
+            let t = i as f32 / 100.0;
+            assert!((sine[i] - (TAU * t).sin()).abs() < 1e-4);
+            let ramp = 4.0 * t;
+            let want = if ramp <= 1.0 {
+                ramp
+            } else if ramp <= 3.0 {
+                2.0 - ramp
+            } else {
+                ramp - 4.0
+            };
+            assert!((triangle[i] - want).abs() < 1e-4, "{i}: {}", triangle[i]);
+            // Phase 0.5 lands a rounding error either side of the edge.
+            if i != 50 {
+                assert_eq!(square[i], if i < 50 { 1.0 } else { -1.0 });
+            }
+        }
+
+        let detuned = carrier(&[(PARAM_WAVEFORM, 2.0), (PARAM_FINE_CENTS, 100.0)], 48_000);
+        let cycles = detuned
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] > 0.0)
+            .count() as f32;
+        assert!(
+            (cycles - 480.0 * 2f32.powf(1.0 / 12.0)).abs() <= 1.0,
+            "{cycles}"
+        );
+    }
+
+    /// A sine carrier turns a tone into its sum and difference frequencies; `Mix` keeps part
+    /// of the dry tone.
+    #[test]
+    fn sine_carrier_gives_sidebands() {
+        let w = TAU * 1000.0 / RATE;
+        let tone: Vec<f32> = (0..48_000).map(|i| (w * i as f32).sin()).collect();
+        let y = render(&mut ringmod(&[(PARAM_FREQ_HZ, 400.0)]), &tone);
+        assert!(amplitude(&y, 1000.0) < 1e-3);
+        for hz in [600.0, 1400.0] {
+            assert!((amplitude(&y, hz) - 0.5).abs() < 1e-3);
+        }
+        let y = render(
+            &mut ringmod(&[(PARAM_FREQ_HZ, 400.0), (PARAM_MIX, 0.5)]),
+            &tone,
+        );
+        assert!((amplitude(&y, 1000.0) - 0.5).abs() < 1e-3);
+        assert!((amplitude(&y, 600.0) - 0.25).abs() < 1e-3);
+    }
+
+    /// An external carrier replaces the oscillator, its channels reused round-robin, and the
+    /// oscillator holds its phase until the carrier goes away.
+    #[test]
+    fn external_carrier_replaces_the_oscillator() {
+        let mut r = ringmod(&[(PARAM_FREQ_HZ, 480.0)]);
+        let mut y = [0.0; 4];
+        Node::process_aux(&mut r, &[1.0, 2.0, 1.0, 2.0], &[0.5, -1.0], 1, &mut y, 2);
+        assert_eq!(y, [0.5, 1.0, -1.0, -2.0]);
+        let sine = render(&mut r, &[1.0; 26]);
+        assert!((sine[25] - 1.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,340 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::envelope::EnvelopeFollower;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `SampleHold::PARAMS`, in the order of `sample_hold_set_params`' arguments.
+pub const PARAM_MODE: u32 = 0;
+pub const PARAM_RATE_HZ: u32 = 1;
+pub const PARAM_RISE_MS: u32 = 2;
+pub const PARAM_FALL_MS: u32 = 3;
+
+/// Input passes straight to the slew limiter.
+pub const THROUGH: u32 = 0;
+/// Sampled at the internal rate.
+pub const INTERNAL_CLOCK: u32 = 1;
+/// Sampled on each rising edge of the clock input.
+pub const EXTERNAL_CLOCK: u32 = 2;
+
+/// Clock input thresholds: an edge is a rise above `CLOCK_HIGH` after a fall below
+/// `CLOCK_LOW`, so a noisy or slow clock doesn't double-trigger.
+const CLOCK_HIGH: f32 = 0.5;
+const CLOCK_LOW: f32 = 0.25;
+
+/// Sample and hold into a slew limiter, for shaping control signals. Each channel of the input
+/// is sampled on the internal clock or on rising edges of the clock input (a mono clock drives
+/// every channel) and held until the next; the held (or, in through mode, the live) value is
+/// then followed with separate rise and fall times, each the time constant of a one-pole lag.
+pub struct SampleHold {
+    mode: u32,
+    rate_hz: f32,
+    rise_ms: f32,
+    fall_ms: f32,
+    /// Internal clock phase (0..1) and advance per sample.
+    phase: f32,
+    inc: f32,
+    held: [f32; MAX_CHANNELS],
+    clock_high: [bool; MAX_CHANNELS],
+    slew: [EnvelopeFollower; MAX_CHANNELS],
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl SampleHold {
+    fn update_slew(&mut self) {
+        for slew in &mut self.slew {
+            slew.set_times(self.rise_ms, self.fall_ms, self.sample_rate_hz);
+        }
+    }
+
+    /// `clock` is `None` when nothing is connected; external clocking then holds.
+    fn hold(
+        &mut self,
+        input: &[f32],
+        clock: Option<(&[f32], usize)>,
+        output: &mut [f32],
+        channels: usize,
+    ) {
+        let frames = input.len() / channels;
+        for i in 0..frames {
+            self.phase += self.inc;
+            let tick = self.phase >= 1.0;
+            self.phase -= self.phase.floor();
+            for ch in 0..channels {
+                let x = input[i * channels + ch];
+                let x = if x.is_finite() { x } else { 0.0 };
+                let sample = match self.mode {
+                    INTERNAL_CLOCK => tick,
+                    EXTERNAL_CLOCK => clock.is_some_and(|(clock, clock_channels)| {
+                        let c = clock[i * clock_channels + ch % clock_channels];
+                        let was_high = self.clock_high[ch];
+                        if c > CLOCK_HIGH {
+                            self.clock_high[ch] = true;
+                        } else if c < CLOCK_LOW {
+                            self.clock_high[ch] = false;
+                        }
+                        self.clock_high[ch] && !was_high
+                    }),
+                    _ => true,
+                };
+                if sample {
+                    self.held[ch] = x;
+                }
+                output[i * channels + ch] = self.slew[ch].process(self.held[ch]);
+            }
+        }
+    }
+}
+
+impl Node for SampleHold {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Mode` 0 through (slew only), 1 internal clock at `Rate`, 2 external clock; `Rise` and
+    /// `Fall` 0 jump straight to the value.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped(
+            "Mode",
+            "",
+            0.0,
+            EXTERNAL_CLOCK as f32,
+            INTERNAL_CLOCK as f32,
+        ),
+        ParamDescriptor::log("Rate", "Hz", 0.01, 1000.0, 4.0),
+        ParamDescriptor::linear("Rise", "ms", 0.0, 10_000.0, 0.0),
+        ParamDescriptor::linear("Fall", "ms", 0.0, 10_000.0, 0.0),
+    ];
+    /// The clock; channel `c` of the signal is clocked by clock channel `c % aux_channels`.
+    const AUX_CHANNELS: usize = MAX_CHANNELS;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        SampleHold {
+            mode: INTERNAL_CLOCK,
+            rate_hz: 4.0,
+            rise_ms: 0.0,
+            fall_ms: 0.0,
+            phase: 0.0,
+            inc: 4.0 / sample_rate_hz,
+            held: [0.0; MAX_CHANNELS],
+            clock_high: [false; MAX_CHANNELS],
+            slew: [EnvelopeFollower::new(0.0, 0.0, sample_rate_hz); MAX_CHANNELS],
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_MODE => self.mode = clamp(value, 0.0, EXTERNAL_CLOCK as f32).round() as u32,
+            PARAM_RATE_HZ => {
+                self.rate_hz = clamp(value, 0.01, 1000.0);
+                self.inc = self.rate_hz / self.sample_rate_hz;
+            }
+            PARAM_RISE_MS => {
+                self.rise_ms = clamp(value, 0.0, 10_000.0);
+                self.update_slew();
+            }
+            PARAM_FALL_MS => {
+                self.fall_ms = clamp(value, 0.0, 10_000.0);
+                self.update_slew();
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_MODE => self.mode as f32,
+            PARAM_RATE_HZ => self.rate_hz,
+            PARAM_RISE_MS => self.rise_ms,
+            PARAM_FALL_MS => self.fall_ms,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    /// Without a clock, external clocking holds.
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.hold(input, None, output, channels);
+    }
+
+    fn process_aux(
+        &mut self,
+        input: &[f32],
+        aux: &[f32],
+        aux_channels: usize,
+        output: &mut [f32],
+        channels: usize,
+    ) {
+        let clock = (!aux.is_empty()).then_some((aux, aux_channels));
+        self.hold(input, clock, output, channels);
+    }
+
+    /// Drops the held values, the clock edges and the slew, and restarts the internal clock.
+    fn reset(&mut self) {
+        self.phase = 0.0;
+        self.held = [0.0; MAX_CHANNELS];
+        self.clock_high = [false; MAX_CHANNELS];
+        for slew in &mut self.slew {
+            slew.reset(0.0);
+        }
+    }
+}
+
+node_abi::export_node!(SampleHold {
+    abi_version: sample_hold_abi_version,
+    new: sample_hold_new,
+    free: sample_hold_free,
+    param_count: sample_hold_param_count,
+    param_descriptors: sample_hold_param_descriptors,
+    set_param: sample_hold_set_param,
+    get_param: sample_hold_get_param,
+    param_events: sample_hold_param_events,
+    process: sample_hold_process,
+    process_offline: sample_hold_process_offline,
+    latency_samples: sample_hold_latency_samples,
+    io_layout: sample_hold_get_io_layout,
+    process_io: sample_hold_process_io,
+    reset: sample_hold_reset,
+    state_size: sample_hold_state_size,
+    save_state: sample_hold_save_state,
+    load_state: sample_hold_load_state,
+    preset_size: sample_hold_preset_size,
+    save_preset: sample_hold_save_preset,
+    load_preset: sample_hold_load_preset,
+    last_error_code: sample_hold_last_error_code,
+    last_error_message: sample_hold_last_error_message_ptr,
+    process_aux: sample_hold_process_clocked,
+    process_aux_offline: sample_hold_process_offline_clocked,
+    process_io_aux: sample_hold_process_io_clocked,
+});
+
+node_abi::test_params!(SampleHold);
+
+dsp_core::test_block_sizes!(
+    sample_hold_new,
+    sample_hold_process_clocked(input, aux, output),
+    sample_hold_free,
+    setup: |p| sample_hold_set_param(p, PARAM_MODE, EXTERNAL_CLOCK as f32),
+);
+
+node_abi::test_io_block!(
+    SampleHold,
+    setup: |s: &mut SampleHold| s.set_param(PARAM_MODE, EXTERNAL_CLOCK as f32),
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(SampleHold as SampleHoldNode);
+
+/// Every parameter in one call, as `sample_hold_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn sample_hold_set_params(
+    handle: Handle,
+    mode: u32,
+    rate_hz: f32,
+    rise_ms: f32,
+    fall_ms: f32,
+) {
+    guard(handle, |s: &mut SampleHold| {
+        s.set_param(PARAM_MODE, mode as f32);
+        s.set_param(PARAM_RATE_HZ, rate_hz);
+        s.set_param(PARAM_RISE_MS, rise_ms);
+        s.set_param(PARAM_FALL_MS, fall_ms);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    fn sample_hold(params: &[(u32, f32)]) -> SampleHold {
+        let mut s = SampleHold::new(RATE);
+        for &(index, value) in params {
+            s.set_param(index, value);
+        }
+        s
+    }
+
+    /// `input` (and `clock`, if any) through `s`.
+    fn render(s: &mut SampleHold, input: &[f32], clock: Option<&[f32]>) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| match clock {
+            Some(c) => s.process_aux(&input[span.clone()], &c[span], 1, y, 1),
+            None => Node::process(s, &input[span], y, 1),
+        })
+    }
+
+    /// The internal clock samples the input `Rate` times a second and holds it between.
+    #[test]
+    fn internal_clock_samples_and_holds() {
+        let mut s = sample_hold(&[(PARAM_RATE_HZ, 100.0)]);
+        let ramp: Vec<f32> = (0..4_800).map(|i| i as f32).collect();
+        let y = render(&mut s, &ramp, None);
+        let changes: Vec<usize> = (1..y.len()).filter(|&i| y[i] != y[i - 1]).collect();
+        assert_eq!(changes.len(), 10);
+        for (k, &i) in changes.iter().enumerate() {
+            assert!(i.abs_diff(480 * (k + 1) - 1) <= 1, "{k}: {i}");
+            assert_eq!(y[i], ramp[i]);
+        }
+    }
+
+    /// The external clock samples on rising edges, which need a fall below the low threshold
+    /// to re-arm; with no clock connected the value holds.
+    #[test]
+    fn external_clock_samples_on_edges() {
+        let mut s = sample_hold(&[(PARAM_MODE, EXTERNAL_CLOCK as f32)]);
+        let ramp: Vec<f32> = (0..512).map(|i| i as f32).collect();
+        let mut clock = vec![0.0; 512];
+        for (range, level) in [
+            (100..150, 0.6),
+            (150..200, 0.4),
+            (200..250, 0.6),
+            (300..350, 0.6),
+        ] {
+            clock[range].fill(level);
+        }
+        let y = render(&mut s, &ramp, Some(&clock));
+        assert!(y[..100].iter().all(|&v| v == 0.0));
+        assert!(y[100..300].iter().all(|&v| v == 100.0));
+        assert!(y[300..].iter().all(|&v| v == 300.0));
+
+        let y = render(&mut s, &ramp, None);
+        assert!(y.iter().all(|&v| v == 300.0));
+    }
+
+    /// The slew limiter follows rises and falls with their own time constants.
+    #[test]
+    fn slew_limits_rise_and_fall() {
+        let mut s = sample_hold(&[
+            (PARAM_MODE, THROUGH as f32),
+            (PARAM_RISE_MS, 10.0),
+            (PARAM_FALL_MS, 0.0),
+        ]);
+        let step: Vec<f32> = (0..1_024)
+            .map(|i| if i < 512 { 1.0 } else { 0.0 })
+            .collect();
+        let y = render(&mut s, &step, None);
+        // One time constant (480 samples) in: 1 - 1/e of the way up.
+        let expected = 1.0 - (-1.0_f32).exp();
+        assert!((y[479] - expected).abs() < 0.01, "{}", y[479]);
+        assert!(y[..512].windows(2).all(|w| w[1] > w[0]));
+        assert!(y[512..].iter().all(|&v| v == 0.0));
+    }
+}
@@ -0,0 +1,694 @@
+// `sampler_load` reads the sample through a pointer the worklet got from us; null is the only
+// invalid value we can detect.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use dsp_core::envelope::{Adsr, AdsrParams};
+use dsp_core::events::{EventRing, NoteEvent, ALL_NOTES_OFF, NOTE_OFF, NOTE_ON, PITCH_BEND};
+use dsp_core::gain::db_to_lin;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_VOICES: usize = 16;
+/// Sample channels kept (a stereo sample plays its channels on even/odd outputs).
+pub const MAX_SAMPLE_CHANNELS: usize = 2;
+
+// Indices into `Sampler::PARAMS`, in the order of the arguments of `sampler_set_region`,
+// `sampler_set_envelope` and `sampler_set_params`.
+pub const PARAM_START: u32 = 0;
+pub const PARAM_END: u32 = 1;
+pub const PARAM_LOOP_START: u32 = 2;
+pub const PARAM_LOOP_END: u32 = 3;
+pub const PARAM_LOOPING: u32 = 4;
+pub const PARAM_ATTACK_MS: u32 = 5;
+pub const PARAM_DECAY_MS: u32 = 6;
+pub const PARAM_SUSTAIN: u32 = 7;
+pub const PARAM_RELEASE_MS: u32 = 8;
+pub const PARAM_ROOT_NOTE: u32 = 9;
+pub const PARAM_TUNE: u32 = 10;
+pub const PARAM_ONE_SHOT: u32 = 11;
+pub const PARAM_LEVEL_DB: u32 = 12;
+
+/// Longest sample accepted, in seconds at its own rate (longer uploads are cut).
+const MAX_SAMPLE_SECONDS: f32 = 60.0;
+
+#[derive(Clone, Copy, Default)]
+struct Voice {
+    /// Read position in sample frames, and the advance per output sample.
+    pos: f64,
+    rate: f64,
+    note: u8,
+    velocity: f32,
+    gate: bool,
+    playing: bool,
+    /// Note-on order, for stealing the oldest voice.
+    age: u64,
+    env: Adsr,
+}
+
+/// Sampler: plays a sample uploaded through `wasm_alloc` (`sampler_load`) on up to 16
+/// overlapping voices, triggered through the same note-event ring as the synths
+/// (`sampler_events`). A note plays the sample transposed from `root_note`, resampled with
+/// 4-point Hermite interpolation, between the start and end points; with looping on it cycles
+/// between the loop points until its envelope has released. In one-shot mode note-offs are
+/// ignored and every trigger plays to the end.
+pub struct Sampler {
+    notes: EventRing,
+    voices: [Voice; MAX_VOICES],
+    data: Vec<f32>,
+    sample_channels: usize,
+    sample_frames: usize,
+    /// Sample rate of the loaded sample over the context's.
+    rate_ratio: f64,
+    /// Region and loop in sample frames (`loop_end > loop_start` when looping).
+    start: usize,
+    end: usize,
+    loop_start: usize,
+    loop_end: usize,
+    looping: bool,
+    /// The fractions they were set from, reapplied when a new sample is loaded.
+    region: [f32; 4],
+    attack_ms: f32,
+    decay_ms: f32,
+    sustain: f32,
+    release_ms: f32,
+    envelope: AdsrParams,
+    root_note: f32,
+    tune: f32,
+    bend: f32,
+    one_shot: bool,
+    level_db: f32,
+    gain: f32,
+    started: u64,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Sampler {
+    fn note_on(&mut self, note: u8, velocity: f32) {
+        if self.sample_frames == 0 {
+            return;
+        }
+        let index = (0..MAX_VOICES)
+            .find(|&i| !self.voices[i].playing)
+            .or_else(|| {
+                (0..MAX_VOICES)
+                    .filter(|&i| !self.voices[i].gate)
+                    .min_by(|&a, &b| {
+                        let level = |i: usize| self.voices[i].env.level();
+                        level(a).total_cmp(&level(b))
+                    })
+            })
+            .or_else(|| (0..MAX_VOICES).min_by_key(|&i| self.voices[i].age))
+            .unwrap_or(0);
+        self.started += 1;
+        let v = &mut self.voices[index];
+        v.pos = self.start as f64;
+        v.note = note;
+        v.velocity = velocity;
+        v.gate = true;
+        v.playing = true;
+        v.age = self.started;
+        // A fresh voice attacks from silence even if it was stolen mid-note.
+        v.env = Adsr::new();
+        v.env.gate_on();
+        self.update_rate(index);
+    }
+
+    fn note_off(&mut self, note: u8) {
+        if self.one_shot {
+            return;
+        }
+        for v in &mut self.voices {
+            if v.gate && v.note == note {
+                v.gate = false;
+                v.env.gate_off();
+            }
+        }
+    }
+
+    fn handle(&mut self, e: NoteEvent) {
+        let note = e.note.min(127) as u8;
+        match e.kind {
+            NOTE_ON if e.value > 0.0 => self.note_on(note, clamp(e.value, 0.0, 1.0)),
+            // Velocity 0 is a note-off, as in MIDI.
+            NOTE_ON | NOTE_OFF => self.note_off(note),
+            ALL_NOTES_OFF => {
+                for v in &mut self.voices {
+                    v.gate = false;
+                    v.env.gate_off();
+                }
+            }
+            PITCH_BEND => {
+                self.bend = clamp(e.value, -48.0, 48.0);
+                self.update_rates();
+            }
+            _ => {}
+        }
+    }
+
+    fn update_envelope(&mut self) {
+        self.envelope = AdsrParams::new(
+            self.attack_ms,
+            self.decay_ms,
+            self.sustain,
+            self.release_ms,
+            self.sample_rate_hz,
+        );
+    }
+
+    fn update_rate(&mut self, index: usize) {
+        let v = &mut self.voices[index];
+        let semitones = v.note as f32 - self.root_note + self.tune + self.bend;
+        v.rate = self.rate_ratio * ((semitones / 12.0).exp2() as f64);
+    }
+
+    fn update_rates(&mut self) {
+        for index in 0..MAX_VOICES {
+            self.update_rate(index);
+        }
+    }
+
+    /// Converts the region fractions to frames of the loaded sample.
+    fn apply_region(&mut self) {
+        let n = self.sample_frames;
+        let at = |f: f32| ((f * n as f32) as usize).min(n);
+        let [start, end, loop_start, loop_end] = self.region;
+        self.start = at(start).min(n.saturating_sub(1));
+        self.end = at(end).max(self.start + 1).min(n);
+        self.loop_start = at(loop_start).clamp(self.start, self.end);
+        self.loop_end = at(loop_end).clamp(self.loop_start, self.end);
+    }
+
+    /// Sample `i` of channel `ch`, wrapped into the loop when the voice is looping and silent
+    /// outside the region.
+    #[inline]
+    fn tap(&self, i: isize, ch: usize, looping: bool) -> f32 {
+        let mut i = i;
+        if looping && i >= self.loop_end as isize {
+            i -= (self.loop_end - self.loop_start) as isize;
+        }
+        if i < self.start as isize || i >= self.end as isize {
+            return 0.0;
+        }
+        self.data[i as usize * self.sample_channels + ch]
+    }
+
+    /// Takes `frames` frames of `channels` interleaved samples recorded at `sample_rate_hz`
+    /// (channels past the second are dropped) and stops the playing voices.
+    fn load(&mut self, input: &[f32], channels: usize, sample_rate_hz: f32) {
+        let kept = channels.min(MAX_SAMPLE_CHANNELS);
+        self.data = input
+            .chunks_exact(channels)
+            .flat_map(|frame| frame[..kept].iter())
+            .map(|&v| if v.is_finite() { v } else { 0.0 })
+            .collect();
+        self.sample_channels = kept;
+        self.sample_frames = input.len() / channels;
+        self.rate_ratio = (sample_rate_hz / self.sample_rate_hz) as f64;
+        self.apply_region();
+        for v in &mut self.voices {
+            v.playing = false;
+            v.gate = false;
+        }
+    }
+
+    #[inline]
+    fn read(&self, pos: f64, ch: usize, looping: bool) -> f32 {
+        let i = pos.floor() as isize;
+        let t = (pos - i as f64) as f32;
+        let ym1 = self.tap(i - 1, ch, looping);
+        let y0 = self.tap(i, ch, looping);
+        let y1 = self.tap(i + 1, ch, looping);
+        let y2 = self.tap(i + 2, ch, looping);
+        let c1 = 0.5 * (y1 - ym1);
+        let c2 = ym1 - 2.5 * y0 + 2.0 * y1 - 0.5 * y2;
+        let c3 = 0.5 * (y2 - ym1) + 1.5 * (y0 - y1);
+        ((c3 * t + c2) * t + c1) * t + y0
+    }
+
+    fn render(&mut self, output: &mut [f32], channels: usize) {
+        output.fill(0.0);
+        let looping = self.looping && self.loop_end > self.loop_start;
+        let loop_len = (self.loop_end - self.loop_start) as f64;
+        for out in output.chunks_exact_mut(channels) {
+            for index in 0..MAX_VOICES {
+                if !self.voices[index].playing {
+                    continue;
+                }
+                let mut v = self.voices[index];
+                let level = v.env.process(&self.envelope) * v.velocity * self.gain;
+                let mut frame = [0.0; MAX_SAMPLE_CHANNELS];
+                for (ch, s) in frame.iter_mut().enumerate().take(self.sample_channels) {
+                    *s = self.read(v.pos, ch, looping) * level;
+                }
+                for (ch, y) in out.iter_mut().enumerate() {
+                    *y += frame[ch % self.sample_channels];
+                }
+                v.pos += v.rate;
+                if looping {
+                    while v.pos >= self.loop_end as f64 {
+                        v.pos -= loop_len;
+                    }
+                }
+                if v.env.is_idle() || v.pos >= self.end as f64 {
+                    v.playing = false;
+                }
+                self.voices[index] = v;
+            }
+        }
+    }
+}
+
+impl Node for Sampler {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Start`, `End` and the loop points are fractions of the sample; with `Loop` on a held
+    /// voice cycles between the loop points (kept inside the region) until it has released.
+    /// The envelope's `Decay` and `Release` run to -60 dB. `Root note` plays the sample at its
+    /// own pitch; `Tune` transposes everything; `One-shot` ignores note-offs.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Start", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("End", "", 0.0, 1.0, 1.0),
+        ParamDescriptor::linear("Loop start", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Loop end", "", 0.0, 1.0, 1.0),
+        ParamDescriptor::toggle("Loop", false),
+        ParamDescriptor::linear("Attack", "ms", 0.0, 10_000.0, 1.0),
+        ParamDescriptor::log("Decay", "ms", 1.0, 20_000.0, 1000.0),
+        ParamDescriptor::linear("Sustain", "", 0.0, 1.0, 1.0),
+        ParamDescriptor::log("Release", "ms", 1.0, 20_000.0, 200.0),
+        ParamDescriptor::stepped("Root note", "", 0.0, 127.0, 60.0),
+        ParamDescriptor::linear("Tune", "st", -48.0, 48.0, 0.0),
+        ParamDescriptor::toggle("One-shot", false),
+        ParamDescriptor::linear("Level", "dB", -60.0, 12.0, 0.0),
+    ];
+    const INPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        Sampler {
+            notes: EventRing::new(),
+            voices: [Voice::default(); MAX_VOICES],
+            data: Vec::new(),
+            sample_channels: 1,
+            sample_frames: 0,
+            rate_ratio: 1.0,
+            start: 0,
+            end: 0,
+            loop_start: 0,
+            loop_end: 0,
+            looping: false,
+            region: [0.0, 1.0, 0.0, 1.0],
+            attack_ms: 1.0,
+            decay_ms: 1000.0,
+            sustain: 1.0,
+            release_ms: 200.0,
+            envelope: AdsrParams::new(1.0, 1000.0, 1.0, 200.0, sample_rate_hz),
+            root_note: 60.0,
+            tune: 0.0,
+            bend: 0.0,
+            one_shot: false,
+            level_db: 0.0,
+            gain: 1.0,
+            started: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_START..=PARAM_LOOP_END => {
+                self.region[index as usize] = clamp(value, 0.0, 1.0);
+                self.apply_region();
+            }
+            PARAM_LOOPING => self.looping = clamp(value, 0.0, 1.0) >= 0.5,
+            PARAM_ATTACK_MS => {
+                self.attack_ms = clamp(value, 0.0, 10_000.0);
+                self.update_envelope();
+            }
+            PARAM_DECAY_MS => {
+                self.decay_ms = clamp(value, 1.0, 20_000.0);
+                self.update_envelope();
+            }
+            PARAM_SUSTAIN => {
+                self.sustain = clamp(value, 0.0, 1.0);
+                self.update_envelope();
+            }
+            PARAM_RELEASE_MS => {
+                self.release_ms = clamp(value, 1.0, 20_000.0);
+                self.update_envelope();
+            }
+            PARAM_ROOT_NOTE => {
+                self.root_note = clamp(value, 0.0, 127.0);
+                self.update_rates();
+            }
+            PARAM_TUNE => {
+                self.tune = clamp(value, -48.0, 48.0);
+                self.update_rates();
+            }
+            PARAM_ONE_SHOT => self.one_shot = clamp(value, 0.0, 1.0) >= 0.5,
+            PARAM_LEVEL_DB => {
+                self.level_db = clamp(value, -60.0, 12.0);
+                self.gain = db_to_lin(self.level_db);
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_START..=PARAM_LOOP_END => self.region[index as usize],
+            PARAM_LOOPING => u32::from(self.looping) as f32,
+            PARAM_ATTACK_MS => self.attack_ms,
+            PARAM_DECAY_MS => self.decay_ms,
+            PARAM_SUSTAIN => self.sustain,
+            PARAM_RELEASE_MS => self.release_ms,
+            PARAM_ROOT_NOTE => self.root_note,
+            PARAM_TUNE => self.tune,
+            PARAM_ONE_SHOT => u32::from(self.one_shot) as f32,
+            PARAM_LEVEL_DB => self.level_db,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn note_events(&mut self) -> Option<&mut EventRing> {
+        Some(&mut self.notes)
+    }
+
+    fn note(&mut self, event: NoteEvent) {
+        self.handle(event);
+    }
+
+    /// A mono sample plays on every channel, a stereo one on even (left) and odd (right)
+    /// channels.
+    fn process(&mut self, _input: &[f32], output: &mut [f32], channels: usize) {
+        self.render(output, channels);
+    }
+
+    /// Stops every voice at once (no release tails); the loaded sample and queued events are
+    /// kept.
+    fn reset(&mut self) {
+        self.voices = [Voice::default(); MAX_VOICES];
+        self.started = 0;
+    }
+}
+
+node_abi::export_node!(Sampler {
+    abi_version: sampler_abi_version,
+    new: sampler_new,
+    free: sampler_free,
+    param_count: sampler_param_count,
+    param_descriptors: sampler_param_descriptors,
+    set_param: sampler_set_param,
+    get_param: sampler_get_param,
+    param_events: sampler_param_events,
+    process: sampler_process,
+    process_offline: sampler_process_offline,
+    latency_samples: sampler_latency_samples,
+    io_layout: sampler_get_io_layout,
+    process_io: sampler_process_io,
+    reset: sampler_reset,
+    state_size: sampler_state_size,
+    save_state: sampler_save_state,
+    load_state: sampler_load_state,
+    preset_size: sampler_preset_size,
+    save_preset: sampler_save_preset,
+    load_preset: sampler_load_preset,
+    last_error_code: sampler_last_error_code,
+    last_error_message: sampler_last_error_message_ptr,
+});
+
+node_abi::test_params!(Sampler);
+
+dsp_core::test_block_sizes!(
+    sampler_new,
+    sampler_process(input, output),
+    sampler_free,
+    setup: |p| {
+        let sample = dsp_core::blocksize::test_signal(4_800, 2, 2);
+        sampler_load(p, sample.as_ptr(), 4_800, 2, 44_100.0);
+        let events = unsafe { &mut *sampler_events(p) };
+        events.push(NoteEvent { kind: NOTE_ON, note: 60, value: 1.0, frame: 0 });
+    },
+);
+
+node_abi::test_io_block!(
+    Sampler,
+    setup: |s: &mut Sampler| {
+        s.load(&dsp_core::blocksize::test_signal(4_800, 2, 2), 2, 44_100.0);
+        s.notes.push(NoteEvent { kind: NOTE_ON, note: 60, value: 1.0, frame: 0 });
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Sampler as SamplerNode);
+
+/// The note-event ring inside the sampler, for the worklet to write into (layout in
+/// `dsp_core::events`). Stays put for the sampler's lifetime.
+#[no_mangle]
+pub extern "C" fn sampler_events(handle: Handle) -> *mut EventRing {
+    guard(handle, |s: &mut Sampler| &mut s.notes as *mut EventRing).unwrap_or(core::ptr::null_mut())
+}
+
+/// Loads a sample the worklet copied into a `wasm_alloc`ed buffer: `frames` frames of
+/// `channels` interleaved samples (channels past the second are dropped) recorded at
+/// `sample_rate_hz`, up to 60 s. Playing voices stop. The buffer can be freed as soon as this
+/// returns. Allocates, so call it from the message handler rather than mid-render.
+#[no_mangle]
+pub extern "C" fn sampler_load(
+    handle: Handle,
+    data_ptr: *const f32,
+    frames: usize,
+    channels: usize,
+    sample_rate_hz: f32,
+) {
+    if data_ptr.is_null() || channels == 0 {
+        return;
+    }
+    let rate = clamp(sample_rate_hz, 1000.0, 384_000.0);
+    let frames = frames.min((MAX_SAMPLE_SECONDS * rate) as usize);
+    let input = unsafe { core::slice::from_raw_parts(data_ptr, frames.saturating_mul(channels)) };
+    guard(handle, |s: &mut Sampler| s.load(input, channels, rate));
+}
+
+/// `sampler_load` for the bindgen class: `data` holds whole frames of `channels` interleaved
+/// samples.
+#[cfg(feature = "bindgen")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+impl SamplerNode {
+    pub fn load(&mut self, data: &[f32], channels: usize, sample_rate_hz: f32) {
+        if channels == 0 {
+            return;
+        }
+        let rate = clamp(sample_rate_hz, 1000.0, 384_000.0);
+        let frames = (data.len() / channels).min((MAX_SAMPLE_SECONDS * rate) as usize);
+        self.node.load(&data[..frames * channels], channels, rate);
+    }
+}
+
+/// `Start`, `End`, `Loop start`, `Loop end` and `Loop` in one call.
+#[no_mangle]
+pub extern "C" fn sampler_set_region(
+    handle: Handle,
+    start: f32,
+    end: f32,
+    loop_start: f32,
+    loop_end: f32,
+    looping: u32,
+) {
+    guard(handle, |s: &mut Sampler| {
+        s.set_param(PARAM_START, start);
+        s.set_param(PARAM_END, end);
+        s.set_param(PARAM_LOOP_START, loop_start);
+        s.set_param(PARAM_LOOP_END, loop_end);
+        s.set_param(PARAM_LOOPING, looping as f32);
+    });
+}
+
+/// The amplitude envelope in one call.
+#[no_mangle]
+pub extern "C" fn sampler_set_envelope(
+    handle: Handle,
+    attack_ms: f32,
+    decay_ms: f32,
+    sustain: f32,
+    release_ms: f32,
+) {
+    guard(handle, |s: &mut Sampler| {
+        s.set_param(PARAM_ATTACK_MS, attack_ms);
+        s.set_param(PARAM_DECAY_MS, decay_ms);
+        s.set_param(PARAM_SUSTAIN, sustain);
+        s.set_param(PARAM_RELEASE_MS, release_ms);
+    });
+}
+
+/// `Root note`, `Tune`, `One-shot` and `Level` in one call.
+#[no_mangle]
+pub extern "C" fn sampler_set_params(
+    handle: Handle,
+    root_note: f32,
+    tune_semitones: f32,
+    one_shot: u32,
+    level_db: f32,
+) {
+    guard(handle, |s: &mut Sampler| {
+        s.set_param(PARAM_ROOT_NOTE, root_note);
+        s.set_param(PARAM_TUNE, tune_semitones);
+        s.set_param(PARAM_ONE_SHOT, one_shot as f32);
+        s.set_param(PARAM_LEVEL_DB, level_db);
+    });
+}
+
+/// Voices currently playing, for the UI.
+#[no_mangle]
+pub extern "C" fn sampler_active_voices(handle: Handle) -> u32 {
+    guard(handle, |s: &mut Sampler| {
+        s.voices.iter().filter(|v| v.playing).count() as u32
+    })
+    .unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// A sampler holding one second of a 0..1 ramp at `sample_rate_hz`, with an instant attack
+    /// and a 10 ms release.
+    fn sampler(sample_rate_hz: f32) -> Handle {
+        let h = sampler_new(RATE);
+        let n = sample_rate_hz as usize;
+        let ramp: Vec<f32> = (0..n).map(|i| i as f32 / n as f32).collect();
+        sampler_load(h, ramp.as_ptr(), n, 1, sample_rate_hz);
+        sampler_set_envelope(h, 0.0, 1000.0, 1.0, 10.0);
+        h
+    }
+
+    fn send(h: Handle, kind: u32, note: u32) {
+        let events = unsafe { &mut *sampler_events(h) };
+        assert!(events.push(NoteEvent {
+            kind,
+            note,
+            value: 1.0,
+            frame: 0,
+        }));
+    }
+
+    fn render(h: Handle, frames: usize) -> Vec<f32> {
+        render_quanta(frames, |_, y| {
+            sampler_process(h, core::ptr::null(), y.as_mut_ptr(), y.len(), 1)
+        })
+    }
+
+    /// The root note plays the ramp at its own speed, an octave up at twice it, and a sample
+    /// recorded at half the rate is stretched to its own duration.
+    #[test]
+    fn pitch_sets_the_playback_rate() {
+        for (rate, note, speed) in [(RATE, 60, 1.0), (RATE, 72, 2.0), (RATE / 2.0, 60, 1.0)] {
+            let h = sampler(rate);
+            send(h, NOTE_ON, note);
+            let y = render(h, 1_280);
+            for (i, &v) in y.iter().enumerate().skip(4) {
+                let expected = i as f32 * speed / RATE;
+                assert!((v - expected).abs() < 1e-4, "{rate} {note} at {i}: {v}");
+            }
+            sampler_free(h);
+        }
+    }
+
+    /// Between the start and end points the voice plays that stretch of the sample once,
+    /// then stops.
+    #[test]
+    fn region_bounds_playback() {
+        let h = sampler(RATE);
+        sampler_set_region(h, 0.25, 0.5, 0.0, 1.0, 0);
+        send(h, NOTE_ON, 60);
+        let y = render(h, 12_800);
+        assert!((y[100] - (0.25 + 100.0 / RATE)).abs() < 1e-4, "{}", y[100]);
+        assert!((y[11_990] - (0.25 + 11_990.0 / RATE)).abs() < 1e-4);
+        assert!(y[12_000..].iter().all(|&v| v == 0.0));
+        assert_eq!(sampler_active_voices(h), 0);
+        sampler_free(h);
+    }
+
+    /// A looping voice cycles between the loop points while held and fades out on release.
+    #[test]
+    fn loop_cycles_until_released() {
+        let h = sampler(RATE);
+        sampler_set_region(h, 0.0, 1.0, 0.5, 0.75, 1);
+        send(h, NOTE_ON, 60);
+        let y = render(h, 96_000);
+        // Into the loop at 0.5 s, back to its start every 0.25 s after.
+        assert!((y[30_000] - 0.625).abs() < 1e-4, "{}", y[30_000]);
+        assert!((y[42_000] - 0.625).abs() < 1e-4, "{}", y[42_000]);
+        assert!((y[95_000] - 0.729_166_7).abs() < 1e-4, "{}", y[95_000]);
+        assert_eq!(sampler_active_voices(h), 1);
+
+        send(h, NOTE_OFF, 60);
+        render(h, 4_800);
+        assert_eq!(sampler_active_voices(h), 0);
+        sampler_free(h);
+    }
+
+    /// In one-shot mode a note-off is ignored and the voice plays to the end.
+    #[test]
+    fn one_shot_ignores_note_off() {
+        let h = sampler(RATE);
+        sampler_set_params(h, 60.0, 0.0, 1, 0.0);
+        send(h, NOTE_ON, 60);
+        render(h, 1_280);
+        send(h, NOTE_OFF, 60);
+        let y = render(h, 38_400);
+        assert!(
+            (y[38_000] - (39_280.0 / RATE)).abs() < 1e-4,
+            "{}",
+            y[38_000]
+        );
+        render(h, 9_600);
+        assert_eq!(sampler_active_voices(h), 0);
+        sampler_free(h);
+    }
+
+    /// Up to sixteen triggers overlap and sum; a seventeenth takes over the oldest voice.
+    #[test]
+    fn voices_overlap() {
+        let h = sampler(RATE);
+        for _ in 0..MAX_VOICES {
+            send(h, NOTE_ON, 60);
+            render(h, 128);
+        }
+        assert_eq!(sampler_active_voices(h), 16);
+        // Voices started 128 frames apart: the sum of their positions.
+        let y = render(h, 128)[0];
+        let expected = (1..=16).map(|k| (k * 128) as f32 / RATE).sum::<f32>();
+        assert!((y - expected).abs() < 1e-3, "{y} {expected}");
+
+        send(h, NOTE_ON, 60);
+        let y = render(h, 128)[4];
+        // The oldest voice starts over; the other fifteen have moved on a quantum.
+        let expected = ((2..=16).map(|k| k * 128 + 4).sum::<usize>() + 4) as f32 / RATE;
+        assert_eq!(sampler_active_voices(h), 16);
+        assert!((y - expected).abs() < 1e-3, "{y} {expected}");
+        sampler_free(h);
+    }
+}
@@ -0,0 +1,444 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::events::{EventRing, NoteEvent, NOTE_OFF, NOTE_ON};
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_STEPS: usize = 64;
+
+// Indices into `Sequencer::PARAMS`, in the order of `sequencer_set_transport`'s arguments.
+pub const PARAM_BPM: u32 = 0;
+pub const PARAM_STEPS_PER_BEAT: u32 = 1;
+pub const PARAM_PLAYING: u32 = 2;
+
+/// One step as laid out in shared memory. `gate` is the fraction of the step the note is held
+/// for: 0 is a rest, 1 ties into the next step (which doesn't retrigger if it plays the same
+/// note).
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Step {
+    pub note: u32,
+    pub velocity: f32,
+    pub gate: f32,
+}
+
+/// The pattern, written by the worklet straight into the node (see `sequencer_pattern`).
+/// Steps past `length` (1–64) are ignored.
+#[repr(C)]
+pub struct Pattern {
+    pub length: u32,
+    pub steps: [Step; MAX_STEPS],
+}
+
+/// Step sequencer. Plays its pattern at `steps_per_beat` steps to the beat of the transport
+/// tempo, to the sample, as note events into an outgoing ring (for the worklet to pass on to
+/// the synths) and as a pitch / gate / velocity control signal (pitch in V/oct, 0 V at C0).
+/// The pattern is read as each step starts, so edits are heard from the next step on.
+pub struct Sequencer {
+    pattern: Pattern,
+    out_events: EventRing,
+    playing: bool,
+    bpm: f32,
+    steps_per_beat: u32,
+    /// Step playing now and the one to play next.
+    step: usize,
+    next_step: usize,
+    /// Samples until the next step starts and until the sounding note's gate closes.
+    until_step: f64,
+    gate_left: f64,
+    sounding: Option<u32>,
+    tied: bool,
+    /// The last note played, whose pitch and velocity stay on the outputs through its release.
+    last_note: u32,
+    velocity: f32,
+    /// Frames of the block already rendered, so outgoing events are stamped from its start.
+    block_frame: usize,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Sequencer {
+    fn samples_per_step(&self) -> f64 {
+        self.sample_rate_hz as f64 * 60.0 / (self.bpm as f64 * self.steps_per_beat as f64)
+    }
+
+    /// Queues an outgoing event; dropped if the worklet has let the ring fill up.
+    fn emit(&mut self, kind: u32, note: u32, value: f32, frame: usize) {
+        self.out_events.push(NoteEvent {
+            kind,
+            note,
+            value,
+            frame: (self.block_frame + frame) as u32,
+        });
+    }
+
+    fn release(&mut self, frame: usize) {
+        if let Some(note) = self.sounding.take() {
+            self.emit(NOTE_OFF, note, 0.0, frame);
+        }
+        self.tied = false;
+    }
+
+    fn start_step(&mut self, frame: usize) {
+        let length = (self.pattern.length as usize).clamp(1, MAX_STEPS);
+        self.step = self.next_step % length;
+        self.next_step = self.step + 1;
+        let s = self.pattern.steps[self.step];
+        let gate = clamp(s.gate, 0.0, 1.0);
+        if gate <= 0.0 {
+            self.release(frame);
+            return;
+        }
+        let note = s.note.min(127);
+        if !(self.tied && self.sounding == Some(note)) {
+            self.release(frame);
+            self.velocity = clamp(s.velocity, 0.0, 1.0);
+            self.emit(NOTE_ON, note, self.velocity, frame);
+            self.sounding = Some(note);
+            self.last_note = note;
+        }
+        self.tied = gate >= 1.0;
+        self.gate_left = gate as f64 * self.samples_per_step();
+    }
+
+    fn render(&mut self, output: &mut [f32], channels: usize) {
+        for (i, out) in output.chunks_exact_mut(channels).enumerate() {
+            if self.playing {
+                self.until_step -= 1.0;
+                if self.until_step <= 0.0 {
+                    self.start_step(i);
+                    self.until_step += self.samples_per_step();
+                }
+                if self.sounding.is_some() && !self.tied {
+                    self.gate_left -= 1.0;
+                    if self.gate_left < 0.0 {
+                        self.release(i);
+                    }
+                }
+            }
+
+            let gate = if self.sounding.is_some() { 1.0 } else { 0.0 };
+            let cv = [self.last_note as f32 / 12.0, gate, self.velocity];
+            for (ch, y) in out.iter_mut().enumerate() {
+                *y = cv.get(ch).copied().unwrap_or(0.0);
+            }
+        }
+        self.block_frame += output.len() / channels;
+    }
+}
+
+impl Node for Sequencer {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Steps per beat` 4 plays sixteenths; `Playing` runs the pattern: starting plays step 1
+    /// at the next frame, stopping releases the note. A tempo change takes effect from the
+    /// next step.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Tempo", "bpm", 20.0, 300.0, 120.0),
+        ParamDescriptor::stepped("Steps per beat", "", 1.0, 8.0, 4.0),
+        ParamDescriptor::toggle("Playing", false),
+    ];
+    const INPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        Sequencer {
+            pattern: Pattern {
+                length: 16,
+                steps: [Step {
+                    note: 60,
+                    velocity: 0.8,
+                    gate: 0.0,
+                }; MAX_STEPS],
+            },
+            out_events: EventRing::new(),
+            playing: false,
+            bpm: 120.0,
+            steps_per_beat: 4,
+            step: 0,
+            next_step: 0,
+            until_step: 1.0,
+            gate_left: 0.0,
+            sounding: None,
+            tied: false,
+            last_note: 60,
+            velocity: 0.0,
+            block_frame: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_BPM => self.bpm = clamp(value, 20.0, 300.0),
+            PARAM_STEPS_PER_BEAT => self.steps_per_beat = clamp(value, 1.0, 8.0).round() as u32,
+            PARAM_PLAYING => {
+                let playing = clamp(value, 0.0, 1.0) >= 0.5;
+                if playing && !self.playing {
+                    self.next_step = 0;
+                    self.until_step = 1.0;
+                } else if !playing && self.playing {
+                    self.release(0);
+                }
+                self.playing = playing;
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_BPM => self.bpm,
+            PARAM_STEPS_PER_BEAT => self.steps_per_beat as f32,
+            PARAM_PLAYING => u32::from(self.playing) as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn start_block(&mut self) {
+        self.block_frame = 0;
+    }
+
+    /// Renders pitch on channel 0, gate (0 or 1) on 1 and the last note's velocity on 2; any
+    /// further channels are silent.
+    fn process(&mut self, _input: &[f32], output: &mut [f32], channels: usize) {
+        self.render(output, channels);
+    }
+
+    /// Rewinds to the first step and ends any sounding note (sending its note-off); the
+    /// pattern and the transport state are kept.
+    fn reset(&mut self) {
+        self.release(0);
+        self.step = 0;
+        self.next_step = 0;
+        self.until_step = 1.0;
+        self.gate_left = 0.0;
+        self.velocity = 0.0;
+    }
+}
+
+node_abi::export_node!(Sequencer {
+    abi_version: sequencer_abi_version,
+    new: sequencer_new,
+    free: sequencer_free,
+    param_count: sequencer_param_count,
+    param_descriptors: sequencer_param_descriptors,
+    set_param: sequencer_set_param,
+    get_param: sequencer_get_param,
+    param_events: sequencer_param_events,
+    process: sequencer_process,
+    process_offline: sequencer_process_offline,
+    latency_samples: sequencer_latency_samples,
+    io_layout: sequencer_get_io_layout,
+    process_io: sequencer_process_io,
+    reset: sequencer_reset,
+    state_size: sequencer_state_size,
+    save_state: sequencer_save_state,
+    load_state: sequencer_load_state,
+    preset_size: sequencer_preset_size,
+    save_preset: sequencer_save_preset,
+    load_preset: sequencer_load_preset,
+    last_error_code: sequencer_last_error_code,
+    last_error_message: sequencer_last_error_message_ptr,
+});
+
+node_abi::test_params!(Sequencer);
+
+dsp_core::test_block_sizes!(
+    sequencer_new,
+    sequencer_process(input, output),
+    sequencer_free,
+    setup: |p| {
+        let pattern = unsafe { &mut *sequencer_pattern(p) };
+        for (i, step) in pattern.steps.iter_mut().enumerate() {
+            step.note = 48 + i as u32 % 12;
+            step.gate = [0.5, 1.0, 0.0, 0.25][i % 4];
+        }
+        sequencer_set_transport(p, 300.0, 8, 1);
+    },
+);
+
+node_abi::test_io_block!(
+    Sequencer,
+    setup: |s: &mut Sequencer| {
+        for (i, step) in s.pattern.steps.iter_mut().enumerate() {
+            step.note = 48 + i as u32 % 12;
+            step.gate = [0.5, 1.0, 0.0, 0.25][i % 4];
+        }
+        s.set_param(PARAM_BPM, 300.0);
+        s.set_param(PARAM_STEPS_PER_BEAT, 8.0);
+        s.set_param(PARAM_PLAYING, 1.0);
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Sequencer as SequencerNode);
+
+/// The `Pattern` inside the node, for the worklet to write steps straight into (`length` at
+/// byte 0, then 12-byte steps: `note`, `velocity` as f32, `gate` as f32). Stays put for the
+/// node's lifetime.
+#[no_mangle]
+pub extern "C" fn sequencer_pattern(handle: Handle) -> *mut Pattern {
+    guard(handle, |s: &mut Sequencer| &mut s.pattern as *mut Pattern)
+        .unwrap_or(core::ptr::null_mut())
+}
+
+/// The ring the sequencer writes its note events into, for the worklet to read (layout in
+/// `dsp_core::events`; the worklet advances the read count). Frames are offsets into the
+/// block just rendered. Stays put for the node's lifetime.
+#[no_mangle]
+pub extern "C" fn sequencer_out_events(handle: Handle) -> *mut EventRing {
+    guard(handle, |s: &mut Sequencer| {
+        &mut s.out_events as *mut EventRing
+    })
+    .unwrap_or(core::ptr::null_mut())
+}
+
+/// Every parameter in one call, as `sequencer_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn sequencer_set_transport(
+    handle: Handle,
+    bpm: f32,
+    steps_per_beat: u32,
+    playing: u32,
+) {
+    guard(handle, |s: &mut Sequencer| {
+        s.set_param(PARAM_BPM, bpm);
+        s.set_param(PARAM_STEPS_PER_BEAT, steps_per_beat as f32);
+        s.set_param(PARAM_PLAYING, playing as f32);
+    });
+}
+
+/// Step playing now (0-based), for the UI.
+#[no_mangle]
+pub extern "C" fn sequencer_current_step(handle: Handle) -> u32 {
+    guard(handle, |s: &mut Sequencer| s.step as u32).unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::events::ParamEvent;
+
+    const RATE: f32 = 48_000.0;
+    /// Sixteenths at 120 bpm.
+    const STEP: usize = 6_000;
+
+    /// A sequencer playing `steps` (note, gate) at full velocity as its pattern.
+    fn sequencer(steps: &[(u32, f32)]) -> Handle {
+        let h = sequencer_new(RATE);
+        let pattern = unsafe { &mut *sequencer_pattern(h) };
+        pattern.length = steps.len() as u32;
+        for (step, &(note, gate)) in pattern.steps.iter_mut().zip(steps) {
+            *step = Step {
+                note,
+                velocity: 1.0,
+                gate,
+            };
+        }
+        sequencer_set_transport(h, 120.0, 4, 1);
+        h
+    }
+
+    /// Renders `frames` on `channels` channels in render quanta, returning the output and the
+    /// events sent as (kind, note, frame from the start).
+    fn render(h: Handle, frames: usize, channels: usize) -> (Vec<f32>, Vec<(u32, u32, usize)>) {
+        let mut output = vec![0.0; frames * channels];
+        let mut events = Vec::new();
+        for (block, y) in output.chunks_mut(128 * channels).enumerate() {
+            sequencer_process(
+                h,
+                core::ptr::null(),
+                y.as_mut_ptr(),
+                y.len() / channels,
+                channels,
+            );
+            let ring = unsafe { &mut *sequencer_out_events(h) };
+            while let Some(e) = ring.pop() {
+                events.push((e.kind, e.note, block * 128 + e.frame as usize));
+            }
+        }
+        (output, events)
+    }
+
+    /// Steps start on time, each note held for its gate; rests send nothing and a tie holds
+    /// into the next step.
+    #[test]
+    fn steps_play_on_time() {
+        let h = sequencer(&[(60, 0.5), (62, 0.25), (0, 0.0), (65, 1.0)]);
+        let (_, events) = render(h, 4 * STEP + 128, 1);
+        sequencer_free(h);
+        assert_eq!(
+            events,
+            [
+                (NOTE_ON, 60, 0),
+                (NOTE_OFF, 60, STEP / 2),
+                (NOTE_ON, 62, STEP),
+                (NOTE_OFF, 62, STEP + STEP / 4),
+                (NOTE_ON, 65, 3 * STEP),
+                (NOTE_OFF, 65, 4 * STEP),
+                (NOTE_ON, 60, 4 * STEP),
+            ]
+        );
+    }
+
+    /// A tie into the same note carries on without retriggering.
+    #[test]
+    fn tie_into_the_same_note_holds() {
+        let h = sequencer(&[(60, 1.0), (60, 0.5)]);
+        let (_, events) = render(h, 2 * STEP, 1);
+        sequencer_free(h);
+        assert_eq!(events, [(NOTE_ON, 60, 0), (NOTE_OFF, 60, STEP + STEP / 2)]);
+    }
+
+    /// The control outputs carry pitch (1 V/oct from C0), gate and velocity.
+    #[test]
+    fn cv_outputs_follow_the_steps() {
+        let h = sequencer(&[(60, 0.5), (67, 0.5)]);
+        let (y, _) = render(h, 2 * STEP, 3);
+        sequencer_free(h);
+        let frame = |i: usize| [y[i * 3], y[i * 3 + 1], y[i * 3 + 2]];
+        assert_eq!(frame(100), [5.0, 1.0, 1.0]);
+        assert_eq!(frame(STEP - 100), [5.0, 0.0, 1.0]);
+        assert_eq!(frame(STEP + 100), [67.0 / 12.0, 1.0, 1.0]);
+    }
+
+    /// Stopping the transport releases the note on the frame it lands; starting again plays
+    /// from the first step.
+    #[test]
+    fn transport_stops_and_restarts() {
+        let transport = |h: Handle, playing: f32, frame: u32| {
+            let ring = unsafe { &mut *sequencer_param_events(h) };
+            assert!(ring.push(ParamEvent {
+                index: PARAM_PLAYING,
+                value: playing,
+                frame,
+            }));
+        };
+        let h = sequencer(&[(60, 1.0), (62, 1.0), (64, 1.0)]);
+        render(h, STEP + 128, 1);
+        assert_eq!(sequencer_current_step(h), 1);
+        transport(h, 0.0, 50);
+        let (_, events) = render(h, 128, 1);
+        assert_eq!(events, [(NOTE_OFF, 62, 50)]);
+        transport(h, 1.0, 20);
+        let (_, events) = render(h, 128, 1);
+        assert_eq!(events, [(NOTE_ON, 60, 20)]);
+        sequencer_free(h);
+    }
+}
@@ -0,0 +1,356 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use core::f32::consts::PI;
+use dsp_core::fft::Complex;
+use dsp_core::param::clamp;
+use dsp_core::stft::Stft;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+/// Every channel holds its own frozen frame, so this node stops at stereo.
+pub const MAX_CHANNELS: usize = 2;
+
+// Indices into `SpectralFreeze::PARAMS`, in the order of `freeze_set_params`' arguments.
+pub const PARAM_FREEZE: u32 = 0;
+pub const PARAM_FADE_MS: u32 = 1;
+pub const PARAM_BLEND: u32 = 2;
+pub const PARAM_SCATTER: u32 = 3;
+
+/// Frame length in seconds (rounded up to a power of two in samples).
+const FRAME_SECONDS: f32 = 0.04;
+const OVERLAP: usize = 4;
+
+fn wrap(p: f32) -> f32 {
+    p - 2.0 * PI * (p / (2.0 * PI)).round()
+}
+
+/// One channel: its STFT, the phases of the previous live frame (to measure each bin's
+/// frequency at capture), and the frozen frame.
+struct Channel {
+    stft: Stft,
+    prev_phase: Vec<f32>,
+    magnitude: Vec<f32>,
+    /// Phase advance per hop of each frozen bin, and its running phase.
+    advance: Vec<f32>,
+    phase: Vec<f32>,
+}
+
+/// Spectral freeze. On a rising `freeze` the next STFT frame is captured: each bin's magnitude
+/// and its measured frequency (as a phase advance per hop). While frozen the frame is
+/// resynthesised every hop, its phases advancing at those frequencies plus a random scatter,
+/// so it sustains as a steady drone rather than a buzzing repeated grain. The frozen sound
+/// fades in and out over `fade_ms` and is blended against the live input.
+pub struct SpectralFreeze {
+    channels: Vec<Channel>,
+    frozen: bool,
+    capture: bool,
+    /// Fade position 0–1 and its per-sample step.
+    fade: f32,
+    fade_ms: f32,
+    fade_step: f32,
+    blend: f32,
+    scatter: f32,
+    rng: u32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+/// Level lost to scattering, measured on a frozen sine. Any jitter soon decorrelates the bins
+/// of a partial from each other (about -3 dB), and heavier jitter also decorrelates
+/// overlapping frames (another -3 dB by full scatter).
+fn scatter_makeup(scatter: f32) -> f32 {
+    1.0 + 0.4 * (scatter / 0.1).min(1.0) + 0.6 * ((scatter - 0.2) / 0.8).max(0.0)
+}
+
+/// Uniform in [-1, 1) from a xorshift32.
+#[inline]
+fn random(rng: &mut u32) -> f32 {
+    *rng ^= *rng << 13;
+    *rng ^= *rng >> 17;
+    *rng ^= *rng << 5;
+    (*rng >> 7) as f32 / (1u32 << 24) as f32 - 1.0
+}
+
+impl Channel {
+    fn new(n: usize) -> Self {
+        let stft = Stft::new(n, OVERLAP);
+        let bins = stft.bins();
+        Self {
+            stft,
+            prev_phase: vec![0.0; bins],
+            magnitude: vec![0.0; bins],
+            advance: vec![0.0; bins],
+            phase: vec![0.0; bins],
+        }
+    }
+}
+
+impl SpectralFreeze {
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let target = if self.frozen { 1.0 } else { 0.0 };
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            self.fade = if self.fade < target {
+                (self.fade + self.fade_step).min(target)
+            } else {
+                (self.fade - self.fade_step).max(target)
+            };
+            let wet = self.fade * self.blend;
+            let makeup = scatter_makeup(self.scatter);
+            let capture = self.capture;
+            let mut framed = false;
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let c = &mut self.channels[ch];
+                let x = if x.is_finite() { *x } else { 0.0 };
+                let rng = &mut self.rng;
+                let scatter = self.scatter * PI;
+                let hop = c.stft.hop() as f32;
+                let n = c.stft.len() as f32;
+                *y = c.stft.process(x, |bins| {
+                    framed = true;
+                    for (k, bin) in bins.iter_mut().enumerate() {
+                        let phase = bin.im.atan2(bin.re);
+                        if capture {
+                            let omega = 2.0 * PI * k as f32 / n * hop;
+                            c.magnitude[k] = bin.norm();
+                            c.advance[k] = omega + wrap(phase - c.prev_phase[k] - omega);
+                            c.phase[k] = phase;
+                        } else {
+                            c.phase[k] = wrap(c.phase[k] + c.advance[k] + scatter * random(rng));
+                        }
+                        c.prev_phase[k] = phase;
+                        if wet > 0.0 {
+                            let (s, co) = c.phase[k].sin_cos();
+                            let m = c.magnitude[k] * wet * makeup;
+                            *bin = Complex::new(
+                                bin.re * (1.0 - wet) + m * co,
+                                bin.im * (1.0 - wet) + m * s,
+                            );
+                        }
+                    }
+                });
+            }
+            if framed {
+                self.capture = false;
+            }
+        }
+    }
+}
+
+impl Node for SpectralFreeze {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Freeze` holds the frame captured when it last went on; `Fade` is the crossfade in and
+    /// out; `Blend` is how much of the live input the frozen frame replaces once faded in (1
+    /// is frozen only, 0.5 layers them); `Scatter` is the random phase jitter per hop (0 keeps
+    /// the captured frequencies exact, 1 fully randomises).
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::toggle("Freeze", false),
+        ParamDescriptor::log("Fade", "ms", 1.0, 5000.0, 100.0),
+        ParamDescriptor::linear("Blend", "", 0.0, 1.0, 1.0),
+        ParamDescriptor::linear("Scatter", "", 0.0, 1.0, 0.2),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let n = (FRAME_SECONDS * sample_rate_hz.max(8000.0)) as usize;
+        SpectralFreeze {
+            channels: (0..MAX_CHANNELS).map(|_| Channel::new(n)).collect(),
+            frozen: false,
+            capture: false,
+            fade: 0.0,
+            fade_ms: 100.0,
+            fade_step: 1.0 / (0.1 * sample_rate_hz),
+            blend: 1.0,
+            scatter: 0.2,
+            rng: 0x9e37_79b9,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_FREEZE => {
+                let frozen = clamp(value, 0.0, 1.0) >= 0.5;
+                if frozen && !self.frozen {
+                    self.capture = true;
+                }
+                self.frozen = frozen;
+            }
+            PARAM_FADE_MS => {
+                self.fade_ms = clamp(value, 1.0, 5000.0);
+                self.fade_step = 1.0 / (self.fade_ms / 1000.0 * self.sample_rate_hz);
+            }
+            PARAM_BLEND => self.blend = clamp(value, 0.0, 1.0),
+            PARAM_SCATTER => self.scatter = clamp(value, 0.0, 1.0),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_FREEZE => u32::from(self.frozen) as f32,
+            PARAM_FADE_MS => self.fade_ms,
+            PARAM_BLEND => self.blend,
+            PARAM_SCATTER => self.scatter,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// One STFT frame; the live path goes through the same transform, so it is delayed too.
+    fn latency_samples(&self) -> u32 {
+        self.channels[0].stft.latency() as u32
+    }
+
+    /// Clears the analysis and the frozen frame. While `Freeze` is on, the next frame is
+    /// captured afresh and faded in.
+    fn reset(&mut self) {
+        for c in &mut self.channels {
+            c.stft.clear();
+            c.prev_phase.fill(0.0);
+            c.magnitude.fill(0.0);
+            c.advance.fill(0.0);
+            c.phase.fill(0.0);
+        }
+        self.capture = self.frozen;
+        self.fade = 0.0;
+        self.rng = 0x9e37_79b9;
+    }
+}
+
+node_abi::export_node!(SpectralFreeze {
+    abi_version: freeze_abi_version,
+    new: freeze_new,
+    free: freeze_free,
+    param_count: freeze_param_count,
+    param_descriptors: freeze_param_descriptors,
+    set_param: freeze_set_param,
+    get_param: freeze_get_param,
+    param_events: freeze_param_events,
+    process: freeze_process,
+    process_offline: freeze_process_offline,
+    latency_samples: freeze_latency_samples,
+    io_layout: freeze_get_io_layout,
+    process_io: freeze_process_io,
+    reset: freeze_reset,
+    state_size: freeze_state_size,
+    save_state: freeze_save_state,
+    load_state: freeze_load_state,
+    preset_size: freeze_preset_size,
+    save_preset: freeze_save_preset,
+    load_preset: freeze_load_preset,
+    last_error_code: freeze_last_error_code,
+    last_error_message: freeze_last_error_message_ptr,
+});
+
+node_abi::test_params!(SpectralFreeze);
+
+dsp_core::test_block_sizes!(
+    freeze_new,
+    freeze_process(input, output),
+    freeze_free,
+    setup: |p| freeze_set_params(p, 1, 20.0, 0.7, 0.2),
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(SpectralFreeze as SpectralFreezeNode);
+
+/// Every parameter in one call, as `freeze_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn freeze_set_params(
+    handle: Handle,
+    freeze: u32,
+    fade_ms: f32,
+    blend: f32,
+    scatter: f32,
+) {
+    guard(handle, |f: &mut SpectralFreeze| {
+        f.set_param(PARAM_FREEZE, freeze as f32);
+        f.set_param(PARAM_FADE_MS, fade_ms);
+        f.set_param(PARAM_BLEND, blend);
+        f.set_param(PARAM_SCATTER, scatter);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    /// A freeze with a short fade and no scatter, then `params`.
+    fn freeze(params: &[(u32, f32)]) -> SpectralFreeze {
+        let mut f = SpectralFreeze::new(RATE);
+        f.set_param(PARAM_FADE_MS, 10.0);
+        f.set_param(PARAM_SCATTER, 0.0);
+        for &(index, value) in params {
+            f.set_param(index, value);
+        }
+        f
+    }
+
+    fn render(f: &mut SpectralFreeze, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(f, &input[span], y, 1))
+    }
+
+    /// 1 s of a sine at `hz`.
+    fn sine(hz: f32) -> Vec<f32> {
+        let w = 2.0 * PI * hz / RATE;
+        (0..48_000).map(|i| 0.5 * (w * i as f32).sin()).collect()
+    }
+
+    /// Freezing captures the sound playing and sustains it in place of whatever follows;
+    /// unfreezing fades back to the live input.
+    #[test]
+    fn freeze_sustains_the_captured_frame() {
+        let mut f = freeze(&[]);
+        let live = render(&mut f, &sine(600.0));
+        assert!((amplitude(&live[24_000..], 600.0) - 0.5).abs() < 0.02);
+        f.set_param(PARAM_FREEZE, 1.0);
+        let held = render(&mut f, &sine(2000.0));
+        let level = amplitude(&held[24_000..], 600.0);
+        assert!((level - 0.5).abs() < 0.05, "{level}");
+        assert!(amplitude(&held[24_000..], 2000.0) < 0.01);
+        f.set_param(PARAM_FREEZE, 0.0);
+        let released = render(&mut f, &sine(2000.0));
+        assert!(amplitude(&released[24_000..], 600.0) < 0.01);
+        assert!((amplitude(&released[24_000..], 2000.0) - 0.5).abs() < 0.02);
+    }
+
+    /// `Blend` 0.5 layers the frozen frame and the live input at half level each.
+    #[test]
+    fn blend_layers_the_frame_and_the_input() {
+        let mut f = freeze(&[(PARAM_BLEND, 0.5)]);
+        render(&mut f, &sine(600.0));
+        f.set_param(PARAM_FREEZE, 1.0);
+        let y = render(&mut f, &sine(2000.0));
+        for hz in [600.0, 2000.0] {
+            let level = amplitude(&y[24_000..], hz);
+            assert!((level - 0.25).abs() < 0.03, "{hz}: {level}");
+        }
+    }
+}
@@ -0,0 +1,381 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use dsp_core::envelope::one_pole_coeff;
+use dsp_core::gain::db_to_lin;
+use dsp_core::param::clamp;
+use dsp_core::stft::Stft;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+/// Every channel keeps its own noise profile, so this node stops at stereo.
+pub const MAX_CHANNELS: usize = 2;
+
+// Indices into `SpectralGate::PARAMS`, in the order of `spectral_gate_set_params`' arguments.
+pub const PARAM_THRESHOLD_DB: u32 = 0;
+pub const PARAM_REDUCTION_DB: u32 = 1;
+pub const PARAM_SMOOTHING_MS: u32 = 2;
+
+/// Frame length in seconds (rounded up to a power of two in samples).
+const FRAME_SECONDS: f32 = 0.04;
+const OVERLAP: usize = 4;
+/// How fast a bin's gain opens when its level clears the threshold.
+const ATTACK_MS: f32 = 5.0;
+const MAX_CAPTURE_MS: f32 = 10_000.0;
+
+struct Channel {
+    stft: Stft,
+    /// Noise magnitude per bin (all zero until a capture completes, which passes everything).
+    profile: Vec<f32>,
+    /// Power summed over the frames of a capture in progress.
+    learning: Vec<f32>,
+    gain: Vec<f32>,
+}
+
+/// Spectral gate / denoiser. A capture averages each bin's power over a stretch of noise-only
+/// input into a profile; after that, bins whose magnitude stays below `threshold` above the
+/// profile are turned down by `reduction`. Per-bin gains open quickly and close over
+/// `smoothing`, which keeps the residual from sparkling ("musical noise").
+pub struct SpectralGate {
+    channels: Vec<Channel>,
+    threshold_db: f32,
+    threshold: f32,
+    reduction_db: f32,
+    floor: f32,
+    smoothing_ms: f32,
+    attack: f32,
+    release: f32,
+    /// Frames left in the capture in progress, and how many it has summed.
+    capture_left: u32,
+    captured: u32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl SpectralGate {
+    fn frame_rate(&self) -> f32 {
+        self.sample_rate_hz / self.channels[0].stft.hop() as f32
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let (threshold, floor) = (self.threshold, self.floor);
+        let (attack, release) = (self.attack, self.release);
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            let learning = self.capture_left > 0;
+            let mut framed = false;
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let c = &mut self.channels[ch];
+                let x = if x.is_finite() { *x } else { 0.0 };
+                *y = c.stft.process(x, |bins| {
+                    framed = true;
+                    for (k, bin) in bins.iter_mut().enumerate() {
+                        let mag = bin.norm();
+                        if learning {
+                            c.learning[k] += mag * mag;
+                        }
+                        let target = if mag >= c.profile[k] * threshold {
+                            1.0
+                        } else {
+                            floor
+                        };
+                        let coeff = if target > c.gain[k] { attack } else { release };
+                        c.gain[k] = target + (c.gain[k] - target) * coeff;
+                        bin.re *= c.gain[k];
+                        bin.im *= c.gain[k];
+                    }
+                });
+            }
+            if framed && learning {
+                self.captured += 1;
+                self.capture_left -= 1;
+                if self.capture_left == 0 {
+                    let scale = 1.0 / self.captured as f32;
+                    for c in &mut self.channels {
+                        for (p, l) in c.profile.iter_mut().zip(&c.learning) {
+                            *p = (l * scale).sqrt();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Starts learning a new profile from the next `duration_ms` of input.
+    fn capture_profile(&mut self, duration_ms: f32) {
+        let frames = clamp(duration_ms, 50.0, MAX_CAPTURE_MS) / 1000.0 * self.frame_rate();
+        self.capture_left = (frames.round() as u32).max(1);
+        self.captured = 0;
+        for c in &mut self.channels {
+            c.learning.fill(0.0);
+        }
+    }
+
+    fn clear_profile(&mut self) {
+        self.capture_left = 0;
+        for c in &mut self.channels {
+            c.profile.fill(0.0);
+        }
+    }
+}
+
+impl Channel {
+    fn new(n: usize) -> Self {
+        let stft = Stft::new(n, OVERLAP);
+        let bins = stft.bins();
+        Self {
+            stft,
+            profile: vec![0.0; bins],
+            learning: vec![0.0; bins],
+            gain: vec![1.0; bins],
+        }
+    }
+}
+
+impl Node for SpectralGate {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Threshold` above the noise profile is where a bin counts as signal; `Reduction` is how
+    /// far bins below it are turned down; `Smoothing` is how long a bin's gain takes to close.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Threshold", "dB", 0.0, 24.0, 6.0),
+        ParamDescriptor::linear("Reduction", "dB", 0.0, 80.0, 24.0),
+        ParamDescriptor::log("Smoothing", "ms", 10.0, 1000.0, 100.0),
+    ];
+    const METER_COUNT: usize = 1;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let n = (FRAME_SECONDS * sample_rate_hz.max(8000.0)) as usize;
+        let mut g = SpectralGate {
+            channels: (0..MAX_CHANNELS).map(|_| Channel::new(n)).collect(),
+            threshold_db: 6.0,
+            threshold: db_to_lin(6.0),
+            reduction_db: 24.0,
+            floor: db_to_lin(-24.0),
+            smoothing_ms: 100.0,
+            attack: 0.0,
+            release: 0.0,
+            capture_left: 0,
+            captured: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        let frame_rate = g.frame_rate();
+        g.attack = one_pole_coeff(ATTACK_MS, frame_rate);
+        g.release = one_pole_coeff(g.smoothing_ms, frame_rate);
+        g
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_THRESHOLD_DB => {
+                self.threshold_db = clamp(value, 0.0, 24.0);
+                self.threshold = db_to_lin(self.threshold_db);
+            }
+            PARAM_REDUCTION_DB => {
+                self.reduction_db = clamp(value, 0.0, 80.0);
+                self.floor = db_to_lin(-self.reduction_db);
+            }
+            PARAM_SMOOTHING_MS => {
+                self.smoothing_ms = clamp(value, 10.0, 1000.0);
+                self.release = one_pole_coeff(self.smoothing_ms, self.frame_rate());
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_THRESHOLD_DB => self.threshold_db,
+            PARAM_REDUCTION_DB => self.reduction_db,
+            PARAM_SMOOTHING_MS => self.smoothing_ms,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// One STFT frame.
+    fn latency_samples(&self) -> u32 {
+        self.channels[0].stft.latency() as u32
+    }
+
+    /// 1 while a profile capture is running, as `spectral_gate_is_capturing` reads it.
+    fn meters(&self, out: &mut [f32]) {
+        out[0] = u32::from(self.capture_left > 0) as f32;
+    }
+
+    /// Clears the analysis and opens every bin's gain, and cancels a capture in progress; the
+    /// learned profile is kept (`spectral_gate_clear_profile` forgets it).
+    fn reset(&mut self) {
+        self.capture_left = 0;
+        self.captured = 0;
+        for c in &mut self.channels {
+            c.stft.clear();
+            c.learning.fill(0.0);
+            c.gain.fill(1.0);
+        }
+    }
+}
+
+node_abi::export_node!(SpectralGate {
+    abi_version: spectral_gate_abi_version,
+    new: spectral_gate_new,
+    free: spectral_gate_free,
+    param_count: spectral_gate_param_count,
+    param_descriptors: spectral_gate_param_descriptors,
+    set_param: spectral_gate_set_param,
+    get_param: spectral_gate_get_param,
+    param_events: spectral_gate_param_events,
+    process: spectral_gate_process,
+    process_offline: spectral_gate_process_offline,
+    latency_samples: spectral_gate_latency_samples,
+    io_layout: spectral_gate_get_io_layout,
+    process_io: spectral_gate_process_io,
+    reset: spectral_gate_reset,
+    state_size: spectral_gate_state_size,
+    save_state: spectral_gate_save_state,
+    load_state: spectral_gate_load_state,
+    preset_size: spectral_gate_preset_size,
+    save_preset: spectral_gate_save_preset,
+    load_preset: spectral_gate_load_preset,
+    last_error_code: spectral_gate_last_error_code,
+    last_error_message: spectral_gate_last_error_message_ptr,
+});
+
+node_abi::test_params!(SpectralGate);
+
+dsp_core::test_block_sizes!(
+    spectral_gate_new,
+    spectral_gate_process(input, output),
+    spectral_gate_free,
+    setup: |p| spectral_gate_capture_profile(p, 50.0),
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(SpectralGate as SpectralGateNode);
+
+/// Every parameter in one call, as `spectral_gate_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn spectral_gate_set_params(
+    handle: Handle,
+    threshold_db: f32,
+    reduction_db: f32,
+    smoothing_ms: f32,
+) {
+    guard(handle, |g: &mut SpectralGate| {
+        g.set_param(PARAM_THRESHOLD_DB, threshold_db);
+        g.set_param(PARAM_REDUCTION_DB, reduction_db);
+        g.set_param(PARAM_SMOOTHING_MS, smoothing_ms);
+    });
+}
+
+/// Starts learning a new noise profile from the next `duration_ms` (50–10000) of input; feed
+/// it noise only. The previous profile stays in use until the capture completes.
+#[no_mangle]
+pub extern "C" fn spectral_gate_capture_profile(handle: Handle, duration_ms: f32) {
+    guard(handle, |g: &mut SpectralGate| {
+        g.capture_profile(duration_ms)
+    });
+}
+
+/// 1 while a profile capture is running; for the UI's "learning" indicator.
+#[no_mangle]
+pub extern "C" fn spectral_gate_is_capturing(handle: Handle) -> u32 {
+    guard(handle, |g: &mut SpectralGate| u32::from(g.capture_left > 0)).unwrap_or(0)
+}
+
+/// Forgets the profile (and cancels a capture), so everything passes again.
+#[no_mangle]
+pub extern "C" fn spectral_gate_clear_profile(handle: Handle) {
+    guard(handle, |g: &mut SpectralGate| g.clear_profile());
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::TAU;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    fn render(g: &mut SpectralGate, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(g, &input[span], y, 1))
+    }
+
+    /// 1 s of white noise at about -30 dBFS RMS, from a xorshift32.
+    fn noise(seed: u32) -> Vec<f32> {
+        let mut s = seed;
+        (0..48_000)
+            .map(|_| {
+                s ^= s << 13;
+                s ^= s >> 17;
+                s ^= s << 5;
+                0.055 * ((s >> 8) as f32 / (1u32 << 23) as f32 - 1.0)
+            })
+            .collect()
+    }
+
+    /// RMS level of the second half of `y` in dB.
+    fn level_db(y: &[f32]) -> f32 {
+        let tail = &y[y.len() / 2..];
+        10.0 * (tail.iter().map(|v| v * v).sum::<f32>() / tail.len() as f32).log10()
+    }
+
+    /// After a capture of the noise floor (flagged on the meter while it runs), the noise is
+    /// turned down by about `Reduction` while a tone above it passes; clearing the profile
+    /// lets everything through again. (The threshold sits well clear of the noise's peaks, so
+    /// no bin opens on them.)
+    #[test]
+    fn captured_profile_gates_the_noise() {
+        let mut g = SpectralGate::new(RATE);
+        g.set_param(PARAM_THRESHOLD_DB, 12.0);
+        let quiet = noise(1);
+        assert!((level_db(&render(&mut g, &quiet)) - level_db(&quiet)).abs() < 0.5);
+
+        g.capture_profile(500.0);
+        let mut meters = [0.0];
+        render(&mut g, &quiet[..4800]);
+        Node::meters(&g, &mut meters);
+        assert_eq!(meters, [1.0]);
+        render(&mut g, &quiet[4800..]);
+        Node::meters(&g, &mut meters);
+        assert_eq!(meters, [0.0]);
+
+        let fresh = noise(2);
+        let drop = level_db(&render(&mut g, &fresh)) - level_db(&fresh);
+        assert!((-27.0..-20.0).contains(&drop), "{drop}");
+
+        let w = TAU * 1000.0 / RATE;
+        let tone: Vec<f32> = fresh
+            .iter()
+            .enumerate()
+            .map(|(i, n)| n + 0.5 * (w * i as f32).sin())
+            .collect();
+        let y = render(&mut g, &tone);
+        assert!((amplitude(&y[y.len() / 2..], 1000.0) - 0.5).abs() < 0.02);
+
+        g.clear_profile();
+        assert!((level_db(&render(&mut g, &fresh)) - level_db(&fresh)).abs() < 0.5);
+    }
+}
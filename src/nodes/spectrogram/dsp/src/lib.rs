@@ -0,0 +1,494 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use dsp_core::fft::{Complex, Fft};
+use dsp_core::param::clamp;
+use dsp_core::window::Window;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing, FLAG_NO_AUTOMATION};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Spectrogram::PARAMS`, in the order of the arguments of
+// `spectrogram_set_layout`, `spectrogram_set_analysis` and `spectrogram_set_mapping`.
+pub const PARAM_HISTORY: u32 = 0;
+pub const PARAM_BINS: u32 = 1;
+pub const PARAM_SIZE: u32 = 2;
+pub const PARAM_HOP_MS: u32 = 3;
+pub const PARAM_SCALE: u32 = 4;
+pub const PARAM_MIN_HZ: u32 = 5;
+pub const PARAM_MAX_HZ: u32 = 6;
+
+pub const MIN_SIZE: usize = 512;
+pub const MAX_SIZE: usize = 8192;
+pub const MAX_HISTORY: usize = 2048;
+pub const MAX_BINS: usize = 2048;
+
+pub const LINEAR: u32 = 0;
+pub const LOG: u32 = 1;
+pub const MEL: u32 = 2;
+
+/// Floor of the readings, standing in for silence so the UI only sees finite values.
+const MIN_DB: f32 = -160.0;
+
+fn power_to_db(power: f32) -> f32 {
+    if power > 0.0 {
+        (10.0 * power.log10()).max(MIN_DB)
+    } else {
+        MIN_DB
+    }
+}
+
+fn hz_to_mel(hz: f32) -> f32 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10.0_f32.powf(mel / 2595.0) - 1.0)
+}
+
+/// Spectrogram for waterfall displays. The channels are summed to mono; every hop the last
+/// `size` samples are Hann-windowed and transformed, and the spectrum is resampled onto
+/// `bins` display bins spaced linearly, logarithmically or on the mel scale between two
+/// frequencies (each taking the loudest FFT bin it spans, or interpolating where it is
+/// narrower than one). The result, in dBFS, becomes the newest row of a `history` x `bins`
+/// matrix the UI reads in place; rows are written round-robin, so the newest is at
+/// `spectrogram_latest_row` and the oldest follows it. The audio is only read; the worklet
+/// passes it on.
+pub struct Spectrogram {
+    fft: Fft,
+    window: Vec<f32>,
+    /// Mono input, circular over `MAX_SIZE`.
+    input: Vec<f32>,
+    write: usize,
+    hop_ms: f32,
+    hop: usize,
+    since_frame: usize,
+    spectrum: Vec<Complex>,
+    power: Vec<f32>,
+    /// Each display bin's span in fractional FFT bins.
+    spans: Vec<(f32, f32)>,
+    scale: u32,
+    min_hz: f32,
+    max_hz: f32,
+    matrix: Vec<f32>,
+    history: usize,
+    latest: usize,
+    rows: u32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Spectrogram {
+    fn size(&self) -> usize {
+        self.fft.len()
+    }
+
+    fn bins(&self) -> usize {
+        self.spans.len()
+    }
+
+    fn map_spans(&mut self) {
+        let bins = self.bins();
+        let hz_per_bin = self.sample_rate_hz / self.size() as f32;
+        let nyquist = self.sample_rate_hz / 2.0;
+        let max_hz = self.max_hz.min(nyquist);
+        let min_hz = self.min_hz.min(max_hz * 0.5);
+        let edge = |t: f32| match self.scale {
+            LOG => min_hz * (max_hz / min_hz).powf(t),
+            MEL => mel_to_hz(hz_to_mel(min_hz) + (hz_to_mel(max_hz) - hz_to_mel(min_hz)) * t),
+            _ => min_hz + (max_hz - min_hz) * t,
+        };
+        let spans: Vec<(f32, f32)> = (0..bins)
+            .map(|j| {
+                let lo = edge(j as f32 / bins as f32);
+                let hi = edge((j + 1) as f32 / bins as f32);
+                (lo / hz_per_bin, hi / hz_per_bin)
+            })
+            .collect();
+        self.spans = spans;
+    }
+
+    fn set_layout(&mut self, history: usize, bins: usize) {
+        if history != self.history || bins != self.bins() {
+            self.history = history;
+            self.matrix = vec![MIN_DB; history * bins];
+            self.spans.resize(bins, (0.0, 0.0));
+            self.latest = history - 1;
+            self.map_spans();
+        }
+    }
+
+    fn set_size(&mut self, size: usize) {
+        if size != self.size() {
+            self.fft = Fft::new(size);
+            self.window.resize(size, 0.0);
+            Window::Hann.fill(&mut self.window);
+            self.spectrum.resize(size, Complex::default());
+            self.power.resize(size / 2 + 1, 0.0);
+            self.map_spans();
+        }
+    }
+
+    fn frame(&mut self) {
+        let n = self.size();
+        let start = self.write + MAX_SIZE - n;
+        for (i, (c, w)) in self.spectrum.iter_mut().zip(&self.window).enumerate() {
+            *c = Complex::new(self.input[(start + i) % MAX_SIZE] * w, 0.0);
+        }
+        self.fft.forward(&mut self.spectrum);
+        // Single-sided amplitude of a full-scale sine on a bin comes out at 1.
+        let scale = 2.0 / (n as f32 * Window::Hann.coherent_gain());
+        for (p, c) in self.power.iter_mut().zip(&self.spectrum) {
+            let amp = c.norm() * scale;
+            *p = amp * amp;
+        }
+
+        self.latest = (self.latest + 1) % self.history;
+        let bins = self.bins();
+        let last = self.power.len() - 1;
+        let row = &mut self.matrix[self.latest * bins..(self.latest + 1) * bins];
+        for (out, &(lo, hi)) in row.iter_mut().zip(&self.spans) {
+            let (first, end) = (lo.ceil() as usize, (hi.floor() as usize).min(last));
+            let power = if first <= end {
+                self.power[first..=end]
+                    .iter()
+                    .fold(0.0_f32, |m, &p| m.max(p))
+            } else {
+                let centre = ((lo + hi) * 0.5).min(last as f32);
+                let k = (centre as usize).min(last - 1);
+                let t = centre - k as f32;
+                self.power[k] + (self.power[k + 1] - self.power[k]) * t
+            };
+            *out = power_to_db(power);
+        }
+        self.rows = self.rows.wrapping_add(1);
+    }
+
+    fn measure(&mut self, input: &[f32], channels: usize) {
+        let scale = 1.0 / channels as f32;
+        for frame in input.chunks_exact(channels) {
+            let sum: f32 = frame.iter().filter(|x| x.is_finite()).sum();
+            self.input[self.write] = sum * scale;
+            self.write = (self.write + 1) % MAX_SIZE;
+            self.since_frame += 1;
+            if self.since_frame >= self.hop {
+                self.since_frame = 0;
+                self.frame();
+            }
+        }
+    }
+}
+
+impl Node for Spectrogram {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `History` rows of `Bins` display bins; a change reallocates and clears the matrix, so
+    /// fetch `spectrogram_matrix` again afterwards. `Size` is the FFT length (rounded up to a
+    /// power of two) and `Hop` the time between rows. `Scale` 0 linear, 1 log, 2 mel; the
+    /// display bins span `Min freq` (kept under half of `Max freq`) to `Max freq` (up to
+    /// Nyquist).
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("History", "", 16.0, MAX_HISTORY as f32, 256.0)
+            .with_flags(FLAG_NO_AUTOMATION),
+        ParamDescriptor::stepped("Bins", "", 16.0, MAX_BINS as f32, 256.0)
+            .with_flags(FLAG_NO_AUTOMATION),
+        ParamDescriptor::stepped("Size", "", MIN_SIZE as f32, MAX_SIZE as f32, 2048.0)
+            .with_flags(FLAG_NO_AUTOMATION),
+        ParamDescriptor::log("Hop", "ms", 1.0, 200.0, 10.0),
+        ParamDescriptor::stepped("Scale", "", 0.0, MEL as f32, LOG as f32),
+        ParamDescriptor::log("Min freq", "Hz", 10.0, 10_000.0, 20.0),
+        ParamDescriptor::log("Max freq", "Hz", 100.0, 96_000.0, 20_000.0),
+    ];
+    /// The audio is only read; the worklet passes it on.
+    const OUTPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let (size, history, bins) = (2048, 256, 256);
+        let mut window = vec![0.0; size];
+        Window::Hann.fill(&mut window);
+        let mut s = Spectrogram {
+            fft: Fft::new(size),
+            window,
+            input: vec![0.0; MAX_SIZE],
+            write: 0,
+            hop_ms: 10.0,
+            hop: ((sample_rate_hz * 0.01) as usize).max(1),
+            since_frame: 0,
+            spectrum: vec![Complex::default(); size],
+            power: vec![0.0; size / 2 + 1],
+            spans: vec![(0.0, 0.0); bins],
+            scale: LOG,
+            min_hz: 20.0,
+            max_hz: 20_000.0,
+            matrix: vec![MIN_DB; history * bins],
+            history,
+            latest: history - 1,
+            rows: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        s.map_spans();
+        s
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        let whole = |min: usize, max: usize| clamp(value, min as f32, max as f32).round() as usize;
+        match index {
+            PARAM_HISTORY => self.set_layout(whole(16, MAX_HISTORY), self.bins()),
+            PARAM_BINS => self.set_layout(self.history, whole(16, MAX_BINS)),
+            PARAM_SIZE => self.set_size(whole(MIN_SIZE, MAX_SIZE).next_power_of_two()),
+            PARAM_HOP_MS => {
+                self.hop_ms = clamp(value, 1.0, 200.0);
+                self.hop = ((self.hop_ms / 1000.0 * self.sample_rate_hz) as usize).max(1);
+            }
+            PARAM_SCALE => {
+                self.scale = whole(0, MEL as usize) as u32;
+                self.map_spans();
+            }
+            PARAM_MIN_HZ => {
+                self.min_hz = clamp(value, 10.0, 10_000.0);
+                self.map_spans();
+            }
+            PARAM_MAX_HZ => {
+                self.max_hz = clamp(value, 100.0, 96_000.0);
+                self.map_spans();
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_HISTORY => self.history as f32,
+            PARAM_BINS => self.bins() as f32,
+            PARAM_SIZE => self.size() as f32,
+            PARAM_HOP_MS => self.hop_ms,
+            PARAM_SCALE => self.scale as f32,
+            PARAM_MIN_HZ => self.min_hz,
+            PARAM_MAX_HZ => self.max_hz,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], _output: &mut [f32], channels: usize) {
+        self.measure(input, channels);
+    }
+
+    /// Clears the input history, so the next row only sees audio from here on; the rows
+    /// already drawn are kept.
+    fn reset(&mut self) {
+        self.input.fill(0.0);
+        self.write = 0;
+        self.since_frame = 0;
+    }
+}
+
+node_abi::export_node!(Spectrogram {
+    abi_version: spectrogram_abi_version,
+    new: spectrogram_new,
+    free: spectrogram_free,
+    param_count: spectrogram_param_count,
+    param_descriptors: spectrogram_param_descriptors,
+    set_param: spectrogram_set_param,
+    get_param: spectrogram_get_param,
+    param_events: spectrogram_param_events,
+    process: spectrogram_process,
+    process_offline: spectrogram_process_offline,
+    latency_samples: spectrogram_latency_samples,
+    io_layout: spectrogram_get_io_layout,
+    process_io: spectrogram_process_io,
+    reset: spectrogram_reset,
+    state_size: spectrogram_state_size,
+    save_state: spectrogram_save_state,
+    load_state: spectrogram_load_state,
+    preset_size: spectrogram_preset_size,
+    save_preset: spectrogram_save_preset,
+    load_preset: spectrogram_load_preset,
+    last_error_code: spectrogram_last_error_code,
+    last_error_message: spectrogram_last_error_message_ptr,
+});
+
+node_abi::test_params!(Spectrogram);
+
+dsp_core::test_block_sizes!(
+    spectrogram_new,
+    spectrogram_process(input, output),
+    spectrogram_free,
+    setup: |p| spectrogram_set_layout(p, 64, 128),
+    read: |p| {
+        let matrix = unsafe { core::slice::from_raw_parts(spectrogram_matrix(p), 64 * 128) };
+        let mut out = matrix.to_vec();
+        out.extend([spectrogram_latest_row(p) as f32, spectrogram_row_count(p) as f32]);
+        out
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Spectrogram as SpectrogramNode);
+
+/// `History` and `Bins` in one call.
+#[no_mangle]
+pub extern "C" fn spectrogram_set_layout(handle: Handle, history: u32, bins: u32) {
+    guard(handle, |s: &mut Spectrogram| {
+        s.set_param(PARAM_HISTORY, history as f32);
+        s.set_param(PARAM_BINS, bins as f32);
+    });
+}
+
+/// `Size` and `Hop` in one call.
+#[no_mangle]
+pub extern "C" fn spectrogram_set_analysis(handle: Handle, size: u32, hop_ms: f32) {
+    guard(handle, |s: &mut Spectrogram| {
+        s.set_param(PARAM_SIZE, size as f32);
+        s.set_param(PARAM_HOP_MS, hop_ms);
+    });
+}
+
+/// `Scale`, `Min freq` and `Max freq` in one call.
+#[no_mangle]
+pub extern "C" fn spectrogram_set_mapping(handle: Handle, scale: u32, min_hz: f32, max_hz: f32) {
+    guard(handle, |s: &mut Spectrogram| {
+        s.set_param(PARAM_SCALE, scale as f32);
+        s.set_param(PARAM_MIN_HZ, min_hz);
+        s.set_param(PARAM_MAX_HZ, max_hz);
+    });
+}
+
+/// The `history` x `bins` matrix in dBFS (floored at -160), row-major with the lowest
+/// frequency first in each row, for the UI to read in place until the next layout change.
+#[no_mangle]
+pub extern "C" fn spectrogram_matrix(handle: Handle) -> *const f32 {
+    guard(handle, |s: &mut Spectrogram| s.matrix.as_ptr()).unwrap_or(core::ptr::null())
+}
+
+/// Index of the row written last.
+#[no_mangle]
+pub extern "C" fn spectrogram_latest_row(handle: Handle) -> u32 {
+    guard(handle, |s: &mut Spectrogram| s.latest as u32).unwrap_or(0)
+}
+
+/// Counts the rows written (wrapping), so the UI knows how many to draw since it last looked.
+#[no_mangle]
+pub extern "C" fn spectrogram_row_count(handle: Handle) -> u32 {
+    guard(handle, |s: &mut Spectrogram| s.rows).unwrap_or(0)
+}
+
+/// Centre frequency of display bin `bin` in Hz, for the UI's axis labels.
+#[no_mangle]
+pub extern "C" fn spectrogram_bin_hz(handle: Handle, bin: u32) -> f32 {
+    guard(handle, |s: &mut Spectrogram| {
+        let &(lo, hi) = s.spans.get(bin as usize)?;
+        Some((lo + hi) * 0.5 * s.sample_rate_hz / s.size() as f32)
+    })
+    .flatten()
+    .unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// Feeds mono `x` to the spectrogram in render quanta.
+    fn feed(h: Handle, x: &[f32]) {
+        render_quanta(x.len(), |span, y| {
+            spectrogram_process(h, x[span].as_ptr(), y.as_mut_ptr(), y.len(), 1)
+        });
+    }
+
+    /// A row is written every hop, round-robin through the history.
+    #[test]
+    fn writes_a_row_every_hop() {
+        let h = spectrogram_new(RATE);
+        spectrogram_set_layout(h, 16, 64);
+        spectrogram_set_analysis(h, 2048, 10.0);
+        feed(h, &[0.0; 4_800]);
+        assert_eq!(spectrogram_row_count(h), 10);
+        assert_eq!(spectrogram_latest_row(h), 9);
+        feed(h, &[0.0; 4_800]);
+        assert_eq!(spectrogram_row_count(h), 20);
+        assert_eq!(spectrogram_latest_row(h), 3);
+        spectrogram_free(h);
+    }
+
+    /// Display bins are spaced evenly in Hz, in log frequency or in mels between the two
+    /// limits.
+    #[test]
+    fn spaces_bins_on_each_scale() {
+        let h = spectrogram_new(RATE);
+        spectrogram_set_layout(h, 16, 100);
+        let edge = |scale: u32, t: f32| match scale {
+            LINEAR => 100.0 + 9_900.0 * t,
+            LOG => 100.0 * 100.0_f32.powf(t),
+            _ => mel_to_hz(hz_to_mel(100.0) + (hz_to_mel(10_000.0) - hz_to_mel(100.0)) * t),
+        };
+        for scale in [LINEAR, LOG, MEL] {
+            spectrogram_set_mapping(h, scale, 100.0, 10_000.0);
+            for j in [0, 37, 99] {
+                let centre =
+                    0.5 * (edge(scale, j as f32 / 100.0) + edge(scale, (j + 1) as f32 / 100.0));
+                let hz = spectrogram_bin_hz(h, j);
+                assert!(
+                    (hz / centre - 1.0).abs() < 1e-3,
+                    "{scale} {j}: {hz} vs {centre}"
+                );
+            }
+        }
+        assert!((hz_to_mel(1_000.0) - 1_000.0).abs() < 0.1);
+        spectrogram_free(h);
+    }
+
+    /// A full-scale sine reads 0 dBFS in the display bin covering its frequency, and well
+    /// down away from it.
+    #[test]
+    fn places_a_tone_in_its_bin() {
+        let h = spectrogram_new(RATE);
+        spectrogram_set_layout(h, 16, 100);
+        spectrogram_set_mapping(h, LOG, 20.0, 20_000.0);
+        // On FFT bin 44 of 2048.
+        let hz = 44.0 * RATE / 2048.0;
+        let x: Vec<f32> = (0..4_800)
+            .map(|i| (2.0 * PI * hz * i as f32 / RATE).sin())
+            .collect();
+        feed(h, &x);
+        let matrix = unsafe { core::slice::from_raw_parts(spectrogram_matrix(h), 16 * 100) };
+        let latest = spectrogram_latest_row(h) as usize;
+        let row = &matrix[latest * 100..(latest + 1) * 100];
+        let (peak, &db) = row
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap();
+        assert!(db.abs() < 0.01, "{db}");
+        let step = 1_000.0_f32.powf(1.0 / 100.0);
+        assert!(
+            (spectrogram_bin_hz(h, peak as u32) / hz).ln().abs() < step.ln(),
+            "{peak}"
+        );
+        assert!(row[..peak - 10]
+            .iter()
+            .chain(&row[peak + 10..])
+            .all(|&v| v < -40.0));
+        spectrogram_free(h);
+    }
+}
@@ -0,0 +1,438 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use dsp_core::envelope::one_pole_coeff;
+use dsp_core::fft::{Complex, Fft};
+use dsp_core::param::clamp;
+use dsp_core::window::Window;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing, FLAG_NO_AUTOMATION};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `SpectrumAnalyzer::PARAMS`, in the order of `spectrum_set_params`' arguments.
+pub const PARAM_SIZE: u32 = 0;
+pub const PARAM_WINDOW: u32 = 1;
+pub const PARAM_AVERAGING_MS: u32 = 2;
+pub const PARAM_PEAK_FALL: u32 = 3;
+
+pub const MIN_SIZE: usize = 512;
+pub const MAX_SIZE: usize = 16_384;
+/// Bins of the largest transform; the magnitude buffers are this long whatever the size, so
+/// their addresses never change.
+pub const MAX_BINS: usize = MAX_SIZE / 2 + 1;
+
+const OVERLAP: usize = 4;
+/// Caps the hop so large transforms still refresh about 45 times a second at 48 kHz.
+const MAX_HOP: usize = 1024;
+/// Floor of the readings, standing in for silence so the UI only sees finite values.
+const MIN_DB: f32 = -160.0;
+
+fn power_to_db(power: f32) -> f32 {
+    if power > 0.0 {
+        (10.0 * power.log10()).max(MIN_DB)
+    } else {
+        MIN_DB
+    }
+}
+
+/// FFT spectrum analyzer. The channels are summed to mono; every hop the last `size` samples
+/// are windowed and transformed, and each bin's power is averaged over frames and written as
+/// dBFS (a full-scale sine on a bin reads 0 dB) into a buffer the UI reads in place, next to a
+/// peak-hold buffer that falls at a set rate. The audio is only read; the worklet passes it
+/// on.
+pub struct SpectrumAnalyzer {
+    fft: Fft,
+    window_kind: Window,
+    window: Vec<f32>,
+    /// Mono input, circular over `MAX_SIZE` so a size change can use history already there.
+    history: Vec<f32>,
+    write: usize,
+    since_frame: usize,
+    spectrum: Vec<Complex>,
+    power: Vec<f32>,
+    magnitudes: Vec<f32>,
+    peaks: Vec<f32>,
+    averaging_ms: f32,
+    average: f32,
+    /// 0 holds the peaks until reset.
+    peak_fall_db_per_second: f32,
+    /// dB the peaks fall per frame.
+    peak_fall: f32,
+    frames: u32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl SpectrumAnalyzer {
+    fn size(&self) -> usize {
+        self.fft.len()
+    }
+
+    fn hop(&self) -> usize {
+        (self.size() / OVERLAP).min(MAX_HOP)
+    }
+
+    fn bins(&self) -> usize {
+        self.size() / 2 + 1
+    }
+
+    /// The time-based settings in per-frame terms, for the current hop.
+    fn update_rates(&mut self) {
+        let frame_rate = self.sample_rate_hz / self.hop() as f32;
+        self.average = one_pole_coeff(self.averaging_ms, frame_rate);
+        self.peak_fall = self.peak_fall_db_per_second / frame_rate;
+    }
+
+    fn set_size(&mut self, size: usize) {
+        if size == self.size() {
+            return;
+        }
+        self.fft = Fft::new(size);
+        self.window.resize(size, 0.0);
+        self.window_kind.fill(&mut self.window);
+        self.spectrum.resize(size, Complex::default());
+        self.since_frame = 0;
+        self.reset_readings();
+        self.update_rates();
+    }
+
+    fn set_window(&mut self, window: Window) {
+        if window != self.window_kind {
+            self.window_kind = window;
+            window.fill(&mut self.window);
+        }
+    }
+
+    fn reset_readings(&mut self) {
+        self.power.fill(0.0);
+        self.magnitudes.fill(MIN_DB);
+        self.peaks.fill(MIN_DB);
+    }
+
+    fn frame(&mut self) {
+        let n = self.size();
+        let start = self.write + MAX_SIZE - n;
+        for (i, (c, w)) in self.spectrum.iter_mut().zip(&self.window).enumerate() {
+            *c = Complex::new(self.history[(start + i) % MAX_SIZE] * w, 0.0);
+        }
+        self.fft.forward(&mut self.spectrum);
+        // Single-sided amplitude: twice the bin over the window's sum, halved again at DC and
+        // Nyquist which have no mirror.
+        let scale = 2.0 / (n as f32 * self.window_kind.coherent_gain());
+        let bins = self.bins();
+        for k in 0..bins {
+            let edge = if k == 0 || k == bins - 1 { 0.5 } else { 1.0 };
+            let amp = self.spectrum[k].norm() * scale * edge;
+            let p = amp * amp;
+            self.power[k] = p + (self.power[k] - p) * self.average;
+            let db = power_to_db(self.power[k]);
+            self.magnitudes[k] = db;
+            self.peaks[k] = (self.peaks[k] - self.peak_fall).max(db);
+        }
+        self.frames = self.frames.wrapping_add(1);
+    }
+
+    fn measure(&mut self, input: &[f32], channels: usize) {
+        let scale = 1.0 / channels as f32;
+        let hop = self.hop();
+        for frame in input.chunks_exact(channels) {
+            let sum: f32 = frame.iter().filter(|x| x.is_finite()).sum();
+            self.history[self.write] = sum * scale;
+            self.write = (self.write + 1) % MAX_SIZE;
+            self.since_frame += 1;
+            if self.since_frame >= hop {
+                self.since_frame = 0;
+                self.frame();
+            }
+        }
+    }
+}
+
+impl Node for SpectrumAnalyzer {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Size` is the FFT length (rounded up to a power of two; a change clears the readings);
+    /// `Window` 0 rectangular, 1 Hann, 2 Hamming, 3 Blackman, 4 Blackman-Harris, 5 flat-top;
+    /// `Averaging` is the time constant of the power average (0 shows each frame as is);
+    /// `Peak fall` 0 holds peaks until `spectrum_reset_peaks`.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Size", "", MIN_SIZE as f32, MAX_SIZE as f32, 4096.0)
+            .with_flags(FLAG_NO_AUTOMATION),
+        ParamDescriptor::stepped("Window", "", 0.0, 5.0, 1.0),
+        ParamDescriptor::linear("Averaging", "ms", 0.0, 10_000.0, 0.0),
+        ParamDescriptor::linear("Peak fall", "dB/s", 0.0, 240.0, 20.0),
+    ];
+    /// The audio is only read; the worklet passes it on.
+    const OUTPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let size = 4096;
+        let window_kind = Window::Hann;
+        let mut window = vec![0.0; size];
+        window_kind.fill(&mut window);
+        let mut a = SpectrumAnalyzer {
+            fft: Fft::new(size),
+            window_kind,
+            window,
+            history: vec![0.0; MAX_SIZE],
+            write: 0,
+            since_frame: 0,
+            spectrum: vec![Complex::default(); size],
+            power: vec![0.0; MAX_BINS],
+            magnitudes: vec![MIN_DB; MAX_BINS],
+            peaks: vec![MIN_DB; MAX_BINS],
+            averaging_ms: 0.0,
+            average: 0.0,
+            peak_fall_db_per_second: 20.0,
+            peak_fall: 0.0,
+            frames: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        a.update_rates();
+        a
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_SIZE => {
+                let size = clamp(value, MIN_SIZE as f32, MAX_SIZE as f32).round() as usize;
+                self.set_size(size.next_power_of_two());
+            }
+            PARAM_WINDOW => {
+                self.set_window(Window::from_index(clamp(value, 0.0, 5.0).round() as u32));
+            }
+            PARAM_AVERAGING_MS => {
+                self.averaging_ms = clamp(value, 0.0, 10_000.0);
+                self.update_rates();
+            }
+            PARAM_PEAK_FALL => {
+                self.peak_fall_db_per_second = clamp(value, 0.0, 240.0);
+                self.update_rates();
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_SIZE => self.size() as f32,
+            PARAM_WINDOW => self.window_kind as u32 as f32,
+            PARAM_AVERAGING_MS => self.averaging_ms,
+            PARAM_PEAK_FALL => self.peak_fall_db_per_second,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], _output: &mut [f32], channels: usize) {
+        self.measure(input, channels);
+    }
+
+    /// Clears the input history, the averaged magnitudes and the peak holds.
+    fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.write = 0;
+        self.since_frame = 0;
+        self.reset_readings();
+    }
+}
+
+node_abi::export_node!(SpectrumAnalyzer {
+    abi_version: spectrum_abi_version,
+    new: spectrum_new,
+    free: spectrum_free,
+    param_count: spectrum_param_count,
+    param_descriptors: spectrum_param_descriptors,
+    set_param: spectrum_set_param,
+    get_param: spectrum_get_param,
+    param_events: spectrum_param_events,
+    process: spectrum_process,
+    process_offline: spectrum_process_offline,
+    latency_samples: spectrum_latency_samples,
+    io_layout: spectrum_get_io_layout,
+    process_io: spectrum_process_io,
+    reset: spectrum_reset,
+    state_size: spectrum_state_size,
+    save_state: spectrum_save_state,
+    load_state: spectrum_load_state,
+    preset_size: spectrum_preset_size,
+    save_preset: spectrum_save_preset,
+    load_preset: spectrum_load_preset,
+    last_error_code: spectrum_last_error_code,
+    last_error_message: spectrum_last_error_message_ptr,
+});
+
+node_abi::test_params!(SpectrumAnalyzer);
+
+dsp_core::test_block_sizes!(
+    spectrum_new,
+    spectrum_process(input, output),
+    spectrum_free,
+    read: |p| {
+        let bins = spectrum_bins(p) as usize;
+        let mut out = unsafe { core::slice::from_raw_parts(spectrum_magnitudes(p), bins) }.to_vec();
+        out.extend(unsafe { core::slice::from_raw_parts(spectrum_peaks(p), bins) });
+        out.push(spectrum_frame_count(p) as f32);
+        out
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(SpectrumAnalyzer as SpectrumAnalyzerNode);
+
+/// Every parameter in one call, as `spectrum_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn spectrum_set_params(
+    handle: Handle,
+    size: u32,
+    window: u32,
+    averaging_ms: f32,
+    peak_fall_db_per_second: f32,
+) {
+    guard(handle, |a: &mut SpectrumAnalyzer| {
+        a.set_param(PARAM_SIZE, size as f32);
+        a.set_param(PARAM_WINDOW, window as f32);
+        a.set_param(PARAM_AVERAGING_MS, averaging_ms);
+        a.set_param(PARAM_PEAK_FALL, peak_fall_db_per_second);
+    });
+}
+
+/// Bins in use, DC up to and including Nyquist (`size / 2 + 1`); bin `k` is at
+/// `k * sample_rate / size` Hz.
+#[no_mangle]
+pub extern "C" fn spectrum_bins(handle: Handle) -> u32 {
+    guard(handle, |a: &mut SpectrumAnalyzer| a.bins() as u32).unwrap_or(0)
+}
+
+/// The averaged magnitudes in dBFS (floored at -160), `spectrum_bins` of them, for the UI to
+/// read in place. `MAX_BINS` floats long and stays put for the node's lifetime.
+#[no_mangle]
+pub extern "C" fn spectrum_magnitudes(handle: Handle) -> *const f32 {
+    guard(handle, |a: &mut SpectrumAnalyzer| a.magnitudes.as_ptr()).unwrap_or(core::ptr::null())
+}
+
+/// Peak-hold magnitudes in dBFS, laid out like `spectrum_magnitudes`.
+#[no_mangle]
+pub extern "C" fn spectrum_peaks(handle: Handle) -> *const f32 {
+    guard(handle, |a: &mut SpectrumAnalyzer| a.peaks.as_ptr()).unwrap_or(core::ptr::null())
+}
+
+/// Counts the frames analyzed (wrapping), so the UI can skip redrawing an unchanged spectrum.
+#[no_mangle]
+pub extern "C" fn spectrum_frame_count(handle: Handle) -> u32 {
+    guard(handle, |a: &mut SpectrumAnalyzer| a.frames).unwrap_or(0)
+}
+
+/// Drops the peak holds to the current magnitudes.
+#[no_mangle]
+pub extern "C" fn spectrum_reset_peaks(handle: Handle) {
+    guard(handle, |a: &mut SpectrumAnalyzer| {
+        let bins = a.bins();
+        let (magnitudes, peaks) = (&a.magnitudes[..bins], &mut a.peaks[..bins]);
+        peaks.copy_from_slice(magnitudes);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+
+    const RATE: f32 = 48_000.0;
+
+    /// `frames` of a sine on bin `bin` of a 4096-point transform, on both of two channels.
+    fn stereo_bin_tone(amplitude: f32, bin: f32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|i| {
+                let x = amplitude * (2.0 * PI * bin * i as f32 / 4096.0).sin();
+                [x, x]
+            })
+            .collect()
+    }
+
+    /// Feeds stereo `x` to the analyzer in render quanta.
+    fn feed(h: Handle, x: &[f32]) {
+        let mut out = [0.0; 256];
+        for block in x.chunks(256) {
+            spectrum_process(h, block.as_ptr(), out.as_mut_ptr(), block.len() / 2, 2);
+        }
+    }
+
+    fn magnitudes(h: Handle) -> &'static [f32] {
+        unsafe { core::slice::from_raw_parts(spectrum_magnitudes(h), spectrum_bins(h) as usize) }
+    }
+
+    fn peaks(h: Handle) -> &'static [f32] {
+        unsafe { core::slice::from_raw_parts(spectrum_peaks(h), spectrum_bins(h) as usize) }
+    }
+
+    /// A full-scale sine on a bin reads 0 dBFS there through every window, with the channels
+    /// summed to mono; the amplitude-flat windows keep it within a fraction of a dB off-bin.
+    #[test]
+    fn full_scale_sine_reads_zero_db() {
+        for window in 0..=5 {
+            let h = spectrum_new(RATE);
+            spectrum_set_params(h, 4096, window, 0.0, 20.0);
+            assert_eq!(spectrum_bins(h), 2049);
+            feed(h, &stereo_bin_tone(1.0, 100.0, 8_192));
+            let m = magnitudes(h);
+            assert!(m[100].abs() < 0.01, "window {window}: {}", m[100]);
+            assert!(m[300] < -30.0, "window {window}: {}", m[300]);
+            spectrum_free(h);
+        }
+        // Half a bin off, the flat-top window loses almost nothing.
+        let h = spectrum_new(RATE);
+        spectrum_set_params(h, 4096, 5, 0.0, 20.0);
+        feed(h, &stereo_bin_tone(1.0, 100.5, 8_192));
+        let m = magnitudes(h);
+        assert!(m[100].max(m[101]).abs() < 0.1, "{} {}", m[100], m[101]);
+        spectrum_free(h);
+    }
+
+    /// A frame comes every quarter of the size, up to 1024 samples.
+    #[test]
+    fn frames_every_hop() {
+        for (size, hop) in [(512, 128), (4096, 1024), (16_384, 1024)] {
+            let h = spectrum_new(RATE);
+            spectrum_set_params(h, size, 1, 0.0, 20.0);
+            feed(h, &vec![0.0; 2 * 16_384]);
+            assert_eq!(spectrum_frame_count(h) as usize, 16_384 / hop, "{size}");
+            spectrum_free(h);
+        }
+    }
+
+    /// Once the tone stops the magnitudes drop to the floor while the peaks fall at their set
+    /// rate, and `spectrum_reset_peaks` drops them to the magnitudes.
+    #[test]
+    fn peaks_fall_at_their_rate() {
+        let h = spectrum_new(RATE);
+        spectrum_set_params(h, 4096, 1, 0.0, 20.0);
+        feed(h, &stereo_bin_tone(0.5, 100.0, 4_096));
+        assert!((peaks(h)[100] + 6.02).abs() < 0.01);
+        feed(h, &vec![0.0; 2 * 48_000]);
+        assert_eq!(magnitudes(h)[100], MIN_DB);
+        // 46 frames of 1024 samples fall 19.6 dB.
+        let fall = -6.02 - peaks(h)[100];
+        assert!((fall - 46.0 * 20.0 * 1024.0 / RATE).abs() < 0.01, "{fall}");
+        spectrum_reset_peaks(h);
+        assert_eq!(peaks(h)[100], MIN_DB);
+        spectrum_free(h);
+    }
+}
@@ -0,0 +1,554 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::f32::consts::{PI, TAU};
+use dsp_core::delay::DelayLine;
+use dsp_core::denormal::flush;
+use dsp_core::envelope::one_pole_coeff;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `StereoDelay::PARAMS`, in the order of the arguments of
+// `stereo_delay_set_params` and `stereo_delay_set_tempo`.
+pub const PARAM_TIME_L_MS: u32 = 0;
+pub const PARAM_TIME_R_MS: u32 = 1;
+pub const PARAM_SYNC: u32 = 2;
+pub const PARAM_DIVISION_L: u32 = 3;
+pub const PARAM_DIVISION_R: u32 = 4;
+pub const PARAM_FEEDBACK: u32 = 5;
+pub const PARAM_CROSS_FEEDBACK: u32 = 6;
+pub const PARAM_DAMPING_HZ: u32 = 7;
+pub const PARAM_MIX: u32 = 8;
+pub const PARAM_MODE: u32 = 9;
+pub const PARAM_WOW: u32 = 10;
+pub const PARAM_FLUTTER: u32 = 11;
+pub const PARAM_BPM: u32 = 12;
+
+const MAX_DELAY_MS: f32 = 4000.0;
+const MAX_FEEDBACK: f32 = 0.95;
+/// Delay time changes glide (pitching the repeats, like a tape delay) over roughly this long.
+const GLIDE_MS: f32 = 60.0;
+
+/// Tape-mode pitch wobble at full `wow`/`flutter`, as peak delay deviation, and its rates.
+const WOW_MS: f32 = 1.5;
+const WOW_HZ: f32 = 0.5;
+const FLUTTER_MS: f32 = 0.12;
+const FLUTTER_HZ: f32 = 6.5;
+/// Reverse-mode chunk edges fade over this long (at most a quarter chunk) so the jumps don't
+/// click.
+const REVERSE_FADE_MS: f32 = 5.0;
+
+/// Synced delay lengths in quarter notes: 1/1, 1/2, 1/2 dotted, 1/2 triplet, then the same for
+/// 1/4, 1/8 and 1/16, and finally 1/32.
+const DIVISIONS: [f32; 14] = [
+    4.0,
+    2.0,
+    3.0,
+    4.0 / 3.0,
+    1.0,
+    1.5,
+    2.0 / 3.0,
+    0.5,
+    0.75,
+    1.0 / 3.0,
+    0.25,
+    0.375,
+    1.0 / 6.0,
+    0.125,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+enum Mode {
+    Digital,
+    /// Wow and flutter on the read position, `tanh` saturation on what enters the loop.
+    Tape,
+    /// Each delay-time-long chunk is played back reversed once it has been captured.
+    Reverse,
+}
+
+/// Two-tap feedback delay. Even channels use the left time and odd channels the right one;
+/// cross-feedback sends each repeat to its pair partner (0<->1, 2<->3, ...), which with the
+/// plain feedback at zero gives ping-pong. A one-pole low-pass in the loop darkens every pass.
+#[repr(C)]
+pub struct StereoDelay {
+    time_ms: [f32; 2],
+    divisions: [u32; 2],
+    sync: bool,
+    bpm: f32,
+    feedback: f32,
+    cross_feedback: f32,
+    /// Scales both feedbacks down so their sum stays within `MAX_FEEDBACK`.
+    loop_scale: f32,
+    damping_hz: f32,
+    damping_coeff: f32,
+    mix: f32,
+    mode: Mode,
+    wow: f32,
+    flutter: f32,
+    /// Tape LFO positions in cycles.
+    wow_phase: f32,
+    flutter_phase: f32,
+    /// Position within, and length of, the chunk being reversed, per side.
+    reverse_pos: [usize; 2],
+    reverse_len: [usize; 2],
+    /// Target and current (gliding) delay per side, in samples.
+    target: [f32; 2],
+    current: [f32; 2],
+    glide_coeff: f32,
+    lines: Vec<DelayLine>,
+    lowpass: [f32; MAX_CHANNELS],
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl StereoDelay {
+    fn update_times(&mut self) {
+        for side in 0..2 {
+            let ms = if self.sync {
+                let beats = DIVISIONS[(self.divisions[side] as usize).min(DIVISIONS.len() - 1)];
+                beats * 60_000.0 / self.bpm
+            } else {
+                self.time_ms[side]
+            };
+            self.target[side] =
+                (clamp(ms, 1.0, MAX_DELAY_MS) / 1000.0 * self.sample_rate_hz).max(2.0);
+        }
+    }
+
+    fn update_feedback(&mut self) {
+        let total = self.feedback + self.cross_feedback;
+        self.loop_scale = if total > MAX_FEEDBACK {
+            MAX_FEEDBACK / total
+        } else {
+            1.0
+        };
+    }
+
+    fn update_damping(&mut self) {
+        let hz = self.damping_hz.min(self.sample_rate_hz * 0.45);
+        self.damping_coeff = (-2.0 * PI * hz / self.sample_rate_hz).exp();
+    }
+
+    /// Read delay (relative to the last push) and gain for each side this frame.
+    fn advance(&mut self) -> [(f32, f32); 2] {
+        let sr = self.sample_rate_hz;
+        let mut taps = [(0.0, 1.0); 2];
+        for side in 0..2 {
+            self.current[side] =
+                self.target[side] + (self.current[side] - self.target[side]) * self.glide_coeff;
+        }
+        match self.mode {
+            Mode::Digital => {
+                // Reading before this frame's push, one sample less reaches back just as far.
+                for (tap, current) in taps.iter_mut().zip(self.current) {
+                    tap.0 = current - 1.0;
+                }
+            }
+            Mode::Tape => {
+                let ms = sr / 1000.0;
+                let wobble = self.wow * WOW_MS * ms * (TAU * self.wow_phase).sin()
+                    + self.flutter * FLUTTER_MS * ms * (TAU * self.flutter_phase).sin();
+                for (tap, current) in taps.iter_mut().zip(self.current) {
+                    tap.0 = current - 1.0 + wobble;
+                }
+                self.wow_phase = (self.wow_phase + WOW_HZ / sr).fract();
+                self.flutter_phase = (self.flutter_phase + FLUTTER_HZ / sr).fract();
+            }
+            Mode::Reverse => {
+                let fade = REVERSE_FADE_MS / 1000.0 * sr;
+                let longest = self.lines[0].capacity() / 2;
+                for (side, tap) in taps.iter_mut().enumerate() {
+                    if self.reverse_pos[side] >= self.reverse_len[side] {
+                        self.reverse_pos[side] = 0;
+                        self.reverse_len[side] = (self.target[side] as usize).min(longest);
+                    }
+                    let p = self.reverse_pos[side];
+                    let len = self.reverse_len[side];
+                    let fade = fade.min(len as f32 * 0.25).max(1.0);
+                    // The chunk ending `p` samples ago, read from its end towards its start.
+                    tap.0 = (2 * p) as f32;
+                    tap.1 = (p as f32 / fade).min((len - p) as f32 / fade).min(1.0);
+                    self.reverse_pos[side] += 1;
+                }
+            }
+        }
+        taps
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let mut damped = [0.0_f32; MAX_CHANNELS];
+        let feedback = self.feedback * self.loop_scale;
+        let cross_feedback = self.cross_feedback * self.loop_scale;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            let taps = self.advance();
+            for (ch, d) in damped.iter_mut().enumerate().take(channels) {
+                let (delay, gain) = taps[ch % 2];
+                let delayed = self.lines[ch].read_cubic(delay) * gain;
+                let lp = &mut self.lowpass[ch];
+                *lp = flush(delayed + (*lp - delayed) * self.damping_coeff);
+                *d = *lp;
+            }
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let partner = ch ^ 1;
+                let cross = if partner < channels {
+                    damped[partner]
+                } else {
+                    0.0
+                };
+                let v = *x + feedback * damped[ch] + cross_feedback * cross;
+                self.lines[ch].push(if self.mode == Mode::Tape { v.tanh() } else { v });
+                *y = *x * (1.0 - self.mix) + damped[ch] * self.mix;
+            }
+        }
+    }
+}
+
+impl Node for StereoDelay {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Time L`/`Time R` are used with `Sync` off; otherwise `Division L`/`Division R` index
+    /// the musical divisions (0 1/1, 1 1/2, 2 1/2 dotted, 3 1/2 triplet, 4 1/4, 5 1/4 dotted,
+    /// 6 1/4 triplet, 7 1/8, 8 1/8 dotted, 9 1/8 triplet, 10 1/16, 11 1/16 dotted,
+    /// 12 1/16 triplet, 13 1/32) at `Tempo`. `Feedback` and `Cross feedback` are scaled down
+    /// together when their sum passes 0.95. `Damping` is the loop low-pass corner. `Mode`: 0
+    /// digital, 1 tape (`Wow` and `Flutter` set its pitch wobble; the loop saturates), 2
+    /// reverse (each delay-time chunk, at most 2 s, plays backwards). Time changes glide.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::log("Time L", "ms", 1.0, MAX_DELAY_MS, 375.0),
+        ParamDescriptor::log("Time R", "ms", 1.0, MAX_DELAY_MS, 500.0),
+        ParamDescriptor::toggle("Sync", false),
+        ParamDescriptor::stepped("Division L", "", 0.0, (DIVISIONS.len() - 1) as f32, 5.0),
+        ParamDescriptor::stepped("Division R", "", 0.0, (DIVISIONS.len() - 1) as f32, 4.0),
+        ParamDescriptor::linear("Feedback", "", 0.0, MAX_FEEDBACK, 0.35),
+        ParamDescriptor::linear("Cross feedback", "", 0.0, MAX_FEEDBACK, 0.0),
+        ParamDescriptor::log("Damping", "Hz", 500.0, 20_000.0, 20_000.0),
+        ParamDescriptor::linear("Mix", "", 0.0, 1.0, 0.35),
+        ParamDescriptor::stepped("Mode", "", 0.0, 2.0, 0.0),
+        ParamDescriptor::linear("Wow", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Flutter", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Tempo", "bpm", 20.0, 300.0, 120.0),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let max_delay = (MAX_DELAY_MS / 1000.0 * sample_rate_hz) as usize + 2;
+        let mut d = StereoDelay {
+            time_ms: [375.0, 500.0],
+            divisions: [5, 4],
+            sync: false,
+            bpm: 120.0,
+            feedback: 0.35,
+            cross_feedback: 0.0,
+            loop_scale: 1.0,
+            damping_hz: 20_000.0,
+            damping_coeff: 0.0,
+            mix: 0.35,
+            mode: Mode::Digital,
+            wow: 0.0,
+            flutter: 0.0,
+            wow_phase: 0.0,
+            flutter_phase: 0.0,
+            reverse_pos: [0; 2],
+            reverse_len: [0; 2],
+            target: [0.0; 2],
+            current: [0.0; 2],
+            glide_coeff: one_pole_coeff(GLIDE_MS, sample_rate_hz),
+            lines: (0..MAX_CHANNELS)
+                .map(|_| DelayLine::new(max_delay))
+                .collect(),
+            lowpass: [0.0; MAX_CHANNELS],
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        d.update_damping();
+        d.update_times();
+        d.current = d.target;
+        d
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        let division = || clamp(value, 0.0, (DIVISIONS.len() - 1) as f32).round() as u32;
+        match index {
+            PARAM_TIME_L_MS => self.time_ms[0] = clamp(value, 1.0, MAX_DELAY_MS),
+            PARAM_TIME_R_MS => self.time_ms[1] = clamp(value, 1.0, MAX_DELAY_MS),
+            PARAM_SYNC => self.sync = clamp(value, 0.0, 1.0) >= 0.5,
+            PARAM_DIVISION_L => self.divisions[0] = division(),
+            PARAM_DIVISION_R => self.divisions[1] = division(),
+            PARAM_FEEDBACK => {
+                self.feedback = clamp(value, 0.0, MAX_FEEDBACK);
+                self.update_feedback();
+            }
+            PARAM_CROSS_FEEDBACK => {
+                self.cross_feedback = clamp(value, 0.0, MAX_FEEDBACK);
+                self.update_feedback();
+            }
+            PARAM_DAMPING_HZ => {
+                self.damping_hz = clamp(value, 500.0, 20_000.0);
+                self.update_damping();
+            }
+            PARAM_MIX => self.mix = clamp(value, 0.0, 1.0),
+            PARAM_MODE => {
+                let mode = match clamp(value, 0.0, 2.0).round() as u32 {
+                    1 => Mode::Tape,
+                    2 => Mode::Reverse,
+                    _ => Mode::Digital,
+                };
+                if mode != self.mode {
+                    self.reverse_pos = [0; 2];
+                    self.reverse_len = [0; 2];
+                }
+                self.mode = mode;
+            }
+            PARAM_WOW => self.wow = clamp(value, 0.0, 1.0),
+            PARAM_FLUTTER => self.flutter = clamp(value, 0.0, 1.0),
+            PARAM_BPM => self.bpm = clamp(value, 20.0, 300.0),
+            _ => {}
+        }
+        if matches!(index, PARAM_TIME_L_MS..=PARAM_DIVISION_R | PARAM_BPM) {
+            self.update_times();
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_TIME_L_MS => self.time_ms[0],
+            PARAM_TIME_R_MS => self.time_ms[1],
+            PARAM_SYNC => u32::from(self.sync) as f32,
+            PARAM_DIVISION_L => self.divisions[0] as f32,
+            PARAM_DIVISION_R => self.divisions[1] as f32,
+            PARAM_FEEDBACK => self.feedback,
+            PARAM_CROSS_FEEDBACK => self.cross_feedback,
+            PARAM_DAMPING_HZ => self.damping_hz,
+            PARAM_MIX => self.mix,
+            PARAM_MODE => self.mode as u32 as f32,
+            PARAM_WOW => self.wow,
+            PARAM_FLUTTER => self.flutter,
+            PARAM_BPM => self.bpm,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Empties the delay lines and the damping filters, lands the delay times on their
+    /// targets and restarts the tape wobble and the reverse chunks.
+    fn reset(&mut self) {
+        for line in &mut self.lines {
+            line.clear();
+        }
+        self.lowpass = [0.0; MAX_CHANNELS];
+        self.current = self.target;
+        self.wow_phase = 0.0;
+        self.flutter_phase = 0.0;
+        self.reverse_pos = [0; 2];
+        self.reverse_len = [0; 2];
+    }
+}
+
+node_abi::export_node!(StereoDelay {
+    abi_version: stereo_delay_abi_version,
+    new: stereo_delay_new,
+    free: stereo_delay_free,
+    param_count: stereo_delay_param_count,
+    param_descriptors: stereo_delay_param_descriptors,
+    set_param: stereo_delay_set_param,
+    get_param: stereo_delay_get_param,
+    param_events: stereo_delay_param_events,
+    process: stereo_delay_process,
+    process_offline: stereo_delay_process_offline,
+    latency_samples: stereo_delay_latency_samples,
+    io_layout: stereo_delay_get_io_layout,
+    process_io: stereo_delay_process_io,
+    reset: stereo_delay_reset,
+    state_size: stereo_delay_state_size,
+    save_state: stereo_delay_save_state,
+    load_state: stereo_delay_load_state,
+    preset_size: stereo_delay_preset_size,
+    save_preset: stereo_delay_save_preset,
+    load_preset: stereo_delay_load_preset,
+    last_error_code: stereo_delay_last_error_code,
+    last_error_message: stereo_delay_last_error_message_ptr,
+});
+
+node_abi::test_params!(StereoDelay);
+
+dsp_core::test_block_sizes!(
+    stereo_delay_new,
+    stereo_delay_process(input, output),
+    stereo_delay_free
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(StereoDelay as StereoDelayNode);
+
+/// Every parameter but `Tempo` in one call, as `stereo_delay_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn stereo_delay_set_params(
+    handle: Handle,
+    time_l_ms: f32,
+    time_r_ms: f32,
+    sync: u32,
+    division_l: u32,
+    division_r: u32,
+    feedback: f32,
+    cross_feedback: f32,
+    damping_hz: f32,
+    mix: f32,
+    mode: u32,
+    wow: f32,
+    flutter: f32,
+) {
+    guard(handle, |d: &mut StereoDelay| {
+        d.set_param(PARAM_TIME_L_MS, time_l_ms);
+        d.set_param(PARAM_TIME_R_MS, time_r_ms);
+        d.set_param(PARAM_SYNC, sync as f32);
+        d.set_param(PARAM_DIVISION_L, division_l as f32);
+        d.set_param(PARAM_DIVISION_R, division_r as f32);
+        d.set_param(PARAM_FEEDBACK, feedback);
+        d.set_param(PARAM_CROSS_FEEDBACK, cross_feedback);
+        d.set_param(PARAM_DAMPING_HZ, damping_hz);
+        d.set_param(PARAM_MIX, mix);
+        d.set_param(PARAM_MODE, mode as f32);
+        d.set_param(PARAM_WOW, wow);
+        d.set_param(PARAM_FLUTTER, flutter);
+    });
+}
+
+/// Host tempo for synced times, as `Tempo`.
+#[no_mangle]
+pub extern "C" fn stereo_delay_set_tempo(handle: Handle, bpm: f32) {
+    guard(handle, |d: &mut StereoDelay| d.set_param(PARAM_BPM, bpm));
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 48_000.0;
+
+    /// A fully wet delay with `params` set and its times landed, no feedback unless asked.
+    fn delay(params: &[(u32, f32)]) -> StereoDelay {
+        let mut d = StereoDelay::new(RATE);
+        d.set_param(PARAM_MIX, 1.0);
+        d.set_param(PARAM_FEEDBACK, 0.0);
+        for &(index, value) in params {
+            d.set_param(index, value);
+        }
+        Node::reset(&mut d);
+        d
+    }
+
+    /// Stereo in render quanta; returns the left and right outputs.
+    fn render(d: &mut StereoDelay, left: &[f32], right: &[f32]) -> (Vec<f32>, Vec<f32>) {
+        let input: Vec<f32> = left.iter().zip(right).flat_map(|(&l, &r)| [l, r]).collect();
+        let mut output = vec![0.0; input.len()];
+        for (x, y) in input.chunks(256).zip(output.chunks_mut(256)) {
+            Node::process(d, x, y, 2);
+        }
+        let side = |ch: usize| output.iter().skip(ch).step_by(2).copied().collect();
+        (side(0), side(1))
+    }
+
+    fn impulse(frames: usize, at: usize) -> Vec<f32> {
+        let mut x = vec![0.0; frames];
+        x[at] = 1.0;
+        x
+    }
+
+    /// Index of the loudest sample in `range`.
+    fn peak_at(y: &[f32], range: core::ops::Range<usize>) -> usize {
+        let start = range.start;
+        y[range]
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+            .unwrap()
+            .0
+            + start
+    }
+
+    /// Each side repeats after its own time, and cross-feedback bounces the repeat between
+    /// them: left at 10 ms, over to the right 15 ms later, back to the left 10 ms after that.
+    #[test]
+    fn times_per_side_and_ping_pong() {
+        let mut d = delay(&[
+            (PARAM_TIME_L_MS, 10.0),
+            (PARAM_TIME_R_MS, 15.0),
+            (PARAM_CROSS_FEEDBACK, 0.5),
+        ]);
+        let (l, r) = render(&mut d, &impulse(2400, 0), &[0.0; 2400]);
+        assert_eq!(peak_at(&l, 0..960), 480);
+        assert_eq!(peak_at(&r, 0..1440), 1200);
+        assert_eq!(peak_at(&l, 960..2400), 1680);
+        assert!(l[960..1600].iter().all(|v| v.abs() < 1e-3));
+    }
+
+    /// With `Sync` on, the divisions set the times at the tempo: a 1/8 and a 1/16 at 120 bpm.
+    #[test]
+    fn sync_follows_the_divisions_at_the_tempo() {
+        let mut d = delay(&[
+            (PARAM_TIME_L_MS, 10.0),
+            (PARAM_TIME_R_MS, 10.0),
+            (PARAM_SYNC, 1.0),
+            (PARAM_DIVISION_L, 7.0),
+            (PARAM_DIVISION_R, 10.0),
+            (PARAM_BPM, 120.0),
+        ]);
+        let x = impulse(14_000, 0);
+        let (l, r) = render(&mut d, &x, &x);
+        assert_eq!(peak_at(&l, 0..14_000), 12_000);
+        assert_eq!(peak_at(&r, 0..14_000), 6000);
+    }
+
+    /// Reverse mode plays each captured 100 ms chunk backwards: a click 1000 samples into
+    /// the first chunk comes out 1000 samples before the end of the second.
+    #[test]
+    fn reverse_plays_the_chunk_backwards() {
+        let x = impulse(9600, 1000);
+        let mut digital = delay(&[(PARAM_TIME_L_MS, 100.0)]);
+        assert_eq!(peak_at(&render(&mut digital, &x, &x).0, 0..9600), 5800);
+        let mut reverse = delay(&[(PARAM_TIME_L_MS, 100.0), (PARAM_MODE, 2.0)]);
+        assert_eq!(peak_at(&render(&mut reverse, &x, &x).0, 0..9600), 8599);
+    }
+
+    /// Tape mode saturates what enters the loop, and `Wow` stretches the delay by up to
+    /// 1.5 ms, most a quarter of the way through its 2 s cycle.
+    #[test]
+    fn tape_saturates_and_wobbles() {
+        let mut x = impulse(1000, 0);
+        x[0] = 4.0;
+        let mut digital = delay(&[(PARAM_TIME_L_MS, 10.0)]);
+        assert!(render(&mut digital, &x, &x).0[480] > 3.0);
+        let mut tape = delay(&[(PARAM_TIME_L_MS, 10.0), (PARAM_MODE, 1.0)]);
+        assert!(render(&mut tape, &x, &x).0.iter().all(|v| v.abs() <= 1.0));
+
+        let x = impulse(25_000, 24_000);
+        let mut tape = delay(&[(PARAM_TIME_L_MS, 10.0), (PARAM_MODE, 1.0), (PARAM_WOW, 1.0)]);
+        let at = peak_at(&render(&mut tape, &x, &x).0, 24_000..25_000);
+        assert!((24_550..=24_554).contains(&at), "{at}");
+    }
+}
@@ -0,0 +1,291 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::param::clamp;
+use dsp_core::svf::{Coeffs, SvfState};
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Svf::PARAMS`, in the order of `svf_set_params`' arguments.
+pub const PARAM_CUTOFF_HZ: u32 = 0;
+pub const PARAM_RESONANCE: u32 = 1;
+pub const PARAM_MORPH: u32 = 2;
+
+/// Smallest damping (`1/Q`); full resonance rings for a long time but stays a filter.
+const MIN_DAMPING: f32 = 0.01;
+/// Damping `1/sqrt(2)` (a Q of `sqrt(2)`), as resonance.
+const DEFAULT_RESONANCE: f32 = 1.0 - core::f32::consts::FRAC_1_SQRT_2 / 2.0;
+
+#[repr(C)]
+pub struct Svf {
+    cutoff_hz: f32,
+    resonance: f32,
+    damping: f32,
+    morph: f32,
+    coeffs: Coeffs,
+    states: [SvfState; MAX_CHANNELS],
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+/// Blends low -> band -> high as `morph` goes 0 -> 0.5 -> 1.
+#[inline]
+fn morph_output(morph: f32, (low, band, high): (f32, f32, f32)) -> f32 {
+    if morph < 0.5 {
+        let t = morph * 2.0;
+        low + (band - low) * t
+    } else {
+        let t = morph * 2.0 - 1.0;
+        band + (high - band) * t
+    }
+}
+
+impl Svf {
+    /// `cutoff_mod` holds a cutoff offset in octaves per frame, every `mod_stride` values.
+    fn run(
+        &mut self,
+        input: &[f32],
+        cutoff_mod: Option<(&[f32], usize)>,
+        output: &mut [f32],
+        channels: usize,
+    ) {
+        let frames = input.len() / channels;
+        let nyquist = self.sample_rate_hz * 0.49;
+        for i in 0..frames {
+            let modulated;
+            let c = match cutoff_mod {
+                Some((m, stride)) => {
+                    let octaves = m[i * stride].clamp(-10.0, 10.0);
+                    let hz = (self.cutoff_hz * octaves.exp2()).clamp(10.0, nyquist);
+                    modulated = Coeffs::new(hz, self.damping, self.sample_rate_hz);
+                    &modulated
+                }
+                None => &self.coeffs,
+            };
+            for ch in 0..channels {
+                let bands = self.states[ch].process(input[i * channels + ch], c);
+                output[i * channels + ch] = morph_output(self.morph, bands);
+            }
+        }
+    }
+
+    fn update_coeffs(&mut self) {
+        self.coeffs = Coeffs::new(self.cutoff_hz, self.damping, self.sample_rate_hz);
+    }
+}
+
+impl Node for Svf {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Resonance` 0–1 maps damping from 2 (no peak) down to a small floor, where the filter
+    /// rings on the edge of self-oscillation. `Morph` 0–1 sweeps the output low-pass ->
+    /// band-pass -> high-pass.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::log("Cutoff", "Hz", 10.0, 20000.0, 1000.0),
+        ParamDescriptor::linear("Resonance", "", 0.0, 1.0, DEFAULT_RESONANCE),
+        ParamDescriptor::linear("Morph", "", 0.0, 1.0, 0.0),
+    ];
+    /// The cutoff offset in octaves; only the first channel is read.
+    const AUX_CHANNELS: usize = 1;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let damping = 2.0 * (1.0 - DEFAULT_RESONANCE);
+        Svf {
+            cutoff_hz: 1000.0,
+            resonance: DEFAULT_RESONANCE,
+            damping,
+            morph: 0.0,
+            coeffs: Coeffs::new(1000.0, damping, sample_rate_hz),
+            states: [SvfState::default(); MAX_CHANNELS],
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_CUTOFF_HZ => {
+                self.cutoff_hz = clamp(value, 10.0, (self.sample_rate_hz * 0.49).min(20000.0));
+                self.update_coeffs();
+            }
+            PARAM_RESONANCE => {
+                self.resonance = clamp(value, 0.0, 1.0);
+                self.damping = (2.0 * (1.0 - self.resonance)).max(MIN_DAMPING);
+                self.update_coeffs();
+            }
+            PARAM_MORPH => self.morph = clamp(value, 0.0, 1.0),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_CUTOFF_HZ => self.cutoff_hz,
+            PARAM_RESONANCE => self.resonance,
+            PARAM_MORPH => self.morph,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.run(input, None, output, channels);
+    }
+
+    /// Coefficients are recomputed every sample while modulated.
+    fn process_aux(
+        &mut self,
+        input: &[f32],
+        cutoff_mod: &[f32],
+        mod_channels: usize,
+        output: &mut [f32],
+        channels: usize,
+    ) {
+        let cutoff_mod = (!cutoff_mod.is_empty()).then_some((cutoff_mod, mod_channels));
+        self.run(input, cutoff_mod, output, channels);
+    }
+
+    /// Clears the filter memories.
+    fn reset(&mut self) {
+        for state in &mut self.states {
+            state.reset();
+        }
+    }
+}
+
+node_abi::export_node!(Svf {
+    abi_version: svf_abi_version,
+    new: svf_new,
+    free: svf_free,
+    param_count: svf_param_count,
+    param_descriptors: svf_param_descriptors,
+    set_param: svf_set_param,
+    get_param: svf_get_param,
+    param_events: svf_param_events,
+    process: svf_process,
+    process_offline: svf_process_offline,
+    latency_samples: svf_latency_samples,
+    io_layout: svf_get_io_layout,
+    process_io: svf_process_io,
+    reset: svf_reset,
+    state_size: svf_state_size,
+    save_state: svf_save_state,
+    load_state: svf_load_state,
+    preset_size: svf_preset_size,
+    save_preset: svf_save_preset,
+    load_preset: svf_load_preset,
+    last_error_code: svf_last_error_code,
+    last_error_message: svf_last_error_message_ptr,
+    process_aux: svf_process_modulated,
+    process_aux_offline: svf_process_offline_modulated,
+    process_io_aux: svf_process_io_modulated,
+});
+
+node_abi::test_params!(Svf);
+
+dsp_core::test_block_sizes!(svf_new, svf_process(input, output), svf_free);
+
+node_abi::test_io_block!(Svf);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Svf as SvfNode);
+
+/// Every parameter in one call, as `svf_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn svf_set_params(handle: Handle, cutoff_hz: f32, resonance: f32, morph: f32) {
+    guard(handle, |s: &mut Svf| {
+        s.set_param(PARAM_CUTOFF_HZ, cutoff_hz);
+        s.set_param(PARAM_RESONANCE, resonance);
+        s.set_param(PARAM_MORPH, morph);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    fn svf(params: &[(u32, f32)]) -> Svf {
+        let mut s = Svf::new(RATE);
+        for &(index, value) in params {
+            s.set_param(index, value);
+        }
+        s
+    }
+
+    /// `input` through `s`, with `cutoff_mod` as the aux input when given.
+    fn render(s: &mut Svf, input: &[f32], cutoff_mod: Option<&[f32]>) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| match cutoff_mod {
+            Some(m) => Node::process_aux(s, &input[span.clone()], &m[span], 1, y, 1),
+            None => Node::process(s, &input[span], y, 1),
+        })
+    }
+
+    /// Settled gain in dB on a `hz` sine.
+    fn gain_db_at(s: &mut Svf, hz: f32) -> f32 {
+        let w = core::f32::consts::TAU * hz / RATE;
+        let input: Vec<f32> = (0..48_000).map(|i| (w * i as f32).sin()).collect();
+        let output = render(s, &input, None);
+        let peak = output[24_000..].iter().fold(0.0_f32, |m, y| m.max(y.abs()));
+        20.0 * peak.log10()
+    }
+
+    /// `Morph` walks the output from low-pass through band-pass to high-pass.
+    #[test]
+    fn morph_sweeps_low_band_high() {
+        let gains = |morph| {
+            [100.0, 1_000.0, 10_000.0].map(|hz| gain_db_at(&mut svf(&[(PARAM_MORPH, morph)]), hz))
+        };
+        let [low, _, high] = gains(0.0);
+        assert!(low.abs() < 0.1 && high < -36.0, "low-pass: {low} {high}");
+        let [low, centre, high] = gains(0.5);
+        assert!(
+            low < centre - 18.0 && high < centre - 18.0,
+            "band-pass: {low} {centre} {high}"
+        );
+        let [low, _, high] = gains(1.0);
+        assert!(low < -36.0 && high.abs() < 0.1, "high-pass: {low} {high}");
+    }
+
+    /// At full resonance an impulse rings at the cutoff but dies away instead of
+    /// self-oscillating.
+    #[test]
+    fn full_resonance_rings_but_decays() {
+        let mut s = svf(&[(PARAM_RESONANCE, 1.0), (PARAM_MORPH, 0.5)]);
+        let mut input = vec![0.0; 4 * 48_000];
+        input[0] = 1.0;
+        let output = render(&mut s, &input, None);
+        let peak = |y: &[f32]| y.iter().fold(0.0_f32, |m, y| m.max(y.abs()));
+        let first = peak(&output[..4_800]);
+        assert!(first > 0.01);
+        assert!(peak(&output[3 * 48_000..]) < first * 0.1);
+        assert!(output.iter().all(|y| y.is_finite()));
+    }
+
+    /// The modulation input offsets the cutoff in octaves, as if `Cutoff` were set there.
+    #[test]
+    fn cutoff_modulation_is_in_octaves() {
+        let input = dsp_core::blocksize::test_signal(9_600, 1, 4);
+        let modulated = render(&mut svf(&[]), &input, Some(&[1.0; 9_600]));
+        let set = render(&mut svf(&[(PARAM_CUTOFF_HZ, 2_000.0)]), &input, None);
+        for (a, b) in modulated.iter().zip(&set) {
+            assert!((a - b).abs() < 1e-5, "{a} {b}");
+        }
+    }
+}
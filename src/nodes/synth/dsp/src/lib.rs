@@ -0,0 +1,734 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+mod voice;
+
+use dsp_core::envelope::AdsrParams;
+use dsp_core::events::{EventRing, NoteEvent, ALL_NOTES_OFF, NOTE_OFF, NOTE_ON, PITCH_BEND};
+use dsp_core::gain::db_to_lin;
+use dsp_core::oscillator::Waveform;
+use dsp_core::param::clamp;
+use dsp_core::svf::Coeffs;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+use voice::{OscParams, Voice};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_VOICES: usize = 16;
+
+// Indices into `Synth::PARAMS`: oscillator `i`'s start at `i * OSC_PARAMS`, in the order of
+// `synth_set_oscillator`'s arguments, and envelope `i`'s (0 amp, 1 filter) at
+// `PARAM_ENVELOPES + i * ENVELOPE_PARAMS`, in the order of `synth_set_envelope`'s; the
+// arguments of `synth_set_filter`, `synth_set_lfo`, `synth_set_params` and `synth_set_sync`
+// fill the rest.
+pub const OSC_PARAMS: u32 = 4;
+pub const PARAM_WAVE: u32 = 0;
+pub const PARAM_SEMITONES: u32 = 1;
+pub const PARAM_CENTS: u32 = 2;
+pub const PARAM_OSC_LEVEL: u32 = 3;
+pub const PARAM_CUTOFF_HZ: u32 = 2 * OSC_PARAMS;
+pub const PARAM_RESONANCE: u32 = PARAM_CUTOFF_HZ + 1;
+pub const PARAM_KEY_TRACK: u32 = PARAM_CUTOFF_HZ + 2;
+pub const PARAM_ENV_OCTAVES: u32 = PARAM_CUTOFF_HZ + 3;
+pub const ENVELOPE_PARAMS: u32 = 4;
+pub const PARAM_ENVELOPES: u32 = PARAM_CUTOFF_HZ + 4;
+pub const PARAM_ATTACK_MS: u32 = 0;
+pub const PARAM_DECAY_MS: u32 = 1;
+pub const PARAM_SUSTAIN: u32 = 2;
+pub const PARAM_RELEASE_MS: u32 = 3;
+pub const PARAM_LFO_RATE_HZ: u32 = PARAM_ENVELOPES + 2 * ENVELOPE_PARAMS;
+pub const PARAM_LFO_WAVE: u32 = PARAM_LFO_RATE_HZ + 1;
+pub const PARAM_LFO_SEMITONES: u32 = PARAM_LFO_RATE_HZ + 2;
+pub const PARAM_LFO_OCTAVES: u32 = PARAM_LFO_RATE_HZ + 3;
+pub const PARAM_POLYPHONY: u32 = PARAM_LFO_RATE_HZ + 4;
+pub const PARAM_NOISE: u32 = PARAM_LFO_RATE_HZ + 5;
+pub const PARAM_LEVEL_DB: u32 = PARAM_LFO_RATE_HZ + 6;
+pub const PARAM_SYNC: u32 = PARAM_LFO_RATE_HZ + 7;
+
+/// Samples between pitch/cutoff updates (the LFO and filter envelope are smooth enough).
+const CONTROL_BLOCK: u64 = 16;
+/// Smallest filter damping; resonance stops short of self-oscillation.
+const MIN_DAMPING: f32 = 0.05;
+/// Key tracking pivots around middle C.
+const TRACK_NOTE: f32 = 60.0;
+
+/// Polyphonic subtractive synth. Each voice runs two oscillators and white noise into a
+/// low-pass SVF, with an amp and a filter ADSR; one LFO, shared by all voices, wobbles pitch
+/// and cutoff. Notes arrive through an event ring the worklet writes into wasm memory
+/// (`synth_events`) and land at their frame within the next block. A note-on takes, in
+/// order: the voice already playing that note, an idle voice, the quietest released voice,
+/// the oldest held one.
+pub struct Synth {
+    notes: EventRing,
+    voices: [Voice; MAX_VOICES],
+    polyphony: usize,
+    oscs: [OscParams; 2],
+    /// Each oscillator's offset from the note in semitones and in cents.
+    tuning: [(f32, f32); 2],
+    sync: bool,
+    noise: f32,
+    envelopes: [AdsrParams; 2],
+    /// Attack, decay, sustain and release of each envelope, in the order of its parameters.
+    adsr: [[f32; ENVELOPE_PARAMS as usize]; 2],
+    cutoff_hz: f32,
+    resonance: f32,
+    damping: f32,
+    key_track: f32,
+    env_octaves: f32,
+    lfo_wave: Waveform,
+    lfo_rate_hz: f32,
+    lfo_inc: f32,
+    lfo_phase: f32,
+    lfo_semitones: f32,
+    lfo_octaves: f32,
+    bend: f32,
+    level_db: f32,
+    gain: f32,
+    /// Samples rendered (the control clock), and note-ons so far (voice ages).
+    clock: u64,
+    started: u64,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+fn waveform(wave: u32) -> Waveform {
+    match wave {
+        1 => Waveform::Triangle,
+        2 => Waveform::Saw,
+        3 => Waveform::Square,
+        _ => Waveform::Sine,
+    }
+}
+
+impl Synth {
+    fn note_on(&mut self, note: u8, velocity: f32) {
+        let pool = &self.voices[..self.polyphony];
+        let index = pool
+            .iter()
+            .position(|v| v.is_active() && v.note == note)
+            .or_else(|| pool.iter().position(|v| !v.is_active()))
+            .or_else(|| {
+                (0..pool.len())
+                    .filter(|&i| !pool[i].gate)
+                    .min_by(|&a, &b| pool[a].amp.level().total_cmp(&pool[b].amp.level()))
+            })
+            .or_else(|| (0..pool.len()).min_by_key(|&i| pool[i].age))
+            .unwrap_or(0);
+        self.started += 1;
+        self.voices[index].start(note, velocity, self.started);
+        self.update_voice(index, self.lfo_value());
+    }
+
+    fn note_off(&mut self, note: u8) {
+        for v in &mut self.voices {
+            if v.gate && v.note == note {
+                v.release();
+            }
+        }
+    }
+
+    fn handle(&mut self, e: NoteEvent) {
+        let note = e.note.min(127) as u8;
+        match e.kind {
+            NOTE_ON if e.value > 0.0 => self.note_on(note, clamp(e.value, 0.0, 1.0)),
+            // Velocity 0 is a note-off, as in MIDI.
+            NOTE_ON | NOTE_OFF => self.note_off(note),
+            ALL_NOTES_OFF => self.voices.iter_mut().for_each(Voice::release),
+            PITCH_BEND => self.bend = clamp(e.value, -48.0, 48.0),
+            _ => {}
+        }
+    }
+
+    fn update_ratio(&mut self, osc: usize) {
+        let (semitones, cents) = self.tuning[osc];
+        self.oscs[osc].ratio = (semitones / 12.0 + cents / 1200.0).exp2();
+    }
+
+    fn update_envelope(&mut self, index: usize) {
+        let [attack_ms, decay_ms, sustain, release_ms] = self.adsr[index];
+        self.envelopes[index] = AdsrParams::new(
+            attack_ms,
+            decay_ms,
+            sustain,
+            release_ms,
+            self.sample_rate_hz,
+        );
+    }
+
+    fn lfo_value(&self) -> f32 {
+        self.lfo_wave.value(self.lfo_phase)
+    }
+
+    /// Sets voice `index`'s oscillator increments and filter from its note, filter envelope
+    /// and the LFO.
+    fn update_voice(&mut self, index: usize, lfo: f32) {
+        let v = &mut self.voices[index];
+        let pitch = v.note as f32 + self.bend + lfo * self.lfo_semitones;
+        let base = 440.0 * ((pitch - 69.0) / 12.0).exp2() / self.sample_rate_hz;
+        for (o, params) in v.oscs.iter_mut().zip(&self.oscs) {
+            o.set_increment(base * params.ratio);
+        }
+        let octaves = self.env_octaves * v.filter_env.level()
+            + lfo * self.lfo_octaves
+            + self.key_track * (v.note as f32 - TRACK_NOTE) / 12.0;
+        let hz = clamp(
+            self.cutoff_hz * octaves.exp2(),
+            20.0,
+            self.sample_rate_hz * 0.45,
+        );
+        v.coeffs = Coeffs::new(hz, self.damping, self.sample_rate_hz);
+    }
+
+    fn render(&mut self, output: &mut [f32], channels: usize) {
+        for out in output.chunks_exact_mut(channels) {
+            if self.clock.is_multiple_of(CONTROL_BLOCK) {
+                let lfo = self.lfo_value();
+                for index in 0..MAX_VOICES {
+                    if self.voices[index].is_active() {
+                        self.update_voice(index, lfo);
+                    }
+                }
+            }
+            self.clock += 1;
+            self.lfo_phase = (self.lfo_phase + self.lfo_inc).fract();
+
+            let mut y = 0.0;
+            for v in self.voices.iter_mut().filter(|v| v.is_active()) {
+                y += v.process(
+                    &self.oscs,
+                    self.sync,
+                    self.noise,
+                    &self.envelopes[0],
+                    &self.envelopes[1],
+                );
+            }
+            out.fill(y * self.gain);
+        }
+    }
+}
+
+impl Node for Synth {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// Per oscillator: `Wave` 0 sine, 1 triangle, 2 saw, 3 square; its offset from the note
+    /// in semitones and cents; `Level`. The filter: `Cutoff`, `Resonance`, `Key track` (1
+    /// follows the note exactly, from middle C), `Env amount` (how far the filter envelope
+    /// sweeps the cutoff). Per envelope (amp, then filter): `Decay` and `Release` to -60 dB.
+    /// The LFO: `Rate`, `Wave` as for the oscillators (naive, it is a control signal), its
+    /// pitch and cutoff depths. Then `Polyphony` (lowering it releases nothing; voices above
+    /// it just finish), `Noise` into each voice's filter, `Level`, and `Sync`, which
+    /// hard-syncs oscillator 2 to oscillator 1 so its own pitch shapes the timbre.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Osc 1 wave", "", 0.0, 3.0, 2.0),
+        ParamDescriptor::linear("Osc 1 semitones", "st", -48.0, 48.0, 0.0),
+        ParamDescriptor::linear("Osc 1 cents", "ct", -100.0, 100.0, 0.0),
+        ParamDescriptor::linear("Osc 1 level", "", 0.0, 1.0, 1.0),
+        ParamDescriptor::stepped("Osc 2 wave", "", 0.0, 3.0, 3.0),
+        ParamDescriptor::linear("Osc 2 semitones", "st", -48.0, 48.0, -12.0),
+        ParamDescriptor::linear("Osc 2 cents", "ct", -100.0, 100.0, 7.0),
+        ParamDescriptor::linear("Osc 2 level", "", 0.0, 1.0, 0.5),
+        ParamDescriptor::log("Cutoff", "Hz", 20.0, 20_000.0, 1500.0),
+        ParamDescriptor::linear("Resonance", "", 0.0, 1.0, 0.2),
+        ParamDescriptor::linear("Key track", "", 0.0, 1.0, 0.5),
+        ParamDescriptor::linear("Env amount", "oct", -6.0, 6.0, 2.0),
+        ParamDescriptor::linear("Amp attack", "ms", 0.0, 10_000.0, 5.0),
+        ParamDescriptor::log("Amp decay", "ms", 1.0, 20_000.0, 300.0),
+        ParamDescriptor::linear("Amp sustain", "", 0.0, 1.0, 0.7),
+        ParamDescriptor::log("Amp release", "ms", 1.0, 20_000.0, 300.0),
+        ParamDescriptor::linear("Filter attack", "ms", 0.0, 10_000.0, 5.0),
+        ParamDescriptor::log("Filter decay", "ms", 1.0, 20_000.0, 500.0),
+        ParamDescriptor::linear("Filter sustain", "", 0.0, 1.0, 0.2),
+        ParamDescriptor::log("Filter release", "ms", 1.0, 20_000.0, 300.0),
+        ParamDescriptor::log("LFO rate", "Hz", 0.01, 50.0, 5.0),
+        ParamDescriptor::stepped("LFO wave", "", 0.0, 3.0, 0.0),
+        ParamDescriptor::linear("LFO pitch", "st", 0.0, 12.0, 0.0),
+        ParamDescriptor::linear("LFO cutoff", "oct", 0.0, 6.0, 0.0),
+        ParamDescriptor::stepped("Polyphony", "", 1.0, MAX_VOICES as f32, 8.0),
+        ParamDescriptor::linear("Noise", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Level", "dB", -60.0, 0.0, -18.0),
+        ParamDescriptor::toggle("Sync", false),
+    ];
+    const INPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let seed = 0x9e37_79b9_u32;
+        let adsr = [[5.0, 300.0, 0.7, 300.0], [5.0, 500.0, 0.2, 300.0]];
+        let mut s = Synth {
+            notes: EventRing::new(),
+            voices: core::array::from_fn(|i| {
+                Voice::new(seed.wrapping_mul(i as u32 + 1), sample_rate_hz)
+            }),
+            polyphony: 8,
+            oscs: [
+                OscParams {
+                    wave: Waveform::Saw,
+                    ratio: 1.0,
+                    level: 1.0,
+                },
+                OscParams {
+                    wave: Waveform::Square,
+                    ratio: 1.0,
+                    level: 0.5,
+                },
+            ],
+            tuning: [(0.0, 0.0), (-12.0, 7.0)],
+            sync: false,
+            noise: 0.0,
+            envelopes: [AdsrParams::new(0.0, 1.0, 0.0, 1.0, sample_rate_hz); 2],
+            adsr,
+            cutoff_hz: 1500.0,
+            resonance: 0.2,
+            damping: 1.6,
+            key_track: 0.5,
+            env_octaves: 2.0,
+            lfo_wave: Waveform::Sine,
+            lfo_rate_hz: 5.0,
+            lfo_inc: 5.0 / sample_rate_hz,
+            lfo_phase: 0.0,
+            lfo_semitones: 0.0,
+            lfo_octaves: 0.0,
+            bend: 0.0,
+            level_db: -18.0,
+            gain: db_to_lin(-18.0),
+            clock: 0,
+            started: 0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        for i in 0..2 {
+            s.update_ratio(i);
+            s.update_envelope(i);
+        }
+        s
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        let wave = || waveform(clamp(value, 0.0, 3.0).round() as u32);
+        match index {
+            PARAM_WAVE..PARAM_CUTOFF_HZ => {
+                let osc = (index / OSC_PARAMS) as usize;
+                match index % OSC_PARAMS {
+                    PARAM_WAVE => self.oscs[osc].wave = wave(),
+                    PARAM_SEMITONES => {
+                        self.tuning[osc].0 = clamp(value, -48.0, 48.0);
+                        self.update_ratio(osc);
+                    }
+                    PARAM_CENTS => {
+                        self.tuning[osc].1 = clamp(value, -100.0, 100.0);
+                        self.update_ratio(osc);
+                    }
+                    _ => self.oscs[osc].level = clamp(value, 0.0, 1.0),
+                }
+            }
+            PARAM_CUTOFF_HZ => self.cutoff_hz = clamp(value, 20.0, 20_000.0),
+            PARAM_RESONANCE => {
+                self.resonance = clamp(value, 0.0, 1.0);
+                self.damping = (2.0 * (1.0 - self.resonance)).max(MIN_DAMPING);
+            }
+            PARAM_KEY_TRACK => self.key_track = clamp(value, 0.0, 1.0),
+            PARAM_ENV_OCTAVES => self.env_octaves = clamp(value, -6.0, 6.0),
+            PARAM_ENVELOPES..PARAM_LFO_RATE_HZ => {
+                let offset = index - PARAM_ENVELOPES;
+                let envelope = (offset / ENVELOPE_PARAMS) as usize;
+                let stage = offset % ENVELOPE_PARAMS;
+                self.adsr[envelope][stage as usize] = match stage {
+                    PARAM_ATTACK_MS => clamp(value, 0.0, 10_000.0),
+                    PARAM_SUSTAIN => clamp(value, 0.0, 1.0),
+                    _ => clamp(value, 1.0, 20_000.0),
+                };
+                self.update_envelope(envelope);
+            }
+            PARAM_LFO_RATE_HZ => {
+                self.lfo_rate_hz = clamp(value, 0.01, 50.0);
+                self.lfo_inc = self.lfo_rate_hz / self.sample_rate_hz;
+            }
+            PARAM_LFO_WAVE => self.lfo_wave = wave(),
+            PARAM_LFO_SEMITONES => self.lfo_semitones = clamp(value, 0.0, 12.0),
+            PARAM_LFO_OCTAVES => self.lfo_octaves = clamp(value, 0.0, 6.0),
+            PARAM_POLYPHONY => {
+                self.polyphony = clamp(value, 1.0, MAX_VOICES as f32).round() as usize;
+            }
+            PARAM_NOISE => self.noise = clamp(value, 0.0, 1.0),
+            PARAM_LEVEL_DB => {
+                self.level_db = clamp(value, -60.0, 0.0);
+                self.gain = db_to_lin(self.level_db);
+            }
+            PARAM_SYNC => self.sync = clamp(value, 0.0, 1.0) >= 0.5,
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_WAVE..PARAM_CUTOFF_HZ => {
+                let osc = (index / OSC_PARAMS) as usize;
+                match index % OSC_PARAMS {
+                    PARAM_WAVE => self.oscs[osc].wave as u32 as f32,
+                    PARAM_SEMITONES => self.tuning[osc].0,
+                    PARAM_CENTS => self.tuning[osc].1,
+                    _ => self.oscs[osc].level,
+                }
+            }
+            PARAM_CUTOFF_HZ => self.cutoff_hz,
+            PARAM_RESONANCE => self.resonance,
+            PARAM_KEY_TRACK => self.key_track,
+            PARAM_ENV_OCTAVES => self.env_octaves,
+            PARAM_ENVELOPES..PARAM_LFO_RATE_HZ => {
+                let offset = index - PARAM_ENVELOPES;
+                self.adsr[(offset / ENVELOPE_PARAMS) as usize][(offset % ENVELOPE_PARAMS) as usize]
+            }
+            PARAM_LFO_RATE_HZ => self.lfo_rate_hz,
+            PARAM_LFO_WAVE => self.lfo_wave as u32 as f32,
+            PARAM_LFO_SEMITONES => self.lfo_semitones,
+            PARAM_LFO_OCTAVES => self.lfo_octaves,
+            PARAM_POLYPHONY => self.polyphony as f32,
+            PARAM_NOISE => self.noise,
+            PARAM_LEVEL_DB => self.level_db,
+            PARAM_SYNC => u32::from(self.sync) as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn note_events(&mut self) -> Option<&mut EventRing> {
+        Some(&mut self.notes)
+    }
+
+    fn note(&mut self, event: NoteEvent) {
+        self.handle(event);
+    }
+
+    /// Renders the same mono mix on every channel.
+    fn process(&mut self, _input: &[f32], output: &mut [f32], channels: usize) {
+        self.render(output, channels);
+    }
+
+    /// Silences every voice at once (no release tails) and restarts the LFO; queued events
+    /// are kept.
+    fn reset(&mut self) {
+        let seed = 0x9e37_79b9_u32;
+        let sample_rate_hz = self.sample_rate_hz;
+        self.voices =
+            core::array::from_fn(|i| Voice::new(seed.wrapping_mul(i as u32 + 1), sample_rate_hz));
+        self.lfo_phase = 0.0;
+        self.clock = 0;
+        self.started = 0;
+    }
+}
+
+node_abi::export_node!(Synth {
+    abi_version: synth_abi_version,
+    new: synth_new,
+    free: synth_free,
+    param_count: synth_param_count,
+    param_descriptors: synth_param_descriptors,
+    set_param: synth_set_param,
+    get_param: synth_get_param,
+    param_events: synth_param_events,
+    process: synth_process,
+    process_offline: synth_process_offline,
+    latency_samples: synth_latency_samples,
+    io_layout: synth_get_io_layout,
+    process_io: synth_process_io,
+    reset: synth_reset,
+    state_size: synth_state_size,
+    save_state: synth_save_state,
+    load_state: synth_load_state,
+    preset_size: synth_preset_size,
+    save_preset: synth_save_preset,
+    load_preset: synth_load_preset,
+    last_error_code: synth_last_error_code,
+    last_error_message: synth_last_error_message_ptr,
+});
+
+node_abi::test_params!(Synth);
+
+dsp_core::test_block_sizes!(
+    synth_new,
+    synth_process(input, output),
+    synth_free,
+    setup: |p| {
+        let events = unsafe { &mut *synth_events(p) };
+        events.push(NoteEvent { kind: NOTE_ON, note: 60, value: 1.0, frame: 0 });
+    },
+);
+
+node_abi::test_io_block!(
+    Synth,
+    setup: |s: &mut Synth| {
+        s.notes.push(NoteEvent { kind: NOTE_ON, note: 60, value: 1.0, frame: 0 });
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Synth as SynthNode);
+
+/// The note-event ring inside the synth, for the worklet to write into (layout in
+/// `dsp_core::events`). Stays put for the synth's lifetime.
+#[no_mangle]
+pub extern "C" fn synth_events(handle: Handle) -> *mut EventRing {
+    guard(handle, |s: &mut Synth| &mut s.notes as *mut EventRing).unwrap_or(core::ptr::null_mut())
+}
+
+/// Oscillator `index`'s parameters in one call (0 or 1), as `synth_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn synth_set_oscillator(
+    handle: Handle,
+    index: u32,
+    wave: u32,
+    semitones: f32,
+    cents: f32,
+    level: f32,
+) {
+    if index > 1 {
+        return;
+    }
+    guard(handle, |s: &mut Synth| {
+        let base = index * OSC_PARAMS;
+        s.set_param(base + PARAM_WAVE, wave as f32);
+        s.set_param(base + PARAM_SEMITONES, semitones);
+        s.set_param(base + PARAM_CENTS, cents);
+        s.set_param(base + PARAM_OSC_LEVEL, level);
+    });
+}
+
+/// `Cutoff`, `Resonance`, `Key track` and `Env amount` in one call.
+#[no_mangle]
+pub extern "C" fn synth_set_filter(
+    handle: Handle,
+    cutoff_hz: f32,
+    resonance: f32,
+    key_track: f32,
+    env_octaves: f32,
+) {
+    guard(handle, |s: &mut Synth| {
+        s.set_param(PARAM_CUTOFF_HZ, cutoff_hz);
+        s.set_param(PARAM_RESONANCE, resonance);
+        s.set_param(PARAM_KEY_TRACK, key_track);
+        s.set_param(PARAM_ENV_OCTAVES, env_octaves);
+    });
+}
+
+/// Envelope `index`'s parameters in one call (0 amp, 1 filter).
+#[no_mangle]
+pub extern "C" fn synth_set_envelope(
+    handle: Handle,
+    index: u32,
+    attack_ms: f32,
+    decay_ms: f32,
+    sustain: f32,
+    release_ms: f32,
+) {
+    if index > 1 {
+        return;
+    }
+    guard(handle, |s: &mut Synth| {
+        let base = PARAM_ENVELOPES + index * ENVELOPE_PARAMS;
+        s.set_param(base + PARAM_ATTACK_MS, attack_ms);
+        s.set_param(base + PARAM_DECAY_MS, decay_ms);
+        s.set_param(base + PARAM_SUSTAIN, sustain);
+        s.set_param(base + PARAM_RELEASE_MS, release_ms);
+    });
+}
+
+/// The LFO's parameters in one call.
+#[no_mangle]
+pub extern "C" fn synth_set_lfo(
+    handle: Handle,
+    rate_hz: f32,
+    wave: u32,
+    pitch_semitones: f32,
+    cutoff_octaves: f32,
+) {
+    guard(handle, |s: &mut Synth| {
+        s.set_param(PARAM_LFO_RATE_HZ, rate_hz);
+        s.set_param(PARAM_LFO_WAVE, wave as f32);
+        s.set_param(PARAM_LFO_SEMITONES, pitch_semitones);
+        s.set_param(PARAM_LFO_OCTAVES, cutoff_octaves);
+    });
+}
+
+/// `Polyphony`, `Noise` and `Level` in one call.
+#[no_mangle]
+pub extern "C" fn synth_set_params(handle: Handle, polyphony: u32, noise: f32, level_db: f32) {
+    guard(handle, |s: &mut Synth| {
+        s.set_param(PARAM_POLYPHONY, polyphony as f32);
+        s.set_param(PARAM_NOISE, noise);
+        s.set_param(PARAM_LEVEL_DB, level_db);
+    });
+}
+
+/// `Sync`, as `synth_set_param` sets it.
+#[no_mangle]
+pub extern "C" fn synth_set_sync(handle: Handle, sync: u32) {
+    guard(handle, |s: &mut Synth| s.set_param(PARAM_SYNC, sync as f32));
+}
+
+/// Voices currently sounding (held or releasing), for the UI.
+#[no_mangle]
+pub extern "C" fn synth_active_voices(handle: Handle) -> u32 {
+    guard(handle, |s: &mut Synth| {
+        s.voices.iter().filter(|v| v.is_active()).count() as u32
+    })
+    .unwrap_or(0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// A synth with oscillator 2 muted, so oscillator 1 alone sets the pitch.
+    fn synth(polyphony: u32) -> Handle {
+        let h = synth_new(RATE);
+        synth_set_param(h, OSC_PARAMS + PARAM_OSC_LEVEL, 0.0);
+        synth_set_param(h, PARAM_POLYPHONY, polyphony as f32);
+        h
+    }
+
+    fn send(h: Handle, kind: u32, note: u32, value: f32) {
+        let events = unsafe { &mut *synth_events(h) };
+        assert!(events.push(NoteEvent {
+            kind,
+            note,
+            value,
+            frame: 0,
+        }));
+    }
+
+    /// Plays `notes` at full velocity, one render quantum apart.
+    fn hold(h: Handle, notes: &[u32]) {
+        for &note in notes {
+            send(h, NOTE_ON, note, 1.0);
+            render(h, 128);
+        }
+    }
+
+    fn render(h: Handle, frames: usize) -> Vec<f32> {
+        render_quanta(frames, |_, y| {
+            synth_process(h, core::ptr::null(), y.as_mut_ptr(), y.len(), 1)
+        })
+    }
+
+    /// Each voice's note while it sounds, by voice index.
+    fn voices(h: Handle) -> Vec<Option<u8>> {
+        guard(h, |s: &mut Synth| {
+            s.voices
+                .iter()
+                .map(|v| v.is_active().then_some(v.note))
+                .collect()
+        })
+        .unwrap()
+    }
+
+    /// The strongest frequency between 100 Hz and 1 kHz, to the nearest hertz.
+    fn fundamental_hz(signal: &[f32]) -> f32 {
+        let magnitude = |hz: f32| {
+            let w = core::f32::consts::TAU * hz / RATE;
+            let (re, im) = signal
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(re, im), (i, x)| {
+                    let (sin, cos) = (w * i as f32).sin_cos();
+                    (re + x * cos, im + x * sin)
+                });
+            re * re + im * im
+        };
+        (100..=1000)
+            .map(|hz| hz as f32)
+            .max_by(|&a, &b| magnitude(a).total_cmp(&magnitude(b)))
+            .unwrap()
+    }
+
+    #[test]
+    fn note_on_steals_in_order() {
+        let h = synth(4);
+        hold(h, &[60, 62, 64]);
+        // The voice already playing the note.
+        hold(h, &[62]);
+        assert_eq!(voices(h)[..4], [Some(60), Some(62), Some(64), None]);
+        // Then an idle one.
+        hold(h, &[65]);
+        assert_eq!(synth_active_voices(h), 4);
+
+        // Then the quietest released one: 62 has had longer to fade than 64.
+        send(h, NOTE_OFF, 62, 0.0);
+        render(h, 4_800);
+        send(h, NOTE_OFF, 64, 0.0);
+        render(h, 480);
+        hold(h, &[67]);
+        assert_eq!(voices(h)[..4], [Some(60), Some(67), Some(64), Some(65)]);
+
+        // 69 takes the last released voice (64); with none left, 71 takes the oldest held (60).
+        hold(h, &[69, 71]);
+        assert_eq!(voices(h)[..4], [Some(71), Some(67), Some(69), Some(65)]);
+        synth_free(h);
+    }
+
+    #[test]
+    fn polyphony_caps_the_voice_pool() {
+        let h = synth(4);
+        hold(h, &[60, 61, 62, 63, 64, 65]);
+        assert_eq!(synth_active_voices(h), 4);
+        assert!(voices(h)[4..].iter().all(Option::is_none));
+
+        synth_set_param(h, PARAM_POLYPHONY, 6.0);
+        hold(h, &[66, 67, 68]);
+        assert_eq!(synth_active_voices(h), 6);
+        assert_eq!(voices(h)[4..7], [Some(66), Some(67), None]);
+        synth_free(h);
+    }
+
+    #[test]
+    fn velocity_zero_and_all_notes_off_release_voices() {
+        let h = synth(8);
+        hold(h, &[60, 64]);
+        send(h, NOTE_ON, 60, 0.0);
+        render(h, 128);
+        let gates = guard(h, |s: &mut Synth| [s.voices[0].gate, s.voices[1].gate]).unwrap();
+        assert_eq!(gates, [false, true]);
+
+        hold(h, &[67, 71]);
+        send(h, ALL_NOTES_OFF, 0, 0.0);
+        render(h, 128);
+        assert!(guard(h, |s: &mut Synth| s.voices.iter().all(|v| !v.gate)).unwrap());
+        // The 300 ms release runs out, and the voices with it.
+        render(h, 48_000);
+        assert_eq!(synth_active_voices(h), 0);
+        assert!(render(h, 128).iter().all(|&y| y == 0.0));
+        synth_free(h);
+    }
+
+    #[test]
+    fn a4_sounds_at_440_hz() {
+        let h = synth(8);
+        hold(h, &[69]);
+        render(h, 4_800);
+        assert_eq!(fundamental_hz(&render(h, 24_000)), 440.0);
+        synth_free(h);
+    }
+
+    #[test]
+    fn pitch_bend_moves_held_notes() {
+        let h = synth(8);
+        hold(h, &[57]);
+        send(h, PITCH_BEND, 0, 12.0);
+        render(h, 4_800);
+        assert_eq!(fundamental_hz(&render(h, 24_000)), 440.0);
+        synth_free(h);
+    }
+}
@@ -0,0 +1,99 @@
+use dsp_core::envelope::{Adsr, AdsrParams};
+use dsp_core::oscillator::{Oscillator, Waveform};
+use dsp_core::svf::{Coeffs, SvfState};
+
+#[derive(Clone, Copy)]
+pub struct OscParams {
+    pub wave: Waveform,
+    /// Frequency relative to the note (from the octave/semitone and fine offsets).
+    pub ratio: f32,
+    pub level: f32,
+}
+
+/// One synth voice: two oscillators (the second optionally hard-synced to the first) and noise
+/// into a low-pass SVF, shaped by the amp envelope. Pitch and cutoff are set at control rate by
+/// the synth; everything else runs per sample.
+#[derive(Clone, Copy)]
+pub struct Voice {
+    pub note: u8,
+    pub velocity: f32,
+    pub gate: bool,
+    /// Note-on order, for stealing the oldest voice.
+    pub age: u64,
+    pub amp: Adsr,
+    pub filter_env: Adsr,
+    pub oscs: [Oscillator; 2],
+    pub coeffs: Coeffs,
+    filter: SvfState,
+    rng: u32,
+}
+
+impl Voice {
+    pub fn new(seed: u32, sample_rate_hz: f32) -> Self {
+        Self {
+            note: 0,
+            velocity: 0.0,
+            gate: false,
+            age: 0,
+            amp: Adsr::new(),
+            filter_env: Adsr::new(),
+            oscs: [Oscillator::new(); 2],
+            coeffs: Coeffs::new(1000.0, 1.0, sample_rate_hz),
+            filter: SvfState::default(),
+            rng: seed,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.amp.is_idle()
+    }
+
+    pub fn start(&mut self, note: u8, velocity: f32, age: u64) {
+        if !self.is_active() {
+            // A silent voice can start clean; a sounding one keeps its phases and filter
+            // state so the handover is smooth.
+            self.oscs.iter_mut().for_each(|o| o.reset(0.0));
+            self.filter.reset();
+        }
+        self.note = note;
+        self.velocity = velocity;
+        self.gate = true;
+        self.age = age;
+        self.amp.gate_on();
+        self.filter_env.gate_on();
+    }
+
+    pub fn release(&mut self) {
+        self.gate = false;
+        self.amp.gate_off();
+        self.filter_env.gate_off();
+    }
+
+    /// Uniform in [-1, 1) from a xorshift32.
+    #[inline]
+    fn noise(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 7) as f32 / (1u32 << 24) as f32 - 1.0
+    }
+
+    #[inline]
+    pub fn process(
+        &mut self,
+        oscs: &[OscParams; 2],
+        sync: bool,
+        noise: f32,
+        amp: &AdsrParams,
+        filter_env: &AdsrParams,
+    ) -> f32 {
+        self.filter_env.process(filter_env);
+        let mut x = self.noise() * noise;
+        let [one, two] = &mut self.oscs;
+        x += one.next(oscs[0].wave) * oscs[0].level;
+        let master = if sync { one.wrapped() } else { None };
+        x += two.next_synced(oscs[1].wave, master) * oscs[1].level;
+        let (low, _, _) = self.filter.process(x, &self.coeffs);
+        low * self.amp.process(amp) * self.velocity
+    }
+}
@@ -0,0 +1,359 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::f32::consts::TAU;
+use dsp_core::biquad::{Biquad, Response};
+use dsp_core::delay::DelayLine;
+use dsp_core::gain::db_to_lin;
+use dsp_core::halfband::HalfbandOversampler;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing, FLAG_NO_AUTOMATION};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Tape::PARAMS`, in the order of `tape_set_params`' arguments.
+pub const PARAM_DRIVE_DB: u32 = 0;
+pub const PARAM_BIAS: u32 = 1;
+pub const PARAM_WOW: u32 = 2;
+pub const PARAM_FLUTTER: u32 = 3;
+pub const PARAM_OUTPUT_DB: u32 = 4;
+pub const PARAM_OVERSAMPLE: u32 = 5;
+
+/// Hysteresis width (in driven-signal units) with no bias at all.
+const MAX_HYSTERESIS: f32 = 0.2;
+/// Roll-off corner at 0 dB drive; it falls as the drive goes up.
+const ROLLOFF_HZ: f32 = 18_000.0;
+const WOW_MS: f32 = 1.2;
+const WOW_HZ: f32 = 0.6;
+const FLUTTER_MS: f32 = 0.1;
+const FLUTTER_HZ: f32 = 7.0;
+/// Fixed part of the transport delay, so the wobble never reaches back past "now".
+const TRANSPORT_MS: f32 = WOW_MS + FLUTTER_MS + 0.1;
+
+/// Tape saturation: the driven signal goes through a play (backlash) operator, whose width
+/// shrinks as `bias` rises, and then a `tanh` curve, both oversampled. The play operator gives
+/// the loop its memory: after a reversal the output stalls until the input has moved back
+/// across the width, the crossover smear of under-biased tape. Afterwards a low-pass whose
+/// corner falls with drive stands in for self-erasure, and a modulated delay adds wow and
+/// flutter.
+#[repr(C)]
+pub struct Tape {
+    drive_db: f32,
+    drive: f32,
+    bias: f32,
+    width: f32,
+    output_db: f32,
+    output_gain: f32,
+    wow: f32,
+    flutter: f32,
+    /// Wobble LFO positions in cycles, shared by every channel (one tape transport).
+    wow_phase: f32,
+    flutter_phase: f32,
+    play: [f32; MAX_CHANNELS],
+    oversamplers: Vec<HalfbandOversampler>,
+    rolloff: [Biquad; MAX_CHANNELS],
+    transport: Vec<DelayLine>,
+    /// Centre of the transport delay in samples.
+    transport_delay: f32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Tape {
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let sr = self.sample_rate_hz;
+        let ms = sr / 1000.0;
+        let width = self.width;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            let wobble = self.wow * WOW_MS * ms * (TAU * self.wow_phase).sin()
+                + self.flutter * FLUTTER_MS * ms * (TAU * self.flutter_phase).sin();
+            self.wow_phase = (self.wow_phase + WOW_HZ / sr).fract();
+            self.flutter_phase = (self.flutter_phase + FLUTTER_HZ / sr).fract();
+
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let play = &mut self.play[ch];
+                let v = self.oversamplers[ch].process(*x * self.drive, |h| {
+                    *play = play.clamp(h - width, h + width);
+                    play.tanh()
+                });
+                let v = self.rolloff[ch].process(v);
+                let line = &mut self.transport[ch];
+                line.push(v);
+                *y = line.read_cubic(self.transport_delay + wobble) * self.output_gain;
+            }
+        }
+    }
+
+    fn update_rolloff(&mut self) {
+        let corner = (ROLLOFF_HZ / (1.0 + self.drive_db / 12.0)).min(self.sample_rate_hz * 0.45);
+        for r in &mut self.rolloff {
+            r.set(Response::Lowpass, corner, 0.6, self.sample_rate_hz);
+        }
+    }
+}
+
+impl Node for Tape {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Drive` into the tape (the high end rolls off further as it rises); `Bias` 1 is clean,
+    /// lower widens the hysteresis; `Wow` and `Flutter` depths; `Output` gain; `Oversample` 1,
+    /// 2 or 4 (see `latency_samples`).
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Drive", "dB", 0.0, 24.0, 0.0),
+        ParamDescriptor::linear("Bias", "", 0.0, 1.0, 0.7),
+        ParamDescriptor::linear("Wow", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Flutter", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Output", "dB", -24.0, 12.0, 0.0),
+        ParamDescriptor::stepped("Oversample", "x", 1.0, 4.0, 2.0).with_flags(FLAG_NO_AUTOMATION),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let ms = sample_rate_hz / 1000.0;
+        let transport_delay = (TRANSPORT_MS * ms).ceil();
+        let mut t = Tape {
+            drive_db: 0.0,
+            drive: 1.0,
+            bias: 0.7,
+            width: MAX_HYSTERESIS * 0.3,
+            output_db: 0.0,
+            output_gain: 1.0,
+            wow: 0.0,
+            flutter: 0.0,
+            wow_phase: 0.0,
+            flutter_phase: 0.0,
+            play: [0.0; MAX_CHANNELS],
+            oversamplers: (0..MAX_CHANNELS)
+                .map(|_| HalfbandOversampler::new(2))
+                .collect(),
+            rolloff: [Biquad::default(); MAX_CHANNELS],
+            transport: (0..MAX_CHANNELS)
+                .map(|_| DelayLine::new(2 * transport_delay as usize))
+                .collect(),
+            transport_delay,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        t.update_rolloff();
+        t
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_DRIVE_DB => {
+                self.drive_db = clamp(value, 0.0, 24.0);
+                self.drive = db_to_lin(self.drive_db);
+                self.update_rolloff();
+            }
+            PARAM_BIAS => {
+                self.bias = clamp(value, 0.0, 1.0);
+                self.width = MAX_HYSTERESIS * (1.0 - self.bias);
+            }
+            PARAM_WOW => self.wow = clamp(value, 0.0, 1.0),
+            PARAM_FLUTTER => self.flutter = clamp(value, 0.0, 1.0),
+            PARAM_OUTPUT_DB => {
+                self.output_db = clamp(value, -24.0, 12.0);
+                self.output_gain = db_to_lin(self.output_db);
+            }
+            PARAM_OVERSAMPLE => {
+                let factor = clamp(value, 1.0, 4.0).round() as usize;
+                for os in &mut self.oversamplers {
+                    os.set_factor(factor);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_DRIVE_DB => self.drive_db,
+            PARAM_BIAS => self.bias,
+            PARAM_WOW => self.wow,
+            PARAM_FLUTTER => self.flutter,
+            PARAM_OUTPUT_DB => self.output_db,
+            PARAM_OVERSAMPLE => self.oversamplers[0].factor() as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Oversampling filter delay plus the centre of the wow/flutter delay line.
+    fn latency_samples(&self) -> u32 {
+        (self.oversamplers[0].latency() + self.transport_delay as usize) as u32
+    }
+
+    /// Clears the hysteresis, the oversamplers, the roll-off filters and the transport delay,
+    /// and restarts the wobble.
+    fn reset(&mut self) {
+        self.wow_phase = 0.0;
+        self.flutter_phase = 0.0;
+        self.play = [0.0; MAX_CHANNELS];
+        for os in &mut self.oversamplers {
+            os.clear();
+        }
+        for f in &mut self.rolloff {
+            f.clear();
+        }
+        for line in &mut self.transport {
+            line.clear();
+        }
+    }
+}
+
+node_abi::export_node!(Tape {
+    abi_version: tape_abi_version,
+    new: tape_new,
+    free: tape_free,
+    param_count: tape_param_count,
+    param_descriptors: tape_param_descriptors,
+    set_param: tape_set_param,
+    get_param: tape_get_param,
+    param_events: tape_param_events,
+    process: tape_process,
+    process_offline: tape_process_offline,
+    latency_samples: tape_latency_samples,
+    io_layout: tape_get_io_layout,
+    process_io: tape_process_io,
+    reset: tape_reset,
+    state_size: tape_state_size,
+    save_state: tape_save_state,
+    load_state: tape_load_state,
+    preset_size: tape_preset_size,
+    save_preset: tape_save_preset,
+    load_preset: tape_load_preset,
+    last_error_code: tape_last_error_code,
+    last_error_message: tape_last_error_message_ptr,
+});
+
+node_abi::test_params!(Tape);
+
+dsp_core::test_block_sizes!(tape_new, tape_process(input, output), tape_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Tape as TapeNode);
+
+/// Every parameter in one call, as `tape_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn tape_set_params(
+    handle: Handle,
+    drive_db: f32,
+    bias: f32,
+    wow: f32,
+    flutter: f32,
+    output_db: f32,
+    oversample: u32,
+) {
+    guard(handle, |t: &mut Tape| {
+        t.set_param(PARAM_DRIVE_DB, drive_db);
+        t.set_param(PARAM_BIAS, bias);
+        t.set_param(PARAM_WOW, wow);
+        t.set_param(PARAM_FLUTTER, flutter);
+        t.set_param(PARAM_OUTPUT_DB, output_db);
+        t.set_param(PARAM_OVERSAMPLE, oversample as f32);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    /// Tape without oversampling or hysteresis, then `params`.
+    fn tape(params: &[(u32, f32)]) -> Tape {
+        let mut t = Tape::new(RATE);
+        t.set_param(PARAM_OVERSAMPLE, 1.0);
+        t.set_param(PARAM_BIAS, 1.0);
+        for &(index, value) in params {
+            t.set_param(index, value);
+        }
+        t
+    }
+
+    fn render(t: &mut Tape, input: &[f32]) -> Vec<f32> {
+        render_quanta(input.len(), |span, y| Node::process(t, &input[span], y, 1))
+    }
+
+    /// 1.5 s of a sine at `hz` and `level`.
+    fn sine(hz: f32, level: f32) -> Vec<f32> {
+        let w = TAU * hz / RATE;
+        (0..72_000).map(|i| level * (w * i as f32).sin()).collect()
+    }
+
+    /// Gain of `t` for a sine at `hz` and `level`.
+    fn gain(params: &[(u32, f32)], hz: f32, level: f32) -> f32 {
+        amplitude(&render(&mut tape(params), &sine(hz, level)), hz) / level
+    }
+
+    /// Driven hard, loud signals get less gain than quiet ones.
+    #[test]
+    fn drive_compresses_softly() {
+        let drive = [(PARAM_DRIVE_DB, 12.0)];
+        let quiet = gain(&drive, 100.0, 0.01);
+        assert!((quiet - db_to_lin(12.0)).abs() < 0.02 * quiet);
+        assert!(gain(&drive, 100.0, 1.0) < 0.5 * quiet);
+    }
+
+    /// The roll-off corner falls as the drive rises: 18 kHz at 0 dB, 6 kHz at 24 dB.
+    #[test]
+    fn drive_rolls_off_the_top() {
+        let top = |drive_db: f32| {
+            let drive = [(PARAM_DRIVE_DB, drive_db)];
+            gain(&drive, 10_000.0, 0.001) / gain(&drive, 100.0, 0.001)
+        };
+        assert!(top(0.0) > 0.9);
+        assert!(top(24.0) < 0.5);
+    }
+
+    /// Under-biased tape stalls until the input moves across the hysteresis width, so a
+    /// signal smaller than it never gets through; full bias passes it.
+    #[test]
+    fn low_bias_stalls_small_signals() {
+        let x = sine(100.0, 0.05);
+        assert!(render(&mut tape(&[(PARAM_BIAS, 0.0)]), &x)
+            .iter()
+            .all(|&v| v == 0.0));
+        assert!(amplitude(&render(&mut tape(&[]), &x), 100.0) > 0.045);
+    }
+
+    /// A click comes out after the reported latency; `Wow` stretches that by up to 1.2 ms,
+    /// most a quarter of the way through its cycle.
+    #[test]
+    fn wow_wobbles_the_transport_delay() {
+        let mut x = vec![0.0; 20_400];
+        x[20_000] = 1.0;
+        let arrival = |params: &[(u32, f32)]| {
+            let y = render(&mut tape(params), &x);
+            let peak = y.iter().map(|v| v.abs()).fold(0.0, f32::max);
+            y.iter().position(|v| v.abs() == peak).unwrap() - 20_000
+        };
+        let steady = arrival(&[]);
+        assert!((0..=1).contains(&(steady - Node::latency_samples(&tape(&[])) as usize)));
+        let wobbled = arrival(&[(PARAM_WOW, 1.0)]);
+        assert!((57..=59).contains(&(wobbled - steady)), "{wobbled}");
+    }
+}
@@ -0,0 +1,210 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::gain::db_to_lin;
+use dsp_core::oscillator::{Oscillator, Waveform};
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `TestTone::PARAMS`, in the order of `tone_set_params`' arguments.
+pub const PARAM_WAVE: u32 = 0;
+pub const PARAM_FREQ_HZ: u32 = 1;
+pub const PARAM_LEVEL_DB: u32 = 2;
+
+/// Test tone: one band-limited classic waveform (the synths' oscillator) at a set frequency
+/// and level, the same on every channel.
+pub struct TestTone {
+    osc: Oscillator,
+    wave: Waveform,
+    freq_hz: f32,
+    level_db: f32,
+    gain: f32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl TestTone {
+    fn render(&mut self, output: &mut [f32], channels: usize) {
+        for out in output.chunks_exact_mut(channels) {
+            out.fill(self.osc.next(self.wave) * self.gain);
+        }
+    }
+}
+
+impl Node for TestTone {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Wave` 0 sine, 1 triangle, 2 saw, 3 square; `Frequency` is held below Nyquist.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Wave", "", 0.0, 3.0, 0.0),
+        ParamDescriptor::log("Frequency", "Hz", 1.0, 20_000.0, 440.0),
+        ParamDescriptor::linear("Level", "dB", -96.0, 0.0, -18.0),
+    ];
+    const INPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut osc = Oscillator::new();
+        osc.set_frequency(440.0, sample_rate_hz);
+        TestTone {
+            osc,
+            wave: Waveform::Sine,
+            freq_hz: 440.0,
+            level_db: -18.0,
+            gain: db_to_lin(-18.0),
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_WAVE => {
+                self.wave = match clamp(value, 0.0, 3.0).round() as u32 {
+                    1 => Waveform::Triangle,
+                    2 => Waveform::Saw,
+                    3 => Waveform::Square,
+                    _ => Waveform::Sine,
+                };
+            }
+            PARAM_FREQ_HZ => {
+                self.freq_hz = clamp(value, 1.0, 20_000.0);
+                self.osc.set_frequency(self.freq_hz, self.sample_rate_hz);
+            }
+            PARAM_LEVEL_DB => {
+                self.level_db = clamp(value, -96.0, 0.0);
+                self.gain = db_to_lin(self.level_db);
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_WAVE => self.wave as u32 as f32,
+            PARAM_FREQ_HZ => self.freq_hz,
+            PARAM_LEVEL_DB => self.level_db,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, _input: &[f32], output: &mut [f32], channels: usize) {
+        self.render(output, channels);
+    }
+
+    /// Restarts the oscillator from phase 0.
+    fn reset(&mut self) {
+        self.osc.reset(0.0);
+    }
+}
+
+node_abi::export_node!(TestTone {
+    abi_version: tone_abi_version,
+    new: tone_new,
+    free: tone_free,
+    param_count: tone_param_count,
+    param_descriptors: tone_param_descriptors,
+    set_param: tone_set_param,
+    get_param: tone_get_param,
+    param_events: tone_param_events,
+    process: tone_process,
+    process_offline: tone_process_offline,
+    latency_samples: tone_latency_samples,
+    io_layout: tone_get_io_layout,
+    process_io: tone_process_io,
+    reset: tone_reset,
+    state_size: tone_state_size,
+    save_state: tone_save_state,
+    load_state: tone_load_state,
+    preset_size: tone_preset_size,
+    save_preset: tone_save_preset,
+    load_preset: tone_load_preset,
+    last_error_code: tone_last_error_code,
+    last_error_message: tone_last_error_message_ptr,
+});
+
+node_abi::test_params!(TestTone);
+
+dsp_core::test_block_sizes!(tone_new, tone_process(input, output), tone_free);
+
+node_abi::test_io_block!(TestTone);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(TestTone as TestToneNode);
+
+/// Every parameter in one call, as `tone_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn tone_set_params(handle: Handle, wave: u32, freq_hz: f32, level_db: f32) {
+    guard(handle, |t: &mut TestTone| {
+        t.set_param(PARAM_WAVE, wave as f32);
+        t.set_param(PARAM_FREQ_HZ, freq_hz);
+        t.set_param(PARAM_LEVEL_DB, level_db);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+    use dsp_core::blocksize::amplitude;
+
+    const RATE: f32 = 48_000.0;
+
+    /// One second of `wave` at 1 kHz and 0 dB, stereo in render quanta.
+    fn render(wave: f32) -> Vec<f32> {
+        let h = tone_new(RATE);
+        tone_set_params(h, wave as u32, 1000.0, 0.0);
+        let mut output = vec![0.0; 96_000];
+        for y in output.chunks_mut(256) {
+            tone_process(h, core::ptr::null(), y.as_mut_ptr(), y.len() / 2, 2);
+        }
+        tone_free(h);
+        output
+    }
+
+    /// Each wave has its textbook harmonics at the set frequency and level, on every channel.
+    #[test]
+    fn waves_have_their_harmonics() {
+        // Fundamental and third harmonic of each wave at unit amplitude.
+        let expected = [
+            (1.0, 0.0),
+            (8.0 / (PI * PI), 8.0 / (9.0 * PI * PI)),
+            (2.0 / PI, 2.0 / (3.0 * PI)),
+            (4.0 / PI, 4.0 / (3.0 * PI)),
+        ];
+        for (wave, (first, third)) in expected.into_iter().enumerate() {
+            let y = render(wave as f32);
+            assert!(y.chunks_exact(2).all(|f| f[0] == f[1]));
+            let left: Vec<f32> = y.iter().step_by(2).copied().collect();
+            let (a, b) = (amplitude(&left, 1000.0), amplitude(&left, 3000.0));
+            assert!((a - first).abs() < 0.01, "wave {wave}: {a}");
+            assert!((b - third).abs() < 0.01, "wave {wave}: {b}");
+        }
+    }
+
+    /// `Level` scales the output in dB.
+    #[test]
+    fn level_scales_the_output() {
+        let h = tone_new(RATE);
+        tone_set_params(h, 3, 1000.0, -20.0);
+        let mut output = vec![0.0; 128];
+        tone_process(h, core::ptr::null(), output.as_mut_ptr(), 128, 1);
+        tone_free(h);
+        // Away from the edges the band-limited square sits at its level.
+        assert!((output[10] - 0.1).abs() < 1e-4, "{}", output[10]);
+    }
+}
@@ -0,0 +1,246 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::biquad::{Biquad, Response};
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Tilt::PARAMS`, in the order of `tilt_set_params`' arguments.
+pub const PARAM_PIVOT_HZ: u32 = 0;
+pub const PARAM_TILT_DB_PER_OCT: u32 = 1;
+
+/// Shelves per side of the pivot, one octave apart.
+const MAX_SHELVES: usize = 6;
+const SHELF_Q: f32 = 0.5;
+const MIN_HZ: f32 = 20.0;
+const MAX_HZ: f32 = 20_000.0;
+
+/// Spectral tilt around a pivot: low shelves below it and high shelves above, spaced an octave
+/// apart and each worth `tilt_db_per_oct`, so the response leans at roughly that slope between
+/// 20 Hz and 20 kHz (flattening outside) while the pivot itself stays at unity.
+#[repr(C)]
+pub struct Tilt {
+    pivot_hz: f32,
+    tilt_db_per_oct: f32,
+    shelves: [[Biquad; 2 * MAX_SHELVES]; MAX_CHANNELS],
+    shelf_count: usize,
+    /// Undoes whatever the shelves leave at the pivot.
+    trim: f32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Tilt {
+    fn update(&mut self) {
+        let sr = self.sample_rate_hz;
+        let top = MAX_HZ.min(sr * 0.45);
+        let mut shelves = [(Response::LowShelf, 0.0, 0.0); 2 * MAX_SHELVES];
+        let mut count = 0;
+        for k in 0..MAX_SHELVES {
+            let offset = (k as f32 + 0.5).exp2();
+            let low = self.pivot_hz / offset;
+            if low >= MIN_HZ {
+                shelves[count] = (Response::LowShelf, low, -self.tilt_db_per_oct);
+                count += 1;
+            }
+            let high = self.pivot_hz * offset;
+            if high <= top {
+                shelves[count] = (Response::HighShelf, high, self.tilt_db_per_oct);
+                count += 1;
+            }
+        }
+
+        // Coefficients change in place so the tilt can be swept; newly used slots start clean.
+        for ch in &mut self.shelves {
+            for (i, (b, &(response, freq, gain))) in ch.iter_mut().zip(&shelves).enumerate() {
+                if i >= count {
+                    break;
+                }
+                if i >= self.shelf_count {
+                    b.clear();
+                }
+                b.set_with_gain(response, freq, SHELF_Q, gain, sr);
+            }
+        }
+        self.shelf_count = count;
+
+        let at_pivot: f32 = self.shelves[0][..count]
+            .iter()
+            .map(|b| b.magnitude_at(self.pivot_hz, sr))
+            .product();
+        self.trim = 1.0 / at_pivot.max(1e-6);
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let count = self.shelf_count;
+        let trim = self.trim;
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            for ((x, y), shelves) in inp.iter().zip(out.iter_mut()).zip(&mut self.shelves) {
+                let mut v = *x;
+                for s in &mut shelves[..count] {
+                    v = s.process(v);
+                }
+                *y = v * trim;
+            }
+        }
+    }
+}
+
+impl Node for Tilt {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Pivot` is the frequency left untouched; a positive `Tilt` brightens (highs up, lows
+    /// down), a negative one darkens.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::log("Pivot", "Hz", 100.0, 5000.0, 1000.0),
+        ParamDescriptor::linear("Tilt", "dB/oct", -6.0, 6.0, 0.0),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut t = Tilt {
+            pivot_hz: 1000.0,
+            tilt_db_per_oct: 0.0,
+            shelves: [[Biquad::default(); 2 * MAX_SHELVES]; MAX_CHANNELS],
+            shelf_count: 0,
+            trim: 1.0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        t.update();
+        t
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_PIVOT_HZ => self.pivot_hz = clamp(value, 100.0, 5000.0),
+            PARAM_TILT_DB_PER_OCT => self.tilt_db_per_oct = clamp(value, -6.0, 6.0),
+            _ => return,
+        }
+        self.update();
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_PIVOT_HZ => self.pivot_hz,
+            PARAM_TILT_DB_PER_OCT => self.tilt_db_per_oct,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Clears the shelf filters.
+    fn reset(&mut self) {
+        for f in self.shelves.iter_mut().flatten() {
+            f.clear();
+        }
+    }
+}
+
+node_abi::export_node!(Tilt {
+    abi_version: tilt_abi_version,
+    new: tilt_new,
+    free: tilt_free,
+    param_count: tilt_param_count,
+    param_descriptors: tilt_param_descriptors,
+    set_param: tilt_set_param,
+    get_param: tilt_get_param,
+    param_events: tilt_param_events,
+    process: tilt_process,
+    process_offline: tilt_process_offline,
+    latency_samples: tilt_latency_samples,
+    io_layout: tilt_get_io_layout,
+    process_io: tilt_process_io,
+    reset: tilt_reset,
+    state_size: tilt_state_size,
+    save_state: tilt_save_state,
+    load_state: tilt_load_state,
+    preset_size: tilt_preset_size,
+    save_preset: tilt_save_preset,
+    load_preset: tilt_load_preset,
+    last_error_code: tilt_last_error_code,
+    last_error_message: tilt_last_error_message_ptr,
+});
+
+node_abi::test_params!(Tilt);
+
+dsp_core::test_block_sizes!(tilt_new, tilt_process(input, output), tilt_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Tilt as TiltNode);
+
+/// Every parameter in one call, as `tilt_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn tilt_set_params(handle: Handle, pivot_hz: f32, tilt_db_per_oct: f32) {
+    guard(handle, |t: &mut Tilt| {
+        t.set_param(PARAM_PIVOT_HZ, pivot_hz);
+        t.set_param(PARAM_TILT_DB_PER_OCT, tilt_db_per_oct);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// Settled gain in dB on a `hz` sine, mono in render quanta.
+    fn gain_db_at(tilt_db_per_oct: f32, hz: f32) -> f32 {
+        let mut t = Tilt::new(RATE);
+        t.set_param(PARAM_TILT_DB_PER_OCT, tilt_db_per_oct);
+        let w = core::f32::consts::TAU * hz / RATE;
+        let input: Vec<f32> = (0..48_000).map(|i| (w * i as f32).sin()).collect();
+        let output = render_quanta(input.len(), |span, y| {
+            Node::process(&mut t, &input[span], y, 1)
+        });
+        let peak = output[24_000..].iter().fold(0.0_f32, |m, y| m.max(y.abs()));
+        20.0 * peak.log10()
+    }
+
+    /// The response leans by about `Tilt` per octave either side of the pivot, which stays at
+    /// unity.
+    #[test]
+    fn leans_around_the_pivot() {
+        for tilt in [3.0, -4.5] {
+            assert!(gain_db_at(tilt, 1_000.0).abs() < 0.05);
+            for octaves in [-2.0, -1.0, 1.0, 2.0_f32] {
+                let got = gain_db_at(tilt, 1_000.0 * octaves.exp2());
+                let want = tilt * octaves;
+                assert!(
+                    (got - want).abs() < 0.25 * want.abs(),
+                    "{tilt} dB/oct, {octaves} oct: {got}"
+                );
+            }
+        }
+    }
+
+    /// No tilt is no change at all.
+    #[test]
+    fn zero_tilt_is_flat() {
+        for hz in [50.0, 1_000.0, 12_000.0] {
+            assert!(gain_db_at(0.0, hz).abs() < 0.01);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::envelope::EnvelopeFollower;
+use dsp_core::gain::{db_to_lin, lin_to_db_floor};
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `TransientShaper::PARAMS`, in the order of `transient_set_params`' arguments.
+pub const PARAM_ATTACK: u32 = 0;
+pub const PARAM_SUSTAIN: u32 = 1;
+pub const PARAM_OUTPUT_DB: u32 = 2;
+
+/// Largest boost or cut the shaper applies, in dB.
+const MAX_GAIN_DB: f32 = 24.0;
+
+/// Four followers per channel: the attack component is how far a fast-attack envelope runs
+/// ahead of a slow-attack one, the sustain component how far a slow-release envelope hangs
+/// above a fast-release one. Both are level-independent (measured in dB).
+#[derive(Clone, Copy)]
+struct Channel {
+    fast_attack: EnvelopeFollower,
+    slow_attack: EnvelopeFollower,
+    fast_release: EnvelopeFollower,
+    slow_release: EnvelopeFollower,
+}
+
+impl Channel {
+    fn new(sample_rate_hz: f32) -> Self {
+        Self {
+            fast_attack: EnvelopeFollower::new(0.5, 40.0, sample_rate_hz),
+            slow_attack: EnvelopeFollower::new(15.0, 40.0, sample_rate_hz),
+            fast_release: EnvelopeFollower::new(0.5, 40.0, sample_rate_hz),
+            slow_release: EnvelopeFollower::new(0.5, 300.0, sample_rate_hz),
+        }
+    }
+
+    /// Returns `(attack_db, sustain_db)`, both >= 0.
+    #[inline]
+    fn analyse(&mut self, x: f32) -> (f32, f32) {
+        let level = x.abs();
+        let attack = lin_to_db_floor(self.fast_attack.process(level))
+            - lin_to_db_floor(self.slow_attack.process(level));
+        let sustain = lin_to_db_floor(self.slow_release.process(level))
+            - lin_to_db_floor(self.fast_release.process(level));
+        (attack.max(0.0), sustain.max(0.0))
+    }
+}
+
+#[repr(C)]
+pub struct TransientShaper {
+    attack: f32,
+    sustain: f32,
+    output_db: f32,
+    output: f32,
+    channels: [Channel; MAX_CHANNELS],
+    events: ParamRing,
+    error: LastError,
+}
+
+impl TransientShaper {
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        for (inp, out) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+        {
+            for ((x, y), state) in inp.iter().zip(out.iter_mut()).zip(&mut self.channels) {
+                let (attack_db, sustain_db) = state.analyse(*x);
+                let gain_db = self.attack * attack_db + self.sustain * sustain_db;
+                *y = x * db_to_lin(gain_db.clamp(-MAX_GAIN_DB, MAX_GAIN_DB)) * self.output;
+            }
+        }
+    }
+}
+
+impl Node for TransientShaper {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Attack` and `Sustain` run from -1 (full cut) to 1 (full boost); at 1 every dB of
+    /// detected transient/tail is added again on top (capped at 24 dB).
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Attack", "", -1.0, 1.0, 0.0),
+        ParamDescriptor::linear("Sustain", "", -1.0, 1.0, 0.0),
+        ParamDescriptor::linear("Output", "dB", -24.0, 24.0, 0.0),
+    ];
+
+    fn new(sample_rate_hz: f32) -> Self {
+        TransientShaper {
+            attack: 0.0,
+            sustain: 0.0,
+            output_db: 0.0,
+            output: 1.0,
+            channels: [Channel::new(sample_rate_hz); MAX_CHANNELS],
+            events: ParamRing::new(),
+            error: LastError::new(),
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_ATTACK => self.attack = clamp(value, -1.0, 1.0),
+            PARAM_SUSTAIN => self.sustain = clamp(value, -1.0, 1.0),
+            PARAM_OUTPUT_DB => {
+                self.output_db = clamp(value, -24.0, 24.0);
+                self.output = db_to_lin(self.output_db);
+            }
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_ATTACK => self.attack,
+            PARAM_SUSTAIN => self.sustain,
+            PARAM_OUTPUT_DB => self.output_db,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Drops every envelope to silence.
+    fn reset(&mut self) {
+        for c in &mut self.channels {
+            c.fast_attack.reset(0.0);
+            c.slow_attack.reset(0.0);
+            c.fast_release.reset(0.0);
+            c.slow_release.reset(0.0);
+        }
+    }
+}
+
+node_abi::export_node!(TransientShaper {
+    abi_version: transient_abi_version,
+    new: transient_new,
+    free: transient_free,
+    param_count: transient_param_count,
+    param_descriptors: transient_param_descriptors,
+    set_param: transient_set_param,
+    get_param: transient_get_param,
+    param_events: transient_param_events,
+    process: transient_process,
+    process_offline: transient_process_offline,
+    latency_samples: transient_latency_samples,
+    io_layout: transient_get_io_layout,
+    process_io: transient_process_io,
+    reset: transient_reset,
+    state_size: transient_state_size,
+    save_state: transient_save_state,
+    load_state: transient_load_state,
+    preset_size: transient_preset_size,
+    save_preset: transient_save_preset,
+    load_preset: transient_load_preset,
+    last_error_code: transient_last_error_code,
+    last_error_message: transient_last_error_message_ptr,
+});
+
+node_abi::test_params!(TransientShaper);
+
+dsp_core::test_block_sizes!(
+    transient_new,
+    transient_process(input, output),
+    transient_free
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(TransientShaper as TransientShaperNode);
+
+/// Every parameter in one call, as `transient_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn transient_set_params(handle: Handle, attack: f32, sustain: f32, output_db: f32) {
+    guard(handle, |t: &mut TransientShaper| {
+        t.set_param(PARAM_ATTACK, attack);
+        t.set_param(PARAM_SUSTAIN, sustain);
+        t.set_param(PARAM_OUTPUT_DB, output_db);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// Silence, then half a second at 0.5, then 2.5 s at 0.05: a hit and its tail.
+    fn hit() -> Vec<f32> {
+        [(4_800, 0.0), (24_000, 0.5), (120_000, 0.05)]
+            .into_iter()
+            .flat_map(|(n, x)| core::iter::repeat_n(x, n))
+            .collect()
+    }
+
+    fn render(attack: f32, sustain: f32) -> Vec<f32> {
+        let mut t = TransientShaper::new(RATE);
+        t.set_param(PARAM_ATTACK, attack);
+        t.set_param(PARAM_SUSTAIN, sustain);
+        let input = hit();
+        render_quanta(input.len(), |span, y| {
+            Node::process(&mut t, &input[span], y, 1)
+        })
+    }
+
+    /// `Attack` boosts or cuts the onset, then lets the held level through as it was.
+    #[test]
+    fn attack_shapes_the_onset_only() {
+        let onset = 4_800 + 48;
+        let held = 4_800 + 23_999;
+        let boosted = render(1.0, 0.0);
+        let cut = render(-1.0, 0.0);
+        assert!(boosted[onset] > 0.5 * db_to_lin(6.0), "{}", boosted[onset]);
+        assert!(cut[onset] < 0.5 * db_to_lin(-6.0), "{}", cut[onset]);
+        assert!((boosted[held] - 0.5).abs() < 1e-3 && (cut[held] - 0.5).abs() < 1e-3);
+        // The drop to the tail isn't an attack.
+        assert!((boosted[held + 480] - cut[held + 480]).abs() < 1e-4);
+    }
+
+    /// `Sustain` lifts or ducks the tail after a hit, until it settles.
+    #[test]
+    fn sustain_shapes_the_tail_only() {
+        let tail = 4_800 + 24_000 + 2_400;
+        let end = 148_799;
+        let lifted = render(0.0, 1.0);
+        let ducked = render(0.0, -1.0);
+        assert!(lifted[tail] > 0.05 * db_to_lin(6.0), "{}", lifted[tail]);
+        assert!(ducked[tail] < 0.05 * db_to_lin(-6.0), "{}", ducked[tail]);
+        assert!((lifted[end] - 0.05).abs() < 1e-3 && (ducked[end] - 0.05).abs() < 1e-3);
+        // The onset isn't a tail.
+        assert_eq!(lifted[4_848], ducked[4_848]);
+    }
+}
@@ -0,0 +1,225 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::gain::lin_to_db;
+use dsp_core::true_peak::{TruePeakCoeffs, TruePeakDetector};
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+/// Fall rate of the current reading.
+const FALL_DB_PER_SECOND: f32 = 20.0;
+
+/// True-peak meter: the 4x oversampled peak per channel (as the limiter detects it), as a
+/// current reading that jumps to each new peak and falls back at 20 dB/s, and a max hold kept
+/// until reset. The audio is only read; the worklet passes it on. The UI polls the getters.
+pub struct TruePeakMeter {
+    coeffs: TruePeakCoeffs,
+    detectors: [TruePeakDetector; MAX_CHANNELS],
+    current: [f32; MAX_CHANNELS],
+    max: [f32; MAX_CHANNELS],
+    /// Per-sample factor of the current reading's fall.
+    fall: f32,
+    events: ParamRing,
+    error: LastError,
+}
+
+impl TruePeakMeter {
+    fn measure(&mut self, input: &[f32], channels: usize) {
+        for frame in input.chunks_exact(channels) {
+            for (ch, &x) in frame.iter().enumerate() {
+                let peak = self.detectors[ch].process(x, &self.coeffs);
+                let current = (self.current[ch] * self.fall).max(peak);
+                self.current[ch] = current;
+                self.max[ch] = self.max[ch].max(current);
+            }
+        }
+    }
+}
+
+impl Node for TruePeakMeter {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    const PARAMS: &'static [ParamDescriptor] = &[];
+    /// The audio is only read; the worklet passes it on.
+    const OUTPUT: bool = false;
+    const METER_COUNT: usize = 2 * MAX_CHANNELS;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        TruePeakMeter {
+            coeffs: TruePeakCoeffs::new(),
+            detectors: [TruePeakDetector::new(); MAX_CHANNELS],
+            current: [0.0; MAX_CHANNELS],
+            max: [0.0; MAX_CHANNELS],
+            fall: 10.0_f32.powf(-FALL_DB_PER_SECOND / 20.0 / sample_rate_hz),
+            events: ParamRing::new(),
+            error: LastError::new(),
+        }
+    }
+
+    fn set_param(&mut self, _index: u32, _value: f32) {}
+
+    fn get_param(&self, _index: u32) -> f32 {
+        0.0
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], _output: &mut [f32], channels: usize) {
+        self.measure(input, channels);
+    }
+
+    /// Every channel's current reading, then every channel's max hold, as the getters read
+    /// them.
+    fn meters(&self, out: &mut [f32]) {
+        let (current, max) = out.split_at_mut(MAX_CHANNELS);
+        for (m, &v) in current.iter_mut().zip(&self.current) {
+            *m = lin_to_db(v);
+        }
+        for (m, &v) in max.iter_mut().zip(&self.max) {
+            *m = lin_to_db(v);
+        }
+    }
+
+    /// Clears the readings, the max holds and the detectors' filters.
+    fn reset(&mut self) {
+        for d in &mut self.detectors {
+            d.clear();
+        }
+        self.current = [0.0; MAX_CHANNELS];
+        self.max = [0.0; MAX_CHANNELS];
+    }
+}
+
+node_abi::export_node!(TruePeakMeter {
+    abi_version: true_peak_abi_version,
+    new: true_peak_new,
+    free: true_peak_free,
+    param_count: true_peak_param_count,
+    param_descriptors: true_peak_param_descriptors,
+    set_param: true_peak_set_param,
+    get_param: true_peak_get_param,
+    param_events: true_peak_param_events,
+    process: true_peak_process,
+    process_offline: true_peak_process_offline,
+    latency_samples: true_peak_latency_samples,
+    io_layout: true_peak_get_io_layout,
+    process_io: true_peak_process_io,
+    reset: true_peak_reset,
+    state_size: true_peak_state_size,
+    save_state: true_peak_save_state,
+    load_state: true_peak_load_state,
+    preset_size: true_peak_preset_size,
+    save_preset: true_peak_save_preset,
+    load_preset: true_peak_load_preset,
+    last_error_code: true_peak_last_error_code,
+    last_error_message: true_peak_last_error_message_ptr,
+});
+
+node_abi::test_params!(TruePeakMeter);
+
+dsp_core::test_block_sizes!(
+    true_peak_new,
+    true_peak_process(input, output),
+    true_peak_free,
+    read: |p| {
+        (0..2)
+            .flat_map(|c| [true_peak_get_current_dbtp(p, c), true_peak_get_max_dbtp(p, c)])
+            .collect::<Vec<_>>()
+    },
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(TruePeakMeter as TruePeakMeterNode);
+
+/// Current true peak of `channel` in dBTP (-Infinity for silence or a channel out of range).
+#[no_mangle]
+pub extern "C" fn true_peak_get_current_dbtp(handle: Handle, channel: u32) -> f32 {
+    guard(handle, |m: &mut TruePeakMeter| {
+        m.current.get(channel as usize).map(|&v| lin_to_db(v))
+    })
+    .flatten()
+    .unwrap_or(f32::NEG_INFINITY)
+}
+
+/// Highest true peak of `channel` since creation or the last `true_peak_reset`, in dBTP.
+#[no_mangle]
+pub extern "C" fn true_peak_get_max_dbtp(handle: Handle, channel: u32) -> f32 {
+    guard(handle, |m: &mut TruePeakMeter| {
+        m.max.get(channel as usize).map(|&v| lin_to_db(v))
+    })
+    .flatten()
+    .unwrap_or(f32::NEG_INFINITY)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+
+    const RATE: f32 = 48_000.0;
+
+    /// Feeds `x` to the meter in render quanta, `channels` interleaved.
+    fn feed(h: Handle, x: &[f32], channels: usize) {
+        let mut out = vec![0.0; 128 * channels];
+        for block in x.chunks(128 * channels) {
+            let frames = block.len() / channels;
+            true_peak_process(h, block.as_ptr(), out.as_mut_ptr(), frames, channels);
+        }
+    }
+
+    /// A full-scale sine at a quarter of the rate, sampled 45 degrees off its crests, has
+    /// sample peaks 3 dB down; the meter finds the 0 dBTP peak between them. A silent
+    /// channel reads -Infinity.
+    #[test]
+    fn finds_inter_sample_peaks() {
+        let h = true_peak_new(RATE);
+        let x: Vec<f32> = (0..4_800)
+            .flat_map(|i| [(PI / 2.0 * i as f32 + PI / 4.0).sin(), 0.0])
+            .collect();
+        assert!(x.iter().all(|&v| v.abs() < 0.71));
+        feed(h, &x, 2);
+        assert!(
+            true_peak_get_max_dbtp(h, 0).abs() < 0.2,
+            "{}",
+            true_peak_get_max_dbtp(h, 0)
+        );
+        assert!(true_peak_get_current_dbtp(h, 0).abs() < 0.2);
+        assert_eq!(true_peak_get_max_dbtp(h, 1), f32::NEG_INFINITY);
+        true_peak_free(h);
+    }
+
+    /// After the signal stops, the current reading falls at 20 dB/s while the max hold stays
+    /// until reset.
+    #[test]
+    fn current_falls_and_max_holds_until_reset() {
+        let h = true_peak_new(RATE);
+        let x: Vec<f32> = (0..4_800)
+            .map(|i| 0.5 * (2.0 * PI * 1_000.0 * i as f32 / RATE).sin())
+            .collect();
+        feed(h, &x, 1);
+        let peak = true_peak_get_max_dbtp(h, 0);
+        assert!((peak + 6.02).abs() < 0.1, "{peak}");
+
+        // Half a second of silence.
+        feed(h, &[0.0; 24_000], 1);
+        let fall = peak - true_peak_get_current_dbtp(h, 0);
+        assert!((fall - 10.0).abs() < 0.1, "{fall}");
+        assert_eq!(true_peak_get_max_dbtp(h, 0), peak);
+
+        true_peak_reset(h);
+        assert_eq!(true_peak_get_current_dbtp(h, 0), f32::NEG_INFINITY);
+        assert_eq!(true_peak_get_max_dbtp(h, 0), f32::NEG_INFINITY);
+        true_peak_free(h);
+    }
+}
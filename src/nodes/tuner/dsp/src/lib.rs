@@ -0,0 +1,348 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use dsp_core::param::clamp;
+use dsp_core::yin::Yin;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `Tuner::PARAMS`, in the order of `tuner_set_params`' arguments.
+pub const PARAM_UPDATE_HZ: u32 = 0;
+pub const PARAM_REFERENCE_HZ: u32 = 1;
+pub const PARAM_MIN_CONFIDENCE: u32 = 2;
+
+/// Range covered: a five-string bass's low B up to the top of most voices and leads.
+const MIN_HZ: f32 = 30.0;
+const MAX_HZ: f32 = 2000.0;
+
+/// Tuner. YIN (`dsp_core::yin`) runs on the mono sum a set number of times a second; a
+/// detection at or over the confidence threshold is reported as a frequency, the nearest MIDI
+/// note against the reference A4 and the offset from it in cents. Below the threshold (or in
+/// silence) the node reads unvoiced, keeping the confidence so the UI can show it. The audio
+/// is only read; the worklet passes it on.
+pub struct Tuner {
+    yin: Yin,
+    /// Mono input, circular over one YIN frame.
+    history: Vec<f32>,
+    write: usize,
+    scratch: Vec<f32>,
+    update_hz: f32,
+    hop: usize,
+    since_detect: usize,
+    reference_hz: f32,
+    min_confidence: f32,
+    hz: f32,
+    confidence: f32,
+    note: i32,
+    cents: f32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Tuner {
+    fn detect(&mut self) {
+        let len = self.history.len();
+        let (older, newer) = self.history.split_at(self.write);
+        self.scratch[..len - self.write].copy_from_slice(newer);
+        self.scratch[len - self.write..].copy_from_slice(older);
+        let pitch = self.yin.detect(&self.scratch);
+        self.confidence = pitch.map_or(0.0, |p| p.confidence);
+        match pitch {
+            Some(p) if p.confidence >= self.min_confidence => {
+                let note = 69.0 + 12.0 * (p.hz / self.reference_hz).log2();
+                self.hz = p.hz;
+                self.note = note.round() as i32;
+                self.cents = (note - self.note as f32) * 100.0;
+            }
+            _ => {
+                self.hz = 0.0;
+                self.note = -1;
+                self.cents = 0.0;
+            }
+        }
+    }
+
+    fn measure(&mut self, input: &[f32], channels: usize) {
+        let scale = 1.0 / channels as f32;
+        for frame in input.chunks_exact(channels) {
+            let sum: f32 = frame.iter().filter(|x| x.is_finite()).sum();
+            self.history[self.write] = sum * scale;
+            self.write = (self.write + 1) % self.history.len();
+            self.since_detect += 1;
+            if self.since_detect >= self.hop {
+                self.since_detect = 0;
+                self.detect();
+            }
+        }
+    }
+}
+
+impl Node for Tuner {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Update` is detections a second; `Reference` is A4; `Confidence` is what a detection
+    /// needs to count as voiced (0.8 suits most instruments).
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::log("Update", "Hz", 1.0, 100.0, 20.0),
+        ParamDescriptor::linear("Reference", "Hz", 400.0, 480.0, 440.0),
+        ParamDescriptor::linear("Confidence", "", 0.0, 1.0, 0.8),
+    ];
+    /// The audio is only read; the worklet passes it on.
+    const OUTPUT: bool = false;
+    const METER_COUNT: usize = 4;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let yin = Yin::new(MIN_HZ, MAX_HZ, sample_rate_hz);
+        let len = yin.frame_len();
+        Tuner {
+            yin,
+            history: vec![0.0; len],
+            write: 0,
+            scratch: vec![0.0; len],
+            update_hz: 20.0,
+            hop: ((sample_rate_hz / 20.0) as usize).max(1),
+            since_detect: 0,
+            reference_hz: 440.0,
+            min_confidence: 0.8,
+            hz: 0.0,
+            confidence: 0.0,
+            note: -1,
+            cents: 0.0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_UPDATE_HZ => {
+                self.update_hz = clamp(value, 1.0, 100.0);
+                self.hop = ((self.sample_rate_hz / self.update_hz) as usize).max(1);
+                self.since_detect = self.since_detect.min(self.hop);
+            }
+            PARAM_REFERENCE_HZ => self.reference_hz = clamp(value, 400.0, 480.0),
+            PARAM_MIN_CONFIDENCE => self.min_confidence = clamp(value, 0.0, 1.0),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_UPDATE_HZ => self.update_hz,
+            PARAM_REFERENCE_HZ => self.reference_hz,
+            PARAM_MIN_CONFIDENCE => self.min_confidence,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], _output: &mut [f32], channels: usize) {
+        self.measure(input, channels);
+    }
+
+    /// Frequency, confidence, note and cents, as the getters read them.
+    fn meters(&self, out: &mut [f32]) {
+        out[0] = self.hz;
+        out[1] = self.confidence;
+        out[2] = self.note as f32;
+        out[3] = self.cents;
+    }
+
+    /// Clears the input history and the reading (no pitch until a frame's worth of new
+    /// input).
+    fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.write = 0;
+        self.since_detect = 0;
+        self.hz = 0.0;
+        self.confidence = 0.0;
+        self.note = -1;
+        self.cents = 0.0;
+    }
+}
+
+node_abi::export_node!(Tuner {
+    abi_version: tuner_abi_version,
+    new: tuner_new,
+    free: tuner_free,
+    param_count: tuner_param_count,
+    param_descriptors: tuner_param_descriptors,
+    set_param: tuner_set_param,
+    get_param: tuner_get_param,
+    param_events: tuner_param_events,
+    process: tuner_process,
+    process_offline: tuner_process_offline,
+    latency_samples: tuner_latency_samples,
+    io_layout: tuner_get_io_layout,
+    process_io: tuner_process_io,
+    reset: tuner_reset,
+    state_size: tuner_state_size,
+    save_state: tuner_save_state,
+    load_state: tuner_load_state,
+    preset_size: tuner_preset_size,
+    save_preset: tuner_save_preset,
+    load_preset: tuner_load_preset,
+    last_error_code: tuner_last_error_code,
+    last_error_message: tuner_last_error_message_ptr,
+});
+
+node_abi::test_params!(Tuner);
+
+dsp_core::test_block_sizes!(
+    tuner_new,
+    tuner_process(input, output),
+    tuner_free,
+    read: |p| vec![tuner_get_hz(p), tuner_get_confidence(p), tuner_get_cents(p)],
+);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Tuner as TunerNode);
+
+/// Every parameter in one call, as `tuner_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn tuner_set_params(
+    handle: Handle,
+    update_hz: f32,
+    reference_hz: f32,
+    min_confidence: f32,
+) {
+    guard(handle, |t: &mut Tuner| {
+        t.set_param(PARAM_UPDATE_HZ, update_hz);
+        t.set_param(PARAM_REFERENCE_HZ, reference_hz);
+        t.set_param(PARAM_MIN_CONFIDENCE, min_confidence);
+    });
+}
+
+/// Detected frequency in Hz (0 while unvoiced), for the UI.
+#[no_mangle]
+pub extern "C" fn tuner_get_hz(handle: Handle) -> f32 {
+    guard(handle, |t: &mut Tuner| t.hz).unwrap_or(0.0)
+}
+
+/// How periodic the last frame was, 0–1 (around 0.9 and up for a clean note).
+#[no_mangle]
+pub extern "C" fn tuner_get_confidence(handle: Handle) -> f32 {
+    guard(handle, |t: &mut Tuner| t.confidence).unwrap_or(0.0)
+}
+
+/// Nearest MIDI note (69 is A4; -1 while unvoiced).
+#[no_mangle]
+pub extern "C" fn tuner_get_note(handle: Handle) -> i32 {
+    guard(handle, |t: &mut Tuner| t.note).unwrap_or(-1)
+}
+
+/// Offset from the nearest note in cents, -50–50 (positive is sharp).
+#[no_mangle]
+pub extern "C" fn tuner_get_cents(handle: Handle) -> f32 {
+    guard(handle, |t: &mut Tuner| t.cents).unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+    use dsp_core::blocksize::render_quanta;
+
+    const RATE: f32 = 48_000.0;
+
+    /// Half a second of a note at `hz` with three harmonics, like a bright string.
+    fn note(hz: f32) -> Vec<f32> {
+        (0..24_000)
+            .map(|i| {
+                let t = i as f32 / RATE;
+                (1..=3)
+                    .map(|k| (2.0 * PI * k as f32 * hz * t).sin() / k as f32)
+                    .sum::<f32>()
+                    * 0.3
+            })
+            .collect()
+    }
+
+    /// Feeds mono `x` to the tuner in render quanta.
+    fn feed(h: Handle, x: &[f32]) {
+        render_quanta(x.len(), |span, y| {
+            tuner_process(h, x[span].as_ptr(), y.as_mut_ptr(), y.len(), 1)
+        });
+    }
+
+    /// Known pitches across the range read as their note and offset in cents.
+    #[test]
+    fn detects_known_pitches() {
+        // Low B, A4, 20 cents sharp of A4 and A6.
+        for (hz, expected_note, expected_cents) in [
+            (30.868, 23, 0.0),
+            (440.0, 69, 0.0),
+            (440.0 * 2.0_f32.powf(20.0 / 1200.0), 69, 20.0),
+            (1_760.0, 93, 0.0),
+        ] {
+            let h = tuner_new(RATE);
+            feed(h, &note(hz));
+            assert!(
+                (tuner_get_hz(h) / hz - 1.0).abs() < 1e-3,
+                "{hz}: {}",
+                tuner_get_hz(h)
+            );
+            assert_eq!(tuner_get_note(h), expected_note, "{hz}");
+            let cents = tuner_get_cents(h);
+            // Within a cent or two; the short periods of high notes resolve least finely.
+            assert!((cents - expected_cents).abs() < 2.0, "{hz}: {cents}");
+            assert!(tuner_get_confidence(h) > 0.9);
+            tuner_free(h);
+        }
+    }
+
+    /// The note and cents follow the reference A4.
+    #[test]
+    fn follows_the_reference() {
+        let h = tuner_new(RATE);
+        tuner_set_params(h, 20.0, 432.0, 0.8);
+        feed(h, &note(440.0));
+        assert_eq!(tuner_get_note(h), 69);
+        let cents = 1200.0 * (440.0_f32 / 432.0).log2();
+        assert!(
+            (tuner_get_cents(h) - cents).abs() < 1.0,
+            "{}",
+            tuner_get_cents(h)
+        );
+        tuner_free(h);
+    }
+
+    /// Noise and silence read unvoiced.
+    #[test]
+    fn noise_and_silence_are_unvoiced() {
+        let h = tuner_new(RATE);
+        let mut seed = 1_u32;
+        let noise: Vec<f32> = (0..24_000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (seed >> 8) as f32 / (1 << 23) as f32 - 1.0
+            })
+            .collect();
+        feed(h, &noise);
+        assert_eq!(tuner_get_note(h), -1);
+        assert_eq!(tuner_get_hz(h), 0.0);
+        assert!(tuner_get_confidence(h) < 0.8);
+
+        feed(h, &[0.0; 24_000]);
+        assert_eq!(tuner_get_note(h), -1);
+        assert_eq!(tuner_get_confidence(h), 0.0);
+        tuner_free(h);
+    }
+}
@@ -0,0 +1,310 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::envelope::EnvelopeFollower;
+use dsp_core::gain::{db_to_lin, lin_to_db_floor};
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+pub const MAX_CHANNELS: usize = 8;
+
+// Indices into `UpwardCompressor::PARAMS`, in the order of `upward_set_params`' arguments.
+pub const PARAM_THRESHOLD_DB: u32 = 0;
+pub const PARAM_RATIO: u32 = 1;
+pub const PARAM_MAX_BOOST_DB: u32 = 2;
+pub const PARAM_ATTACK_MS: u32 = 3;
+pub const PARAM_RELEASE_MS: u32 = 4;
+pub const PARAM_LINK: u32 = 5;
+
+/// Decay of the peak detector so the level doesn't drop to nothing at every zero crossing.
+const DETECTOR_RELEASE_MS: f32 = 20.0;
+
+/// Upward compressor: material below the threshold is raised towards it by `1 - 1/ratio` of the
+/// distance, never by more than `max_boost_db`; material above it passes untouched.
+///
+/// The boost is smoothed with the compressor's convention turned around: "attack" is how fast
+/// the boost backs off when the signal gets louder, "release" how fast it grows back.
+#[repr(C)]
+pub struct UpwardCompressor {
+    threshold_db: f32,
+    ratio: f32,
+    max_boost_db: f32,
+    attack_ms: f32,
+    release_ms: f32,
+    link: bool,
+    /// Peak level per channel; slot 0 is shared when linked.
+    level: [EnvelopeFollower; MAX_CHANNELS],
+    /// Smoothed negative boost in dB (<= 0) per channel; slot 0 is shared when linked. Stored
+    /// negated so a falling boost rides the follower's attack.
+    boost: [EnvelopeFollower; MAX_CHANNELS],
+    meter_boost_db: f32,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl UpwardCompressor {
+    fn update_times(&mut self) {
+        for b in &mut self.boost {
+            b.set_times(self.attack_ms, self.release_ms, self.sample_rate_hz);
+        }
+    }
+
+    #[inline]
+    fn target_boost_db(&self, level: f32) -> f32 {
+        let under = self.threshold_db - lin_to_db_floor(level);
+        (under * (1.0 - 1.0 / self.ratio)).clamp(0.0, self.max_boost_db)
+    }
+
+    /// Detects, applies the static curve and smooths for one gain slot.
+    #[inline]
+    fn boost_db(&mut self, slot: usize, peak: f32) -> f32 {
+        let level = self.level[slot].process(peak);
+        let target = self.target_boost_db(level);
+        -self.boost[slot].process(-target)
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let frames = input.len() / channels;
+        let linked = self.link && channels > 1;
+        let mut most = 0.0_f32;
+        for i in 0..frames {
+            let frame = i * channels;
+            if linked {
+                let level = input[frame..frame + channels]
+                    .iter()
+                    .fold(0.0_f32, |m, x| m.max(x.abs()));
+                let boost = self.boost_db(0, level);
+                most = most.max(boost);
+                let g = db_to_lin(boost);
+                for ch in 0..channels {
+                    output[frame + ch] = input[frame + ch] * g;
+                }
+            } else {
+                for ch in 0..channels {
+                    let x = input[frame + ch];
+                    let boost = self.boost_db(ch, x.abs());
+                    most = most.max(boost);
+                    output[frame + ch] = x * db_to_lin(boost);
+                }
+            }
+        }
+        self.meter_boost_db = most;
+    }
+}
+
+impl Node for UpwardCompressor {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Max boost` is the ceiling on how far quiet material is raised, which also keeps the
+    /// noise floor from being pulled up without limit. `Link` drives all channels from the
+    /// loudest one.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Threshold", "dB", -80.0, 0.0, -30.0),
+        ParamDescriptor::linear("Ratio", "", 1.0, 10.0, 2.0),
+        ParamDescriptor::linear("Max boost", "dB", 0.0, 36.0, 12.0),
+        ParamDescriptor::log("Attack", "ms", 0.01, 500.0, 1.0),
+        ParamDescriptor::log("Release", "ms", 1.0, 5000.0, 200.0),
+        ParamDescriptor::toggle("Stereo link", true),
+    ];
+    const METER_COUNT: usize = 1;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        UpwardCompressor {
+            threshold_db: -30.0,
+            ratio: 2.0,
+            max_boost_db: 12.0,
+            attack_ms: 1.0,
+            release_ms: 200.0,
+            link: true,
+            level: [EnvelopeFollower::new(0.0, DETECTOR_RELEASE_MS, sample_rate_hz); MAX_CHANNELS],
+            boost: [EnvelopeFollower::new(1.0, 200.0, sample_rate_hz); MAX_CHANNELS],
+            meter_boost_db: 0.0,
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        }
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_THRESHOLD_DB => self.threshold_db = clamp(value, -80.0, 0.0),
+            PARAM_RATIO => self.ratio = clamp(value, 1.0, 10.0),
+            PARAM_MAX_BOOST_DB => self.max_boost_db = clamp(value, 0.0, 36.0),
+            PARAM_ATTACK_MS => {
+                self.attack_ms = clamp(value, 0.01, 500.0);
+                self.update_times();
+            }
+            PARAM_RELEASE_MS => {
+                self.release_ms = clamp(value, 1.0, 5000.0);
+                self.update_times();
+            }
+            PARAM_LINK => self.link = clamp(value, 0.0, 1.0) >= 0.5,
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_THRESHOLD_DB => self.threshold_db,
+            PARAM_RATIO => self.ratio,
+            PARAM_MAX_BOOST_DB => self.max_boost_db,
+            PARAM_ATTACK_MS => self.attack_ms,
+            PARAM_RELEASE_MS => self.release_ms,
+            PARAM_LINK => u32::from(self.link) as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    /// Boost as `upward_get_boost_db` reads it.
+    fn meters(&self, out: &mut [f32]) {
+        out[0] = self.meter_boost_db;
+    }
+
+    /// Drops the detectors and the boost back to where a new node starts.
+    fn reset(&mut self) {
+        for e in self.level.iter_mut().chain(&mut self.boost) {
+            e.reset(0.0);
+        }
+        self.meter_boost_db = 0.0;
+    }
+}
+
+node_abi::export_node!(UpwardCompressor {
+    abi_version: upward_abi_version,
+    new: upward_new,
+    free: upward_free,
+    param_count: upward_param_count,
+    param_descriptors: upward_param_descriptors,
+    set_param: upward_set_param,
+    get_param: upward_get_param,
+    param_events: upward_param_events,
+    process: upward_process,
+    process_offline: upward_process_offline,
+    latency_samples: upward_latency_samples,
+    io_layout: upward_get_io_layout,
+    process_io: upward_process_io,
+    reset: upward_reset,
+    state_size: upward_state_size,
+    save_state: upward_save_state,
+    load_state: upward_load_state,
+    preset_size: upward_preset_size,
+    save_preset: upward_save_preset,
+    load_preset: upward_load_preset,
+    last_error_code: upward_last_error_code,
+    last_error_message: upward_last_error_message_ptr,
+});
+
+node_abi::test_params!(UpwardCompressor);
+
+dsp_core::test_block_sizes!(upward_new, upward_process(input, output), upward_free);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(UpwardCompressor as UpwardCompressorNode);
+
+/// Every parameter in one call, as `upward_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn upward_set_params(
+    handle: Handle,
+    threshold_db: f32,
+    ratio: f32,
+    max_boost_db: f32,
+    attack_ms: f32,
+    release_ms: f32,
+    link: u32,
+) {
+    guard(handle, |u: &mut UpwardCompressor| {
+        u.set_param(PARAM_THRESHOLD_DB, threshold_db);
+        u.set_param(PARAM_RATIO, ratio);
+        u.set_param(PARAM_MAX_BOOST_DB, max_boost_db);
+        u.set_param(PARAM_ATTACK_MS, attack_ms);
+        u.set_param(PARAM_RELEASE_MS, release_ms);
+        u.set_param(PARAM_LINK, link as f32);
+    });
+}
+
+/// Largest boost applied during the last processed block, in dB.
+#[no_mangle]
+pub extern "C" fn upward_get_boost_db(handle: Handle) -> f32 {
+    guard(handle, |u: &mut UpwardCompressor| u.meter_boost_db).unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 48_000.0;
+
+    /// Renders three seconds of each channel held at its level in dB, in render quanta, and returns
+    /// the last frame's gain per channel in dB.
+    fn settled_gain_db(u: &mut UpwardCompressor, levels_db: &[f32]) -> Vec<f32> {
+        let channels = levels_db.len();
+        let frame: Vec<f32> = levels_db.iter().map(|&db| db_to_lin(db)).collect();
+        let input = frame.repeat(144_000);
+        let mut output = vec![0.0; input.len()];
+        for (x, y) in input
+            .chunks(128 * channels)
+            .zip(output.chunks_mut(128 * channels))
+        {
+            Node::process(u, x, y, channels);
+        }
+        output[output.len() - channels..]
+            .iter()
+            .zip(&frame)
+            .map(|(y, x)| lin_to_db_floor(y / x))
+            .collect()
+    }
+
+    /// Under the threshold the level is raised by `1 - 1/ratio` of the distance, up to
+    /// `Max boost`; above it nothing changes.
+    #[test]
+    fn raises_quiet_material_up_to_the_ceiling() {
+        for (level_db, ratio, want) in [
+            (-40.0, 2.0, 5.0),
+            (-50.0, 4.0, 12.0),
+            (-36.0, 4.0, 4.5),
+            (-20.0, 4.0, 0.0),
+        ] {
+            let mut u = UpwardCompressor::new(RATE);
+            u.set_param(PARAM_THRESHOLD_DB, -30.0);
+            u.set_param(PARAM_RATIO, ratio);
+            let got = settled_gain_db(&mut u, &[level_db])[0];
+            assert!(
+                (got - want).abs() < 0.01,
+                "{level_db} dB at {ratio}:1: {got}"
+            );
+            let mut meters = [0.0];
+            Node::meters(&u, &mut meters);
+            assert!((meters[0] - want).abs() < 0.01);
+        }
+    }
+
+    /// Linked, a loud channel keeps a quiet one from being raised; unlinked, each goes its own
+    /// way.
+    #[test]
+    fn link_drives_every_channel_from_the_loudest() {
+        let mut u = UpwardCompressor::new(RATE);
+        let linked = settled_gain_db(&mut u, &[-10.0, -50.0]);
+        assert!(linked.iter().all(|g| g.abs() < 0.01), "{linked:?}");
+
+        u.set_param(PARAM_LINK, 0.0);
+        let unlinked = settled_gain_db(&mut u, &[-10.0, -50.0]);
+        assert!(
+            unlinked[0].abs() < 0.01 && (unlinked[1] - 10.0).abs() < 0.01,
+            "{unlinked:?}"
+        );
+    }
+}
@@ -0,0 +1,407 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use dsp_core::biquad::{Biquad, Response};
+use dsp_core::envelope::EnvelopeFollower;
+use dsp_core::oscillator::{Oscillator, Waveform};
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+/// Every channel runs its own synthesis filter bank, so this node stops at stereo.
+pub const MAX_CHANNELS: usize = 2;
+pub const MIN_BANDS: usize = 16;
+pub const MAX_BANDS: usize = 32;
+
+// Indices into `Vocoder::PARAMS`, in the order of `vocoder_set_params`' arguments.
+pub const PARAM_BANDS: u32 = 0;
+pub const PARAM_ATTACK_MS: u32 = 1;
+pub const PARAM_RELEASE_MS: u32 = 2;
+pub const PARAM_CARRIER_HZ: u32 = 3;
+pub const PARAM_MIX: u32 = 4;
+
+const LOW_HZ: f32 = 100.0;
+const HIGH_HZ: f32 = 8000.0;
+/// Output gain at 16 bands, set so noise at about -10 dBFS on both inputs comes out at the
+/// same level (the output follows the product of the two); it grows with the square root of
+/// the band count, as narrower bands pass less of each signal.
+const MAKEUP: f32 = 32.0;
+const REFERENCE_BANDS: f32 = 16.0;
+
+/// One analysis/synthesis band: two cascaded band-passes (sharper skirts than one) on each
+/// side, and the modulator's envelope in between.
+#[derive(Clone, Copy)]
+struct Band {
+    analysis: [Biquad; 2],
+    synthesis: [[Biquad; 2]; MAX_CHANNELS],
+    envelope: EnvelopeFollower,
+}
+
+/// Channel vocoder. The modulator (mono sum) is split into log-spaced bands between 100 Hz and
+/// 8 kHz whose envelopes set the level of the same bands of the carrier. The carrier is an
+/// external signal (`vocoder_process_with_carrier`) or the internal sawtooth.
+#[repr(C)]
+pub struct Vocoder {
+    bands: [Band; MAX_BANDS],
+    band_count: usize,
+    attack_ms: f32,
+    release_ms: f32,
+    makeup: f32,
+    carrier_hz: f32,
+    mix: f32,
+    saw: Oscillator,
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+impl Vocoder {
+    /// Re-tunes the filter banks for `band_count` bands; each band spans its share of the range
+    /// in octaves, which sets the Q. Envelopes keep their level across a change.
+    fn update_bands(&mut self) {
+        let count = self.band_count;
+        let sr = self.sample_rate_hz;
+        let high = HIGH_HZ.min(sr * 0.45);
+        let octaves = (high / LOW_HZ).log2();
+        let width = octaves / (count - 1) as f32;
+        let q = 1.0 / (2.0 * (core::f32::consts::LN_2 * 0.5 * width).sinh());
+        for (i, band) in self.bands.iter_mut().enumerate().take(count) {
+            let hz = LOW_HZ * (i as f32 * width).exp2();
+            for f in band
+                .analysis
+                .iter_mut()
+                .chain(band.synthesis.iter_mut().flatten())
+            {
+                f.set(Response::Bandpass, hz, q, sr);
+            }
+        }
+        self.makeup = MAKEUP * (count as f32 / REFERENCE_BANDS).sqrt();
+    }
+
+    fn update_times(&mut self) {
+        for band in &mut self.bands {
+            band.envelope
+                .set_times(self.attack_ms, self.release_ms, self.sample_rate_hz);
+        }
+    }
+
+    /// Runs the modulator through the analysis bank and returns each band's envelope.
+    fn analyse(&mut self, modulator: f32, levels: &mut [f32; MAX_BANDS]) {
+        for (band, level) in self.bands[..self.band_count]
+            .iter_mut()
+            .zip(levels.iter_mut())
+        {
+            let [a, b] = &mut band.analysis;
+            let v = b.process(a.process(modulator));
+            *level = band.envelope.process(v.abs());
+        }
+    }
+
+    fn synthesise(&mut self, ch: usize, carrier: f32, levels: &[f32; MAX_BANDS]) -> f32 {
+        let mut sum = 0.0;
+        for (band, level) in self.bands[..self.band_count].iter_mut().zip(levels) {
+            let [a, b] = &mut band.synthesis[ch];
+            sum += b.process(a.process(carrier)) * level;
+        }
+        sum * self.makeup
+    }
+
+    /// `carrier(frame_index, channel)` supplies the carrier samples.
+    fn run(
+        &mut self,
+        input: &[f32],
+        output: &mut [f32],
+        channels: usize,
+        mut carrier: impl FnMut(&mut Self, usize, usize) -> f32,
+    ) {
+        let scale = 1.0 / channels as f32;
+        let mut levels = [0.0; MAX_BANDS];
+        for (i, (inp, out)) in input
+            .chunks_exact(channels)
+            .zip(output.chunks_exact_mut(channels))
+            .enumerate()
+        {
+            self.analyse(inp.iter().sum::<f32>() * scale, &mut levels);
+            for (ch, (x, y)) in inp.iter().zip(out.iter_mut()).enumerate() {
+                let c = carrier(self, i, ch);
+                let wet = self.synthesise(ch, c, &levels);
+                *y = *x + (wet - *x) * self.mix;
+            }
+        }
+    }
+
+    fn process_interleaved(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        let mut saw = 0.0;
+        self.run(input, output, channels, |v, _, ch| {
+            if ch == 0 {
+                saw = v.saw.next(Waveform::Saw);
+            }
+            saw
+        });
+    }
+
+    fn process_with_carrier(
+        &mut self,
+        input: &[f32],
+        carrier: &[f32],
+        output: &mut [f32],
+        channels: usize,
+        carrier_channels: usize,
+    ) {
+        self.run(input, output, channels, |_, i, ch| {
+            let c = carrier[i * carrier_channels + ch % carrier_channels];
+            if c.is_finite() {
+                c
+            } else {
+                0.0
+            }
+        });
+    }
+}
+
+impl Node for Vocoder {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Attack` and `Release` are the band envelopes'; `Carrier` tunes the internal sawtooth
+    /// (unused with an external carrier); `Mix` goes from the dry modulator to fully vocoded.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::stepped("Bands", "", MIN_BANDS as f32, MAX_BANDS as f32, 16.0),
+        ParamDescriptor::log("Attack", "ms", 0.5, 100.0, 5.0),
+        ParamDescriptor::log("Release", "ms", 5.0, 1000.0, 50.0),
+        ParamDescriptor::log("Carrier", "Hz", 20.0, 2000.0, 110.0),
+        ParamDescriptor::linear("Mix", "", 0.0, 1.0, 1.0),
+    ];
+    /// The carrier in place of the internal sawtooth, which then holds its phase. A mono
+    /// carrier feeds both synthesis banks.
+    const AUX_CHANNELS: usize = MAX_CHANNELS;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut v = Vocoder {
+            bands: [Band {
+                analysis: [Biquad::default(); 2],
+                synthesis: [[Biquad::default(); 2]; MAX_CHANNELS],
+                envelope: EnvelopeFollower::new(5.0, 50.0, sample_rate_hz),
+            }; MAX_BANDS],
+            band_count: MIN_BANDS,
+            attack_ms: 5.0,
+            release_ms: 50.0,
+            makeup: MAKEUP,
+            carrier_hz: 110.0,
+            mix: 1.0,
+            saw: Oscillator::new(),
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        v.update_bands();
+        v.saw.set_frequency(110.0, sample_rate_hz);
+        v
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_BANDS => {
+                let count = clamp(value, MIN_BANDS as f32, MAX_BANDS as f32).round() as usize;
+                if count != self.band_count {
+                    self.band_count = count;
+                    self.update_bands();
+                }
+            }
+            PARAM_ATTACK_MS => {
+                self.attack_ms = clamp(value, 0.5, 100.0);
+                self.update_times();
+            }
+            PARAM_RELEASE_MS => {
+                self.release_ms = clamp(value, 5.0, 1000.0);
+                self.update_times();
+            }
+            PARAM_CARRIER_HZ => {
+                self.carrier_hz = clamp(value, 20.0, 2000.0);
+                self.saw.set_frequency(self.carrier_hz, self.sample_rate_hz);
+            }
+            PARAM_MIX => self.mix = clamp(value, 0.0, 1.0),
+            _ => {}
+        }
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_BANDS => self.band_count as f32,
+            PARAM_ATTACK_MS => self.attack_ms,
+            PARAM_RELEASE_MS => self.release_ms,
+            PARAM_CARRIER_HZ => self.carrier_hz,
+            PARAM_MIX => self.mix,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    /// Vocodes the input (the modulator) onto the internal sawtooth.
+    fn process(&mut self, input: &[f32], output: &mut [f32], channels: usize) {
+        self.process_interleaved(input, output, channels);
+    }
+
+    fn process_aux(
+        &mut self,
+        input: &[f32],
+        aux: &[f32],
+        aux_channels: usize,
+        output: &mut [f32],
+        channels: usize,
+    ) {
+        if aux.is_empty() {
+            self.process_interleaved(input, output, channels);
+        } else {
+            self.process_with_carrier(input, aux, output, channels, aux_channels);
+        }
+    }
+
+    /// Clears the band filters and envelopes and restarts the internal sawtooth.
+    fn reset(&mut self) {
+        for band in &mut self.bands {
+            for f in &mut band.analysis {
+                f.clear();
+            }
+            for f in band.synthesis.iter_mut().flatten() {
+                f.clear();
+            }
+            band.envelope.reset(0.0);
+        }
+        self.saw.reset(0.0);
+    }
+}
+
+node_abi::export_node!(Vocoder {
+    abi_version: vocoder_abi_version,
+    new: vocoder_new,
+    free: vocoder_free,
+    param_count: vocoder_param_count,
+    param_descriptors: vocoder_param_descriptors,
+    set_param: vocoder_set_param,
+    get_param: vocoder_get_param,
+    param_events: vocoder_param_events,
+    process: vocoder_process,
+    process_offline: vocoder_process_offline,
+    latency_samples: vocoder_latency_samples,
+    io_layout: vocoder_get_io_layout,
+    process_io: vocoder_process_io,
+    reset: vocoder_reset,
+    state_size: vocoder_state_size,
+    save_state: vocoder_save_state,
+    load_state: vocoder_load_state,
+    preset_size: vocoder_preset_size,
+    save_preset: vocoder_save_preset,
+    load_preset: vocoder_load_preset,
+    last_error_code: vocoder_last_error_code,
+    last_error_message: vocoder_last_error_message_ptr,
+    process_aux: vocoder_process_with_carrier,
+    process_aux_offline: vocoder_process_offline_with_carrier,
+    process_io_aux: vocoder_process_io_with_carrier,
+});
+
+node_abi::test_params!(Vocoder);
+
+dsp_core::test_block_sizes!(
+    vocoder_new,
+    vocoder_process_with_carrier(input, aux, output),
+    vocoder_free
+);
+
+node_abi::test_io_block!(Vocoder);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Vocoder as VocoderNode);
+
+/// Every parameter in one call, as `vocoder_set_param` sets them.
+#[no_mangle]
+pub extern "C" fn vocoder_set_params(
+    handle: Handle,
+    bands: u32,
+    attack_ms: f32,
+    release_ms: f32,
+    carrier_hz: f32,
+    mix: f32,
+) {
+    guard(handle, |v: &mut Vocoder| {
+        v.set_param(PARAM_BANDS, bands as f32);
+        v.set_param(PARAM_ATTACK_MS, attack_ms);
+        v.set_param(PARAM_RELEASE_MS, release_ms);
+        v.set_param(PARAM_CARRIER_HZ, carrier_hz);
+        v.set_param(PARAM_MIX, mix);
+    });
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::TAU;
+    use dsp_core::blocksize::{amplitude, render_quanta};
+
+    const RATE: f32 = 48_000.0;
+
+    fn vocoder(params: &[(u32, f32)]) -> Vocoder {
+        let mut v = Vocoder::new(RATE);
+        for &(index, value) in params {
+            v.set_param(index, value);
+        }
+        v
+    }
+
+    /// 2 s of a sine at `hz`.
+    fn sine(hz: f32) -> Vec<f32> {
+        let w = TAU * hz / RATE;
+        (0..96_000).map(|i| 0.3 * (w * i as f32).sin()).collect()
+    }
+
+    /// `modulator` vocoded on the internal sawtooth or on `carrier`.
+    fn render(v: &mut Vocoder, modulator: &[f32], carrier: Option<&[f32]>) -> Vec<f32> {
+        render_quanta(modulator.len(), |span, y| match carrier {
+            Some(c) => Node::process_aux(v, &modulator[span.clone()], &c[span], 1, y, 1),
+            None => Node::process(v, &modulator[span], y, 1),
+        })
+    }
+
+    /// The modulator's spectrum shapes the carrier: a 1 kHz modulator brings out the 100 Hz
+    /// sawtooth's tenth harmonic over its far louder third.
+    #[test]
+    fn modulator_bands_shape_the_sawtooth() {
+        let y = render(
+            &mut vocoder(&[(PARAM_CARRIER_HZ, 100.0)]),
+            &sine(1000.0),
+            None,
+        );
+        assert!(amplitude(&y, 1000.0) > 10.0 * amplitude(&y, 300.0));
+    }
+
+    /// An external carrier replaces the sawtooth, and only comes through in the bands the
+    /// modulator fills.
+    #[test]
+    fn external_carrier_follows_the_modulator() {
+        let carrier = sine(1000.0);
+        let matched = render(&mut vocoder(&[]), &sine(1000.0), Some(&carrier));
+        let apart = render(&mut vocoder(&[]), &sine(4000.0), Some(&carrier));
+        assert!(amplitude(&matched, 1000.0) > 0.05);
+        assert!(amplitude(&apart, 1000.0) < 0.01 * amplitude(&matched, 1000.0));
+        assert!(amplitude(&matched, 110.0) < 1e-3);
+    }
+
+    /// With the modulator silent the output dies away within the release.
+    #[test]
+    fn silence_closes_the_bands() {
+        let mut x = sine(1000.0);
+        x[48_000..].fill(0.0);
+        let y = render(&mut vocoder(&[(PARAM_RELEASE_MS, 20.0)]), &x, None);
+        assert!(y[..48_000].iter().any(|v| v.abs() > 0.05));
+        assert!(y[60_000..].iter().all(|v| v.abs() < 1e-3));
+    }
+}
@@ -0,0 +1,417 @@
+// `wavetable_load` reads the table through a pointer the worklet got from us; null is the only
+// invalid value we can detect.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod tables;
+
+use core::f32::consts::FRAC_PI_4;
+use tables::{level_harmonics, Tables, FRAME_LEN, LEVELS};
+
+use dsp_core::gain::db_to_lin;
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+use dsp_core::param::clamp;
+use node_abi::{guard, Handle, LastError, Node, ParamDescriptor, ParamRing};
+
+pub const MAX_CHANNELS: usize = 8;
+pub const MAX_VOICES: usize = 8;
+pub const MAX_FRAMES: usize = 256;
+
+// Indices into `Wavetable::PARAMS`, in the order of the arguments of `wavetable_set_params`
+// and `wavetable_set_frequency`.
+pub const PARAM_POSITION: u32 = 0;
+pub const PARAM_VOICES: u32 = 1;
+pub const PARAM_DETUNE_CENTS: u32 = 2;
+pub const PARAM_SPREAD: u32 = 3;
+pub const PARAM_LEVEL_DB: u32 = 4;
+pub const PARAM_FREQ_HZ: u32 = 5;
+
+/// Band-limited wavetable oscillator (a generator: it has no input). Every frame of the table
+/// is kept as a set of mip levels with fewer harmonics each; a voice reads the level whose
+/// top harmonic stays below Nyquist at its pitch, crossfading between neighbouring frames
+/// for `position`. Up to eight unison voices spread their detune evenly across
+/// `detune_cents` and their pans across `spread`.
+pub struct Wavetable {
+    tables: Tables,
+    freq_hz: f32,
+    position: f32,
+    voices: usize,
+    detune_cents: f32,
+    spread: f32,
+    level_db: f32,
+    gain: f32,
+    phases: [f32; MAX_VOICES],
+    /// Per voice: increment in cycles per sample, mip level, and left/right gain.
+    incs: [f32; MAX_VOICES],
+    levels: [usize; MAX_VOICES],
+    pans: [(f32, f32); MAX_VOICES],
+    events: ParamRing,
+    error: LastError,
+    sample_rate_hz: f32,
+}
+
+/// Lowest mip level whose top harmonic stays below Nyquist at `inc` cycles per sample.
+fn mip_level(inc: f32) -> usize {
+    (0..LEVELS)
+        .find(|&l| level_harmonics(l) as f32 * inc <= 0.5)
+        .unwrap_or(LEVELS - 1)
+}
+
+impl Wavetable {
+    /// Recomputes the per-voice increments, levels and pans.
+    fn update_voices(&mut self) {
+        let base = self.freq_hz / self.sample_rate_hz;
+        let n = self.voices;
+        for v in 0..n {
+            // -1..1 across the voices; a single voice sits in the middle.
+            let spread = if n > 1 {
+                2.0 * v as f32 / (n - 1) as f32 - 1.0
+            } else {
+                0.0
+            };
+            let inc = base * (spread * self.detune_cents * 0.5 / 1200.0).exp2();
+            self.incs[v] = inc.min(0.5);
+            self.levels[v] = mip_level(inc);
+            let angle = FRAC_PI_4 * (1.0 + spread * self.spread);
+            self.pans[v] = (angle.cos(), angle.sin());
+        }
+    }
+
+    /// Voices are panned into channel pairs (even channels left, odd right); a mono output
+    /// gets their equal-power sum.
+    fn render(&mut self, output: &mut [f32], channels: usize) {
+        let frames = self.tables.frames();
+        let pos = self.position * (frames - 1) as f32;
+        let f0 = (pos as usize).min(frames - 1);
+        let f1 = (f0 + 1).min(frames - 1);
+        let morph = pos - f0 as f32;
+        let gain = self.gain / (self.voices as f32).sqrt();
+        for out in output.chunks_exact_mut(channels) {
+            let (mut left, mut right) = (0.0, 0.0);
+            for v in 0..self.voices {
+                let phase = self.phases[v];
+                let level = self.levels[v];
+                let a = self.tables.read(f0, level, phase);
+                let b = self.tables.read(f1, level, phase);
+                let s = a + (b - a) * morph;
+                left += s * self.pans[v].0;
+                right += s * self.pans[v].1;
+                self.phases[v] = (phase + self.incs[v]).fract();
+            }
+            if channels == 1 {
+                out[0] = (left + right) * FRAC_PI_4.cos() * gain;
+            } else {
+                for (ch, y) in out.iter_mut().enumerate() {
+                    *y = if ch % 2 == 0 { left } else { right } * gain;
+                }
+            }
+        }
+    }
+}
+
+impl Node for Wavetable {
+    const MAX_CHANNELS: usize = MAX_CHANNELS;
+    /// `Position` runs across the table's frames; `Detune` is between the outermost voices and
+    /// `Spread` the share of the full pan width they cover.
+    const PARAMS: &'static [ParamDescriptor] = &[
+        ParamDescriptor::linear("Position", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::stepped("Voices", "", 1.0, MAX_VOICES as f32, 1.0),
+        ParamDescriptor::linear("Detune", "cents", 0.0, 100.0, 0.0),
+        ParamDescriptor::linear("Spread", "", 0.0, 1.0, 0.0),
+        ParamDescriptor::linear("Level", "dB", -60.0, 0.0, -6.0),
+        ParamDescriptor::log("Frequency", "Hz", 0.1, 20_000.0, 220.0),
+    ];
+    const INPUT: bool = false;
+
+    fn new(sample_rate_hz: f32) -> Self {
+        let mut w = Wavetable {
+            tables: Tables::classic(),
+            freq_hz: 220.0,
+            position: 0.0,
+            voices: 1,
+            detune_cents: 0.0,
+            spread: 0.0,
+            level_db: -6.0,
+            gain: db_to_lin(-6.0),
+            // Spread the starting phases so stacked voices don't start as one loud spike.
+            phases: core::array::from_fn(|v| (v as f32 * 0.618_034).fract()),
+            incs: [0.0; MAX_VOICES],
+            levels: [0; MAX_VOICES],
+            pans: [(0.0, 0.0); MAX_VOICES],
+            events: ParamRing::new(),
+            error: LastError::new(),
+            sample_rate_hz,
+        };
+        w.update_voices();
+        w
+    }
+
+    fn set_param(&mut self, index: u32, value: f32) {
+        match index {
+            PARAM_POSITION => {
+                self.position = clamp(value, 0.0, 1.0);
+                return;
+            }
+            PARAM_VOICES => {
+                self.voices = clamp(value, 1.0, MAX_VOICES as f32).round() as usize;
+            }
+            PARAM_DETUNE_CENTS => self.detune_cents = clamp(value, 0.0, 100.0),
+            PARAM_SPREAD => self.spread = clamp(value, 0.0, 1.0),
+            PARAM_LEVEL_DB => {
+                self.level_db = clamp(value, -60.0, 0.0);
+                self.gain = db_to_lin(self.level_db);
+                return;
+            }
+            PARAM_FREQ_HZ => self.freq_hz = clamp(value, 0.1, 20_000.0),
+            _ => return,
+        }
+        self.update_voices();
+    }
+
+    fn get_param(&self, index: u32) -> f32 {
+        match index {
+            PARAM_POSITION => self.position,
+            PARAM_VOICES => self.voices as f32,
+            PARAM_DETUNE_CENTS => self.detune_cents,
+            PARAM_SPREAD => self.spread,
+            PARAM_LEVEL_DB => self.level_db,
+            PARAM_FREQ_HZ => self.freq_hz,
+            _ => 0.0,
+        }
+    }
+
+    fn param_events(&mut self) -> &mut ParamRing {
+        &mut self.events
+    }
+
+    fn last_error(&mut self) -> &mut LastError {
+        &mut self.error
+    }
+
+    fn process(&mut self, _input: &[f32], output: &mut [f32], channels: usize) {
+        self.render(output, channels);
+    }
+
+    /// Restarts every voice from its initial phase; the loaded tables are kept.
+    fn reset(&mut self) {
+        self.phases = core::array::from_fn(|v| (v as f32 * 0.618_034).fract());
+    }
+}
+
+node_abi::export_node!(Wavetable {
+    abi_version: wavetable_abi_version,
+    new: wavetable_new,
+    free: wavetable_free,
+    param_count: wavetable_param_count,
+    param_descriptors: wavetable_param_descriptors,
+    set_param: wavetable_set_param,
+    get_param: wavetable_get_param,
+    param_events: wavetable_param_events,
+    process: wavetable_process,
+    process_offline: wavetable_process_offline,
+    latency_samples: wavetable_latency_samples,
+    io_layout: wavetable_get_io_layout,
+    process_io: wavetable_process_io,
+    reset: wavetable_reset,
+    state_size: wavetable_state_size,
+    save_state: wavetable_save_state,
+    load_state: wavetable_load_state,
+    preset_size: wavetable_preset_size,
+    save_preset: wavetable_save_preset,
+    load_preset: wavetable_load_preset,
+    last_error_code: wavetable_last_error_code,
+    last_error_message: wavetable_last_error_message_ptr,
+});
+
+node_abi::test_params!(Wavetable);
+
+dsp_core::test_block_sizes!(
+    wavetable_new,
+    wavetable_process(input, output),
+    wavetable_free
+);
+
+node_abi::test_io_block!(Wavetable);
+
+#[cfg(feature = "bindgen")]
+node_abi::bindgen_node!(Wavetable as WavetableNode);
+
+/// Loads a wavetable the worklet copied into a `wasm_alloc`ed buffer: `frames` (1–256) single
+/// cycles of `frame_len` samples each, back to back (2048 is native; other lengths are
+/// resampled). Each frame is band-limited into its mip levels and the whole table is
+/// normalised to a peak of 1. Allocates, so call it from the message handler.
+#[no_mangle]
+pub extern "C" fn wavetable_load(
+    handle: Handle,
+    data_ptr: *const f32,
+    frame_len: usize,
+    frames: usize,
+) {
+    if data_ptr.is_null() || frame_len < 2 || frames == 0 {
+        return;
+    }
+    let frames = frames.min(MAX_FRAMES);
+    let frame_len = frame_len.min(FRAME_LEN * 4);
+    let data = unsafe { core::slice::from_raw_parts(data_ptr, frame_len * frames) };
+    guard(handle, |w: &mut Wavetable| {
+        w.tables = Tables::build(data, frame_len, frames)
+    });
+}
+
+/// `wavetable_load` for the bindgen class: `data` holds `frames` cycles of `frame_len`
+/// samples.
+#[cfg(feature = "bindgen")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+impl WavetableNode {
+    pub fn load(&mut self, data: &[f32], frame_len: usize, frames: usize) {
+        let frame_len = frame_len.min(FRAME_LEN * 4);
+        let frames = frames.min(MAX_FRAMES).min(data.len() / frame_len.max(1));
+        if frame_len < 2 || frames == 0 {
+            return;
+        }
+        self.node.tables = Tables::build(&data[..frame_len * frames], frame_len, frames);
+    }
+}
+
+/// `Position`, `Voices`, `Detune`, `Spread` and `Level` in one call.
+#[no_mangle]
+pub extern "C" fn wavetable_set_params(
+    handle: Handle,
+    position: f32,
+    voices: u32,
+    detune_cents: f32,
+    spread: f32,
+    level_db: f32,
+) {
+    guard(handle, |w: &mut Wavetable| {
+        w.set_param(PARAM_POSITION, position);
+        w.set_param(PARAM_VOICES, voices as f32);
+        w.set_param(PARAM_DETUNE_CENTS, detune_cents);
+        w.set_param(PARAM_SPREAD, spread);
+        w.set_param(PARAM_LEVEL_DB, level_db);
+    });
+}
+
+/// Pitch in Hz, as `Frequency`.
+#[no_mangle]
+pub extern "C" fn wavetable_set_frequency(handle: Handle, freq_hz: f32) {
+    guard(handle, |w: &mut Wavetable| {
+        w.set_param(PARAM_FREQ_HZ, freq_hz)
+    });
+}
+
+/// Pitch as a MIDI note number (fractional for pitch bend); 69 is A4 at 440 Hz.
+#[no_mangle]
+pub extern "C" fn wavetable_set_note(handle: Handle, note: f32) {
+    let hz = 440.0 * ((clamp(note, -24.0, 151.0) - 69.0) / 12.0).exp2();
+    wavetable_set_frequency(handle, hz);
+}
+
+#[cfg(not(feature = "rack"))]
+dsp_core::export_memory!();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dsp_core::blocksize::amplitude;
+
+    const RATE: f32 = 48_000.0;
+
+    fn wavetable(params: &[(u32, f32)]) -> Wavetable {
+        let mut w = Wavetable::new(RATE);
+        for &(index, value) in params {
+            w.set_param(index, value);
+        }
+        w
+    }
+
+    /// 1.5 s of `w` on `channels` channels, in render quanta.
+    fn render(w: &mut Wavetable, channels: usize) -> Vec<f32> {
+        let mut output = vec![0.0; 72_000 * channels];
+        for y in output.chunks_mut(128 * channels) {
+            Node::process(w, &[], y, channels);
+        }
+        output
+    }
+
+    /// The mip level follows the pitch: a low note gets the full table, a high one only the
+    /// harmonics that stay below Nyquist.
+    #[test]
+    fn mip_level_follows_pitch() {
+        let w = wavetable(&[(PARAM_FREQ_HZ, 20.0)]);
+        assert_eq!(w.levels[0], 0);
+        let w = wavetable(&[(PARAM_FREQ_HZ, 5000.0)]);
+        assert_eq!(level_harmonics(w.levels[0]), 4);
+        assert_eq!(level_harmonics(w.levels[0] - 1), 8);
+    }
+
+    /// A saw at 5 kHz keeps its harmonics up to 20 kHz and folds nothing back below Nyquist.
+    #[test]
+    fn high_saw_does_not_alias() {
+        let mut w = wavetable(&[(PARAM_POSITION, 2.0 / 3.0), (PARAM_FREQ_HZ, 5000.0)]);
+        let y = render(&mut w, 1);
+        let fundamental = amplitude(&y, 5000.0);
+        assert!(fundamental > 0.1, "{fundamental}");
+        for k in 2..=4 {
+            let h = amplitude(&y, 5000.0 * k as f32) * k as f32 / fundamental;
+            assert!((h - 1.0).abs() < 0.1, "harmonic {k}: {h}");
+        }
+        // Where the 5th to 9th harmonics would fold to.
+        for alias in [23_000.0, 18_000.0, 13_000.0, 8_000.0, 3_000.0] {
+            let a = amplitude(&y, alias) / fundamental;
+            assert!(a < 1e-3, "{alias} Hz: {a}");
+        }
+    }
+
+    /// `Position` morphs from the sine at 0 to the square at 1.
+    #[test]
+    fn position_morphs_across_frames() {
+        let harmonics = |position: f32| {
+            let y = render(&mut wavetable(&[(PARAM_POSITION, position)]), 1);
+            let fundamental = amplitude(&y, 220.0);
+            (
+                amplitude(&y, 440.0) / fundamental,
+                amplitude(&y, 660.0) / fundamental,
+            )
+        };
+        let (second, third) = harmonics(0.0);
+        assert!(second < 1e-3 && third < 1e-3, "{second} {third}");
+        let (second, third) = harmonics(1.0);
+        assert!(
+            second < 1e-3 && (third - 1.0 / 3.0).abs() < 0.01,
+            "{second} {third}"
+        );
+        let (_, half) = harmonics(5.0 / 6.0);
+        assert!(half > 0.1 && half < 1.0 / 3.0, "{half}");
+    }
+
+    /// Unison voices sit at the detune's ends and centre, panned apart by `Spread`.
+    #[test]
+    fn unison_detunes_and_spreads() {
+        let mut w = wavetable(&[
+            (PARAM_FREQ_HZ, 480.0),
+            (PARAM_VOICES, 3.0),
+            (PARAM_DETUNE_CENTS, 100.0),
+            (PARAM_SPREAD, 1.0),
+        ]);
+        let y = render(&mut w, 2);
+        let left: Vec<f32> = y.iter().step_by(2).copied().collect();
+        let right: Vec<f32> = y.iter().skip(1).step_by(2).copied().collect();
+        let (low, high) = (
+            480.0 * (-50.0_f32 / 1200.0).exp2(),
+            480.0 * (50.0_f32 / 1200.0).exp2(),
+        );
+
+        // The centre voice is split equally; the outer ones are hard left and right, at
+        // sqrt(2) of the centre's share.
+        let centre = amplitude(&left, 480.0);
+        assert!((amplitude(&right, 480.0) - centre).abs() < 0.01 * centre);
+        let outer = centre * 2.0_f32.sqrt();
+        assert!((amplitude(&left, low) - outer).abs() < 0.05 * centre);
+        assert!((amplitude(&right, high) - outer).abs() < 0.05 * centre);
+        assert!(amplitude(&right, low) < 0.05 * centre);
+        assert!(amplitude(&left, high) < 0.05 * centre);
+    }
+}
@@ -0,0 +1,123 @@
+use alloc::{vec, vec::Vec};
+use dsp_core::fft::{Complex, Fft};
+
+#[cfg(not(feature = "std"))]
+use dsp_core::math::Float;
+
+/// Samples per single-cycle frame as loaded (other lengths are resampled to it).
+pub const FRAME_LEN: usize = 2048;
+/// Mip levels per frame; level `l` keeps harmonics up to `FRAME_LEN / 2 >> l`.
+pub const LEVELS: usize = 11;
+/// Shortest level table; the top levels keep this length so interpolation stays smooth.
+const MIN_LEVEL_LEN: usize = 64;
+
+/// Band-limited mip-mapped copies of every frame of a wavetable, stored back to back.
+pub struct Tables {
+    data: Vec<f32>,
+    frames: usize,
+    /// `(offset, len)` of each level within a frame's block, and the block size.
+    levels: [(usize, usize); LEVELS],
+    stride: usize,
+}
+
+fn level_len(l: usize) -> usize {
+    (FRAME_LEN >> l).max(MIN_LEVEL_LEN)
+}
+
+/// Highest harmonic kept at level `l`.
+pub fn level_harmonics(l: usize) -> usize {
+    (FRAME_LEN / 2) >> l
+}
+
+impl Tables {
+    /// `cycles` holds `frames` single cycles of `frame_len` samples each.
+    pub fn build(cycles: &[f32], frame_len: usize, frames: usize) -> Self {
+        let mut levels = [(0, 0); LEVELS];
+        let mut stride = 0;
+        for (l, level) in levels.iter_mut().enumerate() {
+            *level = (stride, level_len(l));
+            stride += level_len(l);
+        }
+        let ffts: Vec<Fft> = (0..LEVELS).map(|l| Fft::new(level_len(l))).collect();
+        let mut data = vec![0.0; stride * frames];
+        let mut spectrum = vec![Complex::default(); FRAME_LEN];
+        let mut scratch = vec![Complex::default(); FRAME_LEN];
+
+        for (f, block) in data.chunks_exact_mut(stride).enumerate() {
+            let cycle = &cycles[f * frame_len..(f + 1) * frame_len];
+            for (i, s) in spectrum.iter_mut().enumerate() {
+                let pos = i as f32 * frame_len as f32 / FRAME_LEN as f32;
+                let j = pos as usize;
+                let t = pos - j as f32;
+                let a = cycle[j];
+                let b = cycle[(j + 1) % frame_len];
+                let v = a + (b - a) * t;
+                *s = Complex::new(if v.is_finite() { v } else { 0.0 }, 0.0);
+            }
+            ffts[0].forward(&mut spectrum);
+
+            for (l, &(offset, len)) in levels.iter().enumerate() {
+                let buf = &mut scratch[..len];
+                buf.fill(Complex::default());
+                let scale = len as f32 / FRAME_LEN as f32;
+                let top = level_harmonics(l).min(len / 2 - 1);
+                for h in 1..=top {
+                    let c = spectrum[h];
+                    buf[h] = Complex::new(c.re * scale, c.im * scale);
+                    buf[len - h] = Complex::new(c.re * scale, -c.im * scale);
+                }
+                ffts[l].inverse(buf);
+                for (d, c) in block[offset..offset + len].iter_mut().zip(buf.iter()) {
+                    *d = c.re;
+                }
+            }
+        }
+
+        let peak = (0..frames)
+            .flat_map(|f| &data[f * stride..f * stride + levels[0].1])
+            .fold(0.0_f32, |m, v| m.max(v.abs()));
+        if peak > 1e-6 {
+            for v in &mut data {
+                *v /= peak;
+            }
+        }
+        Self {
+            data,
+            frames,
+            levels,
+            stride,
+        }
+    }
+
+    /// Sine, triangle, saw and square, for morphing before anything is loaded.
+    pub fn classic() -> Self {
+        let shapes: [fn(f32) -> f32; 4] = [
+            |p| (2.0 * core::f32::consts::PI * p).sin(),
+            |p| 1.0 - 4.0 * (p - 0.5).abs(),
+            |p| 2.0 * p - 1.0,
+            |p| if p < 0.5 { 1.0 } else { -1.0 },
+        ];
+        let cycles: Vec<f32> = shapes
+            .iter()
+            .flat_map(|f| (0..FRAME_LEN).map(move |i| f(i as f32 / FRAME_LEN as f32)))
+            .collect();
+        Self::build(&cycles, FRAME_LEN, shapes.len())
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Linear read of `frame` at `level`, `phase` in cycles (0..1).
+    #[inline]
+    pub fn read(&self, frame: usize, level: usize, phase: f32) -> f32 {
+        let (offset, len) = self.levels[level];
+        let table = &self.data[frame * self.stride + offset..][..len];
+        let pos = phase * len as f32;
+        let i = pos as usize % len;
+        let t = pos - pos.floor();
+        let a = table[i];
+        let b = table[(i + 1) % len];
+        a + (b - a) * t
+    }
+}
@@ -0,0 +1,54 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+ROOT_DIR="$(cd -- "$(dirname -- "${BASH_SOURCE[0]}")" && pwd)"
+DSP_DIR="$ROOT_DIR"
+OUT_WASM="$ROOT_DIR/rack.wasm"
+# simd128 build, picked by the loader where the browser validates it. Both run every node's
+# filters in f64, as the EQ's own builds do (see `f64` in Cargo.toml).
+OUT_SIMD_WASM="$ROOT_DIR/rack.simd.wasm"
+
+TARGET="wasm32-unknown-unknown"
+# no_std, with dsp-core's small allocator in place of std's (`dsp_core::bump`).
+
+if ! command -v cargo >/dev/null 2>&1; then
+  echo "cargo not found" >&2
+  exit 1
+fi
+
+echo "Building the node rack (Rust -> WASM)..."
+
+# Prefer rustup-managed toolchains so `rustup target add wasm32-unknown-unknown` works as expected
+# even if Homebrew's `cargo/rustc` appear earlier in PATH.
+if command -v rustup >/dev/null 2>&1; then
+  TOOLCHAIN="${RUSTUP_TOOLCHAIN:-stable}"
+  CARGO_BIN="$(rustup which cargo --toolchain "$TOOLCHAIN")"
+  RUSTC_BIN="$(rustup which rustc --toolchain "$TOOLCHAIN")"
+  TOOLCHAIN_BIN_DIR="$(dirname -- "$RUSTC_BIN")"
+  PATH="$TOOLCHAIN_BIN_DIR:$PATH"
+  export RUSTC="$RUSTC_BIN"
+else
+  CARGO_BIN=cargo
+fi
+
+# build <target dir> [cargo args...]: the scalar and simd128 builds keep separate target dirs
+# so switching RUSTFLAGS doesn't rebuild the other from scratch.
+build() {
+  local target_dir="$1"
+  shift
+  (cd "$DSP_DIR" && "$CARGO_BIN" build --release --target "$TARGET" --target-dir "$target_dir" "$@")
+  local wasm_path="$target_dir/$TARGET/release/webaudio_playground_rack.wasm"
+  if [[ ! -f "$wasm_path" ]]; then
+    echo "WASM output not found at: $wasm_path" >&2
+    exit 1
+  fi
+  BUILT_WASM="$wasm_path"
+}
+
+build "$DSP_DIR/target" --no-default-features --features bump-alloc,f64
+cp -f "$BUILT_WASM" "$OUT_WASM"
+echo "Wrote $OUT_WASM"
+
+RUSTFLAGS="${RUSTFLAGS:-} -C target-feature=+simd128" build "$DSP_DIR/target/simd" --no-default-features --features bump-alloc,simd,f64
+cp -f "$BUILT_WASM" "$OUT_SIMD_WASM"
+echo "Wrote $OUT_SIMD_WASM"
@@ -0,0 +1,512 @@
+//! Chains of rack nodes rendered inside the module. The worklet builds a chain once and calls
+//! `chain_process` once per block, where it would otherwise call each node's process export in
+//! turn and copy the audio across the boundary either side of every call:
+//!
+//! - `chain_new(channels) -> chain` and `chain_free(chain)`: `channels` (1 to `MAX_CHANNELS`)
+//!   is the width of the chain's input, output and every node in it; `chain` is a handle,
+//!   generation-checked like a node's, so a freed or made-up one makes every export a no-op
+//! - `chain_add(chain, node) -> slot`: appends a node from `node_create`; slots count from 0 in
+//!   the order nodes are added, which is the order they run in. `CHAIN_IO` for a freed node,
+//!   or one narrower than the chain (`node_type_max_channels`: a two-channel node in a chain of
+//!   three or more)
+//! - `chain_connect(chain, from, to) -> u32`: feeds `from`'s output into `to`'s input, 1 if it
+//!   could. `CHAIN_IO` as `from` is the chain's input and as `to` its output; a slot can only
+//!   feed a later one, so a chain has no cycles
+//! - `chain_process(chain, in_ptr, aux_ptr, out_ptr, frames, aux_channels)`: renders a block;
+//!   a null `in_ptr` is silence, and `aux_ptr` (`aux_channels` interleaved) goes to every
+//!   side-chained node, cut down to the channels each one takes
+//! - `chain_latency_samples(chain) -> u32` and `chain_reset(chain)`
+//!
+//! A serial chain of three nodes is slots 0, 1 and 2 connected `CHAIN_IO` to 0, 0 to 1, 1 to
+//! 2 and 2 to `CHAIN_IO`. Every input (a slot's, or the chain's output) is the sum of what's
+//! connected to it, silence if nothing is. Meters and analyzers pass their input through. To
+//! change a chain, build a new one.
+//!
+//! The chain doesn't own its nodes: their own exports (parameters, readings) go on working
+//! while they're in it, and they're freed with `node_free`. It keeps each node's rack handle
+//! and looks it up on every call, so a node freed first renders as silence (a meter's slot
+//! still passes its input through) instead of being read after it's gone.
+
+use alloc::{vec, vec::Vec};
+use core::ptr;
+
+use dsp_core::offline::{self, CHUNK_FRAMES};
+use node_abi::{Handle, Registry};
+
+use crate::{Kind, Process};
+
+/// As `chain_connect`'s `from`, the chain's input; as its `to`, the chain's output.
+pub const CHAIN_IO: u32 = u32::MAX;
+
+/// Widest chain `chain_new` makes, as wide as the widest node.
+pub const MAX_CHANNELS: usize = 8;
+
+struct Slot {
+    /// The rack handle `chain_add` was given; it finds nothing once the node is freed.
+    node: Handle,
+    kind: &'static Kind,
+    /// What's summed into its input: earlier slots, or `CHAIN_IO`.
+    inputs: Vec<u32>,
+    /// Its output for the chunk being rendered.
+    output: Vec<f32>,
+}
+
+pub struct Chain {
+    channels: usize,
+    slots: Vec<Slot>,
+    /// What's summed into the chain's output.
+    outputs: Vec<u32>,
+    /// Input of the slot being run.
+    scratch: Vec<f32>,
+    /// The aux block cut down to what the slot being run takes, when it takes fewer channels.
+    aux: Vec<f32>,
+}
+
+impl Chain {
+    fn is_source(&self, slot: u32) -> bool {
+        slot == CHAIN_IO || (slot as usize) < self.slots.len()
+    }
+
+    /// Renders one chunk of at most `CHUNK_FRAMES` frames through every slot; `aux` holds
+    /// `aux_channels` interleaved.
+    fn render(
+        &mut self,
+        input: Option<&[f32]>,
+        aux: Option<&[f32]>,
+        frames: usize,
+        aux_channels: usize,
+    ) {
+        let n = frames * self.channels;
+        for i in 0..self.slots.len() {
+            let (done, rest) = self.slots.split_at_mut(i);
+            let slot = &mut rest[0];
+            let x = &mut self.scratch[..n];
+            mix(x, &slot.inputs, input, done);
+            let y = &mut slot.output[..n];
+            match crate::node(slot.node) {
+                Some(node) => {
+                    let (aux, aux_channels) =
+                        fit_aux(aux, aux_channels, slot.kind.aux_channels, &mut self.aux);
+                    let (in_ptr, out_ptr) = (x.as_ptr(), y.as_mut_ptr());
+                    node.process(in_ptr, aux, out_ptr, frames, self.channels, aux_channels);
+                }
+                None => y.fill(0.0),
+            }
+            if matches!(slot.kind.process, Process::Analyzer(_)) {
+                y.copy_from_slice(x);
+            }
+        }
+    }
+}
+
+/// The aux block for a node taking up to `width` channels: `aux` as it is when it's no wider,
+/// otherwise the first `width` channels of each frame, copied into `scratch`. Null (and 0)
+/// for a node without an aux input, or no aux.
+fn fit_aux(
+    aux: Option<&[f32]>,
+    channels: usize,
+    width: usize,
+    scratch: &mut [f32],
+) -> (*const f32, usize) {
+    let Some(aux) = aux.filter(|_| width > 0) else {
+        return (ptr::null(), 0);
+    };
+    if channels <= width {
+        return (aux.as_ptr(), channels);
+    }
+    let frames = aux.len() / channels;
+    let fitted = &mut scratch[..frames * width];
+    for (to, from) in fitted
+        .chunks_exact_mut(width)
+        .zip(aux.chunks_exact(channels))
+    {
+        to.copy_from_slice(&from[..width]);
+    }
+    (fitted.as_ptr(), width)
+}
+
+/// Sums `sources` into `out`: the chain's input for `CHAIN_IO` (nothing when it's silent),
+/// otherwise a slot's output.
+fn mix(out: &mut [f32], sources: &[u32], input: Option<&[f32]>, slots: &[Slot]) {
+    out.fill(0.0);
+    for &source in sources {
+        let signal = match source {
+            CHAIN_IO => match input {
+                Some(input) => input,
+                None => continue,
+            },
+            slot => &slots[slot as usize].output,
+        };
+        for (o, s) in out.iter_mut().zip(signal) {
+            *o += s;
+        }
+    }
+}
+
+/// Latency from the chain's input to the end of `sources`' slowest path, given each slot's in
+/// `at`. Paths of different latency are mixed as they come, not delayed to line up.
+fn slowest(sources: &[u32], at: &[u32]) -> u32 {
+    let arrival = |&s: &u32| if s == CHAIN_IO { 0 } else { at[s as usize] };
+    sources.iter().map(arrival).max().unwrap_or(0)
+}
+
+/// The live chains, by handle.
+fn with_chains<R>(f: impl FnOnce(&mut Registry<Chain>) -> R) -> R {
+    node_abi::registry_static!(CHAINS: Registry<Chain>);
+    CHAINS.with(|r| f(&mut r.borrow_mut()))
+}
+
+/// Runs `f` on the chain `handle` names, if it hasn't been freed. The registry isn't borrowed
+/// meanwhile, so `f` can look nodes up.
+fn with_chain<R>(handle: Handle, f: impl FnOnce(&mut Chain) -> R) -> Option<R> {
+    let instance = with_chains(|r| r.get(handle))?;
+    Some(f(unsafe { &mut (*instance).node }))
+}
+
+#[no_mangle]
+pub extern "C" fn chain_new(channels: u32) -> Handle {
+    let channels = (channels as usize).clamp(1, MAX_CHANNELS);
+    with_chains(|r| {
+        r.insert(Chain {
+            channels,
+            slots: Vec::new(),
+            outputs: Vec::new(),
+            scratch: vec![0.0; CHUNK_FRAMES * channels],
+            aux: vec![0.0; CHUNK_FRAMES * MAX_CHANNELS],
+        })
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn chain_free(chain: Handle) {
+    drop(with_chains(|r| r.remove(chain)));
+}
+
+#[no_mangle]
+pub extern "C" fn chain_add(chain: Handle, node: Handle) -> u32 {
+    let Some(kind) = crate::node(node).map(|n| n.kind()) else {
+        return CHAIN_IO;
+    };
+    with_chain(chain, |c| {
+        if kind.max_channels < c.channels {
+            return CHAIN_IO;
+        }
+        c.slots.push(Slot {
+            node,
+            kind,
+            inputs: Vec::new(),
+            output: vec![0.0; CHUNK_FRAMES * c.channels],
+        });
+        (c.slots.len() - 1) as u32
+    })
+    .unwrap_or(CHAIN_IO)
+}
+
+/// 1 if connected (or already was), 0 for an unknown slot or a slot feeding itself or an
+/// earlier one.
+#[no_mangle]
+pub extern "C" fn chain_connect(chain: Handle, from: u32, to: u32) -> u32 {
+    with_chain(chain, |c| {
+        let backwards = from != CHAIN_IO && to != CHAIN_IO && from >= to;
+        if !c.is_source(from) || !c.is_source(to) || backwards {
+            return 0;
+        }
+        let sources = match to {
+            CHAIN_IO => &mut c.outputs,
+            slot => &mut c.slots[slot as usize].inputs,
+        };
+        if !sources.contains(&from) {
+            sources.push(from);
+        }
+        1
+    })
+    .unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn chain_process(
+    chain: Handle,
+    in_ptr: *const f32,
+    aux_ptr: *const f32,
+    out_ptr: *mut f32,
+    frames: usize,
+    aux_channels: usize,
+) {
+    if out_ptr.is_null() {
+        return;
+    }
+    with_chain(chain, |c| {
+        let channels = c.channels;
+        let n = frames.saturating_mul(channels);
+        let input = (!in_ptr.is_null()).then(|| unsafe { core::slice::from_raw_parts(in_ptr, n) });
+        let aux = (!aux_ptr.is_null() && aux_channels > 0).then(|| unsafe {
+            core::slice::from_raw_parts(aux_ptr, frames.saturating_mul(aux_channels))
+        });
+        let output = unsafe { core::slice::from_raw_parts_mut(out_ptr, n) };
+        for span in offline::chunks(frames) {
+            let at = span.start * channels..span.end * channels;
+            let input = input.map(|input| &input[at.clone()]);
+            let aux = aux.map(|aux| &aux[span.start * aux_channels..span.end * aux_channels]);
+            c.render(input, aux, span.len(), aux_channels);
+            mix(&mut output[at], &c.outputs, input, &c.slots);
+        }
+    });
+}
+
+/// The slowest path from the chain's input to its output.
+#[no_mangle]
+pub extern "C" fn chain_latency_samples(chain: Handle) -> u32 {
+    with_chain(chain, |c| {
+        let mut at = Vec::with_capacity(c.slots.len());
+        for slot in &c.slots {
+            let latency = crate::node(slot.node).map_or(0, |n| n.latency_samples());
+            at.push(slowest(&slot.inputs, &at).saturating_add(latency));
+        }
+        slowest(&c.outputs, &at)
+    })
+    .unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn chain_reset(chain: Handle) {
+    with_chain(chain, |c| {
+        for slot in &c.slots {
+            if let Some(node) = crate::node(slot.node) {
+                node.reset();
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node_create, node_free, node_latency_samples, node_process, node_type_count, node_type_name,
+    };
+    use core::ffi::CStr;
+    use dsp_core::blocksize::{test_signal, CHANNELS, SAMPLE_RATE};
+    use node_abi::NULL_HANDLE;
+
+    fn create(name: &str) -> Handle {
+        let named = |&id: &u32| {
+            let p = node_type_name(id);
+            unsafe { CStr::from_ptr(p.cast()) }.to_bytes() == name.as_bytes()
+        };
+        let id = (0..node_type_count()).find(named).unwrap();
+        node_create(id, SAMPLE_RATE)
+    }
+
+    fn serial(chain: Handle, nodes: &[Handle]) {
+        let mut from = CHAIN_IO;
+        for &node in nodes {
+            let slot = chain_add(chain, node);
+            assert_eq!(chain_connect(chain, from, slot), 1);
+            from = slot;
+        }
+        assert_eq!(chain_connect(chain, from, CHAIN_IO), 1);
+    }
+
+    #[test]
+    fn renders_a_serial_chain_as_the_nodes_do_in_turn() {
+        let frames = 1_000;
+        let input = test_signal(frames, CHANNELS, 0);
+        let names = ["tilt", "distortion", "tape", "levelMeter", "compressor"];
+
+        let nodes: Vec<_> = names.iter().map(|name| create(name)).collect();
+        let chain = chain_new(CHANNELS as u32);
+        serial(chain, &nodes);
+        let mut chained = vec![0.0; frames * CHANNELS];
+        let (in_ptr, out_ptr) = (input.as_ptr(), chained.as_mut_ptr());
+        chain_process(chain, in_ptr, ptr::null(), out_ptr, frames, 0);
+        chain_free(chain);
+        nodes.into_iter().for_each(|node| node_free(node));
+
+        let mut direct = input.clone();
+        for name in names {
+            let node = create(name);
+            let mut output = direct.clone();
+            for span in offline::chunks(frames) {
+                let at = span.start * CHANNELS;
+                let (x, y) = (direct[at..].as_ptr(), output[at..].as_mut_ptr());
+                node_process(node, x, ptr::null(), y, span.len(), CHANNELS, 0);
+            }
+            node_free(node);
+            direct = output;
+        }
+        assert_eq!(chained, direct);
+        assert!(chained != input);
+    }
+
+    #[test]
+    fn sums_what_is_connected_to_each_input() {
+        let frames = 300;
+        let input = test_signal(frames, CHANNELS, 0);
+        let meter = create("levelMeter");
+        let tone = create("testTone");
+        let chain = chain_new(CHANNELS as u32);
+        let (m, t) = (chain_add(chain, meter), chain_add(chain, tone));
+        assert_eq!((m, t), (0, 1));
+        // Dry input plus the meter's pass-through; the tone isn't connected to the output.
+        for (from, to) in [
+            (CHAIN_IO, m),
+            (CHAIN_IO, CHAIN_IO),
+            (m, CHAIN_IO),
+            (m, CHAIN_IO),
+        ] {
+            assert_eq!(chain_connect(chain, from, to), 1);
+        }
+        for (from, to) in [(t, m), (m, m), (2, CHAIN_IO), (CHAIN_IO, 2)] {
+            assert_eq!(chain_connect(chain, from, to), 0);
+        }
+        assert_eq!(chain_add(chain, NULL_HANDLE), CHAIN_IO);
+
+        let mut output = vec![0.0; frames * CHANNELS];
+        chain_process(
+            chain,
+            input.as_ptr(),
+            ptr::null(),
+            output.as_mut_ptr(),
+            frames,
+            0,
+        );
+        assert!(output.iter().zip(&input).all(|(y, x)| *y == 2.0 * x));
+        // Silent input: only the tone, once connected, is heard.
+        assert_eq!(chain_connect(chain, t, CHAIN_IO), 1);
+        chain_process(
+            chain,
+            ptr::null(),
+            ptr::null(),
+            output.as_mut_ptr(),
+            frames,
+            0,
+        );
+        assert!(output.iter().any(|&y| y != 0.0));
+
+        chain_reset(chain);
+        chain_free(chain);
+        node_free(meter);
+        node_free(tone);
+    }
+
+    #[test]
+    fn reports_the_slowest_path() {
+        let (tape, gate, tape2) = (create("tape"), create("spectralGate"), create("tape"));
+        let (t, g) = (node_latency_samples(tape), node_latency_samples(gate));
+        assert!(t > 0 && g > t);
+        let chain = chain_new(CHANNELS as u32);
+        serial(chain, &[tape]);
+        assert_eq!(chain_latency_samples(chain), t);
+        // The gate in parallel with the tape, then both into the tape again.
+        let gate_slot = chain_add(chain, gate);
+        let tape_again = chain_add(chain, tape2);
+        chain_connect(chain, CHAIN_IO, gate_slot);
+        chain_connect(chain, 0, tape_again);
+        chain_connect(chain, gate_slot, tape_again);
+        chain_connect(chain, tape_again, CHAIN_IO);
+        assert_eq!(chain_latency_samples(chain), g + t);
+        chain_free(chain);
+        [tape, gate, tape2]
+            .into_iter()
+            .for_each(|node| node_free(node));
+    }
+
+    #[test]
+    fn rejects_nodes_narrower_than_the_chain() {
+        let wide = chain_new(4);
+        let stereo = chain_new(2);
+        let reverb = create("convolutionReverb");
+        let eq = create("eq");
+        assert_eq!(chain_add(wide, reverb), CHAIN_IO);
+        assert_eq!(chain_add(wide, eq), 0);
+        assert_eq!(chain_add(stereo, reverb), 0);
+        chain_free(wide);
+        chain_free(stereo);
+        node_free(reverb);
+        node_free(eq);
+    }
+
+    /// A node freed before its chain renders as its handle does once freed: silence from an
+    /// effect, the input passed on by a meter.
+    #[test]
+    fn freed_nodes_fall_silent_in_a_live_chain() {
+        let frames = 300;
+        let input = test_signal(frames, CHANNELS, 0);
+        let (meter, tape) = (create("levelMeter"), create("tape"));
+        let chain = chain_new(CHANNELS as u32);
+        serial(chain, &[meter, tape]);
+        assert!(chain_latency_samples(chain) > 0);
+        node_free(tape);
+        assert_eq!(chain_latency_samples(chain), 0);
+
+        let mut output = vec![1.0; frames * CHANNELS];
+        let (x, y) = (input.as_ptr(), output.as_mut_ptr());
+        chain_process(chain, x, ptr::null(), y, frames, 0);
+        assert!(output.iter().all(|&y| y == 0.0));
+
+        // The meter alone, freed too, still passes its input through.
+        let only_meter = chain_new(CHANNELS as u32);
+        serial(only_meter, &[meter]);
+        node_free(meter);
+        chain_process(only_meter, x, ptr::null(), y, frames, 0);
+        assert_eq!(output, input);
+        chain_reset(chain);
+        chain_free(chain);
+        chain_free(only_meter);
+    }
+
+    #[test]
+    fn null_and_freed_chains_are_ignored() {
+        let freed = chain_new(CHANNELS as u32);
+        chain_free(freed);
+        chain_free(freed);
+        let meter = create("levelMeter");
+        for chain in [NULL_HANDLE, freed, 0x1234_5678] {
+            let mut out = [1.0; 4];
+            chain_process(chain, out.as_ptr(), ptr::null(), out.as_mut_ptr(), 2, 0);
+            assert_eq!(out, [1.0; 4]);
+            assert_eq!(chain_add(chain, meter), CHAIN_IO);
+            assert_eq!(chain_connect(chain, CHAIN_IO, CHAIN_IO), 0);
+            assert_eq!(chain_latency_samples(chain), 0);
+            chain_reset(chain);
+            chain_free(chain);
+        }
+        node_free(meter);
+        let chain = chain_new(CHANNELS as u32);
+        assert_ne!(chain, freed);
+        assert_eq!(chain_add(chain, meter), CHAIN_IO);
+        chain_free(chain);
+    }
+
+    /// A stereo aux going into the filter, which takes one channel of cutoff modulation, is
+    /// cut down to its first channel rather than read as consecutive frames.
+    #[test]
+    fn fits_the_aux_to_each_node() {
+        use svf::{svf_free, svf_new, svf_process_modulated};
+        let frames = 1_000;
+        let input = test_signal(frames, CHANNELS, 0);
+        let aux: Vec<f32> = (0..frames)
+            .flat_map(|i| [(i as f32 * 0.01).sin(), -1.0])
+            .collect();
+        let svf = create("svf");
+        let chain = chain_new(CHANNELS as u32);
+        serial(chain, &[svf]);
+        let mut chained = vec![0.0; frames * CHANNELS];
+        let (x, y) = (input.as_ptr(), chained.as_mut_ptr());
+        chain_process(chain, x, aux.as_ptr(), y, frames, 2);
+        chain_free(chain);
+        node_free(svf);
+
+        let first: Vec<f32> = aux.iter().step_by(2).copied().collect();
+        let handle = svf_new(SAMPLE_RATE);
+        let mut direct = vec![0.0; frames * CHANNELS];
+        for span in offline::chunks(frames) {
+            let at = span.start * CHANNELS;
+            let (x, m) = (input[at..].as_ptr(), first[span.start..].as_ptr());
+            let y = direct[at..].as_mut_ptr();
+            svf_process_modulated(handle, x, m, y, span.len(), CHANNELS, 1);
+        }
+        svf_free(handle);
+        assert_eq!(chained, direct);
+    }
+}
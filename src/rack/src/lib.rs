@@ -0,0 +1,797 @@
+//! Every hand-written node crate linked into one WASM module, so the playground fetches and
+//! compiles one module instead of one per node, and the code the nodes share (dsp-core's FFT,
+//! filters, tables, the allocator) is in it once. The nodes' own exports (`eq_set_params`,
+//! `tuner_get_hz`, ...) are all there under their usual names, taking the node-abi handle
+//! `node_instance` gives; on top of them the rack exports a factory over a table of node
+//! types:
+//!
+//! - `node_type_count() -> u32`; a type id is a position in `KINDS`, below the count
+//! - `node_type_name(type_id) -> ptr` and `node_type_prefix(type_id) -> ptr`: the node's name
+//!   (its folder under `src/nodes`) and the prefix of its exports, NUL-terminated; null for an
+//!   unknown id
+//! - `node_type_max_channels(type_id) -> u32`: the widest interleaved block the node's process
+//!   export takes (its crate's `MAX_CHANNELS`, saturated); 0 for an unknown id
+//! - `node_create(type_id, sample_rate_hz) -> node` and `node_free(node)`: `node` is a rack
+//!   handle, generation-checked like a node-abi one (see `node_abi::registry`); 0 for an
+//!   unknown id
+//! - `node_type(node) -> u32` and `node_instance(node) -> handle`: what `node` is, and the
+//!   handle its own exports take
+//! - `node_process(node, in_ptr, aux_ptr, out_ptr, frames, channels, aux_channels)`: the
+//!   node's process export, with the buffers its kind takes (see `Process`)
+//! - `node_latency_samples(node) -> u32` and `node_reset(node)`
+//!
+//! plus `export_memory!`'s allocation functions, and `chain`'s for rendering several nodes in
+//! one call. A freed, stale or made-up node handle makes every export a no-op (0, or null).
+
+#![cfg_attr(not(feature = "std"), no_std)]
+// The exports are called from the worklet with pointers it got from us; null is the only
+// invalid value we can detect.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+extern crate alloc;
+
+use core::ptr;
+use node_abi::{Handle, Registry, NULL_HANDLE};
+
+pub mod chain;
+
+/// Which buffers a node type's process export reads and writes; `node_process` passes it
+/// those and null for the rest.
+#[derive(Clone, Copy)]
+pub enum Process {
+    /// Effects: `in_ptr` to `out_ptr`.
+    Effect(fn(Handle, *const f32, *mut f32, usize, usize)),
+    /// Generators (synths, sequencers, LFOs): `out_ptr` only.
+    Generator(fn(Handle, *mut f32, usize, usize)),
+    /// Meters and analyzers: `in_ptr` only; `out_ptr` is left as it is.
+    Analyzer(fn(Handle, *const f32, usize, usize)),
+    /// Effects keyed by a side-chain, carrier or clock (their `process_aux` export): `in_ptr`
+    /// and `aux_ptr` to `out_ptr`; a null `aux_ptr` is their plain process.
+    Sidechained(fn(Handle, *const f32, *const f32, *mut f32, usize, usize, usize)),
+}
+
+/// One node type: its names and its node-abi exports.
+pub struct Kind {
+    pub name: &'static str,
+    pub prefix: &'static str,
+    pub new: fn(f32) -> Handle,
+    pub free: fn(Handle),
+    pub latency_samples: fn(Handle) -> u32,
+    pub reset: fn(Handle),
+    pub process: Process,
+    /// The node crate's `MAX_CHANNELS`: a wider block is clamped to it, and read at the wrong
+    /// stride.
+    pub max_channels: usize,
+    /// The node's `AUX_CHANNELS` (0 without an aux input): as with `max_channels`, a wider aux
+    /// block is clamped to it and read at the wrong stride.
+    pub aux_channels: usize,
+}
+
+macro_rules! kind {
+    (
+        $name:literal, $prefix:literal,
+        $krate:ident::{$new:ident, $free:ident, $latency:ident, $reset:ident $(,)?},
+        $process:ident($($buffers:tt)+)
+    ) => {
+        Kind {
+            name: concat!($name, "\0"),
+            prefix: concat!($prefix, "\0"),
+            new: |sample_rate_hz| $krate::$new(sample_rate_hz),
+            free: |h| $krate::$free(h),
+            latency_samples: |h| $krate::$latency(h),
+            reset: |h| $krate::$reset(h),
+            process: kind!(@process $krate::$process($($buffers)+)),
+            max_channels: $krate::MAX_CHANNELS,
+            aux_channels: kind!(@aux $krate($($buffers)+)),
+        }
+    };
+    (@process $krate:ident::$process:ident(input, output)) => {
+        Process::Effect(|h, input, output, frames, channels| {
+            $krate::$process(h, input, output, frames, channels)
+        })
+    };
+    (@process $krate:ident::$process:ident(output)) => {
+        Process::Generator(|h, output, frames, channels| {
+            $krate::$process(h, ptr::null(), output, frames, channels)
+        })
+    };
+    (@process $krate:ident::$process:ident(input)) => {
+        Process::Analyzer(|h, input, frames, channels| {
+            $krate::$process(h, input, ptr::null_mut(), frames, channels)
+        })
+    };
+    (@process $krate:ident::$process:ident(input, aux($node:ident), output)) => {
+        Process::Sidechained(|h, input, aux, output, frames, channels, aux_channels| {
+            $krate::$process(h, input, aux, output, frames, channels, aux_channels)
+        })
+    };
+    (@aux $krate:ident(input, aux($node:ident), output)) => {
+        <$krate::$node as node_abi::Node>::AUX_CHANNELS
+    };
+    (@aux $krate:ident($($buffers:tt)+)) => {
+        0
+    };
+}
+
+/// The node types, by type id. Append new ones at the end, so saved ids keep their meaning.
+pub static KINDS: &[Kind] = &[
+    kind!(
+        "bitcrusher", "bitcrusher",
+        bitcrusher::{bitcrusher_new, bitcrusher_free, bitcrusher_latency_samples, bitcrusher_reset},
+        bitcrusher_process(input, output)
+    ),
+    kind!(
+        "chorus", "chorus",
+        chorus::{chorus_new, chorus_free, chorus_latency_samples, chorus_reset},
+        chorus_process(input, output)
+    ),
+    kind!(
+        "clipper", "clipper",
+        clipper::{clipper_new, clipper_free, clipper_latency_samples, clipper_reset},
+        clipper_process(input, output)
+    ),
+    kind!(
+        "comb", "comb",
+        comb::{comb_new, comb_free, comb_latency_samples, comb_reset},
+        comb_process(input, output)
+    ),
+    kind!(
+        "compressor", "compressor",
+        compressor::{compressor_new, compressor_free, compressor_latency_samples, compressor_reset},
+        compressor_process(input, output)
+    ),
+    kind!(
+        "console", "console",
+        console::{console_new, console_free, console_latency_samples, console_reset},
+        console_process(input, output)
+    ),
+    kind!(
+        "convolutionReverb", "convolver",
+        convolution_reverb::{
+            convolver_new,
+            convolver_free,
+            convolver_latency_samples,
+            convolver_reset,
+        },
+        convolver_process(input, output)
+    ),
+    kind!(
+        "dcBlocker", "dc_blocker",
+        dc_blocker::{dc_blocker_new, dc_blocker_free, dc_blocker_latency_samples, dc_blocker_reset},
+        dc_blocker_process(input, output)
+    ),
+    kind!(
+        "deEsser", "deesser",
+        de_esser::{deesser_new, deesser_free, deesser_latency_samples, deesser_reset},
+        deesser_process(input, output)
+    ),
+    kind!(
+        "diagnostics", "diagnostics",
+        diagnostics::{
+            diagnostics_new,
+            diagnostics_free,
+            diagnostics_latency_samples,
+            diagnostics_reset,
+        },
+        diagnostics_process(input)
+    ),
+    kind!(
+        "distortion", "distortion",
+        distortion::{distortion_new, distortion_free, distortion_latency_samples, distortion_reset},
+        distortion_process(input, output)
+    ),
+    kind!(
+        "drums", "drums",
+        drums::{drums_new, drums_free, drums_latency_samples, drums_reset},
+        drums_process(output)
+    ),
+    kind!(
+        "ducker", "ducker",
+        ducker::{ducker_new, ducker_free, ducker_latency_samples, ducker_reset},
+        ducker_process_sidechained(input, aux(Ducker), output)
+    ),
+    kind!(
+        "dynamicEq", "dyneq",
+        dynamic_eq::{dyneq_new, dyneq_free, dyneq_latency_samples, dyneq_reset},
+        dyneq_process(input, output)
+    ),
+    kind!(
+        "dynamicsMeter", "dynamics",
+        dynamics_meter::{dynamics_new, dynamics_free, dynamics_latency_samples, dynamics_reset},
+        dynamics_process(input)
+    ),
+    kind!(
+        "envelope", "envelope",
+        envelope::{envelope_new, envelope_free, envelope_latency_samples, envelope_reset},
+        envelope_process(output)
+    ),
+    kind!(
+        "eq", "eq",
+        eq::{eq_new, eq_free, eq_latency_samples, eq_reset},
+        eq_process(input, output)
+    ),
+    kind!(
+        "euclid", "euclid",
+        euclid::{euclid_new, euclid_free, euclid_latency_samples, euclid_reset},
+        euclid_process(output)
+    ),
+    kind!(
+        "exciter", "exciter",
+        exciter::{exciter_new, exciter_free, exciter_latency_samples, exciter_reset},
+        exciter_process(input, output)
+    ),
+    kind!(
+        "fdnReverb", "reverb",
+        fdn_reverb::{reverb_new, reverb_free, reverb_latency_samples, reverb_reset},
+        reverb_process(input, output)
+    ),
+    kind!(
+        "flanger", "flanger",
+        flanger::{flanger_new, flanger_free, flanger_latency_samples, flanger_reset},
+        flanger_process(input, output)
+    ),
+    kind!(
+        "fmSynth", "fm",
+        fm_synth::{fm_new, fm_free, fm_latency_samples, fm_reset},
+        fm_process(output)
+    ),
+    kind!(
+        "formant", "formant",
+        formant::{formant_new, formant_free, formant_latency_samples, formant_reset},
+        formant_process(input, output)
+    ),
+    kind!(
+        "frequencyShifter", "freqshift",
+        frequency_shifter::{
+            freqshift_new,
+            freqshift_free,
+            freqshift_latency_samples,
+            freqshift_reset,
+        },
+        freqshift_process(input, output)
+    ),
+    kind!(
+        "gate", "gate",
+        gate::{gate_new, gate_free, gate_latency_samples, gate_reset},
+        gate_process_sidechained(input, aux(Gate), output)
+    ),
+    kind!(
+        "goniometer", "goniometer",
+        goniometer::{goniometer_new, goniometer_free, goniometer_latency_samples, goniometer_reset},
+        goniometer_process(input)
+    ),
+    kind!(
+        "granular", "granular",
+        granular::{granular_new, granular_free, granular_latency_samples, granular_reset},
+        granular_process(output)
+    ),
+    kind!(
+        "levelMeter", "level_meter",
+        level_meter::{
+            level_meter_new,
+            level_meter_free,
+            level_meter_latency_samples,
+            level_meter_reset,
+        },
+        level_meter_process(input)
+    ),
+    kind!(
+        "lfo", "lfo",
+        lfo::{lfo_new, lfo_free, lfo_latency_samples, lfo_reset},
+        lfo_process(output)
+    ),
+    kind!(
+        "loudnessMeter", "loudness",
+        loudness_meter::{loudness_new, loudness_free, loudness_latency_samples, loudness_reset},
+        loudness_process(input)
+    ),
+    kind!(
+        "multibandCompressor", "multiband",
+        multiband_compressor::{
+            multiband_new,
+            multiband_free,
+            multiband_latency_samples,
+            multiband_reset,
+        },
+        multiband_process(input, output)
+    ),
+    kind!(
+        "noise", "noise",
+        noise::{noise_new, noise_free, noise_latency_samples, noise_reset},
+        noise_process(output)
+    ),
+    kind!(
+        "octaveAnalyzer", "octave",
+        octave_analyzer::{octave_new, octave_free, octave_latency_samples, octave_reset},
+        octave_process(input)
+    ),
+    kind!(
+        "octaver", "octaver",
+        octaver::{octaver_new, octaver_free, octaver_latency_samples, octaver_reset},
+        octaver_process(input, output)
+    ),
+    kind!(
+        "oscilloscope", "scope",
+        oscilloscope::{scope_new, scope_free, scope_latency_samples, scope_reset},
+        scope_process(input)
+    ),
+    kind!(
+        "phaseVocoder", "pvoc",
+        phase_vocoder::{pvoc_new, pvoc_free, pvoc_latency_samples, pvoc_reset},
+        pvoc_process(input, output)
+    ),
+    kind!(
+        "phaser", "phaser",
+        phaser::{phaser_new, phaser_free, phaser_latency_samples, phaser_reset},
+        phaser_process(input, output)
+    ),
+    kind!(
+        "pitchCorrector", "pitchcorr",
+        pitch_corrector::{
+            pitchcorr_new,
+            pitchcorr_free,
+            pitchcorr_latency_samples,
+            pitchcorr_reset,
+        },
+        pitchcorr_process(input, output)
+    ),
+    kind!(
+        "pitchShifter", "pitchshift",
+        pitch_shifter::{
+            pitchshift_new,
+            pitchshift_free,
+            pitchshift_latency_samples,
+            pitchshift_reset,
+        },
+        pitchshift_process(input, output)
+    ),
+    kind!(
+        "pluck", "pluck",
+        pluck::{pluck_new, pluck_free, pluck_latency_samples, pluck_reset},
+        pluck_process(output)
+    ),
+    kind!(
+        "ringModulator", "ringmod",
+        ring_modulator::{ringmod_new, ringmod_free, ringmod_latency_samples, ringmod_reset},
+        ringmod_process_with_carrier(input, aux(RingMod), output)
+    ),
+    kind!(
+        "sampleHold", "sample_hold",
+        sample_hold::{
+            sample_hold_new,
+            sample_hold_free,
+            sample_hold_latency_samples,
+            sample_hold_reset,
+        },
+        sample_hold_process_clocked(input, aux(SampleHold), output)
+    ),
+    kind!(
+        "sampler", "sampler",
+        sampler::{sampler_new, sampler_free, sampler_latency_samples, sampler_reset},
+        sampler_process(output)
+    ),
+    kind!(
+        "sequencer", "sequencer",
+        sequencer::{sequencer_new, sequencer_free, sequencer_latency_samples, sequencer_reset},
+        sequencer_process(output)
+    ),
+    kind!(
+        "spectralFreeze", "freeze",
+        spectral_freeze::{freeze_new, freeze_free, freeze_latency_samples, freeze_reset},
+        freeze_process(input, output)
+    ),
+    kind!(
+        "spectralGate", "spectral_gate",
+        spectral_gate::{
+            spectral_gate_new,
+            spectral_gate_free,
+            spectral_gate_latency_samples,
+            spectral_gate_reset,
+        },
+        spectral_gate_process(input, output)
+    ),
+    kind!(
+        "spectrogram", "spectrogram",
+        spectrogram::{
+            spectrogram_new,
+            spectrogram_free,
+            spectrogram_latency_samples,
+            spectrogram_reset,
+        },
+        spectrogram_process(input)
+    ),
+    kind!(
+        "spectrumAnalyzer", "spectrum",
+        spectrum_analyzer::{spectrum_new, spectrum_free, spectrum_latency_samples, spectrum_reset},
+        spectrum_process(input)
+    ),
+    kind!(
+        "stereoDelay", "stereo_delay",
+        stereo_delay::{
+            stereo_delay_new,
+            stereo_delay_free,
+            stereo_delay_latency_samples,
+            stereo_delay_reset,
+        },
+        stereo_delay_process(input, output)
+    ),
+    kind!(
+        "svf", "svf",
+        svf::{svf_new, svf_free, svf_latency_samples, svf_reset},
+        svf_process_modulated(input, aux(Svf), output)
+    ),
+    kind!(
+        "synth", "synth",
+        synth::{synth_new, synth_free, synth_latency_samples, synth_reset},
+        synth_process(output)
+    ),
+    kind!(
+        "tape", "tape",
+        tape::{tape_new, tape_free, tape_latency_samples, tape_reset},
+        tape_process(input, output)
+    ),
+    kind!(
+        "testTone", "tone",
+        test_tone::{tone_new, tone_free, tone_latency_samples, tone_reset},
+        tone_process(output)
+    ),
+    kind!(
+        "tilt", "tilt",
+        tilt::{tilt_new, tilt_free, tilt_latency_samples, tilt_reset},
+        tilt_process(input, output)
+    ),
+    kind!(
+        "transientShaper", "transient",
+        transient_shaper::{
+            transient_new,
+            transient_free,
+            transient_latency_samples,
+            transient_reset,
+        },
+        transient_process(input, output)
+    ),
+    kind!(
+        "truePeakMeter", "true_peak",
+        true_peak_meter::{
+            true_peak_new,
+            true_peak_free,
+            true_peak_latency_samples,
+            true_peak_reset,
+        },
+        true_peak_process(input)
+    ),
+    kind!(
+        "tuner", "tuner",
+        tuner::{tuner_new, tuner_free, tuner_latency_samples, tuner_reset},
+        tuner_process(input)
+    ),
+    kind!(
+        "upwardCompressor", "upward",
+        upward_compressor::{upward_new, upward_free, upward_latency_samples, upward_reset},
+        upward_process(input, output)
+    ),
+    kind!(
+        "vocoder", "vocoder",
+        vocoder::{vocoder_new, vocoder_free, vocoder_latency_samples, vocoder_reset},
+        vocoder_process_with_carrier(input, aux(Vocoder), output)
+    ),
+    kind!(
+        "wavetable", "wavetable",
+        wavetable::{wavetable_new, wavetable_free, wavetable_latency_samples, wavetable_reset},
+        wavetable_process(output)
+    ),
+    kind!(
+        "limiter", "limiter",
+        limiter::{limiter_new, limiter_free, limiter_latency_samples, limiter_reset},
+        limiter_process_sidechained(input, aux(Limiter), output)
+    ),
+];
+
+/// What a rack handle names: the node's own handle and its type.
+#[derive(Clone, Copy)]
+pub struct Node {
+    type_id: u32,
+    handle: Handle,
+}
+
+impl Node {
+    fn kind(&self) -> &'static Kind {
+        &KINDS[self.type_id as usize]
+    }
+
+    fn process(
+        &self,
+        in_ptr: *const f32,
+        aux_ptr: *const f32,
+        out_ptr: *mut f32,
+        frames: usize,
+        channels: usize,
+        aux_channels: usize,
+    ) {
+        match self.kind().process {
+            Process::Effect(process) => process(self.handle, in_ptr, out_ptr, frames, channels),
+            Process::Generator(process) => process(self.handle, out_ptr, frames, channels),
+            Process::Analyzer(process) => process(self.handle, in_ptr, frames, channels),
+            Process::Sidechained(process) => process(
+                self.handle,
+                in_ptr,
+                aux_ptr,
+                out_ptr,
+                frames,
+                channels,
+                aux_channels,
+            ),
+        }
+    }
+
+    fn latency_samples(&self) -> u32 {
+        (self.kind().latency_samples)(self.handle)
+    }
+
+    fn reset(&self) {
+        (self.kind().reset)(self.handle);
+    }
+}
+
+fn kind(type_id: u32) -> Option<&'static Kind> {
+    KINDS.get(type_id as usize)
+}
+
+/// The rack's own registry, of the nodes `node_create` made; its handles are what the `node_`
+/// exports take.
+fn with_nodes<R>(f: impl FnOnce(&mut Registry<Node>) -> R) -> R {
+    node_abi::registry_static!(NODES: Registry<Node>);
+    NODES.with(|r| f(&mut r.borrow_mut()))
+}
+
+/// The node `handle` names, if it hasn't been freed. A copy, so the registry isn't borrowed
+/// while the node runs.
+pub(crate) fn node(handle: Handle) -> Option<Node> {
+    with_nodes(|r| r.get(handle)).map(|instance| unsafe { (*instance).node })
+}
+
+#[no_mangle]
+pub extern "C" fn node_type_count() -> u32 {
+    KINDS.len() as u32
+}
+
+#[no_mangle]
+pub extern "C" fn node_type_name(type_id: u32) -> *const u8 {
+    kind(type_id).map_or(ptr::null(), |k| k.name.as_ptr())
+}
+
+#[no_mangle]
+pub extern "C" fn node_type_prefix(type_id: u32) -> *const u8 {
+    kind(type_id).map_or(ptr::null(), |k| k.prefix.as_ptr())
+}
+
+#[no_mangle]
+pub extern "C" fn node_type_max_channels(type_id: u32) -> u32 {
+    kind(type_id).map_or(0, |k| k.max_channels.min(u32::MAX as usize) as u32)
+}
+
+#[no_mangle]
+pub extern "C" fn node_create(type_id: u32, sample_rate_hz: f32) -> Handle {
+    let Some(kind) = kind(type_id) else {
+        return NULL_HANDLE;
+    };
+    let handle = (kind.new)(sample_rate_hz);
+    if handle == NULL_HANDLE {
+        return NULL_HANDLE;
+    }
+    let node = with_nodes(|r| r.insert(Node { type_id, handle }));
+    if node == NULL_HANDLE {
+        (kind.free)(handle);
+    }
+    node
+}
+
+#[no_mangle]
+pub extern "C" fn node_free(node: Handle) {
+    if let Some(instance) = with_nodes(|r| r.remove(node)) {
+        (instance.node.kind().free)(instance.node.handle);
+    }
+}
+
+/// `u32::MAX` for a stale handle.
+#[no_mangle]
+pub extern "C" fn node_type(node: Handle) -> u32 {
+    self::node(node).map_or(u32::MAX, |n| n.type_id)
+}
+
+/// `NULL_HANDLE` (0) for a stale handle.
+#[no_mangle]
+pub extern "C" fn node_instance(node: Handle) -> Handle {
+    self::node(node).map_or(NULL_HANDLE, |n| n.handle)
+}
+
+#[no_mangle]
+pub extern "C" fn node_process(
+    node: Handle,
+    in_ptr: *const f32,
+    aux_ptr: *const f32,
+    out_ptr: *mut f32,
+    frames: usize,
+    channels: usize,
+    aux_channels: usize,
+) {
+    if let Some(node) = self::node(node) {
+        node.process(in_ptr, aux_ptr, out_ptr, frames, channels, aux_channels);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn node_latency_samples(node: Handle) -> u32 {
+    self::node(node).map_or(0, |n| n.latency_samples())
+}
+
+#[no_mangle]
+pub extern "C" fn node_reset(node: Handle) {
+    if let Some(n) = self::node(node) {
+        n.reset();
+    }
+}
+
+dsp_core::export_memory!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compressor::{compressor_free, compressor_new, compressor_process, compressor_set_params};
+    use core::ffi::CStr;
+    use dsp_core::blocksize::{test_signal, CHANNELS, SAMPLE_RATE};
+
+    fn text(p: *const u8) -> &'static str {
+        unsafe { CStr::from_ptr(p.cast()) }.to_str().unwrap()
+    }
+
+    #[test]
+    fn names_and_prefixes_are_unique() {
+        let count = node_type_count();
+        assert_eq!(count as usize, KINDS.len());
+        let mut names: Vec<_> = (0..count).map(|id| text(node_type_name(id))).collect();
+        let mut prefixes: Vec<_> = (0..count).map(|id| text(node_type_prefix(id))).collect();
+        assert_eq!(names[0], "bitcrusher");
+        assert_eq!(text(node_type_prefix(count - 1)), "limiter");
+        names.sort_unstable();
+        names.dedup();
+        prefixes.sort_unstable();
+        prefixes.dedup();
+        assert_eq!((names.len(), prefixes.len()), (KINDS.len(), KINDS.len()));
+        assert!(node_type_name(count).is_null());
+        assert_eq!(node_type_max_channels(count), 0);
+        assert!(node_type_prefix(count).is_null());
+    }
+
+    #[test]
+    fn every_node_type_runs() {
+        let frames = 40 * 128;
+        let input = test_signal(frames, CHANNELS, 0);
+        let aux = test_signal(frames, CHANNELS, 1);
+        for type_id in 0..node_type_count() {
+            let name = text(node_type_name(type_id));
+            let node = node_create(type_id, SAMPLE_RATE);
+            assert_ne!(node, NULL_HANDLE, "{name}");
+            assert_eq!(node_type(node), type_id);
+            assert_ne!(node_instance(node), NULL_HANDLE);
+            let mut output = vec![0.0; frames * CHANNELS];
+            for start in (0..frames).step_by(128) {
+                let at = start * CHANNELS;
+                let (input, aux) = (input[at..].as_ptr(), aux[at..].as_ptr());
+                let output = output[at..].as_mut_ptr();
+                node_process(node, input, aux, output, 128, CHANNELS, CHANNELS);
+            }
+            assert!(output.iter().all(|v| v.is_finite()), "{name}");
+            node_latency_samples(node);
+            node_reset(node);
+            node_free(node);
+        }
+    }
+
+    #[test]
+    fn renders_what_the_node_crate_does() {
+        let frames = 2_048;
+        let input = test_signal(frames, CHANNELS, 0);
+        let set = |p| compressor_set_params(p, -30.0, 8.0, 0.0, 1.0, 50.0, 6.0, 0, 1);
+        let id = (0..node_type_count()).find(|&id| text(node_type_name(id)) == "compressor");
+        let node = node_create(id.unwrap(), SAMPLE_RATE);
+        set(node_instance(node));
+        let mut rack = vec![0.0; frames * CHANNELS];
+        node_process(
+            node,
+            input.as_ptr(),
+            ptr::null(),
+            rack.as_mut_ptr(),
+            frames,
+            CHANNELS,
+            0,
+        );
+        node_free(node);
+
+        let handle = compressor_new(SAMPLE_RATE);
+        set(handle);
+        let mut direct = vec![0.0; frames * CHANNELS];
+        compressor_process(
+            handle,
+            input.as_ptr(),
+            direct.as_mut_ptr(),
+            frames,
+            CHANNELS,
+        );
+        compressor_free(handle);
+        assert_eq!(rack, direct);
+        assert!(rack != input);
+    }
+
+    /// A side-chained node gets `aux_ptr` as its key, as its own aux export takes it.
+    #[test]
+    fn passes_the_key_to_sidechained_nodes() {
+        use ducker::{
+            ducker_free, ducker_new, ducker_process, ducker_process_sidechained, ducker_reset,
+        };
+        let frames = 2_048;
+        let input = test_signal(frames, CHANNELS, 0);
+        let key: Vec<f32> = test_signal(frames, CHANNELS, 1)
+            .iter()
+            .map(|v| v * 4.0)
+            .collect();
+        let id = (0..node_type_count()).find(|&id| text(node_type_name(id)) == "ducker");
+        let node = node_create(id.unwrap(), SAMPLE_RATE);
+        let mut rack = vec![0.0; frames * CHANNELS];
+        let (x, k, y) = (input.as_ptr(), key.as_ptr(), rack.as_mut_ptr());
+        node_process(node, x, k, y, frames, CHANNELS, CHANNELS);
+        node_free(node);
+
+        let handle = ducker_new(SAMPLE_RATE);
+        let mut direct = vec![0.0; frames * CHANNELS];
+        let y = direct.as_mut_ptr();
+        ducker_process_sidechained(handle, x, k, y, frames, CHANNELS, CHANNELS);
+        assert_eq!(rack, direct);
+        ducker_reset(handle);
+        ducker_process(handle, x, y, frames, CHANNELS);
+        ducker_free(handle);
+        assert!(rack != direct);
+    }
+
+    #[test]
+    fn null_nodes_and_unknown_types_are_ignored() {
+        let null = NULL_HANDLE;
+        assert_eq!(node_create(node_type_count(), SAMPLE_RATE), null);
+        let mut out = [1.0; 4];
+        node_process(null, out.as_ptr(), ptr::null(), out.as_mut_ptr(), 2, 2, 0);
+        assert_eq!(out, [1.0; 4]);
+        assert_eq!(node_type(null), u32::MAX);
+        assert_eq!(node_instance(null), null);
+        assert_eq!(node_latency_samples(null), 0);
+        node_reset(null);
+        node_free(null);
+    }
+
+    /// A freed node's handle finds nothing: a second free, or any call after the first, is a
+    /// no-op, and doesn't reach a node made since in the same slot.
+    #[test]
+    fn freed_and_made_up_handles_are_ignored() {
+        let id = (0..node_type_count()).find(|&id| text(node_type_name(id)) == "tape");
+        let node = node_create(id.unwrap(), SAMPLE_RATE);
+        let instance = node_instance(node);
+        node_free(node);
+        node_free(node);
+        let mut out = [1.0; 4];
+        node_process(node, out.as_ptr(), ptr::null(), out.as_mut_ptr(), 2, 2, 0);
+        assert_eq!(out, [1.0; 4]);
+        assert_eq!(node_type(node), u32::MAX);
+        assert_eq!(node_instance(node), NULL_HANDLE);
+        assert_eq!(node_latency_samples(node), 0);
+        node_reset(node);
+
+        let again = node_create(id.unwrap(), SAMPLE_RATE);
+        assert_ne!(again, node);
+        assert_ne!(node_instance(again), instance);
+        node_free(node);
+        assert!(node_latency_samples(again) > 0);
+        node_free(again);
+        for junk in [0xffff_ffff, 0x1234_5678, again + 1] {
+            assert_eq!(node_type(junk), u32::MAX, "{junk:#x}");
+        }
+    }
+}
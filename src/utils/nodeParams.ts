@@ -0,0 +1,145 @@
+// Reads the parameter table a node exports through `node-abi` (`<prefix>_param_descriptors`):
+// 64-byte records of NUL-padded UTF-8 name (32 bytes) and unit (12 bytes), then min, max and
+// default as f32 and scale and flags as u32, all little-endian.
+
+export const PARAM_DESCRIPTOR_BYTES = 64;
+
+export const PARAM_SCALE_LINEAR = 0;
+export const PARAM_SCALE_LOG = 1;
+
+export const PARAM_FLAG_TOGGLE = 1 << 0;
+export const PARAM_FLAG_STEPPED = 1 << 1;
+export const PARAM_FLAG_NO_AUTOMATION = 1 << 2;
+
+export type ParamDescriptor = Readonly<{
+  index: number;
+  name: string;
+  unit: string;
+  min: number;
+  max: number;
+  defaultValue: number;
+  scale: "linear" | "log";
+  toggle: boolean;
+  stepped: boolean;
+  automatable: boolean;
+}>;
+
+const decoder = new TextDecoder();
+
+function readText(bytes: Uint8Array): string {
+  const end = bytes.indexOf(0);
+  return decoder.decode(end < 0 ? bytes : bytes.subarray(0, end));
+}
+
+export function readParamDescriptors(
+  memory: WebAssembly.Memory,
+  ptr: number,
+  count: number
+): ParamDescriptor[] {
+  const view = new DataView(memory.buffer);
+  const bytes = new Uint8Array(memory.buffer);
+  const out: ParamDescriptor[] = [];
+  for (let index = 0; index < count; index++) {
+    const base = ptr + index * PARAM_DESCRIPTOR_BYTES;
+    const flags = view.getUint32(base + 60, true);
+    out.push({
+      index,
+      name: readText(bytes.subarray(base, base + 32)),
+      unit: readText(bytes.subarray(base + 32, base + 44)),
+      min: view.getFloat32(base + 44, true),
+      max: view.getFloat32(base + 48, true),
+      defaultValue: view.getFloat32(base + 52, true),
+      scale: view.getUint32(base + 56, true) === PARAM_SCALE_LOG ? "log" : "linear",
+      toggle: (flags & PARAM_FLAG_TOGGLE) !== 0,
+      stepped: (flags & PARAM_FLAG_STEPPED) !== 0,
+      automatable: (flags & PARAM_FLAG_NO_AUTOMATION) === 0,
+    });
+  }
+  return out;
+}
+
+// Parameter-event ring (`<prefix>_param_events`, layout in `dsp-core/src/events.rs`): write
+// and read counts, then `PARAM_EVENT_CAPACITY` 12-byte events (index, value as f32, frame).
+export const PARAM_EVENT_CAPACITY = 256;
+const PARAM_EVENT_BYTES = 12;
+
+/**
+ * Queues `index` = `value` at `frame` of the next block the node processes (past its end means
+ * its last frame). Events must go in in frame order. Returns false when the ring is full.
+ */
+export function pushParamEvent(
+  memory: WebAssembly.Memory,
+  ringPtr: number,
+  index: number,
+  value: number,
+  frame: number
+): boolean {
+  const counts = new Uint32Array(memory.buffer, ringPtr, 2);
+  const write = counts[0]!;
+  const read = counts[1]!;
+  if (((write - read) >>> 0) >= PARAM_EVENT_CAPACITY) return false;
+  const view = new DataView(memory.buffer);
+  const base = ringPtr + 8 + (write % PARAM_EVENT_CAPACITY) * PARAM_EVENT_BYTES;
+  view.setUint32(base, index, true);
+  view.setFloat32(base + 4, value, true);
+  view.setUint32(base + 8, Math.max(0, Math.floor(frame)), true);
+  counts[0] = (write + 1) >>> 0;
+  return true;
+}
+
+// Last failure (`<prefix>_last_error_code`, `<prefix>_last_error_message_ptr`): a code and a
+// NUL-terminated UTF-8 message of up to 127 bytes. A node that failed outputs silence from then
+// on; after a trap (a panic on a target that can't unwind) the exports still read it back.
+export const NODE_ERROR_NONE = 0;
+export const NODE_ERROR_PANIC = 1;
+const NODE_ERROR_MESSAGE_BYTES = 128;
+
+export type NodeError = Readonly<{ code: number; message: string }>;
+
+export function readNodeError(
+  memory: WebAssembly.Memory,
+  code: number,
+  messagePtr: number
+): NodeError {
+  const message = messagePtr
+    ? readText(new Uint8Array(memory.buffer, messagePtr, NODE_ERROR_MESSAGE_BYTES))
+    : "";
+  return { code, message };
+}
+
+// Per-instance I/O block (`<prefix>_get_io_layout`, layout in `node-abi/src/io.rs`): a header
+// of 16 u32s (size, max frames, channels, channel stride in floats, then byte offsets of the
+// input, output and aux planes, aux channel count, params offset and count, meters offset and
+// count, input and output channel counts), followed by the regions it describes. A generator
+// has no input planes and a meter no output planes.
+export type NodeIoViews = Readonly<{
+  maxFrames: number;
+  inputs: Float32Array[];
+  outputs: Float32Array[];
+  aux: Float32Array[];
+  params: Float32Array;
+  meters: Float32Array;
+}>;
+
+/**
+ * Views over the I/O block at `ptr`, one `maxFrames`-long plane per channel. They go stale
+ * when memory grows (its buffer is replaced) or the block is renegotiated; build them again.
+ */
+export function readNodeIo(memory: WebAssembly.Memory, ptr: number): NodeIoViews {
+  const header = new Uint32Array(memory.buffer, ptr, 16);
+  const maxFrames = header[1]!;
+  const stride = header[3]!;
+  const planes = (offset: number, channels: number) =>
+    Array.from(
+      { length: channels },
+      (_, c) => new Float32Array(memory.buffer, ptr + offset + c * stride * 4, maxFrames)
+    );
+  return {
+    maxFrames,
+    inputs: planes(header[4]!, header[12]!),
+    outputs: planes(header[5]!, header[13]!),
+    aux: planes(header[6]!, header[7]!),
+    params: new Float32Array(memory.buffer, ptr + header[8]!, header[9]!),
+    meters: new Float32Array(memory.buffer, ptr + header[10]!, header[11]!),
+  };
+}
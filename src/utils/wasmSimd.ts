@@ -0,0 +1,22 @@
+// Smallest module using a simd128 instruction: `(func (result v128) (i32x4.splat (i32.const 0)))`.
+// Engines without simd128 fail to validate it, as they would any module built with `+simd128`.
+const SIMD_PROBE = new Uint8Array([
+  0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // magic, version
+  0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7b, // type: () -> v128
+  0x03, 0x02, 0x01, 0x00, // func: type 0
+  0x0a, 0x08, 0x01, 0x06, 0x00, 0x41, 0x00, 0xfd, 0x11, 0x0b, // code: i32.const 0; i32x4.splat
+]);
+
+let supported: boolean | undefined;
+
+/** Whether this engine runs wasm simd128, for picking a node's `.simd.wasm` build. */
+export function wasmSimdSupported(): boolean {
+  if (supported === undefined) {
+    try {
+      supported = WebAssembly.validate(SIMD_PROBE);
+    } catch {
+      supported = false;
+    }
+  }
+  return supported;
+}